@@ -0,0 +1,94 @@
+//! Parsing throughput over a few corpora: `cargo bench --bench parse`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use smiles::{is_valid, SmilesParser};
+
+/// Small molecules, mostly organic subset atoms.
+const SMALL: [&str; 12] = [
+    "C",
+    "CC",
+    "CCO",
+    "CC=O",
+    "CC(=O)O",
+    "C1CCCCC1",
+    "c1ccccc1",
+    "OCC#N",
+    "ClCCl",
+    "CN(C)C",
+    "C=CC=C",
+    "[NH4+].[Cl-]",
+];
+
+/// Drug-like molecules, with rings, branches, charges and stereo.
+const DRUGS: [&str; 16] = [
+    "CC(=O)Oc1ccccc1C(=O)O",
+    "CN1C=NC2=C1C(=O)N(C(=O)N2C)C",
+    "CC(C)Cc1ccc(cc1)[C@@H](C)C(=O)O",
+    "CC(=O)Nc1ccc(O)cc1",
+    "CN1CCC[C@H]1c2cccnc2",
+    "COc1ccc2[nH]cc(CCNC(C)=O)c2c1",
+    "CC1(C)S[C@@H]2[C@H](NC(=O)Cc3ccccc3)C(=O)N2[C@H]1C(=O)O",
+    "CN1C(=O)CN=C(c2ccccc2)c2cc(Cl)ccc21",
+    "Clc1ccc2c(c1)C(=NCc1nncn1-2)c1ccccc1F",
+    "O=C(O)c1cc(N=Nc2ccc(O)c(C(=O)O)c2)ccc1O",
+    "C[C@]12CC[C@H]3[C@@H](CCc4cc(O)ccc43)[C@@H]1CC[C@@H]2O",
+    "CCN(CC)CCNC(=O)c1ccc(N)cc1",
+    "OC[C@H]1O[C@@H](O)[C@H](O)[C@@H](O)[C@@H]1O",
+    "C/C=C/C(=O)O",
+    "[O-][N+](=O)c1ccc(cc1)S(=O)(=O)N",
+    "CC(C)(C)NCC(O)c1ccc(O)c(CO)c1",
+];
+
+/// A long chain and a large macrocycle.
+fn large() -> Vec<String> {
+    let chain = "C".repeat(1000);
+    let peptide = format!("N{}C(=O)O", "CC(=O)NC(C)".repeat(100));
+    let macrocycle = format!("C1{}C1", "OCC".repeat(300));
+    vec![chain, peptide, macrocycle]
+}
+
+/// Ring bonds at their limits: all two digit rings open at once, and digits reused along
+/// fused ladders, with every atom of the second one opening or closing a ring.
+fn rings() -> Vec<String> {
+    let open: String = (10..100).map(|ring| format!("C%{ring}")).collect();
+    let close: String = (10..100).rev().map(|ring| format!("C%{ring}")).collect();
+    let ladder = format!("C1C{}CC1", "2CC1CC2".repeat(200));
+    let triangles = "C1C2C1C2".repeat(200);
+    vec![open + "C" + &close, ladder, triangles]
+}
+
+fn bench_corpus(c: &mut Criterion, name: &str, corpus: &[&str]) {
+    let parser = SmilesParser::new();
+    let bytes: usize = corpus.iter().map(|smiles| smiles.len()).sum();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(bytes as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            for smiles in corpus {
+                black_box(parser.parse(black_box(smiles)).unwrap());
+            }
+        })
+    });
+    group.bench_function("is_valid", |b| {
+        b.iter(|| {
+            for smiles in corpus {
+                assert!(is_valid(black_box(smiles)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn parse(c: &mut Criterion) {
+    bench_corpus(c, "small", &SMALL);
+    bench_corpus(c, "drugs", &DRUGS);
+    let large = large();
+    let large: Vec<&str> = large.iter().map(String::as_str).collect();
+    bench_corpus(c, "large", &large);
+    let rings = rings();
+    let rings: Vec<&str> = rings.iter().map(String::as_str).collect();
+    bench_corpus(c, "rings", &rings);
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+//! Feeds arbitrary input to everything that reads SMILES, and writes back what parses:
+//! `cargo fuzz run parse`. None of it may panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use smiles::{first_error, validate, Reaction, Smarts, SmilesParser, SmilesParserOptions};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = first_error(input);
+    let _ = validate(input);
+    let _ = Smarts::parse(input);
+    let _ = Reaction::parse(input);
+
+    let strict = SmilesParser::with_options(SmilesParserOptions {
+        strict: true,
+        kekulize: true,
+        allow_aromatic_outside_rings: false,
+        ..Default::default()
+    });
+    let _ = strict.parse(input);
+
+    let parser = SmilesParser::new();
+    if let Ok(mol) = parser.parse(input) {
+        // what's written parses again, to the same molecule
+        let written = mol.to_smiles();
+        let reparsed = parser.parse(&written).expect("written SMILES parses");
+        assert_eq!(mol.to_canonical_smiles(), reparsed.to_canonical_smiles());
+    }
+});
@@ -0,0 +1,351 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::error::KekulizeError;
+use crate::rings::{sssr, Ring};
+use crate::types::{AtomIdx, BondIdx, BondOrder, Mol};
+use crate::valence::{bond_order_sum, charged_valence, default_valences};
+
+/// Which rings are candidates for aromaticity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AromaticityModel {
+    /// SSSR rings and fused pairs of them (e.g. the 10-membered envelope of azulene).
+    #[default]
+    Default,
+    /// SSSR rings only.
+    OpenSmiles,
+}
+
+/// Replaces the aromatic bonds with alternating single and double bonds,
+/// and marks all atoms aliphatic.
+pub fn kekulize(mol: &mut Mol) -> Result<(), KekulizeError> {
+    let graph = &mol.graph;
+    let aromatic_edges: Vec<EdgeIndex> = graph
+        .edge_indices()
+        .filter(|edge| graph[*edge].order == BondOrder::Aromatic)
+        .collect();
+
+    // aromatic atoms that still need a double bond to reach their valence
+    let needs_double: Vec<bool> = graph
+        .node_indices()
+        .map(|node| needs_double_bond(mol, node))
+        .collect();
+
+    let mut matched: Vec<Option<EdgeIndex>> = vec![None; graph.node_count()];
+    let mut candidates: Vec<Vec<(EdgeIndex, NodeIndex)>> = vec![vec![]; graph.node_count()];
+    for edge in &aromatic_edges {
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(*edge).unwrap();
+        if needs_double[a.index()] && needs_double[b.index()] {
+            candidates[a.index()].push((*edge, b));
+            candidates[b.index()].push((*edge, a));
+        }
+    }
+    let mut pending: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|node| needs_double[node.index()])
+        .collect();
+    if !match_double_bonds(&candidates, &mut matched, &mut pending) {
+        // unwrap: matching only fails with pending atoms
+        let atom = graph
+            .node_indices()
+            .find(|node| needs_double[node.index()] && matched[node.index()].is_none())
+            .unwrap_or(NodeIndex::new(0));
+        return Err(KekulizeError { atom: atom.into() });
+    }
+
+    for edge in aromatic_edges {
+        // unwrap: edge comes from the graph
+        let (a, _) = mol.graph.edge_endpoints(edge).unwrap();
+        mol.graph[edge].order = if matched[a.index()] == Some(edge) {
+            BondOrder::Double
+        } else {
+            BondOrder::Single
+        };
+    }
+    for atom in mol.graph.node_weights_mut() {
+        atom.aromatic = false;
+    }
+    Ok(())
+}
+
+/// Finds a perfect matching of the pending atoms by backtracking,
+/// always continuing with the atom that has the fewest options.
+fn match_double_bonds(
+    candidates: &[Vec<(EdgeIndex, NodeIndex)>],
+    matched: &mut [Option<EdgeIndex>],
+    pending: &mut Vec<NodeIndex>,
+) -> bool {
+    pending.retain(|node| matched[node.index()].is_none());
+    let options = |node: &NodeIndex| {
+        candidates[node.index()]
+            .iter()
+            .filter(|(_, other)| matched[other.index()].is_none())
+            .count()
+    };
+    let Some(position) = (0..pending.len()).min_by_key(|i| options(&pending[*i])) else {
+        return true;
+    };
+    let node = pending[position];
+    let choices: Vec<(EdgeIndex, NodeIndex)> = candidates[node.index()]
+        .iter()
+        .filter(|(_, other)| matched[other.index()].is_none())
+        .copied()
+        .collect();
+    for (edge, other) in choices {
+        matched[node.index()] = Some(edge);
+        matched[other.index()] = Some(edge);
+        let mut remaining = pending.clone();
+        if match_double_bonds(candidates, matched, &mut remaining) {
+            return true;
+        }
+        matched[node.index()] = None;
+        matched[other.index()] = None;
+    }
+    false
+}
+
+/// Whether an aromatic atom needs one of its aromatic bonds to be double:
+/// its valence isn't reached by its other bonds and hydrogens.
+fn needs_double_bond(mol: &Mol, node: NodeIndex) -> bool {
+    let atom = &mol.graph[node];
+    if !atom.aromatic {
+        return false;
+    }
+    let bonds = bond_order_sum(mol, node) + atom.total_num_hs();
+    default_valences(atom.number)
+        .first()
+        .and_then(|valence| charged_valence(atom.number, *valence, atom.charge))
+        .is_some_and(|valence| valence == bonds + 1)
+}
+
+/// Perceives aromaticity: kekulizes, then marks the atoms and bonds of rings with
+/// 4n + 2 pi electrons aromatic.
+pub fn set_aromaticity(mol: &mut Mol, model: AromaticityModel) -> Result<(), KekulizeError> {
+    kekulize(mol)?;
+
+    let rings = sssr(mol);
+    let in_ring: Vec<bool> = mol
+        .graph
+        .node_indices()
+        .map(|node| rings.iter().any(|ring| ring.atoms.contains(&node.into())))
+        .collect();
+
+    let mut candidates: Vec<Ring> = rings.clone();
+    if model == AromaticityModel::Default {
+        for (i, a) in rings.iter().enumerate() {
+            for b in &rings[i + 1..] {
+                if let Some(fused) = fuse(a, b) {
+                    candidates.push(fused);
+                }
+            }
+        }
+    }
+
+    let aromatic: Vec<Ring> = candidates
+        .into_iter()
+        .filter(|ring| is_aromatic(mol, ring, &in_ring))
+        .collect();
+    for ring in aromatic {
+        for atom in &ring.atoms {
+            mol.graph[NodeIndex::from(*atom)].aromatic = true;
+        }
+        for bond in &ring.bonds {
+            mol.graph[EdgeIndex::from(*bond)].order = BondOrder::Aromatic;
+        }
+    }
+    // bonds inside a fully aromatic ring, like the fusion bond of azulene
+    for ring in &rings {
+        if ring
+            .atoms
+            .iter()
+            .all(|atom| mol.graph[NodeIndex::from(*atom)].aromatic)
+        {
+            for bond in &ring.bonds {
+                mol.graph[EdgeIndex::from(*bond)].order = BondOrder::Aromatic;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The envelope of two rings sharing exactly one bond, `None` otherwise.
+fn fuse(a: &Ring, b: &Ring) -> Option<Ring> {
+    let shared: Vec<BondIdx> = a
+        .bonds
+        .iter()
+        .filter(|bond| b.bonds.contains(bond))
+        .copied()
+        .collect();
+    if shared.len() != 1 {
+        return None;
+    }
+    let bonds: Vec<BondIdx> = a
+        .bonds
+        .iter()
+        .chain(&b.bonds)
+        .filter(|bond| !shared.contains(bond))
+        .copied()
+        .collect();
+    let mut atoms: Vec<AtomIdx> = a.atoms.iter().chain(&b.atoms).copied().collect();
+    atoms.sort();
+    atoms.dedup();
+    Some(Ring { atoms, bonds })
+}
+
+/// Hückel rule on a kekulized ring: every atom must contribute to the pi system
+/// and the electron count must be 4n + 2.
+fn is_aromatic(mol: &Mol, ring: &Ring, in_ring: &[bool]) -> bool {
+    let mut electrons = 0;
+    for atom in &ring.atoms {
+        match pi_electrons(mol, NodeIndex::from(*atom), in_ring) {
+            Some(count) => electrons += count,
+            None => return false,
+        }
+    }
+    electrons % 4 == 2
+}
+
+/// Pi electrons an atom contributes to a ring, `None` if it can't be part of an aromatic ring.
+fn pi_electrons(mol: &Mol, node: NodeIndex, in_ring: &[bool]) -> Option<u32> {
+    let atom = &mol.graph[node];
+    let mut exocyclic_double = false;
+    for edge in mol.graph.edges(node) {
+        match edge.weight().order {
+            BondOrder::Double => {
+                let other = edge.target();
+                if in_ring[other.index()] {
+                    return Some(1);
+                }
+                exocyclic_double = true;
+                // exocyclic double bond to carbon breaks the pi system
+                if mol.graph[other].number == 6 {
+                    return None;
+                }
+            }
+            BondOrder::Triple | BondOrder::Quadruple => return None,
+            BondOrder::Single | BondOrder::Aromatic => {}
+        }
+    }
+    if exocyclic_double {
+        // e.g. the carbonyl carbon of a pyridone
+        return Some(0);
+    }
+    match (atom.number, atom.charge) {
+        // lone pair
+        (7 | 15, 0) | (8 | 16 | 34, 0) | (6, -1) => Some(2),
+        // empty p orbital
+        (5, 0) | (6, 1) => Some(0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::KekulizeError;
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, BondOrder, Mol};
+
+    use super::AromaticityModel;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn kekulized(smiles: &str) -> String {
+        let mut mol = mol(smiles);
+        mol.kekulize().unwrap();
+        mol.to_smiles()
+    }
+
+    fn aromatized(smiles: &str, model: AromaticityModel) -> String {
+        let mut mol = mol(smiles);
+        mol.set_aromaticity(model).unwrap();
+        mol.to_smiles()
+    }
+
+    #[test]
+    fn kekulize_benzene() {
+        let mut mol = mol("c1ccccc1");
+        mol.kekulize().unwrap();
+
+        let double_bonds = mol
+            .graph
+            .edge_weights()
+            .filter(|bond| bond.order == BondOrder::Double)
+            .count();
+        assert_eq!(3, double_bonds);
+        assert!(mol.graph.node_weights().all(|atom| !atom.aromatic));
+        assert_eq!("C1=CC=CC=C1", mol.to_smiles());
+    }
+
+    #[test]
+    fn kekulize_heteroaromatics() {
+        assert_eq!("C1=CC=NC=C1", kekulized("c1ccncc1"));
+        assert_eq!("C=1C=C[NH]C1", kekulized("c1cc[nH]c1"));
+        assert_eq!("C=1C=CSC1", kekulized("c1ccsc1"));
+        assert_eq!("O=C1C=CC=C[NH]1", kekulized("O=c1cccc[nH]1"));
+    }
+
+    #[test]
+    fn kekulize_fused() {
+        assert_eq!("C1=CC=C2C=CC=CC2=C1", kekulized("c1ccc2ccccc2c1"));
+    }
+
+    #[test]
+    fn kekulize_failure() {
+        let mut mol = mol("c1cccc1");
+
+        assert_eq!(
+            Err(KekulizeError {
+                atom: AtomIdx::new(0)
+            }),
+            mol.kekulize()
+        );
+    }
+
+    #[test]
+    fn aromaticity_from_kekule() {
+        let model = AromaticityModel::Default;
+        assert_eq!("c1ccccc1", aromatized("C1=CC=CC=C1", model));
+        assert_eq!("c1c[nH]cc1", aromatized("C1=CNC=C1", model));
+        assert_eq!("c1cocc1", aromatized("C1=COC=C1", model));
+        assert_eq!("O=c1cccc[nH]1", aromatized("O=C1C=CC=CN1", model));
+        assert_eq!("c1ccc2ccccc2c1", aromatized("C1=CC=C2C=CC=CC2=C1", model));
+        assert_eq!("c1c[cH-]cc1", aromatized("C1=C[CH-]C=C1", model));
+    }
+
+    #[test]
+    fn aromaticity_rejects_non_aromatic_rings() {
+        let model = AromaticityModel::Default;
+        assert_eq!("C1=CCC=C1", aromatized("C1=CCC=C1", model));
+        assert_eq!("C1=CC=CC=CC=C1", aromatized("C1=CC=CC=CC=C1", model));
+        assert_eq!("O=C1C=CC(=O)C=C1", aromatized("O=C1C=CC(=O)C=C1", model));
+        assert_eq!("C=C1C=CC=C1", aromatized("C=C1C=CC=C1", model));
+    }
+
+    #[test]
+    fn aromaticity_models() {
+        let azulene = "C1=CC2=CC=CC=CC2=C1";
+        assert_eq!(
+            "c1cc2cccccc2c1",
+            aromatized(azulene, AromaticityModel::Default)
+        );
+        assert_eq!(
+            "C1=CC2=CC=CC=CC2=C1",
+            aromatized(azulene, AromaticityModel::OpenSmiles)
+        );
+    }
+
+    #[test]
+    fn aromaticity_reperceives_aromatic_input() {
+        assert_eq!(
+            "c1ccccc1",
+            aromatized("c1ccccc1", AromaticityModel::OpenSmiles)
+        );
+    }
+}
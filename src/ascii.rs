@@ -0,0 +1,151 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::layout::{Point, BOND_LENGTH};
+use crate::smiles::element_symbol;
+use crate::types::{Atom, BondOrder, Mol};
+
+/// Columns and rows per bond length: characters are about twice as high as wide.
+const COLUMNS_PER_BOND: f64 = 4.0;
+const ROWS_PER_BOND: f64 = 2.0;
+
+/// Rough text drawing of the molecule from its 2D coordinates: atoms are their element
+/// symbol and charge on a grid, bonds are drawn with `-`, `|`, `/` and `\`, double and
+/// triple bonds between horizontal neighbors with `=` and `#`. Aromatic rings are drawn
+/// kekulized when possible. Each line ends with a line break, without trailing spaces.
+pub fn to_ascii_art(mol: &Mol) -> String {
+    let mut mol = mol.clone();
+    let _ = mol.kekulize();
+    let conformer = mol.compute_2d_coords();
+    let positions = conformer.positions();
+    if positions.is_empty() {
+        return String::new();
+    }
+
+    // grid cells of the atoms, row 0 at the top
+    let min_x = positions.iter().map(|p| p.x).fold(f64::INFINITY, f64::min);
+    let max_y = positions
+        .iter()
+        .map(|p| p.y)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let cell = |p: &Point| {
+        (
+            libm::round((p.x - min_x) / BOND_LENGTH * COLUMNS_PER_BOND) as usize,
+            libm::round((max_y - p.y) / BOND_LENGTH * ROWS_PER_BOND) as usize,
+        )
+    };
+    let cells: Vec<(usize, usize)> = positions.iter().map(cell).collect();
+    let labels: Vec<String> = mol.atoms().map(label).collect();
+    let width = cells
+        .iter()
+        .zip(&labels)
+        .map(|((column, _), label)| column + label.len())
+        .max()
+        .unwrap_or(0);
+    let height = cells.iter().map(|(_, row)| row + 1).max().unwrap_or(0);
+    let mut grid = vec![vec![' '; width]; height];
+
+    for edge in mol.graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = mol.graph.edge_endpoints(edge).unwrap();
+        let (pa, pb) = (positions[a.index()], positions[b.index()]);
+        let symbol = bond_symbol(pb.x - pa.x, pb.y - pa.y, mol.graph[edge].order);
+        let ((ca, ra), (cb, rb)) = (cells[a.index()], cells[b.index()]);
+        let (dc, dr) = (cb as f64 - ca as f64, rb as f64 - ra as f64);
+        let steps = dc.abs().max(dr.abs()) as usize;
+        for step in 1..steps {
+            let t = step as f64 / steps as f64;
+            let column = libm::round(ca as f64 + dc * t) as usize;
+            let row = libm::round(ra as f64 + dr * t) as usize;
+            grid[row][column] = symbol;
+        }
+    }
+    // labels over the bonds
+    for ((column, row), label) in cells.iter().zip(&labels) {
+        for (i, c) in label.chars().enumerate() {
+            grid[*row][column + i] = c;
+        }
+    }
+
+    let mut out = String::new();
+    for row in grid {
+        let line: String = row.into_iter().collect();
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Element symbol and charge, e.g. `N+` or `O2-`.
+fn label(atom: &Atom) -> String {
+    let symbol = element_symbol(atom.number).unwrap_or("*");
+    match atom.charge {
+        0 => symbol.to_string(),
+        1 => format!("{}+", symbol),
+        -1 => format!("{}-", symbol),
+        charge if charge > 0 => format!("{}{}+", symbol, charge),
+        charge => format!("{}{}-", symbol, -charge),
+    }
+}
+
+/// The character of a bond going `(dx, dy)` in the layout, y up.
+fn bond_symbol(dx: f64, dy: f64, order: BondOrder) -> char {
+    // atan2 is in [-180, 180], the line's angle in [0, 180)
+    let angle = libm::fmod(libm::atan2(dy, dx).to_degrees() + 180.0, 180.0);
+    if !(20.0..=160.0).contains(&angle) {
+        match order {
+            BondOrder::Double => '=',
+            BondOrder::Triple | BondOrder::Quadruple => '#',
+            BondOrder::Single | BondOrder::Aromatic => '-',
+        }
+    } else if (70.0..=110.0).contains(&angle) {
+        '|'
+    } else if angle < 90.0 {
+        '/'
+    } else {
+        '\\'
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::BondOrder;
+
+    use super::bond_symbol;
+
+    fn ascii(smiles: &str) -> String {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap().to_ascii_art()
+    }
+
+    #[test]
+    fn symbols() {
+        assert_eq!('-', bond_symbol(1.0, 0.1, BondOrder::Single));
+        assert_eq!('=', bond_symbol(-1.0, 0.0, BondOrder::Double));
+        assert_eq!('#', bond_symbol(1.0, 0.0, BondOrder::Triple));
+        assert_eq!('|', bond_symbol(0.0, -1.0, BondOrder::Double));
+        assert_eq!('/', bond_symbol(0.87, 0.5, BondOrder::Single));
+        assert_eq!('/', bond_symbol(-0.87, -0.5, BondOrder::Single));
+        assert_eq!('\\', bond_symbol(0.87, -0.5, BondOrder::Single));
+    }
+
+    #[test]
+    fn drawings() {
+        assert_eq!("C\\    /O\n  \\C//\n", ascii("CC=O"));
+        assert_eq!(
+            "O\n \\\n  C===C\nC/     \\C\n \\     /\n  C---C\n",
+            ascii("c1ccccc1O")
+        );
+        // fragments side by side, with charges
+        assert!(ascii("CC(=O)[O-].[Na+]").contains("/O-\n"));
+        assert!(ascii("CC(=O)[O-].[Na+]").contains("Na+\n"));
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!("", ascii(""));
+        assert_eq!("C\n", ascii("C"));
+    }
+}
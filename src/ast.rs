@@ -0,0 +1,581 @@
+//! SMILES as written: chains of atoms with their branches and ring bonds, before the
+//! molecule is built from them with [`SmilesParser::build`](crate::SmilesParser::build).
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::{SmilesError, SmilesErrorKind};
+use crate::lexer::{BondSymbol, Lexer, Span, Token, TokenKind};
+use crate::smiles::SmilesParserOptions;
+use crate::types::Atom;
+
+/// A parsed SMILES string. Parsing only checks the syntax, e.g. that parentheses are
+/// balanced, not that ring bonds are closed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SmilesAst {
+    /// The atoms written outside of branches. The first one has no atom before it, its
+    /// connection is ignored.
+    pub chain: Vec<ChainAtom>,
+}
+
+/// An atom of a chain, with how it's connected to the atom before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainAtom {
+    pub connection: Connection,
+    /// The atom as written. Its hydrogens aren't derived yet.
+    pub atom: Atom,
+    pub span: Span,
+    /// Ring bonds and branches, in the order they're written.
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connection {
+    /// A bond without symbol: aromatic between aromatic atoms, otherwise single.
+    Implicit,
+    Bond(BondSymbol),
+    /// No bond, the atom starts a new fragment (`.`).
+    Dot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attachment {
+    /// A ring bond number, with the bond symbol written before it.
+    RingBond {
+        number: u16,
+        symbol: Option<BondSymbol>,
+        span: Span,
+    },
+    /// A branch, whose first atom is connected to the atom it's attached to.
+    Branch(Vec<ChainAtom>),
+}
+
+impl SmilesAst {
+    /// Calls the visitor as [`read`] does for the SMILES the AST was parsed from.
+    pub(crate) fn walk(&self, visitor: &mut impl Visitor) {
+        walk_chain(&self.chain, visitor);
+    }
+}
+
+fn walk_chain(chain: &[ChainAtom], visitor: &mut impl Visitor) {
+    for chain_atom in chain {
+        visitor.atom(
+            chain_atom.connection,
+            chain_atom.atom.clone(),
+            chain_atom.span,
+        );
+        for attachment in &chain_atom.attachments {
+            match attachment {
+                Attachment::RingBond {
+                    number,
+                    symbol,
+                    span,
+                } => visitor.ring_bond(*number, *symbol, *span),
+                Attachment::Branch(branch) => {
+                    visitor.open_branch();
+                    walk_chain(branch, visitor);
+                    visitor.close_branch();
+                }
+            }
+        }
+    }
+}
+
+/// Receives the parts of a SMILES string from [`read`], in the order they're written.
+/// Ring bonds and branches always come after an atom.
+pub(crate) trait Visitor {
+    fn atom(&mut self, connection: Connection, atom: Atom, span: Span);
+    fn ring_bond(&mut self, number: u16, symbol: Option<BondSymbol>, span: Span);
+    fn open_branch(&mut self);
+    fn close_branch(&mut self);
+}
+
+/// Builds the AST: the chain being read and the chains whose branches are open.
+struct AstParser {
+    chain: Vec<ChainAtom>,
+    branches: Vec<Vec<ChainAtom>>,
+}
+
+impl AstParser {
+    /// The atom the next ring bond or branch is attached to: the last one read, or at
+    /// the start of a branch, the atom the branch is attached to.
+    fn last_atom(&mut self) -> &mut ChainAtom {
+        let outer = self.branches.iter_mut().rev();
+        core::iter::once(&mut self.chain)
+            .chain(outer)
+            .find_map(|chain| chain.last_mut())
+            // unwrap: the reader checks that ring bonds and branches come after an atom
+            .unwrap()
+    }
+}
+
+impl Visitor for AstParser {
+    fn atom(&mut self, connection: Connection, atom: Atom, span: Span) {
+        self.chain.push(ChainAtom {
+            connection,
+            atom,
+            span,
+            attachments: vec![],
+        });
+    }
+
+    fn ring_bond(&mut self, number: u16, symbol: Option<BondSymbol>, span: Span) {
+        self.last_atom().attachments.push(Attachment::RingBond {
+            number,
+            symbol,
+            span,
+        });
+    }
+
+    fn open_branch(&mut self) {
+        let chain = core::mem::take(&mut self.chain);
+        self.branches.push(chain);
+    }
+
+    fn close_branch(&mut self) {
+        // unwrap: the reader checks that parentheses are balanced
+        let outer = self.branches.pop().unwrap();
+        let branch = core::mem::replace(&mut self.chain, outer);
+        self.last_atom()
+            .attachments
+            .push(Attachment::Branch(branch));
+    }
+}
+
+pub(crate) fn parse(smiles: &str, options: SmilesParserOptions) -> Result<SmilesAst, SmilesError> {
+    let mut parser = AstParser {
+        chain: vec![],
+        branches: vec![],
+    };
+    read(smiles, options, &mut parser)?;
+    Ok(SmilesAst {
+        chain: parser.chain,
+    })
+}
+
+/// Reads the SMILES into the visitor, checking the syntax on the way. The visitor may
+/// have received part of the input when this fails.
+pub(crate) fn read(
+    smiles: &str,
+    options: SmilesParserOptions,
+    visitor: &mut impl Visitor,
+) -> Result<(), SmilesError> {
+    let mut reader = Reader {
+        lexer: Lexer::new(smiles, options),
+        peeked: None,
+        options,
+        visitor,
+        atoms: 0,
+        depth: 0,
+    };
+    reader.smiles()
+}
+
+/// A recursive descent parser over the tokens, following the OpenSMILES grammar:
+///
+/// ```text
+/// smiles        ::= chain?
+/// chain         ::= branched_atom ((bond | '.')? branched_atom)*
+/// branched_atom ::= atom (ring_bond | branch)*
+/// ring_bond     ::= bond? ring_number
+/// branch        ::= '(' (ring_bond | branch)* (bond | '.')? chain ')'
+/// ```
+///
+/// Like common toolkits, it's more tolerant than the specification unless the options
+/// are strict: ring bonds can follow branches, and a branch can start with ring bonds and
+/// branches, which are those of the atom before it, as in `C((C)C)`.
+struct Reader<'a, 'v, V> {
+    lexer: Lexer<'a>,
+    // the next token, if it has been looked at
+    peeked: Option<Result<Token, SmilesError>>,
+    options: SmilesParserOptions,
+    visitor: &'v mut V,
+    // number of atoms read, to tell whether a branch has any
+    atoms: usize,
+    // number of open branches
+    depth: usize,
+}
+
+// The methods on the recursion through branches keep only small values in their
+// frames, so that deeply nested branches fit on the stack. Tokens with atoms are read
+// in `atom`, which isn't on it.
+impl<V: Visitor> Reader<'_, '_, V> {
+    /// smiles ::= chain?
+    fn smiles(&mut self) -> Result<(), SmilesError> {
+        match self.peek()? {
+            None => return Ok(()),
+            Some(TokenKind::OrganicAtom(_) | TokenKind::BracketAtom(_)) => {}
+            Some(_) => return Err(self.missing_atom()),
+        }
+        self.chain(Connection::Implicit)?;
+        // the chain ends at a ')' or at the end
+        match self.peek()? {
+            None => Ok(()),
+            Some(_) => Err(self.error(SmilesErrorKind::UnbalancedParenthesis)),
+        }
+    }
+
+    /// chain ::= branched_atom ((bond | '.')? branched_atom)*
+    ///
+    /// Starts at an atom, connected to the atom before the chain if any, and stops before
+    /// a ')' or at the end.
+    fn chain(&mut self, mut connection: Connection) -> Result<(), SmilesError> {
+        loop {
+            self.atom(connection)?;
+            connection = match self.attachments(false)? {
+                Some(symbol) => Connection::Bond(symbol),
+                None => match self.peek()? {
+                    Some(TokenKind::OrganicAtom(_) | TokenKind::BracketAtom(_)) => {
+                        Connection::Implicit
+                    }
+                    Some(TokenKind::Dot) => {
+                        self.dot()?;
+                        Connection::Dot
+                    }
+                    // the attachments took the ring bonds, bonds and branches
+                    _ => return Ok(()),
+                },
+            };
+        }
+    }
+
+    /// The atom of a branched_atom, the rest being its attachments.
+    #[inline(never)]
+    fn atom(&mut self, connection: Connection) -> Result<(), SmilesError> {
+        // unwrap: the caller has seen the atom
+        let token = self.next()?.unwrap();
+        let (TokenKind::OrganicAtom(atom) | TokenKind::BracketAtom(atom)) = token.kind else {
+            unreachable!("a chain starts with an atom");
+        };
+        self.visitor.atom(connection, atom, token.span);
+        self.atoms += 1;
+        Ok(())
+    }
+
+    /// (ring_bond | branch)*, of the atom read last. In strict mode, ring_bond* branch*,
+    /// and none at the start of a branch.
+    ///
+    /// Returns the bond symbol after them if it's followed by an atom, whose it is.
+    fn attachments(&mut self, branch_start: bool) -> Result<Option<BondSymbol>, SmilesError> {
+        let strict = self.options.strict;
+        let mut branched = false;
+        loop {
+            match self.peek()? {
+                Some(TokenKind::RingBond(_) | TokenKind::BranchOpen) if strict && branch_start => {
+                    return Err(self.unexpected("atom"));
+                }
+                Some(TokenKind::RingBond(_)) if strict && branched => {
+                    return Err(self.unexpected("atom or branch"));
+                }
+                Some(&TokenKind::RingBond(number)) => self.ring_bond(number, None)?,
+                Some(&TokenKind::Bond(symbol)) => {
+                    let bond = self.advance();
+                    match self.peek()? {
+                        Some(TokenKind::OrganicAtom(_) | TokenKind::BracketAtom(_)) => {
+                            return Ok(Some(symbol));
+                        }
+                        Some(TokenKind::RingBond(_)) if strict && (branch_start || branched) => {
+                            return Err(SmilesError::expected(
+                                SmilesErrorKind::UnexpectedCharacter(symbol.to_char()),
+                                bond.start,
+                                "atom",
+                            ));
+                        }
+                        Some(&TokenKind::RingBond(number)) => {
+                            self.ring_bond(number, Some(symbol))?;
+                        }
+                        Some(TokenKind::Bond(_)) => return Err(self.missing_atom()),
+                        _ => {
+                            return Err(SmilesError::new(SmilesErrorKind::DanglingBond, bond.start))
+                        }
+                    }
+                }
+                Some(TokenKind::BranchOpen) => {
+                    self.branch()?;
+                    branched = true;
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /// ring_bond ::= bond? ring_number
+    fn ring_bond(&mut self, number: u16, symbol: Option<BondSymbol>) -> Result<(), SmilesError> {
+        if number > self.options.max_ring_number {
+            let max = self.options.max_ring_number;
+            return Err(self.error(SmilesErrorKind::RingNumberTooHigh { number, max }));
+        }
+        let span = self.advance();
+        self.visitor.ring_bond(number, symbol, span);
+        Ok(())
+    }
+
+    /// branch ::= '(' (ring_bond | branch)* (bond | '.')? chain ')'
+    fn branch(&mut self) -> Result<(), SmilesError> {
+        if self.depth == self.options.max_branch_depth {
+            return Err(self.error(SmilesErrorKind::NestingTooDeep));
+        }
+        let open = self.advance().start;
+        self.depth += 1;
+        self.visitor.open_branch();
+        let atoms = self.atoms;
+
+        // those of the atom before the branch
+        let connection = match self.attachments(true)? {
+            Some(symbol) => Some(Connection::Bond(symbol)),
+            None => match self.peek()? {
+                Some(TokenKind::OrganicAtom(_) | TokenKind::BracketAtom(_)) => {
+                    Some(Connection::Implicit)
+                }
+                Some(TokenKind::Dot) => {
+                    self.dot()?;
+                    Some(Connection::Dot)
+                }
+                _ => None,
+            },
+        };
+        if let Some(connection) = connection {
+            self.chain(connection)?;
+        }
+
+        if self.peek()?.is_none() {
+            return Err(SmilesError::new(
+                SmilesErrorKind::UnbalancedParenthesis,
+                open,
+            ));
+        }
+        if self.atoms == atoms {
+            return Err(self.error(SmilesErrorKind::MissingAtom));
+        }
+        self.advance();
+        self.visitor.close_branch();
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Reads a '.', which has to be followed by an atom.
+    fn dot(&mut self) -> Result<(), SmilesError> {
+        self.advance();
+        match self.peek()? {
+            Some(TokenKind::OrganicAtom(_) | TokenKind::BracketAtom(_)) => Ok(()),
+            Some(_) => Err(self.missing_atom()),
+            None => Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedEnd,
+                self.lexer.position(),
+                "atom",
+            )),
+        }
+    }
+
+    /// The error for the next token, which isn't an atom but needs one before it.
+    #[inline(never)]
+    fn missing_atom(&mut self) -> SmilesError {
+        // unwrap: the caller has seen the token
+        let token = self.peeked.take().unwrap().unwrap();
+        let position = token.span.start;
+        match token.kind {
+            TokenKind::Bond(symbol) => SmilesError::expected(
+                SmilesErrorKind::UnexpectedCharacter(symbol.to_char()),
+                position,
+                "atom",
+            ),
+            TokenKind::RingBond(number) if number > self.options.max_ring_number => {
+                let max = self.options.max_ring_number;
+                SmilesError::new(SmilesErrorKind::RingNumberTooHigh { number, max }, position)
+            }
+            _ => SmilesError::new(SmilesErrorKind::MissingAtom, position),
+        }
+    }
+
+    /// The error for the next token, which strict mode doesn't accept here.
+    #[inline(never)]
+    fn unexpected(&mut self, expected: &'static str) -> SmilesError {
+        // unwrap: the caller has seen the token
+        let token = self.peeked.take().unwrap().unwrap();
+        let c = match token.kind {
+            TokenKind::RingBond(number) if token.span.end - token.span.start == 1 => {
+                // unwrap: single digit ring numbers are digits
+                char::from_digit(number.into(), 10).unwrap()
+            }
+            TokenKind::RingBond(_) => '%',
+            TokenKind::Bond(symbol) => symbol.to_char(),
+            _ => '(',
+        };
+        SmilesError::expected(
+            SmilesErrorKind::UnexpectedCharacter(c),
+            token.span.start,
+            expected,
+        )
+    }
+
+    /// An error at the next token.
+    #[inline(never)]
+    fn error(&mut self, kind: SmilesErrorKind) -> SmilesError {
+        // unwrap: the caller has seen the token
+        let token = self.peeked.take().unwrap().unwrap();
+        SmilesError::new(kind, token.span.start)
+    }
+
+    /// The kind of the next token, without reading it.
+    fn peek(&mut self) -> Result<Option<&TokenKind>, SmilesError> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next();
+        }
+        match &self.peeked {
+            None => Ok(None),
+            Some(Ok(token)) => Ok(Some(&token.kind)),
+            Some(Err(error)) => Err(error.clone()),
+        }
+    }
+
+    /// Reads the next token, which has been seen, returning its span.
+    fn advance(&mut self) -> Span {
+        // unwrap: the caller has seen the token
+        self.next().unwrap().unwrap().span
+    }
+
+    #[inline(never)]
+    fn next(&mut self) -> Result<Option<Token>, SmilesError> {
+        match self.peeked.take() {
+            Some(token) => token.map(Some),
+            None => self.lexer.next().transpose(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{SmilesError, SmilesErrorKind};
+    use crate::lexer::{BondSymbol, Span};
+    use crate::smiles::{SmilesParser, SmilesParserOptions};
+    use crate::types::BondOrder;
+
+    use super::{Attachment, ChainAtom, Connection, SmilesAst};
+
+    fn ast(smiles: &str) -> SmilesAst {
+        let parser = SmilesParser::new();
+        parser.parse_ast(smiles).unwrap()
+    }
+
+    fn numbers(chain: &[ChainAtom]) -> Vec<u32> {
+        chain.iter().map(|atom| atom.atom.number).collect()
+    }
+
+    #[test]
+    fn chain_with_branches() {
+        let ast = ast("CC(=O)(N)O");
+
+        assert_eq!(vec![6, 6, 8], numbers(&ast.chain));
+        let attachments = &ast.chain[1].attachments;
+        assert_eq!(2, attachments.len());
+        let Attachment::Branch(branch) = &attachments[0] else {
+            panic!("expected a branch");
+        };
+        assert_eq!(vec![8], numbers(branch));
+        assert_eq!(
+            Connection::Bond(BondSymbol {
+                order: BondOrder::Double,
+                direction: None
+            }),
+            branch[0].connection
+        );
+        assert_eq!(Span { start: 4, end: 5 }, branch[0].span);
+    }
+
+    #[test]
+    fn ring_bonds_and_dots() {
+        let ast = ast("C=1CC1.[Na+]");
+
+        assert_eq!(vec![6, 6, 6, 11], numbers(&ast.chain));
+        assert_eq!(
+            vec![Attachment::RingBond {
+                number: 1,
+                symbol: Some(BondSymbol {
+                    order: BondOrder::Double,
+                    direction: None
+                }),
+                span: Span { start: 2, end: 3 },
+            }],
+            ast.chain[0].attachments
+        );
+        assert_eq!(Connection::Implicit, ast.chain[1].connection);
+        assert_eq!(Connection::Dot, ast.chain[3].connection);
+    }
+
+    #[test]
+    fn rings_are_not_matched() {
+        // an unclosed ring is an error when building the molecule
+        let parser = SmilesParser::new();
+        let ast = parser.parse_ast("C1CC").unwrap();
+
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::UnclosedRing(1), 1)),
+            parser.build(&ast).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn tolerated_forms() {
+        // ring bonds after branches, branches and ring bonds at the start of a branch
+        let ast = ast("C(C)1CC1");
+        assert!(matches!(
+            ast.chain[0].attachments[..],
+            [
+                Attachment::Branch(_),
+                Attachment::RingBond { number: 1, .. }
+            ]
+        ));
+        let ast = super::parse("C((C)C)O", SmilesParserOptions::default()).unwrap();
+        assert_eq!(vec![6, 8], numbers(&ast.chain));
+        let ast = super::parse("C(1C)C1", SmilesParserOptions::default()).unwrap();
+        assert_eq!(vec![6, 6], numbers(&ast.chain));
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let parser = SmilesParser::new();
+        let max = parser.options().max_branch_depth;
+        let nested = |depth: usize| format!("C{}{}", "(C".repeat(depth), ")".repeat(depth));
+
+        let ast = parser.parse_ast(&nested(max)).unwrap();
+        assert_eq!(max + 1, parser.build(&ast).unwrap().graph.node_count());
+        let mol = parser.parse(&nested(max)).unwrap();
+        assert_eq!(max + 1, mol.graph.node_count());
+        let too_deep = nested(max + 1);
+        let position = 1 + 2 * max;
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::NestingTooDeep, position)),
+            parser.parse(&too_deep).map(|_| ())
+        );
+        assert_eq!(Some(position), crate::first_error(&too_deep));
+        let (_, diagnostics) = parser.parse_lenient(&too_deep);
+        assert_eq!(position, diagnostics[0].span.start);
+        let mut state = crate::ParserState::new(SmilesParserOptions::default());
+        state.push_str(&too_deep);
+        assert_eq!(Some(position), state.error().map(|error| error.position));
+
+        let options = SmilesParserOptions {
+            max_branch_depth: 2,
+            ..Default::default()
+        };
+        let parser = SmilesParser::with_options(options);
+        assert!(parser.parse("C(C(C))C").is_ok());
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::NestingTooDeep, 5)),
+            parser.parse("C(C(C(C)))").map(|_| ())
+        );
+        let mut state = crate::ParserState::new(options);
+        state.push_str("C(C(C");
+        assert!(!state.next_tokens().contains(&crate::NextToken::BranchOpen));
+    }
+
+    #[test]
+    fn build_transformed() {
+        let parser = SmilesParser::new();
+        let mut ast = ast("CCO");
+        ast.chain[2].atom.number = 7;
+        ast.chain[2].connection = Connection::Dot;
+
+        assert_eq!("CC.N", parser.build(&ast).unwrap().to_smiles());
+    }
+}
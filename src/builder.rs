@@ -0,0 +1,333 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{NodeIndex, UnGraph};
+
+use crate::error::EditError;
+use crate::types::{Atom, AtomIdx, Bond, BondIdx, BondOrder, Mol};
+use crate::valence;
+
+/// Builds or edits a molecule atom by atom and bond by bond, then validates it with
+/// [`finalize`](MolBuilder::finalize).
+///
+/// Atoms and bonds are numbered in the order they're added. Removing one shifts the
+/// indices above it down by one, like removing from a `Vec`.
+#[derive(Debug, Clone, Default)]
+pub struct MolBuilder {
+    graph: UnGraph<Atom, Bond>,
+}
+
+impl MolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn num_atoms(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn num_bonds(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    /// Adds the atom, returns its index. Hydrogens are derived in `finalize` for
+    /// atoms without `explicit_h`.
+    pub fn add_atom(&mut self, atom: Atom) -> AtomIdx {
+        self.graph.add_node(atom).into()
+    }
+
+    /// Bonds the atoms, returns the bond's index. For atoms with a chirality, the
+    /// neighbors are in the order their bonds are added.
+    pub fn add_bond(
+        &mut self,
+        a: AtomIdx,
+        b: AtomIdx,
+        order: BondOrder,
+    ) -> Result<BondIdx, EditError> {
+        let (node_a, node_b) = (self.node(a)?, self.node(b)?);
+        if a == b {
+            return Err(EditError::SelfBond(a));
+        }
+        if self.graph.contains_edge(node_a, node_b) {
+            return Err(EditError::DuplicateBond(a, b));
+        }
+        for (atom, neighbor) in [(node_a, b), (node_b, a)] {
+            let atom = &mut self.graph[atom];
+            if atom.chirality.is_some() {
+                atom.chiral_neighbors.push(neighbor);
+            }
+        }
+        let bond = Bond {
+            order,
+            ..Default::default()
+        };
+        Ok(self.graph.add_edge(node_a, node_b, bond).into())
+    }
+
+    pub fn set_bond_order(&mut self, bond: BondIdx, order: BondOrder) -> Result<(), EditError> {
+        let bond = self
+            .graph
+            .edge_weight_mut(bond.into())
+            .ok_or(EditError::BondOutOfRange(bond))?;
+        bond.order = order;
+        // cis/trans only applies to double bonds
+        if order != BondOrder::Double {
+            bond.stereo = None;
+        }
+        Ok(())
+    }
+
+    /// Removes the atom and its bonds. Stereo that involved the atom is cleared.
+    pub fn remove_atom(&mut self, atom: AtomIdx) -> Result<Atom, EditError> {
+        let removed = self.graph[self.node(atom)?].clone();
+        self.retain(|node| node != atom.index(), |_| true);
+        Ok(removed)
+    }
+
+    /// Removes the bond. Stereo that involved the bond is cleared.
+    pub fn remove_bond(&mut self, bond: BondIdx) -> Result<Bond, EditError> {
+        let removed = self
+            .graph
+            .edge_weight(bond.into())
+            .cloned()
+            .ok_or(EditError::BondOutOfRange(bond))?;
+        self.retain(|_| true, |edge| edge != bond.index());
+        Ok(removed)
+    }
+
+    /// Checks the valences and derives the implicit hydrogens.
+    pub fn finalize(self) -> Result<Mol, EditError> {
+        let mut mol = Mol { graph: self.graph };
+        if let Some(node) = mol
+            .graph
+            .node_indices()
+            .find(|node| !valence::is_valence_allowed(&mol, *node))
+        {
+            return Err(EditError::InvalidValence(node.into()));
+        }
+        for node in mol.graph.node_indices() {
+            mol.graph[node].implicit_h = valence::implicit_h_count(&mol, node);
+        }
+        Ok(mol)
+    }
+
+    fn node(&self, atom: AtomIdx) -> Result<NodeIndex, EditError> {
+        let node = NodeIndex::from(atom);
+        match self.graph.node_weight(node) {
+            Some(_) => Ok(node),
+            None => Err(EditError::AtomOutOfRange(atom)),
+        }
+    }
+
+    /// Keeps the atoms and bonds passing the filters (and bonds between kept atoms),
+    /// in order, and updates the indices stored in the atoms and bonds.
+    fn retain(&mut self, keep_atom: impl Fn(usize) -> bool, keep_bond: impl Fn(usize) -> bool) {
+        let mut new_index: Vec<Option<AtomIdx>> = vec![None; self.graph.node_count()];
+        let mut count = 0;
+        for (atom, index) in new_index.iter_mut().enumerate() {
+            if keep_atom(atom) {
+                *index = Some(AtomIdx::new(count));
+                count += 1;
+            }
+        }
+        let remap = |atom: AtomIdx| new_index[atom.index()];
+        let graph = self.graph.filter_map(
+            |node, atom| new_index[node.index()].map(|_| atom.clone()),
+            |edge, bond| keep_bond(edge.index()).then(|| bond.clone()),
+        );
+
+        self.graph = graph.map(
+            |_, atom| {
+                let mut atom = atom.clone();
+                let neighbors: Option<Vec<AtomIdx>> =
+                    atom.chiral_neighbors.iter().map(|n| remap(*n)).collect();
+                match neighbors {
+                    Some(neighbors) => atom.chiral_neighbors = neighbors,
+                    None => {
+                        atom.chirality = None;
+                        atom.chiral_neighbors = vec![];
+                    }
+                }
+                atom
+            },
+            |_, bond| {
+                let mut bond = bond.clone();
+                bond.stereo = bond.stereo.and_then(|mut stereo| {
+                    stereo.start_neighbor = remap(stereo.start_neighbor)?;
+                    stereo.end_neighbor = remap(stereo.end_neighbor)?;
+                    Some(stereo)
+                });
+                bond
+            },
+        );
+
+        // chirality whose bonds were removed no longer applies
+        for node in self.graph.node_indices() {
+            let atom = &self.graph[node];
+            let bonded = atom
+                .chiral_neighbors
+                .iter()
+                .all(|n| self.graph.contains_edge(node, (*n).into()));
+            if !bonded {
+                let atom = &mut self.graph[node];
+                atom.chirality = None;
+                atom.chiral_neighbors = vec![];
+            }
+        }
+        // and so does cis/trans stereo
+        for edge in self.graph.edge_indices() {
+            let Some(stereo) = self.graph[edge].stereo else {
+                continue;
+            };
+            // unwrap: edge comes from the graph
+            let (start, end) = self.graph.edge_endpoints(edge).unwrap();
+            let bonded = |a: NodeIndex, b: AtomIdx| self.graph.contains_edge(a, b.into());
+            if !bonded(start, stereo.start_neighbor) || !bonded(end, stereo.end_neighbor) {
+                self.graph[edge].stereo = None;
+            }
+        }
+    }
+}
+
+impl From<Mol> for MolBuilder {
+    fn from(mol: Mol) -> Self {
+        MolBuilder { graph: mol.graph }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::EditError;
+    use crate::smiles::SmilesParser;
+    use crate::types::{Atom, AtomIdx, BondIdx, BondOrder, Chirality};
+
+    use super::MolBuilder;
+
+    fn carbon() -> Atom {
+        Atom {
+            number: 6,
+            ..Default::default()
+        }
+    }
+
+    fn edit(smiles: &str) -> MolBuilder {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap().into()
+    }
+
+    #[test]
+    fn build_molecule() {
+        let mut builder = MolBuilder::new();
+        let c = builder.add_atom(carbon());
+        let o = builder.add_atom(Atom {
+            number: 8,
+            ..Default::default()
+        });
+        let bond = builder.add_bond(c, o, BondOrder::Single).unwrap();
+        builder.set_bond_order(bond, BondOrder::Double).unwrap();
+        let mol = builder.finalize().unwrap();
+
+        assert_eq!("C=O", mol.to_smiles());
+        assert_eq!(Some(2), mol.implicit_h_count(AtomIdx::new(0)));
+    }
+
+    #[test]
+    fn edit_errors() {
+        let mut builder = MolBuilder::new();
+        let c = builder.add_atom(carbon());
+        let other = builder.add_atom(carbon());
+        builder.add_bond(c, other, BondOrder::Single).unwrap();
+
+        assert_eq!(
+            Err(EditError::AtomOutOfRange(AtomIdx::new(5))),
+            builder.add_bond(c, AtomIdx::new(5), BondOrder::Single)
+        );
+        assert_eq!(
+            Err(EditError::SelfBond(AtomIdx::new(0))),
+            builder.add_bond(c, c, BondOrder::Single)
+        );
+        assert_eq!(
+            Err(EditError::DuplicateBond(AtomIdx::new(1), AtomIdx::new(0))),
+            builder.add_bond(other, c, BondOrder::Single)
+        );
+        assert_eq!(
+            Err(EditError::BondOutOfRange(BondIdx::new(1))),
+            builder.set_bond_order(BondIdx::new(1), BondOrder::Double)
+        );
+        assert_eq!(
+            Err(EditError::AtomOutOfRange(AtomIdx::new(2))),
+            builder.remove_atom(AtomIdx::new(2)).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn remove_atom_remaps_indices() {
+        let mut builder = edit("CC(O)N");
+        builder.remove_atom(AtomIdx::new(2)).unwrap();
+
+        assert_eq!(3, builder.num_atoms());
+        assert_eq!(2, builder.num_bonds());
+        let mol = builder.finalize().unwrap();
+        assert_eq!("CCN", mol.to_smiles());
+        assert_eq!(
+            Some((AtomIdx::new(1), AtomIdx::new(2))),
+            mol.bond_atoms(BondIdx::new(1))
+        );
+    }
+
+    #[test]
+    fn remove_clears_stereo() {
+        let mut builder = edit("N[C@@H](C)C(=O)O");
+        builder.remove_atom(AtomIdx::new(0)).unwrap();
+        // the bracket's hydrogen count is kept
+        assert_eq!("[CH](C)C(=O)O", builder.finalize().unwrap().to_smiles());
+
+        let mut builder = edit("F/C=C/F");
+        builder.remove_bond(BondIdx::new(2)).unwrap();
+        let mol = builder.finalize().unwrap();
+        assert_eq!(None, mol.bond_with_idx(BondIdx::new(1)).unwrap().stereo);
+    }
+
+    #[test]
+    fn chiral_neighbors_follow_added_bonds() {
+        let mut builder = MolBuilder::new();
+        let center = builder.add_atom(Atom {
+            chirality: Some(Chirality::Anticlockwise),
+            ..carbon()
+        });
+        for number in [9, 17, 35, 53] {
+            let neighbor = builder.add_atom(Atom {
+                number,
+                ..Default::default()
+            });
+            builder
+                .add_bond(center, neighbor, BondOrder::Single)
+                .unwrap();
+        }
+
+        assert_eq!("[C@](F)(Cl)(Br)I", builder.finalize().unwrap().to_smiles());
+    }
+
+    #[test]
+    fn finalize_checks_valences() {
+        let mut builder = edit("CC");
+        for _ in 0..4 {
+            let atom = builder.add_atom(carbon());
+            let _ = builder.add_bond(AtomIdx::new(0), atom, BondOrder::Single);
+        }
+
+        assert_eq!(
+            Err(EditError::InvalidValence(AtomIdx::new(0))),
+            builder.finalize().map(|_| ())
+        );
+
+        let mut builder = edit("C[OH2+]");
+        builder
+            .set_bond_order(BondIdx::new(0), BondOrder::Double)
+            .unwrap();
+        assert_eq!(
+            Err(EditError::InvalidValence(AtomIdx::new(1))),
+            builder.finalize().map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::aromaticity::AromaticityModel;
+use crate::types::{BondOrder, Chirality, DoubleBondConfig, Mol};
+use crate::valence;
+use crate::writer;
+
+/// Writes the molecule with its aromaticity perceived, so that its Kekulé and aromatic
+/// forms give the same SMILES.
+pub fn to_canonical_smiles(mol: &Mol) -> String {
+    let mol = perceived(mol);
+    writer::to_smiles_ranked(&mol, &ranks(&mol))
+}
+
+/// The molecule with its aromaticity perceived from scratch, or as it is when it can't
+/// be kekulized or when its aromatic form doesn't perceive back to itself, e.g. a ring
+/// whose double bonds are only found again on atoms above their lowest valence.
+/// Hydrogens are made implicit wherever the valence model gives them, so that `[OH]CC`
+/// and `OCC` are the same molecule.
+pub(crate) fn perceived(mol: &Mol) -> Mol {
+    let mut perceived = aromatic(mol).unwrap_or_else(|| mol.clone());
+    for node in perceived.graph.node_indices() {
+        let hydrogens = perceived.graph[node].total_num_hs();
+        perceived.graph[node].explicit_h = None;
+        let implicit = valence::implicit_h_count(&perceived, node);
+        let atom = &mut perceived.graph[node];
+        if implicit == hydrogens {
+            atom.implicit_h = implicit;
+        } else {
+            atom.explicit_h = Some(hydrogens);
+            atom.implicit_h = 0;
+        }
+    }
+    perceived
+}
+
+/// The molecule with its aromaticity perceived, `None` if it can't be kekulized or its
+/// aromatic form doesn't perceive back to itself.
+fn aromatic(mol: &Mol) -> Option<Mol> {
+    let mut aromatic = mol.clone();
+    aromatic.set_aromaticity(AromaticityModel::Default).ok()?;
+    let mut again = aromatic.clone();
+    match again.set_aromaticity(AromaticityModel::Default) {
+        Ok(()) if again.is_same_structure(&aromatic) => Some(aromatic),
+        _ => None,
+    }
+}
+
+/// Ranks the atoms such that the ranking depends only on the structure of the molecule,
+/// not on the order of the atoms in the input nor on it being written in a Kekulé or
+/// aromatic form. Ranks are unique, from 0 to n - 1.
+///
+/// Atoms are first partitioned by their invariants, then the partition is refined with
+/// the neighbors' ranks and the stereo they tell apart until stable (Morgan). Remaining
+/// ties (symmetric atoms) are broken by picking one atom of the lowest tied class and
+/// refining again, see [`break_ties`].
+pub fn canonical_ranks(mol: &Mol) -> Vec<usize> {
+    ranks(&perceived(mol))
+}
+
+/// Symmetry class of each atom: the ranks before ties are broken. Atoms in the same
+/// class can't be told apart from the structure and stereo, e.g. the carbons of benzene.
+pub fn symmetry_classes(mol: &Mol) -> Vec<usize> {
+    classes(&perceived(mol))
+}
+
+/// The canonical ranks of a molecule whose aromaticity is perceived.
+fn ranks(mol: &Mol) -> Vec<usize> {
+    let mut budget = MAX_TIE_BREAK_TRIES;
+    break_ties(mol, classes(mol), &mut budget)
+}
+
+/// The symmetry classes of a molecule whose aromaticity is perceived.
+fn classes(mol: &Mol) -> Vec<usize> {
+    refine(mol, invariant_ranks(mol))
+}
+
+/// How many atoms [`break_ties`] tries in all, past which it picks the first atom of
+/// each tied class. Only reached by big symmetric molecules with stereo.
+const MAX_TIE_BREAK_TRIES: usize = 256;
+
+/// Breaks the ties of the ranks, each time picking an atom of the lowest tied class and
+/// refining again. Without stereo, or once the stereo is told apart by the ranks, any
+/// atom of a class gives the same SMILES. Otherwise it can depend on the atom, e.g. for
+/// the carbons next to the stereocenters of 1,4-dimethylcyclohexane, so each atom is
+/// tried and the ranks with the smallest SMILES kept.
+fn break_ties(mol: &Mol, mut ranks: Vec<usize>, budget: &mut usize) -> Vec<usize> {
+    let has_stereo = mol
+        .graph
+        .node_weights()
+        .any(|atom| atom.chirality.is_some())
+        || mol.graph.edge_weights().any(|bond| bond.stereo.is_some());
+    while count_classes(&ranks) < ranks.len() {
+        let tied_rank = lowest_tied_rank(&ranks);
+        let mut tied = (0..ranks.len()).filter(|i| ranks[*i] == tied_rank);
+        if has_stereo && *budget > 0 && !stereo_resolved(mol, &ranks) {
+            return tied
+                .map(|chosen| {
+                    *budget = budget.saturating_sub(1);
+                    let ranks = break_ties(mol, pick(mol, &ranks, chosen), budget);
+                    (writer::to_smiles_ranked(mol, &ranks), ranks)
+                })
+                .min_by(|(a, _), (b, _)| a.cmp(b))
+                // unwrap: there's a tie, so there's an atom with this rank
+                .unwrap()
+                .1;
+        }
+        // unwrap: there's a tie, so there's an atom with this rank
+        let chosen = tied.next().unwrap();
+        ranks = pick(mol, &ranks, chosen);
+    }
+    ranks
+}
+
+/// The ranks refined after ranking `chosen` before the other atoms of its class.
+fn pick(mol: &Mol, ranks: &[usize], chosen: usize) -> Vec<usize> {
+    let keys: Vec<(usize, bool)> = ranks
+        .iter()
+        .enumerate()
+        .map(|(i, rank)| (*rank, i != chosen))
+        .collect();
+    refine(mol, dense_ranks(&keys))
+}
+
+/// Initial ranks, from atom invariants only.
+fn invariant_ranks(mol: &Mol) -> Vec<usize> {
+    let invariants: Vec<_> = mol
+        .graph
+        .node_indices()
+        .map(|node| {
+            let atom = &mol.graph[node];
+            (
+                mol.graph.edges(node).count(),
+                atom.number,
+                atom.isotope,
+                atom.charge,
+                atom.aromatic,
+                atom.total_num_hs(),
+                atom.num_radical_electrons,
+                atom.map_number,
+                atom.chirality.is_some(),
+            )
+        })
+        .collect();
+    dense_ranks(&invariants)
+}
+
+/// Refines the ranks with the neighbors' ranks and the stereo they tell apart, until the
+/// number of classes doesn't change.
+fn refine(mol: &Mol, mut ranks: Vec<usize>) -> Vec<usize> {
+    type Key = (usize, Vec<(usize, BondOrder)>, Option<bool>, Option<bool>);
+    loop {
+        let keys: Vec<Key> = mol
+            .graph
+            .node_indices()
+            .map(|node| {
+                let mut neighbors: Vec<(usize, BondOrder)> = mol
+                    .graph
+                    .edges(node)
+                    .map(|edge| (ranks[edge.target().index()], edge.weight().order))
+                    .collect();
+                neighbors.sort();
+                let double_bond = mol
+                    .graph
+                    .edges(node)
+                    .find(|edge| edge.weight().stereo.is_some())
+                    .and_then(|edge| ranked_double_bond_config(mol, edge.id(), &ranks));
+                (
+                    ranks[node.index()],
+                    neighbors,
+                    ranked_chirality(mol, node, &ranks).map(|c| c == Chirality::Clockwise),
+                    double_bond.map(|config| config == DoubleBondConfig::Trans),
+                )
+            })
+            .collect();
+        let refined = dense_ranks(&keys);
+        if count_classes(&refined) == count_classes(&ranks) {
+            return refined;
+        }
+        ranks = refined;
+    }
+}
+
+/// The chirality of the atom relative to its neighbors in rank order, `None` if it has
+/// none or two of its neighbors have the same rank.
+fn ranked_chirality(mol: &Mol, node: NodeIndex, ranks: &[usize]) -> Option<Chirality> {
+    let atom = &mol.graph[node];
+    let chirality = atom.chirality?;
+    let mut sorted = atom.chiral_neighbors.clone();
+    sorted.sort_by_key(|neighbor| ranks[neighbor.index()]);
+    if sorted
+        .windows(2)
+        .any(|pair| ranks[pair[0].index()] == ranks[pair[1].index()])
+    {
+        return None;
+    }
+    // the implicit hydrogen stays first
+    chirality.reordered(&atom.chiral_neighbors, &sorted)
+}
+
+/// The configuration of the double bond relative to the neighbor with lowest rank on each
+/// side, `None` if it has none or the neighbors on a side have the same rank.
+fn ranked_double_bond_config(
+    mol: &Mol,
+    double_bond: EdgeIndex,
+    ranks: &[usize],
+) -> Option<DoubleBondConfig> {
+    let stereo = mol.graph[double_bond].stereo?;
+    // unwrap: edge comes from the graph
+    let (start, end) = mol.graph.edge_endpoints(double_bond).unwrap();
+    let lowest = |atom: NodeIndex, other: NodeIndex| {
+        let mut neighbors: Vec<NodeIndex> = mol
+            .graph
+            .neighbors(atom)
+            .filter(|neighbor| *neighbor != other)
+            .collect();
+        neighbors.sort_by_key(|neighbor| ranks[neighbor.index()]);
+        match neighbors[..] {
+            [a, b] if ranks[a.index()] == ranks[b.index()] => None,
+            _ => neighbors.first().copied(),
+        }
+    };
+    let mut config = stereo.config;
+    if NodeIndex::from(stereo.start_neighbor) != lowest(start, end)? {
+        config = config.inverted();
+    }
+    if NodeIndex::from(stereo.end_neighbor) != lowest(end, start)? {
+        config = config.inverted();
+    }
+    Some(config)
+}
+
+/// Whether the ranks tell the neighbors of every stereocenter and stereo double bond apart.
+fn stereo_resolved(mol: &Mol, ranks: &[usize]) -> bool {
+    mol.graph.node_indices().all(|node| {
+        mol.graph[node].chirality.is_none() || ranked_chirality(mol, node, ranks).is_some()
+    }) && mol.graph.edge_indices().all(|edge| {
+        mol.graph[edge].stereo.is_none() || ranked_double_bond_config(mol, edge, ranks).is_some()
+    })
+}
+
+/// Ranks the keys: equal keys get the same rank, ranks are consecutive starting at 0.
+fn dense_ranks<K: Ord>(keys: &[K]) -> Vec<usize> {
+    let mut sorted: Vec<&K> = keys.iter().collect();
+    sorted.sort();
+    sorted.dedup();
+    keys.iter()
+        .map(|key| sorted.binary_search(&key).unwrap()) // unwrap: all keys are in sorted
+        .collect()
+}
+
+fn count_classes(ranks: &[usize]) -> usize {
+    ranks.iter().max().map(|max| max + 1).unwrap_or(0)
+}
+
+fn lowest_tied_rank(ranks: &[usize]) -> usize {
+    let mut counts = vec![0; ranks.len()];
+    for rank in ranks {
+        counts[*rank] += 1;
+    }
+    // unwrap: only called when there's a tie
+    counts.iter().position(|count| *count > 1).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    fn canonical(smiles: &str) -> String {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap().to_canonical_smiles()
+    }
+
+    fn assert_same_canonical(smiles: &[&str]) {
+        let expected = canonical(smiles[0]);
+        for s in smiles {
+            assert_eq!(expected, canonical(s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn canonical_ethanol() {
+        assert_same_canonical(&["CCO", "OCC", "C(O)C", "C(C)O"]);
+    }
+
+    #[test]
+    fn canonical_acetic_acid() {
+        assert_same_canonical(&["CC(=O)O", "OC(C)=O", "O=C(O)C", "C(=O)(O)C"]);
+    }
+
+    #[test]
+    fn canonical_toluene() {
+        assert_same_canonical(&["Cc1ccccc1", "c1ccccc1C", "c1cc(C)ccc1", "c1ccc(cc1)C"]);
+    }
+
+    #[test]
+    fn canonical_rings() {
+        assert_same_canonical(&["C1CC2CCC1C2", "C2CC1CCC2C1", "C1CC(C2)CCC12"]);
+        assert_same_canonical(&["c1ccc2ccccc2c1", "c1cccc2c1cccc2", "c12ccccc1cccc2"]);
+    }
+
+    #[test]
+    fn canonical_kekule_and_aromatic() {
+        assert_same_canonical(&["c1ccccc1", "C1=CC=CC=C1", "C1C=CC=CC=1"]);
+        assert_same_canonical(&["Cc1ccccc1", "CC1=CC=CC=C1", "C1=CC=C(C)C=C1"]);
+        assert_same_canonical(&["c1ccncc1", "C1=CC=NC=C1"]);
+        assert_same_canonical(&["c1cc[nH]c1", "C1=CNC=C1"]);
+        assert_same_canonical(&["c1ccc2ccccc2c1", "C1=CC=C2C=CC=CC2=C1"]);
+        assert_eq!("c1ccccc1", canonical("C1=CC=CC=C1"));
+        // not aromatic
+        assert_eq!(canonical("C1=CCC=C1"), canonical("C1=CC=CC1"));
+    }
+
+    #[test]
+    fn canonical_written_hydrogens() {
+        assert_same_canonical(&["OCC", "[OH]CC", "[OH][CH2][CH3]"]);
+        assert_same_canonical(&["[SH2]", "S"]);
+        assert_same_canonical(&["c1ccccc1", "[cH]1ccccc1", "c1cc[cH]cc1"]);
+        assert_same_canonical(&["CC(=O)O", "[CH3]C(=O)[OH]"]);
+        assert_eq!("CCO", canonical("[OH]CC"));
+        assert_eq!("c1ccccc1", canonical("[cH]1ccccc1"));
+        // hydrogens the valence model doesn't give stay in brackets
+        assert_same_canonical(&["[CH2]C", "C[CH2]"]);
+        assert_eq!("[SH4]", canonical("[SH4]"));
+        assert_ne!(canonical("[CH2]C"), canonical("CC"));
+    }
+
+    #[test]
+    fn canonical_after_json() {
+        for smiles in ["[CH2]C", "C[CH2]", "[OH]CC", "[cH]1ccccc1", "C[N+](C)(C)C"] {
+            let mol = SmilesParser::new().parse(smiles).unwrap();
+            let read = Mol::from_json(&mol.to_json()).unwrap();
+            assert_eq!(canonical(smiles), read.to_canonical_smiles(), "{}", smiles);
+        }
+    }
+
+    #[test]
+    fn canonical_charged() {
+        assert_same_canonical(&["C[NH3+]", "[NH3+]C"]);
+        assert_same_canonical(&["OC(=O)CC([O-])=O", "[O-]C(=O)CC(=O)O"]);
+    }
+
+    #[test]
+    fn canonical_chirality() {
+        // L-alanine
+        assert_same_canonical(&[
+            "C[C@H](N)C(=O)O",
+            "N[C@@H](C)C(=O)O",
+            "[C@H](N)(C)C(=O)O",
+            "OC(=O)[C@@H](N)C",
+        ]);
+        assert_same_canonical(&["C[C@H]1CCCCO1", "C[C@@H]1OCCCC1", "O1CCCC[C@@H]1C"]);
+        // D-alanine
+        assert_ne!(canonical("C[C@H](N)C(=O)O"), canonical("C[C@@H](N)C(=O)O"));
+    }
+
+    #[test]
+    fn canonical_double_bond_stereo() {
+        assert_same_canonical(&["F/C=C/F", "F\\C=C\\F", "C(\\F)=C/F", "C(/F)=C\\F"]);
+        assert_same_canonical(&["F/C=C\\F", "F\\C=C/F", "C(\\F)=C\\F"]);
+        assert_same_canonical(&["C/C=C/C(=O)O", "OC(=O)/C=C/C", "C(=O)(O)\\C=C\\C"]);
+        assert_ne!(canonical("F/C=C/F"), canonical("F/C=C\\F"));
+    }
+
+    #[test]
+    fn canonical_symmetric_stereocenters() {
+        // both isomers of 1,4-dimethylcyclohexane, each also written with both centers
+        // inverted
+        assert_same_canonical(&["C[C@H]1CC[C@@H](C)CC1", "C[C@@H]1CC[C@H](C)CC1"]);
+        assert_same_canonical(&["C[C@H]1CC[C@H](C)CC1", "C[C@@H]1CC[C@@H](C)CC1"]);
+        assert_ne!(
+            canonical("C[C@H]1CC[C@@H](C)CC1"),
+            canonical("C[C@H]1CC[C@H](C)CC1")
+        );
+        // myo-inositol, which is its own mirror image, and written from random atom orders
+        let myo = "O[C@H]1[C@H](O)[C@@H](O)[C@H](O)[C@H](O)[C@@H]1O";
+        assert_same_canonical(&[myo, "O[C@@H]1[C@@H](O)[C@H](O)[C@@H](O)[C@@H](O)[C@H]1O"]);
+        let parser = SmilesParser::new();
+        let scyllo = "O[C@H]1[C@H](O)[C@@H](O)[C@H](O)[C@@H](O)[C@@H]1O";
+        for smiles in ["C[C@H]1CC[C@@H](C)CC1", myo, scyllo] {
+            let mol = parser.parse(smiles).unwrap();
+            let expected = mol.to_canonical_smiles();
+            for written in mol.random_smiles(20, 3) {
+                assert_eq!(
+                    expected,
+                    canonical(&written),
+                    "{} written as {}",
+                    smiles,
+                    written
+                );
+            }
+        }
+        assert_ne!(canonical(myo), canonical(scyllo));
+    }
+
+    #[test]
+    fn canonical_symmetric_double_bonds() {
+        let parser = SmilesParser::new();
+        for smiles in [
+            "C/C=C/C(=C/C)/C=C/C",
+            "C/C=C/C(=C/C)/C=C\\C",
+            "C/C=C/C=C/C=C/C",
+        ] {
+            let mol = parser.parse(smiles).unwrap();
+            let expected = mol.to_canonical_smiles();
+            for written in mol.random_smiles(20, 3) {
+                assert_eq!(
+                    expected,
+                    canonical(&written),
+                    "{} written as {}",
+                    smiles,
+                    written
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_distinguishes_molecules() {
+        assert_ne!(canonical("CCO"), canonical("COC"));
+        assert_ne!(canonical("C=CC"), canonical("CCC"));
+        assert_ne!(canonical("[13CH4]"), canonical("C"));
+    }
+
+    #[test]
+    fn canonical_is_valid_smiles() {
+        let parser = SmilesParser::new();
+        let canonical = canonical("OC(=O)c1ccccc1OC(=O)C");
+
+        assert_eq!(13, parser.parse(&canonical).unwrap().num_atoms());
+    }
+
+    #[test]
+    fn canonical_ranks_ignore_input_order() {
+        let parser = SmilesParser::new();
+        let ranks = parser.parse("CCO").unwrap().canonical_ranks();
+        let reversed = parser.parse("OCC").unwrap().canonical_ranks();
+
+        assert_eq!(3, ranks.len());
+        assert_eq!(ranks, reversed.into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn symmetry_classes() {
+        let parser = SmilesParser::new();
+        let classes = |smiles: &str| parser.parse(smiles).unwrap().symmetry_classes();
+
+        assert_eq!(vec![0; 6], classes("c1ccccc1"));
+        // the methyls, the central carbon, the oxygen
+        let classes = classes("CC(C)(C)O");
+        assert_eq!(classes[0], classes[2]);
+        assert_eq!(classes[0], classes[3]);
+        assert_ne!(classes[0], classes[1]);
+        assert_ne!(classes[1], classes[4]);
+    }
+}
@@ -0,0 +1,494 @@
+//! A quick syntax check of SMILES, without building the molecule.
+
+use crate::smiles::{is_symbol, is_symbol_prefix, SmilesParserOptions};
+
+/// Ring bond numbers go up to `%99`, or `%(99)`.
+const RING_NUMBERS: usize = 100;
+
+/// Whether [`SmilesParser`](crate::SmilesParser) with the default options parses the input,
+/// as far as the syntax goes: see [`first_error`].
+pub fn is_valid(smiles: &str) -> bool {
+    first_error(smiles).is_none()
+}
+
+/// Position of the error [`SmilesParser`](crate::SmilesParser) with the default options
+/// reports for the input, `None` if it parses. Checks the tokens and that branches and
+/// ring bonds are closed in a single pass, without allocating.
+///
+/// Which atoms a ring bond joins isn't checked: the parser also rejects ring bonds from
+/// an atom to itself and between atoms already bonded, like `C11` and `C1C1`.
+pub fn first_error(smiles: &str) -> Option<usize> {
+    Checker {
+        input: smiles,
+        cursor: 0,
+    }
+    .check()
+    .err()
+}
+
+/// What a token is, as far as the structure is concerned.
+enum Token {
+    Atom,
+    RingBond(usize),
+    Bond(u8),
+    BranchOpen,
+    BranchClose,
+    Dot,
+}
+
+/// Reads the input byte by byte. Only ASCII is valid, so until the first error, byte
+/// positions are character positions.
+struct Checker<'a> {
+    input: &'a str,
+    cursor: usize,
+}
+
+impl Checker<'_> {
+    fn check(mut self) -> Result<(), usize> {
+        // position of the opening of each open ring bond, with its bond symbol
+        let mut rings: [Option<(usize, Option<u8>)>; RING_NUMBERS] = [None; RING_NUMBERS];
+        // position of the first ring bond closed with a bond symbol that conflicts with the
+        // one it was opened with, reported after the syntax errors like the parser does
+        let mut conflict: Option<usize> = None;
+        let mut depth = 0usize;
+        let mut seen_atom = false;
+        // a '(' was read and no atom since
+        let mut empty_branch = false;
+        // a '.' was read, the next atom starts a new fragment
+        let mut dot = false;
+        // position of a bond symbol not yet applied to a bond, with the symbol
+        let mut pending_bond: Option<(usize, u8)> = None;
+
+        while let Some(c) = self.pop() {
+            let position = self.cursor - 1;
+            let token = match c {
+                b'C' => {
+                    self.take(b'l');
+                    Token::Atom
+                }
+                b'B' => {
+                    self.take(b'r');
+                    Token::Atom
+                }
+                b'N' | b'O' | b'P' | b'S' | b'F' | b'I' | b'b' | b'c' | b'n' | b'o' | b'p'
+                | b's' | b'*' => Token::Atom,
+                b'[' => {
+                    self.bracket_atom()?;
+                    Token::Atom
+                }
+                b'%' if self.take(b'(') => Token::RingBond(self.extended_ring_number()?),
+                b'%' => {
+                    let tens = self.digit().ok_or(self.cursor)?;
+                    let units = self.digit().ok_or(self.cursor)?;
+                    Token::RingBond(tens * 10 + units)
+                }
+                b'0'..=b'9' => Token::RingBond((c - b'0') as usize),
+                b'(' => Token::BranchOpen,
+                b')' => Token::BranchClose,
+                b'.' => Token::Dot,
+                b'-' | b'=' | b'#' | b'$' | b':' | b'/' | b'\\' => Token::Bond(c),
+                _ => return Err(position),
+            };
+
+            // the checks of the AST parser, in the same order
+            let has_last_atom = seen_atom && !dot;
+            match token {
+                Token::Atom => {
+                    pending_bond = None;
+                    seen_atom = true;
+                    dot = false;
+                    empty_branch = false;
+                }
+                Token::RingBond(number) => {
+                    let symbol = pending_bond.take().map(|(_, symbol)| symbol);
+                    // out of range or without an atom before it
+                    if number >= RING_NUMBERS || !has_last_atom {
+                        return Err(position);
+                    }
+                    rings[number] = match rings[number] {
+                        Some((_, start)) => {
+                            if let (Some(start), Some(end)) = (start, symbol) {
+                                // the same direction at both ends goes opposite ways
+                                let opposite = start == end && matches!(start, b'/' | b'\\');
+                                if bond_order(start) != bond_order(end) || opposite {
+                                    conflict.get_or_insert(position);
+                                }
+                            }
+                            None
+                        }
+                        None => Some((position, symbol)),
+                    };
+                }
+                Token::Bond(symbol) => {
+                    if !has_last_atom || pending_bond.is_some() {
+                        return Err(position);
+                    }
+                    pending_bond = Some((position, symbol));
+                }
+                Token::BranchOpen => {
+                    if !has_last_atom {
+                        return Err(position);
+                    }
+                    if let Some((bond, _)) = pending_bond {
+                        return Err(bond);
+                    }
+                    if depth == SmilesParserOptions::default().max_branch_depth {
+                        return Err(position);
+                    }
+                    depth += 1;
+                    empty_branch = true;
+                }
+                Token::BranchClose => {
+                    if let Some((bond, _)) = pending_bond {
+                        return Err(bond);
+                    }
+                    if !has_last_atom || empty_branch || depth == 0 {
+                        return Err(position);
+                    }
+                    depth -= 1;
+                }
+                Token::Dot => {
+                    if let Some((bond, _)) = pending_bond {
+                        return Err(bond);
+                    }
+                    if !has_last_atom {
+                        return Err(position);
+                    }
+                    dot = true;
+                    empty_branch = false;
+                }
+            }
+        }
+
+        if dot {
+            return Err(self.cursor);
+        }
+        if let Some((bond, _)) = pending_bond {
+            return Err(bond);
+        }
+        if depth > 0 {
+            return Err(self.innermost_open_branch());
+        }
+        if let Some(position) = conflict {
+            return Err(position);
+        }
+        match rings.iter().flatten().map(|(position, _)| position).min() {
+            Some(position) => Err(*position),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks a bracket atom like [`bracket_atom`](crate::lexer::bracket_atom) does.
+    /// Expects the opening `[` to have been consumed already.
+    fn bracket_atom(&mut self) -> Result<(), usize> {
+        // isotope
+        self.number_at_most(u16::MAX.into())?;
+
+        let start = self.cursor;
+        let input = self.input;
+        let slice = |len: usize| input.get(start..start + len);
+        if slice(2).is_some_and(is_symbol) {
+            self.cursor += 2;
+        } else if slice(1).is_some_and(|s| is_symbol(s) || matches!(s, "*" | "D" | "T")) {
+            self.cursor += 1;
+        } else if slice(1).is_some_and(is_symbol_prefix) {
+            // the start of a two letter symbol, without its second letter
+            return Err(if start + 1 < self.input.len() {
+                start
+            } else {
+                start + 1
+            });
+        } else {
+            return Err(start);
+        }
+
+        // chirality
+        if self.take(b'@') && !self.take(b'@') && self.take(b'T') {
+            if !self.take(b'H') {
+                return Err(self.cursor);
+            }
+            if !self.take(b'1') && !self.take(b'2') {
+                return Err(self.cursor);
+            }
+        }
+
+        if self.take(b'H') {
+            self.number_at_most(u8::MAX.into())?;
+        }
+
+        for sign in [b'+', b'-'] {
+            if self.take(sign) {
+                if !self.number_at_most(i8::MAX as u64)? {
+                    // as many signs as fit in an i8
+                    let max = if sign == b'+' { 127 } else { 128 };
+                    let mut count = 1;
+                    while self.take(sign) {
+                        count += 1;
+                        if count > max {
+                            return Err(self.cursor - 1);
+                        }
+                    }
+                }
+                break;
+            }
+        }
+
+        if self.take(b':') && !self.number_at_most(u32::MAX.into())? {
+            return Err(self.cursor);
+        }
+
+        if !self.take(b']') {
+            return Err(self.cursor);
+        }
+        Ok(())
+    }
+
+    /// Reads a `%(n)` ring number like the lexer does.
+    /// Expects the `%(` to have been consumed already.
+    fn extended_ring_number(&mut self) -> Result<usize, usize> {
+        let mut number = self.digit().ok_or(self.cursor)?;
+        for _ in 0..2 {
+            match self.digit() {
+                Some(digit) => number = number * 10 + digit,
+                None => break,
+            }
+        }
+        if !self.take(b')') {
+            return Err(self.cursor);
+        }
+        Ok(number)
+    }
+
+    /// Position of the last `(` without its `)`.
+    fn innermost_open_branch(&self) -> usize {
+        let mut closed = 0;
+        for (position, c) in self.input.bytes().enumerate().rev() {
+            match c {
+                b')' => closed += 1,
+                b'(' if closed == 0 => return position,
+                b'(' => closed -= 1,
+                _ => {}
+            }
+        }
+        unreachable!("a branch is open")
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        let c = *self.input.as_bytes().get(self.cursor)?;
+        self.cursor += 1;
+        Some(c)
+    }
+
+    fn take(&mut self, c: u8) -> bool {
+        let taken = self.input.as_bytes().get(self.cursor) == Some(&c);
+        if taken {
+            self.cursor += 1;
+        }
+        taken
+    }
+
+    fn digit(&mut self) -> Option<usize> {
+        let c = *self.input.as_bytes().get(self.cursor)?;
+        if !c.is_ascii_digit() {
+            return None;
+        }
+        self.cursor += 1;
+        Some((c - b'0') as usize)
+    }
+
+    /// Reads a (possibly multi-digit) unsigned number, saturating like the lexer.
+    fn number(&mut self) -> Option<u64> {
+        let mut number: Option<u64> = None;
+        while let Some(digit) = self.digit() {
+            number = Some(
+                number
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit as u64),
+            );
+        }
+        number
+    }
+
+    /// Reads an optional number, which is out of range above `max`. Returns whether there
+    /// was one.
+    fn number_at_most(&mut self, max: u64) -> Result<bool, usize> {
+        match self.number() {
+            Some(number) if number > max => Err(self.cursor - 1),
+            number => Ok(number.is_some()),
+        }
+    }
+}
+
+/// The bond order of a bond symbol, as a number: directions are single bonds.
+fn bond_order(symbol: u8) -> u8 {
+    match symbol {
+        b'=' => 2,
+        b'#' => 3,
+        b'$' => 4,
+        b':' => 5,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::SmilesErrorKind;
+    use crate::smiles::SmilesParser;
+
+    use proptest::prelude::*;
+
+    use super::{first_error, is_valid};
+
+    fn parser_error(smiles: &str) -> Option<usize> {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).err().map(|error| error.position)
+    }
+
+    /// Whether the parser rejects the ring bonds for the atoms they join, which isn't checked.
+    fn joins_wrong_atoms(smiles: &str) -> bool {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).is_err_and(|error| {
+            matches!(
+                error.kind,
+                SmilesErrorKind::RingBondToSelf(_) | SmilesErrorKind::DuplicateBond(_)
+            )
+        })
+    }
+
+    const CORPUS: [&str; 24] = [
+        "CCO",
+        "c1ccccc1",
+        "CC(=O)Oc1ccccc1C(=O)O",
+        "CN1C=NC2=C1C(=O)N(C(=O)N2C)C",
+        "F/C=C/F",
+        "N[C@@H](C)C(=O)O",
+        "[C@TH1H](F)(Cl)Br",
+        "[13CH4]",
+        "[NH4+].[Cl-]",
+        "[Fe++]",
+        "[Cu+2]",
+        "[CH3:12]",
+        "[2H]C([D])[T]",
+        "C%12CC%12",
+        "C=1CC1",
+        "*C(*)=*",
+        "[se]1cccc1",
+        "BrCCl",
+        "C((C))O",
+        "C.C",
+        "OCC#N",
+        "C$C",
+        "C:C",
+        "C\\C=C\\C",
+    ];
+
+    #[test]
+    fn valid() {
+        for smiles in CORPUS {
+            assert!(is_valid(smiles), "{}", smiles);
+        }
+        // the empty molecule
+        assert!(is_valid(""));
+        // syntactically
+        assert!(is_valid("C11"));
+        assert!(is_valid("C12CC12"));
+        assert!(is_valid("C%(12)CC%(5)C5%12"));
+    }
+
+    #[test]
+    fn invalid() {
+        for smiles in [
+            "C(",
+            "C)",
+            "(C)",
+            "C1CC",
+            "C=",
+            "C==C",
+            ".C",
+            "C.",
+            "C..C",
+            "C(=)C",
+            "[C",
+            "[Xy]",
+            "[X",
+            "[C@T]",
+            "[C@TH3]",
+            "[CH256]",
+            "[C+128]",
+            "[70000C]",
+            "[CH3:]",
+            "[CH3:4294967296]",
+            "[C:99999999999]",
+            "C%1",
+            "Cé",
+            "[Cé]",
+            "C((C)",
+            "C(C))",
+            "Q",
+            "C=1CC#1",
+            "C$1CC=1C",
+            "C=1CC-1C1",
+            "C/1CC/1",
+            "C()C",
+            "C(1)CC1",
+            "C%(123)CC%(123)",
+            "C=%(12)CC#%(12)",
+            "C%(",
+            "C%()",
+            "C%(1234)C",
+        ] {
+            assert!(!is_valid(smiles), "{}", smiles);
+            assert_eq!(parser_error(smiles), first_error(smiles), "{}", smiles);
+        }
+        // a charge as repeated signs, past the range of an i8
+        for sign in ["+", "-"] {
+            for count in [127, 128, 129] {
+                let smiles = format!("[C{}]", sign.repeat(count));
+                assert_eq!(parser_error(&smiles), first_error(&smiles), "{}", smiles);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn matches_parser(smiles in r"[CNOcn\[\]()=#.%@H+\-:0-9]{0,30}") {
+            if !joins_wrong_atoms(&smiles) {
+                prop_assert_eq!(parser_error(&smiles), first_error(&smiles), "{}", smiles);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_parser_on_mutations() {
+        const INSERTED: &str = "C1%2()[]=#.@H+-:Tl5é";
+        for smiles in CORPUS {
+            let chars: Vec<char> = smiles.chars().collect();
+            for i in 0..=chars.len() {
+                let mut mutations = vec![];
+                if i < chars.len() {
+                    let mut deleted = chars.clone();
+                    deleted.remove(i);
+                    mutations.push(deleted);
+                }
+                for c in INSERTED.chars() {
+                    let mut inserted = chars.clone();
+                    inserted.insert(i, c);
+                    mutations.push(inserted);
+                }
+                for mutation in mutations {
+                    let mutation: String = mutation.into_iter().collect();
+                    if joins_wrong_atoms(&mutation) {
+                        continue;
+                    }
+                    assert_eq!(
+                        parser_error(&mutation),
+                        first_error(&mutation),
+                        "{}",
+                        mutation
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Comparing molecules the way a chemist would, for tests: [`Mol::is_same_mol`] and
+//! [`assert_same_mol!`](crate::assert_same_mol).
+
+use alloc::string::String;
+
+use crate::aromaticity::AromaticityModel;
+use crate::types::Mol;
+use crate::valence;
+
+/// The molecule written one way only: hydrogen atoms folded into their neighbors,
+/// aromaticity perceived from scratch (unless it can't be kekulized), and hydrogens
+/// implicit when the valence model gives them.
+pub(crate) fn normalized(mol: &Mol) -> Mol {
+    let mut mol = mol.remove_hs();
+    let mut aromatic = mol.clone();
+    if aromatic.set_aromaticity(AromaticityModel::Default).is_ok() {
+        mol = aromatic;
+    }
+    for node in mol.graph.node_indices() {
+        let hydrogens = mol.graph[node].total_num_hs();
+        mol.graph[node].explicit_h = None;
+        let implicit = valence::implicit_h_count(&mol, node);
+        let atom = &mut mol.graph[node];
+        if implicit == hydrogens {
+            atom.implicit_h = hydrogens;
+        } else {
+            atom.explicit_h = Some(hydrogens);
+            atom.implicit_h = 0;
+        }
+    }
+    mol
+}
+
+/// The canonical SMILES of the normalized molecule, the same for the same molecule
+/// however it was written.
+pub(crate) fn normalized_smiles(mol: &Mol) -> String {
+    normalized(mol).to_canonical_smiles()
+}
+
+/// Asserts that two [`Mol`](crate::Mol)s are the same molecule, see
+/// [`Mol::is_same_mol`](crate::Mol::is_same_mol). On failure, the message shows the
+/// canonical SMILES of both.
+#[macro_export]
+macro_rules! assert_same_mol {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_same_mol!($left, $right, "molecules differ")
+    };
+    ($left:expr, $right:expr, $($message:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                let left: &$crate::Mol = left;
+                let right: &$crate::Mol = right;
+                if !left.is_same_mol(right) {
+                    panic!(
+                        "{}\n  left: {}\n right: {}",
+                        format_args!($($message)+),
+                        left.to_canonical_smiles(),
+                        right.to_canonical_smiles()
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    #[test]
+    fn same_mol() {
+        // atom order, kekulized or aromatic, hydrogens as atoms or counts
+        assert!(mol("OCC").is_same_mol(&mol("CCO")));
+        assert!(mol("C1=CC=CC=C1O").is_same_mol(&mol("Oc1ccccc1")));
+        assert!(mol("[H]OC([H])([H])C").is_same_mol(&mol("CCO")));
+        assert!(mol("[CH4]").is_same_mol(&mol("C")));
+        assert!(mol("N[C@@H](C)C(=O)O").is_same_mol(&mol("C[C@H](N)C(=O)O")));
+
+        assert!(!mol("CCO").is_same_mol(&mol("CCN")));
+        assert!(!mol("[CH2]C").is_same_mol(&mol("CC")));
+        assert!(!mol("[13CH4]").is_same_mol(&mol("C")));
+        assert!(!mol("N[C@@H](C)C(=O)O").is_same_mol(&mol("N[C@H](C)C(=O)O")));
+        assert!(!mol("F/C=C/F").is_same_mol(&mol("F/C=C\\F")));
+    }
+
+    #[test]
+    fn assert_macro() {
+        assert_same_mol!(mol("c1ccccc1"), mol("C1=CC=CC=C1"));
+        assert_same_mol!(&mol("CCO"), &mol("OCC"), "ethanol {}", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ethanol\n  left: CCO\n right: CCN")]
+    fn assert_macro_fails() {
+        assert_same_mol!(mol("CCO"), mol("CCN"), "ethanol");
+    }
+}
@@ -0,0 +1,187 @@
+//! How much of the OpenSMILES specification the parser supports, from test cases for
+//! each of its features: `println!("{}", smiles::conformance::run())`.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error::SmilesError;
+use crate::smiles::SmilesParser;
+
+/// The cases, in `data/opensmiles.txt`.
+const CASES: &str = include_str!("../data/opensmiles.txt");
+
+/// A SMILES from the specification, and what the parser should make of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Case {
+    /// The section of the specification, e.g. "Ring bonds".
+    pub feature: &'static str,
+    pub smiles: &'static str,
+    /// Number of atoms and bonds, `None` for SMILES that must be rejected.
+    pub expected: Option<(usize, usize)>,
+}
+
+/// What the parser made of a case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseResult {
+    pub case: Case,
+    /// Number of atoms and bonds of the parsed molecule.
+    pub parsed: Result<(usize, usize), SmilesError>,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        match self.case.expected {
+            Some(counts) => self.parsed.as_ref() == Ok(&counts),
+            None => self.parsed.is_err(),
+        }
+    }
+}
+
+/// The results of all the cases, in the order of the specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub results: Vec<CaseResult>,
+}
+
+impl Report {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|result| result.passed()).count()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.results.iter().filter(|result| !result.passed())
+    }
+
+    /// Each feature with its number of passed cases and of cases.
+    pub fn features(&self) -> Vec<(&'static str, usize, usize)> {
+        let mut features: Vec<(&'static str, usize, usize)> = vec![];
+        for result in &self.results {
+            match features.last_mut() {
+                Some((feature, passed, total)) if *feature == result.case.feature => {
+                    *passed += result.passed() as usize;
+                    *total += 1;
+                }
+                _ => features.push((result.case.feature, result.passed() as usize, 1)),
+            }
+        }
+        features
+    }
+}
+
+/// One line per feature, then the failed cases:
+///
+/// ```text
+/// Ring bonds                   13/13
+/// Ring bond rules               8/8
+/// ...
+/// 123/129 cases passed
+///
+/// Termination: "CCO ethanol" should have 3 atoms and 2 bonds, unexpected character ' ' at position 3
+/// ```
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (feature, passed, total) in self.features() {
+            writeln!(f, "{:<28}{:>3}/{}", feature, passed, total)?;
+        }
+        writeln!(f, "{}/{} cases passed", self.passed(), self.results.len())?;
+        for failure in self.failures() {
+            write!(f, "\n{}: {:?} ", failure.case.feature, failure.case.smiles)?;
+            match failure.case.expected {
+                Some((atoms, bonds)) => {
+                    write!(f, "should have {} atoms and {} bonds", atoms, bonds)?
+                }
+                None => write!(f, "should be rejected")?,
+            }
+            match &failure.parsed {
+                Ok((atoms, bonds)) => write!(f, ", parsed to {} atoms and {} bonds", atoms, bonds)?,
+                Err(error) => write!(f, ", {}", error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The cases of all the features.
+pub fn cases() -> Vec<Case> {
+    let mut feature = "";
+    let mut cases = vec![];
+    for line in CASES.lines() {
+        if let Some(name) = line.strip_prefix("## ") {
+            feature = name;
+            continue;
+        }
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        // the SMILES can have a tab, the expectation can't
+        // unwrap: every case has an expectation
+        let (smiles, expected) = line.rsplit_once('\t').unwrap();
+        let expected = match expected {
+            "invalid" => None,
+            counts => {
+                // unwrap: the counts are two numbers
+                let (atoms, bonds) = counts.split_once(' ').unwrap();
+                Some((atoms.parse().unwrap(), bonds.parse().unwrap()))
+            }
+        };
+        cases.push(Case {
+            feature,
+            smiles,
+            expected,
+        });
+    }
+    cases
+}
+
+/// Parses every case with the default options.
+pub fn run() -> Report {
+    let parser = SmilesParser::new();
+    let results = cases()
+        .into_iter()
+        .map(|case| CaseResult {
+            case,
+            parsed: parser
+                .parse(case.smiles)
+                .map(|mol| (mol.num_atoms(), mol.num_bonds())),
+        })
+        .collect();
+    Report { results }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cases, run};
+
+    #[test]
+    fn cases_are_well_formed() {
+        let cases = cases();
+        assert!(cases.len() > 100);
+        assert!(cases.iter().all(|case| !case.feature.is_empty()));
+    }
+
+    #[test]
+    fn report() {
+        let report = run();
+        let failures: Vec<&str> = report.failures().map(|result| result.case.smiles).collect();
+
+        // what isn't supported yet, update when it is
+        assert_eq!(
+            vec![
+                // only tetrahedral chirality is read
+                "NC(Br)=[C@AL1]=C(O)C",
+                "F[Po@SP1](Cl)(Br)I",
+                "S[As@TB1](F)(Cl)(Br)N",
+                "C[Co@OH1](F)(Cl)(Br)(I)S",
+                // whitespace doesn't end the SMILES
+                "CCO ethanol",
+                "CCO\tethanol",
+            ],
+            failures
+        );
+        assert_eq!(report.results.len() - failures.len(), report.passed());
+        assert!(report
+            .to_string()
+            .contains("Ring bond rules               8/8"));
+    }
+}
@@ -0,0 +1,933 @@
+//! 3D coordinates by distance geometry. Bounds on the distances between atoms come from
+//! standard bond lengths and angles, random distances within the bounds are embedded in
+//! 3D, and the coordinates are minimized against the bounds, the chirality of the
+//! stereocenters and the planarity of sp2 atoms, then cleaned up with a simple force field.
+//! Hydrogens only get coordinates when they're atoms, see [`Mol::add_hs`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::f64::consts::PI;
+use core::ops::{Add, Mul, Sub};
+
+use hashbrown::HashMap;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+
+use crate::fragments::fragment_atoms;
+use crate::periodic_table::Element;
+use crate::rings::RingInfo;
+use crate::smiles::element_symbol;
+use crate::types::{AtomIdx, BondIdx, BondOrder, Chirality, DoubleBondConfig, Mol};
+use crate::writer::SplitMix64;
+
+/// Covalent radius of the elements without one, in Å.
+const DEFAULT_RADIUS: f64 = 0.76;
+/// Lowest distance between heavy atoms 4 or more bonds apart, in Å.
+const MIN_DISTANCE: f64 = 2.5;
+/// Lowest distance between a hydrogen and an atom 4 or more bonds apart, in Å.
+const MIN_HYDROGEN_DISTANCE: f64 = 2.0;
+/// Distance of atoms without an upper bound, in Å.
+const MAX_DISTANCE: f64 = 1000.0;
+/// Lowest volume of the tetrahedron of a stereocenter's neighbors, in Å³.
+const MIN_VOLUME: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Point3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Point3 {
+        Point3 { x, y, z }
+    }
+
+    pub fn distance(self, other: Point3) -> f64 {
+        (self - other).norm()
+    }
+
+    fn norm(self) -> f64 {
+        libm::sqrt(self.dot(self))
+    }
+
+    fn dot(self, other: Point3) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn cross(self, other: Point3) -> Point3 {
+        Point3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+}
+
+impl Add for Point3 {
+    type Output = Point3;
+
+    fn add(self, other: Point3) -> Point3 {
+        Point3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Point3 {
+    type Output = Point3;
+
+    fn sub(self, other: Point3) -> Point3 {
+        Point3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f64> for Point3 {
+    type Output = Point3;
+
+    fn mul(self, factor: f64) -> Point3 {
+        Point3::new(self.x * factor, self.y * factor, self.z * factor)
+    }
+}
+
+/// 3D coordinates of a molecule's atoms, in Å, indexed like the atoms.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Conformer {
+    positions: Vec<Point3>,
+}
+
+impl Conformer {
+    pub fn new(positions: Vec<Point3>) -> Conformer {
+        Conformer { positions }
+    }
+
+    pub fn position(&self, atom_idx: AtomIdx) -> Option<Point3> {
+        self.positions.get(atom_idx.index()).copied()
+    }
+
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    /// XYZ file of the molecule at these coordinates: the number of atoms, its SMILES as
+    /// the comment line, then the element and coordinates of each atom, `X` for `*`.
+    pub fn to_xyz(&self, mol: &Mol) -> String {
+        let mut out = format!("{}\n{}\n", mol.num_atoms(), mol.to_smiles());
+        for (atom, p) in mol.graph.node_weights().zip(self.atoms(mol)) {
+            out.push_str(&format!(
+                "{:<2} {:>12.6} {:>12.6} {:>12.6}\n",
+                element_symbol(atom.number).unwrap_or("X"),
+                p.x,
+                p.y,
+                p.z
+            ));
+        }
+        out
+    }
+
+    /// PDB file of the molecule at these coordinates: a `HETATM` record for each atom, all
+    /// in one `UNL` residue, and `CONECT` records for the bonds.
+    pub fn to_pdb(&self, mol: &Mol) -> String {
+        let mut out = String::new();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (i, (atom, p)) in mol.graph.node_weights().zip(self.atoms(mol)).enumerate() {
+            let element = element_symbol(atom.number).unwrap_or("X");
+            let symbol = element.to_uppercase();
+            let count = counts.entry(element).or_default();
+            *count += 1;
+            // names of one letter elements start in the second column
+            let mut name = format!("{}{}", symbol, count);
+            name.truncate(4);
+            if symbol.len() == 1 && name.len() < 4 {
+                name.insert(0, ' ');
+            }
+            let charge = match atom.charge {
+                0 => String::new(),
+                c => format!("{}{}", c.unsigned_abs(), if c > 0 { '+' } else { '-' }),
+            };
+            out.push_str(&format!(
+                "HETATM{:>5} {:<4} UNL A   1    {:>8.3}{:>8.3}{:>8.3}{:>6.2}{:>6.2}          {:>2}{:<2}\n",
+                i + 1,
+                name,
+                p.x,
+                p.y,
+                p.z,
+                1.0,
+                0.0,
+                symbol,
+                charge
+            ));
+        }
+        for node in mol.graph.node_indices() {
+            let mut neighbors: Vec<usize> =
+                mol.graph.neighbors(node).map(|n| n.index() + 1).collect();
+            neighbors.sort();
+            for chunk in neighbors.chunks(4) {
+                out.push_str(&format!("CONECT{:>5}", node.index() + 1));
+                for neighbor in chunk {
+                    out.push_str(&format!("{:>5}", neighbor));
+                }
+                out.push('\n');
+            }
+        }
+        out.push_str("END\n");
+        out
+    }
+
+    /// The position of each atom of the molecule, the origin for those without one.
+    fn atoms<'a>(&'a self, mol: &Mol) -> impl Iterator<Item = Point3> + 'a {
+        (0..mol.num_atoms()).map(|i| self.positions.get(i).copied().unwrap_or_default())
+    }
+}
+
+/// Options for [`Mol::embed_3d`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbedOptions {
+    /// Seed of the random distances: different seeds give different conformers.
+    pub seed: u64,
+    /// Steps of each of the two minimizations.
+    pub max_iterations: usize,
+}
+
+impl Default for EmbedOptions {
+    fn default() -> Self {
+        EmbedOptions {
+            seed: 0,
+            max_iterations: 1000,
+        }
+    }
+}
+
+/// Generates 3D coordinates. Disconnected components are placed side by side.
+pub fn embed(mol: &Mol, options: &EmbedOptions) -> Conformer {
+    let ring_info = mol.ring_info();
+    let mut rng = SplitMix64(options.seed);
+    let mut positions = vec![Point3::default(); mol.graph.node_count()];
+    let mut offset_x = 0.0;
+    for atoms in fragment_atoms(mol) {
+        let atoms: Vec<usize> = atoms.iter().map(|atom| atom.index()).collect();
+        let bounds = Bounds::new(mol, &ring_info, &atoms);
+        let volumes = volumes(mol, &atoms);
+
+        let mut smoothed = bounds.clone();
+        smoothed.smooth();
+        let mut coords = smoothed.random_coords(&mut rng);
+        // the mirror image if it gets more stereocenters right
+        let signs: Vec<f64> = volumes
+            .iter()
+            .filter(|(_, sign)| *sign != 0.0)
+            .map(|(points, sign)| sign * signed_volume(&coords, points))
+            .collect();
+        if signs.iter().filter(|sign| **sign < 0.0).count() * 2 > signs.len() {
+            for p in &mut coords {
+                p.z = -p.z;
+            }
+        }
+
+        let terms = Terms {
+            distances: smoothed.pairs(|_| true),
+            volumes: volumes.clone(),
+        };
+        terms.minimize(&mut coords, options.max_iterations);
+        let terms = Terms {
+            distances: bounds.pairs(|bounded| bounded),
+            volumes,
+        };
+        terms.minimize(&mut coords, options.max_iterations);
+
+        let min_x = coords.iter().map(|p| p.x).fold(f64::INFINITY, f64::min);
+        let max_x = coords.iter().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max);
+        let center =
+            coords.iter().fold(Point3::default(), |sum, p| sum + *p) * (1.0 / coords.len() as f64);
+        let shift = Point3::new(offset_x - min_x, -center.y, -center.z);
+        for (atom, p) in atoms.iter().zip(coords) {
+            positions[*atom] = p + shift;
+        }
+        offset_x += max_x - min_x + MIN_DISTANCE;
+    }
+    Conformer { positions }
+}
+
+/// Lower and upper bounds of the distances between the atoms of a component.
+#[derive(Debug, Clone)]
+struct Bounds {
+    n: usize,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    // bounds from bond lengths, angles and torsions, not only the lowest distance
+    bounded: Vec<bool>,
+}
+
+impl Bounds {
+    fn new(mol: &Mol, ring_info: &RingInfo, atoms: &[usize]) -> Bounds {
+        let graph = &mol.graph;
+        let n = atoms.len();
+        let mut local = vec![usize::MAX; graph.node_count()];
+        for (i, atom) in atoms.iter().enumerate() {
+            local[*atom] = i;
+        }
+        let mut bounds = Bounds {
+            n,
+            lower: vec![0.0; n * n],
+            upper: vec![MAX_DISTANCE; n * n],
+            bounded: vec![false; n * n],
+        };
+        for (i, a) in atoms.iter().enumerate() {
+            for (j, b) in atoms.iter().enumerate().skip(i + 1) {
+                let hydrogen = [a, b]
+                    .iter()
+                    .any(|atom| graph[NodeIndex::new(**atom)].number == 1);
+                let distance = if hydrogen {
+                    MIN_HYDROGEN_DISTANCE
+                } else {
+                    MIN_DISTANCE
+                };
+                bounds.set(i, j, distance, MAX_DISTANCE);
+            }
+        }
+
+        // bonds, then angles, then torsions: the first ones found win
+        let mut level = vec![0; n * n];
+        let mut restrain = |bounds: &mut Bounds, i: usize, j: usize, l: f64, u: f64, at: u8| {
+            let index = i * n + j;
+            if level[index] != 0 && level[index] < at {
+                return;
+            }
+            // several paths of the same length: any of their distances
+            let (l, u) = if level[index] == at {
+                (l.min(bounds.lower[index]), u.max(bounds.upper[index]))
+            } else {
+                (l, u)
+            };
+            bounds.set(i, j, l, u);
+            level[index] = at;
+            level[j * n + i] = at;
+            bounds.bounded[index] = true;
+            bounds.bounded[j * n + i] = true;
+        };
+
+        let bond_length = |a: NodeIndex, b: NodeIndex| {
+            // unwrap: the atoms are bonded
+            let edge = graph.find_edge(a, b).unwrap();
+            let radius = |atom: NodeIndex| {
+                Element::from_number(graph[atom].number)
+                    .and_then(|element| element.covalent_radius)
+                    .unwrap_or(DEFAULT_RADIUS)
+            };
+            let factor = match graph[edge].order {
+                BondOrder::Single => 1.0,
+                BondOrder::Aromatic => 0.93,
+                BondOrder::Double => 0.87,
+                BondOrder::Triple => 0.78,
+                BondOrder::Quadruple => 0.75,
+            };
+            (radius(a) + radius(b)) * factor
+        };
+        let angle = |center: NodeIndex, a: NodeIndex, b: NodeIndex| {
+            // unwrap: the atoms are bonded
+            let edges = [a, b].map(|n| BondIdx::from(graph.find_edge(center, n).unwrap()));
+            let ring = ring_info
+                .bond_rings(edges[0])
+                .iter()
+                .copied()
+                .filter(|ring| ring_info.bond_rings(edges[1]).contains(ring));
+            let ring_size = ring.map(|ring| ring_info.rings()[ring].atoms.len()).min();
+            match ring_size {
+                Some(size) if size <= 5 => PI * (size - 2) as f64 / size as f64,
+                _ => ideal_angle(mol, center),
+            }
+        };
+
+        let edges: Vec<(EdgeIndex, NodeIndex, NodeIndex)> = graph
+            .edge_indices()
+            .filter_map(|edge| {
+                // unwrap: edge comes from the graph
+                let (a, b) = graph.edge_endpoints(edge).unwrap();
+                (local[a.index()] != usize::MAX).then_some((edge, a, b))
+            })
+            .collect();
+        for (_, a, b) in &edges {
+            let (a, b) = (*a, *b);
+            let length = bond_length(a, b);
+            let (i, j) = (local[a.index()], local[b.index()]);
+            restrain(&mut bounds, i, j, length - 0.01, length + 0.01, 1);
+        }
+        for center in atoms.iter().map(|atom| NodeIndex::new(*atom)) {
+            let neighbors: Vec<NodeIndex> = graph.neighbors(center).collect();
+            for (k, a) in neighbors.iter().enumerate() {
+                for b in &neighbors[k + 1..] {
+                    let angle = angle(center, *a, *b);
+                    let distance =
+                        third_side(bond_length(center, *a), bond_length(center, *b), angle);
+                    // the distance barely changes around a straight angle
+                    let (l, u) = if angle == PI {
+                        (distance - 0.005, distance)
+                    } else {
+                        (distance - 0.04, distance + 0.04)
+                    };
+                    let (i, j) = (local[a.index()], local[b.index()]);
+                    restrain(&mut bounds, i, j, l, u, 2);
+                }
+            }
+        }
+        for (edge, a, b) in &edges {
+            let (edge, a, b) = (*edge, *a, *b);
+            let bond = &graph[edge];
+            let planar = bond.order == BondOrder::Aromatic
+                || (bond.order == BondOrder::Double && ring_info.is_bond_in_ring(edge.into()));
+            let ring = ring_info
+                .bond_rings(edge.into())
+                .iter()
+                .map(|ring| &ring_info.rings()[*ring])
+                .min_by_key(|ring| ring.atoms.len());
+            for x in graph.neighbors(a).filter(|x| *x != b) {
+                for y in graph.neighbors(b).filter(|y| *y != a && *y != x) {
+                    let (first, middle, last) =
+                        (bond_length(x, a), bond_length(a, b), bond_length(b, y));
+                    let (first_angle, last_angle) = (angle(a, x, b), angle(b, a, y));
+                    let torsion = |dihedral| {
+                        fourth_side(first, middle, last, first_angle, last_angle, dihedral)
+                    };
+                    let (cis, trans) = (torsion(0.0), torsion(PI));
+                    let inside = |atom: NodeIndex| {
+                        ring.is_some_and(|ring| ring.atoms.contains(&atom.into()))
+                    };
+                    let (l, u) = match (&bond.stereo, ring) {
+                        (Some(stereo), _) => {
+                            let mut config = stereo.config;
+                            if AtomIdx::from(x) != stereo.start_neighbor {
+                                config = config.inverted();
+                            }
+                            if AtomIdx::from(y) != stereo.end_neighbor {
+                                config = config.inverted();
+                            }
+                            let distance = match config {
+                                DoubleBondConfig::Cis => cis,
+                                DoubleBondConfig::Trans => trans,
+                            };
+                            (distance - 0.05, distance + 0.05)
+                        }
+                        // the same side of the ring bond, or both outside
+                        (None, Some(_)) if planar && inside(x) == inside(y) => {
+                            (cis - 0.05, cis + 0.05)
+                        }
+                        (None, Some(_)) if planar => (trans - 0.05, trans + 0.05),
+                        // small rings are almost flat
+                        (None, Some(ring)) if ring.atoms.len() <= 5 && inside(x) && inside(y) => {
+                            (cis - 0.05, cis + 0.25)
+                        }
+                        _ => (cis, trans),
+                    };
+                    let (i, j) = (local[x.index()], local[y.index()]);
+                    restrain(&mut bounds, i, j, l, u, 3);
+                }
+            }
+        }
+        bounds
+    }
+
+    fn set(&mut self, i: usize, j: usize, lower: f64, upper: f64) {
+        let n = self.n;
+        self.lower[i * n + j] = lower;
+        self.lower[j * n + i] = lower;
+        self.upper[i * n + j] = upper;
+        self.upper[j * n + i] = upper;
+    }
+
+    /// Triangle smoothing: the bounds that follow from the bounds through a third atom.
+    fn smooth(&mut self) {
+        let n = self.n;
+        for k in 0..n {
+            for i in 0..n {
+                for j in i + 1..n {
+                    let (ik, kj, jk) = (i * n + k, k * n + j, j * n + k);
+                    let upper = self.upper[i * n + j].min(self.upper[ik] + self.upper[kj]);
+                    let lower = self.lower[i * n + j]
+                        .max(self.lower[ik] - self.upper[kj])
+                        .max(self.lower[jk] - self.upper[ik]);
+                    self.set(i, j, lower.min(upper), upper);
+                }
+            }
+        }
+    }
+
+    /// Coordinates from random distances within the bounds: the largest eigenvectors of
+    /// the metric matrix, the scalar products of the atoms' positions around their center.
+    fn random_coords(&self, rng: &mut SplitMix64) -> Vec<Point3> {
+        let n = self.n;
+        let mut squared = vec![0.0; n * n];
+        for i in 0..n {
+            for j in i + 1..n {
+                let (l, u) = (self.lower[i * n + j], self.upper[i * n + j]);
+                let d = l + (u - l) * rng.fraction();
+                squared[i * n + j] = d * d;
+                squared[j * n + i] = d * d;
+            }
+        }
+        let total: f64 = squared.iter().sum::<f64>() / 2.0;
+        let to_center: Vec<f64> = (0..n)
+            .map(|i| {
+                squared[i * n..(i + 1) * n].iter().sum::<f64>() / n as f64 - total / (n * n) as f64
+            })
+            .collect();
+        let mut metric = vec![0.0; n * n];
+        for i in 0..n {
+            for j in 0..n {
+                metric[i * n + j] = (to_center[i] + to_center[j] - squared[i * n + j]) / 2.0;
+            }
+        }
+
+        let mut coords = vec![Point3::default(); n];
+        for dimension in 0..3 {
+            let (value, vector) = largest_eigenvector(&mut metric, n, rng);
+            for (i, p) in coords.iter_mut().enumerate() {
+                // flat embeddings get a little depth, to tell mirror images apart
+                let c = if value > 1e-6 {
+                    libm::sqrt(value) * vector[i]
+                } else {
+                    rng.fraction() - 0.5
+                };
+                match dimension {
+                    0 => p.x = c,
+                    1 => p.y = c,
+                    _ => p.z = c,
+                }
+            }
+        }
+        coords
+    }
+
+    /// The atoms, lower and upper bound of each pair, only the lower bound for the pairs
+    /// that don't pass the filter.
+    fn pairs(&self, filter: impl Fn(bool) -> bool) -> Vec<(usize, usize, f64, f64)> {
+        let n = self.n;
+        let mut pairs = vec![];
+        for i in 0..n {
+            for j in i + 1..n {
+                let index = i * n + j;
+                let upper = if filter(self.bounded[index]) {
+                    self.upper[index]
+                } else {
+                    MAX_DISTANCE
+                };
+                pairs.push((i, j, self.lower[index], upper));
+            }
+        }
+        pairs
+    }
+}
+
+/// The bond angle of an atom from its hybridization: linear with a triple bond or two
+/// double bonds, trigonal with a double or aromatic bond, else tetrahedral.
+fn ideal_angle(mol: &Mol, atom: NodeIndex) -> f64 {
+    let graph = &mol.graph;
+    let orders: Vec<BondOrder> = graph.edges(atom).map(|edge| edge.weight().order).collect();
+    let doubles = orders.iter().filter(|o| **o == BondOrder::Double).count();
+    if orders.contains(&BondOrder::Triple) || doubles >= 2 {
+        PI
+    } else if doubles == 1 || graph[atom].aromatic {
+        2.0 * PI / 3.0
+    } else {
+        libm::acos(-1.0 / 3.0)
+    }
+}
+
+/// Side of a triangle opposite to the angle between the sides `a` and `b`.
+fn third_side(a: f64, b: f64, angle: f64) -> f64 {
+    libm::sqrt(a * a + b * b - 2.0 * a * b * libm::cos(angle))
+}
+
+/// Distance between the ends of three bonds with the given angles and dihedral angle.
+fn fourth_side(first: f64, middle: f64, last: f64, a: f64, b: f64, dihedral: f64) -> f64 {
+    let start = Point3::new(first * libm::cos(a), first * libm::sin(a), 0.0);
+    let end = Point3::new(
+        middle - last * libm::cos(b),
+        last * libm::sin(b) * libm::cos(dihedral),
+        last * libm::sin(b) * libm::sin(dihedral),
+    );
+    start.distance(end)
+}
+
+/// The largest eigenvalue of the symmetric matrix and its unit eigenvector, by power
+/// iteration, removed from the matrix for the next one.
+fn largest_eigenvector(matrix: &mut [f64], n: usize, rng: &mut SplitMix64) -> (f64, Vec<f64>) {
+    // shifted to make all the eigenvalues positive, so that the largest one is found
+    let shift = (0..n)
+        .map(|i| {
+            matrix[i * n..(i + 1) * n]
+                .iter()
+                .map(|v| v.abs())
+                .sum::<f64>()
+        })
+        .fold(0.0, f64::max);
+    let mut vector: Vec<f64> = (0..n).map(|_| rng.fraction() - 0.5).collect();
+    let mut value = 0.0;
+    for _ in 0..1000 {
+        let mut next: Vec<f64> = (0..n)
+            .map(|i| {
+                let row = &matrix[i * n..(i + 1) * n];
+                row.iter().zip(&vector).map(|(m, v)| m * v).sum::<f64>() + shift * vector[i]
+            })
+            .collect();
+        let norm = libm::sqrt(next.iter().map(|v| v * v).sum::<f64>());
+        if norm < 1e-12 {
+            break;
+        }
+        for v in &mut next {
+            *v /= norm;
+        }
+        let change: f64 = next.iter().zip(&vector).map(|(a, b)| (a - b).abs()).sum();
+        vector = next;
+        value = norm - shift;
+        if change < 1e-9 {
+            break;
+        }
+    }
+    for i in 0..n {
+        for j in 0..n {
+            matrix[i * n + j] -= value * vector[i] * vector[j];
+        }
+    }
+    (value, vector)
+}
+
+/// Four atoms of the component whose signed volume should have the sign: the neighbors of
+/// stereocenters (the center in place of an implicit hydrogen), and in a plane, sp2 atoms
+/// with their three neighbors and the neighbors on both sides of double and aromatic bonds.
+fn volumes(mol: &Mol, atoms: &[usize]) -> Vec<([usize; 4], f64)> {
+    let graph = &mol.graph;
+    let local = |atom: usize| atoms.iter().position(|a| *a == atom);
+    let mut volumes = vec![];
+    for edge in graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(edge).unwrap();
+        let order = graph[edge].order;
+        if !matches!(order, BondOrder::Double | BondOrder::Aromatic)
+            || ideal_angle(mol, a) == PI
+            || ideal_angle(mol, b) == PI
+        {
+            continue;
+        }
+        let (Some(i), Some(j)) = (local(a.index()), local(b.index())) else {
+            continue;
+        };
+        for x in graph.neighbors(a).filter(|x| *x != b) {
+            for y in graph.neighbors(b).filter(|y| *y != a && *y != x) {
+                // unwrap: the neighbors are in the component
+                let (x, y) = (local(x.index()).unwrap(), local(y.index()).unwrap());
+                volumes.push(([x, i, j, y], 0.0));
+            }
+        }
+    }
+    for (i, atom) in atoms.iter().enumerate() {
+        let node = NodeIndex::new(*atom);
+        let neighbors: Vec<usize> = graph.neighbors(node).map(|n| n.index()).collect();
+        if let Some(chirality) = graph[node].chirality {
+            let chiral: Option<Vec<usize>> = graph[node]
+                .chiral_neighbors
+                .iter()
+                .map(|n| local(n.index()))
+                .collect();
+            // negative: seen from the first neighbor, the others are anticlockwise
+            let sign = match chirality {
+                Chirality::Anticlockwise => -1.0,
+                Chirality::Clockwise => 1.0,
+            };
+            match chiral.as_deref() {
+                Some(&[a, b, c, d]) => volumes.push(([a, b, c, d], sign)),
+                Some(&[b, c, d]) => volumes.push(([i, b, c, d], sign)),
+                _ => {}
+            }
+        } else if neighbors.len() == 3 && ideal_angle(mol, node) == 2.0 * PI / 3.0 {
+            let local: Option<Vec<usize>> = neighbors.iter().map(|n| local(*n)).collect();
+            if let Some(&[a, b, c]) = local.as_deref() {
+                volumes.push(([i, a, b, c], 0.0));
+            }
+        }
+    }
+    volumes
+}
+
+/// Determinant of (b - a, c - a, d - a).
+fn signed_volume(coords: &[Point3], [a, b, c, d]: &[usize; 4]) -> f64 {
+    let a = coords[*a];
+    (coords[*b] - a).dot((coords[*c] - a).cross(coords[*d] - a))
+}
+
+/// What's minimized: distances out of their bounds, and signed volumes below the lowest
+/// volume for stereocenters, or away from 0 for planar atoms.
+struct Terms {
+    distances: Vec<(usize, usize, f64, f64)>,
+    volumes: Vec<([usize; 4], f64)>,
+}
+
+impl Terms {
+    fn energy(&self, coords: &[Point3], gradient: &mut [Point3]) -> f64 {
+        gradient.fill(Point3::default());
+        let mut energy = 0.0;
+        for (i, j, lower, upper) in &self.distances {
+            let difference = coords[*i] - coords[*j];
+            let distance = difference.norm();
+            let violation = if distance < *lower {
+                distance - lower
+            } else if distance > *upper {
+                distance - upper
+            } else {
+                continue;
+            };
+            energy += violation * violation;
+            let force = difference * (2.0 * violation / distance.max(1e-6));
+            gradient[*i] = gradient[*i] + force;
+            gradient[*j] = gradient[*j] - force;
+        }
+        for (points, sign) in &self.volumes {
+            let volume = signed_volume(coords, points);
+            let violation = if *sign == 0.0 {
+                volume
+            } else if sign * volume < MIN_VOLUME {
+                volume - sign * MIN_VOLUME
+            } else {
+                continue;
+            };
+            energy += violation * violation;
+            let [a, b, c, d] = points.map(|point| coords[point]);
+            let (ab, ac, ad) = (b - a, c - a, d - a);
+            let derivatives = [ac.cross(ad), ad.cross(ab), ab.cross(ac)];
+            for (point, derivative) in points[1..].iter().zip(derivatives) {
+                gradient[*point] = gradient[*point] + derivative * (2.0 * violation);
+                gradient[points[0]] = gradient[points[0]] - derivative * (2.0 * violation);
+            }
+        }
+        energy
+    }
+
+    /// Steepest descent, with a step that grows while the energy goes down.
+    fn minimize(&self, coords: &mut [Point3], iterations: usize) {
+        let mut gradient = vec![Point3::default(); coords.len()];
+        let mut next_gradient = gradient.clone();
+        let mut energy = self.energy(coords, &mut gradient);
+        let mut step = 0.01;
+        for _ in 0..iterations {
+            if energy < 1e-8 || step < 1e-10 {
+                break;
+            }
+            let next: Vec<Point3> = coords
+                .iter()
+                .zip(&gradient)
+                .map(|(p, g)| *p - *g * step)
+                .collect();
+            let next_energy = self.energy(&next, &mut next_gradient);
+            if next_energy < energy {
+                coords.copy_from_slice(&next);
+                core::mem::swap(&mut gradient, &mut next_gradient);
+                energy = next_energy;
+                step *= 1.2;
+            } else {
+                step /= 2.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::f64::consts::PI;
+
+    use petgraph::graph::NodeIndex;
+
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, Mol};
+
+    use super::{signed_volume, Conformer, EmbedOptions, Point3};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn embed(smiles: &str) -> (Mol, Conformer) {
+        let mol = mol(smiles);
+        let conformer = mol.embed_3d(&EmbedOptions::default());
+        (mol, conformer)
+    }
+
+    fn distance(conformer: &Conformer, a: usize, b: usize) -> f64 {
+        let position = |atom| conformer.position(AtomIdx::new(atom)).unwrap();
+        position(a).distance(position(b))
+    }
+
+    fn angle(conformer: &Conformer, a: usize, center: usize, b: usize) -> f64 {
+        let (ca, cb, ab) = (
+            distance(conformer, center, a),
+            distance(conformer, center, b),
+            distance(conformer, a, b),
+        );
+        ((ca * ca + cb * cb - ab * ab) / (2.0 * ca * cb)).acos()
+    }
+
+    fn assert_bond_lengths(mol: &Mol, conformer: &Conformer) {
+        for bond in mol.graph.edge_indices() {
+            let (a, b) = mol.graph.edge_endpoints(bond).unwrap();
+            let length = distance(conformer, a.index(), b.index());
+            assert!(
+                (0.9..1.7).contains(&length),
+                "{}: bond {}-{} has length {}",
+                mol.to_smiles(),
+                a.index(),
+                b.index(),
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn bond_lengths() {
+        for smiles in [
+            "CCO",
+            "CC(=O)Oc1ccccc1C(=O)O",
+            "CC#N",
+            "C1CC1",
+            "C1CCCCC1",
+            "c1ccc2ccccc2c1",
+        ] {
+            let mol = mol(smiles);
+            assert_bond_lengths(&mol, &mol.embed_3d(&EmbedOptions::default()));
+            let mol = mol.add_hs();
+            assert_bond_lengths(&mol, &mol.embed_3d(&EmbedOptions::default()));
+        }
+        let (_, conformer) = embed("CC");
+        assert!((distance(&conformer, 0, 1) - 1.52).abs() < 0.02);
+    }
+
+    #[test]
+    fn bond_angles() {
+        let (_, conformer) = embed("CCC");
+        assert!((angle(&conformer, 0, 1, 2) - 109.47f64.to_radians()).abs() < 0.05);
+        let (_, conformer) = embed("C=CC");
+        assert!((angle(&conformer, 0, 1, 2) - 2.0 * PI / 3.0).abs() < 0.05);
+        let (_, conformer) = embed("CC#CC");
+        assert!(angle(&conformer, 0, 1, 2) > 170f64.to_radians());
+    }
+
+    #[test]
+    fn planar_rings() {
+        let (mol, conformer) = embed("c1ccccc1-c1ccncc1");
+        let positions = conformer.positions();
+        for ring in mol.ring_info().rings() {
+            let atoms: Vec<usize> = ring.atoms.iter().map(|atom| atom.index()).collect();
+            for k in 3..atoms.len() {
+                let volume = signed_volume(positions, &[atoms[0], atoms[1], atoms[2], atoms[k]]);
+                assert!(volume.abs() < 0.1, "volume {}", volume);
+            }
+        }
+    }
+
+    #[test]
+    fn stereocenters() {
+        // negative volumes for anticlockwise neighbors, the center for the hydrogen
+        for (smiles, neighbors, anticlockwise) in [
+            ("F[C@](Cl)(Br)I", [0, 2, 3, 4], true),
+            ("F[C@@](Cl)(Br)I", [0, 2, 3, 4], false),
+            // the hydrogen comes first: N[C@@H](C)C is [C@] seen from it
+            ("N[C@@H](C)C(=O)O", [1, 0, 2, 3], true),
+            ("N[C@H](C)C(=O)O", [1, 0, 2, 3], false),
+            ("[C@@H](N)(C)C(=O)O", [0, 1, 2, 3], false),
+        ] {
+            let (_, conformer) = embed(smiles);
+            let volume = signed_volume(conformer.positions(), &neighbors);
+            assert_eq!(anticlockwise, volume < -0.3, "{}: {}", smiles, volume);
+        }
+        // with the hydrogens as atoms
+        let mol = mol("N[C@@H](C)C(=O)O").add_hs();
+        let conformer = mol.embed_3d(&EmbedOptions::default());
+        let neighbors = &mol.graph[NodeIndex::new(1)].chiral_neighbors;
+        let neighbors = [0, 1, 2, 3].map(|i| neighbors[i].index());
+        assert!(signed_volume(conformer.positions(), &neighbors) < -0.3);
+    }
+
+    #[test]
+    fn double_bond_stereo() {
+        let (_, trans) = embed("C/C=C/C");
+        let (_, cis) = embed("C/C=C\\C");
+        assert!(distance(&trans, 0, 3) > 3.5, "{}", distance(&trans, 0, 3));
+        assert!(distance(&cis, 0, 3) < 3.3, "{}", distance(&cis, 0, 3));
+    }
+
+    #[test]
+    fn components_apart() {
+        let (_, conformer) = embed("CCO.[Na+].c1ccccc1");
+        let positions = conformer.positions();
+        for (i, a) in positions.iter().enumerate() {
+            for b in &positions[i + 1..] {
+                assert!(a.distance(*b) > 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn seeded() {
+        let mol = mol("CC(C)CCO");
+        let options = EmbedOptions::default();
+        assert_eq!(mol.embed_3d(&options), mol.embed_3d(&options));
+        let other = EmbedOptions {
+            seed: 1,
+            ..EmbedOptions::default()
+        };
+        assert_ne!(mol.embed_3d(&options), mol.embed_3d(&other));
+        assert!(mol
+            .embed_3d(&other)
+            .positions()
+            .iter()
+            .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite()));
+    }
+
+    #[test]
+    fn write_xyz() {
+        let conformer = Conformer::new(vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.43, 0.0, 0.0),
+            Point3::new(-0.5, 0.87, -1.0),
+        ]);
+
+        assert_eq!(
+            "3\nCOCl\n\
+             C      0.000000     0.000000     0.000000\n\
+             O      1.430000     0.000000     0.000000\n\
+             Cl    -0.500000     0.870000    -1.000000\n",
+            conformer.to_xyz(&mol("COCl"))
+        );
+    }
+
+    #[test]
+    fn write_pdb() {
+        let conformer = Conformer::new(vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.5, 0.0, 0.0),
+            Point3::new(2.0, 1.2, -0.5),
+            Point3::new(-0.5, -1.0, 0.75),
+        ]);
+
+        assert_eq!(
+            "HETATM    1  C1  UNL A   1       0.000   0.000   0.000  1.00  0.00           C  \n\
+             HETATM    2  N1  UNL A   1       1.500   0.000   0.000  1.00  0.00           N1+\n\
+             HETATM    3  C2  UNL A   1       2.000   1.200  -0.500  1.00  0.00           C  \n\
+             HETATM    4 CL1  UNL A   1      -0.500  -1.000   0.750  1.00  0.00          CL  \n\
+             CONECT    1    2    4\n\
+             CONECT    2    1    3\n\
+             CONECT    3    2\n\
+             CONECT    4    1\n\
+             END\n",
+            conformer.to_pdb(&mol("C([NH2+]C)Cl"))
+        );
+    }
+
+    #[test]
+    fn write_embedded() {
+        let mol = mol("CC(=O)O").add_hs();
+        let conformer = mol.embed_3d(&EmbedOptions::default());
+
+        assert_eq!(10, conformer.to_xyz(&mol).lines().count());
+        let pdb = conformer.to_pdb(&mol);
+        assert_eq!(8, pdb.lines().filter(|l| l.starts_with("HETATM")).count());
+        assert_eq!(8, pdb.lines().filter(|l| l.starts_with("CONECT")).count());
+        assert!(pdb.lines().all(|l| l.len() <= 80));
+    }
+}
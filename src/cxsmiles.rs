@@ -0,0 +1,503 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::str::FromStr;
+
+use crate::error::{SmilesError, SmilesErrorKind};
+use crate::types::AtomIdx;
+
+/// ChemAxon extensions of a CXSMILES, written after the SMILES between `|`, e.g.
+/// `C[C@H](O)CC |a:1|`. Atoms are referred to by their position in the SMILES, which is
+/// their index in the parsed [`Mol`](crate::Mol).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MolExtensions {
+    /// Atom coordinates, `(x,y,z;...)`: one per atom, or empty.
+    pub coordinates: Vec<[f64; 3]>,
+    /// Atom labels, `$label;...$`, e.g. `R1`, or `_AP1` for an attachment point.
+    pub atom_labels: BTreeMap<AtomIdx, String>,
+    /// Radical electrons: `^1:` is 1, `^2:` to `^4:` 2 and `^5:` to `^7:` 3.
+    pub radicals: BTreeMap<AtomIdx, u8>,
+    /// Enhanced stereo groups, `a:`, `o1:` and `&1:`.
+    pub stereo_groups: Vec<StereoGroup>,
+    /// Fields that aren't read, e.g. `c:` or `Sg:`, as written. They aren't written back,
+    /// as they can refer to atoms by position.
+    pub other_fields: Vec<String>,
+}
+
+/// How the stereo centers of a [`StereoGroup`] are known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StereoGroupKind {
+    /// `a`: as written.
+    Absolute,
+    /// `o`: as written or all inverted, it isn't known which.
+    Or,
+    /// `&`: a mixture of as written and all inverted.
+    And,
+}
+
+/// Stereo centers whose configuration is known together, e.g. relative to each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StereoGroup {
+    pub kind: StereoGroupKind,
+    /// The number of the group, `1` in `o1:`, 0 for absolute groups.
+    pub number: u32,
+    pub atoms: Vec<AtomIdx>,
+}
+
+impl MolExtensions {
+    pub fn is_empty(&self) -> bool {
+        self.coordinates.is_empty()
+            && self.atom_labels.is_empty()
+            && self.radicals.is_empty()
+            && self.stereo_groups.is_empty()
+            && self.other_fields.is_empty()
+    }
+}
+
+/// Reads the extensions between the `|`s, which start at character `start` of the input,
+/// of a molecule with `atoms` atoms.
+pub(crate) fn parse(text: &str, start: usize, atoms: usize) -> Result<MolExtensions, SmilesError> {
+    let mut fields = Fields {
+        chars: text.chars().collect(),
+        index: 0,
+        start,
+        atoms,
+    };
+    let mut extensions = MolExtensions::default();
+    while fields.peek().is_some() {
+        let field_start = fields.index;
+        match (fields.peek(), fields.peek_at(1)) {
+            (Some('('), _) => {
+                fields.index += 1;
+                let text = fields.until(')')?;
+                let invalid = || fields.error_at(field_start);
+                let coordinates = text
+                    .split(';')
+                    .map(|point| {
+                        let values = point
+                            .split(',')
+                            .map(|value| match value {
+                                "" => Ok(0.0),
+                                _ => value.parse().map_err(|_| invalid()),
+                            })
+                            .collect::<Result<Vec<f64>, _>>()?;
+                        match values[..] {
+                            [x, y] => Ok([x, y, 0.0]),
+                            [x, y, z] => Ok([x, y, z]),
+                            _ => Err(invalid()),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if coordinates.len() != atoms {
+                    return Err(invalid());
+                }
+                extensions.coordinates = coordinates;
+            }
+            (Some('$'), _) if !fields.starts_with("$_AV:") => {
+                fields.index += 1;
+                let labels = fields.until('$')?;
+                let labels: Vec<&str> = labels.split(';').collect();
+                if labels.len() > atoms.max(1) {
+                    return Err(fields.error_at(field_start));
+                }
+                for (i, label) in labels.into_iter().enumerate() {
+                    if !label.is_empty() {
+                        extensions
+                            .atom_labels
+                            .insert(AtomIdx::new(i), label.to_string());
+                    }
+                }
+            }
+            (Some('^'), _) => {
+                fields.index += 1;
+                let electrons = match fields.number::<u8>()? {
+                    1 => 1,
+                    2..=4 => 2,
+                    5..=7 => 3,
+                    _ => return Err(fields.error_at(field_start + 1)),
+                };
+                fields.expect(':')?;
+                for atom in fields.atom_list()? {
+                    extensions.radicals.insert(atom, electrons);
+                }
+            }
+            (Some('a'), Some(':')) => {
+                fields.index += 2;
+                extensions.stereo_groups.push(StereoGroup {
+                    kind: StereoGroupKind::Absolute,
+                    number: 0,
+                    atoms: fields.atom_list()?,
+                });
+            }
+            (Some(kind @ ('o' | '&')), Some('0'..='9')) => {
+                fields.index += 1;
+                let number = fields.number()?;
+                fields.expect(':')?;
+                extensions.stereo_groups.push(StereoGroup {
+                    kind: if kind == 'o' {
+                        StereoGroupKind::Or
+                    } else {
+                        StereoGroupKind::And
+                    },
+                    number,
+                    atoms: fields.atom_list()?,
+                });
+            }
+            _ => {
+                fields.skip_field()?;
+                let text = fields.chars[field_start..fields.index].iter().collect();
+                extensions.other_fields.push(text);
+            }
+        }
+        if !fields.eat(',') && fields.peek().is_some() {
+            return Err(fields.error_at(fields.index));
+        }
+    }
+    Ok(extensions)
+}
+
+/// The fields of a CXSMILES extension, read character by character.
+struct Fields {
+    chars: Vec<char>,
+    index: usize,
+    // position of the first character in the input
+    start: usize,
+    atoms: usize,
+}
+
+impl Fields {
+    fn error_at(&self, index: usize) -> SmilesError {
+        SmilesError::new(SmilesErrorKind::InvalidExtension, self.start + index)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.index + offset).copied()
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        prefix
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let next = self.peek() == Some(c);
+        if next {
+            self.index += 1;
+        }
+        next
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SmilesError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error_at(self.index))
+        }
+    }
+
+    /// The characters up to `end`, which is skipped.
+    fn until(&mut self, end: char) -> Result<String, SmilesError> {
+        let length = self.chars[self.index..]
+            .iter()
+            .position(|c| *c == end)
+            .ok_or(SmilesError::expected(
+                SmilesErrorKind::UnexpectedEnd,
+                self.start + self.chars.len(),
+                "the end of the field",
+            ))?;
+        let text = self.chars[self.index..self.index + length].iter().collect();
+        self.index += length + 1;
+        Ok(text)
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, SmilesError> {
+        let start = self.index;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.index += 1;
+        }
+        let digits: String = self.chars[start..self.index].iter().collect();
+        digits.parse().map_err(|_| self.error_at(start))
+    }
+
+    /// Atom indices separated by `,`, up to the next field.
+    fn atom_list(&mut self) -> Result<Vec<AtomIdx>, SmilesError> {
+        let mut atoms = vec![];
+        loop {
+            let start = self.index;
+            let atom: usize = self.number()?;
+            if atom >= self.atoms {
+                return Err(self.error_at(start));
+            }
+            atoms.push(AtomIdx::new(atom));
+            if self.peek() == Some(',') && self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+                self.index += 1;
+            } else {
+                return Ok(atoms);
+            }
+        }
+    }
+
+    /// Skips a field that isn't read: up to the next `,` that isn't followed by a digit.
+    fn skip_field(&mut self) -> Result<(), SmilesError> {
+        let end = match self.peek() {
+            Some('$') => Some('$'),
+            Some('(') => Some(')'),
+            _ => None,
+        };
+        if let Some(end) = end {
+            self.index += 1;
+            self.until(end)?;
+            return Ok(());
+        }
+        while let Some(c) = self.peek() {
+            if c == ',' && !self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+                break;
+            }
+            self.index += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The extensions as written after a SMILES, ` |...|`, `order` being the atoms in the
+/// order they're written. Empty if there are none to write.
+pub(crate) fn write(extensions: &MolExtensions, order: &[AtomIdx]) -> String {
+    let mut positions = vec![0; order.len()];
+    for (position, atom) in order.iter().enumerate() {
+        positions[atom.index()] = position;
+    }
+    let position = |atom: &AtomIdx| positions.get(atom.index()).copied();
+    let mut fields = vec![];
+
+    if extensions.coordinates.len() == order.len() && !order.is_empty() {
+        let points: Vec<String> = order
+            .iter()
+            .map(|atom| {
+                let point = extensions.coordinates[atom.index()];
+                let values: Vec<String> = point.iter().map(|value| coordinate(*value)).collect();
+                values.join(",")
+            })
+            .collect();
+        fields.push(format!("({})", points.join(";")));
+    }
+
+    let labels: Vec<&str> = order
+        .iter()
+        .map(|atom| extensions.atom_labels.get(atom).map_or("", String::as_str))
+        .collect();
+    if labels.iter().any(|label| !label.is_empty()) {
+        fields.push(format!("${}$", labels.join(";")));
+    }
+
+    for (electrons, code) in [(1, 1), (2, 2), (3, 5)] {
+        let atoms: Vec<usize> = extensions
+            .radicals
+            .iter()
+            .filter(|(_, count)| **count == electrons)
+            .filter_map(|(atom, _)| position(atom))
+            .collect();
+        if !atoms.is_empty() {
+            fields.push(format!("^{}:{}", code, atom_list(atoms)));
+        }
+    }
+
+    for group in &extensions.stereo_groups {
+        let atoms: Vec<usize> = group.atoms.iter().filter_map(position).collect();
+        if atoms.is_empty() {
+            continue;
+        }
+        let prefix = match group.kind {
+            StereoGroupKind::Absolute => "a".to_string(),
+            StereoGroupKind::Or => format!("o{}", group.number),
+            StereoGroupKind::And => format!("&{}", group.number),
+        };
+        fields.push(format!("{}:{}", prefix, atom_list(atoms)));
+    }
+
+    if fields.is_empty() {
+        String::new()
+    } else {
+        format!(" |{}|", fields.join(","))
+    }
+}
+
+fn atom_list(mut atoms: Vec<usize>) -> String {
+    atoms.sort_unstable();
+    let atoms: Vec<String> = atoms.iter().map(usize::to_string).collect();
+    atoms.join(",")
+}
+
+/// A coordinate with up to 4 decimals, empty for 0 like ChemAxon writes it.
+fn coordinate(value: f64) -> String {
+    let formatted = format!("{:.4}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "0" | "-0" => String::new(),
+        _ => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::collections::BTreeMap;
+
+    use petgraph::graph::NodeIndex;
+
+    use crate::error::{SmilesError, SmilesErrorKind};
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, Mol};
+
+    use super::{MolExtensions, StereoGroup, StereoGroupKind};
+
+    fn extensions(cxsmiles: &str) -> MolExtensions {
+        let parser = SmilesParser::new();
+        parser.parse_cxsmiles(cxsmiles).unwrap().1
+    }
+
+    fn error(cxsmiles: &str) -> SmilesError {
+        let parser = SmilesParser::new();
+        parser.parse_cxsmiles(cxsmiles).unwrap_err()
+    }
+
+    fn atoms(indices: &[usize]) -> Vec<AtomIdx> {
+        indices.iter().map(|i| AtomIdx::new(*i)).collect()
+    }
+
+    #[test]
+    fn read_fields() {
+        assert!(extensions("CCO").is_empty());
+        assert!(extensions("CCO  ").is_empty());
+        assert_eq!(
+            vec![[0.0, 0.0, 0.0], [1.5, -0.75, 0.0]],
+            extensions("CO |(,,;1.5,-0.75,)|").coordinates
+        );
+        assert_eq!(
+            BTreeMap::from([(AtomIdx::new(0), "R1".to_string())]),
+            extensions("*CC |$R1;;$|").atom_labels
+        );
+        assert_eq!(
+            BTreeMap::from([(AtomIdx::new(0), 1), (AtomIdx::new(2), 2)]),
+            extensions("[CH3]C[CH] |^1:0,^3:2|").radicals
+        );
+        assert_eq!(
+            vec![
+                StereoGroup {
+                    kind: StereoGroupKind::Or,
+                    number: 1,
+                    atoms: atoms(&[1, 3])
+                },
+                StereoGroup {
+                    kind: StereoGroupKind::And,
+                    number: 1,
+                    atoms: atoms(&[5])
+                },
+                StereoGroup {
+                    kind: StereoGroupKind::Absolute,
+                    number: 0,
+                    atoms: atoms(&[1])
+                },
+            ],
+            extensions("C[C@H](O)[C@@H](C)[C@H](C)O |o1:1,3,&1:5,a:1|").stereo_groups
+        );
+    }
+
+    #[test]
+    fn radicals_on_atoms() {
+        let parser = SmilesParser::new();
+        let (mol, _) = parser
+            .parse_cxsmiles("CC1(C)CCCC(C)(C)N1O |^1:10|")
+            .unwrap();
+        assert_eq!(1, mol.graph[NodeIndex::new(10)].num_radical_electrons);
+        assert_eq!("CC1(C)CCCC(C)(C)N1[O]", mol.to_smiles());
+        assert_eq!(
+            "CC1(C)CCCC(C)(C)N1[O] |^1:10|",
+            mol.to_cxsmiles(&MolExtensions::default())
+        );
+
+        let (mol, _) = parser.parse_cxsmiles("C |^1:0|").unwrap();
+        assert_eq!("[CH3]", mol.to_smiles());
+        let (mut mol, _) = parser.parse_cxsmiles("CC(C)(C)C |^1:1|").unwrap();
+        assert!(mol.sanitize().is_err());
+    }
+
+    #[test]
+    fn keep_other_fields() {
+        let extensions = extensions("C1=CCCCC1 |c:0,wU:1.0,$_AV:;;;;;$,Sg:n:1::ht,a:1|");
+        assert_eq!(
+            vec!["c:0", "wU:1.0", "$_AV:;;;;;$", "Sg:n:1::ht"],
+            extensions.other_fields
+        );
+        assert_eq!(1, extensions.stereo_groups.len());
+    }
+
+    #[test]
+    fn read_errors() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::InvalidExtension, 6),
+            error("CC |a:2|")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::InvalidExtension, 4),
+            error("CC |(0,0)|")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::InvalidExtension, 7),
+            error("CC |a:1b|")
+        );
+        assert_eq!(
+            SmilesError::expected(SmilesErrorKind::UnexpectedEnd, 7, "'|'"),
+            error("CC |a:1")
+        );
+        assert_eq!(
+            SmilesError::expected(SmilesErrorKind::UnexpectedCharacter('x'), 3, "'|'"),
+            error("CC x")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::UnexpectedCharacter('n'), 9),
+            error("CC |a:1| name")
+        );
+        assert_eq!(SmilesErrorKind::UnclosedRing(1), error("C1CC |a:1|").kind);
+    }
+
+    #[test]
+    fn write_fields() {
+        let parser = SmilesParser::new();
+        for cxsmiles in [
+            "C[C@H](O)C[CH2] |(1,2,;3,,;,,-1;,,;,,),$;R;;;$,^1:4,a:1|",
+            "C[C@H](O)[C@@H](C)[C@H](C)O |o1:1,3,&1:5|",
+        ] {
+            let (mol, extensions) = parser.parse_cxsmiles(cxsmiles).unwrap();
+            assert_eq!(cxsmiles, mol.to_cxsmiles(&extensions));
+        }
+        assert_eq!(
+            "CC",
+            parser
+                .parse("CC")
+                .unwrap()
+                .to_cxsmiles(&MolExtensions::default())
+        );
+    }
+
+    #[test]
+    fn write_in_written_order() {
+        // C, O, C bonded 0-2-1: written as CCO
+        let mol = Mol::from_json(
+            r#"{"atoms": [{"element": "C"}, {"element": "O"}, {"element": "C"}],
+                "bonds": [{"atoms": [0, 2]}, {"atoms": [2, 1]}]}"#,
+        )
+        .unwrap();
+        let extensions = MolExtensions {
+            atom_labels: BTreeMap::from([(AtomIdx::new(1), "x".to_string())]),
+            radicals: BTreeMap::from([(AtomIdx::new(2), 1)]),
+            ..Default::default()
+        };
+        assert_eq!("CCO |$;;x$,^1:1|", mol.to_cxsmiles(&extensions));
+    }
+}
@@ -0,0 +1,588 @@
+//! Reading tables with a SMILES column: CSV files and, with the `parquet` feature, Parquet
+//! files. Each row is yielded as its molecule with the values of the other columns.
+
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::sync::Arc;
+
+#[cfg(feature = "parquet")]
+use parquet::file::reader::{ChunkReader, FileReader, SerializedFileReader};
+#[cfg(feature = "parquet")]
+use parquet::record::reader::RowIter;
+#[cfg(feature = "parquet")]
+use parquet::record::Field;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::error::DatasetError;
+use crate::smi::ErrorPolicy;
+use crate::smiles::SmilesParser;
+use crate::types::Mol;
+
+/// The molecule of a row, with the values of the other columns.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Index of the row in the file, starting at 0, not counting the header.
+    pub index: usize,
+    pub mol: Mol,
+    /// The values, in the order of [`Record::columns`].
+    pub values: Vec<String>,
+    columns: Arc<[String]>,
+}
+
+impl Record {
+    /// Names of the columns other than the SMILES column.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Value of the column, `None` if there's no such column.
+    pub fn get(&self, column: &str) -> Option<&str> {
+        let index = self.columns.iter().position(|name| name == column)?;
+        Some(&self.values[index])
+    }
+}
+
+/// The index and fields of a row.
+type Row = Result<(usize, Vec<String>), DatasetError>;
+
+/// Iterates over the rows of a table, parsing the SMILES column. Reading stops at an I/O
+/// error.
+pub struct DatasetReader {
+    rows: Box<dyn Iterator<Item = Row>>,
+    smiles_column: usize,
+    columns: Arc<[String]>,
+    parser: SmilesParser,
+    policy: ErrorPolicy,
+    errors: Vec<DatasetError>,
+    batch_size: usize,
+    parsed: VecDeque<Result<Record, DatasetError>>,
+}
+
+impl DatasetReader {
+    /// Reads CSV with a header line, fields separated by `delimiter`, e.g. `','` or
+    /// `'\t'`. Fields can be quoted with `"`, a quote in them doubled. Blank lines are
+    /// skipped. Fails if the header has no `smiles_column`.
+    pub fn csv<R: BufRead + 'static>(
+        reader: R,
+        smiles_column: &str,
+        delimiter: char,
+    ) -> Result<DatasetReader, DatasetError> {
+        let mut rows = CsvRows {
+            reader,
+            delimiter,
+            width: 0,
+            record: 0,
+            failed: false,
+        };
+        let header = match rows.read_fields() {
+            Some(header) => header?,
+            None => vec![],
+        };
+        rows.width = header.len();
+        DatasetReader::new(Box::new(rows), header, smiles_column)
+    }
+
+    /// Reads a Parquet file, converting the values of the other columns to text. Fails if
+    /// the file is invalid or has no top level `smiles_column`.
+    #[cfg(feature = "parquet")]
+    pub fn parquet<R: ChunkReader + 'static>(
+        reader: R,
+        smiles_column: &str,
+    ) -> Result<DatasetReader, DatasetError> {
+        let reader = SerializedFileReader::new(reader)?;
+        let header = reader
+            .metadata()
+            .file_metadata()
+            .schema()
+            .get_fields()
+            .iter()
+            .map(|field| field.name().to_string())
+            .collect();
+        let rows = RowIter::from_file_into(Box::new(reader)).enumerate().scan(
+            false,
+            |failed, (index, row)| {
+                if *failed {
+                    return None;
+                }
+                *failed = row.is_err();
+                Some(row.map_err(DatasetError::from).map(|row| {
+                    let fields = row
+                        .get_column_iter()
+                        .map(|(_, field)| field_text(field))
+                        .collect();
+                    (index, fields)
+                }))
+            },
+        );
+        DatasetReader::new(Box::new(rows), header, smiles_column)
+    }
+
+    fn new(
+        rows: Box<dyn Iterator<Item = Row>>,
+        mut header: Vec<String>,
+        smiles_column: &str,
+    ) -> Result<DatasetReader, DatasetError> {
+        let Some(index) = header.iter().position(|name| name == smiles_column) else {
+            return Err(DatasetError::MissingColumn(smiles_column.to_string()));
+        };
+        header.remove(index);
+        Ok(DatasetReader {
+            rows,
+            smiles_column: index,
+            columns: header.into(),
+            parser: SmilesParser::new(),
+            policy: ErrorPolicy::default(),
+            errors: vec![],
+            batch_size: 1,
+            parsed: VecDeque::new(),
+        })
+    }
+
+    pub fn with_parser(mut self, parser: SmilesParser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Reads rows in batches of `batch_size` and parses each batch in parallel, on rayon's
+    /// current thread pool (see `rayon::ThreadPool::install`). Records stay in file order.
+    /// The default, 1, parses one row at a time on the calling thread.
+    #[cfg(feature = "parallel")]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Names of the columns other than the SMILES column.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The errors kept with [`ErrorPolicy::Collect`], in file order.
+    pub fn errors(&self) -> &[DatasetError] {
+        &self.errors
+    }
+
+    /// Reads and parses the next batch of rows.
+    fn parse_batch(&mut self) {
+        let rows: Vec<Row> = self.rows.by_ref().take(self.batch_size).collect();
+        let (parser, columns, smiles_column) = (&self.parser, &self.columns, self.smiles_column);
+        #[cfg(feature = "parallel")]
+        if rows.len() > 1 {
+            let records: Vec<_> = rows
+                .into_par_iter()
+                .map(|row| parse_row(parser, columns, smiles_column, row))
+                .collect();
+            self.parsed.extend(records);
+            return;
+        }
+        self.parsed.extend(
+            rows.into_iter()
+                .map(|row| parse_row(parser, columns, smiles_column, row)),
+        );
+    }
+}
+
+impl Iterator for DatasetReader {
+    type Item = Result<Record, DatasetError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.parsed.is_empty() {
+                self.parse_batch();
+            }
+            let error = match self.parsed.pop_front()? {
+                Ok(record) => return Some(Ok(record)),
+                Err(error) => error,
+            };
+            // reading stops there, whatever the policy
+            #[cfg(feature = "parquet")]
+            if matches!(error, DatasetError::Parquet(_)) {
+                return Some(Err(error));
+            }
+            if matches!(error, DatasetError::Io(_)) {
+                return Some(Err(error));
+            }
+            match self.policy {
+                ErrorPolicy::Yield => return Some(Err(error)),
+                ErrorPolicy::Skip => {}
+                ErrorPolicy::Collect => self.errors.push(error),
+            }
+        }
+    }
+}
+
+/// The record of the row, parsing the field of the SMILES column.
+fn parse_row(
+    parser: &SmilesParser,
+    columns: &Arc<[String]>,
+    smiles_column: usize,
+    row: Row,
+) -> Result<Record, DatasetError> {
+    let (index, mut values) = row?;
+    let smiles = values.remove(smiles_column);
+    let mol = parser
+        .parse(smiles.trim())
+        .map_err(|error| DatasetError::Smiles {
+            record: index,
+            error,
+        })?;
+    Ok(Record {
+        index,
+        mol,
+        values,
+        columns: columns.clone(),
+    })
+}
+
+/// The rows of a CSV file after the header.
+struct CsvRows<R> {
+    reader: R,
+    delimiter: char,
+    /// Number of fields of the header.
+    width: usize,
+    record: usize,
+    failed: bool,
+}
+
+impl<R: BufRead> CsvRows<R> {
+    /// The fields of the next record, which spans several lines if a quoted field has line
+    /// breaks. `None` at the end of the file.
+    fn read_fields(&mut self) -> Option<Result<Vec<String>, DatasetError>> {
+        let mut fields = vec![];
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut line = String::new();
+        while !self.failed {
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) if quoted => {
+                    self.failed = true;
+                    return Some(Err(DatasetError::UnclosedQuote {
+                        record: self.record,
+                    }));
+                }
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(DatasetError::Io(error)));
+                }
+            }
+            if !quoted && line.trim_end_matches(['\r', '\n']).is_empty() {
+                continue;
+            }
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                if quoted {
+                    if c != '"' {
+                        field.push(c);
+                    } else if chars.peek() == Some(&'"') {
+                        chars.next();
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                } else if c == '"' {
+                    quoted = true;
+                } else if c == self.delimiter {
+                    fields.push(std::mem::take(&mut field));
+                } else if c != '\r' && c != '\n' {
+                    field.push(c);
+                }
+            }
+            if !quoted {
+                fields.push(field);
+                return Some(Ok(fields));
+            }
+        }
+        None
+    }
+}
+
+impl<R: BufRead> Iterator for CsvRows<R> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        let fields = self.read_fields()?;
+        let record = self.record;
+        self.record += 1;
+        let fields = match fields {
+            Ok(fields) => fields,
+            Err(error) => return Some(Err(error)),
+        };
+        if fields.len() != self.width {
+            return Some(Err(DatasetError::FieldCount {
+                record,
+                expected: self.width,
+                found: fields.len(),
+            }));
+        }
+        Some(Ok((record, fields)))
+    }
+}
+
+/// The value as text: strings as they are, nulls empty.
+#[cfg(feature = "parquet")]
+fn field_text(field: &Field) -> String {
+    match field {
+        Field::Null => String::new(),
+        Field::Str(value) => value.clone(),
+        Field::Bytes(value) => String::from_utf8_lossy(value.data()).into_owned(),
+        field => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{DatasetError, SmilesErrorKind};
+    use crate::smi::ErrorPolicy;
+
+    use super::{DatasetReader, Record};
+
+    const INPUT: &str = "id,smiles,name\n\
+        1,CCO,ethanol\n\
+        \n\
+        2,c1ccccc1,\"benzene, the ring\"\n\
+        3,C1CC,bad\n\
+        4,N,\"ammonia \"\"NH3\"\"\nwith a line break\"\n";
+
+    fn reader(input: &'static str) -> DatasetReader {
+        DatasetReader::csv(input.as_bytes(), "smiles", ',').unwrap()
+    }
+
+    fn indices(records: &[Record]) -> Vec<usize> {
+        records.iter().map(|record| record.index).collect()
+    }
+
+    #[test]
+    fn read_csv() {
+        let reader = reader(INPUT);
+        assert_eq!(&["id", "name"], reader.columns());
+
+        let records: Vec<_> = reader.collect();
+        assert_eq!(4, records.len());
+        let record = records[0].as_ref().unwrap();
+        assert_eq!("CCO", record.mol.to_smiles());
+        assert_eq!(vec!["1", "ethanol"], record.values);
+        assert_eq!(Some("ethanol"), record.get("name"));
+        assert_eq!(None, record.get("smiles"));
+        let record = records[1].as_ref().unwrap();
+        assert_eq!(1, record.index);
+        assert_eq!(Some("benzene, the ring"), record.get("name"));
+        match &records[2] {
+            Err(DatasetError::Smiles { record, error }) => {
+                assert_eq!(2, *record);
+                assert_eq!(SmilesErrorKind::UnclosedRing(1), error.kind);
+            }
+            other => panic!("expected an error, got {:?}", other),
+        }
+        let record = records[3].as_ref().unwrap();
+        assert_eq!(
+            Some("ammonia \"NH3\"\nwith a line break"),
+            record.get("name")
+        );
+    }
+
+    #[test]
+    fn tab_separated() {
+        let records: Vec<Record> = DatasetReader::csv(
+            "smiles\tid\r\nCC\t1\r\nCN\t2\r\n".as_bytes(),
+            "smiles",
+            '\t',
+        )
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+        assert_eq!(2, records.len());
+        assert_eq!(Some("2"), records[1].get("id"));
+        assert_eq!("CN", records[1].mol.to_smiles());
+    }
+
+    #[test]
+    fn missing_column() {
+        let error = DatasetReader::csv("id,name\n".as_bytes(), "smiles", ',').err();
+        assert!(matches!(error, Some(DatasetError::MissingColumn(column)) if column == "smiles"));
+        let error = DatasetReader::csv("".as_bytes(), "smiles", ',').err();
+        assert!(matches!(error, Some(DatasetError::MissingColumn(_))));
+    }
+
+    #[test]
+    fn malformed_records() {
+        let records: Vec<_> = reader("smiles,id\nC,1\nCC\nCCC,3\nCCCC,\"4\n").collect();
+
+        assert_eq!(4, records.len());
+        assert!(matches!(
+            records[1],
+            Err(DatasetError::FieldCount {
+                record: 1,
+                expected: 2,
+                found: 1
+            })
+        ));
+        assert_eq!(2, records[2].as_ref().unwrap().index);
+        assert!(matches!(
+            records[3],
+            Err(DatasetError::UnclosedQuote { record: 3 })
+        ));
+    }
+
+    #[test]
+    fn error_policies() {
+        let skipped: Vec<Record> = reader(INPUT)
+            .with_policy(ErrorPolicy::Skip)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![0, 1, 3], indices(&skipped));
+
+        let mut reader = reader(INPUT).with_policy(ErrorPolicy::Collect);
+        let collected: Vec<Record> = reader.by_ref().map(Result::unwrap).collect();
+        assert_eq!(3, collected.len());
+        assert_eq!(1, reader.errors().len());
+        assert_eq!(
+            "record 2: unclosed ring 1 at position 1",
+            reader.errors()[0].to_string()
+        );
+    }
+
+    #[test]
+    fn io_errors_are_yielded_whatever_the_policy() {
+        use std::io::{self, BufReader, Read};
+
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk on fire"))
+            }
+        }
+
+        for policy in [ErrorPolicy::Skip, ErrorPolicy::Collect] {
+            let input = "smiles\nCCO\nC1CC\n".as_bytes().chain(FailingReader);
+            let mut reader = DatasetReader::csv(BufReader::new(input), "smiles", ',')
+                .unwrap()
+                .with_policy(policy);
+            let records: Vec<_> = reader.by_ref().collect();
+
+            assert_eq!(2, records.len(), "{:?}", policy);
+            assert!(records[0].is_ok());
+            assert!(matches!(records[1], Err(DatasetError::Io(_))));
+            assert!(reader
+                .errors()
+                .iter()
+                .all(|error| !matches!(error, DatasetError::Io(_))));
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_batches() {
+        let input = "smiles\nC\nCC\nC1C\nCCC\nCCCC\nCCCCC\nCCCCCC\n";
+        let sequential: Vec<_> = reader(input)
+            .map(|record| record.map(|record| record.mol.to_smiles()).ok())
+            .collect();
+        let parallel: Vec<_> = reader(input)
+            .with_batch_size(3)
+            .map(|record| record.map(|record| record.mol.to_smiles()).ok())
+            .collect();
+
+        assert_eq!(7, parallel.len());
+        assert_eq!(None, parallel[2]);
+        assert_eq!(sequential, parallel);
+    }
+
+    /// Writes a Parquet file of 3 records, an id and a SMILES column, and reads it back.
+    #[cfg(feature = "parquet")]
+    fn parquet_records(
+        compression: parquet::basic::Compression,
+    ) -> (Vec<String>, Vec<Result<Record, DatasetError>>) {
+        use std::fs::File;
+        use std::sync::Arc;
+
+        use parquet::data_type::{ByteArray, ByteArrayType, Int32Type};
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        let path = std::env::temp_dir().join(format!(
+            "smiles-{}-{}.parquet",
+            std::process::id(),
+            compression
+        ));
+        let schema = parse_message_type(
+            "message schema { REQUIRED INT32 id; REQUIRED BYTE_ARRAY smiles (UTF8); }",
+        )
+        .unwrap();
+        let properties = WriterProperties::builder()
+            .set_compression(compression)
+            .build();
+        let mut writer = SerializedFileWriter::new(
+            File::create(&path).unwrap(),
+            Arc::new(schema),
+            Arc::new(properties),
+        )
+        .unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<Int32Type>()
+            .write_batch(&[1, 2, 3], None, None)
+            .unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        let smiles = ["CCO", "C1CC", "c1ccccc1"].map(ByteArray::from);
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&smiles, None, None)
+            .unwrap();
+        column.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+
+        let reader = DatasetReader::parquet(File::open(&path).unwrap(), "smiles").unwrap();
+        let columns = reader.columns().to_vec();
+        let records = reader.collect();
+        std::fs::remove_file(&path).unwrap();
+        (columns, records)
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn read_parquet() {
+        let (columns, records) = parquet_records(parquet::basic::Compression::UNCOMPRESSED);
+
+        assert_eq!(&["id"], &columns[..]);
+        assert_eq!(3, records.len());
+        assert_eq!(Some("1"), records[0].as_ref().unwrap().get("id"));
+        assert_eq!("CCO", records[0].as_ref().unwrap().mol.to_smiles());
+        assert!(matches!(
+            records[1],
+            Err(DatasetError::Smiles { record: 1, .. })
+        ));
+        assert_eq!(6, records[2].as_ref().unwrap().mol.num_atoms());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn read_compressed_parquet() {
+        use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+
+        // snappy is the default of pandas and pyarrow
+        for compression in [
+            Compression::SNAPPY,
+            Compression::ZSTD(ZstdLevel::default()),
+            Compression::GZIP(GzipLevel::default()),
+        ] {
+            let (_, records) = parquet_records(compression);
+            assert_eq!(3, records.len(), "{}", compression);
+            assert_eq!("CCO", records[0].as_ref().unwrap().mol.to_smiles());
+            assert_eq!(6, records[2].as_ref().unwrap().mol.num_atoms());
+        }
+    }
+}
@@ -0,0 +1,551 @@
+//! Physicochemical descriptors: counts, polar surface area and lipophilicity, and
+//! topological indices.
+//! Hydrogens are counted whether they're implicit or atoms of the graph, and aromaticity
+//! is perceived first so that Kekulé and aromatic forms give the same values.
+
+use alloc::vec::Vec;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::canon;
+use crate::formula;
+use crate::topology::distance_matrix;
+use crate::types::{Atom, BondOrder, Mol};
+
+/// All the descriptors of a molecule, see the functions of the same name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Descriptors {
+    pub molecular_weight: f64,
+    pub heavy_atom_count: usize,
+    pub ring_count: usize,
+    pub rotatable_bond_count: usize,
+    pub h_bond_donor_count: usize,
+    pub h_bond_acceptor_count: usize,
+    pub tpsa: f64,
+    pub clogp: f64,
+}
+
+impl Descriptors {
+    pub fn new(mol: &Mol) -> Descriptors {
+        let perceived = canon::perceived(mol);
+        Descriptors {
+            molecular_weight: formula::molecular_weight(mol),
+            heavy_atom_count: heavy_atom_count(mol),
+            ring_count: ring_count(mol),
+            rotatable_bond_count: rotatable_bond_count(mol),
+            h_bond_donor_count: h_bond_donor_count(mol),
+            h_bond_acceptor_count: h_bond_acceptor_count(mol),
+            tpsa: perceived_tpsa(&perceived),
+            clogp: perceived_clogp(&perceived),
+        }
+    }
+}
+
+/// Atoms other than hydrogen.
+pub fn heavy_atom_count(mol: &Mol) -> usize {
+    mol.graph
+        .node_weights()
+        .filter(|atom| atom.number != 1)
+        .count()
+}
+
+/// Rings of the SSSR.
+pub fn ring_count(mol: &Mol) -> usize {
+    mol.ring_info().num_rings()
+}
+
+/// Single bonds outside rings between non-terminal atoms, excluding bonds to triple
+/// bonded atoms and amide C-N bonds.
+pub fn rotatable_bond_count(mol: &Mol) -> usize {
+    let ring_info = mol.ring_info();
+    let rotatable_end = |node: NodeIndex| {
+        heavy_neighbors(mol, node).count() > 1
+            && !mol
+                .graph
+                .edges(node)
+                .any(|edge| edge.weight().order == BondOrder::Triple)
+    };
+    mol.graph
+        .edge_references()
+        .filter(|edge| {
+            edge.weight().order == BondOrder::Single
+                && !ring_info.is_bond_in_ring(edge.id().into())
+                && rotatable_end(edge.source())
+                && rotatable_end(edge.target())
+                && !is_amide_bond(mol, edge.source(), edge.target())
+                && !is_amide_bond(mol, edge.target(), edge.source())
+        })
+        .count()
+}
+
+/// Lipinski's donors: the hydrogens on nitrogens and oxygens.
+pub fn h_bond_donor_count(mol: &Mol) -> usize {
+    mol.graph
+        .node_indices()
+        .filter(|node| matches!(mol.graph[*node].number, 7 | 8))
+        .map(|node| hydrogen_count(mol, node) as usize)
+        .sum()
+}
+
+/// Lipinski's acceptors: the nitrogens and oxygens.
+pub fn h_bond_acceptor_count(mol: &Mol) -> usize {
+    mol.graph
+        .node_weights()
+        .filter(|atom| matches!(atom.number, 7 | 8))
+        .count()
+}
+
+/// Topological polar surface area in Å², from the nitrogen and oxygen contributions of
+/// Ertl et al. (2000).
+pub fn tpsa(mol: &Mol) -> f64 {
+    perceived_tpsa(&canon::perceived(mol))
+}
+
+fn perceived_tpsa(mol: &Mol) -> f64 {
+    let ring_info = mol.ring_info();
+    mol.graph
+        .node_indices()
+        .map(|node| {
+            let atom = &mol.graph[node];
+            if !matches!(atom.number, 7 | 8) {
+                return 0.0;
+            }
+            let bonds = BondCounts::new(mol, node);
+            let in_3_ring = ring_info.atom_ring_sizes(node.into()).contains(&3);
+            let hs = hydrogen_count(mol, node);
+            let contribution = match atom.number {
+                7 => nitrogen_psa(&bonds, hs, atom.charge, in_3_ring),
+                _ => oxygen_psa(&bonds, hs, atom.charge, in_3_ring),
+            };
+            contribution.unwrap_or_else(|| {
+                // atom types missing from the table
+                let (base, per_neighbor) = if atom.number == 7 {
+                    (30.5, 8.2)
+                } else {
+                    (28.5, 8.6)
+                };
+                base - per_neighbor * bonds.neighbors as f64 + 1.5 * hs as f64
+            })
+        })
+        .sum()
+}
+
+/// Estimated octanol/water partition coefficient (log P), summing Crippen-style atom
+/// contributions (Wildman and Crippen, 1999) over a reduced set of atom types.
+pub fn clogp(mol: &Mol) -> f64 {
+    perceived_clogp(&canon::perceived(mol))
+}
+
+fn perceived_clogp(mol: &Mol) -> f64 {
+    mol.graph
+        .node_indices()
+        .map(|node| {
+            let atom = &mol.graph[node];
+            // explicit hydrogen atoms are counted with their neighbor
+            if atom.number == 1 && heavy_neighbors(mol, node).next().is_some() {
+                return 0.0;
+            }
+            atom_logp(mol, node) + hydrogen_count(mol, node) as f64 * hydrogen_logp(mol, node)
+        })
+        .sum()
+}
+
+/// Wiener index: the sum of the distances, in bonds, between all the pairs of heavy atoms
+/// of the same fragment.
+pub fn wiener_index(mol: &Mol) -> usize {
+    let distances = heavy_atom_distances(mol);
+    distances.iter().flatten().flatten().sum::<usize>() / 2
+}
+
+/// Balaban's J index of the heavy atoms: the bonds over the rings plus one, times the sum
+/// over the bonds of 1 / √(d_i d_j), with d_i the sum of the distances from atom i to the
+/// others of its fragment. 0 without bonds.
+pub fn balaban_j(mol: &Mol) -> f64 {
+    let graph = &mol.graph;
+    let heavy = |node: NodeIndex| graph[node].number != 1;
+    let distances = heavy_atom_distances(mol);
+    let sums: Vec<usize> = distances
+        .iter()
+        .map(|row| row.iter().flatten().sum())
+        .collect();
+
+    let mut bonds = 0;
+    let mut sum = 0.0;
+    for edge in graph.edge_references() {
+        let (a, b) = (edge.source(), edge.target());
+        if !heavy(a) || !heavy(b) {
+            continue;
+        }
+        bonds += 1;
+        sum += 1.0 / libm::sqrt((sums[a.index()] * sums[b.index()]) as f64);
+    }
+    if bonds == 0 {
+        return 0.0;
+    }
+    let atoms = graph.node_indices().filter(|node| heavy(*node)).count();
+    let fragments = graph
+        .node_indices()
+        .filter(|node| heavy(*node))
+        .filter(|node| {
+            // the first atom of its fragment
+            distances[node.index()][..node.index()]
+                .iter()
+                .all(|distance| distance.is_none())
+        })
+        .count();
+    let rings = bonds + fragments - atoms;
+    bonds as f64 / (rings + 1) as f64 * sum
+}
+
+/// The distance matrix, without hydrogen atoms: `None` in their rows and columns.
+fn heavy_atom_distances(mol: &Mol) -> Vec<Vec<Option<usize>>> {
+    let mut distances = distance_matrix(mol);
+    for node in mol.graph.node_indices() {
+        if mol.graph[node].number != 1 {
+            continue;
+        }
+        distances[node.index()].fill(None);
+        for row in &mut distances {
+            row[node.index()] = None;
+        }
+    }
+    distances
+}
+
+/// Bonds of an atom to other heavy atoms, by order.
+struct BondCounts {
+    neighbors: usize,
+    single: usize,
+    double: usize,
+    triple: usize,
+    aromatic: usize,
+}
+
+impl BondCounts {
+    fn new(mol: &Mol, node: NodeIndex) -> BondCounts {
+        let mut counts = BondCounts {
+            neighbors: 0,
+            single: 0,
+            double: 0,
+            triple: 0,
+            aromatic: 0,
+        };
+        for edge in mol.graph.edges(node) {
+            let neighbor = if edge.source() == node {
+                edge.target()
+            } else {
+                edge.source()
+            };
+            if mol.graph[neighbor].number == 1 {
+                continue;
+            }
+            counts.neighbors += 1;
+            match edge.weight().order {
+                BondOrder::Single | BondOrder::Quadruple => counts.single += 1,
+                BondOrder::Double => counts.double += 1,
+                BondOrder::Triple => counts.triple += 1,
+                BondOrder::Aromatic => counts.aromatic += 1,
+            }
+        }
+        counts
+    }
+
+    fn is(&self, single: usize, double: usize, triple: usize, aromatic: usize) -> bool {
+        (self.single, self.double, self.triple, self.aromatic) == (single, double, triple, aromatic)
+    }
+}
+
+fn nitrogen_psa(bonds: &BondCounts, hs: u8, charge: i8, in_3_ring: bool) -> Option<f64> {
+    let psa = match (charge, hs) {
+        (0, 0) if bonds.is(3, 0, 0, 0) && in_3_ring => 3.01,
+        (0, 0) if bonds.is(3, 0, 0, 0) => 3.24,
+        (0, 0) if bonds.is(1, 1, 0, 0) => 12.36,
+        (0, 0) if bonds.is(0, 0, 1, 0) => 23.79,
+        (0, 0) if bonds.is(1, 2, 0, 0) => 11.68,
+        (0, 0) if bonds.is(0, 1, 1, 0) => 13.60,
+        (0, 0) if bonds.is(0, 0, 0, 2) => 12.89,
+        (0, 0) if bonds.is(0, 0, 0, 3) => 4.41,
+        (0, 0) if bonds.is(1, 0, 0, 2) => 4.93,
+        (0, 0) if bonds.is(0, 1, 0, 2) => 8.39,
+        (0, 1) if bonds.is(2, 0, 0, 0) && in_3_ring => 21.94,
+        (0, 1) if bonds.is(2, 0, 0, 0) => 12.03,
+        (0, 1) if bonds.is(0, 1, 0, 0) => 23.85,
+        (0, 1) if bonds.is(0, 0, 0, 2) => 15.79,
+        (0, 2) if bonds.is(1, 0, 0, 0) => 26.02,
+        (1, 0) if bonds.is(4, 0, 0, 0) => 0.0,
+        (1, 0) if bonds.is(2, 1, 0, 0) => 3.01,
+        (1, 0) if bonds.is(1, 0, 1, 0) => 4.36,
+        (1, 0) if bonds.is(0, 0, 0, 3) => 4.10,
+        (1, 0) if bonds.is(1, 0, 0, 2) => 3.88,
+        (1, 1) if bonds.is(3, 0, 0, 0) => 4.44,
+        (1, 1) if bonds.is(1, 1, 0, 0) => 13.97,
+        (1, 1) if bonds.is(0, 0, 0, 2) => 14.14,
+        (1, 2) if bonds.is(2, 0, 0, 0) => 16.61,
+        (1, 2) if bonds.is(0, 1, 0, 0) => 25.59,
+        (1, 3) if bonds.is(1, 0, 0, 0) => 27.64,
+        _ => return None,
+    };
+    Some(psa)
+}
+
+fn oxygen_psa(bonds: &BondCounts, hs: u8, charge: i8, in_3_ring: bool) -> Option<f64> {
+    let psa = match (charge, hs) {
+        (0, 0) if bonds.is(2, 0, 0, 0) && in_3_ring => 12.53,
+        (0, 0) if bonds.is(2, 0, 0, 0) => 9.23,
+        (0, 0) if bonds.is(0, 1, 0, 0) => 17.07,
+        (0, 0) if bonds.is(0, 0, 0, 2) => 13.14,
+        (0, 1) if bonds.is(1, 0, 0, 0) => 20.23,
+        (-1, 0) if bonds.is(1, 0, 0, 0) => 23.06,
+        _ => return None,
+    };
+    Some(psa)
+}
+
+/// Contribution of a heavy atom, without its hydrogens.
+fn atom_logp(mol: &Mol, node: NodeIndex) -> f64 {
+    let atom = &mol.graph[node];
+    let bonds = BondCounts::new(mol, node);
+    let neighbors: Vec<&Atom> = heavy_neighbors(mol, node).map(|n| &mol.graph[n]).collect();
+    let heteroatoms = neighbors.iter().filter(|n| is_heteroatom(n)).count();
+    match atom.number {
+        6 if atom.aromatic => {
+            if hydrogen_count(mol, node) > 0 {
+                0.1581
+            } else if bonds.aromatic == 3 {
+                // fused ring atom
+                0.2955
+            } else {
+                0.1360
+            }
+        }
+        6 if bonds.triple > 0 => 0.0017,
+        6 if bonds.double > 0 => {
+            let double_to_heteroatom = mol.graph.edges(node).any(|edge| {
+                let other = if edge.source() == node {
+                    edge.target()
+                } else {
+                    edge.source()
+                };
+                edge.weight().order == BondOrder::Double && is_heteroatom(&mol.graph[other])
+            });
+            if double_to_heteroatom {
+                -0.2783
+            } else {
+                0.1551
+            }
+        }
+        6 if heteroatoms == 1 && hydrogen_count(mol, node) >= 2 => -0.2035,
+        6 if heteroatoms > 0 => -0.2051,
+        6 if neighbors.iter().any(|n| n.aromatic) => 0.08452,
+        6 if hydrogen_count(mol, node) >= 2 => 0.1441,
+        6 => 0.0,
+        7 if atom.aromatic => -0.3239,
+        7 if atom.charge > 0 => -0.3239,
+        7 if bonds.triple > 0 => -0.2604,
+        7 if bonds.double > 0 => -0.4806,
+        7 => match hydrogen_count(mol, node) {
+            0 => -0.3187,
+            1 => -0.7096,
+            _ => -1.0190,
+        },
+        8 if atom.aromatic => 0.1552,
+        8 if atom.charge < 0 => -1.326,
+        8 if bonds.double > 0 => {
+            if neighbors.iter().any(|n| n.number != 6) {
+                0.4833
+            } else if neighbors.iter().any(|n| n.aromatic) {
+                0.1129
+            } else {
+                -0.1526
+            }
+        }
+        8 if hydrogen_count(mol, node) > 0 => -0.2893,
+        8 if neighbors.iter().any(|n| n.aromatic) => -0.4195,
+        8 => -0.0684,
+        9 => 0.4202,
+        15 => 0.8612,
+        16 if atom.aromatic => 0.6237,
+        16 => 0.6482,
+        17 => 0.6895,
+        35 => 0.8456,
+        53 => 0.8857,
+        1 => 0.1230,
+        _ => 0.0,
+    }
+}
+
+/// Contribution of each hydrogen on the atom.
+fn hydrogen_logp(mol: &Mol, node: NodeIndex) -> f64 {
+    match mol.graph[node].number {
+        7 => 0.2142,
+        8 if heavy_neighbors(mol, node).any(|n| is_carbonyl_carbon(mol, n)) => 0.2980,
+        8 => -0.2677,
+        _ => 0.1230,
+    }
+}
+
+fn is_heteroatom(atom: &Atom) -> bool {
+    !matches!(atom.number, 0 | 1 | 6)
+}
+
+/// Whether the atom is a carbon with a double bond to oxygen.
+fn is_carbonyl_carbon(mol: &Mol, node: NodeIndex) -> bool {
+    mol.graph[node].number == 6
+        && mol.graph.edges(node).any(|edge| {
+            let other = if edge.source() == node {
+                edge.target()
+            } else {
+                edge.source()
+            };
+            edge.weight().order == BondOrder::Double && mol.graph[other].number == 8
+        })
+}
+
+/// Whether `n` is an NH nitrogen bonded to the carbonyl carbon `c`.
+fn is_amide_bond(mol: &Mol, n: NodeIndex, c: NodeIndex) -> bool {
+    mol.graph[n].number == 7 && hydrogen_count(mol, n) > 0 && is_carbonyl_carbon(mol, c)
+}
+
+fn heavy_neighbors(mol: &Mol, node: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+    mol.graph
+        .neighbors(node)
+        .filter(|n| mol.graph[*n].number != 1)
+}
+
+/// Implicit hydrogens and hydrogen atoms bonded to the atom.
+fn hydrogen_count(mol: &Mol, node: NodeIndex) -> u8 {
+    let atoms = mol
+        .graph
+        .neighbors(node)
+        .filter(|n| mol.graph[*n].number == 1)
+        .count() as u8;
+    mol.graph[node].total_num_hs() + atoms
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    use super::{
+        balaban_j, clogp, h_bond_acceptor_count, h_bond_donor_count, heavy_atom_count, ring_count,
+        rotatable_bond_count, tpsa, wiener_index,
+    };
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() < 1e-2,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    const ASPIRIN: &str = "CC(=O)Oc1ccccc1C(=O)O";
+
+    #[test]
+    fn counts() {
+        assert_eq!(13, heavy_atom_count(&mol(ASPIRIN)));
+        assert_eq!(3, heavy_atom_count(&mol("[H]OC([H])([H])C")));
+        assert_eq!(1, ring_count(&mol(ASPIRIN)));
+        assert_eq!(2, ring_count(&mol("c1ccc2ccccc2c1")));
+        assert_eq!(0, ring_count(&mol("CCO")));
+    }
+
+    #[test]
+    fn rotatable_bonds() {
+        assert_eq!(0, rotatable_bond_count(&mol("CC")));
+        assert_eq!(1, rotatable_bond_count(&mol("CCCC")));
+        assert_eq!(3, rotatable_bond_count(&mol(ASPIRIN)));
+        // ring bonds, bonds to triple bonded atoms and amides don't rotate
+        assert_eq!(0, rotatable_bond_count(&mol("C1CCCCC1")));
+        assert_eq!(1, rotatable_bond_count(&mol("CC#CCCC")));
+        assert_eq!(0, rotatable_bond_count(&mol("CNC(C)=O")));
+    }
+
+    #[test]
+    fn h_bonds() {
+        assert_eq!(1, h_bond_donor_count(&mol(ASPIRIN)));
+        assert_eq!(4, h_bond_acceptor_count(&mol(ASPIRIN)));
+        assert_eq!(3, h_bond_donor_count(&mol("NCCO")));
+        assert_eq!(2, h_bond_acceptor_count(&mol("NCCO")));
+        assert_eq!(3, h_bond_donor_count(&mol("[H]N([H])CCO").add_hs()));
+    }
+
+    #[test]
+    fn polar_surface_area() {
+        assert_close(0.0, tpsa(&mol("CCCC")));
+        assert_close(20.23, tpsa(&mol("CCO")));
+        assert_close(12.89, tpsa(&mol("c1ccncc1")));
+        assert_close(15.79, tpsa(&mol("c1cc[nH]c1")));
+        assert_close(63.6, tpsa(&mol(ASPIRIN)));
+        assert_close(43.14, tpsa(&mol("c1ccccc1[N+](=O)[O-]")));
+        assert_close(tpsa(&mol(ASPIRIN)), tpsa(&mol(ASPIRIN).add_hs()));
+    }
+
+    #[test]
+    fn logp() {
+        assert_close(1.6866, clogp(&mol("c1ccccc1")));
+        assert_close(-0.0014, clogp(&mol("CCO")));
+        assert_close(0.0909, clogp(&mol("CC(=O)O")));
+        assert_close(-0.4251, clogp(&mol("CN")));
+        assert_close(clogp(&mol("CCO")), clogp(&mol("CCO").add_hs()));
+        // longer chains are more lipophilic, hydroxyls less
+        assert!(clogp(&mol("CCCCCCCC")) > clogp(&mol("CCCCCCO")));
+        assert!(clogp(&mol("CCCCCCO")) > clogp(&mol("OCC(O)CO")));
+    }
+
+    #[test]
+    fn kekule_and_aromatic() {
+        for (kekule, aromatic) in [
+            ("C1=CC=NC=C1", "c1ccncc1"),
+            ("C1=CNC=C1", "c1cc[nH]c1"),
+            ("CN1C=NC2=C1C(=O)N(C(=O)N2C)C", "Cn1cnc2c1c(=O)n(c(=O)n2C)C"),
+            ("CC(=O)OC1=CC=CC=C1C(=O)O", ASPIRIN),
+        ] {
+            assert_eq!(
+                mol(aromatic).descriptors(),
+                mol(kekule).descriptors(),
+                "{}",
+                kekule
+            );
+        }
+        assert_close(12.89, tpsa(&mol("C1=CC=NC=C1")));
+        assert_close(clogp(&mol("c1ccncc1")), clogp(&mol("C1=CC=NC=C1")));
+    }
+
+    #[test]
+    fn all_descriptors() {
+        let descriptors = mol(ASPIRIN).descriptors();
+
+        assert_eq!(13, descriptors.heavy_atom_count);
+        assert_eq!(3, descriptors.rotatable_bond_count);
+        assert_close(180.159, descriptors.molecular_weight);
+        assert_close(63.6, descriptors.tpsa);
+    }
+
+    #[test]
+    fn topological_indices() {
+        assert_eq!(10, wiener_index(&mol("CCCC")));
+        assert_eq!(9, wiener_index(&mol("CC(C)C")));
+        assert_eq!(27, wiener_index(&mol("c1ccccc1")));
+        assert_eq!(10, wiener_index(&mol("CCCC").add_hs()));
+        // within fragments
+        assert_eq!(2, wiener_index(&mol("CC.CC")));
+
+        assert_close(1.975, balaban_j(&mol("CCCC")));
+        assert_close(2.324, balaban_j(&mol("CC(C)C")));
+        assert_close(2.0, balaban_j(&mol("c1ccccc1")));
+        assert_close(
+            balaban_j(&mol("CC(C)C")),
+            balaban_j(&mol("CC(C)C").add_hs()),
+        );
+        assert_close(0.0, balaban_j(&mol("C")));
+    }
+}
@@ -0,0 +1,372 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::svg::SvgOptions;
+use crate::types::{AtomIdx, BondIdx, Mol};
+
+/// Extensions of the common substructure search, after which it keeps the largest found.
+const MAX_STEPS: usize = 100_000;
+
+const REMOVED_COLOR: &str = "#FFB0B0";
+const ADDED_COLOR: &str = "#B0FFB0";
+
+/// The differences between two molecules, from the first to the second, after aligning
+/// them on their largest common substructure. See [`mol_diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MolDiff {
+    /// The aligned atoms, in the first molecule and in the second.
+    pub common_atoms: Vec<(AtomIdx, AtomIdx)>,
+    /// Common atoms whose charge or isotope differ.
+    pub changed_atoms: Vec<(AtomIdx, AtomIdx)>,
+    /// Atoms of the first molecule only.
+    pub removed_atoms: Vec<AtomIdx>,
+    /// Atoms of the second molecule only.
+    pub added_atoms: Vec<AtomIdx>,
+    /// Bonds of the first molecule only, including bonds whose order changed.
+    pub removed_bonds: Vec<BondIdx>,
+    /// Bonds of the second molecule only, including bonds whose order changed.
+    pub added_bonds: Vec<BondIdx>,
+}
+
+impl MolDiff {
+    /// Whether the molecules have the same structure.
+    pub fn is_empty(&self) -> bool {
+        self.changed_atoms.is_empty()
+            && self.removed_atoms.is_empty()
+            && self.added_atoms.is_empty()
+            && self.removed_bonds.is_empty()
+            && self.added_bonds.is_empty()
+    }
+
+    /// Both molecules side by side, each `options.width` wide: the removed atoms and
+    /// bonds highlighted in red on the first, the added ones in green on the second, and
+    /// the changed atoms on both.
+    pub fn to_svg(&self, first: &Mol, second: &Mol, options: &SvgOptions) -> String {
+        let mut removed = options.clone();
+        removed.highlight_atoms = self.removed_atoms.clone();
+        removed
+            .highlight_atoms
+            .extend(self.changed_atoms.iter().map(|(atom, _)| *atom));
+        removed.highlight_bonds = self.removed_bonds.clone();
+        removed.highlight_color = REMOVED_COLOR.to_string();
+        let mut added = options.clone();
+        added.highlight_atoms = self.added_atoms.clone();
+        added
+            .highlight_atoms
+            .extend(self.changed_atoms.iter().map(|(_, atom)| *atom));
+        added.highlight_bonds = self.added_bonds.clone();
+        added.highlight_color = ADDED_COLOR.to_string();
+
+        // each drawing as a nested svg element
+        let nested = |svg: String, x: f64| svg.replacen("<svg ", &format!(r#"<svg x="{}" "#, x), 1);
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n{}{}</svg>\n",
+            nested(first.to_svg(&removed), 0.0),
+            nested(second.to_svg(&added), options.width),
+            w = 2.0 * options.width,
+            h = options.height,
+        )
+    }
+}
+
+/// The atoms and bonds in one molecule but not the other. The molecules are aligned on
+/// their largest connected common substructure: atoms match by element, and it has the
+/// most bonds of the same order in both, then the most bonds of any order, which are
+/// the changed bonds. For large molecules the search stops after a fixed number of steps,
+/// with the largest substructure found so far.
+pub fn mol_diff(first: &Mol, second: &Mol) -> MolDiff {
+    let mapping = common_substructure(first, second);
+    let mut diff = MolDiff::default();
+    let mut mapped = vec![false; second.graph.node_count()];
+    for (a, b) in mapping.iter().enumerate() {
+        let Some(b) = b else {
+            diff.removed_atoms.push(AtomIdx::new(a));
+            continue;
+        };
+        mapped[b.index()] = true;
+        let pair = (AtomIdx::new(a), AtomIdx::new(b.index()));
+        diff.common_atoms.push(pair);
+        let (atom, other) = (&first.graph[NodeIndex::new(a)], &second.graph[*b]);
+        if atom.charge != other.charge || atom.isotope != other.isotope {
+            diff.changed_atoms.push(pair);
+        }
+    }
+    diff.added_atoms = (0..second.graph.node_count())
+        .filter(|atom| !mapped[*atom])
+        .map(AtomIdx::new)
+        .collect();
+
+    let mut common_bonds = vec![false; second.graph.edge_count()];
+    for edge in first.graph.edge_references() {
+        let image = mapping[edge.source().index()]
+            .zip(mapping[edge.target().index()])
+            .and_then(|(a, b)| second.graph.find_edge(a, b))
+            .filter(|image| second.graph[*image].order == edge.weight().order);
+        match image {
+            Some(image) => common_bonds[image.index()] = true,
+            None => diff.removed_bonds.push(BondIdx::new(edge.id().index())),
+        }
+    }
+    diff.added_bonds = (0..second.graph.edge_count())
+        .filter(|bond| !common_bonds[*bond])
+        .map(BondIdx::new)
+        .collect();
+    diff
+}
+
+/// The image in the second molecule of each atom of the first, for the largest common
+/// substructure found.
+fn common_substructure(first: &Mol, second: &Mol) -> Vec<Option<NodeIndex>> {
+    let mut search = Search {
+        first,
+        second,
+        mapping: vec![None; first.graph.node_count()],
+        used: vec![false; second.graph.node_count()],
+        excluded: vec![false; first.graph.node_count()],
+        size: (0, 0, 0),
+        best: vec![None; first.graph.node_count()],
+        best_size: (0, 0, 0),
+        steps: 0,
+    };
+    for seed in first.graph.node_indices() {
+        for candidate in second.graph.node_indices() {
+            if search.steps < MAX_STEPS && atoms_match(first, seed, second, candidate) {
+                let gained = search.map(seed, candidate);
+                search.extend();
+                search.unmap(seed, gained);
+            }
+        }
+        // the substructures with the seed have been searched
+        search.excluded[seed.index()] = true;
+    }
+    search.best
+}
+
+fn atoms_match(first: &Mol, a: NodeIndex, second: &Mol, b: NodeIndex) -> bool {
+    first.graph[a].number == second.graph[b].number
+}
+
+/// Backtracking search growing a connected mapping of atoms of the first molecule onto
+/// atoms of the second, one neighbor at a time.
+struct Search<'a> {
+    first: &'a Mol,
+    second: &'a Mol,
+    mapping: Vec<Option<NodeIndex>>,
+    /// Atoms of the second molecule in the mapping.
+    used: Vec<bool>,
+    /// Atoms of the first molecule left out of the mapping.
+    excluded: Vec<bool>,
+    size: Size,
+    best: Vec<Option<NodeIndex>>,
+    best_size: Size,
+    steps: usize,
+}
+
+/// Bonds between mapped atoms whose images are bonded: with the same order, then with
+/// any order. Then mapped atoms.
+type Size = (usize, usize, usize);
+
+impl Search<'_> {
+    /// Maps the atom, returning the size gained.
+    fn map(&mut self, atom: NodeIndex, image: NodeIndex) -> Size {
+        let mut gained = (0, 0, 1);
+        for edge in self.first.graph.edges(atom) {
+            let Some(other) = self.mapping[edge.target().index()]
+                .and_then(|other| self.second.graph.find_edge(image, other))
+            else {
+                continue;
+            };
+            if self.second.graph[other].order == edge.weight().order {
+                gained.0 += 1;
+            }
+            gained.1 += 1;
+        }
+        self.mapping[atom.index()] = Some(image);
+        self.used[image.index()] = true;
+        self.size = (
+            self.size.0 + gained.0,
+            self.size.1 + gained.1,
+            self.size.2 + gained.2,
+        );
+        gained
+    }
+
+    fn unmap(&mut self, atom: NodeIndex, gained: Size) {
+        // unwrap: the atom is mapped
+        let image = self.mapping[atom.index()].take().unwrap();
+        self.used[image.index()] = false;
+        self.size = (
+            self.size.0 - gained.0,
+            self.size.1 - gained.1,
+            self.size.2 - gained.2,
+        );
+    }
+
+    /// Records the mapping if it's the best so far, then extends it with the first
+    /// undecided neighbor of the mapped atoms: mapped to each candidate, then left out.
+    fn extend(&mut self) {
+        self.steps += 1;
+        if self.size > self.best_size {
+            self.best_size = self.size;
+            self.best.clone_from(&self.mapping);
+        }
+        // each extension adds a bond, so only more bonds can do better
+        let bound = self.bound();
+        let (bonds, aligned, _) = self.size;
+        if self.steps >= MAX_STEPS
+            || (bonds + bound, aligned + bound) <= (self.best_size.0, self.best_size.1)
+        {
+            return;
+        }
+        let graph = &self.first.graph;
+        let Some(next) = graph.node_indices().find(|node| {
+            self.mapping[node.index()].is_none()
+                && !self.excluded[node.index()]
+                && graph
+                    .neighbors(*node)
+                    .any(|n| self.mapping[n.index()].is_some())
+        }) else {
+            return;
+        };
+
+        // neighbors of the images of its mapped neighbors
+        let mut candidates: Vec<NodeIndex> = graph
+            .neighbors(next)
+            .filter_map(|neighbor| self.mapping[neighbor.index()])
+            .flat_map(|anchor| self.second.graph.neighbors(anchor))
+            .filter(|candidate| {
+                !self.used[candidate.index()]
+                    && atoms_match(self.first, next, self.second, *candidate)
+            })
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        for candidate in candidates {
+            let gained = self.map(next, candidate);
+            self.extend();
+            self.unmap(next, gained);
+        }
+        self.excluded[next.index()] = true;
+        self.extend();
+        self.excluded[next.index()] = false;
+    }
+
+    /// Upper bound of the bonds the mapping can still gain: bonds with an atom not yet
+    /// decided, in both molecules.
+    fn bound(&self) -> usize {
+        let first = self
+            .first
+            .graph
+            .edge_references()
+            .filter(|edge| {
+                let ends = [edge.source().index(), edge.target().index()];
+                ends.iter().all(|atom| !self.excluded[*atom])
+                    && ends.iter().any(|atom| self.mapping[*atom].is_none())
+            })
+            .count();
+        let second = self
+            .second
+            .graph
+            .edge_references()
+            .filter(|edge| !self.used[edge.source().index()] || !self.used[edge.target().index()])
+            .count();
+        first.min(second)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::svg::SvgOptions;
+    use crate::types::{AtomIdx, BondIdx, Mol};
+
+    use super::mol_diff;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn atoms(indices: &[usize]) -> Vec<AtomIdx> {
+        indices.iter().copied().map(AtomIdx::new).collect()
+    }
+
+    fn bonds(indices: &[usize]) -> Vec<BondIdx> {
+        indices.iter().copied().map(BondIdx::new).collect()
+    }
+
+    #[test]
+    fn substituted_atom() {
+        let diff = mol_diff(&mol("CCO"), &mol("CCN"));
+
+        assert_eq!(2, diff.common_atoms.len());
+        assert_eq!(atoms(&[2]), diff.removed_atoms);
+        assert_eq!(atoms(&[2]), diff.added_atoms);
+        assert_eq!(bonds(&[1]), diff.removed_bonds);
+        assert_eq!(bonds(&[1]), diff.added_bonds);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn added_atoms() {
+        // the ring of toluene in benzene, the methyl group added
+        let diff = mol_diff(&mol("c1ccccc1"), &mol("Cc1ccccc1"));
+
+        assert_eq!(6, diff.common_atoms.len());
+        assert!(diff.removed_atoms.is_empty());
+        assert!(diff.removed_bonds.is_empty());
+        assert_eq!(atoms(&[0]), diff.added_atoms);
+        assert_eq!(bonds(&[0]), diff.added_bonds);
+    }
+
+    #[test]
+    fn same_structure() {
+        let diff = mol_diff(&mol("OCC(=O)N"), &mol("NC(=O)CO"));
+
+        assert!(diff.is_empty());
+        assert_eq!(5, diff.common_atoms.len());
+        // O1 of the first is O0 of the second
+        assert!(diff
+            .common_atoms
+            .contains(&(AtomIdx::new(0), AtomIdx::new(4))));
+    }
+
+    #[test]
+    fn changed_bond_and_charge() {
+        let diff = mol_diff(&mol("C=CC"), &mol("CCC"));
+        assert_eq!(3, diff.common_atoms.len());
+        assert_eq!(1, diff.removed_bonds.len());
+        assert_eq!(1, diff.added_bonds.len());
+        assert!(diff.removed_atoms.is_empty() && diff.added_atoms.is_empty());
+
+        let diff = mol_diff(&mol("CC(=O)O"), &mol("CC(=O)[O-]"));
+        assert_eq!(vec![(AtomIdx::new(3), AtomIdx::new(3))], diff.changed_atoms);
+        assert!(diff.removed_bonds.is_empty() && diff.added_bonds.is_empty());
+    }
+
+    #[test]
+    fn nothing_in_common() {
+        let diff = mol_diff(&mol("O"), &mol("N"));
+        assert!(diff.common_atoms.is_empty());
+        assert_eq!(atoms(&[0]), diff.removed_atoms);
+        assert_eq!(atoms(&[0]), diff.added_atoms);
+
+        assert!(mol_diff(&mol(""), &mol("")).is_empty());
+    }
+
+    #[test]
+    fn svg_highlights_differences() {
+        let (first, second) = (mol("CCO"), mol("CCN"));
+        let svg = mol_diff(&first, &second).to_svg(&first, &second, &SvgOptions::default());
+
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="600""#));
+        assert_eq!(2, svg.matches(r#"<svg x="#).count());
+        assert_eq!(2, svg.matches("<circle ").count());
+        assert!(svg.contains(r##"fill="#FFB0B0""##));
+        assert!(svg.contains(r##"fill="#B0FFB0""##));
+    }
+}
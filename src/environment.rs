@@ -0,0 +1,171 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::fragments::extract;
+use crate::topology::distances_from;
+use crate::types::{AtomIdx, BondIdx, Mol};
+
+/// The atoms and bonds up to some bonds away from an atom: the part of the molecule a
+/// circular fingerprint hashes for it.
+#[derive(Debug, Clone)]
+pub struct AtomEnvironment {
+    pub center: AtomIdx,
+    pub radius: usize,
+    /// The atoms, the center first, then by distance from it.
+    pub atoms: Vec<AtomIdx>,
+    /// The bonds from the atoms less than `radius` bonds away from the center.
+    pub bonds: Vec<BondIdx>,
+    /// The atoms and bonds as a molecule, its atoms in the order of `atoms`.
+    pub mol: Mol,
+    /// SMILES of the molecule, written from the center. Atoms keep their hydrogens, e.g.
+    /// `C[CH2]` for the first atom of `CCO` and 1 bond.
+    pub smiles: String,
+}
+
+/// The environment of the atom, `None` if there's no such atom. Bonds between atoms that
+/// are both `radius` bonds away are left out, like the rest of the molecule, and so is
+/// stereo involving them.
+pub fn atom_environment(mol: &Mol, center: AtomIdx, radius: usize) -> Option<AtomEnvironment> {
+    let graph = &mol.graph;
+    if center.index() >= graph.node_count() {
+        return None;
+    }
+    let distances = distances_from(mol, center.into());
+    let mut atoms: Vec<AtomIdx> = (0..graph.node_count())
+        .filter(|atom| distances[*atom].is_some_and(|distance| distance <= radius))
+        .map(AtomIdx::new)
+        .collect();
+    atoms.sort_by_key(|atom| (distances[atom.index()], *atom));
+    let bonds: Vec<BondIdx> = graph
+        .edge_references()
+        .filter(|edge| {
+            [edge.source(), edge.target()]
+                .iter()
+                .any(|atom| distances[atom.index()].is_some_and(|distance| distance < radius))
+        })
+        .map(|edge| BondIdx::new(edge.id().index()))
+        .collect();
+
+    let mut environment = extract(mol, &atoms);
+    environment.graph.retain_edges(|subgraph, edge| {
+        // unwrap: edge comes from the subgraph
+        let (a, b) = subgraph.edge_endpoints(edge).unwrap();
+        let (a, b) = (
+            NodeIndex::from(atoms[a.index()]),
+            NodeIndex::from(atoms[b.index()]),
+        );
+        graph
+            .find_edge(a, b)
+            .is_some_and(|edge| bonds.contains(&BondIdx::new(edge.index())))
+    });
+    clear_cut_stereo(&mut environment);
+
+    let smiles = environment.to_smiles();
+    Some(AtomEnvironment {
+        center,
+        radius,
+        atoms,
+        bonds,
+        mol: environment,
+        smiles,
+    })
+}
+
+/// Clears chirality and cis/trans stereo relative to atoms that aren't bonded anymore.
+fn clear_cut_stereo(mol: &mut Mol) {
+    let graph = &mut mol.graph;
+    for node in graph.node_indices() {
+        let bonded = graph[node]
+            .chiral_neighbors
+            .iter()
+            .all(|n| graph.contains_edge(node, (*n).into()));
+        if !bonded {
+            let atom = &mut graph[node];
+            atom.chirality = None;
+            atom.chiral_neighbors = vec![];
+        }
+    }
+    for edge in graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (start, end) = graph.edge_endpoints(edge).unwrap();
+        let Some(stereo) = graph[edge].stereo else {
+            continue;
+        };
+        if !graph.contains_edge(start, stereo.start_neighbor.into())
+            || !graph.contains_edge(end, stereo.end_neighbor.into())
+        {
+            graph[edge].stereo = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, BondIdx, Mol};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn smiles(smiles: &str, center: usize, radius: usize) -> String {
+        mol(smiles)
+            .atom_environment(AtomIdx::new(center), radius)
+            .unwrap()
+            .smiles
+    }
+
+    #[test]
+    fn environment_atoms_and_bonds() {
+        let environment = mol("CC(=O)NCC")
+            .atom_environment(AtomIdx::new(3), 1)
+            .unwrap();
+
+        assert_eq!([3, 1, 4].map(AtomIdx::new).to_vec(), environment.atoms);
+        assert_eq!([2, 3].map(BondIdx::new).to_vec(), environment.bonds);
+        assert_eq!(3, environment.mol.num_atoms());
+        assert_eq!(2, environment.mol.num_bonds());
+    }
+
+    #[test]
+    fn environment_smiles() {
+        assert_eq!("[CH2]", smiles("CCO", 1, 0));
+        assert_eq!("C(C)O", smiles("CCO", 1, 1));
+        assert_eq!("C[CH2]", smiles("CCO", 0, 1));
+        assert_eq!("N(C(C)=O)CC", smiles("CC(=O)NCC", 3, 2));
+        assert_eq!("N([C])[CH2]", smiles("CC(=O)NCC", 3, 1));
+        // the whole molecule
+        assert_eq!("OCC", smiles("CCO", 2, 5));
+    }
+
+    #[test]
+    fn ring_environment() {
+        // the bond between the two atoms 2 bonds away isn't part of it
+        let environment = mol("C1CC1").atom_environment(AtomIdx::new(0), 1).unwrap();
+        assert_eq!(3, environment.atoms.len());
+        assert_eq!(2, environment.bonds.len());
+
+        let environment = mol("c1ccccc1")
+            .atom_environment(AtomIdx::new(0), 3)
+            .unwrap();
+        assert_eq!(6, environment.atoms.len());
+        assert_eq!(6, environment.bonds.len());
+    }
+
+    #[test]
+    fn environment_stereo() {
+        assert_eq!("[C@@H](F)(Cl)[CH2]", smiles("F[C@H](Cl)CC", 1, 1));
+        // a neighbor left out
+        assert_eq!("C([CH])C", smiles("F[C@H](Cl)CC", 3, 1));
+    }
+
+    #[test]
+    fn no_atom() {
+        assert!(mol("CC").atom_environment(AtomIdx::new(2), 1).is_none());
+    }
+}
@@ -0,0 +1,567 @@
+use alloc::string::String;
+use core::fmt;
+
+use crate::types::{AtomIdx, BondIdx};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmilesErrorKind {
+    /// A character that can't appear at this position.
+    UnexpectedCharacter(char),
+    /// The input ended while more was expected.
+    UnexpectedEnd,
+    /// An element symbol that isn't in the periodic table.
+    UnknownElement(String),
+    /// A ring bond that was opened but never closed.
+    UnclosedRing(u16),
+    /// A ring bond opened and closed on the same atom, like `C11`.
+    RingBondToSelf(u16),
+    /// A ring bond between atoms that are already bonded, like `C1C1`.
+    DuplicateBond(u16),
+    /// A ring bond whose two ends are written with different bond orders, like `C=1CC-1`,
+    /// or with opposite directions, like `C/1CC/1`.
+    RingBondConflict(u16),
+    /// A `(` without matching `)` or vice versa.
+    UnbalancedParenthesis,
+    /// A ring bond or branch that doesn't follow an atom.
+    MissingAtom,
+    /// A bond symbol that isn't followed by an atom or ring bond.
+    DanglingBond,
+    /// A `%(nnn)` ring number, which OpenSMILES doesn't define, in strict mode.
+    ExtendedRingNumber,
+    /// A ring number past
+    /// [`SmilesParserOptions::max_ring_number`](crate::SmilesParserOptions::max_ring_number).
+    RingNumberTooHigh { number: u16, max: u16 },
+    /// Branches nested deeper than
+    /// [`SmilesParserOptions::max_branch_depth`](crate::SmilesParserOptions::max_branch_depth),
+    /// or `$(...)` and `!` of a SMARTS nested deeper than 128 levels.
+    NestingTooDeep,
+    /// A number (charge, hydrogen count, ...) that is out of range.
+    NumberOutOfRange,
+    /// An aromatic atom that isn't in a ring, rejected by the parser options.
+    AromaticOutsideRing,
+    /// An aromatic system without Kekulé form, when the parser options ask to kekulize.
+    Kekulize,
+    /// A CXSMILES extension that can't be read, e.g. with an atom index out of range.
+    InvalidExtension,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmilesError {
+    pub kind: SmilesErrorKind,
+    /// Character position in the input (from `Scanner::cursor()`).
+    pub position: usize,
+    /// Description of what was expected at `position`, if known.
+    pub expected: Option<&'static str>,
+}
+
+impl SmilesError {
+    pub fn new(kind: SmilesErrorKind, position: usize) -> Self {
+        SmilesError {
+            kind,
+            position,
+            expected: None,
+        }
+    }
+
+    pub fn expected(kind: SmilesErrorKind, position: usize, expected: &'static str) -> Self {
+        SmilesError {
+            kind,
+            position,
+            expected: Some(expected),
+        }
+    }
+
+    /// Displays the parsed input with a caret under the error position, followed by the
+    /// error:
+    ///
+    /// ```text
+    /// C[NH4
+    ///      ^ unexpected end of input at position 5, expected ']'
+    /// ```
+    pub fn annotate<'a>(&'a self, input: &'a str) -> impl fmt::Display + 'a {
+        Annotated {
+            message: self,
+            position: self.position,
+            input,
+        }
+    }
+}
+
+/// The input with a caret under `position`, followed by the message.
+pub(crate) struct Annotated<'a, T> {
+    pub message: &'a T,
+    pub position: usize,
+    pub input: &'a str,
+}
+
+impl<T: fmt::Display> fmt::Display for Annotated<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.input)?;
+        write!(f, "{:width$}^ {}", "", self.message, width = self.position)
+    }
+}
+
+impl fmt::Display for SmilesErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmilesErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            SmilesErrorKind::UnexpectedEnd => write!(f, "unexpected end of input"),
+            SmilesErrorKind::UnknownElement(symbol) => write!(f, "unknown element '{}'", symbol),
+            SmilesErrorKind::UnclosedRing(ring) => write!(f, "unclosed ring {}", ring),
+            SmilesErrorKind::RingBondToSelf(ring) => {
+                write!(f, "ring bond {} from an atom to itself", ring)
+            }
+            SmilesErrorKind::DuplicateBond(ring) => {
+                write!(f, "ring bond {} between atoms already bonded", ring)
+            }
+            SmilesErrorKind::RingBondConflict(ring) => {
+                write!(
+                    f,
+                    "ring bond {} written with conflicting bond symbols",
+                    ring
+                )
+            }
+            SmilesErrorKind::UnbalancedParenthesis => write!(f, "unbalanced parenthesis"),
+            SmilesErrorKind::NestingTooDeep => write!(f, "branches nested too deeply"),
+            SmilesErrorKind::ExtendedRingNumber => {
+                write!(f, "`%(nnn)` ring numbers are not OpenSMILES")
+            }
+            SmilesErrorKind::RingNumberTooHigh { number, max } => write!(
+                f,
+                "ring number {} is past the highest accepted, {} (see \
+                 SmilesParserOptions::max_ring_number)",
+                number, max
+            ),
+            SmilesErrorKind::MissingAtom => write!(f, "missing atom"),
+            SmilesErrorKind::DanglingBond => write!(f, "bond without atom"),
+            SmilesErrorKind::NumberOutOfRange => write!(f, "number out of range"),
+            SmilesErrorKind::AromaticOutsideRing => write!(f, "aromatic atom outside a ring"),
+            SmilesErrorKind::Kekulize => write!(f, "aromatic system can't be kekulized"),
+            SmilesErrorKind::InvalidExtension => write!(f, "invalid CXSMILES extension"),
+        }
+    }
+}
+
+impl fmt::Display for SmilesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.position)?;
+        if let Some(expected) = self.expected {
+            write!(f, ", expected {}", expected)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for SmilesError {}
+
+/// The aromatic system can't be written with alternating single and double bonds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KekulizeError {
+    /// An atom that couldn't get a double bond.
+    pub atom: AtomIdx,
+}
+
+impl fmt::Display for KekulizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "can't kekulize atom {}", self.atom)
+    }
+}
+
+impl core::error::Error for KekulizeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MolfileErrorKind {
+    /// The input ended before all declared atoms and bonds were read.
+    UnexpectedEnd,
+    /// Only the V2000 format is supported.
+    UnsupportedVersion(String),
+    /// The counts line (4th line) couldn't be read.
+    InvalidCountsLine,
+    /// An atom line couldn't be read.
+    InvalidAtomLine,
+    /// A bond line couldn't be read, or refers to atoms that don't exist.
+    InvalidBondLine,
+    /// A property line (`M  CHG`, `M  ISO`) couldn't be read, or refers to atoms that don't exist.
+    InvalidPropertyLine,
+    /// An element symbol that isn't in the periodic table.
+    UnknownElement(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MolfileError {
+    pub kind: MolfileErrorKind,
+    /// Line number in the input, starting at 1.
+    pub line: usize,
+}
+
+impl MolfileError {
+    pub fn new(kind: MolfileErrorKind, line: usize) -> Self {
+        MolfileError { kind, line }
+    }
+}
+
+impl fmt::Display for MolfileErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MolfileErrorKind::UnexpectedEnd => write!(f, "unexpected end of input"),
+            MolfileErrorKind::UnsupportedVersion(version) => {
+                write!(f, "unsupported version '{}'", version)
+            }
+            MolfileErrorKind::InvalidCountsLine => write!(f, "invalid counts line"),
+            MolfileErrorKind::InvalidAtomLine => write!(f, "invalid atom line"),
+            MolfileErrorKind::InvalidBondLine => write!(f, "invalid bond line"),
+            MolfileErrorKind::InvalidPropertyLine => write!(f, "invalid property line"),
+            MolfileErrorKind::UnknownElement(symbol) => write!(f, "unknown element '{}'", symbol),
+        }
+    }
+}
+
+impl fmt::Display for MolfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}", self.kind, self.line)
+    }
+}
+
+impl core::error::Error for MolfileError {}
+
+/// Error reading an SDF file.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum SdfError {
+    /// The underlying reader failed. No further records are read.
+    Io(std::io::Error),
+    /// The molfile of a record is invalid. Reading continues with the next record.
+    Molfile {
+        /// Index of the record in the file, starting at 0.
+        record: usize,
+        error: MolfileError,
+    },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for SdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdfError::Io(error) => write!(f, "{}", error),
+            SdfError::Molfile { record, error } => write!(f, "record {}: {}", record, error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for SdfError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            SdfError::Io(error) => Some(error),
+            SdfError::Molfile { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for SdfError {
+    fn from(error: std::io::Error) -> Self {
+        SdfError::Io(error)
+    }
+}
+
+/// An error reading a SMILES file.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum ParseErrorAt {
+    /// The underlying reader failed. No further lines are read.
+    Io(std::io::Error),
+    /// The SMILES of a line is invalid.
+    Smiles {
+        /// Line in the file, starting at 1.
+        line: usize,
+        error: SmilesError,
+    },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ParseErrorAt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorAt::Io(error) => write!(f, "{}", error),
+            ParseErrorAt::Smiles { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for ParseErrorAt {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ParseErrorAt::Io(error) => Some(error),
+            ParseErrorAt::Smiles { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for ParseErrorAt {
+    fn from(error: std::io::Error) -> Self {
+        ParseErrorAt::Io(error)
+    }
+}
+
+/// An error reading a dataset.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum DatasetError {
+    /// The underlying reader failed. No further records are read.
+    Io(std::io::Error),
+    /// The Parquet file is invalid. No further records are read.
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+    /// The header has no column of this name.
+    MissingColumn(String),
+    /// A record doesn't have a field for each column of the header.
+    FieldCount {
+        /// Index of the record in the file, starting at 0, not counting the header.
+        record: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A quoted field isn't closed by the end of the file. No further records are read.
+    UnclosedQuote { record: usize },
+    /// The SMILES of a record is invalid.
+    Smiles { record: usize, error: SmilesError },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Io(error) => write!(f, "{}", error),
+            #[cfg(feature = "parquet")]
+            DatasetError::Parquet(error) => write!(f, "{}", error),
+            DatasetError::MissingColumn(column) => write!(f, "no column {:?}", column),
+            DatasetError::FieldCount {
+                record,
+                expected,
+                found,
+            } => write!(
+                f,
+                "record {}: {} fields, expected {}",
+                record, found, expected
+            ),
+            DatasetError::UnclosedQuote { record } => {
+                write!(f, "record {}: unclosed quote", record)
+            }
+            DatasetError::Smiles { record, error } => write!(f, "record {}: {}", record, error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for DatasetError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            DatasetError::Io(error) => Some(error),
+            #[cfg(feature = "parquet")]
+            DatasetError::Parquet(error) => Some(error),
+            DatasetError::Smiles { error, .. } => Some(error),
+            DatasetError::MissingColumn(_)
+            | DatasetError::FieldCount { .. }
+            | DatasetError::UnclosedQuote { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for DatasetError {
+    fn from(error: std::io::Error) -> Self {
+        DatasetError::Io(error)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for DatasetError {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        DatasetError::Parquet(error)
+    }
+}
+
+/// An invalid edit of a [`MolBuilder`](crate::MolBuilder).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// No atom with this index.
+    AtomOutOfRange(AtomIdx),
+    /// No bond with this index.
+    BondOutOfRange(BondIdx),
+    /// A bond from an atom to itself.
+    SelfBond(AtomIdx),
+    /// The atoms are already bonded.
+    DuplicateBond(AtomIdx, AtomIdx),
+    /// The atom has more bonds (and hydrogens) than its element allows.
+    InvalidValence(AtomIdx),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::AtomOutOfRange(atom) => write!(f, "no atom {}", atom),
+            EditError::BondOutOfRange(bond) => write!(f, "no bond {}", bond),
+            EditError::SelfBond(atom) => write!(f, "bond from atom {} to itself", atom),
+            EditError::DuplicateBond(a, b) => write!(f, "atoms {} and {} are already bonded", a, b),
+            EditError::InvalidValence(atom) => write!(f, "invalid valence for atom {}", atom),
+        }
+    }
+}
+
+impl core::error::Error for EditError {}
+
+/// Invalid input of [`Mol::from_json`](crate::Mol::from_json).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    /// Not JSON, at this byte position.
+    Syntax(usize),
+    /// JSON, but not a molecule in the format of [`json`](crate::json).
+    Schema(String),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Syntax(position) => write!(f, "invalid JSON at position {}", position),
+            JsonError::Schema(message) => write!(f, "invalid molecule: {}", message),
+        }
+    }
+}
+
+impl core::error::Error for JsonError {}
+
+/// Error reading a Marvin document with [`Mol::from_mrv`](crate::Mol::from_mrv).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MrvError {
+    /// Not well-formed XML, at this byte position.
+    Xml(usize),
+    /// An `elementType` that isn't an element symbol.
+    UnknownElement(String),
+    /// A bond to an atom id that isn't in its molecule.
+    UnknownAtom(String),
+    /// A bond from an atom to itself, or between atoms that are already bonded, by its
+    /// `atomRefs2`.
+    InvalidBond(String),
+    /// An attribute value that can't be read, e.g. a bond `order` or a `formalCharge`.
+    InvalidAttribute { name: String, value: String },
+}
+
+impl fmt::Display for MrvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MrvError::Xml(position) => write!(f, "invalid XML at position {}", position),
+            MrvError::UnknownElement(symbol) => write!(f, "unknown element '{}'", symbol),
+            MrvError::UnknownAtom(id) => write!(f, "bond to unknown atom '{}'", id),
+            MrvError::InvalidBond(atoms) => write!(f, "invalid bond between '{}'", atoms),
+            MrvError::InvalidAttribute { name, value } => {
+                write!(f, "invalid {} '{}'", name, value)
+            }
+        }
+    }
+}
+
+impl core::error::Error for MrvError {}
+
+/// Error generating an InChI with [`Mol::to_inchi`](crate::Mol::to_inchi).
+#[cfg(feature = "inchi")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InchiError {
+    /// More atoms, bonds on an atom or stereo centers than the InChI library takes.
+    TooLarge,
+    /// Something InChI can't represent, e.g. a wildcard atom.
+    Unsupported(String),
+    /// The InChI library's error message.
+    Library(String),
+}
+
+#[cfg(feature = "inchi")]
+impl fmt::Display for InchiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InchiError::TooLarge => write!(f, "molecule too large for InChI"),
+            InchiError::Unsupported(what) => write!(f, "InChI doesn't support {}", what),
+            InchiError::Library(message) => write!(f, "InChI: {}", message),
+        }
+    }
+}
+
+#[cfg(feature = "inchi")]
+impl core::error::Error for InchiError {}
+
+/// An invalid scaffold or substituent of a [`Library`](crate::library::Library).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryError {
+    /// An attachment point of the scaffold that isn't bonded to exactly one atom.
+    ScaffoldPoint(AtomIdx),
+    /// No substituents for the attachment points with this label.
+    MissingSubstituents(u32),
+    /// A substituent, by label and index in its list, that doesn't have exactly one
+    /// attachment point bonded to one atom.
+    SubstituentPoint { label: u32, index: usize },
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::ScaffoldPoint(atom) => {
+                write!(f, "attachment point {} isn't bonded to one atom", atom)
+            }
+            LibraryError::MissingSubstituents(label) => {
+                write!(f, "no substituents for R{}", label)
+            }
+            LibraryError::SubstituentPoint { label, index } => write!(
+                f,
+                "substituent {} of R{} doesn't have one attachment point",
+                index, label
+            ),
+        }
+    }
+}
+
+impl core::error::Error for LibraryError {}
+
+/// A chemically impossible molecule, found by [`Mol::sanitize`](crate::Mol::sanitize).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanitizeError {
+    /// The atom has more bonds and hydrogens than its element allows.
+    InvalidValence {
+        atom: AtomIdx,
+        /// Bonds and explicit hydrogens, aromatic atoms counting one more bond.
+        valence: u8,
+        /// The highest valence allowed for the element and charge.
+        max: u8,
+    },
+    /// An aromatic system that can't be written with alternating single and double bonds.
+    Kekulize(KekulizeError),
+}
+
+impl From<KekulizeError> for SanitizeError {
+    fn from(error: KekulizeError) -> Self {
+        SanitizeError::Kekulize(error)
+    }
+}
+
+impl fmt::Display for SanitizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanitizeError::InvalidValence { atom, valence, max } => write!(
+                f,
+                "atom {} has valence {}, more than the allowed {}",
+                atom, valence, max
+            ),
+            SanitizeError::Kekulize(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl core::error::Error for SanitizeError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            SanitizeError::InvalidValence { .. } => None,
+            SanitizeError::Kekulize(error) => Some(error),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! C API, with the `ffi` feature. Molecules are opaque handles, freed with
+//! [`smiles_free_mol`]; strings returned by the library are freed with
+//! [`smiles_free_string`]. Functions that can fail return a [`SmilesStatus`].
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::smiles::SmilesParser;
+use crate::types::Mol;
+
+/// An opaque molecule handle.
+pub struct SmilesMol(Mol);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmilesStatus {
+    Ok = 0,
+    NullPointer = 1,
+    /// The input isn't valid UTF-8.
+    InvalidUtf8 = 2,
+    InvalidSmiles = 3,
+}
+
+/// Parses the NUL-terminated SMILES into `*out`, which is set to null on failure.
+///
+/// # Safety
+///
+/// `smiles` must be null or a NUL-terminated string, `out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn smiles_parse(
+    smiles: *const c_char,
+    out: *mut *mut SmilesMol,
+) -> SmilesStatus {
+    if out.is_null() {
+        return SmilesStatus::NullPointer;
+    }
+    *out = ptr::null_mut();
+    if smiles.is_null() {
+        return SmilesStatus::NullPointer;
+    }
+    let Ok(smiles) = CStr::from_ptr(smiles).to_str() else {
+        return SmilesStatus::InvalidUtf8;
+    };
+    match SmilesParser::new().parse(smiles) {
+        Ok(mol) => {
+            *out = Box::into_raw(Box::new(SmilesMol(mol)));
+            SmilesStatus::Ok
+        }
+        Err(_) => SmilesStatus::InvalidSmiles,
+    }
+}
+
+/// Frees a molecule from [`smiles_parse`]. Does nothing for null.
+///
+/// # Safety
+///
+/// `mol` must be null or a molecule from [`smiles_parse`] that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smiles_free_mol(mol: *mut SmilesMol) {
+    if !mol.is_null() {
+        drop(Box::from_raw(mol));
+    }
+}
+
+/// Number of atoms, 0 for null.
+///
+/// # Safety
+///
+/// `mol` must be null or a live molecule from [`smiles_parse`].
+#[no_mangle]
+pub unsafe extern "C" fn smiles_mol_num_atoms(mol: *const SmilesMol) -> usize {
+    mol.as_ref().map_or(0, |mol| mol.0.num_atoms())
+}
+
+/// Number of bonds, 0 for null.
+///
+/// # Safety
+///
+/// `mol` must be null or a live molecule from [`smiles_parse`].
+#[no_mangle]
+pub unsafe extern "C" fn smiles_mol_num_bonds(mol: *const SmilesMol) -> usize {
+    mol.as_ref().map_or(0, |mol| mol.0.num_bonds())
+}
+
+/// Writes the canonical SMILES into `*out`, a string to free with [`smiles_free_string`].
+///
+/// # Safety
+///
+/// `mol` must be null or a live molecule from [`smiles_parse`], `out` null or valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn smiles_mol_to_canonical(
+    mol: *const SmilesMol,
+    out: *mut *mut c_char,
+) -> SmilesStatus {
+    if out.is_null() {
+        return SmilesStatus::NullPointer;
+    }
+    *out = ptr::null_mut();
+    let Some(mol) = mol.as_ref() else {
+        return SmilesStatus::NullPointer;
+    };
+    // unwrap: SMILES don't contain NUL
+    let smiles = CString::new(mol.0.to_canonical_smiles()).unwrap();
+    *out = smiles.into_raw();
+    SmilesStatus::Ok
+}
+
+/// Frees a string returned by the library. Does nothing for null.
+///
+/// # Safety
+///
+/// `string` must be null or a string from this library that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smiles_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    use super::*;
+
+    #[test]
+    fn parse_and_canonicalize() {
+        let input = CString::new("OCC").unwrap();
+        let mut mol = ptr::null_mut();
+        unsafe {
+            assert_eq!(SmilesStatus::Ok, smiles_parse(input.as_ptr(), &mut mol));
+            assert_eq!(3, smiles_mol_num_atoms(mol));
+            assert_eq!(2, smiles_mol_num_bonds(mol));
+
+            let mut canonical = ptr::null_mut();
+            assert_eq!(
+                SmilesStatus::Ok,
+                smiles_mol_to_canonical(mol, &mut canonical)
+            );
+            assert_eq!("CCO", CStr::from_ptr(canonical).to_str().unwrap());
+            smiles_free_string(canonical);
+            smiles_free_mol(mol);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let input = CString::new("C1CC").unwrap();
+        let mut mol = ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                SmilesStatus::InvalidSmiles,
+                smiles_parse(input.as_ptr(), &mut mol)
+            );
+            assert!(mol.is_null());
+            assert_eq!(
+                SmilesStatus::NullPointer,
+                smiles_parse(ptr::null(), &mut mol)
+            );
+            let invalid = CString::new(vec![0xff]).unwrap();
+            assert_eq!(
+                SmilesStatus::InvalidUtf8,
+                smiles_parse(invalid.as_ptr(), &mut mol)
+            );
+            assert_eq!(0, smiles_mol_num_atoms(ptr::null()));
+            let mut canonical = ptr::null_mut();
+            assert_eq!(
+                SmilesStatus::NullPointer,
+                smiles_mol_to_canonical(ptr::null(), &mut canonical)
+            );
+            smiles_free_mol(ptr::null_mut());
+            smiles_free_string(ptr::null_mut());
+        }
+    }
+}
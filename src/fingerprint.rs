@@ -0,0 +1,568 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{NodeIndex, UnGraph};
+use petgraph::visit::EdgeRef;
+
+use crate::canon;
+use crate::similarity;
+use crate::structure_key::fnv1a;
+use crate::types::{AtomIdx, BondOrder, Mol};
+
+/// A fixed length vector of bits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitVec {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    /// All bits unset.
+    pub fn new(len: usize) -> BitVec {
+        BitVec {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the bit is set, `false` if out of range.
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Sets the bit. Panics if out of range.
+    pub fn set(&mut self, index: usize) {
+        assert!(index < self.len, "bit {} out of range {}", index, self.len);
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    /// Number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Indices of the set bits, in increasing order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|index| self.get(*index))
+    }
+
+    /// Number of bits set in both vectors.
+    pub fn count_common(&self, other: &BitVec) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Whether all the bits set here are set in `other`.
+    pub fn is_subset(&self, other: &BitVec) -> bool {
+        self.words
+            .iter()
+            .enumerate()
+            .all(|(i, word)| word & !other.words.get(i).unwrap_or(&0) == 0)
+    }
+
+    /// Tanimoto similarity, see [`similarity::tanimoto`].
+    pub fn tanimoto(&self, other: &BitVec) -> f64 {
+        similarity::tanimoto(self, other)
+    }
+}
+
+/// A fingerprint with the atom environments that set its bits, to interpret them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    bits: BitVec,
+    info: BTreeMap<usize, Vec<(AtomIdx, usize)>>,
+}
+
+impl Fingerprint {
+    fn new(len: usize) -> Fingerprint {
+        Fingerprint {
+            bits: BitVec::new(len),
+            info: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the bit, recording the environment.
+    fn add(&mut self, bit: usize, center: NodeIndex, radius: usize) {
+        self.bits.set(bit);
+        let environments = self.info.entry(bit).or_default();
+        let environment = (AtomIdx::new(center.index()), radius);
+        if !environments.contains(&environment) {
+            environments.push(environment);
+            environments.sort();
+        }
+    }
+
+    pub fn bits(&self) -> &BitVec {
+        &self.bits
+    }
+
+    pub fn into_bits(self) -> BitVec {
+        self.bits
+    }
+
+    /// The environments that set the bit, as their center atom and radius in bonds,
+    /// sorted. Empty if the bit isn't set. [`Mol::atom_environment`] gives the atoms and
+    /// bonds of an environment.
+    pub fn bit_info(&self, bit: usize) -> Vec<(AtomIdx, usize)> {
+        self.info.get(&bit).cloned().unwrap_or_default()
+    }
+}
+
+/// ECFP-like circular fingerprint: each atom's environment up to `radius` bonds away
+/// is hashed into an identifier, which sets bit `identifier % nbits`.
+///
+/// Initial identifiers hash the atom invariants (element, heavy degree, hydrogens,
+/// charge, isotope, ring membership, aromaticity, perceived so that Kekulé and aromatic
+/// forms give the same fingerprint). Each iteration hashes an atom's
+/// identifier with its neighbors' identifiers and bond orders. Environments covering
+/// the same bonds as an environment already seen are dropped.
+pub fn morgan_fingerprint(mol: &Mol, radius: usize, nbits: usize) -> BitVec {
+    let mut fingerprint = BitVec::new(nbits);
+    morgan_environments(mol, radius, nbits, &mut |bit, _, _| fingerprint.set(bit));
+    fingerprint
+}
+
+/// [`morgan_fingerprint`] with the environments that set each bit, as their center
+/// atom and radius.
+pub fn morgan_fingerprint_info(mol: &Mol, radius: usize, nbits: usize) -> Fingerprint {
+    let mut fingerprint = Fingerprint::new(nbits);
+    morgan_environments(mol, radius, nbits, &mut |bit, center, radius| {
+        fingerprint.add(bit, center, radius)
+    });
+    fingerprint
+}
+
+/// Reports the bit, center and radius of each environment of the Morgan fingerprint.
+fn morgan_environments(
+    mol: &Mol,
+    radius: usize,
+    nbits: usize,
+    found: &mut dyn FnMut(usize, NodeIndex, usize),
+) {
+    if nbits == 0 {
+        return;
+    }
+    let mol = &canon::perceived(mol);
+    let graph = &mol.graph;
+    let ring_info = mol.ring_info();
+
+    let mut identifiers: Vec<u64> = graph
+        .node_indices()
+        .map(|node| {
+            let atom = &graph[node];
+            let heavy_degree = graph
+                .neighbors(node)
+                .filter(|n| graph[*n].number != 1)
+                .count();
+            hash(&[
+                atom.number as u64,
+                heavy_degree as u64,
+                atom.total_num_hs() as u64,
+                atom.charge as u64,
+                atom.isotope.unwrap_or(0) as u64,
+                ring_info.is_atom_in_ring(node.into()) as u64,
+                atom.aromatic as u64,
+            ])
+        })
+        .collect();
+    for (node, identifier) in graph.node_indices().zip(&identifiers) {
+        found((identifier % nbits as u64) as usize, node, 0);
+    }
+
+    // bonds covered by each atom's environment, sorted
+    let mut environments: Vec<Vec<usize>> = vec![vec![]; graph.node_count()];
+    let mut seen: Vec<Vec<usize>> = vec![];
+    for iteration in 1..=radius {
+        let mut next = identifiers.clone();
+        let mut next_environments = environments.clone();
+        for node in graph.node_indices() {
+            let mut neighbors: Vec<(u64, u64)> = graph
+                .edges(node)
+                .map(|edge| {
+                    (
+                        bond_code(edge.weight().order),
+                        identifiers[edge.target().index()],
+                    )
+                })
+                .collect();
+            neighbors.sort();
+            let mut values = vec![iteration as u64, identifiers[node.index()]];
+            values.extend(neighbors.iter().flat_map(|(bond, id)| [*bond, *id]));
+            next[node.index()] = hash(&values);
+
+            let environment = &mut next_environments[node.index()];
+            for edge in graph.edges(node) {
+                environment.push(edge.id().index());
+                environment.extend(&environments[edge.target().index()]);
+            }
+            environment.sort();
+            environment.dedup();
+        }
+
+        // one bit per distinct environment, the lowest identifier wins ties
+        let mut order: Vec<NodeIndex> = graph.node_indices().collect();
+        order.sort_by_key(|node| next[node.index()]);
+        for node in order {
+            let environment = &next_environments[node.index()];
+            if environment.is_empty() || seen.contains(environment) {
+                continue;
+            }
+            seen.push(environment.clone());
+            found(
+                (next[node.index()] % nbits as u64) as usize,
+                node,
+                iteration,
+            );
+        }
+        identifiers = next;
+        environments = next_environments;
+    }
+}
+
+/// Daylight-like path fingerprint: every linear path of `min_len` to `max_len` bonds
+/// (without repeated atoms) is hashed, from its atoms' elements and aromaticity and
+/// its bond orders, and sets bit `hash % nbits`. A path and its reverse hash the same.
+/// Aromaticity is perceived first, like for [`morgan_fingerprint`].
+pub fn path_fingerprint(mol: &Mol, min_len: usize, max_len: usize, nbits: usize) -> BitVec {
+    let mut fingerprint = BitVec::new(nbits);
+    if nbits == 0 || min_len > max_len {
+        return fingerprint;
+    }
+    path_environments(mol, min_len, max_len, &mut |hash, _| {
+        fingerprint.set((hash % nbits as u64) as usize)
+    });
+    fingerprint
+}
+
+/// [`path_fingerprint`] with the paths that set each bit, as their first atom and length
+/// in bonds. A path is reported from both of its ends.
+pub fn path_fingerprint_info(
+    mol: &Mol,
+    min_len: usize,
+    max_len: usize,
+    nbits: usize,
+) -> Fingerprint {
+    let mut fingerprint = Fingerprint::new(nbits);
+    if nbits == 0 || min_len > max_len {
+        return fingerprint;
+    }
+    path_environments(mol, min_len, max_len, &mut |hash, path| {
+        fingerprint.add((hash % nbits as u64) as usize, path[0], path.len() - 1)
+    });
+    fingerprint
+}
+
+/// Reports the hash of each path of `min_len` to `max_len` bonds, with the path.
+fn path_environments(
+    mol: &Mol,
+    min_len: usize,
+    max_len: usize,
+    found: &mut dyn FnMut(u64, &[NodeIndex]),
+) {
+    let mol = &canon::perceived(mol);
+    let mut path = vec![];
+    for start in mol.graph.node_indices() {
+        path.push(start);
+        extend_path(mol, &mut path, min_len, max_len, found);
+        path.pop();
+    }
+}
+
+/// Length of the pattern fingerprint.
+pub const PATTERN_BITS: usize = 2048;
+
+/// Longest path, in bonds, hashed into the pattern fingerprint.
+const PATTERN_MAX_LEN: usize = 4;
+
+/// Fingerprint for substructure screening, of [`PATTERN_BITS`] bits: every linear path
+/// of up to 4 bonds (without repeated atoms) is hashed from its atoms' elements. A molecule matching a query has all the bits of the
+/// query's pattern fingerprint, see [`Smarts::pattern_fingerprint`](crate::Smarts).
+pub fn pattern_fingerprint(mol: &Mol) -> BitVec {
+    element_paths(&mol.graph, |atom| Some(atom.number))
+}
+
+/// Pattern fingerprint of a graph whose atoms may have a known element. Paths only go
+/// through atoms with a known element; bond orders are left out, so any bond matches.
+pub(crate) fn element_paths<N, E>(
+    graph: &UnGraph<N, E>,
+    element: impl Fn(&N) -> Option<u32>,
+) -> BitVec {
+    let mut fingerprint = BitVec::new(PATTERN_BITS);
+    let elements: Vec<Option<u32>> = graph.node_weights().map(element).collect();
+    let mut path = vec![];
+    for start in graph.node_indices() {
+        if elements[start.index()].is_some() {
+            path.push(start);
+            extend_element_path(graph, &elements, &mut path, &mut fingerprint);
+            path.pop();
+        }
+    }
+    fingerprint
+}
+
+/// Sets the bit of the path, then extends it with each unvisited neighbor of known element.
+fn extend_element_path<N, E>(
+    graph: &UnGraph<N, E>,
+    elements: &[Option<u32>],
+    path: &mut Vec<NodeIndex>,
+    fingerprint: &mut BitVec,
+) {
+    // unwrap: paths only have atoms with a known element
+    let forward: Vec<u64> = path
+        .iter()
+        .map(|node| elements[node.index()].unwrap() as u64)
+        .collect();
+    let backward: Vec<u64> = forward.iter().rev().copied().collect();
+    fingerprint.set((hash(&forward.min(backward)) % PATTERN_BITS as u64) as usize);
+    if path.len() > PATTERN_MAX_LEN {
+        return;
+    }
+    // unwrap: paths aren't empty
+    let last = *path.last().unwrap();
+    let neighbors: Vec<NodeIndex> = graph.neighbors(last).collect();
+    for neighbor in neighbors {
+        if elements[neighbor.index()].is_none() || path.contains(&neighbor) {
+            continue;
+        }
+        path.push(neighbor);
+        extend_element_path(graph, elements, path, fingerprint);
+        path.pop();
+    }
+}
+
+/// Reports the path if long enough, then extends it with each unvisited neighbor.
+fn extend_path(
+    mol: &Mol,
+    path: &mut Vec<NodeIndex>,
+    min_len: usize,
+    max_len: usize,
+    found: &mut dyn FnMut(u64, &[NodeIndex]),
+) {
+    let len = path.len() - 1;
+    if len >= min_len {
+        found(path_hash(mol, path), path);
+    }
+    if len == max_len {
+        return;
+    }
+    // unwrap: paths aren't empty
+    let last = *path.last().unwrap();
+    let neighbors: Vec<NodeIndex> = mol.graph.neighbors(last).collect();
+    for neighbor in neighbors {
+        if path.contains(&neighbor) {
+            continue;
+        }
+        path.push(neighbor);
+        extend_path(mol, path, min_len, max_len, found);
+        path.pop();
+    }
+}
+
+/// Hash of the path's atoms and bonds, the same in both directions.
+fn path_hash(mol: &Mol, path: &[NodeIndex]) -> u64 {
+    let values = |nodes: &mut dyn Iterator<Item = &NodeIndex>| {
+        let nodes: Vec<NodeIndex> = nodes.copied().collect();
+        let mut values = vec![];
+        for (i, node) in nodes.iter().enumerate() {
+            let atom = &mol.graph[*node];
+            values.push(atom.number as u64 * 2 + atom.aromatic as u64);
+            if let Some(next) = nodes.get(i + 1) {
+                // unwrap: consecutive path atoms are bonded
+                let edge = mol.graph.find_edge(*node, *next).unwrap();
+                values.push(bond_code(mol.graph[edge].order));
+            }
+        }
+        values
+    };
+    let forward = values(&mut path.iter());
+    let backward = values(&mut path.iter().rev());
+    hash(&forward.min(backward))
+}
+
+fn bond_code(order: BondOrder) -> u64 {
+    match order {
+        BondOrder::Single => 1,
+        BondOrder::Double => 2,
+        BondOrder::Triple => 3,
+        BondOrder::Quadruple => 4,
+        BondOrder::Aromatic => 5,
+    }
+}
+
+fn hash(values: &[u64]) -> u64 {
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    fnv1a(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::AtomIdx;
+
+    use super::BitVec;
+
+    fn fingerprint(smiles: &str, radius: usize) -> BitVec {
+        let parser = SmilesParser::new();
+        parser
+            .parse(smiles)
+            .unwrap()
+            .morgan_fingerprint(radius, 2048)
+    }
+
+    fn paths(smiles: &str, min_len: usize, max_len: usize) -> BitVec {
+        let parser = SmilesParser::new();
+        parser
+            .parse(smiles)
+            .unwrap()
+            .path_fingerprint(min_len, max_len, 2048)
+    }
+
+    fn similarity(smiles: &str, other: &str) -> f64 {
+        fingerprint(smiles, 2).tanimoto(&fingerprint(other, 2))
+    }
+
+    #[test]
+    fn bit_vec() {
+        let mut bits = BitVec::new(100);
+        bits.set(3);
+        bits.set(64);
+        bits.set(99);
+
+        assert_eq!(100, bits.len());
+        assert!(bits.get(64));
+        assert!(!bits.get(65));
+        assert!(!bits.get(1000));
+        assert_eq!(3, bits.count_ones());
+        assert_eq!(vec![3, 64, 99], bits.ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn tanimoto() {
+        let mut a = BitVec::new(8);
+        let mut b = BitVec::new(8);
+        a.set(1);
+        a.set(2);
+        b.set(2);
+        b.set(3);
+
+        assert_eq!(1.0 / 3.0, a.tanimoto(&b));
+        assert_eq!(1.0, a.tanimoto(&a));
+        assert_eq!(1.0, BitVec::new(8).tanimoto(&BitVec::new(8)));
+    }
+
+    #[test]
+    fn fingerprint_counts_environments() {
+        // radius 0: one bit per distinct atom type
+        assert_eq!(3, fingerprint("CCO", 0).count_ones());
+        // radius 1 adds the environment of each atom
+        assert_eq!(6, fingerprint("CCO", 1).count_ones());
+        // all atoms of benzene are equivalent
+        assert_eq!(1, fingerprint("c1ccccc1", 0).count_ones());
+        assert_eq!(2, fingerprint("c1ccccc1", 1).count_ones());
+    }
+
+    #[test]
+    fn fingerprint_is_independent_of_atom_order() {
+        assert_eq!(fingerprint("OCC(=O)N", 2), fingerprint("NC(=O)CO", 2));
+        assert_eq!(1.0, similarity("c1ccccc1O", "Oc1ccccc1"));
+    }
+
+    #[test]
+    fn fingerprint_is_independent_of_kekule_form() {
+        assert_eq!(fingerprint("c1ccccc1", 2), fingerprint("C1=CC=CC=C1", 2));
+        assert_eq!(fingerprint("Cc1ccncc1", 2), fingerprint("CC1=CC=NC=C1", 2));
+        assert_eq!(1.0, similarity("c1cc[nH]c1", "C1=CNC=C1"));
+    }
+
+    #[test]
+    fn similar_molecules_are_closer() {
+        let toluene_ethylbenzene = similarity("Cc1ccccc1", "CCc1ccccc1");
+        let toluene_hexane = similarity("Cc1ccccc1", "CCCCCC");
+
+        assert!(toluene_ethylbenzene > toluene_hexane);
+        assert!(toluene_hexane < 0.2);
+    }
+
+    #[test]
+    fn path_fingerprint_counts_paths() {
+        // atoms: C and O
+        assert_eq!(2, paths("CCO", 0, 0).count_ones());
+        // bonds: C-C and C-O
+        assert_eq!(2, paths("CCO", 1, 1).count_ones());
+        // C-C-O, in both directions
+        assert_eq!(1, paths("CCO", 2, 2).count_ones());
+        assert_eq!(5, paths("CCO", 0, 7).count_ones());
+        // ring paths of benzene are all the same up to 5 bonds
+        assert_eq!(5, paths("c1ccccc1", 1, 7).count_ones());
+    }
+
+    #[test]
+    fn morgan_bit_info() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("CCO").unwrap();
+        let fingerprint = mol.morgan_fingerprint_info(1, 2048);
+        assert_eq!(&mol.morgan_fingerprint(1, 2048), fingerprint.bits());
+
+        let mut environments: Vec<(AtomIdx, usize)> = fingerprint
+            .bits()
+            .ones()
+            .flat_map(|bit| fingerprint.bit_info(bit))
+            .collect();
+        environments.sort();
+        let expected: Vec<(AtomIdx, usize)> = [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)]
+            .iter()
+            .map(|(atom, radius)| (AtomIdx::new(*atom), *radius))
+            .collect();
+        assert_eq!(expected, environments);
+        assert!(fingerprint.bit_info(fingerprint.bits().len()).is_empty());
+
+        // each atom sets both bits: the environments cover different bonds
+        let fingerprint = parser
+            .parse("c1ccccc1")
+            .unwrap()
+            .morgan_fingerprint_info(1, 2048);
+        for bit in fingerprint.bits().ones() {
+            assert_eq!(6, fingerprint.bit_info(bit).len());
+        }
+    }
+
+    #[test]
+    fn path_bit_info() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("CCO").unwrap();
+        let fingerprint = mol.path_fingerprint_info(2, 2, 2048);
+        assert_eq!(&mol.path_fingerprint(2, 2, 2048), fingerprint.bits());
+
+        // C-C-O from both ends
+        let bit = fingerprint.bits().ones().next().unwrap();
+        assert_eq!(
+            vec![(AtomIdx::new(0), 2), (AtomIdx::new(2), 2)],
+            fingerprint.bit_info(bit)
+        );
+    }
+
+    #[test]
+    fn path_fingerprint_is_independent_of_atom_order() {
+        assert_eq!(paths("OCC(=O)N", 1, 7), paths("NC(=O)CO", 1, 7));
+        assert_eq!(0, paths("CCO", 3, 2).count_ones());
+        assert_eq!(paths("c1ccccc1O", 1, 7), paths("C1=CC=CC=C1O", 1, 7));
+    }
+}
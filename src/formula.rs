@@ -0,0 +1,251 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::periodic_table::Element;
+use crate::smiles::element_symbol;
+use crate::types::{Atom, Mol};
+
+/// Masses of common isotopes: (atomic number, mass number, mass).
+static ISOTOPE_MASSES: [(u32, u16, f64); 24] = [
+    (1, 1, 1.00782503207),
+    (1, 2, 2.0141017778),
+    (1, 3, 3.0160492777),
+    (6, 11, 11.0114336),
+    (6, 12, 12.0),
+    (6, 13, 13.0033548378),
+    (6, 14, 14.003241989),
+    (7, 14, 14.00307400478),
+    (7, 15, 15.0001088982),
+    (8, 16, 15.99491461956),
+    (8, 17, 16.99913170),
+    (8, 18, 17.9991610),
+    (9, 18, 18.0009380),
+    (9, 19, 18.998403224),
+    (15, 31, 30.973761629),
+    (15, 32, 31.97390727),
+    (16, 32, 31.972070999),
+    (16, 34, 33.96786690),
+    (16, 35, 34.96903216),
+    (17, 35, 34.968852682),
+    (17, 37, 36.96590259),
+    (35, 79, 78.9183371),
+    (35, 81, 80.9162906),
+    (53, 131, 130.9061246),
+];
+
+/// Average and monoisotopic mass of an element.
+pub fn masses(number: u32) -> Option<(f64, f64)> {
+    Element::from_number(number).map(|element| (element.average_mass, element.monoisotopic_mass))
+}
+
+/// Mass of a specific isotope. Falls back to the mass number for isotopes not in the table.
+fn isotope_mass(number: u32, isotope: u16) -> f64 {
+    ISOTOPE_MASSES
+        .iter()
+        .find(|(n, i, _)| *n == number && *i == isotope)
+        .map(|(_, _, mass)| *mass)
+        .unwrap_or(isotope as f64)
+}
+
+fn hydrogen_masses() -> (f64, f64) {
+    // unwrap: hydrogen is in the table
+    masses(1).unwrap()
+}
+
+/// Molecular formula in Hill order: C, then H, then the other elements alphabetically.
+/// Without carbon, all elements are alphabetical. Isotopes are counted apart, after their
+/// element, e.g. `CH3D` or `[13C]H4`. A net charge is appended, e.g. `H4N+`.
+pub fn molecular_formula(mol: &Mol) -> String {
+    let mut formula = hill_formula(mol, true);
+    match net_charge(mol) {
+        0 => {}
+        1 => formula.push('+'),
+        -1 => formula.push('-'),
+        charge if charge > 0 => formula.push_str(&format!("+{}", charge)),
+        charge => formula.push_str(&charge.to_string()),
+    }
+    formula
+}
+
+/// Sum of the formal charges.
+pub fn net_charge(mol: &Mol) -> i32 {
+    mol.graph
+        .node_weights()
+        .map(|atom| atom.charge as i32)
+        .sum()
+}
+
+/// Molecular formula in Hill order, without charge and isotopes.
+pub fn element_formula(mol: &Mol) -> String {
+    hill_formula(mol, false)
+}
+
+fn hill_formula(mol: &Mol, separate_isotopes: bool) -> String {
+    // by symbol, then isotope (none first)
+    let mut counts: BTreeMap<(&str, Option<u16>), usize> = BTreeMap::new();
+    for atom in mol.graph.node_weights() {
+        if let Some(symbol) = element_symbol(atom.number) {
+            let isotope = atom.isotope.filter(|_| separate_isotopes);
+            *counts.entry((symbol, isotope)).or_default() += 1;
+        }
+        let hs = atom.total_num_hs() as usize;
+        if hs > 0 {
+            *counts.entry(("H", None)).or_default() += hs;
+        }
+    }
+
+    let mut formula = String::new();
+    let mut append = |(symbol, isotope): (&str, Option<u16>), count: usize| {
+        match (symbol, isotope) {
+            (_, None) => formula.push_str(symbol),
+            ("H", Some(2)) => formula.push('D'),
+            ("H", Some(3)) => formula.push('T'),
+            (_, Some(isotope)) => formula.push_str(&format!("[{}{}]", isotope, symbol)),
+        }
+        if count > 1 {
+            formula.push_str(&count.to_string());
+        }
+    };
+    if counts.keys().any(|(symbol, _)| *symbol == "C") {
+        for first in ["C", "H"] {
+            let element: Vec<_> = counts
+                .keys()
+                .filter(|(symbol, _)| *symbol == first)
+                .copied()
+                .collect();
+            for key in element {
+                // unwrap: key comes from the map
+                append(key, counts.remove(&key).unwrap());
+            }
+        }
+    }
+    for (key, count) in counts {
+        append(key, count);
+    }
+    formula
+}
+
+/// Average molecular weight, from standard atomic weights (isotope masses for isotopes).
+pub fn molecular_weight(mol: &Mol) -> f64 {
+    mol.graph
+        .node_weights()
+        .map(|atom| {
+            atom_mass(atom, |(average, _)| average)
+                + atom.total_num_hs() as f64 * hydrogen_masses().0
+        })
+        .sum()
+}
+
+/// Monoisotopic mass, from the most abundant isotope of each element (isotope masses for isotopes).
+pub fn exact_mass(mol: &Mol) -> f64 {
+    mol.graph
+        .node_weights()
+        .map(|atom| {
+            atom_mass(atom, |(_, monoisotopic)| monoisotopic)
+                + atom.total_num_hs() as f64 * hydrogen_masses().1
+        })
+        .sum()
+}
+
+fn atom_mass(atom: &Atom, select: impl Fn((f64, f64)) -> f64) -> f64 {
+    match atom.isotope {
+        Some(isotope) => isotope_mass(atom.number, isotope),
+        None => masses(atom.number).map(select).unwrap_or(0.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() < 1e-3,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn formula_hill_order() {
+        assert_eq!("CH4", mol("C").molecular_formula());
+        assert_eq!("C2H6O", mol("CCO").molecular_formula());
+        assert_eq!("C6H6", mol("c1ccccc1").molecular_formula());
+        assert_eq!("C7H6O3", mol("OC(=O)c1ccccc1O").molecular_formula());
+        assert_eq!("CH3Br", mol("CBr").molecular_formula());
+        assert_eq!("C2H3ClO", mol("CC(Cl)=O").molecular_formula());
+    }
+
+    #[test]
+    fn formula_without_carbon() {
+        assert_eq!("H2O", mol("O").molecular_formula());
+        assert_eq!("H2O4S", mol("OS(=O)(=O)O").molecular_formula());
+        assert_eq!("ClNa", mol("[Na]Cl").molecular_formula());
+    }
+
+    #[test]
+    fn formula_charge() {
+        assert_eq!("H4N+", mol("[NH4+]").molecular_formula());
+        assert_eq!("C2H3O2-", mol("CC(=O)[O-]").molecular_formula());
+        assert_eq!("Fe+2", mol("[Fe+2]").molecular_formula());
+    }
+
+    #[test]
+    fn formula_wildcards() {
+        assert_eq!("C2H4", mol("*CC*").molecular_formula());
+        assert_close(28.054, mol("*CC*").molecular_weight());
+    }
+
+    #[test]
+    fn net_charge() {
+        assert_eq!(0, mol("CCO").net_charge());
+        assert_eq!(1, mol("C[NH3+]").net_charge());
+        assert_eq!(-1, mol("CC(=O)[O-]").net_charge());
+        assert_eq!(0, mol("[Na+].[Cl-]").net_charge());
+        assert_eq!(0, mol("[Ca++].[O-]C(=O)C.CC(=O)[O-]").net_charge());
+        assert_eq!(-2, mol("[O--]").net_charge());
+        assert_eq!(3, mol("[Fe+3]").net_charge());
+    }
+
+    #[test]
+    fn weight() {
+        assert_close(16.043, mol("C").molecular_weight());
+        assert_close(46.069, mol("CCO").molecular_weight());
+        assert_close(78.114, mol("c1ccccc1").molecular_weight());
+        assert_close(180.159, mol("CC(=O)Oc1ccccc1C(=O)O").molecular_weight());
+    }
+
+    #[test]
+    fn exact_mass() {
+        assert_close(16.0313, mol("C").exact_mass());
+        assert_close(46.0419, mol("CCO").exact_mass());
+        assert_close(180.0423, mol("CC(=O)Oc1ccccc1C(=O)O").exact_mass());
+    }
+
+    #[test]
+    fn isotopes() {
+        assert_close(17.0346, mol("[13CH4]").exact_mass());
+        assert_close(17.0346, mol("[13CH4]").molecular_weight());
+        assert_close(20.0231, mol("[2H]O[2H]").exact_mass());
+        assert_close(20.0231, mol("[D]O[D]").exact_mass());
+    }
+
+    #[test]
+    fn formula_isotopes() {
+        assert_eq!("[13C]H4", mol("[13CH4]").molecular_formula());
+        assert_eq!("C[13C]H6", mol("C[13CH3]").molecular_formula());
+        assert_eq!("CH3D", mol("[2H]C").molecular_formula());
+        assert_eq!("D2O", mol("[D]O[D]").molecular_formula());
+        assert_eq!("C2H5TO", mol("CCO[T]").molecular_formula());
+        assert_eq!("Cl[37Cl]", mol("Cl[37Cl]").molecular_formula());
+    }
+}
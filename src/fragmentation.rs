@@ -0,0 +1,188 @@
+use std::sync::OnceLock;
+
+use petgraph::graph::NodeIndex;
+
+use crate::fragments::{fragments, replace_neighbor};
+use crate::smarts::Smarts;
+use crate::smiles::WILDCARD;
+use crate::types::{Atom, Bond, Mol};
+
+/// Which bonds [`Mol::fragment`] breaks.
+#[derive(Debug, Clone, Default)]
+pub enum FragmentRules {
+    /// The bond types of RECAP (Lewell et al., 1998): amide, ester, amine, urea, ether,
+    /// olefin, quaternary nitrogen, aromatic nitrogen to aliphatic carbon, lactam
+    /// nitrogen to aliphatic carbon, aromatic carbon to aromatic carbon and sulfonamide.
+    #[default]
+    Recap,
+    /// The bonds between the first two atoms of the matches of these SMARTS, e.g.
+    /// `[#6]!@[#6]` for the single bonds between carbons outside rings.
+    Custom(Vec<Smarts>),
+}
+
+/// RECAP's bond types, broken between the first two atoms, outside rings.
+static RECAP: [&str; 11] = [
+    // amide
+    "[NX3;+0]!@[CX3](=O)[#6]",
+    // ester
+    "[OX2;+0]!@[CX3](=O)[#6]",
+    // amine
+    "[CX4]!@[NX3;+0;!$(NC=[O,S,N]);!$(NS=O)]",
+    // urea
+    "[NX3;+0]!@[CX3](=O)[NX3;+0]",
+    // ether
+    "[#6;!$(C=O)]!@[OX2;+0;!$(OC=O)][#6]",
+    // olefin
+    "[CX3]=!@[CX3]",
+    // quaternary nitrogen
+    "[NX4+]!@[CX4]",
+    // aromatic nitrogen to aliphatic carbon
+    "n!@[CX4]",
+    // lactam nitrogen to aliphatic carbon
+    "[NX3;R;$(N@[C;R]=O)]!@[CX4]",
+    // aromatic carbon to aromatic carbon
+    "c!@c",
+    // sulfonamide
+    "[NX3;+0]!@[SX4](=O)=O",
+];
+
+static RECAP_QUERIES: OnceLock<Vec<Smarts>> = OnceLock::new();
+
+/// The fragments left when all the bonds of the rules are broken, ordered by their first
+/// atom. Each broken bond becomes two attachment points, `*` atoms bonded like the atoms
+/// were, e.g. `CC(=O)*` and `N(C)*` for `CC(=O)NC`. Ring bonds aren't broken.
+pub fn fragment(mol: &Mol, rules: &FragmentRules) -> Vec<Mol> {
+    let queries = match rules {
+        FragmentRules::Recap => RECAP_QUERIES.get_or_init(|| {
+            RECAP
+                .iter()
+                // unwrap: the SMARTS of the rules are valid
+                .map(|smarts| Smarts::parse(smarts).unwrap())
+                .collect()
+        }),
+        FragmentRules::Custom(queries) => queries,
+    };
+
+    let ring_info = mol.ring_info();
+    let mut broken: Vec<(NodeIndex, NodeIndex)> = vec![];
+    for query in queries {
+        for atoms in mol.substruct_matches(query) {
+            let [a, b, ..] = atoms[..] else {
+                continue;
+            };
+            let (a, b) = (NodeIndex::from(a), NodeIndex::from(b));
+            let Some(edge) = mol.graph.find_edge(a, b) else {
+                continue;
+            };
+            if !ring_info.is_bond_in_ring(edge.into()) && !broken.contains(&(b, a)) {
+                broken.push((a, b));
+            }
+        }
+    }
+    broken.sort();
+    broken.dedup();
+
+    let mut mol = mol.clone();
+    for (a, b) in broken {
+        // unwrap: bonds are only broken once
+        let edge = mol.graph.find_edge(a, b).unwrap();
+        let bond = mol.graph.remove_edge(edge).unwrap();
+        let point_a = attach_point(&mut mol, a, b, &bond);
+        let point_b = attach_point(&mut mol, b, a, &bond);
+        replace_neighbor(&mut mol, a, b, point_a);
+        replace_neighbor(&mut mol, b, a, point_b);
+    }
+    fragments(&mol)
+}
+
+/// Adds an attachment point bonded to the atom like the bond it replaces.
+fn attach_point(mol: &mut Mol, atom: NodeIndex, other: NodeIndex, bond: &Bond) -> NodeIndex {
+    let point = mol.graph.add_node(Atom {
+        number: WILDCARD,
+        ..Default::default()
+    });
+    let bond = Bond {
+        stereo: None,
+        ..bond.clone()
+    };
+    // the point takes the place of the other atom, which the bond may go from
+    let (start, end) = if atom < other {
+        (atom, point)
+    } else {
+        (point, atom)
+    };
+    mol.graph.add_edge(start, end, bond);
+    point
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smarts::Smarts;
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    use super::FragmentRules;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn fragments(smiles: &str, rules: &FragmentRules) -> Vec<String> {
+        mol(smiles)
+            .fragment(rules)
+            .iter()
+            .map(Mol::to_smiles)
+            .collect()
+    }
+
+    fn recap(smiles: &str) -> Vec<String> {
+        fragments(smiles, &FragmentRules::Recap)
+    }
+
+    #[test]
+    fn recap_bonds() {
+        assert_eq!(vec!["CC(=O)*", "N(C)*"], recap("CC(=O)NC"));
+        assert_eq!(vec!["CC(=O)*", "O(C)*"], recap("CC(=O)OC"));
+        assert_eq!(vec!["c1ccccc1*", "c1(ccccc1)*"], recap("c1ccccc1-c1ccccc1"));
+        assert_eq!(vec!["CC=*", "C(C)=*"], recap("CC=CC"));
+        assert_eq!(vec!["CS(=O)(=O)*", "N(C)*"], recap("CS(=O)(=O)NC"));
+        assert_eq!(vec!["c1ccn(c1)*", "C*"], recap("c1ccn(C)c1"));
+        assert_eq!(vec!["CC*", "N(*)*", "C(C)*"], recap("CCNCC"));
+    }
+
+    #[test]
+    fn no_recap_bonds() {
+        assert_eq!(vec!["CCCC"], recap("CCCC"));
+        // ring bonds stay
+        assert_eq!(vec!["C1CCOC1"], recap("C1CCOC1"));
+        assert_eq!(vec!["O=C1CCCN1"], recap("O=C1CCCN1"));
+    }
+
+    #[test]
+    fn fragment_drug() {
+        // paracetamol: the amide
+        assert_eq!(
+            vec!["CC(=O)*", "N(c1ccc(O)cc1)*"],
+            recap("CC(=O)Nc1ccc(O)cc1")
+        );
+    }
+
+    #[test]
+    fn fragments_keep_stereo() {
+        assert_eq!(
+            vec!["F[C@@H](C)C(=O)*", "N(C)*"],
+            recap("F[C@@H](C)C(=O)NC")
+        );
+    }
+
+    #[test]
+    fn custom_rules() {
+        let rules = FragmentRules::Custom(vec![Smarts::parse("[#6]!@[#8]").unwrap()]);
+
+        assert_eq!(
+            vec!["C*", "O(*)*", "C(C*)*", "O*"],
+            fragments("COCCO", &rules)
+        );
+    }
+}
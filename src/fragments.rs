@@ -0,0 +1,160 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{NodeIndex, UnGraph};
+use petgraph::visit::EdgeRef;
+
+use crate::types::{Atom, AtomIdx, Bond, Mol};
+
+/// Atoms of each connected fragment, in increasing order, fragments ordered by their
+/// first atom.
+pub fn fragment_atoms(mol: &Mol) -> Vec<Vec<AtomIdx>> {
+    let graph = &mol.graph;
+    let mut fragment_of: Vec<Option<usize>> = vec![None; graph.node_count()];
+    let mut fragments: Vec<Vec<AtomIdx>> = vec![];
+    for root in graph.node_indices() {
+        if fragment_of[root.index()].is_some() {
+            continue;
+        }
+        let fragment = fragments.len();
+        let mut atoms = vec![];
+        let mut stack = vec![root];
+        fragment_of[root.index()] = Some(fragment);
+        while let Some(node) = stack.pop() {
+            atoms.push(AtomIdx::from(node));
+            for neighbor in graph.neighbors(node) {
+                if fragment_of[neighbor.index()].is_none() {
+                    fragment_of[neighbor.index()] = Some(fragment);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        atoms.sort();
+        fragments.push(atoms);
+    }
+    fragments
+}
+
+/// Each fragment as its own molecule, keeping the atoms' relative order, so that
+/// chirality and bond stereo stay valid.
+pub fn fragments(mol: &Mol) -> Vec<Mol> {
+    fragment_atoms(mol)
+        .iter()
+        .map(|atoms| extract(mol, atoms))
+        .collect()
+}
+
+/// The molecule made of the given atoms and the bonds between them, in the given order.
+/// Chirality and bond stereo that involve other atoms are cleared.
+pub fn extract(mol: &Mol, atoms: &[AtomIdx]) -> Mol {
+    let graph = &mol.graph;
+    let mut new_index: Vec<Option<AtomIdx>> = vec![None; graph.node_count()];
+    for (i, atom) in atoms.iter().enumerate() {
+        new_index[atom.index()] = Some(AtomIdx::new(i));
+    }
+    let remap = |atom: AtomIdx| new_index[atom.index()];
+
+    let mut fragment = UnGraph::<Atom, Bond>::new_undirected();
+    for atom in atoms {
+        let mut atom = graph[NodeIndex::from(*atom)].clone();
+        let neighbors: Option<Vec<AtomIdx>> =
+            atom.chiral_neighbors.iter().map(|n| remap(*n)).collect();
+        match neighbors {
+            Some(neighbors) => atom.chiral_neighbors = neighbors,
+            None => {
+                atom.chirality = None;
+                atom.chiral_neighbors = vec![];
+            }
+        }
+        fragment.add_node(atom);
+    }
+    for edge in graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(edge).unwrap();
+        let (Some(a), Some(b)) = (new_index[a.index()], new_index[b.index()]) else {
+            continue;
+        };
+        let mut bond = graph[edge].clone();
+        bond.stereo = bond.stereo.and_then(|mut stereo| {
+            stereo.start_neighbor = remap(stereo.start_neighbor)?;
+            stereo.end_neighbor = remap(stereo.end_neighbor)?;
+            Some(stereo)
+        });
+        fragment.add_edge(a.into(), b.into(), bond);
+    }
+    Mol { graph: fragment }
+}
+
+/// Replaces a neighbor of the atom by another atom in its chirality and the stereo of its
+/// bonds, e.g. when a bond moves to another atom.
+pub(crate) fn replace_neighbor(
+    mol: &mut Mol,
+    atom: NodeIndex,
+    neighbor: NodeIndex,
+    replacement: NodeIndex,
+) {
+    let (neighbor, replacement) = (AtomIdx::from(neighbor), AtomIdx::from(replacement));
+    for n in &mut mol.graph[atom].chiral_neighbors {
+        if *n == neighbor {
+            *n = replacement;
+        }
+    }
+    let edges: Vec<_> = mol.graph.edges(atom).map(|edge| edge.id()).collect();
+    for edge in edges {
+        if let Some(stereo) = &mut mol.graph[edge].stereo {
+            for n in [&mut stereo.start_neighbor, &mut stereo.end_neighbor] {
+                if *n == neighbor {
+                    *n = replacement;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    #[test]
+    fn count_fragments() {
+        assert_eq!(1, mol("CCO").num_fragments());
+        assert_eq!(2, mol("[Na+].[Cl-]").num_fragments());
+        assert_eq!(3, mol("C1CC1.O.N").num_fragments());
+        // bonded through a ring closure across the dot
+        assert_eq!(1, mol("C1.C1").num_fragments());
+    }
+
+    #[test]
+    fn split_fragments() {
+        let fragments: Vec<String> = mol("CCO.O.[Na+]")
+            .fragments()
+            .iter()
+            .map(Mol::to_smiles)
+            .collect();
+
+        assert_eq!(vec!["CCO", "O", "[Na+]"], fragments);
+    }
+
+    #[test]
+    fn fragments_keep_stereo() {
+        let fragments = mol("O.N[C@@H](C)C(=O)O.F/C=C/F").fragments();
+
+        assert_eq!("N[C@@H](C)C(=O)O", fragments[1].to_smiles());
+        assert_eq!("F/C=C/F", fragments[2].to_smiles());
+    }
+
+    #[test]
+    fn disconnected_smiles_round_trip() {
+        assert_eq!("CCO.O", mol("CCO.O").to_smiles());
+        assert_eq!(
+            mol("O.CCO").to_canonical_smiles(),
+            mol("CCO.O").to_canonical_smiles()
+        );
+    }
+}
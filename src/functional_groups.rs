@@ -0,0 +1,203 @@
+use std::sync::OnceLock;
+
+use crate::smarts::Smarts;
+use crate::types::{AtomIdx, Mol};
+
+/// A named functional group and the SMARTS that finds it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionalGroup {
+    pub name: &'static str,
+    pub smarts: &'static str,
+}
+
+impl FunctionalGroup {
+    /// The functional group with the name, e.g. `"carboxylic acid"`.
+    pub fn from_name(name: &str) -> Option<&'static FunctionalGroup> {
+        FUNCTIONAL_GROUPS.iter().find(|group| group.name == name)
+    }
+
+    /// All the functional groups that [`Mol::functional_groups`] looks for.
+    pub fn all() -> &'static [FunctionalGroup] {
+        &FUNCTIONAL_GROUPS
+    }
+
+    /// The parsed SMARTS.
+    pub fn query(&self) -> &'static Smarts {
+        let queries = QUERIES.get_or_init(|| {
+            FUNCTIONAL_GROUPS
+                .iter()
+                // unwrap: the SMARTS of the library are valid
+                .map(|group| Smarts::parse(group.smarts).unwrap())
+                .collect()
+        });
+        // unwrap: groups come from the library
+        let index = FUNCTIONAL_GROUPS
+            .iter()
+            .position(|group| group == self)
+            .unwrap();
+        &queries[index]
+    }
+}
+
+/// A match of a functional group: the molecule's atoms, in the order of the SMARTS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionalGroupHit {
+    pub name: &'static str,
+    pub atoms: Vec<AtomIdx>,
+}
+
+static FUNCTIONAL_GROUPS: [FunctionalGroup; 22] = [
+    group("carboxylic acid", "[CX3](=O)[OX2H1]"),
+    group("carboxylate", "[CX3](=O)[OX1-]"),
+    group("ester", "[#6][CX3](=O)[OX2H0][#6]"),
+    group("amide", "[CX3](=O)[NX3]"),
+    group("acyl halide", "[CX3](=O)[F,Cl,Br,I]"),
+    group("aldehyde", "[CX3H1](=O)[#6]"),
+    group("ketone", "[#6][CX3](=O)[#6]"),
+    group("alcohol", "[OX2H][CX4]"),
+    group("phenol", "[OX2H]c"),
+    group("ether", "[OD2]([#6;!$(C=O)])[#6;!$(C=O)]"),
+    group("primary amine", "[NX3;H2;!$(NC=[O,S,N]);!$(NS=O)][#6]"),
+    group(
+        "secondary amine",
+        "[NX3;H1;!$(NC=[O,S,N]);!$(NS=O)]([#6])[#6]",
+    ),
+    group(
+        "tertiary amine",
+        "[NX3;H0;+0;!$(NC=[O,S,N]);!$(NS=O);!$(N~O)]([#6])([#6])[#6]",
+    ),
+    group("imine", "[CX3]=[NX2][#6,#1]"),
+    group("nitrile", "[NX1]#[CX2]"),
+    group("nitro", "[$([NX3](=O)=O),$([NX3+](=O)[O-])](~[OX1])~[OX1]"),
+    group("halide", "[#6][F,Cl,Br,I]"),
+    group("thiol", "[SX2H][#6]"),
+    group("sulfonamide", "[SX4](=O)(=O)[NX3]"),
+    group("sulfonic acid", "[SX4](=O)(=O)[OX2H]"),
+    group("alkene", "[CX3]=[CX3]"),
+    group("alkyne", "[CX2]#[CX2]"),
+];
+
+static QUERIES: OnceLock<Vec<Smarts>> = OnceLock::new();
+
+const fn group(name: &'static str, smarts: &'static str) -> FunctionalGroup {
+    FunctionalGroup { name, smarts }
+}
+
+/// The matches of all the functional groups, in the order of [`FunctionalGroup::all`].
+/// Groups can overlap, e.g. an acyl chloride is also a halide.
+pub fn functional_groups(mol: &Mol) -> Vec<FunctionalGroupHit> {
+    let mut hits = vec![];
+    for group in FunctionalGroup::all() {
+        for atoms in mol.substruct_matches(group.query()) {
+            hits.push(FunctionalGroupHit {
+                name: group.name,
+                atoms,
+            });
+        }
+    }
+    hits
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, Mol};
+
+    use super::{FunctionalGroup, FunctionalGroupHit};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn groups(smiles: &str) -> Vec<&'static str> {
+        let mut names: Vec<&str> = mol(smiles)
+            .functional_groups()
+            .iter()
+            .map(|hit| hit.name)
+            .collect();
+        names.dedup();
+        names
+    }
+
+    #[test]
+    fn library_parses() {
+        for group in FunctionalGroup::all() {
+            assert!(group.query().num_atoms() > 0, "{}", group.name);
+        }
+        assert_eq!(
+            "[OX2H]c",
+            FunctionalGroup::from_name("phenol").unwrap().smarts
+        );
+        assert_eq!(None, FunctionalGroup::from_name("phlogiston"));
+    }
+
+    #[test]
+    fn hit_atoms() {
+        let hits = mol("OCCC(=O)O").functional_groups();
+
+        assert_eq!(
+            vec![
+                FunctionalGroupHit {
+                    name: "carboxylic acid",
+                    atoms: [3, 4, 5].map(AtomIdx::new).to_vec(),
+                },
+                FunctionalGroupHit {
+                    name: "alcohol",
+                    atoms: [0, 1].map(AtomIdx::new).to_vec(),
+                },
+            ],
+            hits
+        );
+    }
+
+    #[test]
+    fn oxygen_groups() {
+        assert_eq!(vec!["carboxylic acid"], groups("CC(=O)O"));
+        assert_eq!(vec!["carboxylate"], groups("CC(=O)[O-]"));
+        assert_eq!(vec!["ester"], groups("CC(=O)OC"));
+        assert_eq!(vec!["aldehyde"], groups("CC=O"));
+        assert_eq!(vec!["ketone"], groups("CC(=O)C"));
+        assert_eq!(vec!["alcohol"], groups("CCO"));
+        assert_eq!(vec!["phenol"], groups("Oc1ccccc1"));
+        assert_eq!(vec!["ether"], groups("CCOCC"));
+        assert_eq!(vec!["ether"], groups("COc1ccccc1"));
+    }
+
+    #[test]
+    fn nitrogen_groups() {
+        assert_eq!(vec!["amide"], groups("CC(=O)NC"));
+        assert_eq!(vec!["primary amine"], groups("CCN"));
+        assert_eq!(vec!["primary amine"], groups("Nc1ccccc1"));
+        assert_eq!(vec!["secondary amine"], groups("CNC"));
+        assert_eq!(vec!["tertiary amine"], groups("CN(C)C"));
+        assert_eq!(vec!["imine"], groups("CC=NC"));
+        assert_eq!(vec!["nitrile"], groups("CC#N"));
+        assert_eq!(vec!["nitro"], groups("C[N+](=O)[O-]"));
+        assert_eq!(vec!["nitro"], groups("CN(=O)=O"));
+        assert_eq!(vec!["sulfonamide"], groups("CS(=O)(=O)N"));
+    }
+
+    #[test]
+    fn other_groups() {
+        assert_eq!(vec!["acyl halide", "halide"], groups("CC(=O)Cl"));
+        assert_eq!(vec!["halide"], groups("ClCCBr"));
+        assert_eq!(vec!["thiol"], groups("CCS"));
+        assert_eq!(vec!["sulfonic acid"], groups("CS(=O)(=O)O"));
+        assert_eq!(vec!["alkene"], groups("CC=CC"));
+        assert_eq!(vec!["alkyne"], groups("CC#CC"));
+        assert!(groups("CCCC").is_empty());
+        assert!(groups("c1ccccc1").is_empty());
+    }
+
+    #[test]
+    fn drug_groups() {
+        // aspirin
+        assert_eq!(
+            vec!["carboxylic acid", "ester"],
+            groups("CC(=O)Oc1ccccc1C(=O)O")
+        );
+        // paracetamol
+        assert_eq!(vec!["amide", "phenol"], groups("CC(=O)Nc1ccc(O)cc1"));
+    }
+}
@@ -0,0 +1,278 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{NodeIndex, UnGraph};
+
+use crate::types::{Atom, AtomIdx, Bond, Mol};
+use crate::valence;
+
+/// The molecule with each hydrogen count turned into hydrogen atoms, added after the
+/// other atoms. A stereocenter's hydrogen becomes its first chiral neighbor, which keeps
+/// the chirality.
+pub fn add_hs(mol: &Mol) -> Mol {
+    let mut graph = mol.graph.clone();
+    for node in mol.graph.node_indices() {
+        let count = mol.graph[node].total_num_hs();
+        let mut hydrogens = vec![];
+        for _ in 0..count {
+            let hydrogen = graph.add_node(Atom {
+                number: 1,
+                explicit_h: Some(0),
+                ..Default::default()
+            });
+            graph.add_edge(node, hydrogen, Bond::default());
+            hydrogens.push(hydrogen.into());
+        }
+
+        let atom = &mut graph[node];
+        if atom.chirality.is_some() {
+            hydrogens.append(&mut atom.chiral_neighbors);
+            atom.chiral_neighbors = hydrogens;
+        }
+        if atom.explicit_h.is_some() {
+            atom.explicit_h = Some(0);
+        }
+    }
+    with_implicit_hs(graph)
+}
+
+/// The molecule without its hydrogen atoms, which are counted on their neighbor instead.
+/// Hydrogens that can't be folded are kept: charged, isotopes, bonded to another hydrogen
+/// or to several atoms, and those needed for stereo (e.g. the only neighbor giving the
+/// cis/trans configuration of a double bond).
+pub fn remove_hs(mol: &Mol) -> Mol {
+    remove_hs_of(mol, &vec![true; mol.graph.node_count()])
+}
+
+/// Like [`remove_hs`], only for the hydrogens among the atoms.
+pub(crate) fn remove_hs_of(mol: &Mol, atoms: &[bool]) -> Mol {
+    let graph = &mol.graph;
+    let mut removed: Vec<bool> = graph
+        .node_indices()
+        .map(|node| {
+            let atom = &graph[node];
+            let mut neighbors = graph.neighbors(node);
+            atoms[node.index()]
+                && atom.number == 1
+                && atom.isotope.is_none()
+                && atom.charge == 0
+                && matches!(
+                    (neighbors.next(), neighbors.next()),
+                    (Some(neighbor), None) if graph[neighbor].number != 1
+                )
+        })
+        .collect();
+
+    let mut graph = graph.clone();
+
+    // cis/trans relative to a removed hydrogen: use the other neighbor, if there's one
+    for edge in graph.edge_indices() {
+        let Some(mut stereo) = graph[edge].stereo else {
+            continue;
+        };
+        // unwrap: edge comes from the graph
+        let (start, end) = graph.edge_endpoints(edge).unwrap();
+        let (start, end) = (AtomIdx::from(start), AtomIdx::from(end));
+        for (atom, other_end, neighbor) in [
+            (start, end, &mut stereo.start_neighbor),
+            (end, start, &mut stereo.end_neighbor),
+        ] {
+            if !removed[neighbor.index()] {
+                continue;
+            }
+            let replacement = graph
+                .neighbors(atom.into())
+                .map(AtomIdx::from)
+                .find(|n| *n != other_end && *n != *neighbor && !removed[n.index()]);
+            match replacement {
+                Some(replacement) => {
+                    *neighbor = replacement;
+                    stereo.config = stereo.config.inverted();
+                }
+                None => removed[neighbor.index()] = false,
+            }
+        }
+        graph[edge].stereo = Some(stereo);
+    }
+
+    // a stereocenter's removed hydrogen has to become its implicit first neighbor
+    for node in graph.node_indices() {
+        let atom = &graph[node];
+        let Some(chirality) = atom.chirality else {
+            continue;
+        };
+        let hydrogens: Vec<AtomIdx> = atom
+            .chiral_neighbors
+            .iter()
+            .copied()
+            .filter(|n| removed[n.index()])
+            .collect();
+        if hydrogens.is_empty() {
+            continue;
+        }
+        if hydrogens.len() > 1 || atom.total_num_hs() > 0 {
+            // not a real stereocenter, keep the hydrogens as they are
+            for hydrogen in hydrogens {
+                removed[hydrogen.index()] = false;
+            }
+            continue;
+        }
+        let hydrogen = hydrogens[0];
+        let mut order = vec![hydrogen];
+        order.extend(atom.chiral_neighbors.iter().filter(|n| **n != hydrogen));
+        let Some(chirality) = chirality.reordered(&atom.chiral_neighbors, &order) else {
+            // a neighbor bonded twice, not a real stereocenter either
+            removed[hydrogen.index()] = false;
+            continue;
+        };
+        let atom = &mut graph[node];
+        atom.chirality = Some(chirality);
+        atom.chiral_neighbors = order[1..].to_vec();
+    }
+
+    let mut new_index: Vec<Option<AtomIdx>> = vec![None; graph.node_count()];
+    let mut count = 0;
+    for (node, index) in new_index.iter_mut().enumerate() {
+        if !removed[node] {
+            *index = Some(AtomIdx::new(count));
+            count += 1;
+        }
+    }
+    let mut folded = vec![0u8; graph.node_count()];
+    for edge in graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(edge).unwrap();
+        if removed[a.index()] {
+            folded[b.index()] += 1;
+        } else if removed[b.index()] {
+            folded[a.index()] += 1;
+        }
+    }
+
+    // unwrap: removed atoms don't appear in kept atoms and bonds
+    let remap = |atom: AtomIdx| new_index[atom.index()].unwrap();
+    let graph: UnGraph<Atom, Bond> = graph.filter_map(
+        |node, atom| {
+            new_index[node.index()]?;
+            let mut atom = atom.clone();
+            atom.chiral_neighbors = atom.chiral_neighbors.iter().map(|n| remap(*n)).collect();
+            if atom.explicit_h.is_some() || atom.chirality.is_some() {
+                atom.explicit_h = Some(atom.total_num_hs() + folded[node.index()]);
+                atom.implicit_h = 0;
+            }
+            Some(atom)
+        },
+        // bonds to removed atoms are dropped with them
+        |_, bond| {
+            let mut bond = bond.clone();
+            if let Some(stereo) = &mut bond.stereo {
+                stereo.start_neighbor = remap(stereo.start_neighbor);
+                stereo.end_neighbor = remap(stereo.end_neighbor);
+            }
+            Some(bond)
+        },
+    );
+
+    let mut mol = Mol { graph };
+    for (node, index) in new_index.iter().enumerate() {
+        let Some(index) = index else {
+            continue;
+        };
+        let index = NodeIndex::from(*index);
+        if mol.graph[index].explicit_h.is_some() {
+            continue;
+        }
+        // written in brackets if the valence model doesn't give the hydrogens
+        let hydrogens = mol.graph[index].implicit_h + folded[node];
+        let implicit_h = valence::implicit_h_count(&mol, index);
+        let atom = &mut mol.graph[index];
+        if implicit_h == hydrogens {
+            atom.implicit_h = hydrogens;
+        } else {
+            atom.explicit_h = Some(hydrogens);
+            atom.implicit_h = 0;
+        }
+    }
+    mol
+}
+
+fn with_implicit_hs(graph: UnGraph<Atom, Bond>) -> Mol {
+    let mut mol = Mol { graph };
+    for node in mol.graph.node_indices() {
+        mol.graph[node].implicit_h = valence::implicit_h_count(&mol, node);
+    }
+    mol
+}
+
+#[cfg(test)]
+mod test {
+    use crate::assert_same_mol;
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    #[test]
+    fn add_hydrogens() {
+        let mol = mol("CCO").add_hs();
+
+        assert_eq!(9, mol.num_atoms());
+        assert_eq!(8, mol.num_bonds());
+        assert!(mol
+            .graph
+            .node_weights()
+            .all(|atom| atom.total_num_hs() == 0));
+        assert_eq!("C2H6O", mol.molecular_formula());
+    }
+
+    #[test]
+    fn add_hydrogens_keeps_chirality() {
+        let with_hs = mol("N[C@@H](C)C(=O)O").add_hs();
+
+        assert!(with_hs.is_same_structure(&mol("N[C@@]([H])(C([H])([H])[H])C(=O)O[H]").add_hs()));
+        assert_same_mol!(mol("N[C@@H](C)C(=O)O"), with_hs);
+    }
+
+    #[test]
+    fn round_trips() {
+        for smiles in [
+            "CCO",
+            "c1ccccc1",
+            "N[C@@H](C)C(=O)O",
+            "N[C@H](C)C(=O)O",
+            "F/C=C/F",
+            "F/C=C\\F",
+            "[NH4+]",
+            "C[CH2]",
+        ] {
+            assert_eq!(smiles, mol(smiles).add_hs().remove_hs().to_smiles());
+        }
+    }
+
+    #[test]
+    fn remove_hydrogens() {
+        assert_eq!("C", mol("[H]C([H])([H])[H]").remove_hs().to_smiles());
+        assert_eq!("[CH3]C", mol("[H][C]([H])([H])C").remove_hs().to_smiles());
+        // kept: isotopes, charged and molecular hydrogen
+        assert_eq!("[2H]C", mol("[2H]C").remove_hs().to_smiles());
+        assert_eq!("[H][H]", mol("[H][H]").remove_hs().to_smiles());
+    }
+
+    #[test]
+    fn remove_hydrogens_keeps_stereo() {
+        assert_eq!("F/C=C/F", mol("F/C([H])=C/F").remove_hs().to_smiles());
+        assert_eq!(
+            mol("F/C=C\\F").to_canonical_smiles(),
+            mol("[H]/C(F)=C/F").remove_hs().to_canonical_smiles()
+        );
+        // the only neighbor giving the configuration
+        assert_eq!("[H]/C=C/F", mol("[H]/C=C/F").remove_hs().to_smiles());
+        assert_eq!(
+            "N[C@@H](C)C(=O)O",
+            mol("N[C@@]([H])(C)C(=O)O").remove_hs().to_smiles()
+        );
+    }
+}
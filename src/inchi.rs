@@ -0,0 +1,316 @@
+//! InChI and InChIKey with the official InChI library, linked as `libinchi`.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+use crate::error::InchiError;
+use crate::smiles::element_symbol;
+use crate::types::{BondOrder, Chirality, DoubleBondConfig, Mol};
+
+// inchi_api.h
+const MAXVAL: usize = 20;
+const ATOM_EL_LEN: usize = 6;
+const NO_ATOM: i16 = -1;
+const BOND_TYPE_SINGLE: i8 = 1;
+const BOND_TYPE_DOUBLE: i8 = 2;
+const BOND_TYPE_TRIPLE: i8 = 3;
+const BOND_TYPE_ALTERN: i8 = 4;
+const STEREO_TYPE_DOUBLE_BOND: i8 = 1;
+const STEREO_TYPE_TETRAHEDRAL: i8 = 2;
+const PARITY_ODD: i8 = 1;
+const PARITY_EVEN: i8 = 2;
+const RET_OKAY: c_int = 0;
+const RET_WARNING: c_int = 1;
+const INCHIKEY_OK: c_int = 0;
+/// An InChIKey is 27 characters.
+const INCHIKEY_LEN: usize = 27;
+
+#[repr(C)]
+struct InchiAtom {
+    x: f64,
+    y: f64,
+    z: f64,
+    neighbor: [i16; MAXVAL],
+    bond_type: [i8; MAXVAL],
+    bond_stereo: [i8; MAXVAL],
+    elname: [c_char; ATOM_EL_LEN],
+    num_bonds: i16,
+    /// Implicit hydrogens, then those of isotopes 1, 2 and 3.
+    num_iso_h: [i8; 4],
+    isotopic_mass: i16,
+    radical: i8,
+    charge: i8,
+}
+
+#[repr(C)]
+struct InchiStereo0D {
+    neighbor: [i16; 4],
+    central_atom: i16,
+    kind: i8,
+    parity: i8,
+}
+
+#[repr(C)]
+struct InchiInput {
+    atom: *mut InchiAtom,
+    stereo0d: *mut InchiStereo0D,
+    options: *mut c_char,
+    num_atoms: i16,
+    num_stereo0d: i16,
+}
+
+#[repr(C)]
+struct InchiOutput {
+    inchi: *mut c_char,
+    aux_info: *mut c_char,
+    message: *mut c_char,
+    log: *mut c_char,
+}
+
+#[link(name = "inchi")]
+extern "C" {
+    fn GetINCHI(input: *mut InchiInput, output: *mut InchiOutput) -> c_int;
+    fn FreeINCHI(output: *mut InchiOutput);
+    fn GetINCHIKeyFromINCHI(
+        inchi: *const c_char,
+        xtra1: c_int,
+        xtra2: c_int,
+        key: *mut c_char,
+        xtra1_out: *mut c_char,
+        xtra2_out: *mut c_char,
+    ) -> c_int;
+}
+
+/// The standard InChI of the molecule, with the hydrogens it has, its isotopes, charges,
+/// chirality and cis/trans stereo. Aromatic rings are kekulized when possible.
+pub fn to_inchi(mol: &Mol) -> Result<String, InchiError> {
+    let mut kekulized = mol.clone();
+    let mol = if kekulized.kekulize().is_ok() {
+        &kekulized
+    } else {
+        mol
+    };
+    let graph = &mol.graph;
+    let num_atoms = i16::try_from(graph.node_count()).map_err(|_| InchiError::TooLarge)?;
+
+    let mut atoms = vec![];
+    for (i, atom) in graph.node_weights().enumerate() {
+        let symbol = element_symbol(atom.number)
+            .ok_or_else(|| InchiError::Unsupported(format!("wildcard atom {}", i)))?;
+        let mut elname = [0; ATOM_EL_LEN];
+        for (c, byte) in elname.iter_mut().zip(symbol.bytes()) {
+            *c = byte as c_char;
+        }
+        let isotopic_mass = match atom.isotope {
+            Some(isotope) => i16::try_from(isotope).map_err(|_| InchiError::TooLarge)?,
+            None => 0,
+        };
+        let hydrogens = i8::try_from(atom.total_num_hs()).map_err(|_| InchiError::TooLarge)?;
+        atoms.push(InchiAtom {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            neighbor: [0; MAXVAL],
+            bond_type: [0; MAXVAL],
+            bond_stereo: [0; MAXVAL],
+            elname,
+            num_bonds: 0,
+            num_iso_h: [hydrogens, 0, 0, 0],
+            isotopic_mass,
+            radical: 0,
+            charge: atom.charge,
+        });
+    }
+
+    // each bond once, on its first atom
+    let mut stereo = vec![];
+    for edge in graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(edge).unwrap();
+        let bond = &graph[edge];
+        let bond_type = match bond.order {
+            BondOrder::Single => BOND_TYPE_SINGLE,
+            BondOrder::Double => BOND_TYPE_DOUBLE,
+            BondOrder::Triple => BOND_TYPE_TRIPLE,
+            BondOrder::Aromatic => BOND_TYPE_ALTERN,
+            BondOrder::Quadruple => {
+                return Err(InchiError::Unsupported(format!(
+                    "quadruple bond {}",
+                    edge.index()
+                )))
+            }
+        };
+        let atom = &mut atoms[a.index()];
+        let slot = atom.num_bonds as usize;
+        if slot == MAXVAL {
+            return Err(InchiError::TooLarge);
+        }
+        atom.neighbor[slot] = b.index() as i16;
+        atom.bond_type[slot] = bond_type;
+        atom.num_bonds += 1;
+
+        if let Some(bond_stereo) = bond.stereo {
+            stereo.push(InchiStereo0D {
+                neighbor: [
+                    bond_stereo.start_neighbor.index() as i16,
+                    a.index() as i16,
+                    b.index() as i16,
+                    bond_stereo.end_neighbor.index() as i16,
+                ],
+                central_atom: NO_ATOM,
+                kind: STEREO_TYPE_DOUBLE_BOND,
+                parity: match bond_stereo.config {
+                    DoubleBondConfig::Trans => PARITY_EVEN,
+                    DoubleBondConfig::Cis => PARITY_ODD,
+                },
+            });
+        }
+    }
+
+    // InChI's even parity is clockwise looking from the first neighbor, like `@@`; the
+    // center stands in for an implicit hydrogen
+    for node in graph.node_indices() {
+        let atom = &graph[node];
+        let Some(chirality) = atom.chirality else {
+            continue;
+        };
+        let center = node.index() as i16;
+        let neighbors: Vec<i16> = atom
+            .chiral_neighbors
+            .iter()
+            .map(|n| n.index() as i16)
+            .collect();
+        let neighbor = match neighbors[..] {
+            [a, b, c] => [center, a, b, c],
+            [a, b, c, d] => [a, b, c, d],
+            _ => continue,
+        };
+        stereo.push(InchiStereo0D {
+            neighbor,
+            central_atom: center,
+            kind: STEREO_TYPE_TETRAHEDRAL,
+            parity: match chirality {
+                Chirality::Clockwise => PARITY_EVEN,
+                Chirality::Anticlockwise => PARITY_ODD,
+            },
+        });
+    }
+
+    // unwrap: no NUL
+    let options = CString::new("").unwrap();
+    let mut input = InchiInput {
+        atom: atoms.as_mut_ptr(),
+        stereo0d: if stereo.is_empty() {
+            ptr::null_mut()
+        } else {
+            stereo.as_mut_ptr()
+        },
+        options: options.as_ptr() as *mut c_char,
+        num_atoms,
+        num_stereo0d: i16::try_from(stereo.len()).map_err(|_| InchiError::TooLarge)?,
+    };
+    let mut output = InchiOutput {
+        inchi: ptr::null_mut(),
+        aux_info: ptr::null_mut(),
+        message: ptr::null_mut(),
+        log: ptr::null_mut(),
+    };
+    // SAFETY: the input points to `num_atoms` atoms and `num_stereo0d` stereo elements
+    // with neighbors in range, which outlive the call; the library allocates the output,
+    // freed by FreeINCHI after copying it
+    unsafe {
+        let code = GetINCHI(&mut input, &mut output);
+        let text = |text: *mut c_char| {
+            (!text.is_null()).then(|| CStr::from_ptr(text).to_string_lossy().into_owned())
+        };
+        let result = match text(output.inchi) {
+            Some(inchi) if code == RET_OKAY || code == RET_WARNING => Ok(inchi),
+            _ => Err(InchiError::Library(
+                text(output.message)
+                    .filter(|message| !message.is_empty())
+                    .unwrap_or_else(|| format!("error code {}", code)),
+            )),
+        };
+        FreeINCHI(&mut output);
+        result
+    }
+}
+
+/// The standard InChIKey of the molecule, the hash of its [`to_inchi`].
+pub fn to_inchikey(mol: &Mol) -> Result<String, InchiError> {
+    let inchi = to_inchi(mol)?;
+    // unwrap: InChIs are ASCII without NUL
+    let inchi = CString::new(inchi).unwrap();
+    let mut key = [0 as c_char; INCHIKEY_LEN + 1];
+    let mut xtra1 = [0 as c_char; 65];
+    let mut xtra2 = [0 as c_char; 65];
+    // SAFETY: the buffers are as large as the library writes, with the NUL
+    let code = unsafe {
+        GetINCHIKeyFromINCHI(
+            inchi.as_ptr(),
+            0,
+            0,
+            key.as_mut_ptr(),
+            xtra1.as_mut_ptr(),
+            xtra2.as_mut_ptr(),
+        )
+    };
+    if code != INCHIKEY_OK {
+        return Err(InchiError::Library(format!("InChIKey error code {}", code)));
+    }
+    // SAFETY: the library wrote a NUL-terminated key
+    let key = unsafe { CStr::from_ptr(key.as_ptr()) };
+    Ok(key.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::InchiError;
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    #[test]
+    fn inchi() {
+        assert_eq!(
+            "InChI=1S/C2H6O/c1-2-3/h3H,2H2,1H3",
+            mol("CCO").to_inchi().unwrap()
+        );
+        assert_eq!(
+            "InChI=1S/C6H6/c1-2-4-6-5-3-1/h1-6H",
+            mol("c1ccccc1").to_inchi().unwrap()
+        );
+        assert_eq!(
+            "InChI=1S/C3H7NO2/c1-2(4)3(5)6/h2H,4H2,1H3,(H,5,6)/t2-/m0/s1",
+            mol("N[C@@H](C)C(=O)O").to_inchi().unwrap()
+        );
+        assert_eq!(
+            "InChI=1S/C4H8/c1-3-4-2/h3-4H,1-2H3/b4-3+",
+            mol("C/C=C/C").to_inchi().unwrap()
+        );
+    }
+
+    #[test]
+    fn inchikey() {
+        assert_eq!(
+            "LFQSCWFLJHTTHZ-UHFFFAOYSA-N",
+            mol("CCO").to_inchikey().unwrap()
+        );
+        assert_eq!(
+            "QNAYBMKLOCPYGJ-REOHCLBHSA-N",
+            mol("N[C@@H](C)C(=O)O").to_inchikey().unwrap()
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(
+            Err(InchiError::Unsupported("wildcard atom 1".to_string())),
+            mol("C*").to_inchi()
+        );
+    }
+}
@@ -0,0 +1,458 @@
+//! Reading a SMILES as it's typed, for structure entry: what can come next, and which
+//! rings and branches are still open.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::{SmilesError, SmilesErrorKind};
+use crate::lexer::{BondSymbol, Lexer, Span, Token, TokenKind};
+use crate::smiles::{SmilesParser, SmilesParserOptions};
+use crate::types::Mol;
+
+/// What can be typed next, see [`ParserState::next_tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NextToken {
+    Atom,
+    Bond,
+    RingBond,
+    BranchOpen,
+    BranchClose,
+    Dot,
+    /// The input can end here: it's a complete SMILES.
+    End,
+    /// The rest of a bracket atom, up to its `]`.
+    RestOfBracketAtom,
+    /// The digits of a ring bond number after `%`.
+    RestOfRingNumber,
+}
+
+/// The syntax read so far, like [`ast::read`](crate::ast) keeps track of.
+#[derive(Debug, Clone, Default)]
+struct Syntax {
+    atoms: usize,
+    // the atom the next bond starts from, none at the start and after a `.`
+    last_atom: Option<usize>,
+    dot: bool,
+    // the bond symbol read but not yet applied to a bond, and its position
+    pending_bond: Option<(BondSymbol, usize)>,
+    // the atom before each open branch and the position of its `(`
+    branches: Vec<(usize, usize)>,
+    // a `(` was read and no atom since
+    empty_branch: bool,
+    // ring number, atom, position and bond symbol of each open ring bond
+    rings: Vec<(u16, usize, usize, Option<BondSymbol>)>,
+    bonds: Vec<(usize, usize)>,
+}
+
+impl Syntax {
+    fn read(&mut self, token: &Token, options: &SmilesParserOptions) -> Result<(), SmilesError> {
+        let position = token.span.start;
+        let error = |kind| Err(SmilesError::new(kind, position));
+        let dangling = |bond| Err(SmilesError::new(SmilesErrorKind::DanglingBond, bond));
+        match token.kind {
+            TokenKind::OrganicAtom(_) | TokenKind::BracketAtom(_) => {
+                let atom = self.atoms;
+                if let Some(last) = self.last_atom {
+                    self.bonds.push((last, atom));
+                }
+                self.atoms += 1;
+                self.last_atom = Some(atom);
+                self.pending_bond = None;
+                self.dot = false;
+                self.empty_branch = false;
+            }
+            TokenKind::RingBond(number) => {
+                if number > options.max_ring_number {
+                    return error(SmilesErrorKind::RingNumberTooHigh {
+                        number,
+                        max: options.max_ring_number,
+                    });
+                }
+                let Some(atom) = self.last_atom else {
+                    return error(SmilesErrorKind::MissingAtom);
+                };
+                let symbol = self.pending_bond.take().map(|(symbol, _)| symbol);
+                match self.rings.iter().position(|(open, ..)| *open == number) {
+                    None => self.rings.push((number, atom, position, symbol)),
+                    Some(index) => {
+                        let (_, start, _, start_symbol) = self.rings.remove(index);
+                        if start == atom {
+                            return error(SmilesErrorKind::RingBondToSelf(number));
+                        }
+                        if self.bonds.contains(&(start, atom))
+                            || self.bonds.contains(&(atom, start))
+                        {
+                            return error(SmilesErrorKind::DuplicateBond(number));
+                        }
+                        if let (Some(start), Some(end)) = (start_symbol, symbol) {
+                            if !start.matches_ring_end(end) {
+                                return error(SmilesErrorKind::RingBondConflict(number));
+                            }
+                        }
+                        self.bonds.push((start, atom));
+                    }
+                }
+            }
+            TokenKind::Bond(symbol) => {
+                if self.last_atom.is_none() || self.pending_bond.is_some() {
+                    return Err(SmilesError::expected(
+                        SmilesErrorKind::UnexpectedCharacter(symbol.to_char()),
+                        position,
+                        "atom",
+                    ));
+                }
+                self.pending_bond = Some((symbol, position));
+            }
+            TokenKind::BranchOpen | TokenKind::BranchClose | TokenKind::Dot => {
+                let Some(atom) = self.last_atom else {
+                    return error(SmilesErrorKind::MissingAtom);
+                };
+                if let Some((_, bond)) = self.pending_bond {
+                    return dangling(bond);
+                }
+                match token.kind {
+                    TokenKind::BranchOpen if self.branches.len() == options.max_branch_depth => {
+                        return error(SmilesErrorKind::NestingTooDeep);
+                    }
+                    TokenKind::BranchOpen => {
+                        self.branches.push((atom, position));
+                        self.empty_branch = true;
+                    }
+                    TokenKind::BranchClose if self.empty_branch => {
+                        return error(SmilesErrorKind::MissingAtom);
+                    }
+                    TokenKind::BranchClose => match self.branches.pop() {
+                        Some((before_branch, _)) => self.last_atom = Some(before_branch),
+                        None => return error(SmilesErrorKind::UnbalancedParenthesis),
+                    },
+                    _ => {
+                        self.last_atom = None;
+                        self.dot = true;
+                        self.empty_branch = false;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn next_tokens(&self, options: &SmilesParserOptions) -> Vec<NextToken> {
+        let mut next = vec![NextToken::Atom];
+        let after_atom = self.last_atom.is_some();
+        let free = after_atom && self.pending_bond.is_none();
+        if free {
+            next.push(NextToken::Bond);
+        }
+        if after_atom {
+            next.push(NextToken::RingBond);
+        }
+        if free && self.branches.len() < options.max_branch_depth {
+            next.push(NextToken::BranchOpen);
+            if !self.branches.is_empty() && !self.empty_branch {
+                next.push(NextToken::BranchClose);
+            }
+            next.push(NextToken::Dot);
+        }
+        if self.pending_bond.is_none()
+            && self.branches.is_empty()
+            && self.rings.is_empty()
+            && !self.dot
+        {
+            next.push(NextToken::End);
+        }
+        next
+    }
+}
+
+/// A SMILES read as it's typed: characters are added with [`push`](Self::push), and
+/// the state can be asked at any point what may come next, which rings and branches are
+/// open, and the first error. Only the added characters are read each time.
+#[derive(Debug, Clone)]
+pub struct ParserState {
+    options: SmilesParserOptions,
+    input: String,
+    // how much of the input is read, in characters and bytes
+    read: usize,
+    read_bytes: usize,
+    syntax: Syntax,
+    // the last token, if typing more can change it: `C` can become `Cl`, and `B` `Br`
+    held: Option<Token>,
+    // the start of a token that isn't complete yet, e.g. `[13C` or `%1`
+    partial: Option<char>,
+    error: Option<SmilesError>,
+}
+
+impl Default for ParserState {
+    fn default() -> Self {
+        ParserState::new(SmilesParserOptions::default())
+    }
+}
+
+impl ParserState {
+    pub fn new(options: SmilesParserOptions) -> Self {
+        ParserState {
+            options,
+            input: String::new(),
+            read: 0,
+            read_bytes: 0,
+            syntax: Syntax::default(),
+            held: None,
+            partial: None,
+            error: None,
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.input.push(c);
+        self.read_rest();
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.input.push_str(text);
+        self.read_rest();
+    }
+
+    /// Everything added so far.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The first error, with its position in the input. Nothing more is read after it.
+    pub fn error(&self) -> Option<&SmilesError> {
+        self.error.as_ref()
+    }
+
+    /// What can be typed next, nothing after an error. [`NextToken::Atom`] is always
+    /// allowed otherwise.
+    pub fn next_tokens(&self) -> Vec<NextToken> {
+        if self.error.is_some() {
+            return vec![];
+        }
+        match self.partial {
+            Some('[') => vec![NextToken::RestOfBracketAtom],
+            Some(_) => vec![NextToken::RestOfRingNumber],
+            None => self.current().next_tokens(&self.options),
+        }
+    }
+
+    /// The numbers of the open ring bonds, with the position where each was opened.
+    pub fn open_rings(&self) -> Vec<(u16, usize)> {
+        let syntax = self.current();
+        syntax
+            .rings
+            .iter()
+            .map(|(number, _, position, _)| (*number, *position))
+            .collect()
+    }
+
+    /// The positions of the `(` of the open branches, the innermost last.
+    pub fn open_branches(&self) -> Vec<usize> {
+        self.syntax
+            .branches
+            .iter()
+            .map(|(_, position)| *position)
+            .collect()
+    }
+
+    /// Whether the input is a whole SMILES, as far as syntax and ring bonds go.
+    pub fn is_complete(&self) -> bool {
+        self.next_tokens().contains(&NextToken::End)
+    }
+
+    /// Parses the input into a molecule, with the options of the state.
+    pub fn to_mol(&self) -> Result<Mol, SmilesError> {
+        SmilesParser::with_options(self.options).parse(&self.input)
+    }
+
+    /// The syntax with the held token.
+    fn current(&self) -> Syntax {
+        let mut syntax = self.syntax.clone();
+        if let Some(token) = &self.held {
+            // atoms are always read
+            let _ = syntax.read(token, &self.options);
+        }
+        syntax
+    }
+
+    /// Reads the tokens after those already read, holding back the last one if more
+    /// input can change it.
+    fn read_rest(&mut self) {
+        if self.error.is_some() {
+            return;
+        }
+        self.held = None;
+        self.partial = None;
+        let rest = &self.input[self.read_bytes..];
+        let length = rest.chars().count();
+        let offset = self.read;
+        let mut lexer = Lexer::new(rest, self.options);
+        loop {
+            let start = lexer.position();
+            let Some(token) = lexer.next() else {
+                break;
+            };
+            let mut token = match token {
+                Ok(token) => token,
+                // the input ends in the middle of the token
+                Err(error) if error.position == length => {
+                    self.partial = rest.chars().nth(start);
+                    return;
+                }
+                Err(mut error) => {
+                    error.position += offset;
+                    self.error = Some(error);
+                    return;
+                }
+            };
+            let end = token.span.end;
+            token.span = shifted(token.span, offset);
+            if end == length && matches!(rest.chars().nth(start), Some('C' | 'B')) {
+                self.held = Some(token);
+                return;
+            }
+            if let Err(error) = self.syntax.read(&token, &self.options) {
+                self.error = Some(error);
+                return;
+            }
+            self.read = offset + end;
+            self.read_bytes = self.input.len() - rest.len()
+                + rest.char_indices().nth(end).map_or(rest.len(), |(i, _)| i);
+        }
+    }
+}
+
+fn shifted(span: Span, offset: usize) -> Span {
+    Span {
+        start: span.start + offset,
+        end: span.end + offset,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{NextToken, ParserState};
+    use crate::error::SmilesErrorKind;
+    use crate::smiles::SmilesParser;
+    use crate::testing::smiles_like;
+    use proptest::prelude::*;
+
+    fn state(smiles: &str) -> ParserState {
+        let mut state = ParserState::default();
+        for c in smiles.chars() {
+            state.push(c);
+        }
+        state
+    }
+
+    #[test]
+    fn next_tokens() {
+        use NextToken::*;
+        // the empty SMILES is valid
+        assert_eq!(vec![Atom, End], state("").next_tokens());
+        assert_eq!(
+            vec![Atom, Bond, RingBond, BranchOpen, Dot, End],
+            state("CC").next_tokens()
+        );
+        assert_eq!(vec![Atom, RingBond], state("CC=").next_tokens());
+        assert_eq!(
+            vec![Atom, Bond, RingBond, BranchOpen, Dot],
+            state("CC(").next_tokens()
+        );
+        assert_eq!(
+            vec![Atom, Bond, RingBond, BranchOpen, BranchClose, Dot],
+            state("CC(C").next_tokens()
+        );
+        assert_eq!(vec![Atom], state("CC.").next_tokens());
+        assert_eq!(vec![RestOfBracketAtom], state("C[13C").next_tokens());
+        assert_eq!(vec![RestOfRingNumber], state("C%1").next_tokens());
+        assert_eq!(vec![RestOfRingNumber], state("C%(12").next_tokens());
+        assert!(state("C[13CH4]").is_complete());
+        assert!(state("C%12CC%12").is_complete());
+        assert!(!state("C1CC").is_complete());
+        assert!(state("C=1CCCC=1").is_complete());
+        assert!(!state("C=1CCCC#1").is_complete());
+    }
+
+    #[test]
+    fn open_rings_and_branches() {
+        let state = state("C1CC2C(C(C");
+        assert_eq!(vec![(1, 1), (2, 4)], state.open_rings());
+        assert_eq!(vec![6, 8], state.open_branches());
+
+        let mut state = state.clone();
+        state.push_str("))C2C1");
+        assert!(state.open_rings().is_empty());
+        assert!(state.open_branches().is_empty());
+        assert!(state.is_complete());
+        assert_eq!(8, state.to_mol().unwrap().graph.node_count());
+    }
+
+    #[test]
+    fn chlorine_and_bromine() {
+        // `C` can still become `Cl`
+        let mut state = state("C1C");
+        state.push('C');
+        assert_eq!(vec![(1, 1)], state.open_rings());
+        state.push('l');
+        assert!(state.next_tokens().contains(&NextToken::RingBond));
+        state.push('1');
+        assert!(state.is_complete());
+        assert_eq!("C1CCl1", state.input());
+        assert_eq!(None, state.error());
+        assert!(state.to_mol().is_ok());
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            (SmilesErrorKind::MissingAtom, 2, "C.("),
+            (SmilesErrorKind::MissingAtom, 2, "C()"),
+            (SmilesErrorKind::DanglingBond, 2, "CC=)"),
+            (SmilesErrorKind::UnbalancedParenthesis, 1, "C)"),
+            (SmilesErrorKind::RingBondToSelf(1), 2, "C11"),
+            (SmilesErrorKind::DuplicateBond(1), 3, "C1C1"),
+            (SmilesErrorKind::RingBondConflict(1), 8, "C=1CCCC#1"),
+            (SmilesErrorKind::RingBondConflict(1), 10, "F/C=C/1CC/1"),
+            (SmilesErrorKind::UnexpectedCharacter('?'), 3, "CC(?"),
+            (
+                SmilesErrorKind::RingNumberTooHigh {
+                    number: 123,
+                    max: 99,
+                },
+                1,
+                "C%(123)",
+            ),
+        ];
+        for (kind, position, smiles) in cases {
+            let state = state(smiles);
+            let error = state.error().unwrap();
+            assert_eq!((&kind, position), (&error.kind, error.position), "{smiles}");
+            assert!(state.next_tokens().is_empty());
+        }
+        // typing after an error changes nothing
+        let mut state = state("C)");
+        state.push('C');
+        assert_eq!(1, state.error().unwrap().position);
+    }
+
+    proptest! {
+        #[test]
+        fn agrees_with_parser(smiles in smiles_like()) {
+            let state = state(&smiles);
+            match SmilesParser::new().parse(&smiles) {
+                Ok(_) => {
+                    prop_assert_eq!(None, state.error());
+                    prop_assert!(state.is_complete());
+                }
+                Err(error) => {
+                    // the parser also fails on what isn't syntax and at the end, and
+                    // checks ring bonds last
+                    if let Some(state_error) = state.error() {
+                        prop_assert!(state_error.position <= error.position);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,747 @@
+//! A JSON format for molecules, for other languages and JavaScript front ends, independent
+//! of the `serde` feature. [`Mol::to_json`] writes it and [`Mol::from_json`] reads it.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "atoms": [
+//!     {"element": "C", "charge": 0, "aromatic": false, "hydrogens": 3},
+//!     {"element": "C", "charge": 0, "aromatic": false, "hydrogens": 1,
+//!      "chirality": "anticlockwise", "chiral_neighbors": [0, 2, 3]},
+//!     ...
+//!   ],
+//!   "bonds": [
+//!     {"atoms": [0, 1], "order": "single"},
+//!     {"atoms": [4, 5], "order": "double",
+//!      "stereo": {"config": "trans", "neighbors": [3, 6]}},
+//!     ...
+//!   ]
+//! }
+//! ```
+//!
+//! Atoms and bonds are referred to by their index, from 0. An atom has:
+//!
+//! - `element`: the element symbol, e.g. `"C"` or `"Cl"`, `"*"` for a wildcard.
+//! - `charge`: the formal charge.
+//! - `aromatic`: whether the atom is aromatic.
+//! - `hydrogens`: the number of attached hydrogens that aren't atoms of their own.
+//! - `isotope` (optional): the mass number.
+//! - `map` (optional): the atom map number, e.g. of a reaction mapping.
+//! - `radicals` (optional): the number of radical electrons, e.g. 1 for a methyl radical.
+//! - `chirality` and `chiral_neighbors` (optional): `"anticlockwise"` (`@` in SMILES) or
+//!   `"clockwise"` (`@@`), looking from the first neighbor, with an implicit hydrogen
+//!   counted first, like in SMILES.
+//!
+//! A bond has:
+//!
+//! - `atoms`: its two atoms.
+//! - `order`: `"single"`, `"double"`, `"triple"`, `"quadruple"` or `"aromatic"`.
+//! - `stereo` (optional): `{"config": "cis" | "trans", "neighbors": [a, b]}`, the
+//!   configuration of a double bond relative to a neighbor of its first atom and one of
+//!   its second atom.
+//! - `direction` (optional): `"up"` or `"down"`, a single bond written with `/` or `\` in
+//!   SMILES, from its first atom to its second.
+//!
+//! When reading, only `element` and the bond `atoms` are required; other fields take the
+//! defaults above (no charge, single bonds, ...), and atoms without `hydrogens` get them
+//! from the SMILES valence model. Atoms with other hydrogen counts and without `radicals`
+//! get the radical electrons a SMILES bracket atom would. Unknown fields are ignored.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+use petgraph::graph::{NodeIndex, UnGraph};
+
+use crate::error::JsonError;
+use crate::periodic_table::Element;
+use crate::smiles::element_symbol;
+use crate::types::{
+    Atom, AtomIdx, Bond, BondDirection, BondOrder, BondStereo, Chirality, DoubleBondConfig, Mol,
+};
+use crate::valence;
+
+/// Version of the format written in `"version"`.
+pub const VERSION: u32 = 1;
+
+/// Deepest nesting of arrays and objects read, much deeper than the format needs.
+const MAX_DEPTH: usize = 32;
+
+/// The molecule in the JSON format, on one line.
+pub fn to_json(mol: &Mol) -> String {
+    let graph = &mol.graph;
+    let mut out = format!(r#"{{"version":{},"atoms":["#, VERSION);
+    for (i, atom) in graph.node_weights().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write!(
+            out,
+            r#"{{"element":"{}","charge":{},"aromatic":{},"hydrogens":{}"#,
+            element_symbol(atom.number).unwrap_or("*"),
+            atom.charge,
+            atom.aromatic,
+            atom.total_num_hs()
+        );
+        if let Some(isotope) = atom.isotope {
+            let _ = write!(out, r#","isotope":{}"#, isotope);
+        }
+        if let Some(map_number) = atom.map_number {
+            let _ = write!(out, r#","map":{}"#, map_number);
+        }
+        if atom.num_radical_electrons > 0 {
+            let _ = write!(out, r#","radicals":{}"#, atom.num_radical_electrons);
+        }
+        if let Some(chirality) = atom.chirality {
+            let chirality = match chirality {
+                Chirality::Anticlockwise => "anticlockwise",
+                Chirality::Clockwise => "clockwise",
+            };
+            let _ = write!(
+                out,
+                r#","chirality":"{}","chiral_neighbors":{}"#,
+                chirality,
+                indices(&atom.chiral_neighbors)
+            );
+        }
+        out.push('}');
+    }
+    out.push_str(r#"],"bonds":["#);
+    for edge in graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(edge).unwrap();
+        let bond = &graph[edge];
+        if edge.index() > 0 {
+            out.push(',');
+        }
+        let order = match bond.order {
+            BondOrder::Single => "single",
+            BondOrder::Double => "double",
+            BondOrder::Triple => "triple",
+            BondOrder::Quadruple => "quadruple",
+            BondOrder::Aromatic => "aromatic",
+        };
+        let _ = write!(
+            out,
+            r#"{{"atoms":[{},{}],"order":"{}""#,
+            a.index(),
+            b.index(),
+            order
+        );
+        if let Some(stereo) = &bond.stereo {
+            let config = match stereo.config {
+                DoubleBondConfig::Cis => "cis",
+                DoubleBondConfig::Trans => "trans",
+            };
+            let _ = write!(
+                out,
+                r#","stereo":{{"config":"{}","neighbors":{}}}"#,
+                config,
+                indices(&[stereo.start_neighbor, stereo.end_neighbor])
+            );
+        }
+        if let Some(direction) = bond.direction {
+            let direction = match direction {
+                BondDirection::Up => "up",
+                BondDirection::Down => "down",
+            };
+            let _ = write!(out, r#","direction":"{}""#, direction);
+        }
+        out.push('}');
+    }
+    out.push_str("]}");
+    out
+}
+
+fn indices(atoms: &[AtomIdx]) -> String {
+    let indices: Vec<String> = atoms.iter().map(|atom| atom.to_string()).collect();
+    format!("[{}]", indices.join(","))
+}
+
+/// Reads a molecule in the JSON format.
+pub fn from_json(json: &str) -> Result<Mol, JsonError> {
+    let mut parser = Parser {
+        bytes: json.as_bytes(),
+        position: 0,
+    };
+    let document = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.position < parser.bytes.len() {
+        return Err(JsonError::Syntax(parser.position));
+    }
+
+    let document = object(&document, "the document")?;
+    let atoms = match field(document, "atoms") {
+        Some(atoms) => array(atoms, "atoms")?,
+        None => &[],
+    };
+    let bonds = match field(document, "bonds") {
+        Some(bonds) => array(bonds, "bonds")?,
+        None => &[],
+    };
+    let count = atoms.len();
+    let atom_index = |value: &Value, what: &str| -> Result<AtomIdx, JsonError> {
+        let index: usize = integer(value, what)?;
+        if index < count {
+            Ok(AtomIdx::new(index))
+        } else {
+            Err(schema(format!(
+                "{} {} out of range, {} atoms",
+                what, index, count
+            )))
+        }
+    };
+
+    let mut graph = UnGraph::<Atom, Bond>::with_capacity(count, bonds.len());
+    // hydrogens of each atom, to compare with the valence model once the bonds are known
+    let mut hydrogens: Vec<Option<u8>> = vec![];
+    let mut radicals_given = vec![];
+    for value in atoms {
+        let fields = object(value, "an atom")?;
+        let symbol = string(required(fields, "element")?, "element")?;
+        let number = match symbol {
+            "*" => 0,
+            _ => {
+                Element::from_symbol(symbol)
+                    .ok_or_else(|| schema(format!("unknown element {:?}", symbol)))?
+                    .number
+            }
+        };
+        let mut atom = Atom {
+            number,
+            ..Default::default()
+        };
+        if let Some(charge) = field(fields, "charge") {
+            atom.charge = integer(charge, "charge")?;
+        }
+        if let Some(aromatic) = field(fields, "aromatic") {
+            atom.aromatic = boolean(aromatic, "aromatic")?;
+        }
+        if let Some(isotope) = optional(fields, "isotope") {
+            atom.isotope = Some(integer(isotope, "isotope")?);
+        }
+        if let Some(map_number) = optional(fields, "map") {
+            atom.map_number = Some(integer(map_number, "map")?);
+        }
+        let radicals = optional(fields, "radicals");
+        if let Some(radicals) = radicals {
+            atom.num_radical_electrons = integer(radicals, "radicals")?;
+        }
+        radicals_given.push(radicals.is_some());
+        if let Some(chirality) = optional(fields, "chirality") {
+            atom.chirality = Some(match string(chirality, "chirality")? {
+                "anticlockwise" => Chirality::Anticlockwise,
+                "clockwise" => Chirality::Clockwise,
+                other => return Err(schema(format!("unknown chirality {:?}", other))),
+            });
+            atom.chiral_neighbors = array(required(fields, "chiral_neighbors")?, "neighbors")?
+                .iter()
+                .map(|neighbor| atom_index(neighbor, "chiral neighbor"))
+                .collect::<Result<_, _>>()?;
+        }
+        hydrogens.push(match optional(fields, "hydrogens") {
+            Some(count) => Some(integer(count, "hydrogens")?),
+            None => None,
+        });
+        graph.add_node(atom);
+    }
+
+    for value in bonds {
+        let fields = object(value, "a bond")?;
+        let [start, end] = array(required(fields, "atoms")?, "bond atoms")? else {
+            return Err(schema("a bond without two atoms".to_string()));
+        };
+        let (start, end) = (
+            atom_index(start, "bond atom")?,
+            atom_index(end, "bond atom")?,
+        );
+        if start == end {
+            return Err(schema(format!("bond from atom {} to itself", start)));
+        }
+        let (a, b) = (NodeIndex::from(start), NodeIndex::from(end));
+        if graph.contains_edge(a, b) {
+            return Err(schema(format!(
+                "duplicate bond between atoms {} and {}",
+                start, end
+            )));
+        }
+        let mut bond = Bond::default();
+        if let Some(order) = field(fields, "order") {
+            bond.order = match string(order, "order")? {
+                "single" => BondOrder::Single,
+                "double" => BondOrder::Double,
+                "triple" => BondOrder::Triple,
+                "quadruple" => BondOrder::Quadruple,
+                "aromatic" => BondOrder::Aromatic,
+                other => return Err(schema(format!("unknown bond order {:?}", other))),
+            };
+        }
+        if let Some(stereo) = optional(fields, "stereo") {
+            let stereo = object(stereo, "stereo")?;
+            let config = match string(required(stereo, "config")?, "config")? {
+                "cis" => DoubleBondConfig::Cis,
+                "trans" => DoubleBondConfig::Trans,
+                other => return Err(schema(format!("unknown stereo config {:?}", other))),
+            };
+            let [start_neighbor, end_neighbor] =
+                array(required(stereo, "neighbors")?, "neighbors")?
+            else {
+                return Err(schema("stereo without two neighbors".to_string()));
+            };
+            bond.stereo = Some(BondStereo {
+                config,
+                start_neighbor: atom_index(start_neighbor, "stereo neighbor")?,
+                end_neighbor: atom_index(end_neighbor, "stereo neighbor")?,
+            });
+        }
+        if let Some(direction) = optional(fields, "direction") {
+            bond.direction = Some(match string(direction, "direction")? {
+                "up" => BondDirection::Up,
+                "down" => BondDirection::Down,
+                other => return Err(schema(format!("unknown direction {:?}", other))),
+            });
+        }
+        graph.add_edge(a, b, bond);
+    }
+
+    // hydrogens the valence model gives stay implicit, others are written like in brackets
+    let mut mol = Mol { graph };
+    for (node, hydrogens) in mol.graph.node_indices().zip(hydrogens) {
+        let implicit = valence::implicit_h_count(&mol, node);
+        match hydrogens {
+            Some(count) if count != implicit => {
+                mol.graph[node].explicit_h = Some(count);
+                if !radicals_given[node.index()] {
+                    mol.graph[node].num_radical_electrons = valence::valence_deficit(&mol, node);
+                }
+            }
+            _ => mol.graph[node].implicit_h = implicit,
+        }
+    }
+    Ok(mol)
+}
+
+fn schema(message: String) -> JsonError {
+    JsonError::Schema(message)
+}
+
+/// A JSON value. Objects keep their fields in order.
+#[derive(Debug, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+fn field<'a>(fields: &'a [(String, Value)], name: &str) -> Option<&'a Value> {
+    fields
+        .iter()
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value)
+}
+
+/// The field, `None` if it's missing or `null`.
+fn optional<'a>(fields: &'a [(String, Value)], name: &str) -> Option<&'a Value> {
+    field(fields, name).filter(|value| **value != Value::Null)
+}
+
+fn required<'a>(fields: &'a [(String, Value)], name: &str) -> Result<&'a Value, JsonError> {
+    optional(fields, name).ok_or_else(|| schema(format!("missing {:?}", name)))
+}
+
+fn object<'a>(value: &'a Value, what: &str) -> Result<&'a [(String, Value)], JsonError> {
+    match value {
+        Value::Object(fields) => Ok(fields),
+        _ => Err(schema(format!("{} isn't an object", what))),
+    }
+}
+
+fn array<'a>(value: &'a Value, what: &str) -> Result<&'a [Value], JsonError> {
+    match value {
+        Value::Array(values) => Ok(values),
+        _ => Err(schema(format!("{} isn't an array", what))),
+    }
+}
+
+fn string<'a>(value: &'a Value, what: &str) -> Result<&'a str, JsonError> {
+    match value {
+        Value::String(text) => Ok(text),
+        _ => Err(schema(format!("{} isn't a string", what))),
+    }
+}
+
+fn boolean(value: &Value, what: &str) -> Result<bool, JsonError> {
+    match value {
+        Value::Bool(value) => Ok(*value),
+        _ => Err(schema(format!("{} isn't a boolean", what))),
+    }
+}
+
+/// The number as an integer of the type, if it's a whole number in its range.
+fn integer<T: TryFrom<i64>>(value: &Value, what: &str) -> Result<T, JsonError> {
+    match value {
+        Value::Number(number) if libm::trunc(*number) == *number && number.abs() < 1e15 => {
+            T::try_from(*number as i64).map_err(|_| schema(format!("{} out of range", what)))
+        }
+        _ => Err(schema(format!("{} isn't an integer", what))),
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+        if depth > MAX_DEPTH {
+            return Err(JsonError::Syntax(self.position));
+        }
+        match self.bytes.get(self.position) {
+            Some(b'{') => {
+                self.position += 1;
+                let mut fields = vec![];
+                if !self.consume(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let name = self.string()?;
+                        self.expect(b':')?;
+                        fields.push((name, self.value(depth + 1)?));
+                        if self.consume(b'}') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+                Ok(Value::Object(fields))
+            }
+            Some(b'[') => {
+                self.position += 1;
+                let mut values = vec![];
+                if !self.consume(b']') {
+                    loop {
+                        values.push(self.value(depth + 1)?);
+                        if self.consume(b']') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+                Ok(Value::Array(values))
+            }
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(JsonError::Syntax(self.position)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.position) {
+            self.position += 1;
+        }
+    }
+
+    /// Skips the byte, after whitespace, if it's next.
+    fn consume(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let next = self.bytes.get(self.position) == Some(&byte);
+        if next {
+            self.position += 1;
+        }
+        next
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.consume(byte) {
+            Ok(())
+        } else {
+            Err(JsonError::Syntax(self.position))
+        }
+    }
+
+    fn literal(&mut self, text: &str, value: Value) -> Result<Value, JsonError> {
+        if self.bytes[self.position..].starts_with(text.as_bytes()) {
+            self.position += text.len();
+            Ok(value)
+        } else {
+            Err(JsonError::Syntax(self.position))
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.position;
+        let digits = |parser: &mut Self| {
+            let from = parser.position;
+            while parser
+                .bytes
+                .get(parser.position)
+                .is_some_and(u8::is_ascii_digit)
+            {
+                parser.position += 1;
+            }
+            parser.position > from
+        };
+        self.consume_byte(b'-');
+        let mut valid = digits(self);
+        if self.consume_byte(b'.') {
+            valid &= digits(self);
+        }
+        if self.consume_byte(b'e') || self.consume_byte(b'E') {
+            let _ = self.consume_byte(b'+') || self.consume_byte(b'-');
+            valid &= digits(self);
+        }
+        // unwrap: the bytes are ASCII
+        let text = core::str::from_utf8(&self.bytes[start..self.position]).unwrap();
+        match text.parse() {
+            Ok(number) if valid => Ok(Value::Number(number)),
+            _ => Err(JsonError::Syntax(start)),
+        }
+    }
+
+    /// Skips the byte if it's next, without skipping whitespace.
+    fn consume_byte(&mut self, byte: u8) -> bool {
+        let next = self.bytes.get(self.position) == Some(&byte);
+        if next {
+            self.position += 1;
+        }
+        next
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        if !self.consume_byte(b'"') {
+            return Err(JsonError::Syntax(self.position));
+        }
+        let mut bytes = vec![];
+        loop {
+            let Some(&byte) = self.bytes.get(self.position) else {
+                return Err(JsonError::Syntax(self.position));
+            };
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.bytes.get(self.position) else {
+                        return Err(JsonError::Syntax(self.position));
+                    };
+                    self.position += 1;
+                    let escaped = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(JsonError::Syntax(self.position - 1)),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend(escaped.encode_utf8(&mut buffer).as_bytes());
+                }
+                0..=0x1f => return Err(JsonError::Syntax(self.position - 1)),
+                _ => bytes.push(byte),
+            }
+        }
+        // the input is a str, and escapes are encoded as UTF-8
+        String::from_utf8(bytes).map_err(|_| JsonError::Syntax(self.position))
+    }
+
+    /// The character of a `\u` escape, after the `u`, with a second escape for the low
+    /// surrogate of characters outside the basic plane.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let start = self.position;
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.bytes[self.position..].starts_with(b"\\u") {
+                return Err(JsonError::Syntax(start));
+            }
+            self.position += 2;
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(JsonError::Syntax(start));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or(JsonError::Syntax(start))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let hex = self
+            .bytes
+            .get(self.position..self.position + 4)
+            .and_then(|hex| core::str::from_utf8(hex).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or(JsonError::Syntax(self.position))?;
+        self.position += 4;
+        Ok(hex)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use petgraph::graph::NodeIndex;
+
+    use crate::error::JsonError;
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    use super::{Parser, Value};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn round_trip(smiles: &str) -> String {
+        let json = mol(smiles).to_json();
+        Mol::from_json(&json).unwrap().to_smiles()
+    }
+
+    fn parse(json: &str) -> Result<Value, JsonError> {
+        Parser {
+            bytes: json.as_bytes(),
+            position: 0,
+        }
+        .value(0)
+    }
+
+    #[test]
+    fn write() {
+        assert_eq!(
+            concat!(
+                r#"{"version":1,"atoms":["#,
+                r#"{"element":"C","charge":0,"aromatic":false,"hydrogens":3},"#,
+                r#"{"element":"O","charge":-1,"aromatic":false,"hydrogens":0,"isotope":18,"map":2}"#,
+                r#"],"bonds":[{"atoms":[0,1],"order":"single"}]}"#
+            ),
+            mol("C[18O-:2]").to_json()
+        );
+        assert!(mol("F/C=C/F").to_json().contains(
+            r#"{"atoms":[1,2],"order":"double","stereo":{"config":"trans","neighbors":[0,3]}}"#
+        ));
+        // looking from the hydrogen rather than from N
+        assert!(mol("N[C@@H](C)O")
+            .to_json()
+            .contains(r#""chirality":"anticlockwise","chiral_neighbors":[0,2,3]"#));
+    }
+
+    #[test]
+    fn round_trips() {
+        for smiles in [
+            "CCO",
+            "c1ccccc1O",
+            "C[NH3+]",
+            "[13CH4]",
+            "F/C=C/F",
+            "N[C@@H](C)C(=O)O",
+            "[CH3:1]C#N",
+            "[Na+].[Cl-]",
+            "*C",
+            "[CH2]C",
+        ] {
+            assert_eq!(smiles, round_trip(smiles));
+        }
+        assert_eq!("", round_trip(""));
+    }
+
+    #[test]
+    fn read_defaults() {
+        let json = r#"{
+            "atoms": [{"element": "C"}, {"element": "O", "hydrogens": null}, {"element": "N", "hydrogens": 0}],
+            "bonds": [{"atoms": [0, 1]}, {"atoms": [1, 2], "extra": [1, {"a": "é"}]}]
+        }"#;
+        let mol = Mol::from_json(json).unwrap();
+        assert_eq!("CO[N]", mol.to_smiles());
+        assert_eq!("", Mol::from_json("{}").unwrap().to_smiles());
+    }
+
+    #[test]
+    fn radicals() {
+        assert!(mol("C[CH2]")
+            .to_json()
+            .contains(r#""hydrogens":2,"radicals":1"#));
+        let json =
+            r#"{"atoms": [{"element": "C", "radicals": 1}, {"element": "O", "hydrogens": 0}]}"#;
+        let mol = Mol::from_json(json).unwrap();
+        assert_eq!(3, mol.graph[NodeIndex::new(0)].implicit_h);
+        assert_eq!(2, mol.graph[NodeIndex::new(1)].num_radical_electrons);
+        assert_eq!("[CH3].[O]", mol.to_smiles());
+    }
+
+    #[test]
+    fn schema_errors() {
+        let error = |json: &str| match Mol::from_json(json) {
+            Err(JsonError::Schema(message)) => message,
+            other => panic!("expected a schema error, got {:?}", other),
+        };
+
+        assert_eq!("the document isn't an object", error("[]"));
+        assert_eq!(r#"missing "element""#, error(r#"{"atoms": [{}]}"#));
+        assert_eq!(
+            r#"unknown element "Xx""#,
+            error(r#"{"atoms": [{"element": "Xx"}]}"#)
+        );
+        assert_eq!(
+            "charge out of range",
+            error(r#"{"atoms": [{"element": "C", "charge": 200}]}"#)
+        );
+        assert_eq!(
+            "hydrogens isn't an integer",
+            error(r#"{"atoms": [{"element": "C", "hydrogens": 1.5}]}"#)
+        );
+        assert_eq!(
+            "bond atom 1 out of range, 1 atoms",
+            error(r#"{"atoms": [{"element": "C"}], "bonds": [{"atoms": [0, 1]}]}"#)
+        );
+        assert_eq!(
+            "bond from atom 0 to itself",
+            error(r#"{"atoms": [{"element": "C"}], "bonds": [{"atoms": [0, 0]}]}"#)
+        );
+        assert_eq!(
+            r#"unknown bond order "double-ish""#,
+            error(
+                r#"{"atoms": [{"element": "C"}, {"element": "C"}],
+                    "bonds": [{"atoms": [0, 1], "order": "double-ish"}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn syntax() {
+        assert_eq!(
+            Ok(Value::Array(vec![
+                Value::Number(-1.5e3),
+                Value::String("a\"\n\u{1F600}".to_string()),
+                Value::Null,
+                Value::Bool(true),
+                Value::Object(vec![]),
+            ])),
+            parse(r#" [-1.5e3, "a\"\n😀", null, true, {}] "#)
+        );
+        assert_eq!(
+            Ok(Value::String("é😀".to_string())),
+            parse(r#""\u00e9\ud83d\ude00""#)
+        );
+        assert!(parse(r#""\ud83d""#).is_err());
+        assert_eq!(Err(JsonError::Syntax(1)), parse("[,]"));
+        assert_eq!(Err(JsonError::Syntax(5)), parse(r#"{"a" 1}"#));
+        assert_eq!(Err(JsonError::Syntax(0)), parse("-x"));
+        assert_eq!(Err(JsonError::Syntax(4)), parse(r#""abc"#));
+        assert!(parse(&"[".repeat(1000)).is_err());
+        assert_eq!(
+            Err(JsonError::Syntax(3)),
+            Mol::from_json("{} x").map(|mol| mol.num_atoms())
+        );
+    }
+}
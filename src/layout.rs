@@ -0,0 +1,549 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+
+use crate::rings::RingInfo;
+use crate::types::{AtomIdx, BondOrder, Mol};
+
+/// Length of the bonds in the generated coordinates, in Å.
+pub const BOND_LENGTH: f64 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    pub fn distance(self, other: Point) -> f64 {
+        libm::hypot(self.x - other.x, self.y - other.y)
+    }
+
+    fn from_polar(center: Point, radius: f64, angle: f64) -> Point {
+        Point::new(
+            center.x + radius * libm::cos(angle),
+            center.y + radius * libm::sin(angle),
+        )
+    }
+
+    /// Angle of the vector from `self` to `other`.
+    fn angle_to(self, other: Point) -> f64 {
+        libm::atan2(other.y - self.y, other.x - self.x)
+    }
+}
+
+/// 2D coordinates of a molecule's atoms, indexed like the atoms.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Conformer {
+    positions: Vec<Point>,
+}
+
+impl Conformer {
+    pub fn new(positions: Vec<Point>) -> Conformer {
+        Conformer { positions }
+    }
+
+    pub fn position(&self, atom_idx: AtomIdx) -> Option<Point> {
+        self.positions.get(atom_idx.index()).copied()
+    }
+
+    pub fn positions(&self) -> &[Point] {
+        &self.positions
+    }
+}
+
+/// Generates 2D coordinates for depiction.
+///
+/// Ring systems are placed from templates (regular polygons, fused along their shared
+/// bonds), chains grow from them in zigzag with substituents spread around each atom,
+/// and a short force-directed refinement pushes apart atoms that ended up too close.
+/// Disconnected components are laid out side by side.
+pub fn compute_2d_coords(mol: &Mol) -> Conformer {
+    let ring_info = mol.ring_info();
+    let mut layout = Layout {
+        mol,
+        ring_info: &ring_info,
+        systems: ring_info.ring_systems(),
+        positions: vec![None; mol.graph.node_count()],
+        turns: vec![1.0; mol.graph.node_count()],
+    };
+
+    let mut offset_x = 0.0;
+    for root in mol.graph.node_indices() {
+        if layout.positions[root.index()].is_some() {
+            continue;
+        }
+        let component = layout.place_component(root);
+        refine(mol, &mut layout.positions, &component);
+
+        // next to the previous component
+        let points: Vec<Point> = component
+            .iter()
+            .filter_map(|node| layout.positions[node.index()])
+            .collect();
+        let min_x = points.iter().map(|p| p.x).fold(f64::INFINITY, f64::min);
+        let max_x = points.iter().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max);
+        let min_y = points.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max);
+        let shift = Point::new(offset_x - min_x, -(min_y + max_y) / 2.0);
+        for node in &component {
+            if let Some(p) = &mut layout.positions[node.index()] {
+                p.x += shift.x;
+                p.y += shift.y;
+            }
+        }
+        offset_x += max_x - min_x + 2.0 * BOND_LENGTH;
+    }
+
+    Conformer {
+        positions: layout
+            .positions
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect(),
+    }
+}
+
+struct Layout<'a> {
+    mol: &'a Mol,
+    ring_info: &'a RingInfo,
+    systems: Vec<Vec<usize>>, // ring indices of each fused ring system
+    positions: Vec<Option<Point>>,
+    // side chains turn to alternate sides, for zigzag
+    turns: Vec<f64>,
+}
+
+impl Layout<'_> {
+    /// Places the component of `root`, returns its atoms.
+    fn place_component(&mut self, root: NodeIndex) -> Vec<NodeIndex> {
+        self.positions[root.index()] = Some(Point::default());
+
+        let mut component = vec![];
+        let mut queue = VecDeque::from([root]);
+        let mut queued = vec![false; self.mol.graph.node_count()];
+        queued[root.index()] = true;
+        while let Some(node) = queue.pop_front() {
+            component.push(node);
+            self.place_substituents(node);
+            for neighbor in self.mol.graph.neighbors(node) {
+                if !queued[neighbor.index()] {
+                    queued[neighbor.index()] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        component
+    }
+
+    /// Places the ring systems containing the (placed) atom, with their first ring's center
+    /// in direction `angle` from the atom.
+    fn place_ring_systems_of(&mut self, node: NodeIndex, angle: f64) {
+        let systems: Vec<Vec<usize>> = self
+            .systems
+            .iter()
+            .filter(|system| {
+                system
+                    .iter()
+                    .any(|ring| self.ring_info.rings()[*ring].atoms.contains(&node.into()))
+            })
+            .cloned()
+            .collect();
+        for system in systems {
+            self.place_ring_system(&system, node, angle);
+        }
+    }
+
+    fn place_ring_system(&mut self, system: &[usize], anchor: NodeIndex, angle: f64) {
+        let mut remaining: Vec<usize> = system.to_vec();
+        while !remaining.is_empty() {
+            // continue with the ring that has the most atoms placed
+            // unwrap: not empty
+            let (i, _) = remaining
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, ring)| self.placed_count(**ring))
+                .unwrap();
+            let ring = remaining.remove(i);
+            let atoms: Vec<usize> = self.ring_info.rings()[ring]
+                .atoms
+                .iter()
+                .map(|atom| atom.index())
+                .collect();
+            let placed: Vec<usize> = (0..atoms.len())
+                .filter(|i| self.positions[atoms[*i]].is_some())
+                .collect();
+            match placed.len() {
+                0 => {}
+                1 => {
+                    let at = placed[0];
+                    let center_angle = if atoms[at] == anchor.index() {
+                        angle
+                    } else {
+                        self.free_angle(NodeIndex::new(atoms[at]))
+                    };
+                    self.place_ring_from_atom(&atoms, at, center_angle);
+                }
+                2 => self.place_fused_ring(&atoms, placed[0], placed[1]),
+                // bridged: the remaining atoms are placed as substituents
+                _ => {}
+            }
+        }
+    }
+
+    fn placed_count(&self, ring: usize) -> usize {
+        self.ring_info.rings()[ring]
+            .atoms
+            .iter()
+            .filter(|atom| self.positions[atom.index()].is_some())
+            .count()
+    }
+
+    /// Regular polygon through the placed atom `atoms[at]`, centered in direction `angle`.
+    fn place_ring_from_atom(&mut self, atoms: &[usize], at: usize, angle: f64) {
+        // unwrap: the atom is placed
+        let start = self.positions[atoms[at]].unwrap();
+        let size = atoms.len();
+        let radius = BOND_LENGTH / (2.0 * libm::sin(PI / size as f64));
+        let center = Point::from_polar(start, radius, angle);
+        let start_angle = center.angle_to(start);
+        for step in 1..size {
+            let atom = atoms[(at + step) % size];
+            if self.positions[atom].is_none() {
+                let angle = start_angle + 2.0 * PI * step as f64 / size as f64;
+                self.positions[atom] = Some(Point::from_polar(center, radius, angle));
+            }
+        }
+    }
+
+    /// Regular polygon on the shared bond `atoms[a]`-`atoms[b]`, on the side away from
+    /// the atoms already placed. Bridged rings (not a bond) are left to substituent placement.
+    fn place_fused_ring(&mut self, atoms: &[usize], a: usize, b: usize) {
+        let size = atoms.len();
+        let (first, second) = if (a + 1) % size == b {
+            (a, b)
+        } else if (b + 1) % size == a {
+            (b, a)
+        } else {
+            return;
+        };
+        // unwrap: both atoms are placed
+        let p1 = self.positions[atoms[first]].unwrap();
+        let p2 = self.positions[atoms[second]].unwrap();
+        let middle = Point::new((p1.x + p2.x) / 2.0, (p1.y + p2.y) / 2.0);
+        let normal = p1.angle_to(p2) + PI / 2.0;
+
+        // the side with fewer placed atoms nearby
+        let placed: Vec<Point> = self.positions.iter().flatten().copied().collect();
+        let crowding = |angle: f64| {
+            let probe = Point::from_polar(middle, BOND_LENGTH, angle);
+            placed
+                .iter()
+                .map(|p| 1.0 / (p.distance(probe) * p.distance(probe) + 0.01))
+                .sum::<f64>()
+        };
+        let side = if crowding(normal) <= crowding(normal + PI) {
+            normal
+        } else {
+            normal + PI
+        };
+
+        let radius = BOND_LENGTH / (2.0 * libm::sin(PI / size as f64));
+        let apothem = BOND_LENGTH / (2.0 * libm::tan(PI / size as f64));
+        let center = Point::from_polar(middle, apothem, side);
+        let start_angle = center.angle_to(p1);
+        let step = 2.0 * PI / size as f64;
+        // going from `first` to `second` turns one step; keep that direction
+        let direction = if normalize(center.angle_to(p2) - start_angle) > 0.0 {
+            1.0
+        } else {
+            -1.0
+        };
+        for i in 2..size {
+            let atom = atoms[(first + i) % size];
+            if self.positions[atom].is_none() {
+                let angle = start_angle + direction * step * i as f64;
+                self.positions[atom] = Some(Point::from_polar(center, radius, angle));
+            }
+        }
+    }
+
+    /// Direction pointing away from the placed neighbors of a placed atom.
+    fn free_angle(&self, node: NodeIndex) -> f64 {
+        // unwrap: the atom is placed
+        let position = self.positions[node.index()].unwrap();
+        let (mut x, mut y) = (0.0, 0.0);
+        for neighbor in self.mol.graph.neighbors(node) {
+            if let Some(p) = self.positions[neighbor.index()] {
+                let angle = position.angle_to(p);
+                x -= libm::cos(angle);
+                y -= libm::sin(angle);
+            }
+        }
+        if x.abs() < 1e-6 && y.abs() < 1e-6 {
+            0.0
+        } else {
+            libm::atan2(y, x)
+        }
+    }
+
+    /// Places the unplaced neighbors of a placed atom, and the ring systems they start.
+    fn place_substituents(&mut self, node: NodeIndex) {
+        // e.g. the other ring of a spiro atom
+        self.place_ring_systems_of(node, self.free_angle(node));
+
+        // unwrap: atoms are placed before their substituents
+        let position = self.positions[node.index()].unwrap();
+        let graph = &self.mol.graph;
+        let placed: Vec<NodeIndex> = graph
+            .neighbors(node)
+            .filter(|n| self.positions[n.index()].is_some())
+            .collect();
+        let mut unplaced: Vec<NodeIndex> = graph
+            .neighbors(node)
+            .filter(|n| self.positions[n.index()].is_none())
+            .collect();
+        if unplaced.is_empty() {
+            return;
+        }
+        unplaced.sort();
+
+        let count = unplaced.len();
+        let angles: Vec<f64> = match placed.as_slice() {
+            [] => (0..count)
+                .map(|i| -PI / 6.0 + 2.0 * PI * i as f64 / count as f64)
+                .collect(),
+            [parent] => {
+                // unwrap: placed
+                let back = position.angle_to(self.positions[parent.index()].unwrap());
+                if is_linear(self.mol, node) && count == 1 {
+                    vec![back + PI]
+                } else if count == 1 {
+                    let turn = -self.turns[parent.index()];
+                    self.turns[node.index()] = turn;
+                    vec![back + turn * 2.0 * PI / 3.0]
+                } else {
+                    let spacing = 2.0 * PI / (count + 1) as f64;
+                    (1..=count).map(|i| back + spacing * i as f64).collect()
+                }
+            }
+            _ => {
+                let away = self.free_angle(node);
+                (0..count)
+                    .map(|i| away + (i as f64 - (count - 1) as f64 / 2.0) * PI / 3.0)
+                    .collect()
+            }
+        };
+
+        for (neighbor, angle) in unplaced.into_iter().zip(angles) {
+            let point = Point::from_polar(position, BOND_LENGTH, angle);
+            self.positions[neighbor.index()] = Some(point);
+            self.place_ring_systems_of(neighbor, angle);
+        }
+    }
+}
+
+/// Sp atoms: a triple bond, or two double bonds.
+fn is_linear(mol: &Mol, node: NodeIndex) -> bool {
+    let orders: Vec<BondOrder> = mol.graph.edges(node).map(|e| e.weight().order).collect();
+    orders.contains(&BondOrder::Triple)
+        || orders.iter().filter(|o| **o == BondOrder::Double).count() == 2
+}
+
+fn normalize(angle: f64) -> f64 {
+    let mut angle = angle % (2.0 * PI);
+    if angle > PI {
+        angle -= 2.0 * PI;
+    } else if angle < -PI {
+        angle += 2.0 * PI;
+    }
+    angle
+}
+
+/// Force-directed refinement: bonds are springs of `BOND_LENGTH`, and atoms closer than
+/// a bond length repel each other. Does nothing on layouts without clashes or stretched
+/// bonds, as templates and substituent placement give for most molecules.
+fn refine(mol: &Mol, positions: &mut [Option<Point>], component: &[NodeIndex]) {
+    let bonds: Vec<EdgeIndex> = mol
+        .graph
+        .edge_indices()
+        .filter(|edge| {
+            // unwrap: edge comes from the graph
+            let (a, _) = mol.graph.edge_endpoints(*edge).unwrap();
+            component.contains(&a)
+        })
+        .collect();
+    let clash = 0.9 * BOND_LENGTH;
+
+    for _ in 0..500 {
+        let mut forces = vec![(0.0, 0.0); positions.len()];
+        let mut clashes = false;
+        for (i, a) in component.iter().enumerate() {
+            for b in &component[i + 1..] {
+                // unwrap: the component is placed
+                let (pa, pb) = (positions[a.index()].unwrap(), positions[b.index()].unwrap());
+                let distance = pa.distance(pb);
+                if distance >= clash || mol.graph.contains_edge(*a, *b) {
+                    continue;
+                }
+                clashes = true;
+                let angle = if distance < 1e-6 {
+                    // same position: push apart in an arbitrary, deterministic direction
+                    (a.index() + b.index()) as f64
+                } else {
+                    pa.angle_to(pb)
+                };
+                let push = (clash - distance) / 2.0;
+                forces[a.index()].0 -= push * libm::cos(angle);
+                forces[a.index()].1 -= push * libm::sin(angle);
+                forces[b.index()].0 += push * libm::cos(angle);
+                forces[b.index()].1 += push * libm::sin(angle);
+            }
+        }
+        let mut strained = false;
+        for edge in &bonds {
+            // unwrap: edge comes from the graph
+            let (a, b) = mol.graph.edge_endpoints(*edge).unwrap();
+            let (pa, pb) = (positions[a.index()].unwrap(), positions[b.index()].unwrap());
+            let angle = pa.angle_to(pb);
+            let stretch = (pa.distance(pb) - BOND_LENGTH) / 2.0;
+            strained |= stretch.abs() > 0.025 * BOND_LENGTH;
+            forces[a.index()].0 += stretch * libm::cos(angle);
+            forces[a.index()].1 += stretch * libm::sin(angle);
+            forces[b.index()].0 -= stretch * libm::cos(angle);
+            forces[b.index()].1 -= stretch * libm::sin(angle);
+        }
+        if !clashes && !strained {
+            return;
+        }
+        for node in component {
+            if let Some(p) = &mut positions[node.index()] {
+                p.x += 0.5 * forces[node.index()].0;
+                p.y += 0.5 * forces[node.index()].1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    use super::{Conformer, Point, BOND_LENGTH};
+
+    fn layout(smiles: &str) -> (Mol, Conformer) {
+        let parser = SmilesParser::new();
+        let mol = parser.parse(smiles).unwrap();
+        let conformer = mol.compute_2d_coords();
+        (mol, conformer)
+    }
+
+    fn assert_bond_lengths(smiles: &str) {
+        let (mol, conformer) = layout(smiles);
+        for bond in mol.graph.edge_indices() {
+            let (start, end) = mol.bond_atoms(bond.into()).unwrap();
+            let a = conformer.position(start).unwrap();
+            let b = conformer.position(end).unwrap();
+            assert!(
+                (a.distance(b) - BOND_LENGTH).abs() < 0.1,
+                "{}: bond {}-{} has length {}",
+                smiles,
+                start,
+                end,
+                a.distance(b)
+            );
+        }
+    }
+
+    fn min_distance(smiles: &str) -> f64 {
+        let (_, conformer) = layout(smiles);
+        let positions = conformer.positions();
+        let mut min = f64::INFINITY;
+        for (i, a) in positions.iter().enumerate() {
+            for b in &positions[i + 1..] {
+                min = min.min(a.distance(*b));
+            }
+        }
+        min
+    }
+
+    #[test]
+    fn single_atom() {
+        let (_, conformer) = layout("C");
+
+        assert_eq!(vec![Point::new(0.0, 0.0)], conformer.positions());
+    }
+
+    #[test]
+    fn chain_zigzag() {
+        let (_, conformer) = layout("CCCC");
+        let p = conformer.positions();
+
+        assert_bond_lengths("CCCC");
+        // 120° angles, alternating sides
+        assert!((p[0].distance(p[2]) - BOND_LENGTH * 3f64.sqrt()).abs() < 1e-6);
+        assert!((p[0].distance(p[3]) - BOND_LENGTH * 7f64.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn linear_atoms() {
+        let (_, conformer) = layout("CC#CC");
+        let p = conformer.positions();
+
+        assert!((p[0].distance(p[3]) - 3.0 * BOND_LENGTH).abs() < 0.1);
+    }
+
+    #[test]
+    fn linear_atom_with_more_neighbors() {
+        // a triple bond and two more neighbors, not drawn linear
+        assert_bond_lengths("C[O-](#C)N");
+    }
+
+    #[test]
+    fn regular_rings() {
+        let (_, conformer) = layout("c1ccccc1");
+        let p = conformer.positions();
+        let center = Point::new(
+            p.iter().map(|p| p.x).sum::<f64>() / 6.0,
+            p.iter().map(|p| p.y).sum::<f64>() / 6.0,
+        );
+
+        for point in p {
+            assert!((point.distance(center) - BOND_LENGTH).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn fused_and_substituted_rings() {
+        for smiles in [
+            "c1ccc2ccccc2c1",
+            "c1ccc2cc3ccccc3cc2c1",
+            "c1cc2cccccc2c1",
+            "CC(C)Cc1ccc(cc1)C(C)C(=O)O",
+            "C1CC12CCC2",
+            "c1ccccc1-c1ccccc1",
+        ] {
+            assert_bond_lengths(smiles);
+            assert!(min_distance(smiles) > 0.5 * BOND_LENGTH, "{}", smiles);
+        }
+    }
+
+    #[test]
+    fn no_clashes() {
+        for smiles in [
+            "CC(C)(C)C",
+            "CC(C)(C)C(C)(C)C",
+            "C1CC2CCC1C2",
+            "OC(=O)C1CCCCC1N",
+        ] {
+            assert!(min_distance(smiles) > 0.5 * BOND_LENGTH, "{}", smiles);
+        }
+    }
+}
@@ -0,0 +1,723 @@
+//! Splits SMILES into tokens, each with the span of input it was read from.
+
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::error::{SmilesError, SmilesErrorKind};
+use crate::scanner::{Action, Error, Scanner};
+use crate::smiles::{atom_number, is_symbol, is_symbol_prefix, SmilesParserOptions, WILDCARD};
+use crate::types::{Atom, BondDirection, BondOrder, Chirality};
+
+/// Deuterium and tritium shorthands, with their mass number.
+static HYDROGEN_ISOTOPES: [(&str, u16); 2] = [("D", 2), ("T", 3)];
+
+/// Characters `start` (inclusive) to `end` (exclusive) of the input, or bytes where
+/// documented so, for slicing the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Converts the character spans of the lexer to byte spans of its input.
+pub(crate) struct ByteOffsets {
+    // byte offset of each character and of the end, empty for ASCII input
+    offsets: Vec<usize>,
+}
+
+impl ByteOffsets {
+    pub(crate) fn new(input: &str) -> ByteOffsets {
+        let offsets = if input.is_ascii() {
+            vec![]
+        } else {
+            let chars = input.char_indices().map(|(offset, _)| offset);
+            chars.chain([input.len()]).collect()
+        };
+        ByteOffsets { offsets }
+    }
+
+    pub(crate) fn span(&self, span: Span) -> Span {
+        if self.offsets.is_empty() {
+            return span;
+        }
+        Span {
+            start: self.offsets[span.start],
+            end: self.offsets[span.end],
+        }
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// An atom of the organic subset, e.g. `C`, `Cl`, `c`, or the wildcard `*`.
+    OrganicAtom(Atom),
+    /// An atom in brackets, e.g. `[13CH4]` or `[NH4+]`.
+    BracketAtom(Atom),
+    /// A ring bond number, `1` or `%12`.
+    RingBond(u16),
+    BranchOpen,
+    BranchClose,
+    Bond(BondSymbol),
+    /// Separates disconnected fragments.
+    Dot,
+}
+
+/// A bond symbol as written: `-`, `=`, `#`, `$`, `:`, `/` or `\`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BondSymbol {
+    pub order: BondOrder,
+    pub direction: Option<BondDirection>,
+}
+
+impl BondSymbol {
+    pub fn from_char(c: char) -> Option<BondSymbol> {
+        let direction = match c {
+            '/' => Some(BondDirection::Up),
+            '\\' => Some(BondDirection::Down),
+            _ => None,
+        };
+        let order = match direction {
+            Some(_) => BondOrder::Single,
+            None => BondOrder::from_symbol(c)?,
+        };
+        Some(BondSymbol { order, direction })
+    }
+
+    /// The character the symbol is written with.
+    pub fn to_char(self) -> char {
+        match (self.direction, self.order) {
+            (Some(BondDirection::Up), _) => '/',
+            (Some(BondDirection::Down), _) => '\\',
+            (None, BondOrder::Single) => '-',
+            (None, BondOrder::Double) => '=',
+            (None, BondOrder::Triple) => '#',
+            (None, BondOrder::Quadruple) => '$',
+            (None, BondOrder::Aromatic) => ':',
+        }
+    }
+
+    /// The same bond, seen from its other atom.
+    pub fn reversed(self) -> BondSymbol {
+        BondSymbol {
+            order: self.order,
+            direction: self.direction.map(BondDirection::inverted),
+        }
+    }
+
+    /// Whether a ring bond opened with this symbol can be closed with `end`: the same
+    /// order and, if both ends have one, the same direction seen from the opening.
+    pub(crate) fn matches_ring_end(self, end: BondSymbol) -> bool {
+        let end = end.reversed();
+        let directions_agree = match (self.direction, end.direction) {
+            (Some(start), Some(end)) => start == end,
+            _ => true,
+        };
+        self.order == end.order && directions_agree
+    }
+}
+
+/// Reads the tokens of a SMILES string, in order. Only checks that each token is well
+/// formed, e.g. not that parentheses are balanced. Stops after the first error.
+pub struct Lexer<'a> {
+    scanner: Scanner<'a>,
+    options: SmilesParserOptions,
+    failed: bool,
+    // the token that failed is a bracket atom
+    failed_bracket: bool,
+}
+
+impl<'a> Lexer<'a> {
+    /// The options are those of the parser, of which the lexer uses `strict`.
+    pub fn new(smiles: &'a str, options: SmilesParserOptions) -> Lexer<'a> {
+        Lexer {
+            scanner: Scanner::new(smiles),
+            options,
+            failed: false,
+            failed_bracket: false,
+        }
+    }
+
+    /// Position of the next token, the input length at the end.
+    pub fn position(&self) -> usize {
+        self.scanner.cursor()
+    }
+
+    /// After an error, skips the rest of the bracket atom that failed, up to its `]`, so
+    /// that lexing goes on after it. Other tokens fail at their first character, which
+    /// was already read.
+    pub(crate) fn recover(&mut self) {
+        if self.failed_bracket {
+            while self.scanner.pop().is_some_and(|c| c != ']') {}
+        }
+        self.failed = false;
+    }
+
+    fn token(&mut self, c: char) -> Result<TokenKind, SmilesError> {
+        let position = self.scanner.cursor() - 1;
+        let kind = match c {
+            'B' | 'C' | 'N' | 'O' | 'P' | 'S' | 'F' | 'I' | 'b' | 'c' | 'n' | 'o' | 'p' | 's' => {
+                let symbol = organic_symbol(c, &mut self.scanner);
+                let number = atom_number(symbol).ok_or_else(|| {
+                    SmilesError::new(
+                        SmilesErrorKind::UnknownElement(symbol.to_string()),
+                        position,
+                    )
+                })?;
+                TokenKind::OrganicAtom(Atom {
+                    number,
+                    aromatic: c.is_ascii_lowercase(),
+                    ..Default::default()
+                })
+            }
+            '*' => TokenKind::OrganicAtom(Atom {
+                number: WILDCARD,
+                ..Default::default()
+            }),
+            '[' => TokenKind::BracketAtom(bracket_atom(&mut self.scanner, &self.options)?),
+            '%' if self.scanner.peek() == Some('(') => {
+                TokenKind::RingBond(extended_ring_number(&mut self.scanner, &self.options)?)
+            }
+            '%' => TokenKind::RingBond(ring_number(&mut self.scanner)?.into()),
+            // unwrap: matched a digit
+            '0'..='9' => TokenKind::RingBond(c.to_digit(10).unwrap() as u16),
+            '(' => TokenKind::BranchOpen,
+            ')' => TokenKind::BranchClose,
+            '.' => TokenKind::Dot,
+            _ => match BondSymbol::from_char(c) {
+                Some(symbol) => TokenKind::Bond(symbol),
+                None => {
+                    return Err(SmilesError::new(
+                        SmilesErrorKind::UnexpectedCharacter(c),
+                        position,
+                    ))
+                }
+            },
+        };
+        Ok(kind)
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, SmilesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let start = self.scanner.cursor();
+        let c = self.scanner.pop()?;
+        let token = self.token(c).map(|kind| Token {
+            kind,
+            span: Span {
+                start,
+                end: self.scanner.cursor(),
+            },
+        });
+        self.failed = token.is_err();
+        self.failed_bracket = self.failed && c == '[';
+        Some(token)
+    }
+}
+
+/// The tokens of the SMILES with the bytes each is read from, e.g. for syntax
+/// highlighting: `&smiles[span]` is the token, even after non-ASCII characters. Tokens
+/// that can't be read are skipped, a bracket atom up to its `]`: their problems are
+/// reported by [`validate`](crate::validate).
+pub fn tokenize_with_spans(smiles: &str) -> Vec<(TokenKind, Range<usize>)> {
+    let mut lexer = Lexer::new(smiles, SmilesParserOptions::default());
+    let offsets = ByteOffsets::new(smiles);
+    let mut tokens = vec![];
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(token) => tokens.push((token.kind, offsets.span(token.span).into())),
+            Err(_) => lexer.recover(),
+        }
+    }
+    tokens
+}
+
+/// Reads the rest of an organic subset symbol, given its first character.
+/// Only `Cl` and `Br` have a second character.
+fn organic_symbol(first: char, scanner: &mut Scanner) -> &'static str {
+    match first {
+        'C' if scanner.take(&'l') => "Cl",
+        'B' if scanner.take(&'r') => "Br",
+        'B' => "B",
+        'C' => "C",
+        'N' => "N",
+        'O' => "O",
+        'P' => "P",
+        'S' => "S",
+        'F' => "F",
+        'I' => "I",
+        'b' => "b",
+        'c' => "c",
+        'n' => "n",
+        'o' => "o",
+        'p' => "p",
+        's' => "s",
+        _ => unreachable!("not an organic subset symbol: {first}"),
+    }
+}
+
+/// Parses a bracket atom, e.g. `[13CH4]`, `[NH4+]`, `[Fe+2]`, `[CH3:1]`, `[*:1]`.
+/// `[D]` and `[T]` are read as `[2H]` and `[3H]`.
+/// Expects the opening `[` to have been consumed already.
+pub fn bracket_atom(
+    scanner: &mut Scanner,
+    options: &SmilesParserOptions,
+) -> Result<Atom, SmilesError> {
+    let isotope = match number(scanner) {
+        Some(isotope) => Some(in_range(isotope, scanner)?),
+        None => None,
+    };
+
+    let symbol_position = scanner.cursor();
+    let symbol = match scanner.scan(|sequence| {
+        if is_symbol(sequence)
+            || sequence == "*"
+            || HYDROGEN_ISOTOPES.iter().any(|(s, _)| *s == sequence)
+        {
+            Some(Action::Request(sequence))
+        } else if is_symbol_prefix(sequence) {
+            Some(Action::Require)
+        } else {
+            None
+        }
+    }) {
+        Ok(Some(symbol)) => symbol,
+        Ok(None) => return Err(unexpected(scanner, "element symbol")),
+        Err(Error::Character(position)) => {
+            return Err(SmilesError::expected(
+                SmilesErrorKind::UnknownElement(scanner.slice(symbol_position, position + 1)),
+                symbol_position,
+                "element symbol",
+            ))
+        }
+        Err(Error::EndOfLine) => {
+            return Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedEnd,
+                scanner.cursor(),
+                "element symbol",
+            ))
+        }
+    };
+
+    let chirality = chirality(scanner)?;
+
+    let explicit_h = if scanner.take(&'H') {
+        let count_position = scanner.cursor();
+        let count = match number(scanner) {
+            Some(count) => in_range(count, scanner)?,
+            None => 1,
+        };
+        // OpenSMILES counts hydrogens with a single digit
+        if options.strict && scanner.cursor() > count_position + 1 {
+            return Err(SmilesError::expected(
+                // unwrap: the count has more than one digit
+                SmilesErrorKind::UnexpectedCharacter(
+                    scanner
+                        .slice(count_position + 1, count_position + 2)
+                        .chars()
+                        .next()
+                        .unwrap(),
+                ),
+                count_position + 1,
+                "charge, atom map number or ']'",
+            ));
+        }
+        count
+    } else {
+        0
+    };
+
+    let charge_position = scanner.cursor();
+    let charge = charge(scanner)?;
+    // `++` and `--` are deprecated, `+2` and `-2` are the standard forms
+    if options.strict {
+        let written = scanner.slice(charge_position, scanner.cursor());
+        if written.len() > 1 && !written.contains(|c: char| c.is_ascii_digit()) {
+            return Err(SmilesError::expected(
+                // unwrap: checked the length
+                SmilesErrorKind::UnexpectedCharacter(written.chars().nth(1).unwrap()),
+                charge_position + 1,
+                "charge count",
+            ));
+        }
+    }
+
+    let map_number = if scanner.take(&':') {
+        match number(scanner) {
+            Some(map_number) => Some(in_range(map_number, scanner)?),
+            None => return Err(unexpected(scanner, "atom map number")),
+        }
+    } else {
+        None
+    };
+
+    if !scanner.take(&']') {
+        return Err(unexpected(scanner, "']'"));
+    }
+
+    let (number, isotope) = match HYDROGEN_ISOTOPES.iter().find(|(s, _)| *s == symbol) {
+        Some(_) if options.strict => {
+            return Err(SmilesError::expected(
+                SmilesErrorKind::UnknownElement(symbol.to_string()),
+                symbol_position,
+                "element symbol",
+            ))
+        }
+        Some((_, mass)) => (1, isotope.or(Some(*mass))),
+        None if symbol == "*" => (WILDCARD, isotope),
+        // unwrap: the scanned symbol is a known element
+        None => (atom_number(symbol).unwrap(), isotope),
+    };
+
+    Ok(Atom {
+        number,
+        aromatic: symbol.starts_with(|c: char| c.is_ascii_lowercase()),
+        isotope,
+        charge,
+        explicit_h: Some(explicit_h),
+        map_number,
+        chirality,
+        ..Default::default()
+    })
+}
+
+/// Parses the two digits of a `%nn` ring number. Expects the `%` to have been consumed already.
+pub fn ring_number(scanner: &mut Scanner) -> Result<u8, SmilesError> {
+    let ring = scanner.scan(|sequence| match sequence.len() {
+        1 if sequence.chars().all(|c| c.is_ascii_digit()) => Some(Action::Require),
+        2 if sequence.chars().all(|c| c.is_ascii_digit()) => {
+            sequence.parse::<u8>().ok().map(Action::Return)
+        }
+        _ => None,
+    });
+    match ring {
+        Ok(Some(ring)) => Ok(ring),
+        // the cursor is left at the offending character
+        _ => Err(unexpected(scanner, "two digit ring number")),
+    }
+}
+
+/// Parses a `%(n)` ring number of one to three digits, which OpenSMILES doesn't define
+/// but toolkits write past `%99`. Rejected in strict mode. Expects the `%` to have been
+/// consumed already.
+fn extended_ring_number(
+    scanner: &mut Scanner,
+    options: &SmilesParserOptions,
+) -> Result<u16, SmilesError> {
+    if options.strict {
+        // at the `%`
+        return Err(SmilesError::new(
+            SmilesErrorKind::ExtendedRingNumber,
+            scanner.cursor() - 1,
+        ));
+    }
+    scanner.take(&'(');
+    let ring = scanner.scan(|sequence| {
+        let digits = sequence.len() <= 3 && sequence.chars().all(|c| c.is_ascii_digit());
+        digits.then(|| sequence.parse::<u16>().ok().map(Action::Request))?
+    });
+    let Ok(Some(ring)) = ring else {
+        return Err(unexpected(scanner, "ring number"));
+    };
+    if !scanner.take(&')') {
+        return Err(unexpected(scanner, "')'"));
+    }
+    Ok(ring)
+}
+
+/// Parses tetrahedral chirality: `@`, `@@`, or the explicit `@TH1` / `@TH2`.
+fn chirality(scanner: &mut Scanner) -> Result<Option<Chirality>, SmilesError> {
+    if !scanner.take(&'@') {
+        return Ok(None);
+    }
+    if scanner.take(&'@') {
+        return Ok(Some(Chirality::Clockwise));
+    }
+    if scanner.take(&'T') {
+        if !scanner.take(&'H') {
+            return Err(unexpected(scanner, "'H'"));
+        }
+        return if scanner.take(&'1') {
+            Ok(Some(Chirality::Anticlockwise))
+        } else if scanner.take(&'2') {
+            Ok(Some(Chirality::Clockwise))
+        } else {
+            Err(unexpected(scanner, "'1' or '2'"))
+        };
+    }
+    Ok(Some(Chirality::Anticlockwise))
+}
+
+/// Parses a charge: `+`, `-`, `+2`, `-3`, or the deprecated `++` / `--`.
+pub fn charge(scanner: &mut Scanner) -> Result<i8, SmilesError> {
+    let sign = if scanner.take(&'+') {
+        1
+    } else if scanner.take(&'-') {
+        -1
+    } else {
+        return Ok(0);
+    };
+    if let Some(n) = number(scanner) {
+        let n: i8 = in_range(n, scanner)?;
+        return Ok(sign * n);
+    }
+    let mut charge = sign;
+    while scanner.take(if sign > 0 { &'+' } else { &'-' }) {
+        charge = charge.checked_add(sign).ok_or_else(|| {
+            SmilesError::new(SmilesErrorKind::NumberOutOfRange, scanner.cursor() - 1)
+        })?;
+    }
+    Ok(charge)
+}
+
+/// Parses a (possibly multi-digit) unsigned number, saturating: check it with [`in_range`].
+pub fn number(scanner: &mut Scanner) -> Option<u64> {
+    let mut number: Option<u64> = None;
+    while let Some(digit) = scanner.transform(|c| c.to_digit(10)) {
+        number = Some(
+            number
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit.into()),
+        );
+    }
+    number
+}
+
+pub fn in_range<T: TryFrom<u64>>(number: u64, scanner: &Scanner) -> Result<T, SmilesError> {
+    T::try_from(number)
+        .map_err(|_| SmilesError::new(SmilesErrorKind::NumberOutOfRange, scanner.cursor() - 1))
+}
+
+/// Error for the character at the cursor, or for the end of the input.
+pub fn unexpected(scanner: &Scanner, expected: &'static str) -> SmilesError {
+    match scanner.peek() {
+        Some(c) => SmilesError::expected(
+            SmilesErrorKind::UnexpectedCharacter(c),
+            scanner.cursor(),
+            expected,
+        ),
+        None => SmilesError::expected(SmilesErrorKind::UnexpectedEnd, scanner.cursor(), expected),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{SmilesError, SmilesErrorKind};
+    use crate::smiles::SmilesParserOptions;
+    use crate::types::{Atom, BondDirection, BondOrder};
+
+    use super::{tokenize_with_spans, BondSymbol, Lexer, Span, Token, TokenKind};
+
+    fn tokens(smiles: &str) -> Result<Vec<Token>, SmilesError> {
+        Lexer::new(smiles, SmilesParserOptions::default()).collect()
+    }
+
+    fn kinds(smiles: &str) -> Vec<TokenKind> {
+        tokens(smiles)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    fn organic(number: u32, aromatic: bool) -> TokenKind {
+        TokenKind::OrganicAtom(Atom {
+            number,
+            aromatic,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn token_kinds() {
+        assert_eq!(
+            vec![
+                organic(6, false),
+                TokenKind::BranchOpen,
+                TokenKind::Bond(BondSymbol {
+                    order: BondOrder::Double,
+                    direction: None
+                }),
+                organic(8, false),
+                TokenKind::BranchClose,
+                organic(17, false),
+                TokenKind::Dot,
+                organic(6, true),
+                TokenKind::RingBond(1),
+                organic(6, true),
+                TokenKind::RingBond(12),
+                TokenKind::Bond(BondSymbol {
+                    order: BondOrder::Single,
+                    direction: Some(BondDirection::Down)
+                }),
+                organic(0, false),
+            ],
+            kinds("C(=O)Cl.c1c%12\\*")
+        );
+        assert_eq!(
+            vec![TokenKind::BracketAtom(Atom {
+                number: 7,
+                charge: 1,
+                explicit_h: Some(4),
+                ..Default::default()
+            })],
+            kinds("[NH4+]")
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let spans: Vec<Span> = tokens("Br[13CH3]%10")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.span)
+            .collect();
+
+        assert_eq!(
+            vec![
+                Span { start: 0, end: 2 },
+                Span { start: 2, end: 9 },
+                Span { start: 9, end: 12 },
+            ],
+            spans
+        );
+    }
+
+    #[test]
+    fn spans_in_bytes() {
+        for smiles in ["Br[13CH3]%10", "Cé(CO", "C€[NH4+]ü=O", "ééC1CC1"] {
+            let tokens = tokenize_with_spans(smiles);
+            let written: Vec<&str> = tokens
+                .iter()
+                .map(|(_, span)| &smiles[span.clone()])
+                .collect();
+            let expected: Vec<&str> = match smiles {
+                "Br[13CH3]%10" => vec!["Br", "[13CH3]", "%10"],
+                "Cé(CO" => vec!["C", "(", "C", "O"],
+                "C€[NH4+]ü=O" => vec!["C", "[NH4+]", "=", "O"],
+                _ => vec!["C", "1", "C", "C", "1"],
+            };
+            assert_eq!(expected, written, "{}", smiles);
+        }
+        assert_eq!(3..4, tokenize_with_spans("Cé(CO")[1].1);
+    }
+
+    #[test]
+    fn errors() {
+        let mut lexer = Lexer::new("C?C", SmilesParserOptions::default());
+        assert!(lexer.next().unwrap().is_ok());
+        assert_eq!(
+            Some(Err(SmilesError::new(
+                SmilesErrorKind::UnexpectedCharacter('?'),
+                1
+            ))),
+            lexer.next()
+        );
+        // stops after the error
+        assert_eq!(None, lexer.next());
+
+        // but doesn't check the structure
+        assert_eq!(3, kinds(")=(").len());
+
+        // a charge as more signs than an i8 holds
+        let smiles = format!("[C{}]", "+".repeat(128));
+        let mut lexer = Lexer::new(&smiles, SmilesParserOptions::default());
+        assert_eq!(
+            Some(Err(SmilesError::new(
+                SmilesErrorKind::NumberOutOfRange,
+                129
+            ))),
+            lexer.next()
+        );
+    }
+
+    #[test]
+    fn extended_ring_numbers() {
+        assert_eq!(
+            vec![
+                organic(6, false),
+                TokenKind::RingBond(123),
+                TokenKind::RingBond(5),
+            ],
+            kinds("C%(123)%(5)")
+        );
+
+        for (smiles, error) in [
+            (
+                "C%(",
+                SmilesError::expected(SmilesErrorKind::UnexpectedEnd, 3, "ring number"),
+            ),
+            (
+                "C%()",
+                SmilesError::expected(SmilesErrorKind::UnexpectedCharacter(')'), 3, "ring number"),
+            ),
+            (
+                "C%(1234)",
+                SmilesError::expected(SmilesErrorKind::UnexpectedCharacter('4'), 6, "')'"),
+            ),
+        ] {
+            assert_eq!(Err(error), tokens(smiles).map(|_| ()), "{}", smiles);
+        }
+
+        // not OpenSMILES
+        let options = SmilesParserOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let mut lexer = Lexer::new("C%(123)", options);
+        lexer.next();
+        assert_eq!(
+            Some(Err(SmilesError::new(
+                SmilesErrorKind::ExtendedRingNumber,
+                1
+            ))),
+            lexer.next()
+        );
+    }
+
+    #[test]
+    fn bond_symbols() {
+        for c in ['-', '=', '#', '$', ':', '/', '\\'] {
+            assert_eq!(Some(c), BondSymbol::from_char(c).map(BondSymbol::to_char));
+        }
+        assert_eq!(None, BondSymbol::from_char('~'));
+    }
+
+    #[test]
+    fn tokens_with_spans() {
+        let tokens = tokenize_with_spans("Cl[13CH3]?%12");
+        assert_eq!(
+            vec![(0, 2), (2, 9), (10, 13)],
+            tokens
+                .iter()
+                .map(|(_, span)| (span.start, span.end))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(TokenKind::RingBond(12), tokens[2].0);
+        // bad tokens are skipped, the bracket atom to its `]`
+        let tokens = tokenize_with_spans("C[Xy+]O");
+        assert_eq!(
+            vec![(organic(6, false), 0..1), (organic(8, false), 6..7)],
+            tokens
+        );
+    }
+}
@@ -0,0 +1,112 @@
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+extern crate alloc;
+
+mod aromaticity;
+mod ascii;
+mod ast;
+mod builder;
+mod canon;
+mod check;
+mod compare;
+pub mod conformance;
+pub mod conformer;
+mod cxsmiles;
+#[cfg(feature = "std")]
+pub mod dataset;
+pub mod descriptors;
+mod diff;
+mod environment;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod fingerprint;
+mod formula;
+#[cfg(feature = "std")]
+mod fragmentation;
+mod fragments;
+#[cfg(feature = "std")]
+mod functional_groups;
+mod hydrogens;
+#[cfg(feature = "inchi")]
+mod inchi;
+mod incremental;
+pub mod json;
+mod layout;
+mod lexer;
+pub mod library;
+mod lint;
+mod molfile;
+mod mrv;
+mod periodic_table;
+mod props;
+mod reaction;
+pub mod rgroup;
+mod rings;
+pub mod rules;
+mod salts;
+mod sanitize;
+mod scaffold;
+mod scanner;
+#[cfg(feature = "std")]
+pub mod sdf;
+pub mod search;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod similarity;
+mod smarts;
+#[cfg(feature = "std")]
+pub mod smi;
+mod smiles;
+mod store;
+mod structure_key;
+mod substruct;
+mod svg;
+pub mod tautomer;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod topology;
+mod types;
+mod valence;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod writer;
+
+pub use aromaticity::AromaticityModel;
+pub use ast::{Attachment, ChainAtom, Connection, SmilesAst};
+pub use builder::MolBuilder;
+pub use check::{first_error, is_valid};
+pub use cxsmiles::{MolExtensions, StereoGroup, StereoGroupKind};
+pub use diff::{mol_diff, MolDiff};
+pub use environment::AtomEnvironment;
+#[cfg(feature = "inchi")]
+pub use error::InchiError;
+#[cfg(feature = "std")]
+pub use error::{DatasetError, ParseErrorAt, SdfError};
+pub use error::{
+    EditError, JsonError, KekulizeError, LibraryError, MolfileError, MolfileErrorKind, MrvError,
+    SanitizeError, SmilesError, SmilesErrorKind,
+};
+pub use fingerprint::{BitVec, Fingerprint};
+#[cfg(feature = "std")]
+pub use fragmentation::FragmentRules;
+#[cfg(feature = "std")]
+pub use functional_groups::{FunctionalGroup, FunctionalGroupHit};
+pub use incremental::{NextToken, ParserState};
+pub use layout::{Conformer, Point};
+pub use lexer::{tokenize_with_spans, BondSymbol, Lexer, Span, Token, TokenKind};
+pub use lint::{validate, Diagnostic, DiagnosticKind};
+pub use periodic_table::Element;
+pub use props::{FromProp, PropValue, Props};
+pub use reaction::Reaction;
+pub use rings::{Ring, RingInfo};
+pub use salts::SaltPolicy;
+pub use smarts::Smarts;
+pub use smiles::{SmilesParser, SmilesParserOptions};
+pub use store::MolStore;
+pub use svg::{grid_svg, Highlight, SvgOptions};
+pub use types::{
+    Atom, AtomIdx, Bond, BondDirection, BondIdx, BondOrder, BondStereo, Chirality,
+    DoubleBondConfig, Mol, SourceSpan,
+};
+pub use writer::SmilesWriteOptions;
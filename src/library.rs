@@ -0,0 +1,408 @@
+//! Combinatorial libraries: the molecules made by attaching substituents to the
+//! attachment points of a scaffold, in all combinations, e.g. for a Markush structure.
+
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::error::LibraryError;
+use crate::fragments::{extract, replace_neighbor};
+use crate::hydrogens::remove_hs_of;
+use crate::smiles::WILDCARD;
+use crate::types::{AtomIdx, Bond, Mol};
+
+/// A scaffold with attachment points, the `*` atoms, and the substituents for each of
+/// them. Attachment points are labeled by their map number, and numbered from 1 in order
+/// when they don't have one, like the cores of [`rgroup`](crate::rgroup). Points with
+/// the same label get the same substituent.
+#[derive(Debug, Clone)]
+pub struct Library {
+    scaffold: Mol,
+    labels: Vec<u32>,
+    // each attachment point, its atom and the position of its label
+    points: Vec<(NodeIndex, NodeIndex, usize)>,
+    // by position of the label
+    substituents: Vec<Vec<Substituent>>,
+}
+
+#[derive(Debug, Clone)]
+struct Substituent {
+    mol: Mol,
+    point: NodeIndex,
+    atom: NodeIndex,
+}
+
+impl Library {
+    /// A library from the scaffold, e.g. `c1ccc([*:1])cc1[*:2]`, and the substituents of
+    /// each label. A substituent has one attachment point, e.g. `*OC` or `[*:1]OC` as
+    /// found by R-group decomposition, and `*[H]` leaves a hydrogen. Substituents of labels
+    /// the scaffold doesn't have are ignored.
+    pub fn new(
+        scaffold: &Mol,
+        substituents: &BTreeMap<u32, Vec<Mol>>,
+    ) -> Result<Library, LibraryError> {
+        let mut points = vec![];
+        let mut count = 0;
+        for node in scaffold.graph.node_indices() {
+            let atom = &scaffold.graph[node];
+            if atom.number != WILDCARD {
+                continue;
+            }
+            count += 1;
+            let neighbor = only_neighbor(scaffold, node)
+                .ok_or(LibraryError::ScaffoldPoint(AtomIdx::from(node)))?;
+            points.push((node, neighbor, atom.map_number.unwrap_or(count)));
+        }
+        let mut labels: Vec<u32> = points.iter().map(|(_, _, label)| *label).collect();
+        labels.sort();
+        labels.dedup();
+
+        let substituents = labels
+            .iter()
+            .map(|label| {
+                let mols = substituents
+                    .get(label)
+                    .ok_or(LibraryError::MissingSubstituents(*label))?;
+                mols.iter()
+                    .enumerate()
+                    .map(|(index, mol)| {
+                        let invalid = LibraryError::SubstituentPoint {
+                            label: *label,
+                            index,
+                        };
+                        let mut points = mol
+                            .graph
+                            .node_indices()
+                            .filter(|node| mol.graph[*node].number == WILDCARD);
+                        let (Some(point), None) = (points.next(), points.next()) else {
+                            return Err(invalid);
+                        };
+                        let atom = only_neighbor(mol, point).ok_or(invalid)?;
+                        Ok(Substituent {
+                            mol: mol.clone(),
+                            point,
+                            atom,
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let points = points
+            .into_iter()
+            .map(|(point, atom, label)| {
+                // unwrap: labels come from the points
+                let position = labels.binary_search(&label).unwrap();
+                (point, atom, position)
+            })
+            .collect();
+        Ok(Library {
+            scaffold: scaffold.clone(),
+            labels,
+            points,
+            substituents,
+        })
+    }
+
+    /// Labels of the attachment points, in increasing order.
+    pub fn labels(&self) -> &[u32] {
+        &self.labels
+    }
+
+    /// The number of molecules of the library.
+    pub fn len(&self) -> usize {
+        self.substituents.iter().map(Vec::len).product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The molecules of the library, made as they're iterated. The substituents of the
+    /// highest label change first, in the order they were given.
+    pub fn iter(&self) -> Products<'_> {
+        let choice = (!self.is_empty()).then(|| vec![0; self.labels.len()]);
+        Products {
+            library: self,
+            choice,
+            remaining: self.len(),
+        }
+    }
+
+    /// The molecule with the substituent at each position of the choice for each label.
+    fn product(&self, choice: &[usize]) -> Mol {
+        let mut mol = self.scaffold.clone();
+        let mut removed = vec![];
+        let mut hydrogens = vec![];
+        for (point, atom, position) in &self.points {
+            let substituent = &self.substituents[*position][choice[*position]];
+            let offset = mol.graph.node_count();
+            let shifted = |node: NodeIndex| NodeIndex::new(node.index() + offset);
+            for atom in substituent.mol.graph.node_weights() {
+                let mut atom = atom.clone();
+                for n in &mut atom.chiral_neighbors {
+                    *n = AtomIdx::new(n.index() + offset);
+                }
+                mol.graph.add_node(atom);
+            }
+            for edge in substituent.mol.graph.edge_references() {
+                let mut bond = edge.weight().clone();
+                if let Some(stereo) = &mut bond.stereo {
+                    stereo.start_neighbor = AtomIdx::new(stereo.start_neighbor.index() + offset);
+                    stereo.end_neighbor = AtomIdx::new(stereo.end_neighbor.index() + offset);
+                }
+                mol.graph
+                    .add_edge(shifted(edge.source()), shifted(edge.target()), bond);
+            }
+
+            // the scaffold's bond joins the atoms
+            let (substituent_point, substituent_atom) =
+                (shifted(substituent.point), shifted(substituent.atom));
+            // unwrap: the point is bonded to the atom
+            let edge = mol.graph.find_edge(*point, *atom).unwrap();
+            let bond = Bond {
+                direction: None,
+                stereo: None,
+                ..mol.graph[edge].clone()
+            };
+            mol.graph.add_edge(*atom, substituent_atom, bond);
+            replace_neighbor(&mut mol, *atom, *point, substituent_atom);
+            replace_neighbor(&mut mol, substituent_atom, substituent_point, *atom);
+            removed.extend([*point, substituent_point]);
+            hydrogens.push(substituent_atom);
+        }
+
+        let kept: Vec<AtomIdx> = mol
+            .graph
+            .node_indices()
+            .filter(|node| !removed.contains(node))
+            .map(AtomIdx::from)
+            .collect();
+        let is_hydrogen: Vec<bool> = kept
+            .iter()
+            .map(|atom| hydrogens.contains(&NodeIndex::from(*atom)))
+            .collect();
+        remove_hs_of(&extract(&mol, &kept), &is_hydrogen)
+    }
+}
+
+fn only_neighbor(mol: &Mol, node: NodeIndex) -> Option<NodeIndex> {
+    let mut neighbors = mol.graph.neighbors(node);
+    match (neighbors.next(), neighbors.next()) {
+        (Some(neighbor), None) if neighbor != node => Some(neighbor),
+        _ => None,
+    }
+}
+
+impl<'a> IntoIterator for &'a Library {
+    type Item = Mol;
+    type IntoIter = Products<'a>;
+
+    fn into_iter(self) -> Products<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the molecules of a [`Library`].
+#[derive(Debug, Clone)]
+pub struct Products<'a> {
+    library: &'a Library,
+    // the next substituent of each label, None when done
+    choice: Option<Vec<usize>>,
+    remaining: usize,
+}
+
+impl Iterator for Products<'_> {
+    type Item = Mol;
+
+    fn next(&mut self) -> Option<Mol> {
+        let choice = self.choice.as_mut()?;
+        let product = self.library.product(choice);
+        self.remaining -= 1;
+
+        let mut done = true;
+        for (position, substituents) in self.library.substituents.iter().enumerate().rev() {
+            choice[position] += 1;
+            if choice[position] < substituents.len() {
+                done = false;
+                break;
+            }
+            choice[position] = 0;
+        }
+        if done {
+            self.choice = None;
+        }
+        Some(product)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Products<'_> {}
+
+impl FusedIterator for Products<'_> {}
+
+#[cfg(test)]
+mod test {
+    use alloc::collections::BTreeMap;
+
+    use crate::error::LibraryError;
+    use crate::rgroup::{decompose, Core};
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, Mol};
+
+    use super::Library;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn substituents(lists: &[(u32, &[&str])]) -> BTreeMap<u32, Vec<Mol>> {
+        lists
+            .iter()
+            .map(|(label, smiles)| (*label, smiles.iter().map(|s| mol(s)).collect()))
+            .collect()
+    }
+
+    fn products(library: &Library) -> Vec<String> {
+        library
+            .iter()
+            .map(|mol| mol.to_canonical_smiles())
+            .collect()
+    }
+
+    fn canonical(smiles: &[&str]) -> Vec<String> {
+        smiles
+            .iter()
+            .map(|s| mol(s).to_canonical_smiles())
+            .collect()
+    }
+
+    #[test]
+    fn enumerate_combinations() {
+        let library = Library::new(
+            &mol("c1ccc([*:1])cc1[*:2]"),
+            &substituents(&[(1, &["*C", "*OC"]), (2, &["*Cl", "*N", "*[H]"])]),
+        )
+        .unwrap();
+
+        assert_eq!(&[1, 2], library.labels());
+        assert_eq!(6, library.len());
+        assert_eq!(6, library.iter().len());
+        assert_eq!(
+            canonical(&[
+                "c1ccc(C)cc1Cl",
+                "c1ccc(C)cc1N",
+                "c1ccc(C)cc1",
+                "c1ccc(OC)cc1Cl",
+                "c1ccc(OC)cc1N",
+                "c1ccc(OC)cc1",
+            ]),
+            products(&library)
+        );
+    }
+
+    #[test]
+    fn unlabeled_points() {
+        let library = Library::new(
+            &mol("*C(=O)N*"),
+            &substituents(&[(1, &["C*"]), (2, &["*C1CC1", "*c1ccccc1"])]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            canonical(&["CC(=O)NC1CC1", "CC(=O)Nc1ccccc1"]),
+            products(&library)
+        );
+    }
+
+    #[test]
+    fn repeated_label() {
+        let library =
+            Library::new(&mol("[*:1]CC[*:1]"), &substituents(&[(1, &["*O", "*F"])])).unwrap();
+
+        assert_eq!(canonical(&["OCCO", "FCCF"]), products(&library));
+    }
+
+    #[test]
+    fn keep_bond_orders_and_stereo() {
+        let library = Library::new(&mol("[*:1]=C1CCCC1"), &substituents(&[(1, &["*=O"])])).unwrap();
+        assert_eq!(canonical(&["O=C1CCCC1"]), products(&library));
+
+        let library = Library::new(
+            &mol("N[C@@H](C)C(=O)[*:1]"),
+            &substituents(&[(1, &["*O", "*N[C@H](C)C(=O)O"])]),
+        )
+        .unwrap();
+        assert_eq!(
+            canonical(&["N[C@@H](C)C(=O)O", "N[C@@H](C)C(=O)N[C@H](C)C(=O)O"]),
+            products(&library)
+        );
+
+        let library = Library::new(&mol("C/C=C/[*:1]"), &substituents(&[(1, &["*C"])])).unwrap();
+        assert_eq!(canonical(&["C/C=C/C"]), products(&library));
+    }
+
+    #[test]
+    fn decomposed_substituents() {
+        let core = Core::new(&mol("c1ccc([*:1])cc1[*:2]"));
+        let table = decompose(&core, &[mol("c1ccc(C)cc1OC"), mol("c1ccc(CC)cc1")]);
+        let mut rgroups: BTreeMap<u32, Vec<Mol>> = BTreeMap::new();
+        for decomposition in table.rows.into_iter().flatten() {
+            for (label, rgroup) in decomposition.rgroups {
+                rgroups.entry(label).or_default().push(rgroup);
+            }
+        }
+
+        let library = Library::new(&mol("c1ccc([*:1])cc1[*:2]"), &rgroups).unwrap();
+
+        assert_eq!(
+            canonical(&[
+                "c1ccc(C)cc1OC",
+                "c1ccc(C)cc1",
+                "c1ccc(CC)cc1OC",
+                "c1ccc(CC)cc1"
+            ]),
+            products(&library)
+        );
+    }
+
+    #[test]
+    fn invalid_library() {
+        assert_eq!(
+            LibraryError::MissingSubstituents(2),
+            Library::new(&mol("[*:1]C[*:2]"), &substituents(&[(1, &["*C"])])).unwrap_err()
+        );
+        assert_eq!(
+            LibraryError::SubstituentPoint { label: 1, index: 1 },
+            Library::new(&mol("[*:1]C"), &substituents(&[(1, &["*C", "*C*"])])).unwrap_err()
+        );
+        assert_eq!(
+            LibraryError::SubstituentPoint { label: 1, index: 0 },
+            Library::new(&mol("[*:1]C"), &substituents(&[(1, &["CC"])])).unwrap_err()
+        );
+        assert_eq!(
+            LibraryError::ScaffoldPoint(AtomIdx::new(1)),
+            Library::new(&mol("C*C"), &substituents(&[(1, &["*C"])])).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn empty_library() {
+        let library = Library::new(&mol("[*:1]C"), &substituents(&[(1, &[])])).unwrap();
+
+        assert!(library.is_empty());
+        assert_eq!(None, library.iter().next().map(|mol| mol.to_smiles()));
+
+        // no attachment points, the scaffold itself
+        let library = Library::new(&mol("CCO"), &BTreeMap::new()).unwrap();
+        assert_eq!(canonical(&["CCO"]), products(&library));
+    }
+}
@@ -0,0 +1,437 @@
+//! Finds the problems of a SMILES string without building the molecule. Unlike the
+//! parser, it goes on after a problem, to report as many as it can.
+
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use hashbrown::HashMap;
+
+use crate::error::{Annotated, SmilesError, SmilesErrorKind};
+use crate::lexer::{BondSymbol, Lexer, Span, TokenKind};
+use crate::smiles::{implicit_bond_order, SmilesParserOptions};
+use crate::types::{Atom, BondOrder};
+use crate::valence;
+
+/// A problem found by [`validate`], with the span of input it's about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// What the parser reports as an error, e.g. an unclosed ring. Some are only errors
+    /// with stricter [`SmilesParserOptions`], e.g. an aromatic atom outside a ring.
+    Parse(SmilesErrorKind),
+    /// An atom with more bonds and hydrogens than its highest normal valence.
+    InvalidValence,
+}
+
+impl Diagnostic {
+    fn new(kind: DiagnosticKind, span: Span) -> Self {
+        Diagnostic { kind, span }
+    }
+
+    pub(crate) fn parse(kind: SmilesErrorKind, span: Span) -> Self {
+        Diagnostic::new(DiagnosticKind::Parse(kind), span)
+    }
+
+    /// The parse error, about the character at its position.
+    pub(crate) fn from_error(error: SmilesError) -> Self {
+        let span = Span {
+            start: error.position,
+            end: error.position + 1,
+        };
+        Diagnostic::parse(error.kind, span)
+    }
+
+    /// Displays the input with a caret under the start of the span, followed by the
+    /// diagnostic.
+    pub fn annotate<'a>(&'a self, input: &'a str) -> impl fmt::Display + 'a {
+        Annotated {
+            message: self,
+            position: self.span.start,
+            input,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::Parse(kind) => write!(f, "{}", kind),
+            DiagnosticKind::InvalidValence => write!(f, "valence above the normal valences"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.span.start)
+    }
+}
+
+/// A ring bond that has been opened but not closed yet.
+struct OpenRing {
+    atom: usize,
+    symbol: Option<BondSymbol>,
+    span: Span,
+}
+
+/// Checks the SMILES string, returning its problems in input order, or nothing if
+/// it's valid. Stops at the first malformed token, e.g. an unknown element.
+pub fn validate(smiles: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut atoms: Vec<(Atom, Span)> = vec![];
+    let mut bonds: Vec<(usize, usize, BondOrder)> = vec![];
+
+    let mut last: Option<usize> = None;
+    let mut branches: Vec<(Option<usize>, Span)> = vec![];
+    let mut rings: HashMap<u16, OpenRing> = HashMap::new();
+    let mut pending_bond: Option<(BondSymbol, Span)> = None;
+    let mut dot = false;
+    // a '(' was read and no atom since
+    let mut empty_branch = false;
+
+    let options = SmilesParserOptions::default();
+    let mut lexer = Lexer::new(smiles, options);
+    // whether the lexer read the whole input
+    let mut complete = true;
+    for token in lexer.by_ref() {
+        let token = match token {
+            Ok(token) => token,
+            Err(error) => {
+                complete = false;
+                diagnostics.push(Diagnostic::from_error(error));
+                break;
+            }
+        };
+        let span = token.span;
+        match token.kind {
+            TokenKind::OrganicAtom(atom) | TokenKind::BracketAtom(atom) => {
+                let index = atoms.len();
+                let symbol = pending_bond.take().map(|(symbol, _)| symbol);
+                if let Some(last) = last {
+                    let order = symbol
+                        .map(|s| s.order)
+                        .unwrap_or_else(|| implicit_bond_order(&atoms[last].0, &atom));
+                    bonds.push((last, index, order));
+                }
+                atoms.push((atom, span));
+                last = Some(index);
+                dot = false;
+                empty_branch = false;
+            }
+            TokenKind::RingBond(ring) => {
+                let symbol = pending_bond.take().map(|(symbol, _)| symbol);
+                if ring > options.max_ring_number {
+                    let kind = SmilesErrorKind::RingNumberTooHigh {
+                        number: ring,
+                        max: options.max_ring_number,
+                    };
+                    diagnostics.push(Diagnostic::parse(kind, span));
+                    continue;
+                }
+                let Some(atom) = last else {
+                    diagnostics.push(Diagnostic::parse(SmilesErrorKind::MissingAtom, span));
+                    continue;
+                };
+                match rings.remove(&ring) {
+                    None => {
+                        rings.insert(ring, OpenRing { atom, symbol, span });
+                    }
+                    Some(open_ring) if open_ring.atom == atom => {
+                        diagnostics.push(Diagnostic::parse(
+                            SmilesErrorKind::RingBondToSelf(ring),
+                            span,
+                        ));
+                    }
+                    Some(open_ring)
+                        if bonds.iter().any(|(a, b, _)| {
+                            (*a, *b) == (open_ring.atom, atom) || (*b, *a) == (open_ring.atom, atom)
+                        }) =>
+                    {
+                        diagnostics.push(Diagnostic::parse(
+                            SmilesErrorKind::DuplicateBond(ring),
+                            span,
+                        ));
+                    }
+                    Some(open_ring) => {
+                        if let (Some(start), Some(end)) = (open_ring.symbol, symbol) {
+                            if !start.matches_ring_end(end) {
+                                diagnostics.push(Diagnostic::parse(
+                                    SmilesErrorKind::RingBondConflict(ring),
+                                    span,
+                                ));
+                            }
+                        }
+                        let order = symbol.or(open_ring.symbol).map_or_else(
+                            || implicit_bond_order(&atoms[open_ring.atom].0, &atoms[atom].0),
+                            |s| s.order,
+                        );
+                        bonds.push((open_ring.atom, atom, order));
+                    }
+                }
+            }
+            TokenKind::Bond(symbol) => {
+                if last.is_none() || pending_bond.is_some() {
+                    let kind = SmilesErrorKind::UnexpectedCharacter(symbol.to_char());
+                    diagnostics.push(Diagnostic::parse(kind, span));
+                    continue;
+                }
+                pending_bond = Some((symbol, span));
+            }
+            TokenKind::BranchOpen => {
+                if let Some((_, bond_span)) = pending_bond.take() {
+                    diagnostics.push(Diagnostic::parse(SmilesErrorKind::DanglingBond, bond_span));
+                }
+                if last.is_none() {
+                    diagnostics.push(Diagnostic::parse(SmilesErrorKind::MissingAtom, span));
+                }
+                if branches.len() == options.max_branch_depth {
+                    diagnostics.push(Diagnostic::parse(SmilesErrorKind::NestingTooDeep, span));
+                }
+                branches.push((last, span));
+                empty_branch = true;
+            }
+            TokenKind::BranchClose => {
+                if let Some((_, bond_span)) = pending_bond.take() {
+                    diagnostics.push(Diagnostic::parse(SmilesErrorKind::DanglingBond, bond_span));
+                }
+                match branches.pop() {
+                    Some((before_branch, _)) => {
+                        if last.is_none() || empty_branch {
+                            diagnostics.push(Diagnostic::parse(SmilesErrorKind::MissingAtom, span));
+                        }
+                        last = before_branch;
+                        dot = false;
+                        empty_branch = false;
+                    }
+                    None => diagnostics.push(Diagnostic::parse(
+                        SmilesErrorKind::UnbalancedParenthesis,
+                        span,
+                    )),
+                }
+            }
+            TokenKind::Dot => {
+                if let Some((_, bond_span)) = pending_bond.take() {
+                    diagnostics.push(Diagnostic::parse(SmilesErrorKind::DanglingBond, bond_span));
+                }
+                if last.is_none() {
+                    diagnostics.push(Diagnostic::parse(SmilesErrorKind::MissingAtom, span));
+                }
+                last = None;
+                dot = true;
+                empty_branch = false;
+            }
+        }
+    }
+
+    // what's missing at the end, unless the lexer stopped before it
+    if complete {
+        let end = lexer.position();
+        if dot {
+            let span = Span { start: end, end };
+            diagnostics.push(Diagnostic::parse(SmilesErrorKind::UnexpectedEnd, span));
+        }
+        if let Some((_, span)) = pending_bond {
+            diagnostics.push(Diagnostic::parse(SmilesErrorKind::DanglingBond, span));
+        }
+        for (_, span) in branches {
+            diagnostics.push(Diagnostic::parse(
+                SmilesErrorKind::UnbalancedParenthesis,
+                span,
+            ));
+        }
+        for (ring, open_ring) in rings {
+            diagnostics.push(Diagnostic::parse(
+                SmilesErrorKind::UnclosedRing(ring),
+                open_ring.span,
+            ));
+        }
+    }
+
+    let mut neighbors: Vec<Vec<usize>> = vec![vec![]; atoms.len()];
+    let mut valences: Vec<u8> = vec![0; atoms.len()];
+    for (bond, &(a, b, order)) in bonds.iter().enumerate() {
+        neighbors[a].push(bond);
+        neighbors[b].push(bond);
+        valences[a] += valence::bond_valence(order);
+        valences[b] += valence::bond_valence(order);
+    }
+    for (index, (atom, span)) in atoms.iter().enumerate() {
+        if complete && atom.aromatic && !neighbors[index].iter().any(|&bond| in_ring(&bonds, bond))
+        {
+            diagnostics.push(Diagnostic::parse(
+                SmilesErrorKind::AromaticOutsideRing,
+                *span,
+            ));
+        }
+        let valence = valences[index] + atom.aromatic as u8 + atom.explicit_h.unwrap_or(0);
+        if valence::max_valence(atom).is_some_and(|max| valence > max) {
+            diagnostics.push(Diagnostic::new(DiagnosticKind::InvalidValence, *span));
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    diagnostics
+}
+
+/// Whether the bond's atoms are also connected without it.
+fn in_ring(bonds: &[(usize, usize, BondOrder)], bond: usize) -> bool {
+    let (start, end, _) = bonds[bond];
+    let mut visited = vec![start];
+    let mut queue = VecDeque::from([start]);
+    while let Some(atom) = queue.pop_front() {
+        for (other, &(a, b, _)) in bonds.iter().enumerate() {
+            let next = match atom {
+                _ if other == bond => continue,
+                atom if atom == a => b,
+                atom if atom == b => a,
+                _ => continue,
+            };
+            if next == end {
+                return true;
+            }
+            if !visited.contains(&next) {
+                visited.push(next);
+                queue.push_back(next);
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::SmilesErrorKind;
+    use crate::lexer::Span;
+
+    use super::{validate, Diagnostic, DiagnosticKind};
+
+    fn kinds(smiles: &str) -> Vec<DiagnosticKind> {
+        validate(smiles)
+            .into_iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        for smiles in [
+            "",
+            "CCO",
+            "c1ccccc1",
+            "C(=O)(O)C.[Na+]",
+            "F/C=C/F",
+            "C=1CC=1",
+        ] {
+            assert_eq!(Vec::<Diagnostic>::new(), validate(smiles), "{}", smiles);
+        }
+    }
+
+    #[test]
+    fn reports_all() {
+        assert_eq!(
+            vec![
+                Diagnostic {
+                    kind: DiagnosticKind::Parse(SmilesErrorKind::UnclosedRing(1)),
+                    span: Span { start: 1, end: 2 },
+                },
+                Diagnostic {
+                    kind: DiagnosticKind::Parse(SmilesErrorKind::UnbalancedParenthesis),
+                    span: Span { start: 7, end: 8 },
+                },
+                Diagnostic {
+                    kind: DiagnosticKind::Parse(SmilesErrorKind::UnbalancedParenthesis),
+                    span: Span { start: 9, end: 10 },
+                },
+            ],
+            validate("C1C(C)C)C(")
+        );
+    }
+
+    #[test]
+    fn ring_bond_atoms() {
+        assert_eq!(
+            vec![
+                DiagnosticKind::Parse(SmilesErrorKind::RingBondToSelf(1)),
+                DiagnosticKind::Parse(SmilesErrorKind::DuplicateBond(2)),
+            ],
+            kinds("C11.C2C2")
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            vec![
+                DiagnosticKind::Parse(SmilesErrorKind::MissingAtom),
+                DiagnosticKind::Parse(SmilesErrorKind::DanglingBond),
+                DiagnosticKind::Parse(SmilesErrorKind::UnexpectedEnd),
+            ],
+            kinds("(C)C=.C.")
+        );
+        // what follows a malformed token isn't checked
+        assert_eq!(
+            vec![DiagnosticKind::Parse(SmilesErrorKind::UnknownElement(
+                "Xy".to_string()
+            ))],
+            kinds("C1C[Xy]C1")
+        );
+        assert_eq!(
+            vec![DiagnosticKind::Parse(SmilesErrorKind::RingNumberTooHigh {
+                number: 100,
+                max: 99
+            })],
+            kinds("C%(100)CC")
+        );
+    }
+
+    #[test]
+    fn ring_bond_conflict() {
+        assert_eq!(
+            vec![Diagnostic {
+                kind: DiagnosticKind::Parse(SmilesErrorKind::RingBondConflict(1)),
+                span: Span { start: 6, end: 7 },
+            }],
+            validate("C=1CC-1")
+        );
+        assert_eq!(Vec::<Diagnostic>::new(), validate("C/1CC-1"));
+    }
+
+    #[test]
+    fn aromatic_outside_ring() {
+        assert_eq!(
+            vec![
+                DiagnosticKind::Parse(SmilesErrorKind::AromaticOutsideRing),
+                DiagnosticKind::Parse(SmilesErrorKind::AromaticOutsideRing),
+            ],
+            kinds("c1ccccc1-cc")
+        );
+    }
+
+    #[test]
+    fn invalid_valence() {
+        assert_eq!(
+            vec![DiagnosticKind::InvalidValence],
+            kinds("C(C)(C)(C)(C)C")
+        );
+        assert_eq!(vec![DiagnosticKind::InvalidValence], kinds("O=O=O"));
+        assert_eq!(vec![DiagnosticKind::InvalidValence], kinds("C[CH4]"));
+        assert_eq!(Vec::<DiagnosticKind>::new(), kinds("C[N+](C)(C)C"));
+    }
+
+    #[test]
+    fn annotate() {
+        let diagnostics = validate("C1CC");
+
+        assert_eq!(
+            "C1CC\n ^ unclosed ring 1 at position 1",
+            diagnostics[0].annotate("C1CC").to_string()
+        );
+    }
+}
@@ -1,256 +1,1031 @@
-mod scanner;
-mod smiles;
-mod types;
-
 use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+use std::process::ExitCode;
+
+use rayon::prelude::*;
+use smiles::descriptors;
+use smiles::sdf::{SdfReader, SdfWriter};
+use smiles::{
+    AtomIdx, BondIdx, BondOrder, Element, Highlight, Mol, MolDiff, SdfError, Smarts, SmilesParser,
+    SvgOptions,
+};
+
+const USAGE: &str = "\
+Usage:
+  smiles parse [SMILES...]                 print a summary of each molecule
+  smiles convert [FILE] [--from FORMAT] --to FORMAT [-o FILE] [--threads N]
+                                           convert between smiles and sdf, on N threads
+  smiles canon [FILE] [-o FILE]            canonicalize a SMILES file
+  smiles dedupe [FILE] [-o FILE] [--key KEY]
+                                           keep the first molecule of each key
+  smiles depict SMILES [--format svg|ascii] [--highlight-smarts SMARTS] [-o FILE]
+                                           draw the molecule as SVG or text, with the
+                                           matches of SMARTS highlighted in the SVG
+  smiles diff SMILES SMILES [--svg FILE]   the atoms and bonds in one but not the other
+  smiles lint [SMILES...]                  report the problems of each SMILES
+  smiles props [FILE] [--props LIST] [--format csv|tsv] [-o FILE]
+                                           compute properties, one row per molecule
+
+Without FILE (or with -), molecules are read from stdin, one SMILES per line.
+SMILES files may have a name after the SMILES, separated by whitespace.
+FORMAT is smiles or sdf, by default from the file extension, else smiles.
+LIST is comma separated, from: mw, exact_mass, formula, charge, heavy_atoms, rings,
+rotatable, hbd, hba, tpsa, clogp. By default all of them.
+KEY is canonical (canonical SMILES, the default, the same for Kekulé and aromatic
+forms), structure-hash (a 64 bit hash of it, less memory for large files) or
+inchikey-like (ignores stereo and bond orders).
+
+Exit codes: 0 success, 1 invalid input or I/O error, 2 usage error.";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+    let result = match command.as_deref() {
+        Some("parse") => Args::parse(args, &[]).and_then(|args| parse(&args)),
+        Some("convert") => Args::parse(args, &["--from", "--to", "--output", "--threads"])
+            .and_then(|args| convert(&args)),
+        Some("canon") => Args::parse(args, &["--output"]).and_then(|args| canon(&args)),
+        Some("dedupe") => Args::parse(args, &["--key", "--output"]).and_then(|args| dedupe(&args)),
+        Some("depict") => Args::parse(args, &["--format", "--highlight-smarts", "--output"])
+            .and_then(|args| depict(&args)),
+        Some("diff") => Args::parse(args, &["--svg"]).and_then(|args| diff(&args)),
+        Some("lint") => Args::parse(args, &[]).and_then(|args| lint(&args)),
+        Some("props") => {
+            Args::parse(args, &["--props", "--format", "--output"]).and_then(|args| props(&args))
+        }
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Some(command) => Err(Error::Usage(format!("unknown command: {}", command))),
+        None => Err(Error::Usage("missing command".to_string())),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        // invalid molecules were reported as they were read
+        Ok(false) => ExitCode::from(1),
+        Err(Error::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Error::Io(error)) => {
+            eprintln!("{}", error);
+            ExitCode::from(1)
+        }
+    }
+}
+
+enum Error {
+    Usage(String),
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// Returns whether all the inputs were valid.
+type CommandResult = Result<bool, Error>;
+
+/// Positional arguments and `--name value` options.
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    /// `allowed` are the long names of the options; `-o` is short for `--output`.
+    fn parse(args: impl IntoIterator<Item = String>, allowed: &[&str]) -> Result<Args, Error> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let name = if arg == "-o" { "--output" } else { &arg };
+            if !name.starts_with('-') || name == "-" {
+                parsed.positional.push(arg);
+            } else if allowed.contains(&name) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Error::Usage(format!("missing value for {}", name)))?;
+                parsed.options.push((name.to_string(), value));
+            } else {
+                return Err(Error::Usage(format!("unknown option: {}", arg)));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The only positional argument, if any.
+    fn single(&self) -> Result<Option<&str>, Error> {
+        match self.positional.as_slice() {
+            [] => Ok(None),
+            [arg] => Ok(Some(arg)),
+            _ => Err(Error::Usage("too many arguments".to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Smiles,
+    Sdf,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Result<Format, Error> {
+        match name.to_ascii_lowercase().as_str() {
+            "smi" | "smiles" => Ok(Format::Smiles),
+            "sdf" | "sd" | "mol" => Ok(Format::Sdf),
+            _ => Err(Error::Usage(format!("unknown format: {}", name))),
+        }
+    }
+
+    /// From the file extension, smiles if unknown.
+    fn from_path(path: Option<&str>) -> Format {
+        path.and_then(|path| path.rsplit_once('.'))
+            .and_then(|(_, extension)| Format::from_name(extension).ok())
+            .unwrap_or(Format::Smiles)
+    }
+}
+
+fn open_input(path: Option<&str>) -> io::Result<Box<dyn BufRead>> {
+    match path {
+        None | Some("-") => Ok(Box::new(io::stdin().lock())),
+        Some(path) => Ok(Box::new(BufReader::new(File::open(path)?))),
+    }
+}
+
+fn open_output(path: Option<&str>) -> io::Result<Box<dyn Write>> {
+    match path {
+        None | Some("-") => Ok(Box::new(BufWriter::new(io::stdout().lock()))),
+        Some(path) => Ok(Box::new(BufWriter::new(File::create(path)?))),
+    }
+}
+
+/// Splits a SMILES file line into the SMILES and the optional name.
+fn smiles_line(line: &str) -> (&str, &str) {
+    let line = line.trim();
+    match line.split_once(char::is_whitespace) {
+        Some((smiles, name)) => (smiles, name.trim_start()),
+        None => (line, ""),
+    }
+}
 
-use petgraph::{graph::NodeIndex, Graph};
-use scanner::Scanner;
-use types::{Atom, Bond, Mol};
-
-pub struct SmilesParser {}
-
-impl SmilesParser {
-    pub fn parse(&self, smiles: &str) -> Mol {
-        let mut scanner = Scanner::new(smiles);
-
-        let mut graph = Graph::<Atom, Bond>::new();
-
-        let mut last_node_index: Option<NodeIndex> = None;
-
-        let mut rings: HashMap<char, NodeIndex> = HashMap::new();
-
-        let mut branches_stack: Vec<NodeIndex> = vec![];
-
-        while !scanner.is_done() {
-            let c = scanner.pop();
-
-            if let Some(c) = c {
-                match &c {
-                    'c' => {
-                        let atom_str;
-                        if let Some(next) = scanner.peek() {
-                            if *next == 'l' {
-                                atom_str = "cl";
-                                scanner.pop();
-                            } else {
-                                atom_str = "c";
-                            }
-                        } else {
-                            atom_str = "c";
-                        }
-                        let number = atom_number(&atom_str);
-                        let node_index = add_to_graph(&mut graph, number, last_node_index);
-                        last_node_index = Some(node_index.clone());
-                    }
-                    'n' | 'o' | 'f' => {
-                        let number = atom_number(&c.to_string());
-                        let node_index = add_to_graph(&mut graph, number, last_node_index);
-                        last_node_index = Some(node_index.clone());
-                    }
-                    '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                        if !rings.contains_key(c) {
-                            // a ring starts
-                            rings.insert(*c, last_node_index.unwrap()); // unwrap: smiles can't start with ring number (there's always a last node)
-                        } else {
-                            // ring ends
-                            let ring_start = rings.get(c).unwrap(); // unwrap: finishing a ring, so must have been started
-                            let ring_end = last_node_index.unwrap(); // unwrap: finishing a ring, so there must be at least a node before
-                            let bond = Bond {
-                                atom_start: ring_start.index(),
-                                atom_end: ring_end.index(),
-                            };
-                            graph.add_edge(*ring_start, ring_end, bond);
-                            rings.remove(c);
-                        }
-                    }
-                    '(' => {
-                        branches_stack.push(last_node_index.unwrap()); // unwrap: smiles can't start with a branch (there's always a last node)
-                    }
-                    ')' => {
-                        let last_index_before_branch = branches_stack.pop();
-                        // replace current last node index (in branch) with index before branch
-                        last_node_index = last_index_before_branch;
-                    }
-                    _ => {}
+/// Calls `f` with each molecule of a SMILES input, its SMILES and its name,
+/// reporting invalid lines.
+fn for_each_smiles(
+    input: impl BufRead,
+    mut f: impl FnMut(Mol, &str, &str) -> io::Result<()>,
+) -> CommandResult {
+    let parser = SmilesParser::new();
+    let mut valid = true;
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let (smiles, name) = smiles_line(&line);
+        if smiles.is_empty() {
+            continue;
+        }
+        match parser.parse(smiles) {
+            Ok(mol) => f(mol, smiles, name)?,
+            Err(error) => {
+                eprintln!("line {}: {}: {}", index + 1, smiles, error);
+                valid = false;
+            }
+        }
+    }
+    Ok(valid)
+}
+
+fn parse(args: &Args) -> CommandResult {
+    let mut out = open_output(None)?;
+    let mut summary = |mol: Mol, input: &str| {
+        writeln!(
+            out,
+            "{}: {}, {} atoms, {} bonds",
+            input,
+            mol.molecular_formula(),
+            mol.num_atoms(),
+            mol.num_bonds()
+        )
+    };
+
+    let valid = if args.positional.is_empty() {
+        for_each_smiles(open_input(None)?, |mol, smiles, _| summary(mol, smiles))?
+    } else {
+        let parser = SmilesParser::new();
+        let mut valid = true;
+        for input in &args.positional {
+            match parser.parse(input) {
+                Ok(mol) => summary(mol, input)?,
+                Err(error) => {
+                    eprintln!("{}", error.annotate(input));
+                    valid = false;
                 }
             }
         }
+        valid
+    };
+    out.flush()?;
+    Ok(valid)
+}
+
+/// Records read before converting them, in parallel with `--threads`.
+const BATCH_SIZE: usize = 1000;
+
+/// An input record. SD records are parsed by the reader, SMILES lines when converted.
+enum Record {
+    Smiles { line: usize, text: String },
+    Sdf(Result<(Mol, HashMap<String, String>), SdfError>),
+}
+
+fn convert(args: &Args) -> CommandResult {
+    let input_path = args.single()?;
+    let output_path = args.option("--output");
+    let from = match args.option("--from") {
+        Some(name) => Format::from_name(name)?,
+        None => Format::from_path(input_path),
+    };
+    let to = match (args.option("--to"), output_path) {
+        (Some(name), _) => Format::from_name(name)?,
+        (None, Some(path)) if path != "-" => Format::from_path(Some(path)),
+        (None, _) => return Err(Error::Usage("missing --to".to_string())),
+    };
+    let threads = match args.option("--threads") {
+        Some(threads) => match threads.parse::<usize>() {
+            Ok(threads) if threads > 0 => threads,
+            _ => return Err(Error::Usage(format!("invalid thread count: {}", threads))),
+        },
+        None => 1,
+    };
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|error| Error::Io(io::Error::other(error)))?;
+
+    let input = open_input(input_path)?;
+    let mut records: Box<dyn Iterator<Item = io::Result<Record>>> = match from {
+        Format::Smiles => Box::new(input.lines().enumerate().map(|(index, line)| {
+            line.map(|text| Record::Smiles {
+                line: index + 1,
+                text,
+            })
+        })),
+        Format::Sdf => Box::new(SdfReader::new(input).map(|record| match record {
+            Err(SdfError::Io(error)) => Err(error),
+            record => Ok(Record::Sdf(record)),
+        })),
+    };
+
+    let mut output = open_output(output_path)?;
+    let mut progress = Progress::new();
+    loop {
+        let batch = records
+            .by_ref()
+            .take(BATCH_SIZE)
+            .collect::<io::Result<Vec<Record>>>()?;
+        if batch.is_empty() {
+            break;
+        }
+        let converted: Vec<Option<Result<String, String>>> = pool.install(|| {
+            batch
+                .into_par_iter()
+                .map(|record| convert_record(record, to))
+                .collect()
+        });
+        for result in converted.into_iter().flatten() {
+            match result {
+                Ok(text) => output.write_all(text.as_bytes())?,
+                Err(message) => progress.fail(&message),
+            }
+            progress.records += 1;
+        }
+        progress.show();
+    }
+    output.flush()?;
+    progress.finish();
+    Ok(progress.failed == 0)
+}
+
+/// The record written in the format, or the error message. `None` for blank lines.
+fn convert_record(record: Record, format: Format) -> Option<Result<String, String>> {
+    match record {
+        Record::Smiles { line, text } => {
+            let (smiles, name) = smiles_line(&text);
+            if smiles.is_empty() {
+                return None;
+            }
+            let result = match SmilesParser::new().parse(smiles) {
+                Ok(mol) if name.is_empty() => Ok(render(&mol, &[], format)),
+                Ok(mol) => Ok(render(&mol, &[("name", name)], format)),
+                Err(error) => Err(format!("line {}: {}: {}", line, smiles, error)),
+            };
+            Some(result)
+        }
+        Record::Sdf(Ok((mol, properties))) => {
+            let mut properties: Vec<(&str, &str)> = properties
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            properties.sort();
+            Some(Ok(render(&mol, &properties, format)))
+        }
+        Record::Sdf(Err(error)) => Some(Err(error.to_string())),
+    }
+}
+
+/// Writes the molecule in the format. A `name` property is written after the SMILES.
+fn render(mol: &Mol, properties: &[(&str, &str)], format: Format) -> String {
+    match format {
+        Format::Smiles => match properties.iter().find(|(name, _)| *name == "name") {
+            Some((_, name)) => format!("{} {}\n", mol.to_smiles(), name),
+            None => format!("{}\n", mol.to_smiles()),
+        },
+        Format::Sdf => {
+            let mut writer = SdfWriter::new(vec![]);
+            // unwrap: writing to a Vec doesn't fail
+            writer.write(mol, properties.iter().copied()).unwrap();
+            // unwrap: molfiles are ASCII
+            String::from_utf8(writer.into_inner()).unwrap()
+        }
+    }
+}
+
+/// Counts the converted records, shown on stderr if it's a terminal, and reports the
+/// failed ones.
+struct Progress {
+    visible: bool,
+    records: usize,
+    failed: usize,
+}
+
+impl Progress {
+    fn new() -> Progress {
+        Progress {
+            visible: io::stderr().is_terminal(),
+            records: 0,
+            failed: 0,
+        }
+    }
+
+    fn fail(&mut self, message: &str) {
+        self.clear();
+        eprintln!("{}", message);
+        self.failed += 1;
+    }
+
+    fn show(&self) {
+        if self.visible {
+            eprint!("\r{} records, {} failed", self.records, self.failed);
+        }
+    }
+
+    fn clear(&self) {
+        if self.visible {
+            eprint!("\r\x1b[K");
+        }
+    }
+
+    fn finish(&self) {
+        self.clear();
+        if self.failed > 0 {
+            eprintln!("{} of {} records failed", self.failed, self.records);
+        }
+    }
+}
+
+fn canon(args: &Args) -> CommandResult {
+    let input = open_input(args.single()?)?;
+    let mut out = open_output(args.option("--output"))?;
+    let valid = for_each_smiles(input, |mol, _, name| {
+        if name.is_empty() {
+            writeln!(out, "{}", mol.to_canonical_smiles())
+        } else {
+            writeln!(out, "{} {}", mol.to_canonical_smiles(), name)
+        }
+    })?;
+    out.flush()?;
+    Ok(valid)
+}
+
+/// What `smiles dedupe` compares molecules by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DedupeKey {
+    Canonical,
+    StructureHash,
+    InchikeyLike,
+}
+
+impl DedupeKey {
+    fn from_name(name: &str) -> Result<DedupeKey, Error> {
+        match name {
+            "canonical" => Ok(DedupeKey::Canonical),
+            "structure-hash" => Ok(DedupeKey::StructureHash),
+            "inchikey-like" => Ok(DedupeKey::InchikeyLike),
+            _ => Err(Error::Usage(format!("unknown key: {}", name))),
+        }
+    }
+
+    fn of(self, mol: &Mol) -> String {
+        match self {
+            DedupeKey::Canonical => mol.to_canonical_smiles(),
+            DedupeKey::StructureHash => {
+                let mut hasher = DefaultHasher::new();
+                mol.to_canonical_smiles().hash(&mut hasher);
+                format!("{:016x}", hasher.finish())
+            }
+            DedupeKey::InchikeyLike => mol.structure_key(),
+        }
+    }
+}
+
+/// Writes the lines of the first molecule of each key, as read, then reports the number
+/// of duplicates on stderr.
+fn dedupe(args: &Args) -> CommandResult {
+    let key = match args.option("--key") {
+        Some(name) => DedupeKey::from_name(name)?,
+        None => DedupeKey::Canonical,
+    };
+    let input = open_input(args.single()?)?;
+    let mut out = open_output(args.option("--output"))?;
+
+    // number of molecules with each key
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut records = 0;
+    let valid = for_each_smiles(input, |mol, smiles, name| {
+        records += 1;
+        let count = seen.entry(key.of(&mol)).or_insert(0);
+        *count += 1;
+        match (*count, name) {
+            (1, "") => writeln!(out, "{}", smiles),
+            (1, name) => writeln!(out, "{} {}", smiles, name),
+            _ => Ok(()),
+        }
+    })?;
+    out.flush()?;
 
-        let mol = Mol { graph };
+    let duplicated = seen.values().filter(|count| **count > 1).count();
+    eprintln!(
+        "{} molecules, {} unique, {} duplicates ({} keys repeated)",
+        records,
+        seen.len(),
+        records - seen.len(),
+        duplicated
+    );
+    Ok(valid)
+}
 
-        mol
+fn depict(args: &Args) -> CommandResult {
+    let smiles = args
+        .single()?
+        .ok_or_else(|| Error::Usage("missing SMILES".to_string()))?;
+    let mol = match (SmilesParser::new()).parse(smiles) {
+        Ok(mol) => mol,
+        Err(error) => {
+            eprintln!("{}", error.annotate(smiles));
+            return Ok(false);
+        }
+    };
+    let mut options = SvgOptions::default();
+    if let Some(smarts) = args.option("--highlight-smarts") {
+        match Smarts::parse(smarts) {
+            Ok(query) => options.highlights = match_highlights(&mol, &query, "#FF8080"),
+            Err(error) => {
+                eprintln!("{}", error.annotate(smarts));
+                return Ok(false);
+            }
+        }
     }
+    let drawing = match args.option("--format") {
+        None | Some("svg") => mol.to_svg(&options),
+        Some("ascii") if options.highlights.is_empty() => mol.to_ascii_art(),
+        Some("ascii") => {
+            return Err(Error::Usage(
+                "--highlight-smarts only applies to svg".to_string(),
+            ))
+        }
+        Some(format) => return Err(Error::Usage(format!("unknown format: {}", format))),
+    };
+    let mut out = open_output(args.option("--output"))?;
+    out.write_all(drawing.as_bytes())?;
+    out.flush()?;
+    Ok(true)
+}
+
+/// The atoms and bonds of each match of the query, in the color.
+fn match_highlights(mol: &Mol, query: &Smarts, color: &str) -> Vec<Highlight> {
+    mol.substruct_matches(query)
+        .iter()
+        .map(|atoms| Highlight::atoms_and_bonds(mol, atoms, color))
+        .collect()
 }
 
-fn atom_number(str: &str) -> u32 {
-    match str {
-        "c" => 6,
-        "n" => 7,
-        "o" => 8,
-        "f" => 9,
-        "cl" => 17,
-        _ => panic!("not supported: {}", str),
+/// Lists the removed (`-`), added (`+`) and changed (`~`) atoms and bonds, in red, green
+/// and yellow on a terminal, and draws them with `--svg`.
+fn diff(args: &Args) -> CommandResult {
+    let [first, second] = args.positional.as_slice() else {
+        return Err(Error::Usage("expected two SMILES".to_string()));
+    };
+    let parser = SmilesParser::new();
+    let mut mols = vec![];
+    for smiles in [first, second] {
+        match parser.parse(smiles) {
+            Ok(mol) => mols.push(mol),
+            Err(error) => {
+                eprintln!("{}", error.annotate(smiles));
+                return Ok(false);
+            }
+        }
+    }
+    let (first, second) = (&mols[0], &mols[1]);
+    let diff = smiles::mol_diff(first, second);
+
+    let mut out = open_output(None)?;
+    let color = io::stdout().is_terminal();
+    for line in diff_lines(&diff, first, second) {
+        let code = match line.as_bytes()[0] {
+            b'-' => "31",
+            b'+' => "32",
+            _ => "33",
+        };
+        if color {
+            writeln!(out, "\x1b[{}m{}\x1b[0m", code, line)?;
+        } else {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    out.flush()?;
+    if let Some(path) = args.option("--svg") {
+        let mut svg = open_output(Some(path))?;
+        svg.write_all(
+            diff.to_svg(first, second, &SvgOptions::default())
+                .as_bytes(),
+        )?;
+        svg.flush()?;
     }
+    Ok(true)
 }
 
-fn add_to_graph(
-    graph: &mut Graph<Atom, Bond>,
-    atom_number: u32,
-    last_node_index: Option<NodeIndex>,
-) -> NodeIndex {
-    let atom: Atom = Atom {
-        number: atom_number,
+/// One line per difference, atoms then bonds, with atom indices of their molecule.
+fn diff_lines(diff: &MolDiff, first: &Mol, second: &Mol) -> Vec<String> {
+    let atom = |mol: &Mol, atom: AtomIdx| {
+        // unwrap: the diff's atoms are atoms of the molecules
+        let atom = mol.atoms().nth(atom.index()).unwrap();
+        let symbol = Element::from_number(atom.number).map_or("*", |element| element.symbol);
+        let isotope = atom.isotope.map(|isotope| isotope.to_string());
+        let charge = match atom.charge {
+            0 => String::new(),
+            1 => "+".to_string(),
+            -1 => "-".to_string(),
+            charge => format!("{:+}", charge),
+        };
+        format!("{}{}{}", isotope.unwrap_or_default(), symbol, charge)
     };
-    let node_index = graph.add_node(atom);
-    if let Some(last) = last_node_index {
-        let bond = Bond {
-            atom_start: last.index(),
-            atom_end: node_index.index(),
+    let bond = |mol: &Mol, bond: BondIdx| {
+        // unwrap: the diff's bonds are bonds of the molecules
+        let (a, b) = mol.bond_atoms(bond).unwrap();
+        let order = mol.bonds().nth(bond.index()).unwrap().order;
+        let symbol = match order {
+            BondOrder::Single => '-',
+            BondOrder::Double => '=',
+            BondOrder::Triple => '#',
+            BondOrder::Quadruple => '$',
+            BondOrder::Aromatic => ':',
         };
-        graph.add_edge(last, node_index, bond);
+        format!(
+            "{}{}{}{}{}",
+            atom(mol, a),
+            a.index(),
+            symbol,
+            atom(mol, b),
+            b.index()
+        )
+    };
+
+    let mut lines = vec![];
+    for removed in &diff.removed_atoms {
+        lines.push(format!(
+            "- atom {}: {}",
+            removed.index(),
+            atom(first, *removed)
+        ));
     }
-    node_index
+    for added in &diff.added_atoms {
+        lines.push(format!(
+            "+ atom {}: {}",
+            added.index(),
+            atom(second, *added)
+        ));
+    }
+    for (a, b) in &diff.changed_atoms {
+        lines.push(format!(
+            "~ atom {}: {} -> atom {}: {}",
+            a.index(),
+            atom(first, *a),
+            b.index(),
+            atom(second, *b)
+        ));
+    }
+    for removed in &diff.removed_bonds {
+        lines.push(format!(
+            "- bond {}: {}",
+            removed.index(),
+            bond(first, *removed)
+        ));
+    }
+    for added in &diff.added_bonds {
+        lines.push(format!(
+            "+ bond {}: {}",
+            added.index(),
+            bond(second, *added)
+        ));
+    }
+    lines
 }
 
-pub fn string(string: &str) -> bool {
-    let mut scanner = Scanner::new(string);
+/// Valid if no input has problems.
+fn lint(args: &Args) -> CommandResult {
+    let mut out = open_output(None)?;
+    let mut valid = true;
+    let mut report = |smiles: &str| -> io::Result<()> {
+        for diagnostic in smiles::validate(smiles) {
+            writeln!(out, "{}", diagnostic.annotate(smiles))?;
+            valid = false;
+        }
+        Ok(())
+    };
 
-    loop {
-        if !unit(&mut scanner) {
-            break;
+    if args.positional.is_empty() {
+        for line in open_input(None)?.lines() {
+            let line = line?;
+            let (smiles, _) = smiles_line(&line);
+            if !smiles.is_empty() {
+                report(smiles)?;
+            }
+        }
+    } else {
+        for input in &args.positional {
+            report(input)?;
+        }
+    }
+    out.flush()?;
+    Ok(valid)
+}
+
+/// A column of `smiles props`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Property {
+    MolecularWeight,
+    ExactMass,
+    Formula,
+    Charge,
+    HeavyAtoms,
+    Rings,
+    RotatableBonds,
+    HBondDonors,
+    HBondAcceptors,
+    Tpsa,
+    Clogp,
+}
+
+impl Property {
+    const ALL: [Property; 11] = [
+        Property::MolecularWeight,
+        Property::ExactMass,
+        Property::Formula,
+        Property::Charge,
+        Property::HeavyAtoms,
+        Property::Rings,
+        Property::RotatableBonds,
+        Property::HBondDonors,
+        Property::HBondAcceptors,
+        Property::Tpsa,
+        Property::Clogp,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Property::MolecularWeight => "mw",
+            Property::ExactMass => "exact_mass",
+            Property::Formula => "formula",
+            Property::Charge => "charge",
+            Property::HeavyAtoms => "heavy_atoms",
+            Property::Rings => "rings",
+            Property::RotatableBonds => "rotatable",
+            Property::HBondDonors => "hbd",
+            Property::HBondAcceptors => "hba",
+            Property::Tpsa => "tpsa",
+            Property::Clogp => "clogp",
         }
     }
 
-    scanner.cursor() > 0 && scanner.is_done()
+    /// Comma separated names.
+    fn parse_list(list: &str) -> Result<Vec<Property>, Error> {
+        list.split(',')
+            .map(|name| {
+                Property::ALL
+                    .into_iter()
+                    .find(|property| property.name() == name.trim())
+                    .ok_or_else(|| Error::Usage(format!("unknown property: {}", name)))
+            })
+            .collect()
+    }
+
+    fn value(self, mol: &Mol) -> String {
+        match self {
+            Property::MolecularWeight => fixed(mol.molecular_weight(), 3),
+            Property::ExactMass => fixed(mol.exact_mass(), 4),
+            Property::Formula => mol.molecular_formula(),
+            Property::Charge => mol.net_charge().to_string(),
+            Property::HeavyAtoms => descriptors::heavy_atom_count(mol).to_string(),
+            Property::Rings => descriptors::ring_count(mol).to_string(),
+            Property::RotatableBonds => descriptors::rotatable_bond_count(mol).to_string(),
+            Property::HBondDonors => descriptors::h_bond_donor_count(mol).to_string(),
+            Property::HBondAcceptors => descriptors::h_bond_acceptor_count(mol).to_string(),
+            Property::Tpsa => fixed(descriptors::tpsa(mol), 2),
+            Property::Clogp => fixed(descriptors::clogp(mol), 2),
+        }
+    }
 }
 
-fn unit(scanner: &mut Scanner) -> bool {
-    scanner.take(&'*')
+/// The value with the number of decimals, without the sign of values rounded to zero.
+fn fixed(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value);
+    match formatted.strip_prefix('-') {
+        Some(unsigned) if unsigned.chars().all(|c| c == '0' || c == '.') => unsigned.to_string(),
+        _ => formatted,
+    }
 }
 
-#[cfg(test)]
-mod test {
+/// Writes a row of comma or tab separated values. CSV fields are quoted when needed,
+/// TSV fields have their tabs and line breaks replaced with spaces.
+fn write_row(out: &mut impl Write, fields: &[String], separator: char) -> io::Result<()> {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| match separator {
+            '\t' => field.replace(['\t', '\n', '\r'], " "),
+            _ if field.contains([separator, '"', '\n', '\r']) => {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            }
+            _ => field.clone(),
+        })
+        .collect();
+    writeln!(out, "{}", fields.join(&separator.to_string()))
+}
 
-    use crate::types::{Atom, Bond};
+/// One row per molecule: its SMILES and name as read, the properties, and an error
+/// message for invalid SMILES.
+fn props(args: &Args) -> CommandResult {
+    let properties = match args.option("--props") {
+        Some(list) => Property::parse_list(list)?,
+        None => Property::ALL.to_vec(),
+    };
+    let separator = match args.option("--format") {
+        None | Some("csv") => ',',
+        Some("tsv") => '\t',
+        Some(format) => return Err(Error::Usage(format!("unknown format: {}", format))),
+    };
+    let input = open_input(args.single()?)?;
+    let mut out = open_output(args.option("--output"))?;
 
-    use super::SmilesParser;
+    let mut header = vec!["smiles".to_string(), "name".to_string()];
+    header.extend(
+        properties
+            .iter()
+            .map(|property| property.name().to_string()),
+    );
+    header.push("error".to_string());
+    write_row(&mut out, &header, separator)?;
 
-    fn bond(atom_start: usize, atom_end: usize) -> Bond {
-        Bond {
-            atom_start,
-            atom_end,
+    let parser = SmilesParser::new();
+    let mut valid = true;
+    for line in input.lines() {
+        let line = line?;
+        let (smiles, name) = smiles_line(&line);
+        if smiles.is_empty() {
+            continue;
+        }
+        let mut row = vec![smiles.to_string(), name.to_string()];
+        match parser.parse(smiles) {
+            Ok(mol) => {
+                row.extend(properties.iter().map(|property| property.value(&mol)));
+                row.push(String::new());
+            }
+            Err(error) => {
+                row.extend(properties.iter().map(|_| String::new()));
+                row.push(error.to_string());
+                valid = false;
+            }
         }
+        write_row(&mut out, &row, separator)?;
+    }
+    out.flush()?;
+    Ok(valid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        convert_record, diff_lines, fixed, match_highlights, smiles_line, write_row, Args,
+        DedupeKey, Error, Format, Property, Record,
+    };
+
+    fn args(args: &[&str], allowed: &[&str]) -> Result<Args, Error> {
+        Args::parse(args.iter().map(|arg| arg.to_string()), allowed)
     }
 
     #[test]
-    fn parse_ccc() {
-        let parser = SmilesParser {};
-        let mol = parser.parse("ccc");
+    fn parse_args() {
+        let parsed = args(
+            &["in.sdf", "--to", "smiles", "-o", "out.smi"],
+            &["--to", "--output"],
+        );
+        let parsed = parsed.ok().unwrap();
+
+        assert_eq!(vec!["in.sdf"], parsed.positional);
+        assert_eq!(Some("smiles"), parsed.option("--to"));
+        assert_eq!(Some("out.smi"), parsed.option("--output"));
+        assert_eq!(None, parsed.option("--from"));
+        assert_eq!(vec!["-"], args(&["-"], &[]).ok().unwrap().positional);
+    }
 
-        assert_eq!(3, mol.num_atoms());
-        assert_eq!(2, mol.num_bonds());
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(0));
+    #[test]
+    fn parse_args_errors() {
+        assert!(matches!(args(&["--to"], &["--to"]), Err(Error::Usage(_))));
+        assert!(matches!(
+            args(&["--from", "sdf"], &["--to"]),
+            Err(Error::Usage(_))
+        ));
+        let two = args(&["a", "b"], &[]).ok().unwrap();
+        assert!(matches!(two.single(), Err(Error::Usage(_))));
     }
 
     #[test]
-    fn parse_cyclopentane() {
-        let parser = SmilesParser {};
-        let mol = parser.parse("c1cccc1");
+    fn highlights_matches() {
+        let mol = smiles::SmilesParser::new().parse("OCC(=O)O").unwrap();
+        let query = smiles::Smarts::parse("C(=O)O").unwrap();
+        let highlights = match_highlights(&mol, &query, "#FF8080");
 
-        assert_eq!(5, mol.num_atoms());
-        assert_eq!(5, mol.num_bonds());
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(0));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(1));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(2));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(3));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(4));
+        assert_eq!(1, highlights.len());
+        assert_eq!(3, highlights[0].atoms.len());
+        assert_eq!(2, highlights[0].bonds.len());
+        assert!(match_highlights(&mol, &smiles::Smarts::parse("N").unwrap(), "red").is_empty());
+    }
 
-        assert_eq!(Some(&bond(0, 1)), mol.bond_with_idx(0));
-        assert_eq!(Some(&bond(1, 2)), mol.bond_with_idx(1));
-        assert_eq!(Some(&bond(2, 3)), mol.bond_with_idx(2));
-        assert_eq!(Some(&bond(3, 4)), mol.bond_with_idx(3));
-        assert_eq!(Some(&bond(0, 4)), mol.bond_with_idx(4));
+    #[test]
+    fn formats() {
+        assert_eq!(Format::Sdf, Format::from_path(Some("dir/in.SDF")));
+        assert_eq!(Format::Smiles, Format::from_path(Some("in.smi")));
+        assert_eq!(Format::Smiles, Format::from_path(None));
+        assert!(matches!(Format::from_name("pdb"), Err(Error::Usage(_))));
     }
 
     #[test]
-    fn parse_bicyclohexyl() {
-        let parser = SmilesParser {};
-        let mol = parser.parse("c1ccccc1c2ccccc2");
-
-        assert_eq!(12, mol.num_atoms());
-        assert_eq!(13, mol.num_bonds());
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(0));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(1));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(2));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(3));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(4));
-
-        assert_eq!(Some(&bond(0, 1)), mol.bond_with_idx(0));
-        assert_eq!(Some(&bond(1, 2)), mol.bond_with_idx(1));
-        assert_eq!(Some(&bond(2, 3)), mol.bond_with_idx(2));
-        assert_eq!(Some(&bond(3, 4)), mol.bond_with_idx(3));
-        assert_eq!(Some(&bond(4, 5)), mol.bond_with_idx(4));
-        assert_eq!(Some(&bond(0, 5)), mol.bond_with_idx(5));
-        assert_eq!(Some(&bond(5, 6)), mol.bond_with_idx(6));
-        assert_eq!(Some(&bond(6, 7)), mol.bond_with_idx(7));
-        assert_eq!(Some(&bond(7, 8)), mol.bond_with_idx(8));
-        assert_eq!(Some(&bond(8, 9)), mol.bond_with_idx(9));
-        assert_eq!(Some(&bond(9, 10)), mol.bond_with_idx(10));
-        assert_eq!(Some(&bond(10, 11)), mol.bond_with_idx(11));
-        assert_eq!(Some(&bond(6, 11)), mol.bond_with_idx(12));
+    fn smiles_lines() {
+        assert_eq!(("CCO", "ethanol"), smiles_line("CCO ethanol\n"));
+        assert_eq!(("CCO", "ethyl alcohol"), smiles_line("CCO\tethyl alcohol"));
+        assert_eq!(("CCO", ""), smiles_line("  CCO"));
     }
 
     #[test]
-    fn parse_fluoroform() {
-        let parser = SmilesParser {};
-        let mol = parser.parse("fc(f)f");
+    fn convert_records() {
+        let record = |text: &str| Record::Smiles {
+            line: 3,
+            text: text.to_string(),
+        };
+
+        assert_eq!(
+            Some(Ok("OCC ethanol\n".to_string())),
+            convert_record(record("OCC  ethanol"), Format::Smiles)
+        );
+        assert!(matches!(
+            convert_record(record("CC"), Format::Sdf),
+            Some(Ok(text)) if text.ends_with("$$$$\n")
+        ));
+        assert_eq!(
+            Some(Err(
+                "line 3: C1CC: unclosed ring 1 at position 1".to_string()
+            )),
+            convert_record(record("C1CC"), Format::Smiles)
+        );
+        assert_eq!(None, convert_record(record("  "), Format::Smiles));
+    }
 
-        assert_eq!(4, mol.num_atoms());
-        assert_eq!(3, mol.num_bonds());
-        assert_eq!(Some(&Atom { number: 9 }), mol.atom_with_idx(0));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(1));
-        assert_eq!(Some(&Atom { number: 9 }), mol.atom_with_idx(2));
-        assert_eq!(Some(&Atom { number: 9 }), mol.atom_with_idx(3));
+    #[test]
+    fn dedupe_keys() {
+        let parser = smiles::SmilesParser::new();
+        let key = |name: &str, smiles: &str| {
+            let key = DedupeKey::from_name(name).ok().unwrap();
+            key.of(&parser.parse(smiles).unwrap())
+        };
 
-        assert_eq!(Some(&bond(0, 1)), mol.bond_with_idx(0));
-        assert_eq!(Some(&bond(1, 2)), mol.bond_with_idx(1));
-        assert_eq!(Some(&bond(1, 3)), mol.bond_with_idx(2));
+        assert_eq!(key("canonical", "OCC"), key("canonical", "CCO"));
+        assert_eq!(
+            key("canonical", "C1=CC=CC=C1"),
+            key("canonical", "c1ccccc1")
+        );
+        assert_ne!(
+            key("canonical", "C[C@H](N)O"),
+            key("canonical", "C[C@@H](N)O")
+        );
+        assert_eq!(16, key("structure-hash", "CCO").len());
+        assert_eq!(key("structure-hash", "OCC"), key("structure-hash", "CCO"));
+        // hydrogens written in brackets or not
+        assert_eq!(key("canonical", "[OH]CC"), key("canonical", "CCO"));
+        assert_eq!(
+            key("structure-hash", "[cH]1ccccc1"),
+            key("structure-hash", "c1ccccc1")
+        );
+        // stereo and Kekulé/aromatic forms are ignored
+        assert_eq!(
+            key("inchikey-like", "C[C@H](N)O"),
+            key("inchikey-like", "C[C@@H](N)O")
+        );
+        assert_eq!(
+            key("inchikey-like", "C1=CC=CC=C1"),
+            key("inchikey-like", "c1ccccc1")
+        );
+        assert!(matches!(
+            DedupeKey::from_name("inchi"),
+            Err(Error::Usage(_))
+        ));
     }
 
     #[test]
-    fn parse_molecule_with_inner_cl() {
-        let parser = SmilesParser {};
-        // probably an invalid molecule, doesn't matter as we're just testing the parsing
-        let mol = parser.parse("ccclc");
+    fn diff_report() {
+        let parser = smiles::SmilesParser::new();
+        let lines = |first: &str, second: &str| {
+            let (first, second) = (parser.parse(first).unwrap(), parser.parse(second).unwrap());
+            diff_lines(&smiles::mol_diff(&first, &second), &first, &second)
+        };
+
+        assert_eq!(
+            vec![
+                "- atom 2: O",
+                "+ atom 2: N",
+                "- bond 1: C1-O2",
+                "+ bond 1: C1-N2"
+            ],
+            lines("CCO", "CCN")
+        );
+        assert_eq!(
+            vec!["~ atom 3: O -> atom 3: O-"],
+            lines("CC(=O)O", "CC(=O)[O-]")
+        );
+        assert!(lines("OCC", "CCO").is_empty());
+    }
 
-        assert_eq!(4, mol.num_atoms());
-        assert_eq!(3, mol.num_bonds());
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(0));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(1));
-        assert_eq!(Some(&Atom { number: 17 }), mol.atom_with_idx(2));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(3));
+    #[test]
+    fn properties() {
+        assert_eq!(
+            vec![Property::MolecularWeight, Property::Tpsa],
+            Property::parse_list("mw, tpsa").ok().unwrap()
+        );
+        assert!(matches!(
+            Property::parse_list("mw,logd"),
+            Err(Error::Usage(_))
+        ));
 
-        assert_eq!(Some(&bond(0, 1)), mol.bond_with_idx(0));
-        assert_eq!(Some(&bond(1, 2)), mol.bond_with_idx(1));
-        assert_eq!(Some(&bond(2, 3)), mol.bond_with_idx(2));
+        let mol = smiles::SmilesParser::new().parse("CCO").unwrap();
+        let values: Vec<String> = [
+            Property::MolecularWeight,
+            Property::Formula,
+            Property::HBondDonors,
+        ]
+        .iter()
+        .map(|property| property.value(&mol))
+        .collect();
+        assert_eq!(vec!["46.069", "C2H6O", "1"], values);
+        assert_eq!("0.00", fixed(-0.001, 2));
+        assert_eq!("-0.01", fixed(-0.009, 2));
     }
 
     #[test]
-    fn parse_molecule_with_last_cl() {
-        let parser = SmilesParser {};
-        // probably an invalid molecule, doesn't matter as we're just testing the parsing
-        let mol = parser.parse("ccccl");
-
-        assert_eq!(4, mol.num_atoms());
-        assert_eq!(3, mol.num_bonds());
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(0));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(1));
-        assert_eq!(Some(&Atom { number: 6 }), mol.atom_with_idx(2));
-        assert_eq!(Some(&Atom { number: 17 }), mol.atom_with_idx(3));
-
-        assert_eq!(Some(&bond(0, 1)), mol.bond_with_idx(0));
-        assert_eq!(Some(&bond(1, 2)), mol.bond_with_idx(1));
-        assert_eq!(Some(&bond(2, 3)), mol.bond_with_idx(2));
+    fn rows() {
+        let row = |fields: &[&str], separator| {
+            let fields: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+            let mut out = vec![];
+            write_row(&mut out, &fields, separator).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!("CCO,ethanol\n", row(&["CCO", "ethanol"], ','));
+        assert_eq!(
+            "C1CC,,\"unclosed ring 1, \"\"a\"\"\"\n",
+            row(&["C1CC", "", "unclosed ring 1, \"a\""], ',')
+        );
+        assert_eq!("a b\tc\n", row(&["a\tb", "c"], '\t'));
     }
 }
@@ -0,0 +1,627 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use petgraph::graph::{NodeIndex, UnGraph};
+
+use crate::error::{MolfileError, MolfileErrorKind};
+use crate::formula::masses;
+use crate::smiles::{atom_number, element_symbol, WILDCARD};
+use crate::types::{Atom, Bond, BondOrder, Mol};
+use crate::valence;
+
+/// Reads an MDL molfile in the V2000 format: the atom and bond blocks, and the
+/// `M  CHG`, `M  ISO` and `M  RAD` properties. Coordinates and stereo flags are ignored.
+/// Hydrogens are derived from the valence model, unless the atom's valence is set: then
+/// they make up the valence. Bond type 8, any bond in queries, is read as a quadruple
+/// bond, which is how quadruple bonds are written.
+pub fn from_molblock(molblock: &str) -> Result<Mol, MolfileError> {
+    let lines: Vec<&str> = molblock.lines().collect();
+    let line = |index: usize| {
+        lines.get(index).copied().ok_or(MolfileError::new(
+            MolfileErrorKind::UnexpectedEnd,
+            index + 1,
+        ))
+    };
+
+    // the first 3 lines are the header: name, program and comment
+    let counts = line(3)?;
+    let invalid_counts = || MolfileError::new(MolfileErrorKind::InvalidCountsLine, 4);
+    let atom_count: usize = field(counts, 0, 3).ok_or_else(invalid_counts)?;
+    let bond_count: usize = field(counts, 3, 6).ok_or_else(invalid_counts)?;
+    let version = counts.get(33..).unwrap_or("").trim();
+    if !version.is_empty() && version != "V2000" {
+        return Err(MolfileError::new(
+            MolfileErrorKind::UnsupportedVersion(version.to_string()),
+            4,
+        ));
+    }
+
+    let mut graph = UnGraph::<Atom, Bond>::new_undirected();
+    let mut valences = Vec::with_capacity(atom_count);
+    for index in 4..4 + atom_count {
+        let (atom, valence) = atom(line(index)?, index + 1)?;
+        graph.add_node(atom);
+        valences.push(valence);
+    }
+
+    for index in 4 + atom_count..4 + atom_count + bond_count {
+        let invalid = || MolfileError::new(MolfileErrorKind::InvalidBondLine, index + 1);
+        let bond_line = line(index)?;
+        let atom_index = |start, end| {
+            field::<usize>(bond_line, start, end)
+                .filter(|n| (1..=atom_count).contains(n))
+                .map(|n| n - 1)
+                .ok_or_else(invalid)
+        };
+        let start = atom_index(0, 3)?;
+        let end = atom_index(3, 6)?;
+        let order = match field::<u8>(bond_line, 6, 9) {
+            Some(1) => BondOrder::Single,
+            Some(2) => BondOrder::Double,
+            Some(3) => BondOrder::Triple,
+            Some(4) => BondOrder::Aromatic,
+            Some(8) => BondOrder::Quadruple,
+            _ => return Err(invalid()),
+        };
+        if start == end {
+            return Err(invalid());
+        }
+        if order == BondOrder::Aromatic {
+            graph[NodeIndex::new(start)].aromatic = true;
+            graph[NodeIndex::new(end)].aromatic = true;
+        }
+        graph.add_edge(
+            NodeIndex::new(start),
+            NodeIndex::new(end),
+            Bond {
+                order,
+                ..Default::default()
+            },
+        );
+    }
+
+    // properties block, until `M  END`, supersedes charges, isotopes and radicals of the
+    // atom block
+    let mut reset = Vec::new();
+    for (index, property) in lines.iter().enumerate().skip(4 + atom_count + bond_count) {
+        if property.starts_with("M  END") {
+            break;
+        }
+        let Some(kind) = Property::from_line(property) else {
+            continue;
+        };
+        if !reset.contains(&kind) {
+            reset.push(kind);
+            for atom in graph.node_weights_mut() {
+                match kind {
+                    Property::Charge => atom.charge = 0,
+                    Property::Isotope => atom.isotope = None,
+                    Property::Radical => atom.num_radical_electrons = 0,
+                }
+            }
+        }
+        for (atom_index, value) in property_pairs(property).ok_or(MolfileError::new(
+            MolfileErrorKind::InvalidPropertyLine,
+            index + 1,
+        ))? {
+            let Some(atom) = atom_index
+                .checked_sub(1)
+                .and_then(|i| graph.node_weight_mut(NodeIndex::new(i)))
+            else {
+                return Err(MolfileError::new(
+                    MolfileErrorKind::InvalidPropertyLine,
+                    index + 1,
+                ));
+            };
+            let invalid = || MolfileError::new(MolfileErrorKind::InvalidPropertyLine, index + 1);
+            match kind {
+                Property::Charge => atom.charge = i8::try_from(value).map_err(|_| invalid())?,
+                Property::Isotope => {
+                    atom.isotope = Some(u16::try_from(value).map_err(|_| invalid())?)
+                }
+                Property::Radical => atom.num_radical_electrons = radical_electrons(value),
+            }
+        }
+    }
+
+    // hydrogens the valence model gives stay implicit, others are written like in brackets
+    let mut mol = Mol { graph };
+    for (node, valence) in mol.graph.node_indices().zip(valences) {
+        let implicit = valence::implicit_h_count(&mol, node);
+        let hydrogens = valence.map(|valence| {
+            valence.saturating_sub(
+                valence::bond_order_sum(&mol, node) + mol.graph[node].aromatic as u8,
+            )
+        });
+        match hydrogens {
+            Some(count) if count != implicit => {
+                mol.graph[node].explicit_h = Some(count);
+                if mol.graph[node].num_radical_electrons == 0 {
+                    mol.graph[node].num_radical_electrons = valence::valence_deficit(&mol, node);
+                }
+            }
+            _ => mol.graph[node].implicit_h = implicit,
+        }
+    }
+    Ok(mol)
+}
+
+/// The atom properties read from the properties block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Property {
+    Charge,
+    Isotope,
+    Radical,
+}
+
+impl Property {
+    fn from_line(line: &str) -> Option<Property> {
+        match line.get(..6)? {
+            "M  CHG" => Some(Property::Charge),
+            "M  ISO" => Some(Property::Isotope),
+            "M  RAD" => Some(Property::Radical),
+            _ => None,
+        }
+    }
+}
+
+/// Radical electrons of an `M  RAD` value: 1 singlet, 2 doublet, 3 triplet.
+fn radical_electrons(value: i32) -> u8 {
+    match value {
+        1 | 3 => 2,
+        2 => 1,
+        _ => 0,
+    }
+}
+
+/// Reads an atom line: coordinates, symbol, mass difference, charge code and valence.
+/// The valence is `None` unless it's set, 15 meaning 0.
+fn atom(atom_line: &str, line: usize) -> Result<(Atom, Option<u8>), MolfileError> {
+    let symbol = atom_line.get(31..34).map(str::trim).unwrap_or("");
+    if symbol.is_empty() {
+        return Err(MolfileError::new(MolfileErrorKind::InvalidAtomLine, line));
+    }
+    let (number, isotope) = match symbol {
+        "D" => (1, Some(2)),
+        "T" => (1, Some(3)),
+        "*" => (WILDCARD, None),
+        _ => (
+            atom_number(symbol).ok_or_else(|| {
+                MolfileError::new(MolfileErrorKind::UnknownElement(symbol.to_string()), line)
+            })?,
+            None,
+        ),
+    };
+
+    let mass_difference: i16 = field(atom_line, 34, 36).unwrap_or(0);
+    let isotope = match (isotope, mass_difference) {
+        (Some(isotope), _) => Some(isotope),
+        (None, 0) => None,
+        // the difference is relative to the rounded average mass
+        (None, difference) => masses(number)
+            .and_then(|(average, _)| u16::try_from(libm::round(average) as i16 + difference).ok()),
+    };
+
+    let charge_code = field::<u8>(atom_line, 36, 39).unwrap_or(0);
+    let charge = match charge_code {
+        1 => 3,
+        2 => 2,
+        3 => 1,
+        5 => -1,
+        6 => -2,
+        7 => -3,
+        _ => 0,
+    };
+    // 4 is a doublet radical
+    let num_radical_electrons = (charge_code == 4) as u8;
+
+    let valence = match field::<u8>(atom_line, 48, 51).unwrap_or(0) {
+        0 => None,
+        15 => Some(0),
+        valence => Some(valence),
+    };
+
+    Ok((
+        Atom {
+            number,
+            isotope,
+            charge,
+            num_radical_electrons,
+            ..Default::default()
+        },
+        valence,
+    ))
+}
+
+/// Parses the fixed width column `start..end` of a line, `None` if missing or invalid.
+fn field<T: core::str::FromStr>(line: &str, start: usize, end: usize) -> Option<T> {
+    line.get(start..end.min(line.len()))?.trim().parse().ok()
+}
+
+/// The (atom number, value) pairs of an `M  CHG`, `M  ISO` or `M  RAD` line.
+fn property_pairs(property: &str) -> Option<Vec<(usize, i32)>> {
+    let mut values = property.get(6..)?.split_whitespace();
+    let count: usize = values.next()?.parse().ok()?;
+    (0..count)
+        .map(|_| Some((values.next()?.parse().ok()?, values.next()?.parse().ok()?)))
+        .collect()
+}
+
+/// Writes an MDL molfile in the V2000 format. Aromatic bonds are kekulized when possible,
+/// all coordinates are 0. Charges, isotopes and radicals are written as `M  CHG`, `M  ISO`
+/// and `M  RAD` properties, and the valence of atoms whose hydrogens the valence model
+/// doesn't give.
+pub fn to_molblock(mol: &Mol) -> String {
+    let mut mol = mol.clone();
+    // aromatic bond type 4 is meant for queries: prefer the Kekulé form, if there's one
+    let _ = mol.kekulize();
+
+    let mut out = String::new();
+    out.push('\n');
+    out.push_str("  smiles\n");
+    out.push('\n');
+    out.push_str(&format!(
+        "{:>3}{:>3}  0  0  0  0  0  0  0  0999 V2000\n",
+        mol.num_atoms(),
+        mol.num_bonds()
+    ));
+
+    // what the reader sees: no explicit hydrogens, radicals as far as `M  RAD` has them
+    let mut read = mol.clone();
+    for atom in read.graph.node_weights_mut() {
+        atom.explicit_h = None;
+        if radical_value(atom.num_radical_electrons).is_none() {
+            atom.num_radical_electrons = 0;
+        }
+    }
+    for node in mol.graph.node_indices() {
+        out.push_str(&format!(
+            "{:>10.4}{:>10.4}{:>10.4} {:<3} 0{}{:>3}{}\n",
+            0.0,
+            0.0,
+            0.0,
+            element_symbol(mol.graph[node].number).unwrap_or("*"),
+            "  0".repeat(4),
+            valence_field(&mol, &read, node),
+            "  0".repeat(6)
+        ));
+    }
+
+    for edge in mol.graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (start, end) = mol.graph.edge_endpoints(edge).unwrap();
+        let order = match mol.graph[edge].order {
+            BondOrder::Single => 1,
+            BondOrder::Double => 2,
+            BondOrder::Triple => 3,
+            BondOrder::Aromatic => 4,
+            // no V2000 bond type for quadruple bonds: 8, any bond in queries, is read back as one
+            BondOrder::Quadruple => 8,
+        };
+        out.push_str(&format!(
+            "{:>3}{:>3}{:>3}  0\n",
+            start.index() + 1,
+            end.index() + 1,
+            order
+        ));
+    }
+
+    let charges: Vec<(usize, i32)> = mol
+        .graph
+        .node_weights()
+        .enumerate()
+        .filter(|(_, atom)| atom.charge != 0)
+        .map(|(i, atom)| (i + 1, atom.charge as i32))
+        .collect();
+    write_property(&mut out, "CHG", &charges);
+    let isotopes: Vec<(usize, i32)> = mol
+        .graph
+        .node_weights()
+        .enumerate()
+        .filter_map(|(i, atom)| atom.isotope.map(|isotope| (i + 1, isotope as i32)))
+        .collect();
+    write_property(&mut out, "ISO", &isotopes);
+    let radicals: Vec<(usize, i32)> = mol
+        .graph
+        .node_weights()
+        .enumerate()
+        .filter_map(|(i, atom)| {
+            radical_value(atom.num_radical_electrons).map(|value| (i + 1, value))
+        })
+        .collect();
+    write_property(&mut out, "RAD", &radicals);
+
+    out.push_str("M  END\n");
+    out
+}
+
+/// `M  RAD` value of radical electrons: 2 doublet, 3 triplet. `None` when there's none or
+/// more than 2.
+fn radical_value(electrons: u8) -> Option<i32> {
+    match electrons {
+        1 => Some(2),
+        2 => Some(3),
+        _ => None,
+    }
+}
+
+/// Valence field of an atom line: 0 when the reader derives the atom's hydrogens from the
+/// valence model, its bonds and hydrogens otherwise, 15 meaning 0. `read` is the molecule
+/// as the reader sees it.
+fn valence_field(mol: &Mol, read: &Mol, node: NodeIndex) -> u8 {
+    let atom = &mol.graph[node];
+    let hydrogens = atom.explicit_h.unwrap_or(0) + atom.implicit_h;
+    if valence::implicit_h_count(read, node) == hydrogens {
+        return 0;
+    }
+
+    match valence::bond_order_sum(mol, node) + atom.aromatic as u8 + hydrogens {
+        0 => 15,
+        valence if valence < 15 => valence,
+        // not representable
+        _ => 0,
+    }
+}
+
+/// Writes (atom number, value) pairs as property lines, at most 8 per line.
+fn write_property(out: &mut String, name: &str, pairs: &[(usize, i32)]) {
+    for chunk in pairs.chunks(8) {
+        out.push_str(&format!("M  {}{:>3}", name, chunk.len()));
+        for (atom, value) in chunk {
+            out.push_str(&format!(" {:>3} {:>3}", atom, value));
+        }
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{MolfileError, MolfileErrorKind};
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, BondOrder, Mol};
+
+    const ETHANOL: &str = "ethanol
+  smiles
+
+  3  2  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.2990    0.7500    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    2.5981   -0.0000    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+  1  2  1  0
+  2  3  1  0
+M  END
+";
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    #[test]
+    fn read_molblock() {
+        let mol = Mol::from_molblock(ETHANOL).unwrap();
+
+        assert_eq!(3, mol.num_atoms());
+        assert_eq!(2, mol.num_bonds());
+        assert_eq!(8, mol.atom_with_idx(AtomIdx::new(2)).unwrap().number);
+        assert_eq!(Some(3), mol.implicit_h_count(AtomIdx::new(0)));
+        assert_eq!(Some(1), mol.implicit_h_count(AtomIdx::new(2)));
+        assert_eq!("CCO", mol.to_smiles());
+    }
+
+    #[test]
+    fn read_charges_and_isotopes() {
+        let molblock = "
+  smiles
+
+  2  1  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   1  0  0  0  0  0  0  0  0  0  0  0
+    0.0000    0.0000    0.0000 N   0  3  0  0  0  0  0  0  0  0  0  0
+  1  2  1  0
+M  END
+";
+        let mol = Mol::from_molblock(molblock).unwrap();
+        assert_eq!(
+            Some(13),
+            mol.atom_with_idx(AtomIdx::new(0)).unwrap().isotope
+        );
+        assert_eq!(1, mol.atom_with_idx(AtomIdx::new(1)).unwrap().charge);
+        assert_eq!("[13CH3][NH3+]", mol.to_smiles());
+
+        // properties supersede the atom block
+        let molblock = molblock.replace(
+            "M  END",
+            "M  CHG  2   1  -1   2   1\nM  ISO  1   2  15\nM  END",
+        );
+        let mol = Mol::from_molblock(&molblock).unwrap();
+        assert_eq!("[CH2-][15NH3+]", mol.to_smiles());
+    }
+
+    #[test]
+    fn read_aromatic_bonds() {
+        let mut molblock = String::from("\n  smiles\n\n  6  6  0  0  0  0  0  0  0  0999 V2000\n");
+        for _ in 0..6 {
+            molblock.push_str(
+                "    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0\n",
+            );
+        }
+        for i in 1..=6 {
+            molblock.push_str(&format!("{:>3}{:>3}  4  0\n", i, i % 6 + 1));
+        }
+        molblock.push_str("M  END\n");
+
+        let mol = Mol::from_molblock(&molblock).unwrap();
+        assert_eq!("c1ccccc1", mol.to_smiles());
+    }
+
+    #[test]
+    fn write_molblock() {
+        let molblock = mol("C[NH3+]").to_molblock();
+
+        assert_eq!(
+            "
+  smiles
+
+  2  1  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    0.0000    0.0000    0.0000 N   0  0  0  0  0  0  0  0  0  0  0  0
+  1  2  1  0
+M  CHG  1   2   1
+M  END
+",
+            molblock
+        );
+    }
+
+    #[test]
+    fn write_kekulizes() {
+        let molblock = mol("c1ccccc1").to_molblock();
+        let mol = Mol::from_molblock(&molblock).unwrap();
+
+        let orders: Vec<BondOrder> = mol.graph.edge_weights().map(|b| b.order).collect();
+        assert_eq!(
+            3,
+            orders.iter().filter(|o| **o == BondOrder::Double).count()
+        );
+        assert_eq!(
+            3,
+            orders.iter().filter(|o| **o == BondOrder::Single).count()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for smiles in [
+            "CC(=O)O",
+            "[13CH4]",
+            "C#N",
+            "[O-]C(=O)C1CC1",
+            "c1ccncc1",
+            "*CC*",
+        ] {
+            let original = mol(smiles);
+            let read = Mol::from_molblock(&original.to_molblock()).unwrap();
+            let mut expected = original.clone();
+            expected.kekulize().unwrap();
+
+            assert!(read.is_same_structure(&expected), "{}", smiles);
+        }
+    }
+
+    #[test]
+    fn round_trip_hydrogens_and_radicals() {
+        for smiles in [
+            "[CH3]",
+            "[CH2]C",
+            "[C]",
+            "[H]",
+            "[HH]",
+            "[CH4]",
+            "[NH4+]",
+            "[O]",
+            "C[Si]C",
+            "[Cr]$[Cr]",
+            "C$C",
+        ] {
+            let original = mol(smiles);
+            let read = Mol::from_molblock(&original.to_molblock()).unwrap();
+
+            assert!(read.is_same_structure(&original), "{}", smiles);
+            assert!(read.is_same_mol(&original), "{}", smiles);
+            let radicals = |mol: &Mol| -> Vec<u8> {
+                mol.atoms().map(|atom| atom.num_radical_electrons).collect()
+            };
+            assert_eq!(radicals(&original), radicals(&read), "{}", smiles);
+        }
+    }
+
+    #[test]
+    fn read_valences_and_radicals() {
+        let methyl = "
+  smiles
+
+  1  0  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+M  RAD  1   1   2
+M  END
+";
+        let read = Mol::from_molblock(methyl).unwrap();
+        assert_eq!(
+            3,
+            read.atom_with_idx(AtomIdx::new(0)).unwrap().total_num_hs()
+        );
+        assert_eq!(
+            1,
+            read.atom_with_idx(AtomIdx::new(0))
+                .unwrap()
+                .num_radical_electrons
+        );
+
+        // valence 15 is 0: a bare carbon
+        let carbon = methyl
+            .replace("  0  0  0  0  0  0  0\n", " 15  0  0  0  0  0  0\n")
+            .replace("M  RAD  1   1   2\n", "");
+        let read = Mol::from_molblock(&carbon).unwrap();
+        assert_eq!(
+            0,
+            read.atom_with_idx(AtomIdx::new(0)).unwrap().total_num_hs()
+        );
+        assert_eq!(
+            4,
+            read.atom_with_idx(AtomIdx::new(0))
+                .unwrap()
+                .num_radical_electrons
+        );
+    }
+
+    #[test]
+    fn read_errors() {
+        assert_eq!(
+            Err(MolfileError::new(MolfileErrorKind::UnexpectedEnd, 4)),
+            Mol::from_molblock("name\n\n").map(|_| ())
+        );
+        assert_eq!(
+            Err(MolfileError::new(MolfileErrorKind::InvalidCountsLine, 4)),
+            Mol::from_molblock("\n\n\nabc\n").map(|_| ())
+        );
+        assert_eq!(
+            Err(MolfileError::new(
+                MolfileErrorKind::UnsupportedVersion("V3000".to_string()),
+                4
+            )),
+            Mol::from_molblock("\n\n\n  0  0  0  0  0  0            999 V3000\n").map(|_| ())
+        );
+        assert_eq!(
+            Err(MolfileError::new(
+                MolfileErrorKind::UnknownElement("Xx".to_string()),
+                5
+            )),
+            Mol::from_molblock(&ETHANOL.replace(
+                "C   0  0  0  0  0  0  0  0  0  0  0  0\n    1",
+                "Xx  0  0  0  0  0  0  0  0  0  0  0  0\n    1"
+            ))
+            .map(|_| ())
+        );
+        assert_eq!(
+            Err(MolfileError::new(MolfileErrorKind::InvalidBondLine, 9)),
+            Mol::from_molblock(&ETHANOL.replace("  2  3  1  0", "  2  4  1  0")).map(|_| ())
+        );
+        assert_eq!(
+            Err(MolfileError::new(MolfileErrorKind::UnexpectedEnd, 9)),
+            Mol::from_molblock(&ETHANOL.replace("  2  3  1  0\nM  END\n", "")).map(|_| ())
+        );
+        // values out of range aren't truncated
+        let property = |line: &str| ETHANOL.replace("M  END", &format!("{}\nM  END", line));
+        assert_eq!(
+            Err(MolfileError::new(MolfileErrorKind::InvalidPropertyLine, 10)),
+            Mol::from_molblock(&property("M  CHG  1   3 200")).map(|_| ())
+        );
+        assert_eq!(
+            Err(MolfileError::new(MolfileErrorKind::InvalidPropertyLine, 10)),
+            Mol::from_molblock(&property("M  ISO  1   3  -2")).map(|_| ())
+        );
+        let charged = Mol::from_molblock(&property("M  CHG  1   3  -1")).unwrap();
+        assert_eq!("CC[O-]", charged.to_smiles());
+    }
+}
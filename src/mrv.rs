@@ -0,0 +1,430 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+use petgraph::graph::{NodeIndex, UnGraph};
+
+use crate::error::MrvError;
+use crate::periodic_table::Element;
+use crate::types::{Atom, Bond, BondOrder, Mol};
+use crate::valence;
+
+/// Reads a Marvin document (MRV), the CML dialect of ChemAxon's drawing tools: element,
+/// charge, isotope and map number of the atoms, in `<atom>` elements or the attribute
+/// arrays of `<atomArray>`, and the order of the `<bond>`s. Molecules of the document
+/// become fragments of one molecule. Coordinates, stereo and S-groups (molecules inside
+/// a molecule) are ignored, hydrogens are derived from the valence model.
+pub fn from_mrv(mrv: &str) -> Result<Mol, MrvError> {
+    let mut graph = UnGraph::<Atom, Bond>::new_undirected();
+    // atoms by id, ids are unique within a molecule
+    let mut ids: HashMap<String, NodeIndex> = HashMap::new();
+    let mut stack: Vec<String> = vec![];
+    let mut tags = Tags {
+        xml: mrv,
+        position: 0,
+    };
+
+    while let Some(tag) = tags.next_tag()? {
+        let (name, attributes, self_closing) = match tag {
+            Tag::End(name) => {
+                if stack.pop().as_deref() != Some(name) {
+                    return Err(MrvError::Xml(tags.position));
+                }
+                continue;
+            }
+            Tag::Start(name, attributes, self_closing) => (name, attributes, self_closing),
+        };
+        let parent = stack.last().map_or("", String::as_str);
+        let grandparent = stack.len().checked_sub(2).map_or("", |i| stack[i].as_str());
+        // elements of S-groups, molecules inside the molecule, are left out
+        let molecules = stack.iter().filter(|name| *name == "molecule").count();
+        let in_molecule = |parent_name: &str| {
+            molecules == 1 && parent == parent_name && grandparent == "molecule"
+        };
+        match name {
+            "molecule" if molecules == 0 => ids.clear(),
+            "atomArray" if molecules == 1 && parent == "molecule" => {
+                for (id, atom) in atom_array(&attributes)? {
+                    ids.insert(id, graph.add_node(atom));
+                }
+            }
+            "atom" if in_molecule("atomArray") => {
+                let id = attribute(&attributes, "id").unwrap_or_default();
+                let atom = atom(
+                    attribute(&attributes, "elementType")
+                        .as_deref()
+                        .unwrap_or(""),
+                    attribute(&attributes, "formalCharge").as_deref(),
+                    attribute(&attributes, "isotope").as_deref(),
+                    attribute(&attributes, "mrvMap").as_deref(),
+                )?;
+                ids.insert(id, graph.add_node(atom));
+            }
+            "bond" if in_molecule("bondArray") => {
+                let refs = attribute(&attributes, "atomRefs2").unwrap_or_default();
+                let atoms: Vec<NodeIndex> = refs
+                    .split_whitespace()
+                    .map(|id| {
+                        ids.get(id)
+                            .copied()
+                            .ok_or_else(|| MrvError::UnknownAtom(id.to_string()))
+                    })
+                    .collect::<Result<_, _>>()?;
+                let [start, end] = atoms[..] else {
+                    return Err(invalid("atomRefs2", &refs));
+                };
+                if start == end || graph.contains_edge(start, end) {
+                    return Err(MrvError::InvalidBond(refs));
+                }
+                let order = attribute(&attributes, "order").unwrap_or_default();
+                let order = match order.as_str() {
+                    "1" => BondOrder::Single,
+                    "2" => BondOrder::Double,
+                    "3" => BondOrder::Triple,
+                    "A" | "a" => BondOrder::Aromatic,
+                    _ => return Err(invalid("order", &order)),
+                };
+                if order == BondOrder::Aromatic {
+                    graph[start].aromatic = true;
+                    graph[end].aromatic = true;
+                }
+                graph.add_edge(
+                    start,
+                    end,
+                    Bond {
+                        order,
+                        ..Default::default()
+                    },
+                );
+            }
+            _ => {}
+        }
+        if !self_closing {
+            stack.push(name.to_string());
+        }
+    }
+    if !stack.is_empty() {
+        return Err(MrvError::Xml(mrv.len()));
+    }
+
+    let mut mol = Mol { graph };
+    for node in mol.graph.node_indices() {
+        mol.graph[node].implicit_h = valence::implicit_h_count(&mol, node);
+    }
+    Ok(mol)
+}
+
+/// The atoms of an `<atomArray>` with attribute arrays, e.g. `atomID="a1 a2"
+/// elementType="C O"`, with their ids. Empty for an `<atomArray>` of `<atom>` elements.
+fn atom_array(attributes: &[(&str, String)]) -> Result<Vec<(String, Atom)>, MrvError> {
+    let Some(ids) = attribute(attributes, "atomID") else {
+        return Ok(vec![]);
+    };
+    let ids: Vec<&str> = ids.split_whitespace().collect();
+    let values = |name: &str| -> Result<Vec<Option<String>>, MrvError> {
+        let Some(values) = attribute(attributes, name) else {
+            return Ok(vec![None; ids.len()]);
+        };
+        let split: Vec<Option<String>> = values
+            .split_whitespace()
+            .map(|value| Some(value.to_string()))
+            .collect();
+        if split.len() != ids.len() {
+            return Err(invalid(name, &values));
+        }
+        Ok(split)
+    };
+    let elements = values("elementType")?;
+    let charges = values("formalCharge")?;
+    let isotopes = values("isotope")?;
+    let maps = values("mrvMap")?;
+    (0..ids.len())
+        .map(|i| {
+            let atom = atom(
+                elements[i].as_deref().unwrap_or(""),
+                charges[i].as_deref(),
+                isotopes[i].as_deref(),
+                maps[i].as_deref(),
+            )?;
+            Ok((ids[i].to_string(), atom))
+        })
+        .collect()
+}
+
+fn atom(
+    element: &str,
+    charge: Option<&str>,
+    isotope: Option<&str>,
+    map_number: Option<&str>,
+) -> Result<Atom, MrvError> {
+    let number = match element {
+        // R-groups and any atom
+        "R" | "*" => 0,
+        _ => {
+            Element::from_symbol(element)
+                .ok_or_else(|| MrvError::UnknownElement(element.to_string()))?
+                .number
+        }
+    };
+    let charge = match charge {
+        Some(charge) => charge
+            .parse()
+            .map_err(|_| invalid("formalCharge", charge))?,
+        None => 0,
+    };
+    // isotope 0 is how arrays write no isotope
+    let isotope = match isotope {
+        Some(isotope) => Some(isotope.parse().map_err(|_| invalid("isotope", isotope))?),
+        None => None,
+    }
+    .filter(|isotope| *isotope != 0);
+    let map_number = match map_number {
+        Some(map) => Some(map.parse().map_err(|_| invalid("mrvMap", map))?),
+        None => None,
+    }
+    .filter(|map| *map != 0);
+    Ok(Atom {
+        number,
+        charge,
+        isotope,
+        map_number,
+        ..Default::default()
+    })
+}
+
+fn invalid(name: &str, value: &str) -> MrvError {
+    MrvError::InvalidAttribute {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn attribute(attributes: &[(&str, String)], name: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|(attribute, _)| *attribute == name)
+        .map(|(_, value)| value.clone())
+}
+
+/// A start tag with its attributes, and whether it's self-closing (`<a/>`), or an end tag.
+enum Tag<'a> {
+    Start(&'a str, Vec<(&'a str, String)>, bool),
+    End(&'a str),
+}
+
+/// The tags of an XML document, skipping text, comments, CDATA, the declaration and the
+/// doctype. Enough XML for files written by drawing tools, not a validating parser.
+struct Tags<'a> {
+    xml: &'a str,
+    position: usize,
+}
+
+impl<'a> Tags<'a> {
+    fn next_tag(&mut self) -> Result<Option<Tag<'a>>, MrvError> {
+        loop {
+            let Some(offset) = self.xml[self.position..].find('<') else {
+                return Ok(None);
+            };
+            self.position += offset;
+            let rest = &self.xml[self.position..];
+            let skip_to = |end: &str| rest.find(end).map(|i| i + end.len());
+            let skipped = if rest.starts_with("<!--") {
+                skip_to("-->")
+            } else if rest.starts_with("<![CDATA[") {
+                skip_to("]]>")
+            } else if rest.starts_with("<?") {
+                skip_to("?>")
+            } else if rest.starts_with("<!") {
+                skip_to(">")
+            } else {
+                break;
+            };
+            self.position += skipped.ok_or(MrvError::Xml(self.position))?;
+        }
+
+        let start = self.position;
+        let error = MrvError::Xml(start);
+        let end = self.xml[start..].find('>').ok_or(error.clone())? + start;
+        self.position = end + 1;
+        let tag = &self.xml[start + 1..end];
+        if let Some(name) = tag.strip_prefix('/') {
+            return Ok(Some(Tag::End(name.trim_end())));
+        }
+        let (tag, self_closing) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = &tag[..name_end];
+        if name.is_empty() {
+            return Err(error);
+        }
+
+        let mut attributes = vec![];
+        let mut rest = tag[name_end..].trim_start();
+        while !rest.is_empty() {
+            let equals = rest.find('=').ok_or(error.clone())?;
+            let attribute = rest[..equals].trim_end();
+            let quoted = rest[equals + 1..].trim_start();
+            let quote = quoted.chars().next().filter(|c| *c == '"' || *c == '\'');
+            let quote = quote.ok_or(error.clone())?;
+            let value_end = quoted[1..].find(quote).ok_or(error.clone())? + 1;
+            let value = unescape(&quoted[1..value_end]).ok_or(error.clone())?;
+            attributes.push((attribute, value));
+            rest = quoted[value_end + 1..].trim_start();
+        }
+        Ok(Some(Tag::Start(name, attributes, self_closing)))
+    }
+}
+
+/// The attribute value with its entities (`&lt;`, `&#233;`, ...) replaced.
+fn unescape(value: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find(';')? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => entity.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::MrvError;
+    use crate::types::Mol;
+
+    fn document(molecules: &str) -> String {
+        format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "\n<!-- drawn by hand -->\n",
+                r#"<cml xmlns="http://www.chemaxon.com" version="ChemAxon file format v20.20.0">"#,
+                "\n<MDocument><MChemicalStruct>\n{}\n</MChemicalStruct></MDocument></cml>\n"
+            ),
+            molecules
+        )
+    }
+
+    fn smiles(molecules: &str) -> String {
+        Mol::from_mrv(&document(molecules)).unwrap().to_smiles()
+    }
+
+    #[test]
+    fn read_atom_elements() {
+        let acetate = r#"<molecule molID="m1">
+            <atomArray>
+                <atom id="a1" elementType="C" x2="-2.31" y2="0.77"/>
+                <atom id="a2" elementType="C" x2="-0.77" y2="0.77"/>
+                <atom id="a3" elementType="O" x2="0.0" y2="2.10"/>
+                <atom id="a4" elementType="O" formalCharge="-1" x2="0.0" y2="-0.56"/>
+            </atomArray>
+            <bondArray>
+                <bond id="b1" atomRefs2="a1 a2" order="1"/>
+                <bond id="b2" atomRefs2="a2 a3" order="2"/>
+                <bond id="b3" atomRefs2="a2 a4" order="1"><bondStereo>W</bondStereo></bond>
+            </bondArray>
+        </molecule>"#;
+        assert_eq!("CC(=O)[O-]", smiles(acetate));
+    }
+
+    #[test]
+    fn read_atom_arrays() {
+        let molecule = r#"<molecule molID="m1">
+            <atomArray atomID="a1 a2 a3" elementType="C N Cl" formalCharge="0 1 0"
+                isotope="13 0 0" mrvMap="1 0 2" x2="0 1 2" y2="0 0 0"/>
+            <bondArray><bond atomRefs2="a1 a2" order="1"/></bondArray>
+        </molecule>"#;
+        assert_eq!("[13CH3:1][NH3+].[ClH:2]", smiles(molecule));
+    }
+
+    #[test]
+    fn read_aromatic_and_fragments() {
+        let benzene = r#"<molecule molID="m1">
+            <atomArray atomID="a1 a2 a3 a4 a5 a6" elementType="C C C C C C"/>
+            <bondArray>
+                <bond atomRefs2="a1 a2" order="A"/><bond atomRefs2="a2 a3" order="A"/>
+                <bond atomRefs2="a3 a4" order="A"/><bond atomRefs2="a4 a5" order="A"/>
+                <bond atomRefs2="a5 a6" order="A"/><bond atomRefs2="a6 a1" order="A"/>
+            </bondArray>
+        </molecule>"#;
+        // ids of different molecules can be the same
+        let water = r#"<molecule molID="m2">
+            <atomArray><atom id="a1" elementType="O"/></atomArray>
+        </molecule>"#;
+        assert_eq!("c1ccccc1.O", smiles(&format!("{}\n{}", benzene, water)));
+    }
+
+    #[test]
+    fn skip_sgroups() {
+        let molecule = r#"<molecule molID="m1">
+            <atomArray atomID="a1 a2" elementType="C O"/>
+            <bondArray><bond atomRefs2="a1 a2" order="1"/></bondArray>
+            <molecule id="sg1" role="SuperatomSgroup" title="OH">
+                <atomArray atomID="a3" elementType="H"/>
+            </molecule>
+        </molecule>"#;
+        assert_eq!("CO", smiles(molecule));
+        assert_eq!("", smiles(""));
+    }
+
+    #[test]
+    fn read_errors() {
+        let error = |molecule: &str| Mol::from_mrv(&document(molecule)).unwrap_err();
+
+        assert_eq!(
+            MrvError::UnknownElement("Xx".to_string()),
+            error(r#"<molecule><atomArray atomID="a1" elementType="Xx"/></molecule>"#)
+        );
+        assert_eq!(
+            MrvError::UnknownAtom("a2".to_string()),
+            error(
+                r#"<molecule><atomArray atomID="a1" elementType="C"/>
+                <bondArray><bond atomRefs2="a1 a2" order="1"/></bondArray></molecule>"#
+            )
+        );
+        assert_eq!(
+            MrvError::InvalidBond("a1 a1".to_string()),
+            error(
+                r#"<molecule><atomArray atomID="a1" elementType="C"/>
+                <bondArray><bond atomRefs2="a1 a1" order="1"/></bondArray></molecule>"#
+            )
+        );
+        assert_eq!(
+            MrvError::InvalidAttribute {
+                name: "formalCharge".to_string(),
+                value: "0 1".to_string()
+            },
+            error(
+                r#"<molecule><atomArray atomID="a1" elementType="C" formalCharge="0 1"/></molecule>"#
+            )
+        );
+        assert!(matches!(
+            error(r#"<molecule><atomArray></molecule>"#),
+            MrvError::Xml(_)
+        ));
+        assert!(matches!(
+            Mol::from_mrv("<cml><molecule atom=></molecule></cml>"),
+            Err(MrvError::Xml(5))
+        ));
+        assert!(matches!(Mol::from_mrv("<cml>"), Err(MrvError::Xml(5))));
+    }
+}
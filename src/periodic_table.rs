@@ -0,0 +1,252 @@
+/// An element and its properties.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Element {
+    pub number: u32,
+    pub symbol: &'static str,
+    pub name: &'static str,
+    /// Normal valences, lowest first, used to derive implicit hydrogens.
+    /// Empty for elements that don't get implicit hydrogens.
+    pub default_valences: &'static [u8],
+    /// Pauling electronegativity, `None` when not known.
+    pub electronegativity: Option<f64>,
+    /// Single bond covalent radius in Å, `None` when not known.
+    pub covalent_radius: Option<f64>,
+    /// Standard atomic weight. For elements without stable isotopes, the mass number of the
+    /// most stable isotope.
+    pub average_mass: f64,
+    /// Mass of the most abundant isotope.
+    pub monoisotopic_mass: f64,
+}
+
+impl Element {
+    /// The element with the symbol, case sensitive (`Cl`, not `CL` or `cl`).
+    pub fn from_symbol(symbol: &str) -> Option<&'static Element> {
+        let number = BY_SYMBOL[symbol_slot(symbol.as_bytes())?];
+        Self::from_number(number.into())
+    }
+
+    /// The element with the symbol, also when its first letter is lowercase (`cl`).
+    pub(crate) fn from_symbol_any_first_case(symbol: &str) -> Option<&'static Element> {
+        match *symbol.as_bytes() {
+            [first] => Self::from_symbol_bytes(&[first.to_ascii_uppercase()]),
+            [first, second] => Self::from_symbol_bytes(&[first.to_ascii_uppercase(), second]),
+            _ => None,
+        }
+    }
+
+    fn from_symbol_bytes(symbol: &[u8]) -> Option<&'static Element> {
+        let number = BY_SYMBOL[symbol_slot(symbol)?];
+        Self::from_number(number.into())
+    }
+
+    pub fn from_number(number: u32) -> Option<&'static Element> {
+        ELEMENTS.get((number as usize).checked_sub(1)?)
+    }
+
+    /// All the elements, by atomic number.
+    pub fn all() -> &'static [Element] {
+        &ELEMENTS
+    }
+}
+
+/// Atomic numbers by [`symbol_slot`], 0 where there's no element.
+static BY_SYMBOL: [u8; 26 * 27] = symbol_table();
+
+const fn symbol_table() -> [u8; 26 * 27] {
+    let mut table = [0; 26 * 27];
+    let mut i = 0;
+    while i < ELEMENTS.len() {
+        match symbol_slot(ELEMENTS[i].symbol.as_bytes()) {
+            Some(slot) => table[slot] = ELEMENTS[i].number as u8,
+            None => panic!("element symbols are an uppercase and a lowercase letter at most"),
+        }
+        i += 1;
+    }
+    table
+}
+
+/// Index of a symbol like `C` or `Cl` in [`BY_SYMBOL`], `None` if it can't be a symbol.
+const fn symbol_slot(symbol: &[u8]) -> Option<usize> {
+    let (first, second) = match *symbol {
+        [first] => (first, 0),
+        [first, second] if second.is_ascii_lowercase() => (first, second - b'a' + 1),
+        _ => return None,
+    };
+    if !first.is_ascii_uppercase() {
+        return None;
+    }
+    Some((first - b'A') as usize * 27 + second as usize)
+}
+
+const fn element(
+    number: u32,
+    symbol: &'static str,
+    name: &'static str,
+    default_valences: &'static [u8],
+    electronegativity: Option<f64>,
+    covalent_radius: Option<f64>,
+    (average_mass, monoisotopic_mass): (f64, f64),
+) -> Element {
+    Element {
+        number,
+        symbol,
+        name,
+        default_valences,
+        electronegativity,
+        covalent_radius,
+        average_mass,
+        monoisotopic_mass,
+    }
+}
+
+/// By atomic number - 1. Covalent radii from Cordero et al. (2008).
+#[rustfmt::skip]
+static ELEMENTS: [Element; 118] = [
+    element(1, "H", "Hydrogen", &[1], Some(2.20), Some(0.31), (1.008, 1.00782503207)),
+    element(2, "He", "Helium", &[], None, Some(0.28), (4.002602, 4.00260325415)),
+    element(3, "Li", "Lithium", &[], Some(0.98), Some(1.28), (6.94, 7.016004548)),
+    element(4, "Be", "Beryllium", &[], Some(1.57), Some(0.96), (9.0121831, 9.012182201)),
+    element(5, "B", "Boron", &[3], Some(2.04), Some(0.84), (10.81, 11.009305406)),
+    element(6, "C", "Carbon", &[4], Some(2.55), Some(0.76), (12.011, 12.0)),
+    element(7, "N", "Nitrogen", &[3, 5], Some(3.04), Some(0.71), (14.007, 14.00307400478)),
+    element(8, "O", "Oxygen", &[2], Some(3.44), Some(0.66), (15.999, 15.99491461956)),
+    element(9, "F", "Fluorine", &[1], Some(3.98), Some(0.57), (18.998403163, 18.998403224)),
+    element(10, "Ne", "Neon", &[], None, Some(0.58), (20.1797, 19.99244017542)),
+    element(11, "Na", "Sodium", &[], Some(0.93), Some(1.66), (22.98976928, 22.98976966)),
+    element(12, "Mg", "Magnesium", &[], Some(1.31), Some(1.41), (24.305, 23.985041899)),
+    element(13, "Al", "Aluminium", &[], Some(1.61), Some(1.21), (26.9815385, 26.981538627)),
+    element(14, "Si", "Silicon", &[], Some(1.90), Some(1.11), (28.085, 27.97692653246)),
+    element(15, "P", "Phosphorus", &[3, 5], Some(2.19), Some(1.07), (30.973761998, 30.973761629)),
+    element(16, "S", "Sulfur", &[2, 4, 6], Some(2.58), Some(1.05), (32.06, 31.972070999)),
+    element(17, "Cl", "Chlorine", &[1], Some(3.16), Some(1.02), (35.45, 34.968852682)),
+    element(18, "Ar", "Argon", &[], None, Some(1.06), (39.948, 39.96238312251)),
+    element(19, "K", "Potassium", &[], Some(0.82), Some(2.03), (39.0983, 38.963706679)),
+    element(20, "Ca", "Calcium", &[], Some(1.00), Some(1.76), (40.078, 39.962590983)),
+    element(21, "Sc", "Scandium", &[], Some(1.36), Some(1.70), (44.955908, 44.955911909)),
+    element(22, "Ti", "Titanium", &[], Some(1.54), Some(1.60), (47.867, 47.947946281)),
+    element(23, "V", "Vanadium", &[], Some(1.63), Some(1.53), (50.9415, 50.943959507)),
+    element(24, "Cr", "Chromium", &[], Some(1.66), Some(1.39), (51.9961, 51.940507472)),
+    element(25, "Mn", "Manganese", &[], Some(1.55), Some(1.39), (54.938044, 54.938045141)),
+    element(26, "Fe", "Iron", &[], Some(1.83), Some(1.32), (55.845, 55.934937475)),
+    element(27, "Co", "Cobalt", &[], Some(1.88), Some(1.26), (58.933194, 58.933195048)),
+    element(28, "Ni", "Nickel", &[], Some(1.91), Some(1.24), (58.6934, 57.935342907)),
+    element(29, "Cu", "Copper", &[], Some(1.90), Some(1.32), (63.546, 62.929597474)),
+    element(30, "Zn", "Zinc", &[], Some(1.65), Some(1.22), (65.38, 63.929142222)),
+    element(31, "Ga", "Gallium", &[], Some(1.81), Some(1.22), (69.723, 68.925573587)),
+    element(32, "Ge", "Germanium", &[], Some(2.01), Some(1.20), (72.630, 73.921177767)),
+    element(33, "As", "Arsenic", &[], Some(2.18), Some(1.19), (74.921595, 74.921596478)),
+    element(34, "Se", "Selenium", &[], Some(2.55), Some(1.20), (78.971, 79.916521271)),
+    element(35, "Br", "Bromine", &[1], Some(2.96), Some(1.20), (79.904, 78.918337087)),
+    element(36, "Kr", "Krypton", &[], Some(3.00), Some(1.16), (83.798, 83.911506687)),
+    element(37, "Rb", "Rubidium", &[], Some(0.82), Some(2.20), (85.4678, 84.911789737)),
+    element(38, "Sr", "Strontium", &[], Some(0.95), Some(1.95), (87.62, 87.905612124)),
+    element(39, "Y", "Yttrium", &[], Some(1.22), Some(1.90), (88.90584, 88.905848295)),
+    element(40, "Zr", "Zirconium", &[], Some(1.33), Some(1.75), (91.224, 89.904704416)),
+    element(41, "Nb", "Niobium", &[], Some(1.6), Some(1.64), (92.90637, 92.906378058)),
+    element(42, "Mo", "Molybdenum", &[], Some(2.16), Some(1.54), (95.95, 97.905408169)),
+    element(43, "Tc", "Technetium", &[], Some(1.9), Some(1.47), (98.0, 97.907216)),
+    element(44, "Ru", "Ruthenium", &[], Some(2.2), Some(1.46), (101.07, 101.904349312)),
+    element(45, "Rh", "Rhodium", &[], Some(2.28), Some(1.42), (102.90550, 102.905504292)),
+    element(46, "Pd", "Palladium", &[], Some(2.20), Some(1.39), (106.42, 105.903485715)),
+    element(47, "Ag", "Silver", &[], Some(1.93), Some(1.45), (107.8682, 106.90509682)),
+    element(48, "Cd", "Cadmium", &[], Some(1.69), Some(1.44), (112.414, 113.90335854)),
+    element(49, "In", "Indium", &[], Some(1.78), Some(1.42), (114.818, 114.903878484)),
+    element(50, "Sn", "Tin", &[], Some(1.96), Some(1.39), (118.710, 119.902194676)),
+    element(51, "Sb", "Antimony", &[], Some(2.05), Some(1.39), (121.760, 120.903815686)),
+    element(52, "Te", "Tellurium", &[], Some(2.1), Some(1.38), (127.60, 129.906224399)),
+    element(53, "I", "Iodine", &[1], Some(2.66), Some(1.39), (126.90447, 126.904472681)),
+    element(54, "Xe", "Xenon", &[], Some(2.60), Some(1.40), (131.293, 131.904153457)),
+    element(55, "Cs", "Caesium", &[], Some(0.79), Some(2.44), (132.90545196, 132.905451932)),
+    element(56, "Ba", "Barium", &[], Some(0.89), Some(2.15), (137.327, 137.905247237)),
+    element(57, "La", "Lanthanum", &[], Some(1.10), Some(2.07), (138.90547, 138.906353267)),
+    element(58, "Ce", "Cerium", &[], Some(1.12), Some(2.04), (140.116, 139.905438706)),
+    element(59, "Pr", "Praseodymium", &[], Some(1.13), Some(2.03), (140.90766, 140.907652769)),
+    element(60, "Nd", "Neodymium", &[], Some(1.14), Some(2.01), (144.242, 141.907723297)),
+    element(61, "Pm", "Promethium", &[], Some(1.13), Some(1.99), (145.0, 144.912749023)),
+    element(62, "Sm", "Samarium", &[], Some(1.17), Some(1.98), (150.36, 151.919732425)),
+    element(63, "Eu", "Europium", &[], Some(1.2), Some(1.98), (151.964, 152.921230339)),
+    element(64, "Gd", "Gadolinium", &[], Some(1.2), Some(1.96), (157.25, 157.924103912)),
+    element(65, "Tb", "Terbium", &[], Some(1.2), Some(1.94), (158.92535, 158.925346757)),
+    element(66, "Dy", "Dysprosium", &[], Some(1.22), Some(1.92), (162.500, 163.929174751)),
+    element(67, "Ho", "Holmium", &[], Some(1.23), Some(1.92), (164.93033, 164.93032207)),
+    element(68, "Er", "Erbium", &[], Some(1.24), Some(1.89), (167.259, 165.930293061)),
+    element(69, "Tm", "Thulium", &[], Some(1.25), Some(1.90), (168.93422, 168.93421325)),
+    element(70, "Yb", "Ytterbium", &[], Some(1.1), Some(1.87), (173.045, 173.938862089)),
+    element(71, "Lu", "Lutetium", &[], Some(1.27), Some(1.87), (174.9668, 174.940771819)),
+    element(72, "Hf", "Hafnium", &[], Some(1.3), Some(1.75), (178.49, 179.946549953)),
+    element(73, "Ta", "Tantalum", &[], Some(1.5), Some(1.70), (180.94788, 180.947995763)),
+    element(74, "W", "Tungsten", &[], Some(2.36), Some(1.62), (183.84, 183.950931188)),
+    element(75, "Re", "Rhenium", &[], Some(1.9), Some(1.51), (186.207, 186.955753109)),
+    element(76, "Os", "Osmium", &[], Some(2.2), Some(1.44), (190.23, 191.96148069)),
+    element(77, "Ir", "Iridium", &[], Some(2.20), Some(1.41), (192.217, 192.96292643)),
+    element(78, "Pt", "Platinum", &[], Some(2.28), Some(1.36), (195.084, 194.964791134)),
+    element(79, "Au", "Gold", &[], Some(2.54), Some(1.36), (196.966569, 196.966568662)),
+    element(80, "Hg", "Mercury", &[], Some(2.00), Some(1.32), (200.592, 201.970643011)),
+    element(81, "Tl", "Thallium", &[], Some(1.62), Some(1.45), (204.38, 204.974427541)),
+    element(82, "Pb", "Lead", &[], Some(2.33), Some(1.46), (207.2, 207.976652071)),
+    element(83, "Bi", "Bismuth", &[], Some(2.02), Some(1.48), (208.98040, 208.980398734)),
+    element(84, "Po", "Polonium", &[], Some(2.0), Some(1.40), (209.0, 208.982430435)),
+    element(85, "At", "Astatine", &[], Some(2.2), Some(1.50), (210.0, 209.987148)),
+    element(86, "Rn", "Radon", &[], Some(2.2), Some(1.50), (222.0, 222.017577738)),
+    element(87, "Fr", "Francium", &[], Some(0.7), Some(2.60), (223.0, 223.019735857)),
+    element(88, "Ra", "Radium", &[], Some(0.9), Some(2.21), (226.0, 226.025409823)),
+    element(89, "Ac", "Actinium", &[], Some(1.1), Some(2.15), (227.0, 227.027752127)),
+    element(90, "Th", "Thorium", &[], Some(1.3), Some(2.06), (232.0377, 232.038055325)),
+    element(91, "Pa", "Protactinium", &[], Some(1.5), Some(2.00), (231.03588, 231.03588399)),
+    element(92, "U", "Uranium", &[], Some(1.38), Some(1.96), (238.02891, 238.050788247)),
+    element(93, "Np", "Neptunium", &[], Some(1.36), Some(1.90), (237.0, 237.048173444)),
+    element(94, "Pu", "Plutonium", &[], Some(1.28), Some(1.87), (244.0, 244.064203907)),
+    element(95, "Am", "Americium", &[], Some(1.13), Some(1.80), (243.0, 243.06138108)),
+    element(96, "Cm", "Curium", &[], Some(1.28), Some(1.69), (247.0, 247.07035354)),
+    element(97, "Bk", "Berkelium", &[], Some(1.3), None, (247.0, 247.07030708)),
+    element(98, "Cf", "Californium", &[], Some(1.3), None, (251.0, 251.079586788)),
+    element(99, "Es", "Einsteinium", &[], Some(1.3), None, (252.0, 252.0829785)),
+    element(100, "Fm", "Fermium", &[], Some(1.3), None, (257.0, 257.095104724)),
+    element(101, "Md", "Mendelevium", &[], Some(1.3), None, (258.0, 258.098431319)),
+    element(102, "No", "Nobelium", &[], Some(1.3), None, (259.0, 259.10103)),
+    element(103, "Lr", "Lawrencium", &[], Some(1.3), None, (266.0, 266.11983)),
+    element(104, "Rf", "Rutherfordium", &[], None, None, (267.0, 267.12179)),
+    element(105, "Db", "Dubnium", &[], None, None, (268.0, 268.12567)),
+    element(106, "Sg", "Seaborgium", &[], None, None, (269.0, 269.12863)),
+    element(107, "Bh", "Bohrium", &[], None, None, (270.0, 270.13336)),
+    element(108, "Hs", "Hassium", &[], None, None, (269.0, 269.13375)),
+    element(109, "Mt", "Meitnerium", &[], None, None, (278.0, 278.15631)),
+    element(110, "Ds", "Darmstadtium", &[], None, None, (281.0, 281.16451)),
+    element(111, "Rg", "Roentgenium", &[], None, None, (282.0, 282.16912)),
+    element(112, "Cn", "Copernicium", &[], None, None, (285.0, 285.17712)),
+    element(113, "Nh", "Nihonium", &[], None, None, (286.0, 286.18221)),
+    element(114, "Fl", "Flerovium", &[], None, None, (289.0, 289.19042)),
+    element(115, "Mc", "Moscovium", &[], None, None, (290.0, 290.19598)),
+    element(116, "Lv", "Livermorium", &[], None, None, (293.0, 293.20449)),
+    element(117, "Ts", "Tennessine", &[], None, None, (294.0, 294.21046)),
+    element(118, "Og", "Oganesson", &[], None, None, (294.0, 294.21392)),
+];
+
+#[cfg(test)]
+mod test {
+    use super::Element;
+
+    #[test]
+    fn lookups() {
+        let carbon = Element::from_symbol("C").unwrap();
+        assert_eq!(6, carbon.number);
+        assert_eq!("Carbon", carbon.name);
+        assert_eq!(Some(carbon), Element::from_number(6));
+        assert_eq!(Some(2.55), carbon.electronegativity);
+        assert_eq!(Some(0.76), carbon.covalent_radius);
+        assert_eq!(&[3, 5], Element::from_symbol("N").unwrap().default_valences);
+
+        assert_eq!(None, Element::from_symbol("Xx"));
+        assert_eq!(None, Element::from_symbol("cl"));
+        assert_eq!(None, Element::from_number(0));
+        assert_eq!(None, Element::from_number(119));
+    }
+
+    #[test]
+    fn table_is_ordered() {
+        for (i, element) in Element::all().iter().enumerate() {
+            assert_eq!(i as u32 + 1, element.number);
+        }
+        assert_eq!("Og", Element::from_number(118).unwrap().symbol);
+    }
+}
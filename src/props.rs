@@ -0,0 +1,211 @@
+//! User properties of atoms and bonds, e.g. predicted charges, highlight flags or SDF
+//! tags. They're stored on the [`Atom`](crate::Atom) or [`Bond`](crate::Bond), so they
+//! follow it when the molecule is copied, edited or split into fragments.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+
+/// The value of a property.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum PropValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+// floats compare by their bits, so that atoms with properties can still be `Eq`
+impl PartialEq for PropValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PropValue::Bool(a), PropValue::Bool(b)) => a == b,
+            (PropValue::Int(a), PropValue::Int(b)) => a == b,
+            (PropValue::Float(a), PropValue::Float(b)) => a.to_bits() == b.to_bits(),
+            (PropValue::Text(a), PropValue::Text(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PropValue {}
+
+impl From<bool> for PropValue {
+    fn from(value: bool) -> Self {
+        PropValue::Bool(value)
+    }
+}
+
+impl From<i64> for PropValue {
+    fn from(value: i64) -> Self {
+        PropValue::Int(value)
+    }
+}
+
+impl From<i32> for PropValue {
+    fn from(value: i32) -> Self {
+        PropValue::Int(value.into())
+    }
+}
+
+impl From<f64> for PropValue {
+    fn from(value: f64) -> Self {
+        PropValue::Float(value)
+    }
+}
+
+impl From<&str> for PropValue {
+    fn from(value: &str) -> Self {
+        PropValue::Text(value.to_string())
+    }
+}
+
+impl From<String> for PropValue {
+    fn from(value: String) -> Self {
+        PropValue::Text(value)
+    }
+}
+
+/// Types a property can be read as, see [`Props::get`].
+pub trait FromProp: Sized {
+    /// The value as this type, if it has this type. Integers can also be read as `f64`.
+    fn from_prop(value: &PropValue) -> Option<Self>;
+}
+
+impl FromProp for bool {
+    fn from_prop(value: &PropValue) -> Option<Self> {
+        match value {
+            PropValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromProp for i64 {
+    fn from_prop(value: &PropValue) -> Option<Self> {
+        match value {
+            PropValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromProp for f64 {
+    fn from_prop(value: &PropValue) -> Option<Self> {
+        match value {
+            PropValue::Float(value) => Some(*value),
+            PropValue::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+}
+
+impl FromProp for String {
+    fn from_prop(value: &PropValue) -> Option<Self> {
+        match value {
+            PropValue::Text(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromProp for PropValue {
+    fn from_prop(value: &PropValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+/// Properties by name. Empty, it doesn't allocate.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Props(BTreeMap<String, PropValue>);
+
+impl Props {
+    /// The property read as `T`, if it's set and has this type.
+    pub fn get<T: FromProp>(&self, key: &str) -> Option<T> {
+        self.0.get(key).and_then(T::from_prop)
+    }
+
+    /// Sets the property, replacing its previous value.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<PropValue>) {
+        self.0.insert(key.into(), value.into());
+    }
+
+    /// Removes the property, returning its value.
+    pub fn remove(&mut self, key: &str) -> Option<PropValue> {
+        self.0.remove(key)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The properties in order of their names.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PropValue)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PropValue, Props};
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, BondIdx};
+
+    #[test]
+    fn typed_values() {
+        let mut props = Props::default();
+        props.set("charge", -0.25);
+        props.set("count", 2);
+        props.set("label", "ring");
+
+        assert_eq!(Some(-0.25), props.get::<f64>("charge"));
+        assert_eq!(Some(2), props.get::<i64>("count"));
+        assert_eq!(Some(2.0), props.get::<f64>("count"));
+        assert_eq!(Some("ring".to_string()), props.get::<String>("label"));
+        // another type, or not set
+        assert_eq!(None, props.get::<bool>("label"));
+        assert_eq!(None, props.get::<f64>("missing"));
+        assert_eq!(
+            vec!["charge", "count", "label"],
+            props.iter().map(|(key, _)| key).collect::<Vec<_>>()
+        );
+
+        props.set("count", true);
+        assert_eq!(Some(true), props.get::<bool>("count"));
+        assert_eq!(Some(PropValue::Bool(true)), props.remove("count"));
+        assert!(!props.contains("count"));
+    }
+
+    #[test]
+    fn follow_atoms_and_bonds() {
+        let parser = SmilesParser::new();
+        let mut mol = parser.parse("CCO.[Na+]").unwrap();
+        mol.set_atom_prop(AtomIdx::new(2), "charge", -0.4);
+        mol.set_bond_prop(BondIdx::new(1), "highlight", true);
+
+        assert_eq!(Some(-0.4), mol.atom_prop::<f64>(AtomIdx::new(2), "charge"));
+        assert_eq!(None, mol.atom_prop::<f64>(AtomIdx::new(1), "charge"));
+        assert_eq!(None, mol.atom_prop::<f64>(AtomIdx::new(9), "charge"));
+        assert_eq!(
+            Some(true),
+            mol.bond_prop::<bool>(BondIdx::new(1), "highlight")
+        );
+
+        let fragment = &mol.fragments()[0];
+        assert_eq!(
+            Some(-0.4),
+            fragment.atom_prop::<f64>(AtomIdx::new(2), "charge")
+        );
+        // properties make atoms differ
+        let mut atom = mol.graph[petgraph::graph::NodeIndex::new(0)].clone();
+        atom.props.set("charge", 0.1);
+        assert_ne!(mol.graph[petgraph::graph::NodeIndex::new(0)], atom);
+    }
+}
@@ -0,0 +1,147 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::{SmilesError, SmilesErrorKind};
+use crate::smiles::SmilesParser;
+use crate::types::Mol;
+
+/// A reaction, parsed from reaction SMILES: `reactants>agents>products`, each section
+/// a list of molecules separated by `.`, possibly empty (e.g. `CC=O.O>>CC(O)O`).
+///
+/// Atom map numbers (`[CH3:1]`) are in [`Atom::map_number`](crate::Atom::map_number), see
+/// also [`Mol::atoms_with_map_number`].
+#[derive(Debug, Clone)]
+pub struct Reaction {
+    pub reactants: Vec<Mol>,
+    pub agents: Vec<Mol>,
+    pub products: Vec<Mol>,
+}
+
+impl Reaction {
+    pub fn parse(smiles: &str) -> Result<Reaction, SmilesError> {
+        // (start position, section), positions in characters like the parser's
+        let mut sections: Vec<(usize, String)> = vec![(0, String::new())];
+        for (position, c) in smiles.chars().enumerate() {
+            if c != '>' {
+                // unwrap: starts with a section
+                sections.last_mut().unwrap().1.push(c);
+            } else if sections.len() == 3 {
+                return Err(SmilesError::new(
+                    SmilesErrorKind::UnexpectedCharacter(c),
+                    position,
+                ));
+            } else {
+                sections.push((position + 1, String::new()));
+            }
+        }
+        if sections.len() < 3 {
+            return Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedEnd,
+                smiles.chars().count(),
+                "'>'",
+            ));
+        }
+
+        let parser = SmilesParser::new();
+        let mut molecules = sections.into_iter().map(|(start, section)| {
+            parser
+                .parse(&section)
+                .map(|mol| mol.fragments())
+                .map_err(|error| SmilesError {
+                    position: error.position + start,
+                    ..error
+                })
+        });
+        // unwrap: 3 sections
+        Ok(Reaction {
+            reactants: molecules.next().unwrap()?,
+            agents: molecules.next().unwrap()?,
+            products: molecules.next().unwrap()?,
+        })
+    }
+
+    /// Writes the reaction SMILES, each molecule as [`Mol::to_smiles`] does.
+    pub fn to_smiles(&self) -> String {
+        [&self.reactants, &self.agents, &self.products]
+            .iter()
+            .map(|molecules| {
+                molecules
+                    .iter()
+                    .map(Mol::to_smiles)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            })
+            .collect::<Vec<_>>()
+            .join(">")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{SmilesError, SmilesErrorKind};
+    use crate::types::Mol;
+
+    use super::Reaction;
+
+    fn smiles(molecules: &[Mol]) -> Vec<String> {
+        molecules.iter().map(Mol::to_smiles).collect()
+    }
+
+    #[test]
+    fn parse_reaction() {
+        let reaction = Reaction::parse("CC(=O)O.OCC>[H+]>CC(=O)OCC.O").unwrap();
+
+        assert_eq!(vec!["CC(=O)O", "OCC"], smiles(&reaction.reactants));
+        assert_eq!(vec!["[H+]"], smiles(&reaction.agents));
+        assert_eq!(vec!["CC(=O)OCC", "O"], smiles(&reaction.products));
+    }
+
+    #[test]
+    fn parse_empty_sections() {
+        let reaction = Reaction::parse("C=C.[H][H]>>CC").unwrap();
+        assert!(reaction.agents.is_empty());
+        assert_eq!(1, reaction.products.len());
+
+        let reaction = Reaction::parse(">>").unwrap();
+        assert!(reaction.reactants.is_empty() && reaction.products.is_empty());
+    }
+
+    #[test]
+    fn atom_maps() {
+        let reaction = Reaction::parse("[CH3:1][OH:2]>>[CH2:1]=[O:2]").unwrap();
+        let maps = |mol: &Mol| -> Vec<Option<u32>> {
+            mol.graph
+                .node_weights()
+                .map(|atom| atom.map_number)
+                .collect()
+        };
+
+        assert_eq!(vec![Some(1), Some(2)], maps(&reaction.reactants[0]));
+        assert_eq!(vec![Some(1), Some(2)], maps(&reaction.products[0]));
+    }
+
+    #[test]
+    fn write_reaction() {
+        for smiles in ["CCO.O>[Na+]>CC[O-]", "C=C>>CC", "[CH3:1]Br>>[CH3:1]O"] {
+            assert_eq!(smiles, Reaction::parse(smiles).unwrap().to_smiles());
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            SmilesError::expected(SmilesErrorKind::UnexpectedEnd, 5, "'>'"),
+            Reaction::parse("CC>CC").unwrap_err()
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::UnexpectedCharacter('>'), 4),
+            Reaction::parse("C>>C>C").unwrap_err()
+        );
+        // positions are in the whole reaction
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::UnclosedRing(1), 6),
+            Reaction::parse("CC>>CC1C").unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//! R-group decomposition: the substituents of molecules that share a core, at the core's
+//! attachment points, e.g. for a structure-activity table.
+
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter;
+
+use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
+use petgraph::visit::EdgeRef;
+
+use crate::error::SmilesError;
+use crate::fragments::extract;
+use crate::smarts::{AtomPrimitive, AtomQuery, BondPrimitive, BondQuery, Expr, Smarts};
+use crate::smiles::WILDCARD;
+use crate::substruct::for_each_mapping;
+use crate::types::{Atom, AtomIdx, Bond, BondOrder, Mol};
+
+/// A core with attachment points, the `*` atoms, each bonded to an atom of the core.
+/// Molecules match the core when their atoms outside it are all bonded to attachment
+/// points.
+#[derive(Debug, Clone)]
+pub struct Core {
+    // without the attachment points
+    query: Smarts,
+    // core atom of the query and label of each attachment point
+    attachments: Vec<(NodeIndex, u32)>,
+}
+
+impl Core {
+    /// A core from a molecule, e.g. parsed from `c1cccc([*:1])c1[*:2]`. Atoms match atoms
+    /// of the same element, aromaticity and charge, whatever their hydrogens, and bonds
+    /// match bonds of the same order. Attachment points are labeled by their map number,
+    /// and numbered from 1 in order when they don't have one.
+    pub fn new(core: &Mol) -> Core {
+        let mut attachments = 0;
+        let graph = core.graph.map(
+            |_, atom| {
+                if atom.number == WILDCARD {
+                    attachments += 1;
+                    return Err(atom.map_number.unwrap_or(attachments));
+                }
+                Ok(Expr::And(vec![
+                    Expr::Primitive(AtomPrimitive::Element {
+                        number: atom.number,
+                        aromatic: Some(atom.aromatic),
+                    }),
+                    Expr::Primitive(AtomPrimitive::Charge(atom.charge)),
+                ]))
+            },
+            |_, bond| {
+                Expr::Primitive(match bond.order {
+                    BondOrder::Single => BondPrimitive::Single,
+                    BondOrder::Double => BondPrimitive::Double,
+                    BondOrder::Triple => BondPrimitive::Triple,
+                    BondOrder::Quadruple => BondPrimitive::Quadruple,
+                    BondOrder::Aromatic => BondPrimitive::Aromatic,
+                })
+            },
+        );
+        Core::with_attachments(graph)
+    }
+
+    /// A core from SMARTS, e.g. `c1ccc(*)cc1*`. Attachment points are numbered from 1 in
+    /// order.
+    pub fn from_smarts(smarts: &str) -> Result<Core, SmilesError> {
+        let query = Smarts::parse(smarts)?;
+        let mut attachments = 0;
+        let graph = query.graph.map(
+            |_, atom| {
+                if atom.matches(&|primitive| *primitive == AtomPrimitive::Any) {
+                    attachments += 1;
+                    return Err(attachments);
+                }
+                Ok(atom.clone())
+            },
+            |_, bond| bond.clone(),
+        );
+        Ok(Core::with_attachments(graph))
+    }
+
+    /// From the core's graph, with the labels of the attachment points.
+    fn with_attachments(graph: UnGraph<Result<AtomQuery, u32>, BondQuery>) -> Core {
+        let mut new_index: Vec<Option<NodeIndex>> = vec![None; graph.node_count()];
+        let mut query = UnGraph::<AtomQuery, BondQuery>::new_undirected();
+        for node in graph.node_indices() {
+            if let Ok(atom) = &graph[node] {
+                new_index[node.index()] = Some(query.add_node(atom.clone()));
+            }
+        }
+        let mut attachments = vec![];
+        for edge in graph.edge_references() {
+            let ends = (
+                new_index[edge.source().index()],
+                new_index[edge.target().index()],
+            );
+            match ends {
+                (Some(a), Some(b)) => {
+                    query.add_edge(a, b, edge.weight().clone());
+                }
+                (Some(atom), None) => {
+                    // unwrap: only attachment points have no index
+                    attachments.push((atom, *graph[edge.target()].as_ref().unwrap_err()));
+                }
+                (None, Some(atom)) => {
+                    // unwrap: only attachment points have no index
+                    attachments.push((atom, *graph[edge.source()].as_ref().unwrap_err()));
+                }
+                (None, None) => {}
+            }
+        }
+        attachments.sort_by_key(|(_, label)| *label);
+        Core {
+            query: Smarts { graph: query },
+            attachments,
+        }
+    }
+
+    /// Labels of the attachment points, in increasing order.
+    pub fn labels(&self) -> Vec<u32> {
+        let mut labels: Vec<u32> = self.attachments.iter().map(|(_, label)| *label).collect();
+        labels.dedup();
+        labels
+    }
+}
+
+/// How a molecule decomposes into the core and its R groups.
+#[derive(Debug, Clone)]
+pub struct Decomposition {
+    /// Atoms of the molecule matching the atoms of the core, attachment points left out.
+    pub core_atoms: Vec<AtomIdx>,
+    /// The R group at each attachment point, by label. Its first atom is the attachment
+    /// point, a `*` with the label as map number, e.g. `[*:1]CC`, and `[*:1][H]` where
+    /// there's no substituent.
+    pub rgroups: BTreeMap<u32, Mol>,
+}
+
+/// The decompositions of molecules, `None` for those that don't match the core.
+///
+/// Written as a tab separated table of the molecules that match, with the SMILES of
+/// their R groups:
+///
+/// ```text
+/// Molecule  R1         R2
+/// 0         [*:1]C     [*:2]Cl
+/// 2         [*:1]OC    [*:2][H]
+/// ```
+#[derive(Debug, Clone)]
+pub struct RGroupTable {
+    pub labels: Vec<u32>,
+    pub rows: Vec<Option<Decomposition>>,
+}
+
+impl fmt::Display for RGroupTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Molecule")?;
+        for label in &self.labels {
+            write!(f, "\tR{}", label)?;
+        }
+        writeln!(f)?;
+        for (i, row) in self.rows.iter().enumerate() {
+            let Some(decomposition) = row else {
+                continue;
+            };
+            write!(f, "{}", i)?;
+            for rgroup in decomposition.rgroups.values() {
+                write!(f, "\t{}", rgroup.to_smiles())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decomposes each molecule. Fragments that aren't bonded to the core are left out, and
+/// a substituent bonded to the core twice, or where the core has no attachment point,
+/// doesn't match. With a symmetric core, substituents go to the lowest labels they can.
+pub fn decompose(core: &Core, mols: &[Mol]) -> RGroupTable {
+    RGroupTable {
+        labels: core.labels(),
+        rows: mols.iter().map(|mol| decompose_mol(core, mol)).collect(),
+    }
+}
+
+fn decompose_mol(core: &Core, mol: &Mol) -> Option<Decomposition> {
+    // the labels without a substituent, the fewest first
+    let mut best: Option<(Vec<bool>, Decomposition)> = None;
+    for_each_mapping(mol, &core.query, &mut |mapping| {
+        if let Some(decomposition) = decompose_mapping(core, mol, mapping) {
+            let hydrogens: Vec<bool> = decomposition.rgroups.values().map(is_hydrogen).collect();
+            if best.as_ref().is_none_or(|(best, _)| hydrogens < *best) {
+                best = Some((hydrogens, decomposition));
+            }
+        }
+        true
+    });
+    best.map(|(_, decomposition)| decomposition)
+}
+
+fn decompose_mapping(core: &Core, mol: &Mol, mapping: &[NodeIndex]) -> Option<Decomposition> {
+    let graph = &mol.graph;
+    let mut in_core = vec![false; graph.node_count()];
+    for node in mapping {
+        in_core[node.index()] = true;
+    }
+    // attachment points without a substituent yet: atom of the molecule and label
+    let mut free: Vec<(NodeIndex, u32)> = core
+        .attachments
+        .iter()
+        .map(|(atom, label)| (mapping[atom.index()], *label))
+        .collect();
+
+    let mut rgroups = BTreeMap::new();
+    let mut seen = vec![false; graph.node_count()];
+    for root in graph.node_indices() {
+        if in_core[root.index()] || seen[root.index()] {
+            continue;
+        }
+        seen[root.index()] = true;
+        let mut atoms = vec![];
+        // bond to the core, the substituent's atom and the core's
+        let mut bonds: Vec<(EdgeIndex, NodeIndex, NodeIndex)> = vec![];
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            atoms.push(AtomIdx::from(node));
+            for edge in graph.edges(node) {
+                let other = edge.target();
+                if in_core[other.index()] {
+                    bonds.push((edge.id(), node, other));
+                } else if !seen[other.index()] {
+                    seen[other.index()] = true;
+                    stack.push(other);
+                }
+            }
+        }
+        match bonds[..] {
+            [] => {}
+            [(bond, atom, core_atom)] => {
+                let free_index = free.iter().position(|(free, _)| *free == core_atom)?;
+                let (_, label) = free.remove(free_index);
+                atoms.sort();
+                let rgroup = substituent(mol, &atoms, bond, atom, core_atom, label);
+                rgroups.insert(label, rgroup);
+            }
+            _ => return None,
+        }
+    }
+    for (_, label) in free {
+        rgroups.insert(label, hydrogen(label));
+    }
+
+    Some(Decomposition {
+        core_atoms: mapping.iter().map(|node| AtomIdx::from(*node)).collect(),
+        rgroups,
+    })
+}
+
+/// The substituent's atoms, after an attachment point that takes the place of the core
+/// atom, also in stereo.
+fn substituent(
+    mol: &Mol,
+    atoms: &[AtomIdx],
+    bond: EdgeIndex,
+    atom: NodeIndex,
+    core_atom: NodeIndex,
+    label: u32,
+) -> Mol {
+    let mut mol = mol.clone();
+    let point = mol.graph.add_node(Atom {
+        number: WILDCARD,
+        map_number: Some(label),
+        ..Default::default()
+    });
+    let bond = Bond {
+        stereo: None,
+        ..mol.graph[bond].clone()
+    };
+    mol.graph.add_edge(point, atom, bond);
+
+    let (core_atom, point) = (AtomIdx::from(core_atom), AtomIdx::from(point));
+    let replace = |neighbor: &mut AtomIdx| {
+        if *neighbor == core_atom {
+            *neighbor = point;
+        }
+    };
+    for atom in mol.graph.node_weights_mut() {
+        atom.chiral_neighbors.iter_mut().for_each(replace);
+    }
+    for bond in mol.graph.edge_weights_mut() {
+        if let Some(stereo) = &mut bond.stereo {
+            replace(&mut stereo.start_neighbor);
+            replace(&mut stereo.end_neighbor);
+        }
+    }
+    let atoms: Vec<AtomIdx> = iter::once(point).chain(atoms.iter().copied()).collect();
+    extract(&mol, &atoms)
+}
+
+/// `[*:label][H]`, the R group where there's no substituent.
+fn hydrogen(label: u32) -> Mol {
+    let mut graph = UnGraph::<Atom, Bond>::new_undirected();
+    let point = graph.add_node(Atom {
+        number: WILDCARD,
+        map_number: Some(label),
+        ..Default::default()
+    });
+    let hydrogen = graph.add_node(Atom {
+        number: 1,
+        explicit_h: Some(0),
+        ..Default::default()
+    });
+    graph.add_edge(point, hydrogen, Bond::default());
+    Mol { graph }
+}
+
+fn is_hydrogen(rgroup: &Mol) -> bool {
+    rgroup.num_atoms() == 2 && rgroup.graph[NodeIndex::new(1)].number == 1
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, Mol};
+
+    use super::{decompose, Core};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    /// SMILES of the R groups of each molecule, by label.
+    fn rgroups(core: &Core, smiles: &[&str]) -> Vec<Option<Vec<String>>> {
+        let mols: Vec<Mol> = smiles.iter().map(|smiles| mol(smiles)).collect();
+        decompose(core, &mols)
+            .rows
+            .iter()
+            .map(|row| {
+                row.as_ref()
+                    .map(|row| row.rgroups.values().map(Mol::to_smiles).collect())
+            })
+            .collect()
+    }
+
+    fn strings(smiles: &[&str]) -> Option<Vec<String>> {
+        Some(smiles.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn decompose_benzene_core() {
+        let core = Core::new(&mol("c1cccc([*:1])c1[*:2]"));
+
+        assert_eq!(vec![1, 2], core.labels());
+        assert_eq!(
+            vec![
+                strings(&["[*:1]C", "[*:2]Cl"]),
+                strings(&["[*:1]OC", "[*:2][H]"]),
+                strings(&["[*:1]C(=O)O", "[*:2]N"]),
+                // a substituent where the core has no attachment point
+                None,
+                // not the core
+                None,
+            ],
+            rgroups(
+                &core,
+                &[
+                    "Clc1ccccc1C",
+                    "COc1ccccc1",
+                    "Nc1ccccc1C(=O)O",
+                    "Clc1cc(C)ccc1C",
+                    "C1CCCCC1",
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn decompose_from_smarts() {
+        let core = Core::from_smarts("*C(=O)N*").unwrap();
+
+        assert_eq!(
+            vec![
+                strings(&["[*:1]C", "[*:2]CC"]),
+                strings(&["[*:1]c1ccccc1", "[*:2][H]"]),
+            ],
+            rgroups(&core, &["CC(=O)NCC", "c1ccccc1C(=O)N"])
+        );
+    }
+
+    #[test]
+    fn decomposition_atoms() {
+        let core = Core::new(&mol("[*:1]C(=O)O"));
+        let table = decompose(&core, &[mol("CCC(=O)O.[Na+]")]);
+
+        let row = table.rows[0].as_ref().unwrap();
+        assert_eq!(
+            vec![2, 3, 4],
+            row.core_atoms.iter().map(|a| a.index()).collect::<Vec<_>>()
+        );
+        assert_eq!(AtomIdx::new(2), row.core_atoms[0]);
+        // the sodium isn't bonded to the core
+        assert_eq!("[*:1]CC", row.rgroups[&1].to_smiles());
+    }
+
+    #[test]
+    fn rgroup_keeps_stereo() {
+        let core = Core::new(&mol("[*:1]C(=O)O"));
+
+        assert_eq!(
+            vec![strings(&["[*:1][C@@H](N)C"])],
+            rgroups(&core, &["OC(=O)[C@@H](N)C"])
+        );
+        assert_eq!(
+            vec![strings(&["[*:1]/C=C/C"])],
+            rgroups(&core, &["OC(=O)/C=C/C"])
+        );
+    }
+
+    #[test]
+    fn rgroup_in_ring_with_core() {
+        let core = Core::new(&mol("c1ccccc1[*:1]"));
+
+        // bonded to the core twice
+        assert_eq!(vec![None], rgroups(&core, &["c1cccc2c1CCC2"]));
+    }
+
+    #[test]
+    fn write_table() {
+        let core = Core::new(&mol("c1cccc([*:1])c1[*:2]"));
+        let mols = [mol("Clc1ccccc1C"), mol("CCCC"), mol("COc1ccccc1")];
+
+        assert_eq!(
+            "Molecule\tR1\tR2\n0\t[*:1]C\t[*:2]Cl\n2\t[*:1]OC\t[*:2][H]\n",
+            decompose(&core, &mols).to_string()
+        );
+    }
+}
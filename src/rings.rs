@@ -0,0 +1,380 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::types::{AtomIdx, BondIdx, Mol};
+
+/// A ring: its atoms in ring order, and its bonds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ring {
+    pub atoms: Vec<AtomIdx>, // in ring order
+    pub bonds: Vec<BondIdx>,
+}
+
+/// Ring membership of a molecule's atoms and bonds, based on its SSSR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingInfo {
+    rings: Vec<Ring>,
+    atom_rings: Vec<Vec<usize>>, // ring indices per atom
+    bond_rings: Vec<Vec<usize>>, // ring indices per bond
+}
+
+impl RingInfo {
+    pub fn new(mol: &Mol) -> RingInfo {
+        let rings = sssr(mol);
+        let mut atom_rings = vec![vec![]; mol.graph.node_count()];
+        let mut bond_rings = vec![vec![]; mol.graph.edge_count()];
+        for (i, ring) in rings.iter().enumerate() {
+            for atom in &ring.atoms {
+                atom_rings[atom.index()].push(i);
+            }
+            for bond in &ring.bonds {
+                bond_rings[bond.index()].push(i);
+            }
+        }
+        RingInfo {
+            rings,
+            atom_rings,
+            bond_rings,
+        }
+    }
+
+    /// The SSSR rings, smallest first.
+    pub fn rings(&self) -> &[Ring] {
+        &self.rings
+    }
+
+    pub fn num_rings(&self) -> usize {
+        self.rings.len()
+    }
+
+    /// Indices (into `rings()`) of the rings containing the atom.
+    pub fn atom_rings(&self, atom_idx: AtomIdx) -> &[usize] {
+        self.atom_rings
+            .get(atom_idx.index())
+            .map_or(&[], |rings| rings)
+    }
+
+    /// Indices (into `rings()`) of the rings containing the bond.
+    pub fn bond_rings(&self, bond_idx: BondIdx) -> &[usize] {
+        self.bond_rings
+            .get(bond_idx.index())
+            .map_or(&[], |rings| rings)
+    }
+
+    pub fn is_atom_in_ring(&self, atom_idx: AtomIdx) -> bool {
+        !self.atom_rings(atom_idx).is_empty()
+    }
+
+    pub fn is_bond_in_ring(&self, bond_idx: BondIdx) -> bool {
+        !self.bond_rings(bond_idx).is_empty()
+    }
+
+    /// Sizes of the rings containing the atom, smallest first.
+    pub fn atom_ring_sizes(&self, atom_idx: AtomIdx) -> Vec<usize> {
+        self.atom_rings(atom_idx)
+            .iter()
+            .map(|ring| self.rings[*ring].atoms.len())
+            .collect()
+    }
+
+    /// Sizes of the rings containing the bond, smallest first.
+    pub fn bond_ring_sizes(&self, bond_idx: BondIdx) -> Vec<usize> {
+        self.bond_rings(bond_idx)
+            .iter()
+            .map(|ring| self.rings[*ring].atoms.len())
+            .collect()
+    }
+
+    /// Fused ring systems: groups of ring indices connected by shared bonds.
+    /// Spiro rings (sharing only an atom) are separate systems.
+    pub fn ring_systems(&self) -> Vec<Vec<usize>> {
+        let mut system_of: Vec<Option<usize>> = vec![None; self.rings.len()];
+        let mut systems: Vec<Vec<usize>> = vec![];
+        for start in 0..self.rings.len() {
+            if system_of[start].is_some() {
+                continue;
+            }
+            let mut system = vec![start];
+            system_of[start] = Some(systems.len());
+            let mut i = 0;
+            while i < system.len() {
+                for bond in &self.rings[system[i]].bonds {
+                    for other in &self.bond_rings[bond.index()] {
+                        if system_of[*other].is_none() {
+                            system_of[*other] = Some(systems.len());
+                            system.push(*other);
+                        }
+                    }
+                }
+                i += 1;
+            }
+            system.sort();
+            systems.push(system);
+        }
+        systems
+    }
+}
+
+/// Smallest set of smallest rings, computed as a minimum cycle basis (Horton):
+/// candidate cycles are built from shortest paths, then the shortest linearly
+/// independent ones are kept. Rings are sorted by size.
+pub fn sssr(mol: &Mol) -> Vec<Ring> {
+    let graph = &mol.graph;
+    let components = petgraph::algo::connected_components(graph);
+    let ring_count = graph.edge_count() + components - graph.node_count();
+    if ring_count == 0 {
+        return vec![];
+    }
+
+    let mut candidates: Vec<Vec<EdgeIndex>> = vec![];
+    for root in graph.node_indices() {
+        let parents = shortest_path_tree(mol, root);
+        for edge in graph.edge_references() {
+            let (Some(path_x), Some(path_y)) = (
+                path_to_root(mol, &parents, edge.source()),
+                path_to_root(mol, &parents, edge.target()),
+            ) else {
+                continue;
+            };
+            // the paths may only share the root, and can't contain the edge itself
+            if path_x.iter().any(|e| *e == edge.id() || path_y.contains(e))
+                || path_y.contains(&edge.id())
+                || shares_nodes_besides_root(mol, &path_x, &path_y, root)
+            {
+                continue;
+            }
+            let mut cycle = path_x;
+            cycle.extend(path_y);
+            cycle.push(edge.id());
+            cycle.sort();
+            candidates.push(cycle);
+        }
+    }
+    candidates.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    candidates.dedup();
+
+    // keep the shortest cycles that are independent over GF(2), as edge sets
+    let mut basis: Vec<Vec<u64>> = vec![];
+    let mut rings = vec![];
+    for cycle in candidates {
+        let mut vector = vec![0u64; graph.edge_count().div_ceil(64)];
+        for edge in &cycle {
+            vector[edge.index() / 64] |= 1 << (edge.index() % 64);
+        }
+        if add_if_independent(&mut basis, vector) {
+            rings.push(ring_from_edges(mol, &cycle));
+            if rings.len() == ring_count {
+                break;
+            }
+        }
+    }
+    rings
+}
+
+/// For each node, the edge to its parent in the BFS tree from `root`.
+/// `None`: not reachable, `Some(None)`: the root.
+fn shortest_path_tree(mol: &Mol, root: NodeIndex) -> Vec<Option<Option<EdgeIndex>>> {
+    let graph = &mol.graph;
+    let mut parents: Vec<Option<Option<EdgeIndex>>> = vec![None; graph.node_count()];
+    parents[root.index()] = Some(None);
+    let mut queue = VecDeque::from([root]);
+    while let Some(node) = queue.pop_front() {
+        let mut edges: Vec<(NodeIndex, EdgeIndex)> =
+            graph.edges(node).map(|e| (e.target(), e.id())).collect();
+        edges.sort();
+        for (neighbor, edge) in edges {
+            if parents[neighbor.index()].is_none() {
+                parents[neighbor.index()] = Some(Some(edge));
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    parents
+}
+
+/// Edges from `node` up to the root of the tree, `None` if `node` isn't reachable.
+fn path_to_root(
+    mol: &Mol,
+    parents: &[Option<Option<EdgeIndex>>],
+    node: NodeIndex,
+) -> Option<Vec<EdgeIndex>> {
+    let mut path = vec![];
+    let mut current = node;
+    while let Some(edge) = parents[current.index()]? {
+        path.push(edge);
+        current = other_end(mol, edge, current);
+    }
+    Some(path)
+}
+
+fn other_end(mol: &Mol, edge: EdgeIndex, node: NodeIndex) -> NodeIndex {
+    // unwrap: edge comes from the graph
+    let (a, b) = mol.graph.edge_endpoints(edge).unwrap();
+    if a == node {
+        b
+    } else {
+        a
+    }
+}
+
+fn shares_nodes_besides_root(
+    mol: &Mol,
+    path_x: &[EdgeIndex],
+    path_y: &[EdgeIndex],
+    root: NodeIndex,
+) -> bool {
+    let nodes = |path: &[EdgeIndex]| -> Vec<NodeIndex> {
+        path.iter()
+            .flat_map(|edge| {
+                // unwrap: edge comes from the graph
+                let (a, b) = mol.graph.edge_endpoints(*edge).unwrap();
+                [a, b]
+            })
+            .filter(|node| *node != root)
+            .collect()
+    };
+    let nodes_x = nodes(path_x);
+    nodes(path_y).iter().any(|node| nodes_x.contains(node))
+}
+
+/// Gaussian elimination over GF(2): reduces `vector` by the basis and adds it if non-zero.
+fn add_if_independent(basis: &mut Vec<Vec<u64>>, mut vector: Vec<u64>) -> bool {
+    for row in basis.iter() {
+        let pivot = leading_bit(row);
+        if let Some(pivot) = pivot {
+            if vector[pivot / 64] & (1 << (pivot % 64)) != 0 {
+                for (v, r) in vector.iter_mut().zip(row) {
+                    *v ^= r;
+                }
+            }
+        }
+    }
+    if leading_bit(&vector).is_none() {
+        return false;
+    }
+    basis.push(vector);
+    // keep rows sorted by pivot, so that each elimination step can only clear bits
+    basis.sort_by_key(|row| core::cmp::Reverse(leading_bit(row)));
+    true
+}
+
+fn leading_bit(vector: &[u64]) -> Option<usize> {
+    vector
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, word)| **word != 0)
+        .map(|(i, word)| i * 64 + 63 - word.leading_zeros() as usize)
+}
+
+/// Orders the edges of a cycle into a ring.
+fn ring_from_edges(mol: &Mol, edges: &[EdgeIndex]) -> Ring {
+    let mut remaining: Vec<EdgeIndex> = edges.to_vec();
+    let first = remaining.remove(0);
+    // unwrap: edge comes from the graph
+    let (start, mut current) = mol.graph.edge_endpoints(first).unwrap();
+    let mut atoms = vec![AtomIdx::from(start)];
+    let mut bonds = vec![BondIdx::from(first)];
+    while current != start {
+        atoms.push(current.into());
+        // unwrap: a cycle always continues
+        let position = remaining
+            .iter()
+            .position(|edge| {
+                let (a, b) = mol.graph.edge_endpoints(*edge).unwrap();
+                a == current || b == current
+            })
+            .unwrap();
+        let edge = remaining.remove(position);
+        bonds.push(edge.into());
+        current = other_end(mol, edge, current);
+    }
+    Ring { atoms, bonds }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, BondIdx};
+
+    use super::{sssr, RingInfo};
+
+    fn ring_sizes(smiles: &str) -> Vec<usize> {
+        let parser = SmilesParser::new();
+        let mol = parser.parse(smiles).unwrap();
+        sssr(&mol).iter().map(|ring| ring.atoms.len()).collect()
+    }
+
+    #[test]
+    fn sssr_acyclic() {
+        assert_eq!(Vec::<usize>::new(), ring_sizes("CCC(C)O"));
+    }
+
+    #[test]
+    fn sssr_single_rings() {
+        assert_eq!(vec![6], ring_sizes("c1ccccc1"));
+        assert_eq!(vec![3, 6], ring_sizes("C1CC1C1CCCCC1"));
+    }
+
+    #[test]
+    fn sssr_fused_rings() {
+        assert_eq!(vec![6, 6], ring_sizes("c1ccc2ccccc2c1"));
+        assert_eq!(vec![6, 6, 6], ring_sizes("c1ccc2cc3ccccc3cc2c1"));
+        assert_eq!(vec![5, 7], ring_sizes("c1cc2cccccc2c1"));
+    }
+
+    #[test]
+    fn sssr_bridged_rings() {
+        // norbornane
+        assert_eq!(vec![5, 5], ring_sizes("C1CC2CCC1C2"));
+        // cubane
+        assert_eq!(vec![4, 4, 4, 4, 4], ring_sizes("C12C3C4C1C5C2C3C45"));
+    }
+
+    #[test]
+    fn sssr_ring_order() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C1CCCC1").unwrap();
+        let ring = &sssr(&mol)[0];
+
+        assert_eq!(5, ring.bonds.len());
+        let mut atoms = ring.atoms.clone();
+        atoms.sort();
+        assert_eq!((0..5).map(AtomIdx::new).collect::<Vec<_>>(), atoms);
+    }
+
+    #[test]
+    fn ring_info_membership() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C1CC1C1CCCC2C1CCC2").unwrap();
+        let info = RingInfo::new(&mol);
+
+        assert_eq!(3, info.num_rings());
+        assert!(info.is_atom_in_ring(AtomIdx::new(0)));
+        assert_eq!(vec![3], info.atom_ring_sizes(AtomIdx::new(0)));
+        // the ring junction belongs to both fused rings
+        assert_eq!(vec![5, 6], info.atom_ring_sizes(AtomIdx::new(8)));
+        assert_eq!(vec![5, 6], info.bond_ring_sizes(BondIdx::new(8)));
+        // linker between the cyclopropane and the fused system
+        assert!(!info.is_bond_in_ring(BondIdx::new(3)));
+        assert!(info.atom_rings(AtomIdx::new(100)).is_empty());
+    }
+
+    #[test]
+    fn ring_info_systems() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C1CC1C1CCCC2C1CCC2").unwrap();
+        assert_eq!(
+            vec![vec![0], vec![1, 2]],
+            RingInfo::new(&mol).ring_systems()
+        );
+
+        // spiro rings are separate systems
+        let mol = parser.parse("C1CCC11CCCC1").unwrap();
+        assert_eq!(vec![vec![0], vec![1]], RingInfo::new(&mol).ring_systems());
+    }
+}
@@ -0,0 +1,216 @@
+//! Drug-likeness rules: limits on descriptors, e.g. Lipinski's rule of five.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::descriptors::Descriptors;
+use crate::types::Mol;
+
+/// A descriptor that rules can limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    MolecularWeight,
+    Clogp,
+    HBondDonors,
+    HBondAcceptors,
+    RotatableBonds,
+    Tpsa,
+}
+
+impl Property {
+    pub fn value(self, descriptors: &Descriptors) -> f64 {
+        match self {
+            Property::MolecularWeight => descriptors.molecular_weight,
+            Property::Clogp => descriptors.clogp,
+            Property::HBondDonors => descriptors.h_bond_donor_count as f64,
+            Property::HBondAcceptors => descriptors.h_bond_acceptor_count as f64,
+            Property::RotatableBonds => descriptors.rotatable_bond_count as f64,
+            Property::Tpsa => descriptors.tpsa,
+        }
+    }
+}
+
+/// An upper limit (inclusive) on a property.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Criterion {
+    pub property: Property,
+    pub max: f64,
+}
+
+impl Criterion {
+    pub fn new(property: Property, max: f64) -> Self {
+        Criterion { property, max }
+    }
+}
+
+/// Criteria that a molecule passes with at most `allowed_violations` failing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSet {
+    pub criteria: Vec<Criterion>,
+    pub allowed_violations: usize,
+}
+
+impl RuleSet {
+    pub fn new(criteria: Vec<Criterion>, allowed_violations: usize) -> Self {
+        RuleSet {
+            criteria,
+            allowed_violations,
+        }
+    }
+
+    /// Lipinski's rule of five, for oral drugs: one violation is allowed.
+    pub fn lipinski() -> Self {
+        RuleSet::new(
+            vec![
+                Criterion::new(Property::MolecularWeight, 500.0),
+                Criterion::new(Property::Clogp, 5.0),
+                Criterion::new(Property::HBondDonors, 5.0),
+                Criterion::new(Property::HBondAcceptors, 10.0),
+            ],
+            1,
+        )
+    }
+
+    /// Congreve's rule of three, for fragments.
+    pub fn rule_of_three() -> Self {
+        RuleSet::new(
+            vec![
+                Criterion::new(Property::MolecularWeight, 300.0),
+                Criterion::new(Property::Clogp, 3.0),
+                Criterion::new(Property::HBondDonors, 3.0),
+                Criterion::new(Property::HBondAcceptors, 3.0),
+                Criterion::new(Property::RotatableBonds, 3.0),
+                Criterion::new(Property::Tpsa, 60.0),
+            ],
+            0,
+        )
+    }
+
+    /// Veber's rules for oral bioavailability.
+    pub fn veber() -> Self {
+        RuleSet::new(
+            vec![
+                Criterion::new(Property::RotatableBonds, 10.0),
+                Criterion::new(Property::Tpsa, 140.0),
+            ],
+            0,
+        )
+    }
+
+    pub fn evaluate(&self, mol: &Mol) -> RuleEvaluation {
+        self.evaluate_descriptors(&mol.descriptors())
+    }
+
+    /// Like [`evaluate`](Self::evaluate), reusing computed descriptors.
+    pub fn evaluate_descriptors(&self, descriptors: &Descriptors) -> RuleEvaluation {
+        let results = self
+            .criteria
+            .iter()
+            .map(|criterion| {
+                let value = criterion.property.value(descriptors);
+                CriterionResult {
+                    criterion: *criterion,
+                    value,
+                    passed: value <= criterion.max,
+                }
+            })
+            .collect();
+        RuleEvaluation {
+            results,
+            allowed_violations: self.allowed_violations,
+        }
+    }
+}
+
+/// A criterion applied to a molecule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CriterionResult {
+    pub criterion: Criterion,
+    pub value: f64,
+    pub passed: bool,
+}
+
+/// The result of each criterion of a rule set, in the rule set's order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleEvaluation {
+    pub results: Vec<CriterionResult>,
+    allowed_violations: usize,
+}
+
+impl RuleEvaluation {
+    /// The criteria that failed.
+    pub fn violations(&self) -> Vec<Criterion> {
+        self.results
+            .iter()
+            .filter(|result| !result.passed)
+            .map(|result| result.criterion)
+            .collect()
+    }
+
+    /// Whether no more criteria failed than the rule set allows.
+    pub fn passed(&self) -> bool {
+        self.violations().len() <= self.allowed_violations
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    use super::{Criterion, Property, RuleSet};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    const ASPIRIN: &str = "CC(=O)Oc1ccccc1C(=O)O";
+
+    #[test]
+    fn lipinski() {
+        let evaluation = RuleSet::lipinski().evaluate(&mol(ASPIRIN));
+        assert!(evaluation.passed());
+        assert!(evaluation.violations().is_empty());
+        assert_eq!(4, evaluation.results.len());
+
+        // hexadecane: only too lipophilic
+        let alkane = mol(&"C".repeat(16));
+        assert_eq!(
+            vec![Criterion::new(Property::Clogp, 5.0)],
+            alkane.lipinski_violations()
+        );
+        assert!(RuleSet::lipinski().evaluate(&alkane).passed());
+
+        // and too heavy
+        let alkane = mol(&"C".repeat(40));
+        assert_eq!(2, alkane.lipinski_violations().len());
+        assert!(!RuleSet::lipinski().evaluate(&alkane).passed());
+    }
+
+    #[test]
+    fn rule_of_three_and_veber() {
+        assert!(RuleSet::rule_of_three().evaluate(&mol("c1ccncc1")).passed());
+        let evaluation = RuleSet::rule_of_three().evaluate(&mol(ASPIRIN));
+        assert_eq!(
+            vec![
+                Criterion::new(Property::HBondAcceptors, 3.0),
+                Criterion::new(Property::Tpsa, 60.0)
+            ],
+            evaluation.violations()
+        );
+        assert!(!evaluation.passed());
+
+        assert!(RuleSet::veber().evaluate(&mol(ASPIRIN)).passed());
+        assert!(!RuleSet::veber().evaluate(&mol(&"C".repeat(14))).passed());
+    }
+
+    #[test]
+    fn custom_rules() {
+        let rules = RuleSet::new(vec![Criterion::new(Property::MolecularWeight, 100.0)], 0);
+        let evaluation = rules.evaluate(&mol("CCO"));
+
+        assert!(evaluation.passed());
+        assert!((evaluation.results[0].value - 46.069).abs() < 1e-3);
+    }
+}
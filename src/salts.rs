@@ -0,0 +1,258 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use petgraph::graph::NodeIndex;
+
+use crate::fragments::{extract, fragment_atoms};
+use crate::smiles::SmilesParser;
+use crate::types::{AtomIdx, Mol};
+use crate::valence;
+
+/// Common counter-ions, acids and solvents of salts, as written in datasets. Fragments
+/// are compared with their charges, so `[Cl-]` and `Cl` are both listed.
+const SALTS: [&str; 36] = [
+    "[F-]",
+    "[Cl-]",
+    "[Br-]",
+    "[I-]",
+    "F",
+    "Cl",
+    "Br",
+    "I",
+    "[Li+]",
+    "[Na+]",
+    "[K+]",
+    "[Mg+2]",
+    "[Ca+2]",
+    "[Zn+2]",
+    "[NH4+]",
+    "N",
+    "O",
+    "[OH-]",
+    "OS(=O)(=O)O",
+    "OS(=O)(=O)[O-]",
+    "[O-]S(=O)(=O)[O-]",
+    "OP(=O)(O)O",
+    "[O-][N+](=O)[O-]",
+    "[O-]Cl(=O)(=O)=O",
+    "CS(=O)(=O)O",
+    "CS(=O)(=O)[O-]",
+    "Cc1ccc(cc1)S(=O)(=O)O",
+    "Cc1ccc(cc1)S(=O)(=O)[O-]",
+    "CC(=O)O",
+    "CC(=O)[O-]",
+    "OC(=O)C(F)(F)F",
+    "[O-]C(=O)C(F)(F)F",
+    "OC(=O)C(=O)O",
+    "OC(=O)/C=C\\C(=O)O",
+    "OC(=O)/C=C/C(=O)O",
+    "OC(=O)CC(O)(CC(=O)O)C(=O)O",
+];
+
+/// Which fragments [`Mol::strip_salts`] removes.
+#[derive(Debug, Clone, Default)]
+pub enum SaltPolicy {
+    /// Removes the fragments that are common counter-ions, acids or solvents, e.g.
+    /// `[Na+]`, `[Cl-]`, `CC(=O)[O-]` or `O`.
+    #[default]
+    Known,
+    /// Removes the fragments with the same structure as one of these molecules.
+    Fragments(Vec<Mol>),
+    /// Keeps only the fragment with the most heavy atoms, the heaviest one if several
+    /// have as many.
+    LargestFragment,
+}
+
+/// The molecule without its salt fragments, the atoms in the same order. Charges aren't
+/// changed: the parent of `C[NH3+].[Cl-]` is `C[NH3+]`, see [`neutralize`] for `CN`. If
+/// every fragment would be removed, the molecule is returned as it is.
+pub fn strip_salts(mol: &Mol, policy: &SaltPolicy) -> Mol {
+    let fragments = fragment_atoms(mol);
+    let kept: Vec<&Vec<AtomIdx>> = match policy {
+        SaltPolicy::Known => {
+            let parser = SmilesParser::new();
+            // unwrap: the list is valid SMILES
+            let salts: Vec<String> = SALTS
+                .iter()
+                .map(|salt| parser.parse(salt).unwrap().to_canonical_smiles())
+                .collect();
+            without_salts(mol, &fragments, &salts)
+        }
+        SaltPolicy::Fragments(salts) => {
+            let salts: Vec<String> = salts.iter().map(Mol::to_canonical_smiles).collect();
+            without_salts(mol, &fragments, &salts)
+        }
+        SaltPolicy::LargestFragment => largest_fragment(mol, &fragments).into_iter().collect(),
+    };
+    if kept.is_empty() || kept.len() == fragments.len() {
+        return mol.clone();
+    }
+
+    let mut atoms: Vec<AtomIdx> = kept.into_iter().flatten().copied().collect();
+    atoms.sort();
+    extract(mol, &atoms)
+}
+
+/// The molecule with its charged atoms neutralized by adding or removing hydrogens, the
+/// atoms in the same order: `C[NH3+]` gives `CN` and `CC(=O)[O-]` gives `CC(=O)O`.
+///
+/// Atoms keep their charge when bonded to an atom of opposite charge, e.g. in nitro
+/// groups, when they're stereocenters, and when their neutral form wouldn't have the
+/// hydrogens of a normal valence, e.g. `C[N+](C)(C)C`, `[Na+]` or `[CH3+]`.
+pub fn neutralize(mol: &Mol) -> Mol {
+    let mut neutral = mol.clone();
+    for node in mol.graph.node_indices() {
+        let atom = &mol.graph[node];
+        let opposite = mol
+            .graph
+            .neighbors(node)
+            .any(|neighbor| mol.graph[neighbor].charge * atom.charge < 0);
+        if atom.charge == 0 || opposite || atom.chirality.is_some() {
+            continue;
+        }
+        let Ok(hydrogens) = u8::try_from(atom.total_num_hs() as i16 - atom.charge as i16) else {
+            continue;
+        };
+
+        let charged = neutral.graph[node].clone();
+        let atom = &mut neutral.graph[node];
+        atom.charge = 0;
+        atom.explicit_h = None;
+        let implicit = valence::implicit_h_count(&neutral, node);
+        if implicit == hydrogens && !valence::default_valences(charged.number).is_empty() {
+            neutral.graph[node].implicit_h = implicit;
+        } else {
+            neutral.graph[node] = charged;
+        }
+    }
+    neutral
+}
+
+fn without_salts<'a>(
+    mol: &Mol,
+    fragments: &'a [Vec<AtomIdx>],
+    salts: &[String],
+) -> Vec<&'a Vec<AtomIdx>> {
+    fragments
+        .iter()
+        .filter(|atoms| !salts.contains(&extract(mol, atoms).to_canonical_smiles()))
+        .collect()
+}
+
+fn largest_fragment<'a>(mol: &Mol, fragments: &'a [Vec<AtomIdx>]) -> Option<&'a Vec<AtomIdx>> {
+    let heavy_atoms = |atoms: &[AtomIdx]| {
+        atoms
+            .iter()
+            .filter(|atom| mol.graph[NodeIndex::from(**atom)].number != 1)
+            .count()
+    };
+    let mut largest: Option<(&Vec<AtomIdx>, usize, f64)> = None;
+    for atoms in fragments {
+        let size = heavy_atoms(atoms);
+        let weight = extract(mol, atoms).molecular_weight();
+        match largest {
+            Some((_, largest_size, largest_weight))
+                if (largest_size, largest_weight) >= (size, weight) => {}
+            _ => largest = Some((atoms, size, weight)),
+        }
+    }
+    largest.map(|(atoms, _, _)| atoms)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    use super::SaltPolicy;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn stripped(smiles: &str, policy: SaltPolicy) -> String {
+        mol(smiles).strip_salts(&policy).to_smiles()
+    }
+
+    #[test]
+    fn strip_known_salts() {
+        assert_eq!("C[NH3+]", stripped("C[NH3+].[Cl-]", SaltPolicy::Known));
+        assert_eq!("CCN", stripped("Cl.CCN", SaltPolicy::Known));
+        assert_eq!(
+            "c1ccccc1C(=O)[O-]",
+            stripped("[Na+].c1ccccc1C(=O)[O-].O.O", SaltPolicy::Known)
+        );
+        assert_eq!(
+            "CN(C)C",
+            stripped("CN(C)C.OC(=O)C(F)(F)F", SaltPolicy::Known)
+        );
+        // a chloride ion, not chloromethane
+        assert_eq!("CCl", stripped("CCl.[Cl-]", SaltPolicy::Known));
+        // the ion itself
+        assert_eq!("[Na+].[Cl-]", stripped("[Na+].[Cl-]", SaltPolicy::Known));
+        assert_eq!("CCO", stripped("CCO", SaltPolicy::Known));
+    }
+
+    #[test]
+    fn neutralize() {
+        let neutral = |smiles: &str| mol(smiles).neutralize().to_smiles();
+
+        assert_eq!("CN", neutral("C[NH3+]"));
+        assert_eq!("CC(=O)O", neutral("CC(=O)[O-]"));
+        assert_eq!("OC(=O)CC(=O)O", neutral("[O-]C(=O)CC(=O)[O-]"));
+        assert_eq!("c1ccncc1", neutral("c1cc[nH+]cc1"));
+        assert_eq!("CS", neutral("C[S-]"));
+        // kept charged
+        assert_eq!("C[N+](=O)[O-]", neutral("C[N+](=O)[O-]"));
+        assert_eq!("C[N+](C)(C)C", neutral("C[N+](C)(C)C"));
+        assert_eq!("[Na+]", neutral("[Na+]"));
+        assert_eq!("[CH3+]", neutral("[CH3+]"));
+        assert_eq!("CC", neutral("CC"));
+    }
+
+    #[test]
+    fn neutral_parent() {
+        let parent = |smiles: &str| {
+            mol(smiles)
+                .strip_salts(&SaltPolicy::Known)
+                .neutralize()
+                .to_smiles()
+        };
+
+        assert_eq!("CN", parent("C[NH3+].[Cl-]"));
+        assert_eq!("c1ccccc1C(=O)O", parent("[Na+].c1ccccc1C(=O)[O-].O.O"));
+        assert_eq!("C[N+](C)(C)C", parent("C[N+](C)(C)C.[Br-]"));
+    }
+
+    #[test]
+    fn strip_salts_keeps_stereo() {
+        assert_eq!(
+            "N[C@@H](C)C(=O)O",
+            stripped("Cl.N[C@@H](C)C(=O)O", SaltPolicy::Known)
+        );
+    }
+
+    #[test]
+    fn strip_listed_fragments() {
+        let policy = SaltPolicy::Fragments(vec![mol("[Cs+]"), mol("OC(=O)C")]);
+
+        assert_eq!("CC(=O)[O-]", stripped("CC(=O)[O-].[Cs+]", policy.clone()));
+        assert_eq!("CCN", stripped("CCN.CC(=O)O", policy.clone()));
+        assert_eq!("CCN.[Na+]", stripped("CCN.[Na+]", policy));
+    }
+
+    #[test]
+    fn keep_largest_fragment() {
+        let policy = SaltPolicy::LargestFragment;
+
+        assert_eq!(
+            "CCCC(=O)[O-]",
+            stripped("[K+].CCCC(=O)[O-]", policy.clone())
+        );
+        assert_eq!("c1ccccc1", stripped("CCC.c1ccccc1.CC", policy.clone()));
+        // as many heavy atoms, the heavier one
+        assert_eq!("CCBr", stripped("CCO.CCBr", policy.clone()));
+        assert_eq!("[2H]C", stripped("[2H]C", policy));
+    }
+}
@@ -0,0 +1,131 @@
+use alloc::vec::Vec;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::error::SanitizeError;
+use crate::types::{BondOrder, Mol};
+use crate::valence;
+
+/// Normalizes charges, then checks the valences and that aromatic systems can be
+/// kekulized, and derives the implicit hydrogens again.
+///
+/// Normalization writes pentavalent nitrogen with a double bonded oxygen, as in nitro
+/// groups written `N(=O)=O`, in the charge separated form `[N+](=O)[O-]`.
+pub fn sanitize(mol: &mut Mol) -> Result<(), SanitizeError> {
+    normalize_charges(mol);
+
+    for node in mol.graph.node_indices() {
+        if !valence::is_valence_allowed(mol, node) {
+            return Err(SanitizeError::InvalidValence {
+                atom: node.into(),
+                valence: valence::explicit_valence(mol, node)
+                    + mol.graph[node].num_radical_electrons,
+                // unwrap: atoms without a valence model are always allowed
+                max: valence::max_valence(&mol.graph[node]).unwrap(),
+            });
+        }
+    }
+
+    mol.clone().kekulize()?;
+
+    for node in mol.graph.node_indices() {
+        mol.graph[node].implicit_h = valence::implicit_h_count(mol, node);
+    }
+    Ok(())
+}
+
+fn normalize_charges(mol: &mut Mol) {
+    let nodes: Vec<NodeIndex> = mol.graph.node_indices().collect();
+    for node in nodes {
+        let atom = &mol.graph[node];
+        if atom.number != 7
+            || atom.charge != 0
+            || atom.aromatic
+            || valence::explicit_valence(mol, node) != 5
+        {
+            continue;
+        }
+        let oxygen = mol.graph.edges(node).find(|edge| {
+            let neighbor = &mol.graph[edge.target()];
+            edge.weight().order == BondOrder::Double
+                && neighbor.number == 8
+                && neighbor.charge == 0
+                && mol.graph.neighbors(edge.target()).count() == 1
+        });
+        if let Some(edge) = oxygen {
+            let (edge, oxygen) = (edge.id(), edge.target());
+            mol.graph[edge].order = BondOrder::Single;
+            mol.graph[node].charge = 1;
+            mol.graph[oxygen].charge = -1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{KekulizeError, SanitizeError};
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, Mol};
+
+    fn sanitized(smiles: &str) -> Result<Mol, SanitizeError> {
+        let parser = SmilesParser::new();
+        let mut mol = parser.parse(smiles).unwrap();
+        mol.sanitize().map(|_| mol)
+    }
+
+    #[test]
+    fn valid_molecules() {
+        for smiles in [
+            "CCO",
+            "c1ccccc1",
+            "[NH4+]",
+            "C[N+](=O)[O-]",
+            "OS(=O)(=O)O",
+            "[Fe+2]",
+        ] {
+            assert_eq!(smiles, sanitized(smiles).unwrap().to_smiles());
+        }
+    }
+
+    #[test]
+    fn invalid_valences() {
+        assert_eq!(
+            Err(SanitizeError::InvalidValence {
+                atom: AtomIdx::new(1),
+                valence: 5,
+                max: 4
+            }),
+            sanitized("CC(C)(C)(C)C").map(|_| ())
+        );
+        assert_eq!(
+            Err(SanitizeError::InvalidValence {
+                atom: AtomIdx::new(0),
+                valence: 5,
+                max: 4
+            }),
+            sanitized("[CH5]").map(|_| ())
+        );
+        assert!(sanitized("CO(C)C").is_err());
+        assert!(sanitized("C[O+](C)C").is_ok());
+    }
+
+    #[test]
+    fn aromatic_systems_must_kekulize() {
+        assert_eq!(
+            Err(SanitizeError::Kekulize(KekulizeError {
+                atom: AtomIdx::new(0)
+            })),
+            sanitized("c1cccc1").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn normalizes_nitro_groups() {
+        assert_eq!("C[N+](=O)[O-]", sanitized("CN(=O)=O").unwrap().to_smiles());
+        assert_eq!(
+            "C[N+](C)(C)[O-]",
+            sanitized("CN(C)(C)=O").unwrap().to_smiles()
+        );
+    }
+}
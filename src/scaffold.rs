@@ -0,0 +1,173 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use crate::fragments::extract;
+use crate::types::{Atom, AtomIdx, Bond, BondOrder, Mol};
+use crate::valence;
+
+/// The Bemis-Murcko scaffold: ring systems and the linkers between them, with the atoms
+/// double bonded to those. Side chains are removed, their bonds become hydrogens.
+/// Empty for molecules without rings.
+pub fn murcko_scaffold(mol: &Mol) -> Mol {
+    let graph = &mol.graph;
+    let ring_info = mol.ring_info();
+
+    // remove chain ends until only rings and the chains between them are left
+    let mut kept = vec![true; graph.node_count()];
+    let mut degrees: Vec<usize> = graph
+        .node_indices()
+        .map(|node| graph.neighbors(node).count())
+        .collect();
+    let mut ends: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|node| degrees[node.index()] <= 1 && !ring_info.is_atom_in_ring((*node).into()))
+        .collect();
+    while let Some(node) = ends.pop() {
+        if !kept[node.index()] {
+            continue;
+        }
+        kept[node.index()] = false;
+        for neighbor in graph.neighbors(node) {
+            let i = neighbor.index();
+            degrees[i] -= 1;
+            if kept[i] && degrees[i] <= 1 && !ring_info.is_atom_in_ring(neighbor.into()) {
+                ends.push(neighbor);
+            }
+        }
+    }
+
+    // e.g. the oxygen of a ring ketone
+    let double_bonded: Vec<usize> = graph
+        .edge_references()
+        .filter(|edge| edge.weight().order == BondOrder::Double)
+        .filter_map(
+            |edge| match (kept[edge.source().index()], kept[edge.target().index()]) {
+                (true, false) => Some(edge.target().index()),
+                (false, true) => Some(edge.source().index()),
+                _ => None,
+            },
+        )
+        .collect();
+    for atom in double_bonded {
+        kept[atom] = true;
+    }
+
+    let atoms: Vec<AtomIdx> = (0..graph.node_count())
+        .filter(|i| kept[*i])
+        .map(AtomIdx::new)
+        .collect();
+    let scaffold = extract(mol, &atoms);
+
+    // hydrogens replace the removed bonds, written when the valence model doesn't give
+    // them (e.g. the n of an N-methylpyrrole)
+    let hydrogens: Vec<u8> = atoms
+        .iter()
+        .map(|atom| {
+            let node = NodeIndex::from(*atom);
+            let removed: u8 = graph
+                .edges(node)
+                .filter(|edge| !kept[edge.source().index()] || !kept[edge.target().index()])
+                .map(|edge| bond_valence(edge.weight()))
+                .sum();
+            graph[node].total_num_hs() + removed
+        })
+        .collect();
+    let mut scaffold = with_implicit_hs(scaffold);
+    for (node, hydrogens) in scaffold.graph.node_indices().zip(hydrogens) {
+        let atom = &mut scaffold.graph[node];
+        if atom.explicit_h.is_some() || atom.implicit_h != hydrogens {
+            atom.explicit_h = Some(hydrogens);
+            atom.implicit_h = 0;
+        }
+    }
+    scaffold
+}
+
+/// The Murcko scaffold with all atoms as carbons and all bonds single, e.g. to group
+/// scaffolds that only differ in heteroatoms or bond orders.
+pub fn generic_scaffold(mol: &Mol) -> Mol {
+    let scaffold = murcko_scaffold(mol);
+    let graph = scaffold.graph.map(
+        |_, _| Atom {
+            number: 6,
+            ..Default::default()
+        },
+        |_, _| Bond::default(),
+    );
+    with_implicit_hs(Mol { graph })
+}
+
+fn bond_valence(bond: &Bond) -> u8 {
+    match bond.order {
+        BondOrder::Single | BondOrder::Aromatic => 1,
+        BondOrder::Double => 2,
+        BondOrder::Triple => 3,
+        BondOrder::Quadruple => 4,
+    }
+}
+
+fn with_implicit_hs(mut mol: Mol) -> Mol {
+    for node in mol.graph.node_indices() {
+        mol.graph[node].implicit_h = valence::implicit_h_count(&mol, node);
+    }
+    mol
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn scaffold(smiles: &str) -> String {
+        mol(smiles).murcko_scaffold().to_canonical_smiles()
+    }
+
+    fn canonical(smiles: &str) -> String {
+        mol(smiles).to_canonical_smiles()
+    }
+
+    #[test]
+    fn removes_side_chains() {
+        assert_eq!(canonical("c1ccccc1"), scaffold("CCc1ccc(O)cc1"));
+        assert_eq!(canonical("c1ccccc1"), scaffold("CC(=O)Oc1ccccc1C(=O)O"));
+        assert_eq!(0, mol("CCCO").murcko_scaffold().num_atoms());
+    }
+
+    #[test]
+    fn keeps_linkers() {
+        assert_eq!(
+            canonical("c1ccc(cc1)CCc1ccncc1"),
+            scaffold("c1ccc(cc1)CC(C)c1ccncc1N")
+        );
+        // with the exocyclic double bonds
+        assert_eq!(
+            canonical("O=C(c1ccccc1)C1CC1"),
+            scaffold("O=C(c1ccc(C)cc1)C1CC1")
+        );
+        assert_eq!(canonical("O=C1CCCCC1"), scaffold("CC1CCCCC1=O"));
+    }
+
+    #[test]
+    fn fills_bracket_hydrogens() {
+        assert_eq!(canonical("c1cc[nH]c1"), scaffold("Cn1cccc1"));
+        assert_eq!(canonical("c1cc[nH+]cc1"), scaffold("C[n+]1ccccc1"));
+    }
+
+    #[test]
+    fn generic() {
+        let scaffold = mol("c1ccc(cc1)C(=O)Nc1ccncc1").generic_scaffold();
+
+        assert_eq!(
+            canonical("C1CCC(CC1)C(C)CC1CCCCC1"),
+            scaffold.to_canonical_smiles()
+        );
+    }
+}
@@ -1,14 +1,22 @@
+use alloc::string::String;
+
 // src: https://depth-first.com/articles/2021/12/16/a-beginners-guide-to-parsing-in-rust/
-pub struct Scanner {
+/// Reads a string character by character. The cursor is a character position, which
+/// is what errors report; the input is indexed by its byte offset, so reading doesn't
+/// copy or allocate.
+pub struct Scanner<'a> {
+    input: &'a str,
+    // byte offset of the cursor
+    offset: usize,
     cursor: usize,
-    characters: Vec<char>,
 }
 
-impl Scanner {
-    pub fn new(string: &str) -> Self {
+impl<'a> Scanner<'a> {
+    pub fn new(string: &'a str) -> Self {
         Self {
+            input: string,
+            offset: 0,
             cursor: 0,
-            characters: string.chars().collect(),
         }
     }
 
@@ -17,86 +25,77 @@ impl Scanner {
         self.cursor
     }
 
-    /// Returns the next character without advancing the cursor.
-    /// AKA "lookahead"
-    pub fn peek(&self) -> Option<&char> {
-        self.characters.get(self.cursor)
+    /// Returns the characters between `start` (inclusive) and `end` (exclusive).
+    pub fn slice(&self, start: usize, end: usize) -> String {
+        let end = end.max(start);
+        self.input.chars().skip(start).take(end - start).collect()
     }
 
-    /// Returns true if further progress is not possible.
-    pub fn is_done(&self) -> bool {
-        self.cursor == self.characters.len()
+    /// Returns the next character without advancing the cursor.
+    /// AKA "lookahead"
+    pub fn peek(&self) -> Option<char> {
+        char_at(self.input, self.offset)
     }
 
     /// Returns the next character (if available) and advances the cursor.
-    pub fn pop(&mut self) -> Option<&char> {
-        match self.characters.get(self.cursor) {
-            Some(character) => {
-                self.cursor += 1;
-
-                Some(character)
-            }
-            None => None,
-        }
+    pub fn pop(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.advance(character);
+        Some(character)
     }
 
     /// Returns true if the `target` is found at the current cursor position,
     /// and advances the cursor.
     /// Otherwise, returns false leaving the cursor unchanged.
     pub fn take(&mut self, target: &char) -> bool {
-        match self.characters.get(self.cursor) {
-            Some(character) => {
-                if target == character {
-                    self.cursor += 1;
-
-                    true
-                } else {
-                    false
-                }
+        match self.peek() {
+            Some(character) if *target == character => {
+                self.advance(character);
+
+                true
             }
-            None => false,
+            _ => false,
         }
     }
 
     /// Invoke `cb` once. If the result is not `None`, return it and advance
     /// the cursor. Otherwise, return None and leave the cursor unchanged.
-    pub fn transform<T>(&mut self, cb: impl FnOnce(&char) -> Option<T>) -> Option<T> {
-        match self.characters.get(self.cursor) {
-            Some(input) => match cb(input) {
-                Some(output) => {
-                    self.cursor += 1;
+    pub fn transform<T>(&mut self, cb: impl FnOnce(char) -> Option<T>) -> Option<T> {
+        let character = self.peek()?;
+        let output = cb(character)?;
+        self.advance(character);
 
-                    Some(output)
-                }
-                None => None,
-            },
-            None => None,
-        }
+        Some(output)
     }
 
-    pub fn scan<T>(&mut self, cb: impl Fn(&str) -> Option<Action<T>>) -> Result<Option<T>, Error> {
-        let mut sequence = String::new();
+    /// Invokes `cb` with the characters from the cursor, one more each time, following
+    /// the returned [`Action`]s.
+    pub fn scan<T>(
+        &mut self,
+        cb: impl Fn(&'a str) -> Option<Action<T>>,
+    ) -> Result<Option<T>, Error> {
+        let start = self.offset;
         let mut require = false;
         let mut request = None;
 
         loop {
-            match self.characters.get(self.cursor) {
+            match self.peek() {
                 Some(target) => {
-                    sequence.push(*target);
+                    let sequence = &self.input[start..self.offset + target.len_utf8()];
 
-                    match cb(&sequence) {
+                    match cb(sequence) {
                         Some(Action::Return(result)) => {
-                            self.cursor += 1;
+                            self.advance(target);
 
                             break Ok(Some(result));
                         }
                         Some(Action::Request(result)) => {
-                            self.cursor += 1;
+                            self.advance(target);
                             require = false;
                             request = Some(result);
                         }
                         Some(Action::Require) => {
-                            self.cursor += 1;
+                            self.advance(target);
                             require = true;
                         }
                         None => {
@@ -118,6 +117,21 @@ impl Scanner {
             }
         }
     }
+
+    fn advance(&mut self, character: char) {
+        self.offset += character.len_utf8();
+        self.cursor += 1;
+    }
+}
+
+/// The character starting at byte `offset`, without decoding for ASCII.
+fn char_at(input: &str, offset: usize) -> Option<char> {
+    let byte = *input.as_bytes().get(offset)?;
+    if byte.is_ascii() {
+        Some(byte as char)
+    } else {
+        input[offset..].chars().next()
+    }
 }
 
 pub enum Action<T> {
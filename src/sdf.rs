@@ -0,0 +1,246 @@
+//! Reading and writing SD files: molfiles followed by data items, separated by `$$$$`.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use crate::error::SdfError;
+use crate::types::Mol;
+
+/// Iterates over the records of an SD file, one at a time, yielding the molecule and its
+/// data items. Invalid records yield an error and are skipped; reading stops at an I/O error.
+pub struct SdfReader<R> {
+    reader: R,
+    record: usize,
+    failed: bool,
+}
+
+impl<R: BufRead> SdfReader<R> {
+    pub fn new(reader: R) -> Self {
+        SdfReader {
+            reader,
+            record: 0,
+            failed: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for SdfReader<R> {
+    type Item = Result<(Mol, HashMap<String, String>), SdfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let mut molblock = String::new();
+        let mut in_molblock = true;
+        let mut properties = HashMap::new();
+        // data item being read: name and value lines
+        let mut item: Option<(String, Vec<String>)> = None;
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            match self.reader.read_line(&mut buffer) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(SdfError::Io(error)));
+                }
+            }
+            let line = buffer.trim_end_matches(['\n', '\r']);
+            if line == "$$$$" {
+                break;
+            }
+            if in_molblock {
+                molblock.push_str(line);
+                molblock.push('\n');
+                in_molblock = !line.starts_with("M  END");
+            } else if line.starts_with('>') {
+                finish_item(&mut item, &mut properties);
+                item = Some((item_name(line), vec![]));
+            } else if line.is_empty() {
+                finish_item(&mut item, &mut properties);
+            } else if let Some((_, values)) = &mut item {
+                values.push(line.to_string());
+            }
+        }
+        finish_item(&mut item, &mut properties);
+
+        // only blank lines after the last record
+        if in_molblock && molblock.trim().is_empty() {
+            return None;
+        }
+
+        let record = self.record;
+        self.record += 1;
+        Some(
+            Mol::from_molblock(&molblock)
+                .map(|mol| (mol, properties))
+                .map_err(|error| SdfError::Molfile { record, error }),
+        )
+    }
+}
+
+/// The name of a data header line, e.g. `> <melting.point>` or `>  25  <MELTING.POINT>`.
+fn item_name(line: &str) -> String {
+    line.split_once('<')
+        .and_then(|(_, rest)| rest.split_once('>'))
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_default()
+}
+
+fn finish_item(item: &mut Option<(String, Vec<String>)>, properties: &mut HashMap<String, String>) {
+    if let Some((name, values)) = item.take() {
+        properties.insert(name, values.join("\n"));
+    }
+}
+
+/// Writes records to an SD file.
+pub struct SdfWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> SdfWriter<W> {
+    pub fn new(writer: W) -> Self {
+        SdfWriter { writer }
+    }
+
+    /// Writes the molfile of the molecule, then the data items in the given order.
+    pub fn write<K, V>(
+        &mut self,
+        mol: &Mol,
+        properties: impl IntoIterator<Item = (K, V)>,
+    ) -> std::io::Result<()>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.writer.write_all(mol.to_molblock().as_bytes())?;
+        for (name, value) in properties {
+            writeln!(self.writer, "> <{}>", name.as_ref())?;
+            writeln!(self.writer, "{}", value.as_ref())?;
+            writeln!(self.writer)?;
+        }
+        writeln!(self.writer, "$$$$")
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io::{self, BufRead, Read};
+
+    use crate::error::{MolfileErrorKind, SdfError};
+    use crate::smiles::SmilesParser;
+
+    use super::{SdfReader, SdfWriter};
+
+    fn sdf(records: &[(&str, &[(&str, &str)])]) -> String {
+        let parser = SmilesParser::new();
+        let mut writer = SdfWriter::new(vec![]);
+        for (smiles, properties) in records {
+            let mol = parser.parse(smiles).unwrap();
+            writer.write(&mol, properties.iter().copied()).unwrap();
+        }
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn write_record() {
+        let written = sdf(&[("C", &[("name", "methane"), ("note", "a\nb")])]);
+
+        assert!(written.ends_with("M  END\n> <name>\nmethane\n\n> <note>\na\nb\n\n$$$$\n"));
+    }
+
+    #[test]
+    fn read_records() {
+        let written = sdf(&[
+            ("CCO", &[("name", "ethanol"), ("id", "1")]),
+            ("c1ccccc1", &[]),
+            ("[NH4+]", &[("note", "two\nlines")]),
+        ]);
+
+        let records: Vec<_> = SdfReader::new(written.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(3, records.len());
+        assert_eq!("CCO", records[0].0.to_smiles());
+        assert_eq!(
+            HashMap::from([
+                ("name".to_string(), "ethanol".to_string()),
+                ("id".to_string(), "1".to_string())
+            ]),
+            records[0].1
+        );
+        assert_eq!(6, records[1].0.num_atoms());
+        assert!(records[1].1.is_empty());
+        assert_eq!("[NH4+]", records[2].0.to_smiles());
+        assert_eq!("two\nlines", records[2].1["note"]);
+    }
+
+    #[test]
+    fn read_skips_invalid_records() {
+        let written = sdf(&[("C", &[]), ("N", &[])]);
+        let input = format!("\n\n\nabc\n$$$$\n{}\n\n", written);
+
+        let records: Vec<_> = SdfReader::new(input.as_bytes()).collect();
+
+        assert_eq!(3, records.len());
+        match &records[0] {
+            Err(SdfError::Molfile { record, error }) => {
+                assert_eq!(0, *record);
+                assert_eq!(MolfileErrorKind::InvalidCountsLine, error.kind);
+            }
+            other => panic!("expected a molfile error, got {:?}", other),
+        }
+        assert_eq!("C", records[1].as_ref().unwrap().0.to_smiles());
+        assert_eq!("N", records[2].as_ref().unwrap().0.to_smiles());
+    }
+
+    /// Fails after the first line.
+    struct FailingReader {
+        read: bool,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.read {
+                return Err(io::Error::other("broken"));
+            }
+            self.read = true;
+            buf[..5].copy_from_slice(b"name\n");
+            Ok(5)
+        }
+    }
+
+    #[test]
+    fn read_stops_at_io_error() {
+        let reader = io::BufReader::new(FailingReader { read: false });
+        let mut records = SdfReader::new(reader);
+
+        assert!(matches!(records.next(), Some(Err(SdfError::Io(_)))));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn read_is_lazy() {
+        let written = sdf(&[("C", &[]), ("N", &[])]);
+        let mut input = written.as_bytes();
+        {
+            let mut records = SdfReader::new(&mut input);
+            assert!(records.next().is_some());
+        }
+
+        // the second record hasn't been read yet
+        let mut rest = String::new();
+        input.read_line(&mut rest).unwrap();
+        assert_eq!("\n", rest);
+    }
+}
@@ -0,0 +1,273 @@
+//! Similarity and substructure search over a compound library.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::fingerprint::BitVec;
+use crate::similarity;
+use crate::smarts::Smarts;
+use crate::types::{AtomIdx, Mol};
+
+/// Fingerprints of a compound library, grouped by their number of set bits. The Tanimoto
+/// similarity of fingerprints with `a` and `b` bits set is at most `min(a, b) / max(a, b)`,
+/// so searches skip the groups that can't reach the threshold (or the k-th best).
+///
+/// Fingerprints are identified by the order they're added, from 0.
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintIndex {
+    // by number of set bits: (identifier, fingerprint)
+    buckets: Vec<Vec<(usize, BitVec)>>,
+    len: usize,
+}
+
+impl FingerprintIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds the fingerprint, returns its identifier.
+    pub fn add(&mut self, fingerprint: BitVec) -> usize {
+        let ones = fingerprint.count_ones();
+        if self.buckets.len() <= ones {
+            self.buckets.resize_with(ones + 1, Vec::new);
+        }
+        let id = self.len;
+        self.buckets[ones].push((id, fingerprint));
+        self.len += 1;
+        id
+    }
+
+    /// The `k` fingerprints most similar to the query, as (identifier, Tanimoto
+    /// similarity), most similar first. Ties are ordered by identifier.
+    pub fn top_k(&self, query: &BitVec, k: usize) -> Vec<(usize, f64)> {
+        let query_ones = query.count_ones();
+        let mut bounds: Vec<(usize, f64)> = (0..self.buckets.len())
+            .map(|ones| (ones, max_tanimoto(query_ones, ones)))
+            .collect();
+        bounds.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut hits: Vec<(usize, f64)> = vec![];
+        for (ones, bound) in bounds {
+            if k == 0 || (hits.len() == k && bound < hits[k - 1].1) {
+                break;
+            }
+            hits.extend(self.similarities(query, ones));
+            sort_hits(&mut hits);
+            hits.truncate(k);
+        }
+        hits
+    }
+
+    /// The fingerprints with a Tanimoto similarity of at least `threshold` to the query,
+    /// as (identifier, similarity), most similar first. Ties are ordered by identifier.
+    pub fn above_threshold(&self, query: &BitVec, threshold: f64) -> Vec<(usize, f64)> {
+        let query_ones = query.count_ones();
+        let mut hits: Vec<(usize, f64)> = (0..self.buckets.len())
+            .filter(|ones| max_tanimoto(query_ones, *ones) >= threshold)
+            .flat_map(|ones| self.similarities(query, ones))
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .collect();
+        sort_hits(&mut hits);
+        hits
+    }
+
+    fn similarities<'a>(
+        &'a self,
+        query: &'a BitVec,
+        ones: usize,
+    ) -> impl Iterator<Item = (usize, f64)> + 'a {
+        self.buckets[ones]
+            .iter()
+            .map(|(id, fingerprint)| (*id, similarity::tanimoto(query, fingerprint)))
+    }
+}
+
+/// Molecules of a compound library with their pattern fingerprints. Substructure
+/// searches only run the matcher on the molecules whose fingerprint has all the bits of
+/// the query's.
+///
+/// Molecules are identified by the order they're added, from 0.
+#[derive(Debug, Clone, Default)]
+pub struct SubstructIndex {
+    mols: Vec<(Mol, BitVec)>,
+}
+
+impl SubstructIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.mols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mols.is_empty()
+    }
+
+    /// Adds the molecule, returns its identifier.
+    pub fn add(&mut self, mol: Mol) -> usize {
+        let fingerprint = mol.pattern_fingerprint();
+        self.mols.push((mol, fingerprint));
+        self.mols.len() - 1
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Mol> {
+        self.mols.get(id).map(|(mol, _)| mol)
+    }
+
+    /// The molecules matching the query, as (identifier, matches), by identifier. The
+    /// matches are those of [`Mol::substruct_matches`].
+    pub fn substruct_matches(&self, query: &Smarts) -> Vec<(usize, Vec<Vec<AtomIdx>>)> {
+        let pattern = query.pattern_fingerprint();
+        self.mols
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, fingerprint))| pattern.is_subset(fingerprint))
+            .filter_map(|(id, (mol, _))| {
+                let matches = mol.substruct_matches(query);
+                (!matches.is_empty()).then_some((id, matches))
+            })
+            .collect()
+    }
+}
+
+/// Highest Tanimoto similarity between fingerprints with `a` and `b` bits set.
+fn max_tanimoto(a: usize, b: usize) -> f64 {
+    if a == 0 && b == 0 {
+        1.0
+    } else {
+        a.min(b) as f64 / a.max(b) as f64
+    }
+}
+
+fn sort_hits(hits: &mut [(usize, f64)]) {
+    hits.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fingerprint::BitVec;
+    use crate::smarts::Smarts;
+    use crate::smiles::SmilesParser;
+    use crate::types::AtomIdx;
+
+    use super::{FingerprintIndex, SubstructIndex};
+
+    fn fingerprint(smiles: &str) -> BitVec {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap().morgan_fingerprint(2, 1024)
+    }
+
+    fn index(library: &[&str]) -> FingerprintIndex {
+        let mut index = FingerprintIndex::new();
+        for smiles in library {
+            index.add(fingerprint(smiles));
+        }
+        index
+    }
+
+    /// Similarities compared one by one, most similar first.
+    fn scan(library: &[&str], query: &BitVec) -> Vec<(usize, f64)> {
+        let mut hits: Vec<(usize, f64)> = library
+            .iter()
+            .map(|smiles| query.tanimoto(&fingerprint(smiles)))
+            .enumerate()
+            .collect();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        hits
+    }
+
+    const LIBRARY: [&str; 8] = [
+        "CCO",
+        "CCCO",
+        "c1ccccc1",
+        "Cc1ccccc1",
+        "c1ccccc1O",
+        "CC(=O)Oc1ccccc1C(=O)O",
+        "CN1C=NC2=C1C(=O)N(C(=O)N2C)C",
+        "C",
+    ];
+
+    #[test]
+    fn top_k_matches_scan() {
+        let index = index(&LIBRARY);
+        assert_eq!(8, index.len());
+
+        for query in ["Cc1ccccc1O", "CCCCO", "C"] {
+            let query = fingerprint(query);
+            let expected = scan(&LIBRARY, &query);
+            assert_eq!(expected[..3], index.top_k(&query, 3)[..]);
+            assert_eq!(expected, index.top_k(&query, 20));
+        }
+        assert!(index.top_k(&fingerprint("CCO"), 0).is_empty());
+    }
+
+    #[test]
+    fn above_threshold_matches_scan() {
+        let index = index(&LIBRARY);
+        let query = fingerprint("Cc1ccccc1O");
+
+        let expected: Vec<(usize, f64)> = scan(&LIBRARY, &query)
+            .into_iter()
+            .filter(|(_, similarity)| *similarity >= 0.3)
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(expected, index.above_threshold(&query, 0.3));
+        assert_eq!(
+            vec![(0, 1.0)],
+            index.above_threshold(&fingerprint("CCO"), 1.0)
+        );
+    }
+
+    #[test]
+    fn substruct_matches() {
+        let parser = SmilesParser::new();
+        let mut index = SubstructIndex::new();
+        for smiles in LIBRARY {
+            index.add(parser.parse(smiles).unwrap());
+        }
+
+        for smarts in ["c1ccccc1", "[OX2H]", "C(=O)O", "N", "*~*", "[C,N]=O"] {
+            let query = Smarts::parse(smarts).unwrap();
+            let expected: Vec<(usize, Vec<Vec<AtomIdx>>)> = (0..index.len())
+                .map(|id| (id, index.get(id).unwrap().substruct_matches(&query)))
+                .filter(|(_, matches)| !matches.is_empty())
+                .collect();
+            assert_eq!(expected, index.substruct_matches(&query), "{}", smarts);
+        }
+
+        let hydroxyls = Smarts::parse("[OX2H]").unwrap();
+        assert_eq!(
+            vec![
+                (0, vec![vec![AtomIdx::new(2)]]),
+                (1, vec![vec![AtomIdx::new(3)]]),
+                (4, vec![vec![AtomIdx::new(6)]]),
+                (5, vec![vec![AtomIdx::new(12)]]),
+            ],
+            index.substruct_matches(&hydroxyls)
+        );
+    }
+
+    #[test]
+    fn pattern_fingerprint_screens() {
+        let parser = SmilesParser::new();
+        let phenol = parser.parse("c1ccccc1O").unwrap().pattern_fingerprint();
+        let pattern = |smarts: &str| Smarts::parse(smarts).unwrap().pattern_fingerprint();
+
+        assert!(pattern("cO").is_subset(&phenol));
+        assert!(pattern("[c;R]~[#8]").is_subset(&phenol));
+        assert!(!pattern("N").is_subset(&phenol));
+        assert!(!pattern("COC").is_subset(&phenol));
+        // atoms without a required element don't contribute
+        assert!(pattern("*").is_subset(&BitVec::new(0)));
+    }
+}
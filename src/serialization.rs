@@ -0,0 +1,220 @@
+//! Serde support for [`Mol`], as a list of atoms and a list of bonds:
+//!
+//! ```json
+//! {"atoms": [{"number": 6, ...}, ...], "bonds": [{"atom_start": 0, "atom_end": 1, ...}, ...]}
+//! ```
+//!
+//! Atom fields left out take their default, e.g. `{"number": 8}` is an oxygen atom.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use petgraph::graph::{NodeIndex, UnGraph};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Atom, AtomIdx, Bond, Mol};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MolData {
+    atoms: Vec<Atom>,
+    bonds: Vec<BondData>,
+}
+
+/// A bond with its atoms, which the graph stores apart from the [`Bond`].
+#[derive(Clone, Serialize, Deserialize)]
+struct BondData {
+    atom_start: AtomIdx,
+    atom_end: AtomIdx,
+    #[serde(flatten)]
+    bond: Bond,
+}
+
+impl From<Mol> for MolData {
+    fn from(mol: Mol) -> Self {
+        let (atoms, bonds) = mol.graph.into_nodes_edges();
+        MolData {
+            atoms: atoms.into_iter().map(|node| node.weight).collect(),
+            bonds: bonds
+                .into_iter()
+                .map(|edge| BondData {
+                    atom_start: edge.source().into(),
+                    atom_end: edge.target().into(),
+                    bond: edge.weight,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<MolData> for Mol {
+    type Error = InvalidMolData;
+
+    fn try_from(data: MolData) -> Result<Self, Self::Error> {
+        let count = data.atoms.len();
+        let check = |index: AtomIdx, what: &str| {
+            if index.index() < count {
+                Ok(())
+            } else {
+                Err(InvalidMolData(format!(
+                    "{} {} out of range, {} atoms",
+                    what, index, count
+                )))
+            }
+        };
+
+        let mut graph = UnGraph::<Atom, Bond>::with_capacity(count, data.bonds.len());
+        for atom in data.atoms {
+            for neighbor in &atom.chiral_neighbors {
+                check(*neighbor, "chiral neighbor")?;
+            }
+            graph.add_node(atom);
+        }
+        for BondData {
+            atom_start,
+            atom_end,
+            bond,
+        } in data.bonds
+        {
+            check(atom_start, "bond atom")?;
+            check(atom_end, "bond atom")?;
+            if atom_start == atom_end {
+                return Err(InvalidMolData(format!(
+                    "bond from atom {} to itself",
+                    atom_start
+                )));
+            }
+            if let Some(stereo) = &bond.stereo {
+                check(stereo.start_neighbor, "stereo neighbor")?;
+                check(stereo.end_neighbor, "stereo neighbor")?;
+            }
+            let (a, b) = (NodeIndex::from(atom_start), NodeIndex::from(atom_end));
+            if graph.contains_edge(a, b) {
+                return Err(InvalidMolData(format!(
+                    "duplicate bond between atoms {} and {}",
+                    atom_start, atom_end
+                )));
+            }
+            graph.add_edge(a, b, bond);
+        }
+        Ok(Mol { graph })
+    }
+}
+
+/// Bonds or stereo neighbors that refer to missing atoms.
+#[derive(Debug)]
+pub struct InvalidMolData(String);
+
+impl fmt::Display for InvalidMolData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid molecule: {}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, BondIdx, BondOrder, Mol};
+
+    fn round_trip(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        let mol = parser.parse(smiles).unwrap();
+        let json = serde_json::to_string(&mol).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn schema() {
+        let parser = SmilesParser::new();
+        let json = serde_json::to_value(parser.parse("C=O").unwrap()).unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "atoms": [
+                    {
+                        "number": 6, "aromatic": false, "isotope": null, "charge": 0,
+                        "explicit_h": null, "map_number": null, "chirality": null,
+                        "chiral_neighbors": [], "implicit_h": 2, "num_radical_electrons": 0
+                    },
+                    {
+                        "number": 8, "aromatic": false, "isotope": null, "charge": 0,
+                        "explicit_h": null, "map_number": null, "chirality": null,
+                        "chiral_neighbors": [], "implicit_h": 0, "num_radical_electrons": 0
+                    }
+                ],
+                "bonds": [
+                    {
+                        "atom_start": 0, "atom_end": 1, "order": "double",
+                        "direction": null, "stereo": null
+                    }
+                ]
+            }),
+            json
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        for smiles in [
+            "CCO",
+            "c1ccccc1",
+            "N[C@@H](C)C(=O)O",
+            "F/C=C/F",
+            "[13CH3:1][NH3+].[Cl-]",
+        ] {
+            assert_eq!(smiles, round_trip(smiles).to_smiles());
+        }
+    }
+
+    #[test]
+    fn props() {
+        let parser = SmilesParser::new();
+        let mut mol = parser.parse("CO").unwrap();
+        mol.set_atom_prop(AtomIdx::new(1), "charge", -0.5);
+        mol.set_atom_prop(AtomIdx::new(1), "tag", "hydroxyl");
+        mol.set_bond_prop(BondIdx::new(0), "count", 2);
+
+        let json = serde_json::to_value(&mol).unwrap();
+        assert_eq!(
+            serde_json::json!({"charge": -0.5, "tag": "hydroxyl"}),
+            json["atoms"][1]["props"]
+        );
+        assert_eq!(serde_json::json!({"count": 2}), json["bonds"][0]["props"]);
+        let read: Mol = serde_json::from_value(json).unwrap();
+        assert_eq!(Some(-0.5), read.atom_prop::<f64>(AtomIdx::new(1), "charge"));
+        assert_eq!(Some(2), read.bond_prop::<i64>(BondIdx::new(0), "count"));
+    }
+
+    #[test]
+    fn defaults() {
+        let mol: Mol = serde_json::from_str(
+            r#"{"atoms": [{"number": 6}, {"number": 8}], "bonds": [{"atom_start": 0, "atom_end": 1}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(2, mol.num_atoms());
+        assert_eq!(
+            BondOrder::Single,
+            mol.bond_with_idx(BondIdx::new(0)).unwrap().order
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let error = serde_json::from_str::<Mol>(
+            r#"{"atoms": [{"number": 6}], "bonds": [{"atom_start": 0, "atom_end": 1}]}"#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("invalid molecule: bond atom 1 out of range, 1 atoms"));
+
+        let error = serde_json::from_str::<Mol>(
+            r#"{"atoms": [{"number": 6}, {"number": 6}], "bonds": [
+                {"atom_start": 0, "atom_end": 1}, {"atom_start": 1, "atom_end": 0}]}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("duplicate bond"));
+    }
+}
@@ -0,0 +1,66 @@
+//! Similarity metrics between fingerprints. All return 1.0 for two empty fingerprints.
+
+use crate::fingerprint::BitVec;
+
+/// Common bits over bits set in either: `c / (a + b - c)`.
+pub fn tanimoto(a: &BitVec, b: &BitVec) -> f64 {
+    metric(a, b, |common, a, b| common / (a + b - common))
+}
+
+/// Twice the common bits over the bits set in each: `2c / (a + b)`.
+pub fn dice(a: &BitVec, b: &BitVec) -> f64 {
+    metric(a, b, |common, a, b| 2.0 * common / (a + b))
+}
+
+/// Cosine of the angle between the bit vectors: `c / sqrt(a * b)`.
+pub fn cosine(a: &BitVec, b: &BitVec) -> f64 {
+    metric(a, b, |common, a, b| common / libm::sqrt(a * b))
+}
+
+/// Applies `formula` to the common bits and the bits set in each, handling empty vectors.
+fn metric(a: &BitVec, b: &BitVec, formula: fn(f64, f64, f64) -> f64) -> f64 {
+    let (common, a, b) = (a.count_common(b), a.count_ones(), b.count_ones());
+    if a == 0 && b == 0 {
+        1.0
+    } else if common == 0 {
+        0.0
+    } else {
+        formula(common as f64, a as f64, b as f64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fingerprint::BitVec;
+
+    use super::{cosine, dice, tanimoto};
+
+    fn bits(ones: &[usize]) -> BitVec {
+        let mut bits = BitVec::new(16);
+        for one in ones {
+            bits.set(*one);
+        }
+        bits
+    }
+
+    #[test]
+    fn metrics() {
+        let (a, b) = (bits(&[1, 2, 3, 4]), bits(&[3, 4, 5]));
+
+        assert_eq!(2.0 / 5.0, tanimoto(&a, &b));
+        assert_eq!(4.0 / 7.0, dice(&a, &b));
+        assert_eq!(2.0 / 12f64.sqrt(), cosine(&a, &b));
+    }
+
+    #[test]
+    fn identical_and_disjoint() {
+        let (a, b) = (bits(&[1, 2]), bits(&[3]));
+
+        for metric in [tanimoto, dice, cosine] {
+            assert_eq!(1.0, metric(&a, &a));
+            assert_eq!(0.0, metric(&a, &b));
+            assert_eq!(0.0, metric(&a, &bits(&[])));
+            assert_eq!(1.0, metric(&bits(&[]), &bits(&[])));
+        }
+    }
+}
@@ -0,0 +1,814 @@
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+use petgraph::graph::{NodeIndex, UnGraph};
+
+use crate::error::{SmilesError, SmilesErrorKind};
+use crate::fingerprint::{self, BitVec};
+use crate::lexer::{charge, in_range, number, ring_number, unexpected};
+use crate::scanner::Scanner;
+use crate::smiles::{atom_number, is_symbol};
+
+/// A substructure query, parsed from SMARTS.
+///
+/// Supported: atoms (`C`, `c`, `*`, `a`, `A`, bracket atoms), atom primitives
+/// (`#n`, isotope, `D`, `H`, `h`, `R`, `r`, `v`, `X`, charge, `$(...)`),
+/// bond primitives (`-`, `=`, `#`, `$`, `:`, `~`, `@`, `/`, `\`), the logical
+/// operators `!`, `&`, `,` and `;`, branches, ring bonds and `.`.
+/// Chirality and atom map numbers are accepted but not matched. `$(...)` and `!` nest
+/// up to 128 levels, deeper nesting is rejected with
+/// [`SmilesErrorKind::NestingTooDeep`].
+#[derive(Debug, Clone)]
+pub struct Smarts {
+    pub(crate) graph: UnGraph<AtomQuery, BondQuery>,
+}
+
+impl PartialEq for Smarts {
+    /// Same atoms and bonds, in the same order.
+    fn eq(&self, other: &Self) -> bool {
+        self.graph.node_weights().eq(other.graph.node_weights())
+            && self
+                .graph
+                .raw_edges()
+                .iter()
+                .map(|e| (e.source(), e.target(), &e.weight))
+                .eq(other
+                    .graph
+                    .raw_edges()
+                    .iter()
+                    .map(|e| (e.source(), e.target(), &e.weight)))
+    }
+}
+
+/// A logical expression over primitives. `And` and `Or` are never empty.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr<P> {
+    Primitive(P),
+    Not(Box<Expr<P>>),
+    And(Vec<Expr<P>>),
+    Or(Vec<Expr<P>>),
+}
+
+impl<P> Expr<P> {
+    pub fn matches(&self, primitive: &impl Fn(&P) -> bool) -> bool {
+        match self {
+            Expr::Primitive(p) => primitive(p),
+            Expr::Not(expr) => !expr.matches(primitive),
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.matches(primitive)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.matches(primitive)),
+        }
+    }
+}
+
+pub(crate) type AtomQuery = Expr<AtomPrimitive>;
+pub(crate) type BondQuery = Expr<BondPrimitive>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AtomPrimitive {
+    /// `*`, and the ignored chirality and atom map number.
+    Any,
+    /// `aromatic` is `None` for `#n`.
+    Element {
+        number: u32,
+        aromatic: Option<bool>,
+    },
+    /// `a` or `A`.
+    Aromatic(bool),
+    Isotope(u16),
+    Charge(i8),
+    /// `D<n>`: explicit connections.
+    Degree(u32),
+    /// `H<n>`: all attached hydrogens.
+    TotalH(u32),
+    /// `h<n>`: implicit hydrogens, at least one if `None`.
+    ImplicitH(Option<u32>),
+    /// `R<n>`: number of SSSR rings, in any ring if `None`.
+    RingMembership(Option<u32>),
+    /// `r<n>`: in an SSSR ring of the size, in any ring if `None`.
+    RingSize(Option<u32>),
+    /// `v<n>`: total bond order, hydrogens included.
+    Valence(u32),
+    /// `X<n>`: connections, hydrogens included.
+    Connectivity(u32),
+    /// `$(...)`: the atom is the first atom of a match of the SMARTS.
+    Recursive(Box<Smarts>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BondPrimitive {
+    Single,
+    Double,
+    Triple,
+    Quadruple,
+    Aromatic,
+    /// `~`
+    Any,
+    /// `@`
+    Ring,
+    /// A bond written without symbol.
+    SingleOrAromatic,
+}
+
+/// A ring bond that has been opened but not closed yet.
+struct OpenRing {
+    atom: NodeIndex,
+    bond: Option<BondQuery>, // bond written at the opening
+    position: usize,         // position of the opening in the input
+}
+
+/// Deepest nesting of `$(...)` and `!` accepted, beyond which parsing, matching and
+/// dropping the query could overflow the stack.
+const MAX_NESTING: usize = 128;
+
+impl Smarts {
+    pub fn parse(smarts: &str) -> Result<Smarts, SmilesError> {
+        let mut scanner = Scanner::new(smarts);
+        let smarts = parse_nested(&mut scanner, 0)?;
+        match scanner.peek() {
+            None => Ok(smarts),
+            Some(c) => Err(SmilesError::new(
+                SmilesErrorKind::UnexpectedCharacter(c),
+                scanner.cursor(),
+            )),
+        }
+    }
+
+    pub fn num_atoms(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn num_bonds(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    /// Bits that every molecule matching the query has in its
+    /// [`Mol::pattern_fingerprint`](crate::Mol::pattern_fingerprint). Only atoms with a
+    /// required element contribute, e.g. not `*` or `[C,N]`.
+    pub fn pattern_fingerprint(&self) -> BitVec {
+        fingerprint::element_paths(&self.graph, required_element)
+    }
+}
+
+/// Parses a SMARTS, `depth` levels of `$(...)` and `!` deep. Inside `$(...)` (`depth` above
+/// 0), stops before the `)` that closes it, or before a `]`.
+fn parse_nested(scanner: &mut Scanner, depth: usize) -> Result<Smarts, SmilesError> {
+    {
+        let mut graph = UnGraph::<AtomQuery, BondQuery>::new_undirected();
+
+        let mut last_node_index: Option<NodeIndex> = None;
+
+        let mut rings: HashMap<u8, OpenRing> = HashMap::new();
+
+        // (node before the branch, position of the '(')
+        let mut branches_stack: Vec<(NodeIndex, usize)> = vec![];
+
+        // bond read but not yet added, with its position
+        let mut pending_bond: Option<(BondQuery, usize)> = None;
+
+        while let Some(c) = scanner.peek() {
+            let position = scanner.cursor();
+            if depth > 0 && (c == ']' || c == ')' && branches_stack.is_empty()) {
+                break;
+            }
+            match c {
+                'B' | 'C' | 'N' | 'O' | 'P' | 'S' | 'F' | 'I' | 'b' | 'c' | 'n' | 'o' | 'p'
+                | 's' | '*' | 'a' | 'A' => {
+                    let atom = Expr::Primitive(organic_atom(scanner)?);
+                    let bond = pending_bond.take().map(|(bond, _)| bond);
+                    last_node_index = Some(add_to_graph(&mut graph, atom, last_node_index, bond));
+                }
+                '[' => {
+                    scanner.pop();
+                    let atom = bracket_atom(scanner, depth)?;
+                    let bond = pending_bond.take().map(|(bond, _)| bond);
+                    last_node_index = Some(add_to_graph(&mut graph, atom, last_node_index, bond));
+                }
+                '0'..='9' | '%' => {
+                    scanner.pop();
+                    let ring = if c == '%' {
+                        ring_number(scanner)?
+                    } else {
+                        // unwrap: matched a digit
+                        c.to_digit(10).unwrap() as u8
+                    };
+                    let Some(ring_end) = last_node_index else {
+                        return Err(SmilesError::new(SmilesErrorKind::MissingAtom, position));
+                    };
+                    let bond = pending_bond.take().map(|(bond, _)| bond);
+                    match rings.remove(&ring) {
+                        None => {
+                            rings.insert(
+                                ring,
+                                OpenRing {
+                                    atom: ring_end,
+                                    bond,
+                                    position,
+                                },
+                            );
+                        }
+                        Some(open_ring) => {
+                            let bond = bond
+                                .or(open_ring.bond)
+                                .unwrap_or(Expr::Primitive(BondPrimitive::SingleOrAromatic));
+                            graph.add_edge(open_ring.atom, ring_end, bond);
+                        }
+                    }
+                }
+                '-' | '=' | '#' | '$' | ':' | '~' | '@' | '/' | '\\' | '!' => {
+                    if last_node_index.is_none() || pending_bond.is_some() {
+                        return Err(SmilesError::expected(
+                            SmilesErrorKind::UnexpectedCharacter(c),
+                            position,
+                            "atom",
+                        ));
+                    }
+                    let bond = expression(
+                        scanner,
+                        &mut |scanner, _| bond_primitive(scanner),
+                        starts_bond,
+                        depth,
+                    )?;
+                    pending_bond = Some((bond, position));
+                }
+                '(' => {
+                    scanner.pop();
+                    let Some(last) = last_node_index else {
+                        return Err(SmilesError::new(SmilesErrorKind::MissingAtom, position));
+                    };
+                    if let Some((_, bond_position)) = pending_bond {
+                        return Err(SmilesError::new(
+                            SmilesErrorKind::DanglingBond,
+                            bond_position,
+                        ));
+                    }
+                    branches_stack.push((last, position));
+                }
+                ')' => {
+                    scanner.pop();
+                    if let Some((_, bond_position)) = pending_bond {
+                        return Err(SmilesError::new(
+                            SmilesErrorKind::DanglingBond,
+                            bond_position,
+                        ));
+                    }
+                    let Some((last_index_before_branch, _)) = branches_stack.pop() else {
+                        return Err(SmilesError::new(
+                            SmilesErrorKind::UnbalancedParenthesis,
+                            position,
+                        ));
+                    };
+                    last_node_index = Some(last_index_before_branch);
+                }
+                '.' => {
+                    scanner.pop();
+                    if last_node_index.is_none() {
+                        return Err(SmilesError::new(SmilesErrorKind::MissingAtom, position));
+                    }
+                    if let Some((_, bond_position)) = pending_bond {
+                        return Err(SmilesError::new(
+                            SmilesErrorKind::DanglingBond,
+                            bond_position,
+                        ));
+                    }
+                    last_node_index = None;
+                }
+                _ => {
+                    return Err(SmilesError::new(
+                        SmilesErrorKind::UnexpectedCharacter(c),
+                        position,
+                    ));
+                }
+            }
+        }
+
+        if let Some((_, bond_position)) = pending_bond {
+            return Err(SmilesError::new(
+                SmilesErrorKind::DanglingBond,
+                bond_position,
+            ));
+        }
+        if let Some((_, branch_position)) = branches_stack.pop() {
+            return Err(SmilesError::new(
+                SmilesErrorKind::UnbalancedParenthesis,
+                branch_position,
+            ));
+        }
+        if let Some((ring, open_ring)) = rings.into_iter().min_by_key(|(_, r)| r.position) {
+            return Err(SmilesError::new(
+                SmilesErrorKind::UnclosedRing(ring.into()),
+                open_ring.position,
+            ));
+        }
+
+        Ok(Smarts { graph })
+    }
+}
+
+/// The element an atom must have to match the query, if any.
+fn required_element(query: &AtomQuery) -> Option<u32> {
+    match query {
+        Expr::Primitive(AtomPrimitive::Element { number, .. }) => Some(*number),
+        Expr::And(exprs) => exprs.iter().find_map(required_element),
+        _ => None,
+    }
+}
+
+fn add_to_graph(
+    graph: &mut UnGraph<AtomQuery, BondQuery>,
+    atom: AtomQuery,
+    last_node_index: Option<NodeIndex>,
+    bond: Option<BondQuery>,
+) -> NodeIndex {
+    let node_index = graph.add_node(atom);
+    if let Some(last) = last_node_index {
+        let bond = bond.unwrap_or(Expr::Primitive(BondPrimitive::SingleOrAromatic));
+        graph.add_edge(last, node_index, bond);
+    }
+    node_index
+}
+
+/// Parses an atom written outside brackets: organic subset, `*`, `a` or `A`.
+fn organic_atom(scanner: &mut Scanner) -> Result<AtomPrimitive, SmilesError> {
+    let position = scanner.cursor();
+    // unwrap: called on an atom character
+    let first = scanner.pop().unwrap();
+    let symbol = match first {
+        '*' => return Ok(AtomPrimitive::Any),
+        'a' => return Ok(AtomPrimitive::Aromatic(true)),
+        'A' => return Ok(AtomPrimitive::Aromatic(false)),
+        'C' if scanner.take(&'l') => "Cl".to_string(),
+        'B' if scanner.take(&'r') => "Br".to_string(),
+        _ => first.to_string(),
+    };
+    let number = atom_number(&symbol).ok_or_else(|| {
+        SmilesError::new(SmilesErrorKind::UnknownElement(symbol.clone()), position)
+    })?;
+    Ok(AtomPrimitive::Element {
+        number,
+        aromatic: Some(first.is_ascii_lowercase()),
+    })
+}
+
+/// Parses a bracket atom expression. Expects the opening `[` to have been consumed already.
+fn bracket_atom(scanner: &mut Scanner, depth: usize) -> Result<AtomQuery, SmilesError> {
+    // `H` is hydrogen when no element precedes it, otherwise a hydrogen count
+    let mut has_element = false;
+    let mut primitive = |scanner: &mut Scanner, depth| {
+        let primitive = atom_primitive(scanner, has_element, depth)?;
+        has_element |= matches!(
+            primitive,
+            AtomPrimitive::Element { .. } | AtomPrimitive::Aromatic(_) | AtomPrimitive::Any
+        );
+        Ok(primitive)
+    };
+    let atom = expression(
+        scanner,
+        &mut primitive,
+        |c| !matches!(c, ']' | ';' | ','),
+        depth,
+    )?;
+    if !scanner.take(&']') {
+        return Err(unexpected(scanner, "']'"));
+    }
+    Ok(atom)
+}
+
+fn atom_primitive(
+    scanner: &mut Scanner,
+    has_element: bool,
+    depth: usize,
+) -> Result<AtomPrimitive, SmilesError> {
+    let position = scanner.cursor();
+    let Some(c) = scanner.peek() else {
+        return Err(unexpected(scanner, "atom primitive"));
+    };
+
+    // two letter elements take precedence over primitives, e.g. `[Cl]`, `[Ra]`
+    let two_letters = scanner.slice(position, position + 2);
+    if c.is_ascii_alphabetic() && two_letters.len() == 2 && is_symbol(&two_letters) {
+        scanner.pop();
+        scanner.pop();
+        // unwrap: the symbol is a known element
+        let number = atom_number(&two_letters).unwrap();
+        return Ok(AtomPrimitive::Element {
+            number,
+            aromatic: Some(c.is_ascii_lowercase()),
+        });
+    }
+
+    if c.is_ascii_digit() {
+        // unwrap: at a digit
+        let isotope = number(scanner).unwrap();
+        return Ok(AtomPrimitive::Isotope(in_range(isotope, scanner)?));
+    }
+    if c == '+' || c == '-' {
+        return Ok(AtomPrimitive::Charge(charge(scanner)?));
+    }
+
+    scanner.pop();
+    let primitive = match c {
+        '*' => AtomPrimitive::Any,
+        'a' => AtomPrimitive::Aromatic(true),
+        'A' => AtomPrimitive::Aromatic(false),
+        '#' => match primitive_number(scanner)? {
+            Some(number) => AtomPrimitive::Element {
+                number,
+                aromatic: None,
+            },
+            None => return Err(unexpected(scanner, "atomic number")),
+        },
+        'H' if !has_element => AtomPrimitive::Element {
+            number: 1,
+            aromatic: Some(false),
+        },
+        'D' => AtomPrimitive::Degree(primitive_number(scanner)?.unwrap_or(1)),
+        'H' => AtomPrimitive::TotalH(primitive_number(scanner)?.unwrap_or(1)),
+        'h' => AtomPrimitive::ImplicitH(primitive_number(scanner)?),
+        'R' => AtomPrimitive::RingMembership(primitive_number(scanner)?),
+        'r' => AtomPrimitive::RingSize(primitive_number(scanner)?),
+        'v' => AtomPrimitive::Valence(primitive_number(scanner)?.unwrap_or(1)),
+        'X' => AtomPrimitive::Connectivity(primitive_number(scanner)?.unwrap_or(1)),
+        '@' => {
+            scanner.take(&'@');
+            AtomPrimitive::Any
+        }
+        ':' => match number(scanner) {
+            Some(_) => AtomPrimitive::Any,
+            None => return Err(unexpected(scanner, "atom map number")),
+        },
+        '$' => AtomPrimitive::Recursive(Box::new(recursive(scanner, depth + 1)?)),
+        c if c.is_ascii_alphabetic() => {
+            let symbol = c.to_string();
+            if !is_symbol(&symbol) {
+                return Err(SmilesError::new(
+                    SmilesErrorKind::UnknownElement(symbol),
+                    position,
+                ));
+            }
+            AtomPrimitive::Element {
+                // unwrap: the symbol is a known element
+                number: atom_number(&symbol).unwrap(),
+                aromatic: Some(c.is_ascii_lowercase()),
+            }
+        }
+        c => {
+            return Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedCharacter(c),
+                position,
+                "atom primitive",
+            ))
+        }
+    };
+    Ok(primitive)
+}
+
+/// Parses an optional number of an atom primitive, out of range above `u32::MAX`.
+fn primitive_number(scanner: &mut Scanner) -> Result<Option<u32>, SmilesError> {
+    number(scanner)
+        .map(|number| in_range(number, scanner))
+        .transpose()
+}
+
+/// Parses the SMARTS of `$(...)`, `depth` levels deep. Expects the `$` to have been
+/// consumed already.
+fn recursive(scanner: &mut Scanner, depth: usize) -> Result<Smarts, SmilesError> {
+    let position = scanner.cursor();
+    if depth > MAX_NESTING {
+        return Err(SmilesError::new(
+            SmilesErrorKind::NestingTooDeep,
+            position - 1,
+        ));
+    }
+    if !scanner.take(&'(') {
+        return Err(unexpected(scanner, "'('"));
+    }
+    let smarts = parse_nested(scanner, depth)?;
+    if !scanner.take(&')') {
+        return Err(SmilesError::new(
+            SmilesErrorKind::UnbalancedParenthesis,
+            position,
+        ));
+    }
+    Ok(smarts)
+}
+
+fn bond_primitive(scanner: &mut Scanner) -> Result<BondPrimitive, SmilesError> {
+    let primitive = match scanner.peek() {
+        Some('-' | '/' | '\\') => BondPrimitive::Single,
+        Some('=') => BondPrimitive::Double,
+        Some('#') => BondPrimitive::Triple,
+        Some('$') => BondPrimitive::Quadruple,
+        Some(':') => BondPrimitive::Aromatic,
+        Some('~') => BondPrimitive::Any,
+        Some('@') => BondPrimitive::Ring,
+        _ => return Err(unexpected(scanner, "bond primitive")),
+    };
+    scanner.pop();
+    Ok(primitive)
+}
+
+fn starts_bond(c: char) -> bool {
+    matches!(
+        c,
+        '-' | '=' | '#' | '$' | ':' | '~' | '@' | '/' | '\\' | '!'
+    )
+}
+
+/// Parses a logical expression, by increasing precedence: `;`, `,`, `&` (or juxtaposition), `!`.
+/// Juxtaposed primitives continue as long as `continues` accepts the next character.
+fn expression<P>(
+    scanner: &mut Scanner,
+    primitive: &mut dyn FnMut(&mut Scanner, usize) -> Result<P, SmilesError>,
+    continues: fn(char) -> bool,
+    depth: usize,
+) -> Result<Expr<P>, SmilesError> {
+    let mut exprs = vec![or_expression(scanner, primitive, continues, depth)?];
+    while scanner.take(&';') {
+        exprs.push(or_expression(scanner, primitive, continues, depth)?);
+    }
+    Ok(collect(exprs, Expr::And))
+}
+
+fn or_expression<P>(
+    scanner: &mut Scanner,
+    primitive: &mut dyn FnMut(&mut Scanner, usize) -> Result<P, SmilesError>,
+    continues: fn(char) -> bool,
+    depth: usize,
+) -> Result<Expr<P>, SmilesError> {
+    let mut exprs = vec![and_expression(scanner, primitive, continues, depth)?];
+    while scanner.take(&',') {
+        exprs.push(and_expression(scanner, primitive, continues, depth)?);
+    }
+    Ok(collect(exprs, Expr::Or))
+}
+
+fn and_expression<P>(
+    scanner: &mut Scanner,
+    primitive: &mut dyn FnMut(&mut Scanner, usize) -> Result<P, SmilesError>,
+    continues: fn(char) -> bool,
+    depth: usize,
+) -> Result<Expr<P>, SmilesError> {
+    let mut exprs = vec![not_expression(scanner, primitive, depth)?];
+    loop {
+        // `&` or juxtaposition
+        if scanner.take(&'&') || scanner.peek().is_some_and(continues) {
+            exprs.push(not_expression(scanner, primitive, depth)?);
+        } else {
+            break;
+        }
+    }
+    Ok(collect(exprs, Expr::And))
+}
+
+/// `!` expressions, counting towards the nesting of `depth`.
+fn not_expression<P>(
+    scanner: &mut Scanner,
+    primitive: &mut dyn FnMut(&mut Scanner, usize) -> Result<P, SmilesError>,
+    depth: usize,
+) -> Result<Expr<P>, SmilesError> {
+    let mut nots = 0;
+    while scanner.peek() == Some('!') {
+        if depth + nots >= MAX_NESTING {
+            return Err(SmilesError::new(
+                SmilesErrorKind::NestingTooDeep,
+                scanner.cursor(),
+            ));
+        }
+        scanner.pop();
+        nots += 1;
+    }
+    let mut expr = Expr::Primitive(primitive(scanner, depth + nots)?);
+    for _ in 0..nots {
+        expr = Expr::Not(Box::new(expr));
+    }
+    Ok(expr)
+}
+
+fn collect<P>(mut exprs: Vec<Expr<P>>, combine: fn(Vec<Expr<P>>) -> Expr<P>) -> Expr<P> {
+    if exprs.len() == 1 {
+        // unwrap: one element
+        exprs.pop().unwrap()
+    } else {
+        combine(exprs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{SmilesError, SmilesErrorKind};
+
+    use super::{AtomPrimitive, BondPrimitive, Expr, Smarts};
+
+    fn atom(smarts: &str) -> Expr<AtomPrimitive> {
+        Smarts::parse(smarts)
+            .unwrap()
+            .graph
+            .node_weights()
+            .next()
+            .unwrap()
+            .clone()
+    }
+
+    fn carbon(aromatic: Option<bool>) -> Expr<AtomPrimitive> {
+        Expr::Primitive(AtomPrimitive::Element {
+            number: 6,
+            aromatic,
+        })
+    }
+
+    #[test]
+    fn parse_organic_atoms() {
+        let smarts = Smarts::parse("CcCl*aA").unwrap();
+
+        assert_eq!(6, smarts.num_atoms());
+        assert_eq!(5, smarts.num_bonds());
+        assert_eq!(carbon(Some(false)), atom("C"));
+        assert_eq!(carbon(Some(true)), atom("c"));
+        assert_eq!(Expr::Primitive(AtomPrimitive::Any), atom("*"));
+        assert_eq!(Expr::Primitive(AtomPrimitive::Aromatic(false)), atom("A"));
+    }
+
+    #[test]
+    fn parse_bracket_primitives() {
+        assert_eq!(carbon(None), atom("[#6]"));
+        assert_eq!(
+            Expr::And(vec![
+                carbon(Some(false)),
+                Expr::Primitive(AtomPrimitive::TotalH(3))
+            ]),
+            atom("[CH3]")
+        );
+        assert_eq!(
+            Expr::Primitive(AtomPrimitive::Element {
+                number: 1,
+                aromatic: Some(false)
+            }),
+            atom("[H]")
+        );
+        assert_eq!(
+            Expr::Primitive(AtomPrimitive::Element {
+                number: 17,
+                aromatic: Some(false)
+            }),
+            atom("[Cl]")
+        );
+        assert_eq!(
+            Expr::And(vec![
+                Expr::Primitive(AtomPrimitive::Isotope(13)),
+                carbon(Some(false)),
+            ]),
+            atom("[13C]")
+        );
+        assert_eq!(
+            Expr::And(vec![
+                Expr::Primitive(AtomPrimitive::RingMembership(None)),
+                Expr::Primitive(AtomPrimitive::RingSize(Some(6))),
+                Expr::Primitive(AtomPrimitive::Degree(2)),
+                Expr::Primitive(AtomPrimitive::Charge(-1)),
+            ]),
+            atom("[Rr6D2-]")
+        );
+    }
+
+    #[test]
+    fn parse_operator_precedence() {
+        // `;` binds weakest, then `,`, then `&`, then `!`
+        assert_eq!(
+            Expr::And(vec![
+                Expr::Or(vec![
+                    Expr::And(vec![
+                        carbon(None),
+                        Expr::Not(Box::new(Expr::Primitive(AtomPrimitive::Charge(1)))),
+                    ]),
+                    Expr::Primitive(AtomPrimitive::Element {
+                        number: 7,
+                        aromatic: None
+                    }),
+                ]),
+                Expr::Primitive(AtomPrimitive::Aromatic(false)),
+            ]),
+            atom("[#6&!+,#7;A]")
+        );
+    }
+
+    #[test]
+    fn parse_bonds() {
+        let smarts = Smarts::parse("C-C=C#C:C~C@C!-C-,=C").unwrap();
+        let bonds: Vec<_> = smarts.graph.edge_weights().cloned().collect();
+
+        assert_eq!(
+            vec![
+                Expr::Primitive(BondPrimitive::Single),
+                Expr::Primitive(BondPrimitive::Double),
+                Expr::Primitive(BondPrimitive::Triple),
+                Expr::Primitive(BondPrimitive::Aromatic),
+                Expr::Primitive(BondPrimitive::Any),
+                Expr::Primitive(BondPrimitive::Ring),
+                Expr::Not(Box::new(Expr::Primitive(BondPrimitive::Single))),
+                Expr::Or(vec![
+                    Expr::Primitive(BondPrimitive::Single),
+                    Expr::Primitive(BondPrimitive::Double),
+                ]),
+            ],
+            bonds
+        );
+    }
+
+    #[test]
+    fn parse_rings_branches_and_components() {
+        let smarts = Smarts::parse("c1ccccc1C(=O)[OH].[Na+]").unwrap();
+
+        assert_eq!(10, smarts.num_atoms());
+        assert_eq!(9, smarts.num_bonds());
+    }
+
+    #[test]
+    fn parse_recursive() {
+        let Expr::Primitive(AtomPrimitive::Recursive(inner)) = atom("[$(C=O)]") else {
+            panic!("expected a recursive primitive");
+        };
+        assert_eq!(2, inner.num_atoms());
+    }
+
+    #[test]
+    fn parse_nested_recursive() {
+        let nested = |depth: usize| "[$(".repeat(depth) + "C" + &")]".repeat(depth);
+
+        let smarts = Smarts::parse(&nested(100)).unwrap();
+        assert_eq!(1, smarts.num_atoms());
+        let mol = crate::SmilesParser::new().parse("CO").unwrap();
+        assert_eq!(1, mol.substruct_matches(&smarts).len());
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::NestingTooDeep, 385)),
+            Smarts::parse(&nested(1000))
+        );
+        // positions are in the whole SMARTS
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::UnclosedRing(1), 6)),
+            Smarts::parse("C[$(CC1C)]")
+        );
+    }
+
+    #[test]
+    fn parse_nested_not() {
+        assert_eq!(
+            Expr::Not(Box::new(Expr::Not(Box::new(carbon(Some(false)))))),
+            atom("[!!C]")
+        );
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::NestingTooDeep, 129)),
+            Smarts::parse(&("[".to_string() + &"!".repeat(100_000) + "C]"))
+        );
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::NestingTooDeep, 129)),
+            Smarts::parse(&("C".to_string() + &"!".repeat(100_000) + "-C"))
+        );
+        // both count
+        let nested = "[!$(".repeat(100) + "C" + &")]".repeat(100);
+        assert!(matches!(
+            Smarts::parse(&nested),
+            Err(SmilesError {
+                kind: SmilesErrorKind::NestingTooDeep,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::UnclosedRing(1), 1)),
+            Smarts::parse("C1CC")
+        );
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::DanglingBond, 1)),
+            Smarts::parse("C=")
+        );
+        assert_eq!(
+            Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedEnd,
+                3,
+                "atom primitive"
+            )),
+            Smarts::parse("[C&")
+        );
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::UnbalancedParenthesis, 2)),
+            Smarts::parse("[$(CC]")
+        );
+        assert_eq!(
+            Err(SmilesError::new(
+                SmilesErrorKind::UnknownElement("Q".to_string()),
+                1
+            )),
+            Smarts::parse("[Q]")
+        );
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::NumberOutOfRange, 11)),
+            Smarts::parse("[#4294967296]")
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Reading SMILES files: one molecule per line, optionally followed by whitespace and a name.
+
+use std::io::BufRead;
+
+use crate::error::{ParseErrorAt, SmilesError};
+use crate::smiles::SmilesParser;
+use crate::types::Mol;
+
+/// What [`SmiReader`] and [`DatasetReader`](crate::dataset::DatasetReader) do with
+/// records that don't parse. I/O errors are always yielded, since reading stops there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Yields the error and continues with the next line.
+    #[default]
+    Yield,
+    /// Skips the line.
+    Skip,
+    /// Skips the line and keeps the error, see [`SmiReader::errors`].
+    Collect,
+}
+
+/// Iterates over the lines of a SMILES file, one at a time, yielding the molecule and its
+/// name. Blank lines are skipped; reading stops at an I/O error.
+pub struct SmiReader<R> {
+    reader: R,
+    parser: SmilesParser,
+    policy: ErrorPolicy,
+    errors: Vec<ParseErrorAt>,
+    line: usize,
+    failed: bool,
+}
+
+impl<R: BufRead> SmiReader<R> {
+    pub fn new(reader: R) -> Self {
+        SmiReader {
+            reader,
+            parser: SmilesParser::new(),
+            policy: ErrorPolicy::default(),
+            errors: vec![],
+            line: 0,
+            failed: false,
+        }
+    }
+
+    pub fn with_parser(mut self, parser: SmilesParser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The errors kept with [`ErrorPolicy::Collect`], in file order.
+    pub fn errors(&self) -> &[ParseErrorAt] {
+        &self.errors
+    }
+}
+
+impl<R: BufRead> Iterator for SmiReader<R> {
+    type Item = Result<(Mol, Option<String>), ParseErrorAt>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = String::new();
+        while !self.failed {
+            buffer.clear();
+            let error = match self.reader.read_line(&mut buffer) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.line += 1;
+                    match parse_line(&self.parser, &buffer) {
+                        None => continue,
+                        Some(Ok(record)) => return Some(Ok(record)),
+                        Some(Err(error)) => ParseErrorAt::Smiles {
+                            line: self.line,
+                            error,
+                        },
+                    }
+                }
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(ParseErrorAt::Io(error)));
+                }
+            };
+            match self.policy {
+                ErrorPolicy::Yield => return Some(Err(error)),
+                ErrorPolicy::Skip => {}
+                ErrorPolicy::Collect => self.errors.push(error),
+            }
+        }
+        None
+    }
+}
+
+/// The molecule and name of a line, `None` for blank lines.
+fn parse_line(
+    parser: &SmilesParser,
+    line: &str,
+) -> Option<Result<(Mol, Option<String>), SmilesError>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let (smiles, name) = match line.split_once(char::is_whitespace) {
+        Some((smiles, name)) => (smiles, Some(name.trim().to_string())),
+        None => (line, None),
+    };
+    Some(parser.parse(smiles).map(|mol| (mol, name)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, BufRead, Read};
+
+    use crate::error::{ParseErrorAt, SmilesErrorKind};
+
+    use super::{ErrorPolicy, SmiReader};
+
+    const INPUT: &str = "CCO ethanol\n\nc1ccccc1\tbenzene ring\nC1CC bad\nN\n";
+
+    #[test]
+    fn read_records() {
+        let records: Vec<_> = SmiReader::new(INPUT.as_bytes()).collect();
+
+        assert_eq!(4, records.len());
+        let (mol, name) = records[0].as_ref().unwrap();
+        assert_eq!("CCO", mol.to_smiles());
+        assert_eq!(Some("ethanol"), name.as_deref());
+        let (mol, name) = records[1].as_ref().unwrap();
+        assert_eq!(6, mol.num_atoms());
+        assert_eq!(Some("benzene ring"), name.as_deref());
+        match &records[2] {
+            Err(ParseErrorAt::Smiles { line, error }) => {
+                assert_eq!(4, *line);
+                assert_eq!(SmilesErrorKind::UnclosedRing(1), error.kind);
+            }
+            other => panic!("expected an error, got {:?}", other),
+        }
+        assert_eq!(None, records[3].as_ref().unwrap().1);
+    }
+
+    #[test]
+    fn error_policies() {
+        let skipped: Vec<_> = SmiReader::new(INPUT.as_bytes())
+            .with_policy(ErrorPolicy::Skip)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(3, skipped.len());
+
+        let mut reader = SmiReader::new(INPUT.as_bytes()).with_policy(ErrorPolicy::Collect);
+        assert_eq!(3, reader.by_ref().map(Result::unwrap).count());
+        assert!(matches!(
+            reader.errors(),
+            [ParseErrorAt::Smiles { line: 4, .. }]
+        ));
+    }
+
+    /// Fails on the first read.
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk on fire"))
+        }
+    }
+
+    impl BufRead for FailingReader {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            Err(io::Error::other("disk on fire"))
+        }
+
+        fn consume(&mut self, _: usize) {}
+    }
+
+    #[test]
+    fn read_stops_at_io_error() {
+        let records: Vec<_> = SmiReader::new(FailingReader).collect();
+
+        assert_eq!(1, records.len());
+        assert!(matches!(records[0], Err(ParseErrorAt::Io(_))));
+    }
+
+    #[test]
+    fn io_errors_are_yielded_whatever_the_policy() {
+        for policy in [ErrorPolicy::Skip, ErrorPolicy::Collect] {
+            let mut reader = SmiReader::new(FailingReader).with_policy(policy);
+            let records: Vec<_> = reader.by_ref().collect();
+
+            assert_eq!(1, records.len(), "{:?}", policy);
+            assert!(matches!(records[0], Err(ParseErrorAt::Io(_))));
+            assert!(reader.errors().is_empty());
+        }
+        // after the lines read before the error
+        let input = io::Cursor::new(
+            "CCO
+C1CC
+",
+        )
+        .chain(FailingReader);
+        let records: Vec<_> = SmiReader::new(io::BufReader::new(input))
+            .with_policy(ErrorPolicy::Skip)
+            .collect();
+        assert_eq!(2, records.len());
+        assert!(records[0].is_ok());
+        assert!(matches!(records[1], Err(ParseErrorAt::Io(_))));
+    }
+}
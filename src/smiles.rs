@@ -0,0 +1,2224 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
+use petgraph::visit::EdgeRef;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::ast::{self, Attachment, ChainAtom, Connection, SmilesAst, Visitor};
+use crate::cxsmiles::{self, MolExtensions};
+use crate::error::{SmilesError, SmilesErrorKind};
+use crate::lexer::{BondSymbol, ByteOffsets, Lexer, Span, TokenKind};
+use crate::lint::Diagnostic;
+use crate::periodic_table::Element;
+use crate::topology;
+use crate::types::{
+    Atom, Bond, BondDirection, BondOrder, BondStereo, Chirality, DoubleBondConfig, Mol, SourceSpan,
+};
+use crate::valence;
+
+/// Parses SMILES into [`Mol`]s. The parser only holds its options and parsing doesn't
+/// change it, the element data it looks up is in statics: one parser can be shared by
+/// any number of threads, e.g. behind an `Arc` or a `static`.
+#[derive(Debug, Clone, Default)]
+pub struct SmilesParser {
+    options: SmilesParserOptions,
+}
+
+// sharing a parser between threads is part of its API
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SmilesParser>();
+};
+
+/// How tolerant [`SmilesParser`] is. The default accepts what common toolkits write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmilesParserOptions {
+    /// Rejects what OpenSMILES doesn't define or deprecates: the `[D]` and `[T]`
+    /// shorthands, charges written as `++` or `--`, hydrogen counts of more than one digit,
+    /// ring numbers written as `%(n)`, ring bonds after branches and branches that don't
+    /// start with an atom, a bond or `.`.
+    pub strict: bool,
+    /// Kekulizes the molecule, which fails for aromatic systems without a Kekulé form.
+    pub kekulize: bool,
+    /// Accepts lowercase aromatic atoms that aren't in a ring, e.g. `cc`.
+    pub allow_aromatic_outside_rings: bool,
+    /// Highest ring bond number accepted, 99 by default. Numbers past 99, up to 999, are
+    /// written as `%(nnn)`, which strict mode rejects. Higher numbers are rejected with
+    /// [`SmilesErrorKind::RingNumberTooHigh`].
+    pub max_ring_number: u16,
+    /// Deepest nesting of branches accepted. The parser recurses into branches, and the
+    /// default fits in a 2 MiB thread stack in debug builds: deeper nesting needs a
+    /// bigger stack.
+    pub max_branch_depth: usize,
+}
+
+impl Default for SmilesParserOptions {
+    fn default() -> Self {
+        SmilesParserOptions {
+            strict: false,
+            kekulize: false,
+            allow_aromatic_outside_rings: true,
+            max_ring_number: 99,
+            max_branch_depth: 256,
+        }
+    }
+}
+
+/// A ring bond that has been opened but not closed yet.
+#[derive(Clone)]
+struct OpenRing {
+    atom: NodeIndex,
+    symbol: Option<BondSymbol>,   // bond symbol written at the opening
+    position: usize,              // position of the opening in the input
+    neighbor_slot: Option<usize>, // index in the atom's written neighbors, if it's chiral
+}
+
+/// Ring bond numbers are mostly up to 99, one slot each.
+const RING_SLOTS: usize = 100;
+
+/// The open ring bonds, by ring number. A number is free again once its ring is closed.
+#[derive(Default)]
+struct OpenRings {
+    // allocated on the first ring bond, most molecules have a few rings or none
+    slots: Vec<Option<OpenRing>>,
+}
+
+impl OpenRings {
+    fn open(&mut self, ring: u16, open_ring: OpenRing) {
+        let index = usize::from(ring);
+        if index >= self.slots.len() {
+            // more than 99 only when written as `%(nnn)` or from an AST built by hand
+            self.slots.resize(RING_SLOTS.max(index + 1), None);
+        }
+        self.slots[index] = Some(open_ring);
+    }
+
+    /// Closes the ring, returning where it was opened if it's open.
+    fn take(&mut self, ring: u16) -> Option<OpenRing> {
+        self.slots.get_mut(usize::from(ring))?.take()
+    }
+
+    /// The rings left open, by number.
+    fn into_open(self) -> impl Iterator<Item = (u16, OpenRing)> {
+        self.slots
+            .into_iter()
+            .enumerate()
+            .filter_map(|(ring, open_ring)| Some((ring as u16, open_ring?)))
+    }
+}
+
+/// Adds the atoms and bonds of a SMILES string or [`SmilesAst`] to a graph.
+struct GraphBuilder {
+    graph: UnGraph<Atom, Bond>,
+    // the atom read last
+    previous: Option<NodeIndex>,
+    // the atoms the open branches are attached to
+    branches: Vec<NodeIndex>,
+    rings: OpenRings,
+    // the first ring bond to the atom itself, to an atom already bonded to it, or whose
+    // ends are written with conflicting bond symbols
+    ring_bond_error: Option<SmilesError>,
+    // bonds between aromatic atoms without a symbol, outside the ring closures: aromatic
+    // only if they end up in a ring, like the ring bonds of biphenyl but not its linkage
+    implicit_aromatic_bonds: Vec<EdgeIndex>,
+}
+
+impl GraphBuilder {
+    /// A builder with room for the number of atoms.
+    fn with_capacity(atoms: usize) -> GraphBuilder {
+        GraphBuilder {
+            graph: UnGraph::with_capacity(atoms, atoms),
+            previous: None,
+            branches: vec![],
+            rings: OpenRings::default(),
+            ring_bond_error: None,
+            implicit_aromatic_bonds: vec![],
+        }
+    }
+
+    /// Records `neighbor` as the next neighbor written for `atom`, if it's chiral: its
+    /// chirality is relative to the neighbors in the order they're written.
+    fn add_written_neighbor(&mut self, atom: NodeIndex, neighbor: NodeIndex) {
+        let atom = &mut self.graph[atom];
+        if atom.chirality.is_some() {
+            atom.chiral_neighbors.push(neighbor.into());
+        }
+    }
+
+    /// Opens the ring, or bonds the atom to the one that opened it.
+    fn add_ring_bond(
+        &mut self,
+        ring_end: NodeIndex,
+        ring: u16,
+        pending_symbol: Option<BondSymbol>,
+        span: Span,
+    ) {
+        let position = span.start;
+        // taking the ring when it ends allows to reuse its number afterwards
+        match self.rings.take(ring) {
+            None => {
+                // a ring starts
+                // placeholder for the neighbor, which is known when the ring ends
+                self.add_written_neighbor(ring_end, ring_end);
+                let neighbor_slot = (self.graph[ring_end].chirality.is_some())
+                    .then(|| self.graph[ring_end].chiral_neighbors.len() - 1);
+                self.rings.open(
+                    ring,
+                    OpenRing {
+                        atom: ring_end,
+                        symbol: pending_symbol,
+                        position,
+                        neighbor_slot,
+                    },
+                );
+            }
+            Some(open_ring)
+                if open_ring.atom == ring_end
+                    || self.graph.find_edge(open_ring.atom, ring_end).is_some() =>
+            {
+                // reported once the input is read, syntax errors come first
+                let kind = if open_ring.atom == ring_end {
+                    SmilesErrorKind::RingBondToSelf(ring)
+                } else {
+                    SmilesErrorKind::DuplicateBond(ring)
+                };
+                self.ring_bond_error
+                    .get_or_insert(SmilesError::new(kind, position));
+                // the bond isn't added, so the stereocenter misses a neighbor
+                if open_ring.neighbor_slot.is_some() {
+                    let atom = &mut self.graph[open_ring.atom];
+                    atom.chirality = None;
+                    atom.chiral_neighbors.clear();
+                }
+            }
+            Some(open_ring) => {
+                let ring_start = open_ring.atom;
+                // ring ends
+                if let (Some(start), Some(end)) = (open_ring.symbol, pending_symbol) {
+                    if !start.matches_ring_end(end) {
+                        self.ring_bond_error.get_or_insert(SmilesError::new(
+                            SmilesErrorKind::RingBondConflict(ring),
+                            position,
+                        ));
+                    }
+                }
+                // the bond symbol can be written at either end of the ring bond
+                // a direction written at the end goes from the end to the start
+                let symbol = pending_symbol
+                    .map(BondSymbol::reversed)
+                    .or(open_ring.symbol);
+                let order = symbol.map(|s| s.order).unwrap_or_else(|| {
+                    implicit_bond_order(&self.graph[ring_start], &self.graph[ring_end])
+                });
+                // the symbol, if any, is just before the number
+                let start = position.saturating_sub(usize::from(pending_symbol.is_some()));
+                let bond = Bond {
+                    order,
+                    direction: symbol.and_then(|s| s.direction),
+                    span: SourceSpan(Some(Span { start, ..span })),
+                    ..Default::default()
+                };
+                self.graph.add_edge(ring_start, ring_end, bond);
+
+                if let Some(slot) = open_ring.neighbor_slot {
+                    self.graph[ring_start].chiral_neighbors[slot] = ring_end.into();
+                }
+                self.add_written_neighbor(ring_end, ring_start);
+            }
+        }
+    }
+}
+
+impl Visitor for GraphBuilder {
+    fn atom(&mut self, connection: Connection, mut atom: Atom, span: Span) {
+        let (last, symbol) = match connection {
+            Connection::Implicit => (self.previous, None),
+            Connection::Bond(symbol) => (self.previous, Some(symbol)),
+            Connection::Dot => (None, None),
+        };
+        if last.is_some() && atom.explicit_h == Some(1) {
+            // the hydrogen is written second, after the previous atom:
+            // move it first, which inverts the chirality
+            atom.chirality = atom.chirality.map(Chirality::inverted);
+        }
+        if atom.chirality.is_some() {
+            atom.chiral_neighbors.clear();
+        }
+        atom.span = SourceSpan(Some(span));
+        let node_index = add_to_graph(&mut self.graph, atom, last, symbol);
+        if let Some(last) = last {
+            if symbol.is_some() {
+                // the symbol is just before the atom
+                let edge = EdgeIndex::new(self.graph.edge_count() - 1);
+                let start = span.start.saturating_sub(1);
+                self.graph[edge].span = SourceSpan(Some(Span {
+                    start,
+                    end: span.start,
+                }));
+            }
+            if symbol.is_none() && self.graph[last].aromatic && self.graph[node_index].aromatic {
+                self.implicit_aromatic_bonds
+                    .push(EdgeIndex::new(self.graph.edge_count() - 1));
+            }
+            self.add_written_neighbor(last, node_index);
+            self.add_written_neighbor(node_index, last);
+        }
+        self.previous = Some(node_index);
+    }
+
+    fn ring_bond(&mut self, number: u16, symbol: Option<BondSymbol>, span: Span) {
+        // unwrap: ring bonds come after an atom
+        let atom = self.previous.unwrap();
+        self.add_ring_bond(atom, number, symbol, span);
+    }
+
+    fn open_branch(&mut self) {
+        // unwrap: branches come after an atom
+        self.branches.push(self.previous.unwrap());
+    }
+
+    fn close_branch(&mut self) {
+        self.previous = self.branches.pop();
+    }
+}
+
+impl SmilesParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: SmilesParserOptions) -> Self {
+        SmilesParser { options }
+    }
+
+    pub fn options(&self) -> &SmilesParserOptions {
+        &self.options
+    }
+
+    /// Parses the SMILES. Any input can be parsed, including untrusted input: whatever the
+    /// string, this returns the molecule or an error and doesn't panic.
+    pub fn parse(&self, smiles: &str) -> Result<Mol, SmilesError> {
+        // every atom has a letter or is `*`
+        let atoms = smiles
+            .bytes()
+            .filter(|c| c.is_ascii_alphabetic() || *c == b'*')
+            .count();
+        let mut builder = GraphBuilder::with_capacity(atoms);
+        ast::read(smiles, self.options, &mut builder)?;
+        self.finish(builder)
+    }
+
+    /// Like [`parse`](Self::parse), also returning the characters each atom is written
+    /// with, by atom index: e.g. to highlight the atom under the cursor in an editor.
+    pub fn parse_with_spans(&self, smiles: &str) -> Result<(Mol, Vec<Range<usize>>), SmilesError> {
+        let mut builder = GraphBuilder::with_capacity(smiles.len());
+        ast::read(smiles, self.options, &mut builder)?;
+        let mol = self.finish(builder)?;
+        let spans = mol.graph.node_weights().map(|atom| atom.span.range());
+        // unwrap: the builder sets the spans of the atoms
+        let spans = spans.map(Option::unwrap).collect();
+        Ok((mol, spans))
+    }
+
+    /// Parses a CXSMILES: SMILES, then optionally ChemAxon extensions between `|` after
+    /// whitespace, e.g. `C[C@H](O)CC |a:1|`. Coordinates, atom labels, radicals and
+    /// enhanced stereo groups are read, other fields are kept as written. Radicals are also
+    /// set on the atoms, which lose the hydrogens they'd otherwise get. Nothing can
+    /// follow the extensions: a name after them is for the caller to split off.
+    pub fn parse_cxsmiles(&self, cxsmiles: &str) -> Result<(Mol, MolExtensions), SmilesError> {
+        let smiles_end = cxsmiles.find(char::is_whitespace).unwrap_or(cxsmiles.len());
+        let mut mol = self.parse(&cxsmiles[..smiles_end])?;
+        let rest = cxsmiles[smiles_end..].trim_start();
+        if rest.is_empty() {
+            return Ok((mol, MolExtensions::default()));
+        }
+        // positions are in characters
+        let position = |byte: usize| cxsmiles[..byte].chars().count();
+        let start = cxsmiles.len() - rest.len();
+        if !rest.starts_with('|') {
+            return Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedCharacter(rest.chars().next().unwrap_or('|')),
+                position(start),
+                "'|'",
+            ));
+        }
+        let Some(length) = rest[1..].find('|') else {
+            return Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedEnd,
+                position(cxsmiles.len()),
+                "'|'",
+            ));
+        };
+        let end = start + 1 + length + 1;
+        if let Some(c) = cxsmiles[end..].chars().find(|c| !c.is_whitespace()) {
+            let trailing = cxsmiles[end..].trim_start();
+            return Err(SmilesError::new(
+                SmilesErrorKind::UnexpectedCharacter(c),
+                position(cxsmiles.len() - trailing.len()),
+            ));
+        }
+        let extensions =
+            cxsmiles::parse(&rest[1..1 + length], position(start + 1), mol.num_atoms())?;
+        for (atom, electrons) in &extensions.radicals {
+            let node = NodeIndex::from(*atom);
+            mol.graph[node].num_radical_electrons = *electrons;
+            mol.graph[node].implicit_h = valence::implicit_h_count(&mol, node);
+        }
+        Ok((mol, extensions))
+    }
+
+    /// Parses the syntax only. The result can be inspected or changed before building
+    /// the molecule with [`build`](Self::build).
+    pub fn parse_ast(&self, smiles: &str) -> Result<SmilesAst, SmilesError> {
+        ast::parse(smiles, self.options)
+    }
+
+    /// Builds the molecule: matches the ring bonds, sets the stereo and derives the
+    /// hydrogens. Error positions are taken from the spans in the AST.
+    pub fn build(&self, ast: &SmilesAst) -> Result<Mol, SmilesError> {
+        let mut builder = GraphBuilder::with_capacity(count_atoms(&ast.chain));
+        ast.walk(&mut builder);
+        self.finish(builder)
+    }
+
+    /// Parses as much of the SMILES as possible, e.g. to show a partial structure while
+    /// it's edited or to salvage bad records: tokens that can't be read or don't fit are
+    /// skipped, and rings and branches left open are dropped. Returns the molecule with
+    /// the problems met, in input order. Without problems, the molecule is what
+    /// [`parse`](Self::parse) returns.
+    pub fn parse_lenient(&self, smiles: &str) -> (Mol, Vec<Diagnostic>) {
+        let mut builder = GraphBuilder::with_capacity(smiles.len());
+        let mut diagnostics = vec![];
+        let mut lexer = Lexer::new(smiles, self.options);
+        // like ast::read, which stops at the first problem instead
+        let mut has_last_atom = false;
+        let mut dot = false;
+        let mut empty_branch = false;
+        // for each open branch, whether the builder opened it: not without an atom before
+        let mut branches: Vec<(bool, Span)> = vec![];
+        let mut pending_bond: Option<(BondSymbol, Span)> = None;
+
+        while let Some(token) = lexer.next() {
+            let token = match token {
+                Ok(token) => token,
+                Err(error) => {
+                    diagnostics.push(Diagnostic::from_error(error));
+                    lexer.recover();
+                    continue;
+                }
+            };
+            let span = token.span;
+            let mut report = |kind| diagnostics.push(Diagnostic::parse(kind, span));
+            match token.kind {
+                TokenKind::OrganicAtom(atom) | TokenKind::BracketAtom(atom) => {
+                    let connection = match pending_bond.take() {
+                        Some((symbol, _)) => Connection::Bond(symbol),
+                        None if dot => Connection::Dot,
+                        None => Connection::Implicit,
+                    };
+                    builder.atom(connection, atom, span);
+                    has_last_atom = true;
+                    dot = false;
+                    empty_branch = false;
+                }
+                TokenKind::RingBond(number) => {
+                    let symbol = pending_bond.take().map(|(symbol, _)| symbol);
+                    if number > self.options.max_ring_number {
+                        report(SmilesErrorKind::RingNumberTooHigh {
+                            number,
+                            max: self.options.max_ring_number,
+                        });
+                    } else if !has_last_atom {
+                        report(SmilesErrorKind::MissingAtom);
+                    } else {
+                        builder.ring_bond(number, symbol, span);
+                        // the ring bond wasn't added, or with the symbol at its end
+                        if let Some(error) = builder.ring_bond_error.take() {
+                            diagnostics.push(Diagnostic::from_error(error));
+                        }
+                    }
+                }
+                TokenKind::Bond(symbol) => {
+                    if !has_last_atom || pending_bond.is_some() {
+                        report(SmilesErrorKind::UnexpectedCharacter(symbol.to_char()));
+                    } else {
+                        pending_bond = Some((symbol, span));
+                    }
+                }
+                TokenKind::BranchOpen | TokenKind::BranchClose | TokenKind::Dot => {
+                    if let Some((_, bond_span)) = pending_bond.take() {
+                        diagnostics
+                            .push(Diagnostic::parse(SmilesErrorKind::DanglingBond, bond_span));
+                    }
+                    let missing_atom = Diagnostic::parse(SmilesErrorKind::MissingAtom, span);
+                    match token.kind {
+                        TokenKind::BranchOpen => {
+                            if branches.len() == self.options.max_branch_depth {
+                                diagnostics
+                                    .push(Diagnostic::parse(SmilesErrorKind::NestingTooDeep, span));
+                            }
+                            if has_last_atom {
+                                builder.open_branch();
+                            } else {
+                                diagnostics.push(missing_atom);
+                            }
+                            branches.push((has_last_atom, span));
+                            empty_branch = true;
+                        }
+                        TokenKind::BranchClose => match branches.pop() {
+                            Some((opened, _)) => {
+                                if !has_last_atom || empty_branch {
+                                    diagnostics.push(missing_atom);
+                                }
+                                empty_branch = false;
+                                if opened {
+                                    builder.close_branch();
+                                    has_last_atom = true;
+                                    dot = false;
+                                }
+                            }
+                            None => diagnostics.push(Diagnostic::parse(
+                                SmilesErrorKind::UnbalancedParenthesis,
+                                span,
+                            )),
+                        },
+                        _ => {
+                            if !has_last_atom {
+                                diagnostics.push(missing_atom);
+                            }
+                            has_last_atom = false;
+                            dot = true;
+                            empty_branch = false;
+                        }
+                    }
+                }
+            }
+        }
+
+        let end = lexer.position();
+        if dot {
+            let span = Span { start: end, end };
+            diagnostics.push(Diagnostic::parse(SmilesErrorKind::UnexpectedEnd, span));
+        }
+        if let Some((_, span)) = pending_bond {
+            diagnostics.push(Diagnostic::parse(SmilesErrorKind::DanglingBond, span));
+        }
+        for (_, span) in branches {
+            let kind = SmilesErrorKind::UnbalancedParenthesis;
+            diagnostics.push(Diagnostic::parse(kind, span));
+        }
+        for (ring, open_ring) in core::mem::take(&mut builder.rings).into_open() {
+            let kind = SmilesErrorKind::UnclosedRing(ring);
+            diagnostics.push(Diagnostic::from_error(SmilesError::new(
+                kind,
+                open_ring.position,
+            )));
+            // the missing neighbor leaves the chirality undefined
+            if open_ring.neighbor_slot.is_some() {
+                let atom = &mut builder.graph[open_ring.atom];
+                atom.chirality = None;
+                atom.chiral_neighbors.clear();
+            }
+        }
+
+        let mut mol = derive(builder);
+        if let Err(error) = self.apply_options(&mut mol) {
+            diagnostics.push(Diagnostic::from_error(error));
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        // the skipped tokens can be any character
+        let offsets = ByteOffsets::new(smiles);
+        for atom in mol.graph.node_weights_mut() {
+            atom.span.0 = atom.span.0.map(|span| offsets.span(span));
+        }
+        for bond in mol.graph.edge_weights_mut() {
+            bond.span.0 = bond.span.0.map(|span| offsets.span(span));
+        }
+        (mol, diagnostics)
+    }
+
+    /// Checks the rings are closed, and derives what's not written.
+    fn finish(&self, mut builder: GraphBuilder) -> Result<Mol, SmilesError> {
+        if let Some(error) = builder.ring_bond_error {
+            return Err(error);
+        }
+        let open_rings = core::mem::take(&mut builder.rings).into_open();
+        if let Some((ring, open_ring)) = open_rings.min_by_key(|(_, r)| r.position) {
+            return Err(SmilesError::new(
+                SmilesErrorKind::UnclosedRing(ring),
+                open_ring.position,
+            ));
+        }
+        let mut mol = derive(builder);
+        self.apply_options(&mut mol)?;
+        Ok(mol)
+    }
+
+    /// Checks and changes the molecule as the options say.
+    fn apply_options(&self, mol: &mut Mol) -> Result<(), SmilesError> {
+        // unwrap: the builder sets the spans of the atoms
+        let position = |mol: &Mol, node: NodeIndex| mol.graph[node].span.0.unwrap().start;
+        if !self.options.allow_aromatic_outside_rings {
+            let ring_info = mol.ring_info();
+            if let Some(node) = mol.graph.node_indices().find(|node| {
+                mol.graph[*node].aromatic && !ring_info.is_atom_in_ring((*node).into())
+            }) {
+                return Err(SmilesError::new(
+                    SmilesErrorKind::AromaticOutsideRing,
+                    position(mol, node),
+                ));
+            }
+        }
+        if self.options.kekulize {
+            mol.kekulize().map_err(|error| {
+                SmilesError::new(SmilesErrorKind::Kekulize, position(mol, error.atom.into()))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Parses each input, returning the results in input order.
+    pub fn parse_many(&self, inputs: &[&str]) -> Vec<Result<Mol, SmilesError>> {
+        inputs.iter().map(|smiles| self.parse(smiles)).collect()
+    }
+
+    /// Like [`parse_many`](Self::parse_many), but in parallel on rayon's thread pool.
+    /// The results are in input order.
+    #[cfg(feature = "parallel")]
+    pub fn par_parse_many(&self, inputs: &[&str]) -> Vec<Result<Mol, SmilesError>> {
+        inputs.par_iter().map(|smiles| self.parse(smiles)).collect()
+    }
+}
+
+/// The molecule of the atoms and bonds read, with the stereo and hydrogens that aren't
+/// written.
+fn derive(builder: GraphBuilder) -> Mol {
+    let GraphBuilder {
+        mut graph,
+        implicit_aromatic_bonds,
+        ..
+    } = builder;
+
+    set_double_bond_stereo(&mut graph);
+
+    let mut mol = Mol { graph };
+    if !implicit_aromatic_bonds.is_empty() {
+        let in_ring = topology::ring_bonds(&mol);
+        for edge in implicit_aromatic_bonds {
+            if !in_ring[edge.index()] {
+                mol.graph[edge].order = BondOrder::Single;
+            }
+        }
+    }
+    for node in mol.graph.node_indices() {
+        mol.graph[node].implicit_h = valence::implicit_h_count(&mol, node);
+        if mol.graph[node].explicit_h.is_some() {
+            mol.graph[node].num_radical_electrons = valence::valence_deficit(&mol, node);
+        }
+    }
+    mol
+}
+
+/// Number of atoms in the chain and its branches.
+fn count_atoms(chain: &[ChainAtom]) -> usize {
+    chain
+        .iter()
+        .map(|chain_atom| {
+            let branches = chain_atom
+                .attachments
+                .iter()
+                .map(|attachment| match attachment {
+                    Attachment::Branch(branch) => count_atoms(branch),
+                    Attachment::RingBond { .. } => 0,
+                });
+            1 + branches.sum::<usize>()
+        })
+        .sum()
+}
+
+/// Sets the cis/trans configuration of double bonds that have a directional bond on each side.
+fn set_double_bond_stereo(graph: &mut UnGraph<Atom, Bond>) {
+    if !graph.edge_weights().any(|bond| bond.direction.is_some()) {
+        return;
+    }
+    for edge in graph.edge_indices() {
+        if graph[edge].order != BondOrder::Double {
+            continue;
+        }
+        // unwrap: edge comes from the graph
+        let (start, end) = graph.edge_endpoints(edge).unwrap();
+        // directions normalized to go from the neighbor to start, and from end to the neighbor
+        let start_side = directional_neighbor(graph, start, edge, false);
+        let end_side = directional_neighbor(graph, end, edge, true);
+        if let (Some((start_neighbor, start_dir)), Some((end_neighbor, end_dir))) =
+            (start_side, end_side)
+        {
+            let config = if start_dir == end_dir {
+                DoubleBondConfig::Trans
+            } else {
+                DoubleBondConfig::Cis
+            };
+            graph[edge].stereo = Some(BondStereo {
+                config,
+                start_neighbor: start_neighbor.into(),
+                end_neighbor: end_neighbor.into(),
+            });
+        }
+    }
+}
+
+/// Finds a neighbor of `atom` (other than through `double_bond`) connected with a directional
+/// bond. The direction is normalized to go from `atom` to the neighbor if `outgoing`,
+/// otherwise from the neighbor to `atom`.
+fn directional_neighbor(
+    graph: &UnGraph<Atom, Bond>,
+    atom: NodeIndex,
+    double_bond: EdgeIndex,
+    outgoing: bool,
+) -> Option<(NodeIndex, BondDirection)> {
+    graph
+        .edges(atom)
+        .filter(|edge| edge.id() != double_bond)
+        .find_map(|edge| {
+            let bond = edge.weight();
+            let direction = bond.direction?;
+            // unwrap: edge comes from the graph
+            let (start, _) = graph.edge_endpoints(edge.id()).unwrap();
+            let from_atom = start == atom;
+            let direction = if from_atom == outgoing {
+                direction
+            } else {
+                direction.inverted()
+            };
+            Some((edge.target(), direction))
+        })
+}
+
+/// Order of a bond written without symbol: aromatic between two aromatic atoms, otherwise single.
+pub fn implicit_bond_order(atom1: &Atom, atom2: &Atom) -> BondOrder {
+    if atom1.aromatic && atom2.aromatic {
+        BondOrder::Aromatic
+    } else {
+        BondOrder::Single
+    }
+}
+
+fn add_to_graph(
+    graph: &mut UnGraph<Atom, Bond>,
+    atom: Atom,
+    last_node_index: Option<NodeIndex>,
+    symbol: Option<BondSymbol>,
+) -> NodeIndex {
+    let node_index = graph.add_node(atom);
+    if let Some(last) = last_node_index {
+        let order = symbol
+            .map(|s| s.order)
+            .unwrap_or_else(|| implicit_bond_order(&graph[last], &graph[node_index]));
+        let bond = Bond {
+            order,
+            direction: symbol.and_then(|s| s.direction),
+            ..Default::default()
+        };
+        graph.add_edge(last, node_index, bond);
+    }
+    node_index
+}
+
+/// Atomic number of the `*` atom, an unknown atom or attachment point.
+pub const WILDCARD: u32 = 0;
+
+/// Symbols that can be written lowercase (aromatic) inside brackets.
+static AROMATIC_SYMBOLS: [&str; 8] = ["b", "c", "n", "o", "p", "s", "se", "as"];
+
+/// Returns the element symbol for an atomic number, or `None` if the number is unknown.
+pub fn element_symbol(number: u32) -> Option<&'static str> {
+    Element::from_number(number).map(|element| element.symbol)
+}
+
+/// Returns the atomic number for an element symbol, or `None` if the symbol is unknown.
+/// Lowercase (aromatic) symbols map to their element.
+pub fn atom_number(symbol: &str) -> Option<u32> {
+    Element::from_symbol_any_first_case(symbol).map(|element| element.number)
+}
+
+pub fn is_symbol(sequence: &str) -> bool {
+    Element::from_symbol(sequence).is_some() || AROMATIC_SYMBOLS.contains(&sequence)
+}
+
+pub fn is_symbol_prefix(sequence: &str) -> bool {
+    if sequence.len() > 1 {
+        // symbols have two letters at most
+        return false;
+    }
+    Element::all()
+        .iter()
+        .map(|element| element.symbol)
+        .chain(AROMATIC_SYMBOLS)
+        .any(|s| s.len() > sequence.len() && s.starts_with(sequence))
+}
+
+#[cfg(test)]
+mod test {
+
+    use crate::error::{SmilesError, SmilesErrorKind};
+    use crate::lint::DiagnosticKind;
+    use crate::testing::smiles_like;
+    use crate::types::{
+        Atom, AtomIdx, Bond, BondDirection, BondIdx, BondOrder, BondStereo, Chirality,
+        DoubleBondConfig, Mol,
+    };
+
+    use proptest::prelude::*;
+
+    use super::{SmilesParser, SmilesParserOptions, WILDCARD};
+
+    /// The atom without the derived implicit hydrogens, to compare only what was parsed.
+    fn parsed_atom(mol: &Mol, idx: usize) -> Option<Atom> {
+        mol.atom_with_idx(AtomIdx::new(idx)).map(|atom| Atom {
+            implicit_h: 0,
+            ..atom.clone()
+        })
+    }
+
+    fn aromatic(number: u32) -> Atom {
+        Atom {
+            number,
+            aromatic: true,
+            ..Default::default()
+        }
+    }
+
+    fn aliphatic(number: u32) -> Atom {
+        Atom {
+            number,
+            aromatic: false,
+            ..Default::default()
+        }
+    }
+
+    /// The bond with its atoms.
+    fn parsed_bond(mol: &Mol, idx: usize) -> Option<(AtomIdx, AtomIdx, Bond)> {
+        let (start, end) = mol.bond_atoms(BondIdx::new(idx))?;
+        let bond = mol.bond_with_idx(BondIdx::new(idx))?;
+        Some((start, end, bond.clone()))
+    }
+
+    fn bond(atom_start: usize, atom_end: usize) -> (AtomIdx, AtomIdx, Bond) {
+        bond_with_order(atom_start, atom_end, BondOrder::Single)
+    }
+
+    fn aromatic_bond(atom_start: usize, atom_end: usize) -> (AtomIdx, AtomIdx, Bond) {
+        bond_with_order(atom_start, atom_end, BondOrder::Aromatic)
+    }
+
+    fn bond_with_order(
+        atom_start: usize,
+        atom_end: usize,
+        order: BondOrder,
+    ) -> (AtomIdx, AtomIdx, Bond) {
+        let bond = Bond {
+            order,
+            ..Default::default()
+        };
+        (AtomIdx::new(atom_start), AtomIdx::new(atom_end), bond)
+    }
+
+    fn atoms(indices: &[usize]) -> Vec<AtomIdx> {
+        indices.iter().map(|i| AtomIdx::new(*i)).collect()
+    }
+
+    #[test]
+    fn parse_ccc() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("ccc").unwrap();
+
+        assert_eq!(3, mol.num_atoms());
+        assert_eq!(2, mol.num_bonds());
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 0));
+    }
+
+    #[test]
+    fn parse_cyclopentane() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("c1cccc1").unwrap();
+
+        assert_eq!(5, mol.num_atoms());
+        assert_eq!(5, mol.num_bonds());
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 0));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 1));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 2));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 3));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 4));
+
+        assert_eq!(Some(aromatic_bond(0, 1)), parsed_bond(&mol, 0));
+        assert_eq!(Some(aromatic_bond(1, 2)), parsed_bond(&mol, 1));
+        assert_eq!(Some(aromatic_bond(2, 3)), parsed_bond(&mol, 2));
+        assert_eq!(Some(aromatic_bond(3, 4)), parsed_bond(&mol, 3));
+        assert_eq!(Some(aromatic_bond(0, 4)), parsed_bond(&mol, 4));
+    }
+
+    #[test]
+    fn parse_bicyclohexyl() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("c1ccccc1c2ccccc2").unwrap();
+
+        assert_eq!(12, mol.num_atoms());
+        assert_eq!(13, mol.num_bonds());
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 0));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 1));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 2));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 3));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 4));
+
+        assert_eq!(Some(aromatic_bond(0, 1)), parsed_bond(&mol, 0));
+        assert_eq!(Some(aromatic_bond(1, 2)), parsed_bond(&mol, 1));
+        assert_eq!(Some(aromatic_bond(2, 3)), parsed_bond(&mol, 2));
+        assert_eq!(Some(aromatic_bond(3, 4)), parsed_bond(&mol, 3));
+        assert_eq!(Some(aromatic_bond(4, 5)), parsed_bond(&mol, 4));
+        assert_eq!(Some(aromatic_bond(0, 5)), parsed_bond(&mol, 5));
+        // the linkage isn't in a ring
+        assert_eq!(Some(bond(5, 6)), parsed_bond(&mol, 6));
+        assert_eq!(Some(aromatic_bond(6, 7)), parsed_bond(&mol, 7));
+        assert_eq!(Some(aromatic_bond(7, 8)), parsed_bond(&mol, 8));
+        assert_eq!(Some(aromatic_bond(8, 9)), parsed_bond(&mol, 9));
+        assert_eq!(Some(aromatic_bond(9, 10)), parsed_bond(&mol, 10));
+        assert_eq!(Some(aromatic_bond(10, 11)), parsed_bond(&mol, 11));
+        assert_eq!(Some(aromatic_bond(6, 11)), parsed_bond(&mol, 12));
+    }
+
+    #[test]
+    fn parse_fluoroform() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("FC(F)F").unwrap();
+
+        assert_eq!(4, mol.num_atoms());
+        assert_eq!(3, mol.num_bonds());
+        assert_eq!(Some(aliphatic(9)), parsed_atom(&mol, 0));
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 1));
+        assert_eq!(Some(aliphatic(9)), parsed_atom(&mol, 2));
+        assert_eq!(Some(aliphatic(9)), parsed_atom(&mol, 3));
+
+        assert_eq!(Some(bond(0, 1)), parsed_bond(&mol, 0));
+        assert_eq!(Some(bond(1, 2)), parsed_bond(&mol, 1));
+        assert_eq!(Some(bond(1, 3)), parsed_bond(&mol, 2));
+    }
+
+    #[test]
+    fn parse_molecule_with_inner_cl() {
+        let parser = SmilesParser::new();
+        // probably an invalid molecule, doesn't matter as we're just testing the parsing
+        let mol = parser.parse("CCClC").unwrap();
+
+        assert_eq!(4, mol.num_atoms());
+        assert_eq!(3, mol.num_bonds());
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 0));
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 1));
+        assert_eq!(Some(aliphatic(17)), parsed_atom(&mol, 2));
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 3));
+
+        assert_eq!(Some(bond(0, 1)), parsed_bond(&mol, 0));
+        assert_eq!(Some(bond(1, 2)), parsed_bond(&mol, 1));
+        assert_eq!(Some(bond(2, 3)), parsed_bond(&mol, 2));
+    }
+
+    #[test]
+    fn parse_molecule_with_last_cl() {
+        let parser = SmilesParser::new();
+        // probably an invalid molecule, doesn't matter as we're just testing the parsing
+        let mol = parser.parse("CCCCl").unwrap();
+
+        assert_eq!(4, mol.num_atoms());
+        assert_eq!(3, mol.num_bonds());
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 0));
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 1));
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 2));
+        assert_eq!(Some(aliphatic(17)), parsed_atom(&mol, 3));
+
+        assert_eq!(Some(bond(0, 1)), parsed_bond(&mol, 0));
+        assert_eq!(Some(bond(1, 2)), parsed_bond(&mol, 1));
+        assert_eq!(Some(bond(2, 3)), parsed_bond(&mol, 2));
+    }
+
+    #[test]
+    fn parse_organic_subset() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("BrC(I)(Cl)NOPSFB").unwrap();
+
+        assert_eq!(10, mol.num_atoms());
+        assert_eq!(9, mol.num_bonds());
+        assert_eq!(Some(aliphatic(35)), parsed_atom(&mol, 0));
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 1));
+        assert_eq!(Some(aliphatic(53)), parsed_atom(&mol, 2));
+        assert_eq!(Some(aliphatic(17)), parsed_atom(&mol, 3));
+        assert_eq!(Some(aliphatic(7)), parsed_atom(&mol, 4));
+        assert_eq!(Some(aliphatic(8)), parsed_atom(&mol, 5));
+        assert_eq!(Some(aliphatic(15)), parsed_atom(&mol, 6));
+        assert_eq!(Some(aliphatic(16)), parsed_atom(&mol, 7));
+        assert_eq!(Some(aliphatic(9)), parsed_atom(&mol, 8));
+        assert_eq!(Some(aliphatic(5)), parsed_atom(&mol, 9));
+    }
+
+    #[test]
+    fn parse_aromatic_heterocycle() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("Cc1ccncc1").unwrap();
+
+        assert_eq!(7, mol.num_atoms());
+        assert_eq!(7, mol.num_bonds());
+        assert_eq!(Some(aliphatic(6)), parsed_atom(&mol, 0));
+        assert_eq!(Some(aromatic(6)), parsed_atom(&mol, 1));
+        assert_eq!(Some(aromatic(7)), parsed_atom(&mol, 4));
+    }
+
+    #[test]
+    fn parse_bracket_atoms() {
+        let parser = SmilesParser::new();
+        // not a valid molecule, just testing the parsing
+        let mol = parser.parse("[13CH4][NH4+][Fe+2][CH3:1][se][O--]").unwrap();
+
+        assert_eq!(
+            Some(&Atom {
+                number: 6,
+                isotope: Some(13),
+                explicit_h: Some(4),
+                ..Default::default()
+            }),
+            mol.atom_with_idx(AtomIdx::new(0))
+        );
+        assert_eq!(
+            Some(&Atom {
+                number: 7,
+                charge: 1,
+                explicit_h: Some(4),
+                ..Default::default()
+            }),
+            mol.atom_with_idx(AtomIdx::new(1))
+        );
+        assert_eq!(
+            Some(&Atom {
+                number: 26,
+                charge: 2,
+                explicit_h: Some(0),
+                ..Default::default()
+            }),
+            mol.atom_with_idx(AtomIdx::new(2))
+        );
+        assert_eq!(
+            Some(&Atom {
+                number: 6,
+                explicit_h: Some(3),
+                map_number: Some(1),
+                ..Default::default()
+            }),
+            mol.atom_with_idx(AtomIdx::new(3))
+        );
+        assert_eq!(
+            Some(&Atom {
+                number: 34,
+                aromatic: true,
+                explicit_h: Some(0),
+                ..Default::default()
+            }),
+            mol.atom_with_idx(AtomIdx::new(4))
+        );
+        assert_eq!(
+            Some(&Atom {
+                number: 8,
+                charge: -2,
+                explicit_h: Some(0),
+                ..Default::default()
+            }),
+            mol.atom_with_idx(AtomIdx::new(5))
+        );
+    }
+
+    #[test]
+    fn strict_mode() {
+        let parser = SmilesParser::with_options(SmilesParserOptions {
+            strict: true,
+            ..Default::default()
+        });
+
+        assert!(parser.parse("[2H]O[O-2]").is_ok());
+        assert_eq!(
+            Err(SmilesError::expected(
+                SmilesErrorKind::UnknownElement("D".to_string()),
+                1,
+                "element symbol"
+            )),
+            parser.parse("[D]O").map(|_| ())
+        );
+        assert_eq!(
+            Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedCharacter('-'),
+                3,
+                "charge count"
+            )),
+            parser.parse("[O--]").map(|_| ())
+        );
+        assert_eq!(
+            Err(SmilesError::expected(
+                SmilesErrorKind::UnexpectedCharacter('9'),
+                4,
+                "charge, atom map number or ']'"
+            )),
+            parser.parse("[CH99]").map(|_| ())
+        );
+        assert!(parser.parse("[CH4]").is_ok());
+        // accepted by default
+        assert!(SmilesParser::new().parse("[D]O[O--][CH99]").is_ok());
+
+        // ring bonds before branches, branches starting with an atom or bond
+        assert!(parser.parse("C1CC(C)(=O)C1").is_ok());
+        assert!(parser.parse("C(.C)C").is_ok());
+        for (smiles, position, c, expected) in [
+            ("C((C))", 2, '(', "atom"),
+            ("C(1C)CC1", 2, '1', "atom"),
+            ("C(=1C)CC1", 2, '=', "atom"),
+            ("C(C)1CC1", 4, '1', "atom or branch"),
+            ("C(C)%10CC%10", 4, '%', "atom or branch"),
+            ("C(C)=1CC1", 4, '=', "atom"),
+        ] {
+            assert_eq!(
+                Err(SmilesError::expected(
+                    SmilesErrorKind::UnexpectedCharacter(c),
+                    position,
+                    expected
+                )),
+                parser.parse(smiles).map(|_| ()),
+                "{}",
+                smiles
+            );
+            assert!(SmilesParser::new().parse(smiles).is_ok(), "{}", smiles);
+        }
+    }
+
+    #[test]
+    fn atom_spans() {
+        let parser = SmilesParser::new();
+        let (mol, spans) = parser.parse_with_spans("Cl[13CH3].c1ccccc1").unwrap();
+        assert_eq!(mol.num_atoms(), spans.len());
+        assert_eq!(vec![0..2, 2..9, 10..11, 12..13], spans[..4].to_vec());
+        assert_eq!(
+            SmilesErrorKind::DanglingBond,
+            parser.parse_with_spans("C=").unwrap_err().kind
+        );
+    }
+
+    #[test]
+    fn source_spans() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C1=CC(=O)CC=1C%10.[NH3+]%10").unwrap();
+        let atom_span = |index| mol.atom_span(AtomIdx::new(index));
+        let bond_span = |index| mol.bond_span(BondIdx::new(index));
+        assert_eq!(Some(0..1), atom_span(0));
+        assert_eq!(Some(9..10), atom_span(4));
+        assert_eq!(Some(18..24), atom_span(7));
+        assert_eq!(None, atom_span(8));
+
+        // symbols, and ring bonds where they close
+        assert_eq!(Some(2..3), bond_span(0));
+        assert_eq!(None, bond_span(1));
+        assert_eq!(Some(6..7), bond_span(2));
+        assert_eq!(Some(11..13), bond_span(5));
+        assert_eq!(Some(24..27), bond_span(7));
+
+        // in bytes, after characters that lenient parsing skips
+        let smiles = "Cé(CO€C=C";
+        let (lenient, _) = parser.parse_lenient(smiles);
+        let atoms: Vec<&str> = (0..lenient.num_atoms())
+            .map(|index| &smiles[lenient.atom_span(AtomIdx::new(index)).unwrap()])
+            .collect();
+        assert_eq!(vec!["C", "C", "O", "C", "C"], atoms);
+        assert_eq!(Some(4..5), lenient.atom_span(AtomIdx::new(1)));
+        assert_eq!(Some(10..11), lenient.bond_span(BondIdx::new(3)));
+        assert_eq!("=", &smiles[10..11]);
+
+        // the same atom written elsewhere
+        let propane = parser.parse("CCC").unwrap();
+        assert_eq!(
+            mol.atom_with_idx(AtomIdx::new(6)),
+            propane.atom_with_idx(AtomIdx::new(1))
+        );
+    }
+
+    #[test]
+    fn lenient() {
+        let parser = SmilesParser::new();
+        let parse = |smiles: &str| {
+            let (mol, diagnostics) = parser.parse_lenient(smiles);
+            let kinds: Vec<_> = diagnostics
+                .into_iter()
+                .map(|diagnostic| match diagnostic.kind {
+                    DiagnosticKind::Parse(kind) => (kind, diagnostic.span.start),
+                    kind => panic!("{:?}", kind),
+                })
+                .collect();
+            (mol.to_smiles(), kinds)
+        };
+
+        assert_eq!(("CCO".to_string(), vec![]), parse("CCO"));
+        // skipped tokens
+        assert_eq!(
+            (
+                "CCO".to_string(),
+                vec![
+                    (SmilesErrorKind::UnknownElement("Xy".to_string()), 3),
+                    (SmilesErrorKind::UnexpectedCharacter('?'), 7)
+                ]
+            ),
+            parse("CC[Xy]O?")
+        );
+        assert_eq!(
+            (
+                "C.CO".to_string(),
+                vec![
+                    (SmilesErrorKind::UnbalancedParenthesis, 1),
+                    (SmilesErrorKind::MissingAtom, 3)
+                ]
+            ),
+            parse("C).(C)O")
+        );
+        // patched: unclosed rings and branches, dangling bonds
+        assert_eq!(
+            (
+                "CCCC".to_string(),
+                vec![
+                    (SmilesErrorKind::UnbalancedParenthesis, 1),
+                    (SmilesErrorKind::UnclosedRing(1), 4),
+                    (SmilesErrorKind::DanglingBond, 6)
+                ]
+            ),
+            parse("C(CC1C=")
+        );
+        assert_eq!(
+            (
+                "C1CC1C".to_string(),
+                vec![(SmilesErrorKind::DuplicateBond(2), 7)]
+            ),
+            parse("C1CC12C2")
+        );
+        // a ring bond of a stereocenter missing, its chirality is unknown
+        assert_eq!(
+            (
+                "[CH](F)(Cl)Br".to_string(),
+                vec![(SmilesErrorKind::UnclosedRing(1), 5)]
+            ),
+            parse("[C@H]1(F)(Cl)Br")
+        );
+        assert_eq!(("".to_string(), vec![]), parse(""));
+    }
+
+    #[test]
+    fn aromatic_bonds_outside_rings() {
+        let parser = SmilesParser::new();
+        let biphenyl = parser.parse("c1ccccc1c1ccccc1").unwrap();
+        assert_eq!(Some(bond(5, 6)), parsed_bond(&biphenyl, 6));
+        assert_eq!(
+            12,
+            biphenyl
+                .graph
+                .edge_weights()
+                .filter(|b| b.is_aromatic())
+                .count()
+        );
+        // unless written
+        let mol = parser.parse("c1ccccc1:c1ccccc1").unwrap();
+        assert!(mol.graph.edge_weights().all(Bond::is_aromatic));
+        // the ring closure makes the chain bond a ring bond
+        let mol = parser.parse("c1cccc1").unwrap();
+        assert!(mol.graph.edge_weights().all(Bond::is_aromatic));
+    }
+
+    #[test]
+    fn aromatic_outside_rings() {
+        let parser = SmilesParser::with_options(SmilesParserOptions {
+            allow_aromatic_outside_rings: false,
+            ..Default::default()
+        });
+
+        assert!(parser.parse("c1ccccc1C").is_ok());
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::AromaticOutsideRing, 8)),
+            parser.parse("c1ccccc1[nH]").map(|_| ())
+        );
+        assert!(SmilesParser::new().parse("cc").is_ok());
+    }
+
+    #[test]
+    fn kekulize_option() {
+        let parser = SmilesParser::with_options(SmilesParserOptions {
+            kekulize: true,
+            ..Default::default()
+        });
+
+        let mol = parser.parse("c1ccccc1").unwrap();
+        assert!(mol.graph.node_weights().all(|atom| !atom.aromatic));
+        assert_eq!(
+            SmilesErrorKind::Kekulize,
+            parser.parse("c1cccc1").unwrap_err().kind
+        );
+    }
+
+    #[test]
+    fn max_ring_number() {
+        let parser = SmilesParser::with_options(SmilesParserOptions {
+            max_ring_number: 9,
+            ..Default::default()
+        });
+
+        let too_high = SmilesErrorKind::RingNumberTooHigh { number: 10, max: 9 };
+        assert!(parser.parse("C9CC9").is_ok());
+        assert_eq!(
+            Err(SmilesError::new(too_high.clone(), 1)),
+            parser.parse("C%10CC%10").map(|_| ())
+        );
+        assert_eq!(
+            Err(SmilesError::new(too_high, 1)),
+            parser.parse("C%(10)CC%(10)").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn extended_ring_numbers() {
+        let parser = SmilesParser::with_options(SmilesParserOptions {
+            max_ring_number: 999,
+            ..Default::default()
+        });
+        let mol = parser.parse("C%(123)CCC%(123)").unwrap();
+        assert_eq!(4, mol.graph.edge_count());
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::UnclosedRing(999), 1)),
+            parser.parse("C%(999)CC").map(|_| ())
+        );
+
+        // up to 99 by default, where `%(5)` is the same ring as `5`
+        let parser = SmilesParser::new();
+        let ring = parser.parse("C%(5)CC5").unwrap();
+        assert!(ring.is_same_mol(&parser.parse("C1CC1").unwrap()));
+        let error = parser.parse("C%(123)CCC%(123)").unwrap_err();
+        assert_eq!(
+            SmilesError::new(
+                SmilesErrorKind::RingNumberTooHigh {
+                    number: 123,
+                    max: 99
+                },
+                1
+            ),
+            error
+        );
+        assert_eq!(
+            "ring number 123 is past the highest accepted, 99 (see \
+             SmilesParserOptions::max_ring_number) at position 1",
+            error.to_string()
+        );
+
+        let strict = SmilesParser::with_options(SmilesParserOptions {
+            strict: true,
+            max_ring_number: 999,
+            ..Default::default()
+        });
+        let error = strict.parse("C%(123)CCC%(123)").unwrap_err();
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::ExtendedRingNumber, 1),
+            error
+        );
+        assert_eq!(
+            "`%(nnn)` ring numbers are not OpenSMILES at position 1",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn parse_wildcards() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("*C[*:1]").unwrap();
+
+        assert_eq!(
+            Some(&Atom {
+                number: WILDCARD,
+                ..Default::default()
+            }),
+            mol.atom_with_idx(AtomIdx::new(0))
+        );
+        assert_eq!(Some(0), mol.implicit_h_count(AtomIdx::new(0)));
+        assert_eq!(Some(2), mol.implicit_h_count(AtomIdx::new(1)));
+        assert_eq!(
+            Some(&Atom {
+                number: WILDCARD,
+                explicit_h: Some(0),
+                map_number: Some(1),
+                ..Default::default()
+            }),
+            mol.atom_with_idx(AtomIdx::new(2))
+        );
+    }
+
+    #[test]
+    fn parse_hydrogen_isotope_shorthands() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("[D]C([T])[Ds]").unwrap();
+
+        let isotope = |idx| {
+            mol.atom_with_idx(AtomIdx::new(idx))
+                .map(|atom| (atom.number, atom.isotope))
+        };
+        assert_eq!(Some((1, Some(2))), isotope(0));
+        assert_eq!(Some((1, Some(3))), isotope(2));
+        assert_eq!(Some((110, None)), isotope(3));
+        assert_eq!("[2H]C([3H])[Ds]", mol.to_smiles());
+    }
+
+    #[test]
+    fn parse_bond_orders() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C=CC#N").unwrap();
+
+        assert_eq!(4, mol.num_atoms());
+        assert_eq!(3, mol.num_bonds());
+        assert_eq!(
+            Some(bond_with_order(0, 1, BondOrder::Double)),
+            parsed_bond(&mol, 0)
+        );
+        assert_eq!(Some(bond(1, 2)), parsed_bond(&mol, 1));
+        assert_eq!(
+            Some(bond_with_order(2, 3, BondOrder::Triple)),
+            parsed_bond(&mol, 2)
+        );
+    }
+
+    #[test]
+    fn parse_bond_order_in_branch() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("CC(=O)O").unwrap();
+
+        assert_eq!(Some(bond(0, 1)), parsed_bond(&mol, 0));
+        assert_eq!(
+            Some(bond_with_order(1, 2, BondOrder::Double)),
+            parsed_bond(&mol, 1)
+        );
+        assert_eq!(Some(bond(1, 3)), parsed_bond(&mol, 2));
+    }
+
+    /// The bonds as atoms and order, in the order they're added.
+    fn bond_list(mol: &Mol) -> Vec<(usize, usize, BondOrder)> {
+        mol.graph
+            .edge_indices()
+            .map(|edge| {
+                // unwrap: an edge of the graph
+                let (a, b) = mol.graph.edge_endpoints(edge).unwrap();
+                (a.index(), b.index(), mol.graph[edge].order)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_nested_branches() {
+        use BondOrder::{Double, Single};
+        let parser = SmilesParser::new();
+        // two branches on an atom, the first with its own
+        let mol = parser.parse("CC(C(=O)O)(N)C").unwrap();
+        assert_eq!(
+            vec![
+                (0, 1, Single),
+                (1, 2, Single),
+                (2, 3, Double),
+                (2, 4, Single),
+                (1, 5, Single),
+                (1, 6, Single),
+            ],
+            bond_list(&mol)
+        );
+        // bond symbols right after `(`, two levels deep
+        let mol = parser.parse("CC(=C(C(=O)O)C)C").unwrap();
+        assert_eq!(
+            vec![
+                (0, 1, Single),
+                (1, 2, Double),
+                (2, 3, Single),
+                (3, 4, Double),
+                (3, 5, Single),
+                (2, 6, Single),
+                (1, 7, Single),
+            ],
+            bond_list(&mol)
+        );
+        // a branch in a branch, directly
+        let mol = parser.parse("C((C)C)O").unwrap();
+        assert_eq!(
+            vec![(0, 1, Single), (0, 2, Single), (0, 3, Single)],
+            bond_list(&mol)
+        );
+        // a fragment in a branch: the atom after it is bonded to the branch's atom
+        let mol = parser.parse("C(.C)C").unwrap();
+        assert_eq!(vec![(0, 2, Single)], bond_list(&mol));
+    }
+
+    #[test]
+    fn parse_ring_bonds_in_branches() {
+        use BondOrder::{Double, Single};
+        let parser = SmilesParser::new();
+        // opened on the atom before the branch, closed in it
+        let mol = parser.parse("C1(CC1)C").unwrap();
+        assert_eq!(
+            vec![
+                (0, 1, Single),
+                (1, 2, Single),
+                (0, 2, Single),
+                (0, 3, Single)
+            ],
+            bond_list(&mol)
+        );
+        // opened two branches deep, closed in the main chain
+        let mol = parser.parse("C(C(C1))CC1").unwrap();
+        assert_eq!(
+            vec![
+                (0, 1, Single),
+                (1, 2, Single),
+                (0, 3, Single),
+                (3, 4, Single),
+                (2, 4, Single),
+            ],
+            bond_list(&mol)
+        );
+        // with the bond symbol in the branch
+        let mol = parser.parse("C(C=1)CC1").unwrap();
+        assert_eq!(
+            vec![
+                (0, 1, Single),
+                (0, 2, Single),
+                (2, 3, Single),
+                (1, 3, Double)
+            ],
+            bond_list(&mol)
+        );
+        // the same number reused in sibling branches
+        let mol = parser.parse("C(C1CC1)(C1CC1)").unwrap();
+        assert_eq!((7, 8), (mol.num_atoms(), mol.num_bonds()));
+    }
+
+    #[test]
+    fn error_empty_branch() {
+        for (smiles, position) in [("C()C", 2), ("C(C)()C", 5), ("C(1)CC1", 3), ("C(=1)CC1", 4)] {
+            assert_eq!(
+                SmilesError::new(SmilesErrorKind::MissingAtom, position),
+                parse_error(smiles),
+                "{}",
+                smiles
+            );
+        }
+    }
+
+    #[test]
+    fn parse_bond_order_on_ring_closure() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C=1CCCCC=1").unwrap();
+
+        assert_eq!(6, mol.num_atoms());
+        assert_eq!(6, mol.num_bonds());
+        assert_eq!(Some(bond(0, 1)), parsed_bond(&mol, 0));
+        assert_eq!(
+            Some(bond_with_order(0, 5, BondOrder::Double)),
+            parsed_bond(&mol, 5)
+        );
+
+        // symbol only on the closing end
+        let mol = parser.parse("C1CCCCC=1").unwrap();
+        assert_eq!(
+            Some(bond_with_order(0, 5, BondOrder::Double)),
+            parsed_bond(&mol, 5)
+        );
+    }
+
+    #[test]
+    fn parse_explicit_aromatic_and_quadruple_bonds() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C:C$C").unwrap();
+
+        assert_eq!(
+            Some(bond_with_order(0, 1, BondOrder::Aromatic)),
+            parsed_bond(&mol, 0)
+        );
+        assert_eq!(
+            Some(bond_with_order(1, 2, BondOrder::Quadruple)),
+            parsed_bond(&mol, 1)
+        );
+    }
+
+    fn parse_error(smiles: &str) -> SmilesError {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap_err()
+    }
+
+    #[test]
+    fn parse_empty() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("").unwrap();
+
+        assert_eq!(0, mol.num_atoms());
+    }
+
+    #[test]
+    fn error_unexpected_character() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::UnexpectedCharacter('X'), 2),
+            parse_error("CCX")
+        );
+    }
+
+    #[test]
+    fn error_unclosed_ring() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::UnclosedRing(2), 4),
+            parse_error("C1CC2C1")
+        );
+    }
+
+    #[test]
+    fn error_ring_bond_to_self() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::RingBondToSelf(1), 2),
+            parse_error("C11")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::RingBondToSelf(1), 6),
+            parse_error("CC1(O)1")
+        );
+        // syntax errors come first
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::UnbalancedParenthesis, 3),
+            parse_error("C11(")
+        );
+    }
+
+    #[test]
+    fn error_duplicate_bond() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::DuplicateBond(1), 3),
+            parse_error("C1C1")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::DuplicateBond(2), 6),
+            parse_error("C12CC12")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::DuplicateBond(1), 5),
+            parse_error("C(C1)1")
+        );
+        // bonded through the ring, not twice
+        assert!(SmilesParser::new().parse("C1CC1").is_ok());
+    }
+
+    #[test]
+    fn fused_ring_systems() {
+        let parser = SmilesParser::new();
+        // all two digit numbers open at once, closed in reverse
+        let open: String = (10..100).map(|ring| format!("C%{ring}")).collect();
+        let close: String = (10..100).rev().map(|ring| format!("C%{ring}")).collect();
+        let mol = parser.parse(&format!("{open}C{close}")).unwrap();
+        assert_eq!((181, 270), (mol.num_atoms(), mol.num_bonds()));
+        // digits reused along a ladder of 301 fused rings, and closed and reopened on
+        // the same atom
+        let ladder = format!("C1C{}CC1", "2CC1CC2".repeat(200));
+        let mol = parser.parse(&ladder).unwrap();
+        assert_eq!((804, 1104), (mol.num_atoms(), mol.num_bonds()));
+        // a strip of 400 triangles, every atom opens or closes a ring
+        let mol = parser.parse(&"C1C2C1C2".repeat(200)).unwrap();
+        assert_eq!((800, 1199), (mol.num_atoms(), mol.num_bonds()));
+
+        // numbers above 99 from an AST built by hand
+        let mut ast = parser.parse_ast("C1CC1").unwrap();
+        for chain_atom in &mut ast.chain {
+            for attachment in &mut chain_atom.attachments {
+                if let crate::ast::Attachment::RingBond { number, .. } = attachment {
+                    *number = 200;
+                }
+            }
+        }
+        assert_eq!(3, parser.build(&ast).unwrap().num_bonds());
+    }
+
+    #[test]
+    fn error_ring_bond_conflict() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::RingBondConflict(1), 8),
+            parse_error("C=1CCCC#1")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::RingBondConflict(12), 19),
+            parse_error("C$%12CCC%12C=%12CC-%12")
+        );
+        // the same order at both ends, or a direction on single bonds
+        let parser = SmilesParser::new();
+        for smiles in ["C=1CCCC=1", "C$1CCC$1", "C-1CCCC/1", "C/1CCCC\\1"] {
+            assert!(parser.parse(smiles).is_ok(), "{}", smiles);
+        }
+        // the same direction at both ends goes opposite ways
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::RingBondConflict(1), 10),
+            parse_error("F/C=C/1CC/1")
+        );
+    }
+
+    #[test]
+    fn parse_quadruple_ring_bond() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("[Rh-]$1[Rh-]CC1").unwrap();
+
+        assert_eq!(
+            Some(bond_with_order(0, 3, BondOrder::Quadruple)),
+            parsed_bond(&mol, 3)
+        );
+        assert_eq!("[Rh-]$1[Rh-]CC1", mol.to_smiles());
+    }
+
+    #[test]
+    fn error_unbalanced_parenthesis() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::UnbalancedParenthesis, 1),
+            parse_error("C(C")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::UnbalancedParenthesis, 2),
+            parse_error("CC)C")
+        );
+    }
+
+    #[test]
+    fn error_missing_atom() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::MissingAtom, 0),
+            parse_error("1CC1")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::MissingAtom, 0),
+            parse_error("(C)C")
+        );
+    }
+
+    #[test]
+    fn error_dangling_bond() {
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::DanglingBond, 2),
+            parse_error("CC=")
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::DanglingBond, 3),
+            parse_error("CC(=)C")
+        );
+    }
+
+    #[test]
+    fn error_unknown_element() {
+        assert_eq!(
+            SmilesError::expected(
+                SmilesErrorKind::UnknownElement("Xy".to_string()),
+                1,
+                "element symbol"
+            ),
+            parse_error("[Xy]")
+        );
+    }
+
+    #[test]
+    fn error_unclosed_bracket() {
+        assert_eq!(
+            SmilesError::expected(SmilesErrorKind::UnexpectedEnd, 5, "']'"),
+            parse_error("C[NH4")
+        );
+        assert_eq!(
+            SmilesError::expected(SmilesErrorKind::UnexpectedCharacter('C'), 3, "']'"),
+            parse_error("[NHC]")
+        );
+    }
+
+    #[test]
+    fn error_display() {
+        assert_eq!(
+            "unexpected end of input at position 5, expected ']'",
+            parse_error("C[NH4").to_string()
+        );
+    }
+
+    #[test]
+    fn error_annotated() {
+        assert_eq!(
+            "c1ccc(c1\n     ^ unbalanced parenthesis at position 5",
+            parse_error("c1ccc(c1").annotate("c1ccc(c1").to_string()
+        );
+        assert_eq!(
+            "CC)C\n  ^ unbalanced parenthesis at position 2",
+            parse_error("CC)C").annotate("CC)C").to_string()
+        );
+    }
+
+    #[test]
+    fn parse_ring_number_zero() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C0CC0").unwrap();
+
+        assert_eq!(3, mol.num_atoms());
+        assert_eq!(3, mol.num_bonds());
+    }
+
+    #[test]
+    fn parse_two_digit_ring_numbers() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C%10CC%11CC%11C%10").unwrap();
+
+        assert_eq!(6, mol.num_atoms());
+        assert_eq!(7, mol.num_bonds());
+        assert_eq!(Some(bond(2, 4)), parsed_bond(&mol, 4));
+        assert_eq!(Some(bond(0, 5)), parsed_bond(&mol, 6));
+    }
+
+    #[test]
+    fn parse_ring_number_reused_after_close() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C1CC1C1CC1").unwrap();
+
+        assert_eq!(6, mol.num_atoms());
+        assert_eq!(7, mol.num_bonds());
+        assert_eq!(Some(bond(0, 2)), parsed_bond(&mol, 2));
+        assert_eq!(Some(bond(3, 5)), parsed_bond(&mol, 6));
+    }
+
+    #[test]
+    fn parse_ring_number_with_bond() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C=%12CCC%12").unwrap();
+
+        assert_eq!(
+            Some(bond_with_order(0, 3, BondOrder::Double)),
+            parsed_bond(&mol, 3)
+        );
+    }
+
+    #[test]
+    fn error_incomplete_two_digit_ring_number() {
+        assert_eq!(
+            SmilesError::expected(
+                SmilesErrorKind::UnexpectedCharacter('C'),
+                3,
+                "two digit ring number"
+            ),
+            parse_error("C%1CC")
+        );
+        assert_eq!(
+            SmilesError::expected(SmilesErrorKind::UnexpectedEnd, 2, "two digit ring number"),
+            parse_error("C%")
+        );
+    }
+
+    #[test]
+    fn parse_chirality() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("[C@@](F)(Cl)(Br)I").unwrap();
+        let atom = mol.atom_with_idx(AtomIdx::new(0)).unwrap();
+
+        assert_eq!(Some(Chirality::Clockwise), atom.chirality);
+        assert_eq!(atoms(&[1, 2, 3, 4]), atom.chiral_neighbors);
+
+        let mol = parser.parse("F[C@](Cl)(Br)I").unwrap();
+        let atom = mol.atom_with_idx(AtomIdx::new(1)).unwrap();
+
+        assert_eq!(Some(Chirality::Anticlockwise), atom.chirality);
+        assert_eq!(atoms(&[0, 2, 3, 4]), atom.chiral_neighbors);
+    }
+
+    #[test]
+    fn parse_chirality_with_implicit_hydrogen() {
+        let parser = SmilesParser::new();
+        // the hydrogen is second in the written order, it's stored as first
+        let mol = parser.parse("N[C@@H](C)C(=O)O").unwrap();
+        let atom = mol.atom_with_idx(AtomIdx::new(1)).unwrap();
+
+        assert_eq!(Some(Chirality::Anticlockwise), atom.chirality);
+        assert_eq!(atoms(&[0, 2, 3]), atom.chiral_neighbors);
+        assert_eq!(Some(1), atom.explicit_h);
+
+        let mol = parser.parse("[C@@H](N)(C)C(=O)O").unwrap();
+        let atom = mol.atom_with_idx(AtomIdx::new(0)).unwrap();
+
+        assert_eq!(Some(Chirality::Clockwise), atom.chirality);
+        assert_eq!(atoms(&[1, 2, 3]), atom.chiral_neighbors);
+    }
+
+    #[test]
+    fn parse_chirality_with_ring_bonds() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C[C@H]12CCC2CC1").unwrap();
+        let atom = mol.atom_with_idx(AtomIdx::new(1)).unwrap();
+
+        // neighbors in the position of the ring numbers, not in the order the rings close
+        assert_eq!(atoms(&[0, 6, 4, 2]), atom.chiral_neighbors);
+    }
+
+    #[test]
+    fn parse_explicit_tetrahedral_class() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("F[C@TH2](Cl)(Br)I").unwrap();
+
+        assert_eq!(
+            Some(Chirality::Clockwise),
+            mol.atom_with_idx(AtomIdx::new(1)).unwrap().chirality
+        );
+    }
+
+    #[test]
+    fn parse_bond_directions() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("F/C=C\\F").unwrap();
+
+        assert_eq!(
+            Some(BondDirection::Up),
+            mol.bond_with_idx(BondIdx::new(0)).unwrap().direction
+        );
+        assert_eq!(None, mol.bond_with_idx(BondIdx::new(1)).unwrap().direction);
+        assert_eq!(
+            Some(BondDirection::Down),
+            mol.bond_with_idx(BondIdx::new(2)).unwrap().direction
+        );
+    }
+
+    #[test]
+    fn parse_double_bond_stereo() {
+        let parser = SmilesParser::new();
+        let trans = BondStereo {
+            config: DoubleBondConfig::Trans,
+            start_neighbor: AtomIdx::new(0),
+            end_neighbor: AtomIdx::new(3),
+        };
+        let cis = BondStereo {
+            config: DoubleBondConfig::Cis,
+            ..trans
+        };
+
+        assert_eq!(
+            Some(trans),
+            parser
+                .parse("F/C=C/F")
+                .unwrap()
+                .bond_with_idx(BondIdx::new(1))
+                .unwrap()
+                .stereo
+        );
+        assert_eq!(
+            Some(trans),
+            parser
+                .parse("F\\C=C\\F")
+                .unwrap()
+                .bond_with_idx(BondIdx::new(1))
+                .unwrap()
+                .stereo
+        );
+        assert_eq!(
+            Some(cis),
+            parser
+                .parse("F/C=C\\F")
+                .unwrap()
+                .bond_with_idx(BondIdx::new(1))
+                .unwrap()
+                .stereo
+        );
+        assert_eq!(
+            Some(cis),
+            parser
+                .parse("F\\C=C/F")
+                .unwrap()
+                .bond_with_idx(BondIdx::new(1))
+                .unwrap()
+                .stereo
+        );
+    }
+
+    #[test]
+    fn parse_double_bond_stereo_in_branch() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("C(\\F)=C/F").unwrap();
+
+        assert_eq!(
+            Some(BondStereo {
+                config: DoubleBondConfig::Trans,
+                start_neighbor: AtomIdx::new(1),
+                end_neighbor: AtomIdx::new(3),
+            }),
+            mol.bond_with_idx(BondIdx::new(1)).unwrap().stereo
+        );
+    }
+
+    #[test]
+    fn parse_double_bond_stereo_with_ring_closure() {
+        let parser = SmilesParser::new();
+        // the direction is written at the closing end of the ring bond
+        let mol = parser.parse("C1CCCCCC/C=C/1").unwrap();
+
+        assert_eq!(
+            Some(BondStereo {
+                config: DoubleBondConfig::Trans,
+                start_neighbor: AtomIdx::new(6),
+                end_neighbor: AtomIdx::new(0),
+            }),
+            mol.bond_with_idx(BondIdx::new(7)).unwrap().stereo
+        );
+    }
+
+    #[test]
+    fn parse_incomplete_double_bond_stereo() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("F/C=CF").unwrap();
+
+        assert_eq!(None, mol.bond_with_idx(BondIdx::new(1)).unwrap().stereo);
+    }
+
+    #[test]
+    fn parse_sets_implicit_hydrogens() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("CC(=O)[O-]").unwrap();
+
+        assert_eq!(3, mol.atom_with_idx(AtomIdx::new(0)).unwrap().implicit_h);
+        assert_eq!(0, mol.atom_with_idx(AtomIdx::new(1)).unwrap().implicit_h);
+        assert_eq!(0, mol.atom_with_idx(AtomIdx::new(3)).unwrap().implicit_h);
+        assert_eq!(
+            3,
+            mol.atom_with_idx(AtomIdx::new(0)).unwrap().total_num_hs()
+        );
+
+        let mol = parser.parse("C[NH3+]").unwrap();
+        assert_eq!(0, mol.atom_with_idx(AtomIdx::new(1)).unwrap().implicit_h);
+        assert_eq!(
+            3,
+            mol.atom_with_idx(AtomIdx::new(1)).unwrap().total_num_hs()
+        );
+        assert_eq!(Some(0), mol.implicit_h_count(AtomIdx::new(1)));
+        assert_eq!(None, mol.implicit_h_count(AtomIdx::new(2)));
+    }
+
+    #[test]
+    fn parse_many() {
+        let parser = SmilesParser::new();
+        let results = parser.parse_many(&["CCO", "C1CC", "c1ccccc1"]);
+
+        assert_eq!(3, results.len());
+        assert_eq!(3, results[0].as_ref().unwrap().num_atoms());
+        assert!(results[1].is_err());
+        assert_eq!(6, results[2].as_ref().unwrap().num_atoms());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn par_parse_many_keeps_order() {
+        let parser = SmilesParser::new();
+        let inputs: Vec<String> = (0..2000)
+            .map(|i| match i % 3 {
+                0 => "C".repeat(i % 50 + 1),
+                1 => format!("C1{}", "C".repeat(i % 7)),
+                _ => format!("{}O", "C".repeat(i % 10)),
+            })
+            .collect();
+        let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+
+        let sequential = parser.parse_many(&inputs);
+        let parallel = parser.par_parse_many(&inputs);
+
+        assert_eq!(sequential.len(), parallel.len());
+        for (a, b) in sequential.iter().zip(&parallel) {
+            match (a, b) {
+                (Ok(a), Ok(b)) => assert_eq!(a.to_smiles(), b.to_smiles()),
+                (Err(a), Err(b)) => assert_eq!(a, b),
+                _ => panic!("results differ: {:?} {:?}", a, b),
+            }
+        }
+        assert!(parser.par_parse_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn parse_disconnected() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("[Na+].[Cl-]").unwrap();
+
+        assert_eq!(2, mol.num_atoms());
+        assert_eq!(0, mol.num_bonds());
+
+        let mol = parser.parse("CCO.O").unwrap();
+        assert_eq!(4, mol.num_atoms());
+        assert_eq!(2, mol.num_bonds());
+        assert_eq!(
+            2,
+            mol.atom_with_idx(AtomIdx::new(3)).unwrap().total_num_hs()
+        );
+
+        // a fragment can start inside a branch
+        let mol = parser.parse("C(.O)N").unwrap();
+        assert_eq!(1, mol.num_bonds());
+        assert_eq!(Some(bond(0, 2)), parsed_bond(&mol, 0));
+    }
+
+    #[test]
+    fn parse_disconnected_errors() {
+        let parser = SmilesParser::new();
+
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::MissingAtom, 0),
+            parser.parse(".C").unwrap_err()
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::MissingAtom, 2),
+            parser.parse("C..C").unwrap_err()
+        );
+        assert_eq!(
+            SmilesError::expected(SmilesErrorKind::UnexpectedEnd, 2, "atom"),
+            parser.parse("C.").unwrap_err()
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::DanglingBond, 1),
+            parser.parse("C=.C").unwrap_err()
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::MissingAtom, 2),
+            parser.parse("C.1CC1").unwrap_err()
+        );
+        assert_eq!(
+            SmilesError::new(SmilesErrorKind::MissingAtom, 4),
+            parser.parse("C(C.)C").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn parse_map_numbers() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("[CH3:1][CH2:2]O[CH3:1]").unwrap();
+
+        assert_eq!(
+            Some(2),
+            mol.atom_with_idx(AtomIdx::new(1)).unwrap().map_number
+        );
+        assert_eq!(None, mol.atom_with_idx(AtomIdx::new(2)).unwrap().map_number);
+        assert_eq!(atoms(&[0, 3]), mol.atoms_with_map_number(1));
+        assert_eq!(atoms(&[1]), mol.atoms_with_map_number(2));
+        assert!(mol.atoms_with_map_number(3).is_empty());
+        assert_eq!("[CH3:1][CH2:2]O[CH3:1]", mol.to_smiles());
+
+        // up to u32::MAX
+        let mol = parser.parse("[C:4294967295]").unwrap();
+        assert_eq!(
+            Some(u32::MAX),
+            mol.atom_with_idx(AtomIdx::new(0)).unwrap().map_number
+        );
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::NumberOutOfRange, 12)),
+            parser.parse("[C:4294967296]").map(|_| ())
+        );
+        assert_eq!(
+            Err(SmilesError::new(SmilesErrorKind::NumberOutOfRange, 13)),
+            parser.parse("[C:99999999999]").map(|_| ())
+        );
+        assert_eq!(
+            SmilesError::expected(
+                SmilesErrorKind::UnexpectedCharacter(']'),
+                5,
+                "atom map number"
+            ),
+            parser.parse("[CH3:]").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn parse_matches_build() {
+        // parse builds the graph while reading, build from the AST
+        let parser = SmilesParser::new();
+        for smiles in [
+            "CC(=O)Oc1ccccc1C(=O)O",
+            "N[C@@H](C)C(=O)O",
+            "[C@@]12(F)CC1.C2",
+            "F/C=C/1.C1",
+            "C(C)(C)1CC1",
+            "[NH4+].[Cl-]",
+        ] {
+            let ast = parser.parse_ast(smiles).unwrap();
+            assert_eq!(
+                format!("{:?}", parser.build(&ast).unwrap()),
+                format!("{:?}", parser.parse(smiles).unwrap()),
+                "{}",
+                smiles
+            );
+        }
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let parser = SmilesParser::new();
+        let library = [
+            "CC(=O)Oc1ccccc1C(=O)O",
+            "N[C@@H](C)C(=O)O",
+            "F/C=C/F",
+            "[13CH3:1][NH3+]",
+            "C1CC[C@]2(F)CCCC[C@@H]2C1",
+            "CN1C=NC2=C1C(=O)N(C(=O)N2C)C",
+        ];
+        let expected: Vec<String> = library
+            .iter()
+            .map(|smiles| parser.parse(smiles).unwrap().to_canonical_smiles())
+            .collect();
+
+        std::thread::scope(|scope| {
+            for thread in 0..16 {
+                let (parser, expected) = (&parser, &expected);
+                scope.spawn(move || {
+                    for round in 0..200 {
+                        let i = (thread + round) % library.len();
+                        let mol = parser.parse(library[i]).unwrap();
+                        assert_eq!(expected[i], mol.to_canonical_smiles());
+                        assert!(parser.parse("C1CC(").is_err());
+                    }
+                });
+            }
+        });
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(smiles in any::<String>()) {
+            let _ = SmilesParser::new().parse(&smiles);
+            let _ = SmilesParser::new().parse_lenient(&smiles);
+        }
+
+        #[test]
+        fn never_panics_on_smiles_like(smiles in smiles_like()) {
+            for options in [
+                SmilesParserOptions::default(),
+                SmilesParserOptions {
+                    strict: true,
+                    kekulize: true,
+                    allow_aromatic_outside_rings: false,
+                    max_ring_number: 9,
+                    max_branch_depth: 3,
+                },
+            ] {
+                let _ = SmilesParser::with_options(options).parse(&smiles);
+            }
+        }
+
+        #[test]
+        fn lenient_agrees(smiles in smiles_like()) {
+            let parser = SmilesParser::new();
+            let (mol, diagnostics) = parser.parse_lenient(&smiles);
+            match parser.parse(&smiles) {
+                Ok(parsed) => {
+                    prop_assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+                    prop_assert_eq!(parsed.to_smiles(), mol.to_smiles());
+                }
+                Err(error) => {
+                    // not necessarily first: the parser reports the end of the input last
+                    prop_assert!(
+                        diagnostics.iter().any(|d| d.span.start == error.position),
+                        "{:?} {:?}", error, diagnostics
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Many molecules in a few flat arrays, for libraries too large to keep as [`Mol`]s.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{NodeIndex, UnGraph};
+
+use crate::types::{Atom, AtomIdx, Bond, BondDirection, BondOrder, BondStereo, Chirality, Mol};
+
+const AROMATIC: u8 = 1;
+// explicit_h is set, e.g. for a bracket atom
+const EXPLICIT_H: u8 = 2;
+
+/// Molecules stored column by column: one array per atom property and per bond property,
+/// shared by all the molecules. An atom takes 5 bytes and a bond 9, plus what few atoms
+/// and bonds have (isotopes, atom maps, stereo), where a [`Mol`] takes tens of bytes per
+/// atom and bond and allocates for each molecule.
+///
+/// Molecules are identified by the order they're added, from 0, and are converted back
+/// to [`Mol`] with [`get`](Self::get).
+#[derive(Debug, Clone)]
+pub struct MolStore {
+    // molecule i has the atoms atom_starts[i]..atom_starts[i + 1], same for bonds
+    atom_starts: Vec<usize>,
+    bond_starts: Vec<usize>,
+    numbers: Vec<u8>,
+    charges: Vec<i8>,
+    implicit_h: Vec<u8>,
+    explicit_h: Vec<u8>,
+    flags: Vec<u8>,
+    // atom indices within the molecule, first the one the bond was written from
+    bond_atoms: Vec<[u32; 2]>,
+    bond_orders: Vec<BondOrder>,
+    // by index in the store, for the atoms and bonds that have them
+    atom_extras: Vec<(usize, AtomExtras)>,
+    bond_extras: Vec<(usize, BondExtras)>,
+}
+
+/// What most atoms don't have.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct AtomExtras {
+    // atomic numbers that don't fit in the numbers column
+    number: Option<u32>,
+    isotope: Option<u16>,
+    map_number: Option<u32>,
+    chirality: Option<Chirality>,
+    chiral_neighbors: Vec<AtomIdx>,
+}
+
+/// What most bonds don't have.
+#[derive(Debug, Clone)]
+struct BondExtras {
+    direction: Option<BondDirection>,
+    stereo: Option<BondStereo>,
+}
+
+impl Default for MolStore {
+    fn default() -> Self {
+        MolStore {
+            atom_starts: vec![0],
+            bond_starts: vec![0],
+            numbers: vec![],
+            charges: vec![],
+            implicit_h: vec![],
+            explicit_h: vec![],
+            flags: vec![],
+            bond_atoms: vec![],
+            bond_orders: vec![],
+            atom_extras: vec![],
+            bond_extras: vec![],
+        }
+    }
+}
+
+impl MolStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.atom_starts.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds the molecule, returns its identifier.
+    pub fn push(&mut self, mol: &Mol) -> usize {
+        for atom in mol.atoms() {
+            let index = self.numbers.len();
+            let number = u8::try_from(atom.number).ok();
+            self.numbers.push(number.unwrap_or(u8::MAX));
+            self.charges.push(atom.charge);
+            self.implicit_h.push(atom.implicit_h);
+            self.explicit_h.push(atom.explicit_h.unwrap_or(0));
+            let mut flags = 0;
+            if atom.aromatic {
+                flags |= AROMATIC;
+            }
+            if atom.explicit_h.is_some() {
+                flags |= EXPLICIT_H;
+            }
+            self.flags.push(flags);
+
+            let extras = AtomExtras {
+                number: number.is_none().then_some(atom.number),
+                isotope: atom.isotope,
+                map_number: atom.map_number,
+                chirality: atom.chirality,
+                chiral_neighbors: atom.chiral_neighbors.clone(),
+            };
+            if extras != AtomExtras::default() {
+                self.atom_extras.push((index, extras));
+            }
+        }
+
+        for edge in mol.graph.raw_edges() {
+            let index = self.bond_orders.len();
+            // atom indices are below u32::MAX in a petgraph graph
+            self.bond_atoms
+                .push([edge.source().index() as u32, edge.target().index() as u32]);
+            self.bond_orders.push(edge.weight.order);
+            if edge.weight.direction.is_some() || edge.weight.stereo.is_some() {
+                let extras = BondExtras {
+                    direction: edge.weight.direction,
+                    stereo: edge.weight.stereo,
+                };
+                self.bond_extras.push((index, extras));
+            }
+        }
+
+        self.atom_starts.push(self.numbers.len());
+        self.bond_starts.push(self.bond_orders.len());
+        self.len() - 1
+    }
+
+    pub fn num_atoms(&self, id: usize) -> Option<usize> {
+        let atoms = self.atom_range(id)?;
+        Some(atoms.len())
+    }
+
+    pub fn num_bonds(&self, id: usize) -> Option<usize> {
+        let bonds = self.bond_range(id)?;
+        Some(bonds.len())
+    }
+
+    /// The molecule, as it was added.
+    pub fn get(&self, id: usize) -> Option<Mol> {
+        let atoms = self.atom_range(id)?;
+        let bonds = self.bond_range(id)?;
+        let mut graph = UnGraph::with_capacity(atoms.len(), bonds.len());
+
+        let mut extras = extras_in(&self.atom_extras, &atoms);
+        for index in atoms {
+            let flags = self.flags[index];
+            let mut atom = Atom {
+                number: self.numbers[index].into(),
+                aromatic: flags & AROMATIC != 0,
+                charge: self.charges[index],
+                explicit_h: (flags & EXPLICIT_H != 0).then_some(self.explicit_h[index]),
+                implicit_h: self.implicit_h[index],
+                ..Default::default()
+            };
+            if let Some((_, extras)) = extras.next_if(|(i, _)| *i == index) {
+                atom.number = extras.number.unwrap_or(atom.number);
+                atom.isotope = extras.isotope;
+                atom.map_number = extras.map_number;
+                atom.chirality = extras.chirality;
+                atom.chiral_neighbors = extras.chiral_neighbors.clone();
+            }
+            graph.add_node(atom);
+        }
+
+        let mut extras = extras_in(&self.bond_extras, &bonds);
+        for index in bonds {
+            let mut bond = Bond {
+                order: self.bond_orders[index],
+                ..Default::default()
+            };
+            if let Some((_, extras)) = extras.next_if(|(i, _)| *i == index) {
+                bond.direction = extras.direction;
+                bond.stereo = extras.stereo;
+            }
+            let [a, b] = self.bond_atoms[index];
+            graph.add_edge(NodeIndex::new(a as usize), NodeIndex::new(b as usize), bond);
+        }
+
+        Some(Mol { graph })
+    }
+
+    /// The molecules, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = Mol> + '_ {
+        // unwrap: the identifiers are in range
+        (0..self.len()).map(|id| self.get(id).unwrap())
+    }
+
+    fn atom_range(&self, id: usize) -> Option<core::ops::Range<usize>> {
+        let end = *self.atom_starts.get(id + 1)?;
+        Some(self.atom_starts[id]..end)
+    }
+
+    fn bond_range(&self, id: usize) -> Option<core::ops::Range<usize>> {
+        let end = *self.bond_starts.get(id + 1)?;
+        Some(self.bond_starts[id]..end)
+    }
+}
+
+impl FromIterator<Mol> for MolStore {
+    fn from_iter<I: IntoIterator<Item = Mol>>(mols: I) -> Self {
+        let mut store = MolStore::new();
+        for mol in mols {
+            store.push(&mol);
+        }
+        store
+    }
+}
+
+/// The extras of the indices in the range, in index order.
+fn extras_in<'a, T>(
+    extras: &'a [(usize, T)],
+    range: &core::ops::Range<usize>,
+) -> core::iter::Peekable<impl Iterator<Item = &'a (usize, T)>> {
+    let start = extras.partition_point(|(index, _)| *index < range.start);
+    let end = extras.partition_point(|(index, _)| *index < range.end);
+    extras[start..end].iter().peekable()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::{Atom, AtomIdx, Mol, SourceSpan};
+
+    use super::MolStore;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let library = [
+            "CC(=O)Oc1ccccc1C(=O)O",
+            "N[C@@H](C)C(=O)O",
+            "F/C=C/F",
+            "[13CH3:1][NH3+]",
+            "",
+            "[Na+].[Cl-]",
+            "C1CC[C@]2(F)CCCC[C@@H]2C1",
+        ];
+        let store: MolStore = library.iter().map(|smiles| mol(smiles)).collect();
+
+        assert_eq!(library.len(), store.len());
+        for (id, smiles) in library.iter().enumerate() {
+            let mut expected = mol(smiles);
+            // where the atoms and bonds were written isn't stored
+            expected
+                .graph
+                .node_weights_mut()
+                .for_each(|atom| atom.span = SourceSpan(None));
+            expected
+                .graph
+                .edge_weights_mut()
+                .for_each(|bond| bond.span = SourceSpan(None));
+            let stored = store.get(id).unwrap();
+            assert_eq!(
+                format!("{:?}", expected),
+                format!("{:?}", stored),
+                "{}",
+                smiles
+            );
+            assert_eq!(Some(expected.num_atoms()), store.num_atoms(id));
+            assert_eq!(Some(expected.num_bonds()), store.num_bonds(id));
+        }
+        assert!(store.get(library.len()).is_none());
+        assert!(store.get(library.len() + 1).is_none());
+        assert_eq!(
+            vec!["C=C", "CC"],
+            MolStore::from_iter([mol("C=C"), mol("CC")])
+                .iter()
+                .map(|mol| mol.to_smiles())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn large_atomic_number() {
+        let mut mol = Mol {
+            graph: Default::default(),
+        };
+        mol.graph.add_node(Atom {
+            number: 300,
+            ..Default::default()
+        });
+        let mut store = MolStore::new();
+        store.push(&mol);
+
+        let stored = store.get(0).unwrap();
+        assert_eq!(300, stored.atom_with_idx(AtomIdx::new(0)).unwrap().number);
+    }
+}
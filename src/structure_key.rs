@@ -0,0 +1,230 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use petgraph::graph::{NodeIndex, UnGraph};
+
+use crate::canon::canonical_ranks;
+use crate::formula::element_formula;
+use crate::types::{Atom, Bond, Mol};
+
+/// Layered description of the structure, in the spirit of InChI:
+/// `formula/c<connections>/h<hydrogens>/q<charges>/i<isotopes>`.
+///
+/// Atoms are numbered from 1 in canonical order, hydrogens are folded into their
+/// heavy atom, and bond orders and aromaticity are ignored, so that e.g. Kekulé and
+/// aromatic forms have the same layers. Empty layers are omitted.
+pub fn structure_layers(mol: &Mol) -> String {
+    let skeleton = skeleton(mol);
+    let ranks = canonical_ranks(&skeleton);
+    let mut by_rank: Vec<NodeIndex> = skeleton.graph.node_indices().collect();
+    by_rank.sort_by_key(|node| ranks[node.index()]);
+    let number = |node: NodeIndex| ranks[node.index()] + 1;
+
+    let mut connections: Vec<(usize, usize)> = skeleton
+        .graph
+        .edge_indices()
+        .map(|edge| {
+            // unwrap: edge comes from the graph
+            let (a, b) = skeleton.graph.edge_endpoints(edge).unwrap();
+            (number(a).min(number(b)), number(a).max(number(b)))
+        })
+        .collect();
+    connections.sort();
+
+    let mut layers = vec![element_formula(&skeleton)];
+    let mut push_layer = |prefix: char, items: Vec<String>| {
+        if !items.is_empty() {
+            layers.push(format!("{}{}", prefix, items.join(",")));
+        }
+    };
+    push_layer(
+        'c',
+        connections
+            .iter()
+            .map(|(a, b)| format!("{}-{}", a, b))
+            .collect(),
+    );
+    let atoms = || {
+        by_rank
+            .iter()
+            .map(|node| (number(*node), &skeleton.graph[*node]))
+    };
+    push_layer(
+        'h',
+        atoms()
+            .filter(|(_, atom)| atom.total_num_hs() > 0)
+            .map(|(i, atom)| format!("{}H{}", i, atom.total_num_hs()))
+            .collect(),
+    );
+    push_layer(
+        'q',
+        atoms()
+            .filter(|(_, atom)| atom.charge != 0)
+            .map(|(i, atom)| format!("{}{:+}", i, atom.charge))
+            .collect(),
+    );
+    push_layer(
+        'i',
+        atoms()
+            .filter_map(|(i, atom)| atom.isotope.map(|isotope| format!("{}+{}", i, isotope)))
+            .collect(),
+    );
+    layers.join("/")
+}
+
+/// Fixed length identifier hashed from the layers, in the spirit of the InChIKey:
+/// 14 letters for the formula and connections, 10 letters for the other layers,
+/// and a last letter for the net charge (`N` neutral, `P` positive, `M` negative).
+pub fn structure_key(mol: &Mol) -> String {
+    let layers = structure_layers(mol);
+    let (skeleton, rest) = match layers
+        .find("/h")
+        .or(layers.find("/q"))
+        .or(layers.find("/i"))
+    {
+        Some(split) => layers.split_at(split),
+        None => (layers.as_str(), ""),
+    };
+    let charge: i32 = mol
+        .graph
+        .node_weights()
+        .map(|atom| atom.charge as i32)
+        .sum();
+    let charge = match charge.signum() {
+        1 => 'P',
+        -1 => 'M',
+        _ => 'N',
+    };
+    format!(
+        "{}-{}-{}",
+        letters(fnv1a(skeleton.as_bytes()), 14),
+        letters(fnv1a(rest.as_bytes()), 10),
+        charge
+    )
+}
+
+/// The heavy atoms, with hydrogen atoms folded into their counts, single bonds,
+/// and only the invariants the layers describe.
+fn skeleton(mol: &Mol) -> Mol {
+    let graph = &mol.graph;
+    let is_folded = |node: NodeIndex| {
+        let atom = &graph[node];
+        let mut neighbors = graph.neighbors(node);
+        atom.number == 1
+            && atom.isotope.is_none()
+            && atom.charge == 0
+            && matches!(
+                (neighbors.next(), neighbors.next()),
+                (Some(neighbor), None) if graph[neighbor].number != 1
+            )
+    };
+
+    let mut skeleton = UnGraph::<Atom, Bond>::new_undirected();
+    let mut indices: Vec<Option<NodeIndex>> = vec![None; graph.node_count()];
+    for node in graph.node_indices() {
+        if is_folded(node) {
+            continue;
+        }
+        let atom = &graph[node];
+        let folded = graph.neighbors(node).filter(|n| is_folded(*n)).count() as u8;
+        indices[node.index()] = Some(skeleton.add_node(Atom {
+            number: atom.number,
+            isotope: atom.isotope,
+            charge: atom.charge,
+            explicit_h: Some(atom.total_num_hs() + folded),
+            ..Default::default()
+        }));
+    }
+    for edge in graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(edge).unwrap();
+        if let (Some(a), Some(b)) = (indices[a.index()], indices[b.index()]) {
+            skeleton.add_edge(a, b, Bond::default());
+        }
+    }
+    Mol { graph: skeleton }
+}
+
+/// 64 bit FNV-1a hash: simple and stable across platforms and Rust versions.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Encodes the hash as uppercase letters.
+fn letters(mut hash: u64, count: usize) -> String {
+    (0..count)
+        .map(|_| {
+            let letter = (b'A' + (hash % 26) as u8) as char;
+            hash /= 26;
+            letter
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn layers(smiles: &str) -> String {
+        mol(smiles).structure_layers()
+    }
+
+    fn key(smiles: &str) -> String {
+        mol(smiles).structure_key()
+    }
+
+    #[test]
+    fn layers_of_simple_molecules() {
+        assert_eq!("C2H6O/c1-3,2-3/h1H3,2H1,3H2", layers("CCO"));
+        assert_eq!("CH4/h1H4", layers("C"));
+        assert_eq!("H4N/h1H4/q1+1", layers("[NH4+]"));
+        assert_eq!("CH4/h1H4/i1+13", layers("[13CH4]"));
+    }
+
+    #[test]
+    fn layers_ignore_representation() {
+        assert_eq!(layers("CCO"), layers("OCC"));
+        assert_eq!(layers("c1ccccc1"), layers("C1=CC=CC=C1"));
+        assert_eq!(layers("CO"), layers("[H]OC([H])([H])[H]"));
+        assert_eq!(layers("C[C@H](N)O"), layers("C[C@@H](N)O"));
+    }
+
+    #[test]
+    fn key_format() {
+        let key = key("CCO");
+
+        assert_eq!(27, key.len());
+        assert_eq!(Some(14), key.find('-'));
+        assert!(key.ends_with("-N"));
+        assert!(key.chars().all(|c| c.is_ascii_uppercase() || c == '-'));
+    }
+
+    #[test]
+    fn key_distinguishes_structures() {
+        assert_eq!(key("CCO"), key("OCC"));
+        assert_ne!(key("CCO"), key("COC"));
+        assert_ne!(key("CC=O"), key("CCO"));
+        assert!(key("[NH4+]").ends_with("-P"));
+        assert!(key("C[O-]").ends_with("-M"));
+    }
+
+    #[test]
+    fn key_shares_skeleton_block() {
+        // isotopes only change the second block, hydrogens change the formula
+        let (ethanol, ethanal) = (key("CCO"), key("CC=O"));
+        let (methane, labeled) = (key("C"), key("[13CH4]"));
+
+        assert_eq!(methane[..14], labeled[..14]);
+        assert_ne!(methane[15..25], labeled[15..25]);
+        assert_ne!(ethanol[..14], ethanal[..14]);
+    }
+}
@@ -0,0 +1,401 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::algo::is_isomorphic_matching;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::canon;
+use crate::rings::RingInfo;
+use crate::smarts::{AtomPrimitive, BondPrimitive, Smarts};
+use crate::types::{AtomIdx, BondOrder, Mol};
+use crate::valence::bond_order_sum;
+
+/// The molecule being searched, with the ring information the primitives need. Its
+/// aromaticity is perceived, so that `c` matches the Kekulé form of benzene too. Atoms
+/// keep their indices.
+struct Target {
+    mol: Mol,
+    ring_info: RingInfo,
+}
+
+impl Target {
+    fn new(mol: &Mol) -> Target {
+        let mol = canon::perceived(mol);
+        let ring_info = mol.ring_info();
+        Target { mol, ring_info }
+    }
+}
+
+/// All matches of the query in the molecule, as target atom indices in query atom order.
+/// Matches covering the same set of atoms are reported once.
+pub fn substruct_matches(mol: &Mol, query: &Smarts) -> Vec<Vec<AtomIdx>> {
+    let target = Target::new(mol);
+    let mut matches = vec![];
+    search(&target, query, None, &mut |mapping| {
+        matches.push(mapping.iter().map(|node| AtomIdx::from(*node)).collect());
+        true
+    });
+
+    let mut seen: Vec<Vec<AtomIdx>> = vec![];
+    matches.retain(|mapping: &Vec<AtomIdx>| {
+        let mut atoms = mapping.clone();
+        atoms.sort();
+        if seen.contains(&atoms) {
+            false
+        } else {
+            seen.push(atoms);
+            true
+        }
+    });
+    matches
+}
+
+/// Each mapping of the query in the molecule, target atoms in query atom order, until
+/// `found` returns false. Unlike [`substruct_matches`], the mappings of a symmetric query
+/// onto the same atoms are all reported.
+pub(crate) fn for_each_mapping(
+    mol: &Mol,
+    query: &Smarts,
+    found: &mut dyn FnMut(&[NodeIndex]) -> bool,
+) {
+    let target = Target::new(mol);
+    search(&target, query, None, found);
+}
+
+/// Whether both molecules have the same graph, comparing atomic number, charge, isotope
+/// and hydrogen count of atoms, and order of bonds. Stereo is ignored.
+pub fn is_same_structure(mol: &Mol, other: &Mol) -> bool {
+    is_isomorphic_matching(
+        &mol.graph,
+        &other.graph,
+        |a, b| {
+            a.number == b.number
+                && a.charge == b.charge
+                && a.isotope == b.isotope
+                && a.total_num_hs() == b.total_num_hs()
+        },
+        |a, b| a.order == b.order,
+    )
+}
+
+/// Backtracking search in the spirit of VF2: query atoms are mapped in breadth first order,
+/// so that each one (except the first of each component) is a neighbor of an already mapped
+/// atom and its candidates are limited to the neighbors of that atom's image.
+/// `start` fixes the image of the first query atom. `found` returns whether to continue.
+fn search(
+    target: &Target,
+    query: &Smarts,
+    start: Option<NodeIndex>,
+    found: &mut dyn FnMut(&[NodeIndex]) -> bool,
+) {
+    if query.graph.node_count() == 0 {
+        return;
+    }
+    let order = match_order(query);
+    let mut mapping: Vec<Option<NodeIndex>> = vec![None; query.graph.node_count()];
+    let mut used = vec![false; target.mol.graph.node_count()];
+    extend(target, query, &order, start, &mut mapping, &mut used, found);
+}
+
+/// Query atoms in breadth first order, each with an already ordered neighbor if it has one.
+fn match_order(query: &Smarts) -> Vec<(NodeIndex, Option<NodeIndex>)> {
+    let graph = &query.graph;
+    let mut visited = vec![false; graph.node_count()];
+    let mut order = vec![];
+    for root in graph.node_indices() {
+        if visited[root.index()] {
+            continue;
+        }
+        visited[root.index()] = true;
+        order.push((root, None));
+        let mut i = order.len() - 1;
+        while i < order.len() {
+            let node = order[i].0;
+            for neighbor in graph.neighbors(node) {
+                if !visited[neighbor.index()] {
+                    visited[neighbor.index()] = true;
+                    order.push((neighbor, Some(node)));
+                }
+            }
+            i += 1;
+        }
+    }
+    order
+}
+
+/// Maps the next query atom in `order`, returns false when the search should stop.
+fn extend(
+    target: &Target,
+    query: &Smarts,
+    order: &[(NodeIndex, Option<NodeIndex>)],
+    start: Option<NodeIndex>,
+    mapping: &mut Vec<Option<NodeIndex>>,
+    used: &mut Vec<bool>,
+    found: &mut dyn FnMut(&[NodeIndex]) -> bool,
+) -> bool {
+    let depth = order
+        .iter()
+        .filter(|(node, _)| mapping[node.index()].is_some())
+        .count();
+    let Some((node, anchor)) = order.get(depth) else {
+        // unwrap: all query atoms are mapped
+        let complete: Vec<NodeIndex> = mapping.iter().map(|n| n.unwrap()).collect();
+        return found(&complete);
+    };
+
+    let candidates: Vec<NodeIndex> = match (anchor, start) {
+        // unwrap: anchors are mapped before the atoms they anchor
+        (Some(anchor), _) => target
+            .mol
+            .graph
+            .neighbors(mapping[anchor.index()].unwrap())
+            .collect(),
+        (None, Some(start)) if depth == 0 => vec![start],
+        (None, _) => target.mol.graph.node_indices().collect(),
+    };
+
+    for candidate in candidates {
+        if used[candidate.index()] || !is_feasible(target, query, mapping, *node, candidate) {
+            continue;
+        }
+        mapping[node.index()] = Some(candidate);
+        used[candidate.index()] = true;
+        let proceed = extend(target, query, order, start, mapping, used, found);
+        mapping[node.index()] = None;
+        used[candidate.index()] = false;
+        if !proceed {
+            return false;
+        }
+    }
+    true
+}
+
+/// Whether the query atom can be mapped to the candidate: the atoms match, and so do the
+/// bonds to all the query neighbors that are already mapped.
+fn is_feasible(
+    target: &Target,
+    query: &Smarts,
+    mapping: &[Option<NodeIndex>],
+    node: NodeIndex,
+    candidate: NodeIndex,
+) -> bool {
+    if !query.graph[node].matches(&|p| atom_matches(target, p, candidate)) {
+        return false;
+    }
+    query.graph.edges(node).all(|edge| {
+        let Some(other) = mapping[edge.target().index()] else {
+            return true;
+        };
+        match target.mol.graph.find_edge(candidate, other) {
+            Some(bond) => edge.weight().matches(&|p| bond_matches(target, p, bond)),
+            None => false,
+        }
+    })
+}
+
+fn atom_matches(target: &Target, primitive: &AtomPrimitive, node: NodeIndex) -> bool {
+    let mol = &target.mol;
+    let atom = &mol.graph[node];
+    let idx = AtomIdx::from(node);
+    let degree = mol.graph.neighbors(node).count() as u32;
+    let hydrogen_neighbors = mol
+        .graph
+        .neighbors(node)
+        .filter(|n| mol.graph[*n].number == 1)
+        .count() as u32;
+    match primitive {
+        AtomPrimitive::Any => true,
+        AtomPrimitive::Element { number, aromatic } => {
+            atom.number == *number && aromatic.is_none_or(|aromatic| atom.aromatic == aromatic)
+        }
+        AtomPrimitive::Aromatic(aromatic) => atom.aromatic == *aromatic,
+        AtomPrimitive::Isotope(isotope) => atom.isotope == Some(*isotope),
+        AtomPrimitive::Charge(charge) => atom.charge == *charge,
+        AtomPrimitive::Degree(count) => degree == *count,
+        AtomPrimitive::TotalH(count) => atom.total_num_hs() as u32 + hydrogen_neighbors == *count,
+        AtomPrimitive::ImplicitH(None) => atom.total_num_hs() > 0,
+        AtomPrimitive::ImplicitH(Some(count)) => atom.total_num_hs() as u32 == *count,
+        AtomPrimitive::RingMembership(None) => target.ring_info.is_atom_in_ring(idx),
+        AtomPrimitive::RingMembership(Some(count)) => {
+            target.ring_info.atom_rings(idx).len() as u32 == *count
+        }
+        AtomPrimitive::RingSize(None) => target.ring_info.is_atom_in_ring(idx),
+        AtomPrimitive::RingSize(Some(size)) => target
+            .ring_info
+            .atom_ring_sizes(idx)
+            .contains(&(*size as usize)),
+        AtomPrimitive::Valence(valence) => {
+            // aromatic atoms count one more bond, like in the valence model
+            let bonds = bond_order_sum(mol, node) as u32 + atom.aromatic as u32;
+            bonds + atom.total_num_hs() as u32 == *valence
+        }
+        AtomPrimitive::Connectivity(count) => degree + atom.total_num_hs() as u32 == *count,
+        AtomPrimitive::Recursive(query) => {
+            let mut matched = false;
+            search(target, query, Some(node), &mut |_| {
+                matched = true;
+                false
+            });
+            matched
+        }
+    }
+}
+
+fn bond_matches(target: &Target, primitive: &BondPrimitive, edge: EdgeIndex) -> bool {
+    let order = target.mol.graph[edge].order;
+    match primitive {
+        BondPrimitive::Single => order == BondOrder::Single,
+        BondPrimitive::Double => order == BondOrder::Double,
+        BondPrimitive::Triple => order == BondOrder::Triple,
+        BondPrimitive::Quadruple => order == BondOrder::Quadruple,
+        BondPrimitive::Aromatic => order == BondOrder::Aromatic,
+        BondPrimitive::Any => true,
+        BondPrimitive::Ring => target.ring_info.is_bond_in_ring(edge.into()),
+        BondPrimitive::SingleOrAromatic => {
+            order == BondOrder::Single || order == BondOrder::Aromatic
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smarts::Smarts;
+    use crate::smiles::SmilesParser;
+
+    /// The matches, as atom indices.
+    fn matches(smiles: &str, smarts: &str) -> Vec<Vec<usize>> {
+        let parser = SmilesParser::new();
+        let mol = parser.parse(smiles).unwrap();
+        mol.substruct_matches(&Smarts::parse(smarts).unwrap())
+            .iter()
+            .map(|atoms| atoms.iter().map(|atom| atom.index()).collect())
+            .collect()
+    }
+
+    fn count(smiles: &str, smarts: &str) -> usize {
+        matches(smiles, smarts).len()
+    }
+
+    #[test]
+    fn match_atoms_and_bonds() {
+        assert_eq!(vec![vec![1, 2]], matches("CC=O", "C=O"));
+        assert_eq!(vec![vec![2, 1]], matches("CC=O", "O=C"));
+        assert_eq!(0, count("CCO", "C=O"));
+        assert_eq!(2, count("CCO", "[#6]"));
+        assert_eq!(1, count("CCO", "C~O"));
+    }
+
+    #[test]
+    fn match_unique_atom_sets() {
+        // the 6 rotations and 2 directions of the ring map to the same atoms
+        assert_eq!(1, count("c1ccccc1", "c1ccccc1"));
+        assert_eq!(6, count("c1ccccc1", "cc"));
+        assert_eq!(1, count("CC(C)(C)C", "C(C)(C)(C)C"));
+    }
+
+    #[test]
+    fn match_aromaticity() {
+        assert_eq!(6, count("c1ccccc1C", "a"));
+        assert_eq!(1, count("c1ccccc1C", "A"));
+        assert_eq!(1, count("c1ccccc1C", "cC"));
+        assert_eq!(0, count("c1ccccc1C", "c:C"));
+        assert_eq!(6, count("c1ccccc1C", "c:c"));
+        assert_eq!(1, count("c1ccccc1C", "c-C"));
+        // the linkage of biphenyl is single, between aromatic atoms
+        assert_eq!(1, count("c1ccccc1c1ccccc1", "c-c"));
+        assert_eq!(12, count("c1ccccc1c1ccccc1", "c:c"));
+        assert_eq!(13, count("c1ccccc1c1ccccc1", "cc"));
+        // an aromatic bond written outside rings is perceived single
+        assert_eq!(0, count("c1ccccc1:c1ccccc1", "c:!@c"));
+        assert_eq!(1, count("c1ccccc1:c1ccccc1", "c-!@c"));
+    }
+
+    #[test]
+    fn match_kekule_input() {
+        assert_eq!(6, count("C1=CC=CC=C1", "c"));
+        assert_eq!(0, count("C1=CC=CC=C1", "C"));
+        assert_eq!(6, count("C1=CC=CC=C1", "c:c"));
+        assert_eq!(0, count("C1=CC=CC=C1", "C=C"));
+        assert_eq!(1, count("CC1=CC=CC=C1O", "[OH]c1ccccc1"));
+        assert_eq!(1, count("C1=CNC=C1", "[nH]"));
+        // not aromatic
+        assert_eq!(2, count("C1=CCC=C1", "C=C"));
+    }
+
+    #[test]
+    fn match_counts() {
+        assert_eq!(vec![vec![0], vec![2]], matches("CC(C)O", "[CH3]"));
+        assert_eq!(vec![vec![1]], matches("CC(C)O", "[D3]"));
+        assert_eq!(vec![vec![3]], matches("CC(C)O", "[OX2H]"));
+        assert_eq!(vec![vec![1]], matches("CC(=O)O", "[Cv4D3]"));
+        assert_eq!(vec![vec![1]], matches("C[NH3+]", "[N+;H3]"));
+        assert_eq!(1, count("[2H]C", "[2H]"));
+    }
+
+    #[test]
+    fn match_wildcards() {
+        // only wildcard queries match wildcard atoms
+        assert_eq!(4, count("*CC*", "*"));
+        assert_eq!(2, count("*CC*", "[#0]"));
+        assert_eq!(2, count("*CC*", "C"));
+        assert_eq!(1, count("*CC*", "*CC*"));
+    }
+
+    #[test]
+    fn match_rings() {
+        // cyclopropylbenzene
+        assert_eq!(9, count("C1CC1c1ccccc1", "[R]"));
+        assert_eq!(3, count("C1CC1c1ccccc1", "[r3]"));
+        assert_eq!(
+            vec![vec![3], vec![8]],
+            matches("C1CC1c1ccccc1CC", "[R1;D3;a]")
+        );
+        assert_eq!(1, count("C1CC1c1ccccc1", "[R]!@[R]"));
+        assert_eq!(2, count("c1ccc2ccccc2c1", "[R2]"));
+    }
+
+    #[test]
+    fn match_logical_operators() {
+        assert_eq!(2, count("CCNO", "[N,O]"));
+        assert_eq!(2, count("CCNO", "[!C]"));
+        assert_eq!(1, count("CCNO", "[!C;!N]"));
+    }
+
+    #[test]
+    fn match_recursive() {
+        // carbons next to a carbonyl
+        assert_eq!(vec![vec![0]], matches("CC(=O)CC", "[$(CC=O);CH3]"));
+        assert_eq!(vec![vec![0], vec![3]], matches("CC(=O)CC", "[$(CC=O)]"));
+    }
+
+    #[test]
+    fn match_disconnected_query() {
+        assert_eq!(vec![vec![0, 2], vec![1, 2]], matches("CCO", "C.O"));
+        assert_eq!(0, count("CC", "[Na+].C"));
+    }
+
+    fn is_same(smiles: &str, other: &str) -> bool {
+        let parser = SmilesParser::new();
+        let mol = parser.parse(smiles).unwrap();
+        mol.is_same_structure(&parser.parse(other).unwrap())
+    }
+
+    #[test]
+    fn same_structure_regardless_of_order() {
+        assert!(is_same("OCC", "CCO"));
+        assert!(is_same("c1ccccc1O", "Oc1ccccc1"));
+        assert!(is_same("C[C@H](N)O", "C[C@@H](N)O"));
+        assert!(is_same("CC(=O)O", "OC(C)=O"));
+    }
+
+    #[test]
+    fn different_structure_invariants() {
+        assert!(!is_same("CCO", "COC"));
+        assert!(!is_same("CC=O", "CCO"));
+        assert!(!is_same("[13CH4]", "C"));
+        assert!(!is_same("[NH4+]", "N"));
+        assert!(!is_same("C", "[CH2]"));
+        assert!(!is_same("CCC", "CC"));
+    }
+}
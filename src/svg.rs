@@ -0,0 +1,784 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+
+use crate::layout::{Point, BOND_LENGTH};
+use crate::rings::RingInfo;
+use crate::smiles::element_symbol;
+use crate::types::{AtomIdx, BondIdx, BondOrder, Chirality, Mol};
+
+const BLACK: &str = "#000000";
+
+/// Options for [`Mol::to_svg`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    /// Size of the image, in pixels.
+    pub width: f64,
+    pub height: f64,
+    /// Margin around the molecule, in pixels.
+    pub padding: f64,
+    /// Upper bound for the drawn bond length, in pixels, so that small molecules aren't huge.
+    pub max_bond_length: f64,
+    pub font_size: f64,
+    pub line_width: f64,
+    /// Colors heteroatom labels by element, otherwise everything is black.
+    pub color_atoms: bool,
+    /// Atoms and bonds drawn on a background of `highlight_color`, e.g. a match.
+    pub highlight_atoms: Vec<AtomIdx>,
+    pub highlight_bonds: Vec<BondIdx>,
+    pub highlight_color: String,
+    /// More atoms and bonds on a background of their own color, drawn over the above.
+    pub highlights: Vec<Highlight>,
+}
+
+/// Atoms and bonds to draw on a background of one color, see [`SvgOptions::highlights`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub atoms: Vec<AtomIdx>,
+    pub bonds: Vec<BondIdx>,
+    pub color: String,
+}
+
+impl Highlight {
+    /// The atoms and the bonds between them, e.g. the atoms of a substructure match.
+    pub fn atoms_and_bonds(mol: &Mol, atoms: &[AtomIdx], color: &str) -> Highlight {
+        let bonds = mol
+            .graph
+            .edge_indices()
+            .filter(|edge| {
+                // unwrap: edge comes from the graph
+                let (a, b) = mol.graph.edge_endpoints(*edge).unwrap();
+                atoms.contains(&a.into()) && atoms.contains(&b.into())
+            })
+            .map(BondIdx::from)
+            .collect();
+        Highlight {
+            atoms: atoms.to_vec(),
+            bonds,
+            color: color.to_string(),
+        }
+    }
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            width: 300.0,
+            height: 300.0,
+            padding: 20.0,
+            max_bond_length: 40.0,
+            font_size: 14.0,
+            line_width: 1.5,
+            color_atoms: true,
+            highlight_atoms: vec![],
+            highlight_bonds: vec![],
+            highlight_color: "#FFB0B0".to_string(),
+            highlights: vec![],
+        }
+    }
+}
+
+/// Renders the molecule with its 2D layout: bonds as lines (offset lines for double and
+/// triple bonds, kekulized when possible), wedges for tetrahedral stereo, and labels for
+/// heteroatoms and for carbons with a charge, an isotope or no bonds.
+pub fn to_svg(mol: &Mol, options: &SvgOptions) -> String {
+    let mut mol = mol.clone();
+    let _ = mol.kekulize();
+    let conformer = mol.compute_2d_coords();
+    let ring_info = mol.ring_info();
+
+    // fit the molecule in the image, y pointing down
+    let positions = conformer.positions();
+    let min = |f: fn(&Point) -> f64| positions.iter().map(f).fold(f64::INFINITY, f64::min);
+    let max = |f: fn(&Point) -> f64| positions.iter().map(f).fold(f64::NEG_INFINITY, f64::max);
+    let (min_x, max_x, min_y, max_y) = (min(|p| p.x), max(|p| p.x), min(|p| p.y), max(|p| p.y));
+    let scale = [
+        (options.width - 2.0 * options.padding) / (max_x - min_x),
+        (options.height - 2.0 * options.padding) / (max_y - min_y),
+        options.max_bond_length / BOND_LENGTH,
+    ]
+    .into_iter()
+    .filter(|scale| scale.is_finite())
+    .fold(f64::INFINITY, f64::min)
+    .max(0.0);
+    let points: Vec<Point> = positions
+        .iter()
+        .map(|p| {
+            Point::new(
+                options.width / 2.0 + (p.x - (min_x + max_x) / 2.0) * scale,
+                options.height / 2.0 - (p.y - (min_y + max_y) / 2.0) * scale,
+            )
+        })
+        .collect();
+
+    let drawing = Drawing {
+        mol: &mol,
+        ring_info: &ring_info,
+        points: &points,
+        labels: mol
+            .graph
+            .node_indices()
+            .map(|node| label(&mol, node))
+            .collect(),
+        options,
+        bond_length: BOND_LENGTH * scale,
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = options.width,
+        h = options.height
+    );
+    out.push_str(r##"<rect width="100%" height="100%" fill="#FFFFFF"/>"##);
+    out.push('\n');
+    drawing.highlights(&mut out);
+    let wedges = wedges(&mol, positions);
+    for edge in mol.graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = mol.graph.edge_endpoints(edge).unwrap();
+        let order = mol.graph[edge].order;
+        match wedges.iter().find(|(center, neighbor, _)| {
+            (*center, *neighbor) == (a, b) || (*center, *neighbor) == (b, a)
+        }) {
+            Some((center, neighbor, hashed)) if order == BondOrder::Single => {
+                drawing.wedge(&mut out, *center, *neighbor, *hashed)
+            }
+            _ => drawing.bond(&mut out, edge.index(), a, b, order),
+        }
+    }
+    for node in mol.graph.node_indices() {
+        drawing.label(&mut out, node);
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Renders the molecules in a grid of `columns` columns, each in a cell of the size of
+/// the options, with its label underneath, e.g. its name or ID. Molecules without a label
+/// (`labels` is shorter) are drawn without one.
+pub fn grid_svg(mols: &[Mol], labels: &[&str], columns: usize, options: &SvgOptions) -> String {
+    let columns = columns.clamp(1, mols.len().max(1));
+    let rows = mols.len().div_ceil(columns);
+    let label_height = if labels.is_empty() {
+        0.0
+    } else {
+        1.5 * options.font_size
+    };
+    let (cell_width, cell_height) = (options.width, options.height + label_height);
+    let (width, height) = (columns as f64 * cell_width, rows as f64 * cell_height);
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    );
+    out.push_str(r##"<rect width="100%" height="100%" fill="#FFFFFF"/>"##);
+    out.push('\n');
+    for (i, mol) in mols.iter().enumerate() {
+        let (x, y) = (
+            (i % columns) as f64 * cell_width,
+            (i / columns) as f64 * cell_height,
+        );
+        // each molecule as a nested svg element
+        let svg = to_svg(mol, options);
+        out.push_str(&svg.replacen("<svg ", &format!(r#"<svg x="{}" y="{}" "#, x, y), 1));
+        if let Some(label) = labels.get(i) {
+            let _ = writeln!(
+                out,
+                r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-size="{}" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                x + cell_width / 2.0,
+                y + options.height + label_height / 2.0,
+                options.font_size,
+                BLACK,
+                escape(label)
+            );
+        }
+        let _ = writeln!(
+            out,
+            r##"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="#CCCCCC"/>"##,
+            x, y, cell_width, cell_height
+        );
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// The text with the characters that are markup in XML escaped.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+struct Drawing<'a> {
+    mol: &'a Mol,
+    ring_info: &'a RingInfo,
+    points: &'a [Point],
+    labels: Vec<Option<Label>>,
+    options: &'a SvgOptions,
+    bond_length: f64, // in pixels
+}
+
+impl Drawing<'_> {
+    /// Thick lines under the highlighted bonds and discs under the highlighted atoms, in
+    /// the order of the options. Indices out of range are ignored.
+    fn highlights(&self, out: &mut String) {
+        let options = self.options;
+        self.highlight(
+            out,
+            &options.highlight_atoms,
+            &options.highlight_bonds,
+            &options.highlight_color,
+        );
+        for highlight in &options.highlights {
+            self.highlight(out, &highlight.atoms, &highlight.bonds, &highlight.color);
+        }
+    }
+
+    fn highlight(&self, out: &mut String, atoms: &[AtomIdx], bonds: &[BondIdx], color: &str) {
+        for bond in bonds {
+            let Some((a, b)) = self.mol.graph.edge_endpoints(EdgeIndex::new(bond.index())) else {
+                continue;
+            };
+            let (start, end) = (self.points[a.index()], self.points[b.index()]);
+            let _ = writeln!(
+                out,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="{:.1}" stroke-linecap="round"/>"#,
+                start.x,
+                start.y,
+                end.x,
+                end.y,
+                color,
+                0.3 * self.bond_length
+            );
+        }
+        for atom in atoms {
+            let Some(point) = self.points.get(atom.index()) else {
+                continue;
+            };
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"/>"#,
+                point.x,
+                point.y,
+                0.3 * self.bond_length,
+                color
+            );
+        }
+    }
+
+    /// End points of the bond, shortened to leave room for the labels.
+    fn ends(&self, a: NodeIndex, b: NodeIndex) -> (Point, Point) {
+        let (pa, pb) = (self.points[a.index()], self.points[b.index()]);
+        let length = pa.distance(pb);
+        if length < 1e-6 {
+            return (pa, pb);
+        }
+        let gap = 0.6 * self.options.font_size;
+        let shorten = |from: Point, to: Point, labeled: bool| {
+            if !labeled {
+                return from;
+            }
+            let t = (gap / length).min(0.4);
+            Point::new(from.x + (to.x - from.x) * t, from.y + (to.y - from.y) * t)
+        };
+        (
+            shorten(pa, pb, self.labels[a.index()].is_some()),
+            shorten(pb, pa, self.labels[b.index()].is_some()),
+        )
+    }
+
+    fn bond(&self, out: &mut String, edge: usize, a: NodeIndex, b: NodeIndex, order: BondOrder) {
+        let (start, end) = self.ends(a, b);
+        let offset = 0.18 * self.bond_length;
+        match order {
+            BondOrder::Single => self.line(out, start, end, false),
+            BondOrder::Double | BondOrder::Aromatic => {
+                let dashed = order == BondOrder::Aromatic;
+                match self.ring_center(edge) {
+                    // the second line inside the ring
+                    Some(center) => {
+                        self.line(out, start, end, false);
+                        let side = side_of(start, end, center);
+                        let (s, e) = parallel(start, end, side * offset, 0.15);
+                        self.line(out, s, e, dashed);
+                    }
+                    None => {
+                        let (s, e) = parallel(start, end, offset / 2.0, 0.0);
+                        self.line(out, s, e, false);
+                        let (s, e) = parallel(start, end, -offset / 2.0, 0.0);
+                        self.line(out, s, e, dashed);
+                    }
+                }
+            }
+            BondOrder::Triple | BondOrder::Quadruple => {
+                self.line(out, start, end, false);
+                for side in [1.0, -1.0] {
+                    let (s, e) = parallel(start, end, side * offset, 0.0);
+                    self.line(out, s, e, false);
+                }
+            }
+        }
+    }
+
+    /// Center of the ring the bond's extra lines go into: the ring with the most double
+    /// bonds, e.g. the aromatic ring of a fused system.
+    fn ring_center(&self, edge: usize) -> Option<Point> {
+        let graph = &self.mol.graph;
+        let ring = self
+            .ring_info
+            .bond_rings(BondIdx::new(edge))
+            .iter()
+            .map(|ring| &self.ring_info.rings()[*ring])
+            .max_by_key(|ring| {
+                ring.bonds
+                    .iter()
+                    .filter(|bond| graph[EdgeIndex::from(**bond)].order != BondOrder::Single)
+                    .count()
+            })?;
+        let count = ring.atoms.len() as f64;
+        Some(Point::new(
+            ring.atoms
+                .iter()
+                .map(|a| self.points[a.index()].x)
+                .sum::<f64>()
+                / count,
+            ring.atoms
+                .iter()
+                .map(|a| self.points[a.index()].y)
+                .sum::<f64>()
+                / count,
+        ))
+    }
+
+    fn line(&self, out: &mut String, start: Point, end: Point, dashed: bool) {
+        let _ = writeln!(
+            out,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="{}"{}/>"#,
+            start.x,
+            start.y,
+            end.x,
+            end.y,
+            BLACK,
+            self.options.line_width,
+            if dashed {
+                r#" stroke-dasharray="3,3""#
+            } else {
+                ""
+            }
+        );
+    }
+
+    /// Wedge from the stereocenter to the neighbor: solid towards the viewer, hashed away.
+    fn wedge(&self, out: &mut String, center: NodeIndex, neighbor: NodeIndex, hashed: bool) {
+        let (start, end) = self.ends(center, neighbor);
+        let half_width = 0.12 * self.bond_length;
+        let (_, left_end) = parallel(start, end, half_width, 0.0);
+        let (_, right_end) = parallel(start, end, -half_width, 0.0);
+        if hashed {
+            let count = 6;
+            for i in 1..=count {
+                let t = i as f64 / count as f64;
+                let at = |p: Point| {
+                    Point::new(start.x + (p.x - start.x) * t, start.y + (p.y - start.y) * t)
+                };
+                self.line(out, at(left_end), at(right_end), false);
+            }
+        } else {
+            let _ = writeln!(
+                out,
+                r#"<polygon points="{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}" fill="{}"/>"#,
+                start.x, start.y, left_end.x, left_end.y, right_end.x, right_end.y, BLACK
+            );
+        }
+    }
+
+    fn label(&self, out: &mut String, node: NodeIndex) {
+        let Some(label) = &self.labels[node.index()] else {
+            return;
+        };
+        let point = self.points[node.index()];
+        let color = if self.options.color_atoms {
+            color(self.mol.graph[node].number)
+        } else {
+            BLACK
+        };
+        let small = r#"font-size="70%""#;
+        let mut text = String::new();
+        if let Some(isotope) = label.isotope {
+            let _ = write!(
+                text,
+                r#"<tspan {} baseline-shift="super">{}</tspan>"#,
+                small, isotope
+            );
+        }
+        text.push_str(&label.symbol);
+        if label.hydrogens > 0 {
+            text.push('H');
+            if label.hydrogens > 1 {
+                let _ = write!(
+                    text,
+                    r#"<tspan {} baseline-shift="sub">{}</tspan>"#,
+                    small, label.hydrogens
+                );
+            }
+        }
+        if label.charge != 0 {
+            let sign = if label.charge > 0 { '+' } else { '-' };
+            let magnitude = label.charge.unsigned_abs();
+            let charge = if magnitude == 1 {
+                sign.to_string()
+            } else {
+                format!("{}{}", magnitude, sign)
+            };
+            let _ = write!(
+                text,
+                r#"<tspan {} baseline-shift="super">{}</tspan>"#,
+                small, charge
+            );
+        }
+        let _ = writeln!(
+            out,
+            r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-size="{}" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+            point.x, point.y, self.options.font_size, color, text
+        );
+    }
+}
+
+struct Label {
+    symbol: String,
+    hydrogens: u8,
+    charge: i8,
+    isotope: Option<u16>,
+}
+
+/// Heteroatoms are labeled, carbons only with a charge, an isotope or no bonds.
+fn label(mol: &Mol, node: NodeIndex) -> Option<Label> {
+    let atom = &mol.graph[node];
+    let isolated = mol.graph.neighbors(node).next().is_none();
+    if atom.number == 6 && atom.charge == 0 && atom.isotope.is_none() && !isolated {
+        return None;
+    }
+    Some(Label {
+        symbol: element_symbol(atom.number).unwrap_or("*").to_string(),
+        hydrogens: atom.total_num_hs(),
+        charge: atom.charge,
+        isotope: atom.isotope,
+    })
+}
+
+fn color(number: u32) -> &'static str {
+    match number {
+        7 => "#3050F8",
+        8 => "#FF0D0D",
+        9 | 17 => "#1FA01F",
+        15 => "#FF8000",
+        16 => "#C6A000",
+        35 => "#A62929",
+        53 => "#940094",
+        _ => BLACK,
+    }
+}
+
+/// The segment moved sideways by `offset` (to the left of start → end for positive
+/// offsets, in image coordinates), and shortened by the fraction `shorten` at both ends.
+fn parallel(start: Point, end: Point, offset: f64, shorten: f64) -> (Point, Point) {
+    let length = start.distance(end);
+    if length < 1e-6 {
+        return (start, end);
+    }
+    let (dx, dy) = ((end.x - start.x) / length, (end.y - start.y) / length);
+    let (nx, ny) = (dy * offset, -dx * offset);
+    let cut = length * shorten;
+    (
+        Point::new(start.x + dx * cut + nx, start.y + dy * cut + ny),
+        Point::new(end.x - dx * cut + nx, end.y - dy * cut + ny),
+    )
+}
+
+/// 1 if the point is on the side [`parallel`] moves to with positive offsets, else -1.
+fn side_of(start: Point, end: Point, point: Point) -> f64 {
+    let cross = (end.x - start.x) * (point.y - start.y) - (end.y - start.y) * (point.x - start.x);
+    if cross < 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+/// Picks one bond per tetrahedral stereocenter to draw as a wedge, returns
+/// (stereocenter, neighbor, hashed).
+///
+/// The wedged neighbor is taken above the plane of the drawing and an implicit hydrogen
+/// below it; the wedge is hashed if that doesn't give the atom's chirality.
+fn wedges(mol: &Mol, positions: &[Point]) -> Vec<(NodeIndex, NodeIndex, bool)> {
+    let graph = &mol.graph;
+    let ring_info = mol.ring_info();
+    let mut wedges: Vec<(NodeIndex, NodeIndex, bool)> = vec![];
+    for node in graph.node_indices() {
+        let atom = &graph[node];
+        let Some(chirality) = atom.chirality else {
+            continue;
+        };
+        let hydrogens = atom.explicit_h.unwrap_or(0) as usize;
+        if atom.chiral_neighbors.len() + hydrogens != 4 || hydrogens > 1 {
+            continue;
+        }
+
+        // prefer chain bonds to atoms that aren't stereocenters themselves, then few neighbors
+        let used = |neighbor: AtomIdx| {
+            wedges
+                .iter()
+                .any(|(a, b, _)| AtomIdx::from(*a) == neighbor && *b == node)
+        };
+        let Some(wedged) = atom
+            .chiral_neighbors
+            .iter()
+            .copied()
+            .filter(|neighbor| !used(*neighbor))
+            .min_by_key(|neighbor| {
+                // unwrap: the neighbors are bonded
+                let neighbor = NodeIndex::from(*neighbor);
+                let edge = graph.find_edge(node, neighbor).unwrap();
+                (
+                    ring_info.is_bond_in_ring(edge.into()),
+                    graph[neighbor].chirality.is_some(),
+                    graph.neighbors(neighbor).count(),
+                )
+            })
+        else {
+            continue;
+        };
+
+        let center = positions[node.index()];
+        let direction = |neighbor: AtomIdx| {
+            let p = positions[neighbor.index()];
+            let length = p.distance(center).max(1e-6);
+            let z = if neighbor == wedged { 1.0 } else { 0.0 };
+            [(p.x - center.x) / length, (p.y - center.y) / length, z]
+        };
+        let mut vectors: Vec<[f64; 3]> = vec![];
+        if hydrogens == 1 {
+            let (mut x, mut y) = (0.0, 0.0);
+            for neighbor in &atom.chiral_neighbors {
+                let [dx, dy, _] = direction(*neighbor);
+                x -= dx;
+                y -= dy;
+            }
+            vectors.push([x / 3.0, y / 3.0, -1.0]);
+        }
+        vectors.extend(atom.chiral_neighbors.iter().map(|n| direction(*n)));
+
+        let volume = signed_volume(&vectors);
+        if volume.abs() < 1e-3 {
+            continue;
+        }
+        // negative: seen from the first neighbor, the others are anticlockwise
+        let drawn = if volume < 0.0 {
+            Chirality::Anticlockwise
+        } else {
+            Chirality::Clockwise
+        };
+        wedges.push((node, wedged.into(), drawn != chirality));
+    }
+    wedges
+}
+
+/// Determinant of (b - a, c - a, d - a).
+fn signed_volume(vectors: &[[f64; 3]]) -> f64 {
+    let [a, b, c, d] = [vectors[0], vectors[1], vectors[2], vectors[3]];
+    let sub = |p: [f64; 3]| [p[0] - a[0], p[1] - a[1], p[2] - a[2]];
+    let (u, v, w) = (sub(b), sub(c), sub(d));
+    u[0] * (v[1] * w[2] - v[2] * w[1]) - u[1] * (v[0] * w[2] - v[2] * w[0])
+        + u[2] * (v[0] * w[1] - v[1] * w[0])
+}
+
+#[cfg(test)]
+mod test {
+    use petgraph::graph::NodeIndex;
+
+    use crate::layout::Point;
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, BondIdx};
+
+    use super::{grid_svg, Highlight, SvgOptions};
+
+    fn svg(smiles: &str) -> String {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap().to_svg(&SvgOptions::default())
+    }
+
+    fn count(svg: &str, element: &str) -> usize {
+        svg.matches(&format!("<{} ", element)).count()
+    }
+
+    #[test]
+    fn document() {
+        let svg = svg("CCO");
+
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="300" height="300""#)
+        );
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn bonds_by_order() {
+        assert_eq!(2, count(&svg("CCC"), "line"));
+        assert_eq!(2, count(&svg("C=C"), "line"));
+        assert_eq!(3, count(&svg("C#C"), "line"));
+        // kekulized: 3 single and 3 double bonds
+        assert_eq!(9, count(&svg("c1ccccc1"), "line"));
+    }
+
+    #[test]
+    fn labels() {
+        let ethanol = svg("CCO");
+        assert_eq!(1, count(&ethanol, "text"));
+        assert!(ethanol.contains(r##"fill="#FF0D0D""##));
+        assert!(ethanol.contains(">OH</text>"));
+
+        assert!(
+            svg("C").contains(r#">CH<tspan font-size="70%" baseline-shift="sub">4</tspan></text>"#)
+        );
+        assert!(svg("C[NH3+]").contains(
+            r#">NH<tspan font-size="70%" baseline-shift="sub">3</tspan><tspan font-size="70%" baseline-shift="super">+</tspan></text>"#
+        ));
+        assert_eq!(1, count(&svg("[13CH3]C"), "text"));
+    }
+
+    #[test]
+    fn colors_are_optional() {
+        let options = SvgOptions {
+            color_atoms: false,
+            ..Default::default()
+        };
+        let parser = SmilesParser::new();
+        let svg = parser.parse("CCO").unwrap().to_svg(&options);
+
+        assert!(!svg.contains("#FF0D0D"));
+    }
+
+    #[test]
+    fn highlights() {
+        let options = SvgOptions {
+            highlight_atoms: vec![AtomIdx::new(2), AtomIdx::new(7)],
+            highlight_bonds: vec![BondIdx::new(1)],
+            highlight_color: "#B0FFB0".to_string(),
+            ..Default::default()
+        };
+        let parser = SmilesParser::new();
+        let highlighted = parser.parse("CCO").unwrap().to_svg(&options);
+
+        // atom 7 doesn't exist
+        assert_eq!(1, count(&highlighted, "circle"));
+        assert_eq!(3, count(&highlighted, "line"));
+        assert_eq!(2, highlighted.matches("#B0FFB0").count());
+        assert_eq!(0, count(&svg("CCO"), "circle"));
+    }
+
+    #[test]
+    fn grid() {
+        let parser = SmilesParser::new();
+        let mols: Vec<_> = ["CCO", "c1ccccc1", "CC(=O)O"]
+            .iter()
+            .map(|smiles| parser.parse(smiles).unwrap())
+            .collect();
+        let options = SvgOptions {
+            width: 200.0,
+            height: 150.0,
+            ..Default::default()
+        };
+        let grid = grid_svg(&mols, &["ethanol", "benzene & co"], 2, &options);
+
+        // 2 rows of 2 cells, each with room for a label
+        assert!(
+            grid.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="342""#)
+        );
+        assert!(grid.contains(r#"<svg x="200" y="0" "#));
+        assert!(grid.contains(r#"<svg x="0" y="171" "#));
+        assert!(grid.contains(">ethanol</text>"));
+        assert!(grid.contains(">benzene &amp; co</text>"));
+        // the acid has no label
+        assert_eq!(2 + 3, grid.matches("</text>").count());
+
+        let unlabeled = grid_svg(&mols, &[], 5, &options);
+        assert!(unlabeled
+            .starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="600" height="150""#));
+        assert!(grid_svg(&[], &[], 0, &options).contains(r#"height="0""#));
+    }
+
+    #[test]
+    fn highlights_in_colors() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("OCCN").unwrap();
+        let options = SvgOptions {
+            highlights: vec![
+                Highlight::atoms_and_bonds(&mol, &[AtomIdx::new(0), AtomIdx::new(1)], "#FF0000"),
+                Highlight::atoms_and_bonds(&mol, &[AtomIdx::new(3)], "#0000FF"),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(vec![BondIdx::new(0)], options.highlights[0].bonds);
+        assert!(options.highlights[1].bonds.is_empty());
+
+        let highlighted = mol.to_svg(&options);
+        assert_eq!(3, count(&highlighted, "circle"));
+        // 2 discs and a line under the bond
+        assert_eq!(3, highlighted.matches(r##""#FF0000""##).count());
+        assert_eq!(1, highlighted.matches(r##""#0000FF""##).count());
+    }
+
+    #[test]
+    fn stereo_wedges() {
+        let solid = svg("N[C@@H](C)C(=O)O");
+        let hashed = svg("N[C@H](C)C(=O)O");
+
+        // one wedge each, of opposite kinds: a solid polygon or 6 hash lines
+        assert_eq!(1, count(&solid, "polygon") + count(&hashed, "polygon"));
+        let lines = |svg: &str| count(svg, "line");
+        assert_eq!(
+            6,
+            (lines(&solid) as i32 - lines(&hashed) as i32).unsigned_abs()
+        );
+        assert_eq!(0, count(&svg("NC(C)C(=O)O"), "polygon"));
+    }
+
+    #[test]
+    fn wedge_follows_chirality() {
+        let parser = SmilesParser::new();
+        let wedges = |smiles: &str, positions: &[(f64, f64)]| {
+            let mol = parser.parse(smiles).unwrap();
+            let positions: Vec<Point> = positions.iter().map(|(x, y)| Point::new(*x, *y)).collect();
+            super::wedges(&mol, &positions)
+        };
+        let wedge = |center: usize, neighbor: usize, hashed: bool| {
+            vec![(NodeIndex::new(center), NodeIndex::new(neighbor), hashed)]
+        };
+
+        // F up, then Cl, Br and I anticlockwise
+        let cross = [(0.0, 1.0), (0.0, 0.0), (-1.0, 0.0), (0.0, -1.0), (1.0, 0.0)];
+        assert_eq!(wedge(1, 0, false), wedges("F[C@](Cl)(Br)I", &cross));
+        assert_eq!(wedge(1, 0, true), wedges("F[C@@](Cl)(Br)I", &cross));
+
+        // L-alanine: seen from the hydrogen, N, CH3 and COOH are anticlockwise, which
+        // these positions give with N behind the plane and the hydrogen in front
+        let alanine = [
+            (-0.87, 0.5),
+            (0.0, 0.0),
+            (0.0, -1.0),
+            (0.87, 0.5),
+            (1.74, 0.0),
+            (0.87, 1.5),
+        ];
+        assert_eq!(wedge(1, 0, true), wedges("N[C@@H](C)C(=O)O", &alanine));
+        assert_eq!(wedge(1, 0, false), wedges("N[C@H](C)C(=O)O", &alanine));
+    }
+}
@@ -0,0 +1,368 @@
+//! Tautomers: structures that only differ in where hydrogens are and which bonds are
+//! double, e.g. the keto and enol forms of acetone. A hydrogen moves from an atom to
+//! another of the same conjugated system, whose double bonds are then rearranged:
+//! keto-enol, imine-enamine, amide-imidic acid, amidine, 2-pyridone-2-hydroxypyridine
+//! and the NH of imidazoles. Hydrogens don't move from a carbon to another carbon.
+
+use alloc::collections::btree_map::Entry;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::aromaticity::{kekulize, set_aromaticity, AromaticityModel};
+use crate::types::{BondOrder, Mol};
+use crate::valence;
+
+/// Enumeration stops after this many tautomers.
+pub const MAX_TAUTOMERS: usize = 1000;
+
+/// The tautomers of the molecule, itself included, ordered by canonical SMILES.
+/// Stereo of the atoms and bonds that change is cleared. A molecule that can't be
+/// kekulized is its only tautomer.
+pub fn enumerate(mol: &Mol) -> Vec<Mol> {
+    let mut start = mol.clone();
+    if kekulize(&mut start).is_err() {
+        return vec![mol.clone()];
+    }
+    // hydrogens are counted explicitly while they move
+    for atom in start.graph.node_weights_mut() {
+        atom.explicit_h = Some(atom.total_num_hs());
+        atom.implicit_h = 0;
+    }
+
+    // by canonical SMILES
+    let mut tautomers: BTreeMap<String, Mol> = BTreeMap::new();
+    let tautomer = normalized(&start);
+    tautomers.insert(tautomer.to_canonical_smiles(), tautomer);
+    let mut queue = VecDeque::from([start]);
+    while let Some(mol) = queue.pop_front() {
+        for shifted in shifts(&mol) {
+            if tautomers.len() >= MAX_TAUTOMERS {
+                break;
+            }
+            let tautomer = normalized(&shifted);
+            let smiles = tautomer.to_canonical_smiles();
+            if let Entry::Vacant(entry) = tautomers.entry(smiles) {
+                entry.insert(tautomer);
+                queue.push_back(shifted);
+            }
+        }
+    }
+    tautomers.into_values().collect()
+}
+
+/// The tautomer with the highest [`score`], the first by canonical SMILES if several
+/// have the same. The same for all the tautomers of a molecule, so that they can be
+/// compared by canonical SMILES.
+pub fn canonical(mol: &Mol) -> Mol {
+    let mut best: Option<(i32, Mol)> = None;
+    for tautomer in enumerate(mol) {
+        let score = score(&tautomer);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, tautomer));
+        }
+    }
+    // unwrap: the molecule is one of its tautomers
+    best.unwrap().1
+}
+
+/// How stable a tautomer is expected to be, higher is more stable: 250 for each aromatic
+/// ring of carbons, 100 for other aromatic rings, 2 for each C=O, 1 for each methyl and
+/// -1 for each hydrogen on sulfur.
+pub fn score(mol: &Mol) -> i32 {
+    let graph = &mol.graph;
+    let mut score = 0;
+    for ring in mol.ring_info().rings() {
+        let aromatic = ring
+            .bonds
+            .iter()
+            .all(|bond| graph[EdgeIndex::from(*bond)].order == BondOrder::Aromatic);
+        if aromatic {
+            let carbons = ring
+                .atoms
+                .iter()
+                .all(|atom| graph[NodeIndex::from(*atom)].number == 6);
+            score += if carbons { 250 } else { 100 };
+        }
+    }
+    for edge in graph.edge_references() {
+        let numbers = (graph[edge.source()].number, graph[edge.target()].number);
+        if edge.weight().order == BondOrder::Double && matches!(numbers, (6, 8) | (8, 6)) {
+            score += 2;
+        }
+    }
+    for node in graph.node_indices() {
+        let atom = &graph[node];
+        match atom.number {
+            6 if atom.total_num_hs() == 3 => score += 1,
+            16 => score -= atom.total_num_hs() as i32,
+            _ => {}
+        }
+    }
+    score
+}
+
+/// The molecules with one hydrogen moved, from a kekulized molecule with explicit
+/// hydrogens.
+fn shifts(mol: &Mol) -> Vec<Mol> {
+    let graph = &mol.graph;
+    let mobile: Vec<bool> = graph
+        .node_indices()
+        .map(|node| is_mobile(mol, node))
+        .collect();
+
+    // atoms of conjugated systems: mobile atoms with a double bond to another one
+    let mut system: Vec<Option<usize>> = vec![None; graph.node_count()];
+    let mut systems = 0;
+    for root in graph.node_indices() {
+        if system[root.index()].is_some() || !in_double_bond(mol, root, &mobile) {
+            continue;
+        }
+        system[root.index()] = Some(systems);
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            for neighbor in graph.neighbors(node) {
+                if system[neighbor.index()].is_none() && in_double_bond(mol, neighbor, &mobile) {
+                    system[neighbor.index()] = Some(systems);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        systems += 1;
+    }
+
+    let mut shifted = vec![];
+    for current in 0..systems {
+        let acceptors: Vec<NodeIndex> = graph
+            .node_indices()
+            .filter(|node| system[node.index()] == Some(current))
+            .collect();
+        // the atoms of the system and the saturated ones next to it
+        let donors: Vec<NodeIndex> = graph
+            .node_indices()
+            .filter(|node| {
+                graph[*node].total_num_hs() > 0
+                    && (system[node.index()] == Some(current)
+                        || mobile[node.index()]
+                            && system[node.index()].is_none()
+                            && graph
+                                .neighbors(*node)
+                                .any(|n| system[n.index()] == Some(current)))
+            })
+            .collect();
+        for donor in &donors {
+            for acceptor in &acceptors {
+                let carbons = graph[*donor].number == 6 && graph[*acceptor].number == 6;
+                if donor == acceptor || carbons {
+                    continue;
+                }
+                if let Some(mol) = shift(mol, *donor, *acceptor, &acceptors) {
+                    shifted.push(mol);
+                }
+            }
+        }
+    }
+    shifted
+}
+
+/// Moves a hydrogen from the donor to the acceptor, and finds double bonds for the
+/// system with the donor. `None` if there are none that give each atom its lowest
+/// valence.
+fn shift(mol: &Mol, donor: NodeIndex, acceptor: NodeIndex, system: &[NodeIndex]) -> Option<Mol> {
+    let mut mol = mol.clone();
+    let mut atoms = system.to_vec();
+    if !atoms.contains(&donor) {
+        atoms.push(donor);
+    }
+
+    for (atom, change) in [(donor, -1), (acceptor, 1)] {
+        let atom = &mut mol.graph[atom];
+        // unwrap: all hydrogens are explicit
+        atom.explicit_h = Some(atom.explicit_h.unwrap().checked_add_signed(change)?);
+        atom.chirality = None;
+        atom.chiral_neighbors = vec![];
+    }
+    for atom in &atoms {
+        mol.graph[*atom].aromatic = true;
+    }
+    let edges: Vec<EdgeIndex> = mol
+        .graph
+        .edge_indices()
+        .filter(|edge| {
+            // unwrap: edge comes from the graph
+            let (a, b) = mol.graph.edge_endpoints(*edge).unwrap();
+            atoms.contains(&a) || atoms.contains(&b)
+        })
+        .collect();
+    for edge in edges {
+        // unwrap: edge comes from the graph
+        let (a, b) = mol.graph.edge_endpoints(edge).unwrap();
+        let bond = &mut mol.graph[edge];
+        if atoms.contains(&a) && atoms.contains(&b) {
+            bond.order = BondOrder::Aromatic;
+            bond.stereo = None;
+        }
+        bond.direction = None;
+    }
+    kekulize(&mut mol).ok()?;
+    // e.g. not a carbon that gave away the hydrogen of a C=CH without getting a bond
+    atoms
+        .iter()
+        .all(|atom| is_mobile(&mol, *atom))
+        .then_some(mol)
+}
+
+/// Carbon, nitrogen, oxygen or sulfur, uncharged and at its lowest valence.
+fn is_mobile(mol: &Mol, node: NodeIndex) -> bool {
+    let atom = &mol.graph[node];
+    let valence = valence::bond_order_sum(mol, node) + atom.total_num_hs();
+    matches!(atom.number, 6 | 7 | 8 | 16)
+        && atom.charge == 0
+        && valence::default_valences(atom.number).first() == Some(&valence)
+}
+
+fn in_double_bond(mol: &Mol, node: NodeIndex, mobile: &[bool]) -> bool {
+    mobile[node.index()]
+        && mol
+            .graph
+            .edges(node)
+            .any(|edge| edge.weight().order == BondOrder::Double && mobile[edge.target().index()])
+}
+
+/// The molecule with aromaticity perceived, and hydrogens written only where the valence
+/// model doesn't give them.
+fn normalized(mol: &Mol) -> Mol {
+    let mut mol = mol.clone();
+    // a kekulized molecule can be kekulized again
+    let _ = set_aromaticity(&mut mol, AromaticityModel::Default);
+    for node in mol.graph.node_indices() {
+        let hydrogens = mol.graph[node].total_num_hs();
+        mol.graph[node].explicit_h = None;
+        let implicit = valence::implicit_h_count(&mol, node);
+        let atom = &mut mol.graph[node];
+        if implicit == hydrogens {
+            atom.implicit_h = implicit;
+        } else {
+            atom.explicit_h = Some(hydrogens);
+            atom.implicit_h = 0;
+        }
+    }
+    mol
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::Mol;
+
+    use super::{canonical, enumerate, score};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn canonical_smiles(smiles: &str) -> String {
+        mol(smiles).to_canonical_smiles()
+    }
+
+    fn tautomers(smiles: &str) -> Vec<String> {
+        enumerate(&mol(smiles))
+            .iter()
+            .map(Mol::to_canonical_smiles)
+            .collect()
+    }
+
+    fn canonical_tautomer(smiles: &str) -> String {
+        canonical(&mol(smiles)).to_canonical_smiles()
+    }
+
+    #[test]
+    fn keto_enol() {
+        let mut expected = vec![canonical_smiles("CC(=O)C"), canonical_smiles("C=C(O)C")];
+        expected.sort();
+
+        assert_eq!(expected, tautomers("CC(=O)C"));
+        assert_eq!(expected, tautomers("C=C(O)C"));
+    }
+
+    #[test]
+    fn no_tautomers() {
+        assert_eq!(vec![canonical_smiles("CCO")], tautomers("CCO"));
+        assert_eq!(vec![canonical_smiles("c1ccccc1")], tautomers("c1ccccc1"));
+        // carbons don't exchange hydrogens
+        assert_eq!(vec![canonical_smiles("C=CC")], tautomers("C=CC"));
+        assert_eq!(vec![canonical_smiles("CC#N")], tautomers("CC#N"));
+    }
+
+    #[test]
+    fn nitrogen_tautomers() {
+        assert!(tautomers("CC(=O)N").contains(&canonical_smiles("CC(O)=N")));
+        assert!(tautomers("CC=NC").contains(&canonical_smiles("C=CNC")));
+        // the NH of imidazole
+        assert_eq!(tautomers("Cc1cnc[nH]1"), tautomers("Cc1c[nH]cn1"));
+        assert!(tautomers("Cc1cnc[nH]1").contains(&canonical_smiles("Cc1c[nH]cn1")));
+    }
+
+    #[test]
+    fn canonical_tautomers() {
+        assert_eq!(canonical_smiles("CC(=O)C"), canonical_tautomer("C=C(O)C"));
+        assert_eq!(canonical_smiles("CC(=O)N"), canonical_tautomer("CC(O)=N"));
+        assert_eq!(
+            canonical_smiles("Oc1ccccc1"),
+            canonical_tautomer("O=C1C=CC=CC1")
+        );
+        assert_eq!(
+            canonical_smiles("O=c1cccc[nH]1"),
+            canonical_tautomer("Oc1ccccn1")
+        );
+        assert_eq!(
+            canonical_tautomer("O=c1cccc[nH]1"),
+            canonical_tautomer("Oc1ccccn1")
+        );
+        // guanine
+        assert_eq!(
+            canonical_tautomer("Nc1nc2[nH]cnc2c(=O)[nH]1"),
+            canonical_tautomer("Nc1nc(O)c2[nH]cnc2n1")
+        );
+        assert_eq!(
+            canonical_tautomer("CC(=O)CC(=O)C"),
+            canonical_tautomer("CC(O)=CC(=O)C")
+        );
+    }
+
+    #[test]
+    fn same_canonical_tautomer_for_all() {
+        for smiles in ["CC(=O)CC(=O)C", "O=c1cc[nH]c(=O)[nH]1", "CC(=N)N"] {
+            let expected = canonical_tautomer(smiles);
+            for tautomer in enumerate(&mol(smiles)) {
+                assert_eq!(
+                    expected,
+                    canonical(&tautomer).to_canonical_smiles(),
+                    "{}",
+                    tautomer.to_canonical_smiles()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_tautomer_keeps_other_stereo() {
+        assert_eq!(
+            canonical_smiles("C[C@H](N)CC(=O)C"),
+            canonical_tautomer("C[C@H](N)CC(O)=C")
+        );
+    }
+
+    #[test]
+    fn scores() {
+        assert_eq!(250, score(&mol("c1ccccc1")));
+        assert_eq!(102, score(&mol("O=c1cccc[nH]1")));
+        assert_eq!(4, score(&mol("CC(=O)C")));
+        assert_eq!(-2, score(&mol("S")));
+    }
+}
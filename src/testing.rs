@@ -0,0 +1,169 @@
+//! Proptest strategies for the tests: random molecules and SMILES-like strings, and the
+//! round trips every molecule has to survive. A change to how molecules are read or
+//! written has to keep the round trip tests below passing, on both kinds of input.
+//!
+//! Other crates get it with the `testing` feature, e.g. as a dev-dependency, to check
+//! that molecules they build or transform still round trip.
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::builder::MolBuilder;
+use crate::canon;
+use crate::smiles::SmilesParser;
+use crate::types::{Atom, AtomIdx, BondOrder, Mol};
+use crate::valence;
+
+/// The organic subset, carbon, nitrogen and oxygen more often.
+const NUMBERS: [u32; 14] = [5, 6, 6, 6, 7, 7, 8, 8, 9, 15, 16, 17, 35, 53];
+
+/// Strings made of what SMILES are made of, to get past the first character. Most don't
+/// parse.
+pub fn smiles_like() -> impl Strategy<Value = String> {
+    // unwrap: a valid regex
+    proptest::string::string_regex(r"[BCNOPSFIbcnops*\[\]()=#$:/\\.%@+\-0-9HTDlrea]{0,40}").unwrap()
+}
+
+/// Random molecules of up to 16 atoms: atoms of the organic subset, some of them charged,
+/// with an isotope or with explicit hydrogens, bonded by a random forest and a few ring
+/// bonds. Bond orders are lowered to keep the valences normal. No aromaticity or stereo.
+pub fn mols() -> impl Strategy<Value = Mol> {
+    let atoms = proptest::collection::vec(atom(), 1..=16);
+    let bonds = proptest::collection::vec(
+        proptest::option::weighted(0.9, (any::<Index>(), bond_order())),
+        16,
+    );
+    let rings = proptest::collection::vec((any::<Index>(), any::<Index>(), bond_order()), 0..4);
+    (atoms, bonds, rings).prop_map(|(atoms, bonds, rings)| {
+        let mut builder = MolBuilder::new();
+        // bond orders the atoms can still take
+        let mut free: Vec<u8> = vec![];
+        for mut atom in atoms.iter().cloned() {
+            // unwrap: elements of the organic subset have valences
+            let max = valence::max_valence(&atom).unwrap();
+            atom.explicit_h = atom.explicit_h.map(|h| h.min(max));
+            free.push(max - atom.explicit_h.unwrap_or(0));
+            builder.add_atom(atom);
+        }
+
+        let mut add_bond = |a: usize, b: usize, order: BondOrder| {
+            let order = valence::bond_valence(order).min(free[a]).min(free[b]);
+            let order = match order {
+                0 => return,
+                1 => BondOrder::Single,
+                2 => BondOrder::Double,
+                _ => BondOrder::Triple,
+            };
+            let (start, end) = (AtomIdx::new(a), AtomIdx::new(b));
+            // rejected from an atom to itself or when the atoms are already bonded
+            if builder.add_bond(start, end, order).is_ok() {
+                free[a] -= valence::bond_valence(order);
+                free[b] -= valence::bond_valence(order);
+            }
+        };
+        // each atom after the first to one before it, unless it starts a new fragment
+        for (atom, bond) in bonds.iter().enumerate().take(atoms.len()).skip(1) {
+            if let Some((parent, order)) = bond {
+                add_bond(parent.index(atom), atom, *order);
+            }
+        }
+        for (a, b, order) in rings {
+            add_bond(a.index(atoms.len()), b.index(atoms.len()), order);
+        }
+
+        // unwrap: the bonds fit in the valences
+        builder.finalize().unwrap()
+    })
+}
+
+fn atom() -> impl Strategy<Value = Atom> {
+    let bracket = (
+        -1i8..=1,
+        proptest::option::of(proptest::sample::select(&[2u16, 13, 15, 18][..])),
+        0u8..=3,
+    );
+    (
+        proptest::sample::select(&NUMBERS[..]),
+        proptest::option::weighted(0.2, bracket),
+    )
+        .prop_map(|(number, bracket)| match bracket {
+            None => Atom {
+                number,
+                ..Default::default()
+            },
+            Some((charge, isotope, hydrogens)) => Atom {
+                number,
+                charge,
+                isotope,
+                explicit_h: Some(hydrogens),
+                ..Default::default()
+            },
+        })
+}
+
+fn bond_order() -> impl Strategy<Value = BondOrder> {
+    prop_oneof![
+        6 => Just(BondOrder::Single),
+        2 => Just(BondOrder::Double),
+        1 => Just(BondOrder::Triple),
+    ]
+}
+
+/// Checks that the molecule's SMILES parses to the same structure, and that its canonical
+/// SMILES parses to the same structure with its aromaticity perceived, and to a molecule
+/// with the same canonical SMILES.
+pub fn check_round_trips(mol: &Mol) -> Result<(), TestCaseError> {
+    let parser = SmilesParser::new();
+
+    let smiles = mol.to_smiles();
+    let parsed = parser.parse(&smiles);
+    prop_assert!(parsed.is_ok(), "{} doesn't parse: {:?}", smiles, parsed);
+    // unwrap: checked above
+    let parsed = parsed.unwrap();
+    prop_assert!(mol.is_same_structure(&parsed), "written as {}", smiles);
+
+    let canonical = mol.to_canonical_smiles();
+    let parsed = parser.parse(&canonical);
+    prop_assert!(parsed.is_ok(), "{} doesn't parse: {:?}", canonical, parsed);
+    // unwrap: checked above
+    let parsed = parsed.unwrap();
+    prop_assert!(
+        canon::perceived(mol).is_same_structure(&parsed),
+        "canonical {}",
+        canonical
+    );
+    prop_assert_eq!(&canonical, &parsed.to_canonical_smiles());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use crate::smiles::SmilesParser;
+    use crate::valence;
+
+    use super::{check_round_trips, mols, smiles_like};
+
+    proptest! {
+        #[test]
+        fn generated_mols_round_trip(mol in mols()) {
+            check_round_trips(&mol)?;
+        }
+
+        #[test]
+        fn parsed_mols_round_trip(smiles in smiles_like()) {
+            if let Ok(mol) = SmilesParser::new().parse(&smiles) {
+                check_round_trips(&mol)?;
+            }
+        }
+
+        #[test]
+        fn mols_are_valid(mol in mols()) {
+            prop_assert!((1..=16).contains(&mol.num_atoms()));
+            for node in mol.graph.node_indices() {
+                prop_assert!(valence::is_valence_allowed(&mol, node));
+            }
+        }
+    }
+}
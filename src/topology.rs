@@ -0,0 +1,212 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::types::{AtomIdx, Mol};
+
+/// Whether each pair of atoms is bonded, indexed like the atoms.
+pub fn adjacency_matrix(mol: &Mol) -> Vec<Vec<bool>> {
+    let n = mol.graph.node_count();
+    let mut matrix = vec![vec![false; n]; n];
+    for edge in mol.graph.edge_indices() {
+        // unwrap: edge comes from the graph
+        let (a, b) = mol.graph.edge_endpoints(edge).unwrap();
+        matrix[a.index()][b.index()] = true;
+        matrix[b.index()][a.index()] = true;
+    }
+    matrix
+}
+
+/// The number of bonds of the shortest path between each pair of atoms, indexed like the
+/// atoms, `None` between atoms of different fragments.
+pub fn distance_matrix(mol: &Mol) -> Vec<Vec<Option<usize>>> {
+    mol.graph
+        .node_indices()
+        .map(|node| distances_from(mol, node))
+        .collect()
+}
+
+/// The number of bonds from the atom to each atom, `None` for other fragments.
+pub(crate) fn distances_from(mol: &Mol, root: NodeIndex) -> Vec<Option<usize>> {
+    bfs(mol, root)
+        .iter()
+        .map(|visit| visit.map(|(distance, _)| distance))
+        .collect()
+}
+
+/// Whether each bond is in a ring, indexed like the bonds: whether it isn't a bridge,
+/// whose removal would split its fragment. Linear time, unlike [`RingInfo`](crate::RingInfo).
+pub(crate) fn ring_bonds(mol: &Mol) -> Vec<bool> {
+    let graph = &mol.graph;
+    let mut in_ring = vec![true; graph.edge_count()];
+    // order in which the depth first search reaches each atom, and the lowest order
+    // reachable from its subtree through one bond that isn't a tree bond
+    let mut order: Vec<Option<usize>> = vec![None; graph.node_count()];
+    let mut low = vec![0; graph.node_count()];
+    let mut visited = 0;
+    let neighbors = |node: NodeIndex| -> Vec<(EdgeIndex, NodeIndex)> {
+        graph
+            .edges(node)
+            .map(|edge| (edge.id(), edge.target()))
+            .collect()
+    };
+
+    for root in graph.node_indices() {
+        if order[root.index()].is_some() {
+            continue;
+        }
+        order[root.index()] = Some(visited);
+        low[root.index()] = visited;
+        visited += 1;
+        // without recursion, long chains would overflow the stack
+        let mut stack = vec![(root, None, neighbors(root))];
+        while let Some((node, tree_edge, remaining)) = stack.last_mut() {
+            let (node, tree_edge) = (*node, *tree_edge);
+            if let Some((edge, next)) = remaining.pop() {
+                if Some(edge) == tree_edge {
+                    continue;
+                }
+                match order[next.index()] {
+                    Some(next_order) => low[node.index()] = low[node.index()].min(next_order),
+                    None => {
+                        order[next.index()] = Some(visited);
+                        low[next.index()] = visited;
+                        visited += 1;
+                        stack.push((next, Some(edge), neighbors(next)));
+                    }
+                }
+                continue;
+            }
+            stack.pop();
+            if let (Some(edge), Some((parent, _, _))) = (tree_edge, stack.last()) {
+                let parent = parent.index();
+                low[parent] = low[parent].min(low[node.index()]);
+                // unwrap: the parent was reached before
+                if low[node.index()] > order[parent].unwrap() {
+                    in_ring[edge.index()] = false;
+                }
+            }
+        }
+    }
+    in_ring
+}
+
+/// The atoms of a shortest path from `a` to `b`, both included, empty if they're in
+/// different fragments or aren't atoms of the molecule. Of several shortest paths, always
+/// the same one.
+pub fn shortest_path(mol: &Mol, a: AtomIdx, b: AtomIdx) -> Vec<AtomIdx> {
+    let count = mol.graph.node_count();
+    if a.index() >= count || b.index() >= count {
+        return vec![];
+    }
+    // from b, so that the parents lead to it
+    let visits = bfs(mol, b.into());
+    let mut path = vec![];
+    let mut current = a.index();
+    while let Some((_, parent)) = visits[current] {
+        path.push(AtomIdx::new(current));
+        match parent {
+            Some(parent) => current = parent.index(),
+            None => break,
+        }
+    }
+    path
+}
+
+/// For each atom, its distance from `root` and its parent on a shortest path, visiting
+/// neighbors in increasing order. `None`: not reachable.
+fn bfs(mol: &Mol, root: NodeIndex) -> Vec<Option<(usize, Option<NodeIndex>)>> {
+    let graph = &mol.graph;
+    let mut visits = vec![None; graph.node_count()];
+    visits[root.index()] = Some((0, None));
+    let mut queue = VecDeque::from([(root, 0)]);
+    while let Some((node, distance)) = queue.pop_front() {
+        let mut neighbors: Vec<NodeIndex> = graph.neighbors(node).collect();
+        neighbors.sort();
+        for neighbor in neighbors {
+            if visits[neighbor.index()].is_none() {
+                visits[neighbor.index()] = Some((distance + 1, Some(node)));
+                queue.push_back((neighbor, distance + 1));
+            }
+        }
+    }
+    visits
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+    use crate::types::{AtomIdx, Mol};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    fn path(smiles: &str, a: usize, b: usize) -> Vec<usize> {
+        mol(smiles)
+            .shortest_path(AtomIdx::new(a), AtomIdx::new(b))
+            .iter()
+            .map(|atom| atom.index())
+            .collect()
+    }
+
+    #[test]
+    fn adjacency() {
+        assert_eq!(
+            vec![
+                vec![false, true, false],
+                vec![true, false, true],
+                vec![false, true, false],
+            ],
+            mol("CCO").adjacency_matrix()
+        );
+        assert_eq!(vec![vec![false]], mol("C").adjacency_matrix());
+    }
+
+    #[test]
+    fn distances() {
+        let distances = mol("CC(C)CO").distance_matrix();
+
+        assert_eq!(
+            vec![Some(0), Some(1), Some(2), Some(2), Some(3)],
+            distances[0]
+        );
+        assert_eq!(Some(3), distances[2][4]);
+        assert_eq!(distances[4][2], distances[2][4]);
+        // across the ring
+        assert_eq!(Some(3), mol("C1CCCCC1").distance_matrix()[0][3]);
+        assert_eq!(
+            vec![vec![Some(0), None], vec![None, Some(0)]],
+            mol("C.O").distance_matrix()
+        );
+    }
+
+    #[test]
+    fn shortest_paths() {
+        assert_eq!(vec![0, 1, 3, 4], path("CC(C)CO", 0, 4));
+        assert_eq!(vec![4, 3, 1, 0], path("CC(C)CO", 4, 0));
+        assert_eq!(vec![2], path("CC(C)CO", 2, 2));
+        // the same length both ways around the ring
+        assert_eq!(vec![0, 1, 2, 3], path("C1CCCCC1", 0, 3));
+        assert_eq!(vec![0, 5, 4], path("C1CCCCC1", 0, 4));
+        assert!(path("C.O", 0, 1).is_empty());
+        assert!(path("CC", 0, 5).is_empty());
+    }
+
+    #[test]
+    fn ring_bonds() {
+        assert_eq!(
+            vec![true, true, true, false, false],
+            super::ring_bonds(&mol("C1CC1CO"))
+        );
+        // the bond between the two rings isn't in either
+        let in_ring = super::ring_bonds(&mol("C1CC1C1CC1"));
+        assert_eq!(vec![3], (0..7).filter(|i| !in_ring[*i]).collect::<Vec<_>>());
+        assert_eq!(vec![false], super::ring_bonds(&mol("C.CC")));
+        assert!(super::ring_bonds(&mol("C12CC1CC2")).iter().all(|b| *b));
+    }
+}
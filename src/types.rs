@@ -1,22 +1,314 @@
-use petgraph::{
-    graph::{EdgeIndex, NodeIndex},
-    Graph,
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
+
+use crate::aromaticity::{self, AromaticityModel};
+use crate::ascii;
+use crate::conformer::{self, EmbedOptions};
+use crate::cxsmiles::{self, MolExtensions};
+use crate::descriptors::Descriptors;
+use crate::environment::{self, AtomEnvironment};
+#[cfg(feature = "inchi")]
+use crate::error::InchiError;
+use crate::error::{JsonError, KekulizeError, MolfileError, MrvError, SanitizeError};
+use crate::fingerprint::{self, BitVec, Fingerprint};
+#[cfg(feature = "std")]
+use crate::fragmentation::{self, FragmentRules};
+#[cfg(feature = "std")]
+use crate::functional_groups::{self, FunctionalGroupHit};
+#[cfg(feature = "inchi")]
+use crate::inchi;
+use crate::layout::{self, Conformer};
+use crate::lexer::Span;
+use crate::props::{FromProp, PropValue, Props};
+use crate::rings::RingInfo;
+use crate::rules::{Criterion, RuleSet};
+use crate::salts::{self, SaltPolicy};
+use crate::smarts::Smarts;
+use crate::svg::{self, SvgOptions};
+use crate::writer::SmilesWriteOptions;
+use crate::{
+    canon, compare, formula, fragments, hydrogens, json, molfile, mrv, sanitize, scaffold,
+    structure_key, substruct, tautomer, topology, valence, writer,
 };
 
-#[derive(Debug, PartialEq, Eq)]
+/// Index of an atom in a [`Mol`], in the order the atoms were added.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct AtomIdx(usize);
+
+impl AtomIdx {
+    pub fn new(index: usize) -> AtomIdx {
+        AtomIdx(index)
+    }
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for AtomIdx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<NodeIndex> for AtomIdx {
+    fn from(node: NodeIndex) -> Self {
+        AtomIdx(node.index())
+    }
+}
+
+impl From<AtomIdx> for NodeIndex {
+    fn from(atom: AtomIdx) -> Self {
+        NodeIndex::new(atom.0)
+    }
+}
+
+/// Index of a bond in a [`Mol`], in the order the bonds were added.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct BondIdx(usize);
+
+impl BondIdx {
+    pub fn new(index: usize) -> BondIdx {
+        BondIdx(index)
+    }
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for BondIdx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<EdgeIndex> for BondIdx {
+    fn from(edge: EdgeIndex) -> Self {
+        BondIdx(edge.index())
+    }
+}
+
+impl From<BondIdx> for EdgeIndex {
+    fn from(bond: BondIdx) -> Self {
+        EdgeIndex::new(bond.0)
+    }
+}
+
+/// Tetrahedral chirality, as written in the SMILES.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Chirality {
+    /// `@`: looking from the first neighbor, the others are arranged anticlockwise.
+    Anticlockwise,
+    /// `@@`: looking from the first neighbor, the others are arranged clockwise.
+    Clockwise,
+}
+
+impl Chirality {
+    pub fn inverted(self) -> Chirality {
+        match self {
+            Chirality::Anticlockwise => Chirality::Clockwise,
+            Chirality::Clockwise => Chirality::Anticlockwise,
+        }
+    }
+
+    /// Returns the chirality that describes the same configuration when the neighbors
+    /// are listed in `to` order instead of `from` order.
+    /// `None` if `to` isn't a permutation of `from`, e.g. if it has a neighbor twice.
+    pub fn reordered(self, from: &[AtomIdx], to: &[AtomIdx]) -> Option<Chirality> {
+        if from.len() != to.len() {
+            return None;
+        }
+        let mut positions = to
+            .iter()
+            .map(|n| from.iter().position(|m| m == n))
+            .collect::<Option<Vec<usize>>>()?;
+        // count the swaps needed to sort the permutation
+        let mut swaps = 0;
+        for i in 0..positions.len() {
+            while positions[i] != i {
+                let j = positions[i];
+                if positions[j] == j {
+                    // j is in `to` twice
+                    return None;
+                }
+                positions.swap(i, j);
+                swaps += 1;
+            }
+        }
+        Some(if swaps % 2 == 0 {
+            self
+        } else {
+            self.inverted()
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Atom {
     pub number: u32,
+    pub aromatic: bool, // written lowercase in the smiles
+    pub isotope: Option<u16>,
+    pub charge: i8,
+    pub explicit_h: Option<u8>, // only set for bracket atoms, otherwise hydrogens are implicit
+    pub map_number: Option<u32>, // `:n` in brackets, e.g. atom-atom mapping in reactions
+    // relative to the neighbors in chiral_neighbors order, with an implicit hydrogen first
+    pub chirality: Option<Chirality>,
+    pub chiral_neighbors: Vec<AtomIdx>,
+    pub implicit_h: u8, // derived from the valence model, 0 for bracket atoms
+    // unpaired electrons: what bracket atoms lack of a normal valence, e.g. 1 for `[CH3]`
+    pub num_radical_electrons: u8,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Props::is_empty"))]
+    pub props: Props, // user annotations, not written to SMILES
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub span: SourceSpan, // where the atom is written, if it was parsed
+}
+
+impl Atom {
+    /// Hydrogens attached to this atom, written in a bracket or implicit.
+    pub fn total_num_hs(&self) -> u8 {
+        self.explicit_h.unwrap_or(0) + self.implicit_h
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum BondOrder {
+    #[default]
+    Single,
+    Double,
+    Triple,
+    Quadruple,
+    Aromatic,
+}
+
+impl BondOrder {
+    pub fn from_symbol(symbol: char) -> Option<BondOrder> {
+        match symbol {
+            '-' => Some(BondOrder::Single),
+            '=' => Some(BondOrder::Double),
+            '#' => Some(BondOrder::Triple),
+            '$' => Some(BondOrder::Quadruple),
+            ':' => Some(BondOrder::Aromatic),
+            _ => None,
+        }
+    }
+}
+
+/// Direction of a single bond written with `/` (up) or `\` (down),
+/// going from the bond's first atom to its second, see [`Mol::bond_atoms`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum BondDirection {
+    Up,
+    Down,
+}
+
+impl BondDirection {
+    pub fn inverted(self) -> BondDirection {
+        match self {
+            BondDirection::Up => BondDirection::Down,
+            BondDirection::Down => BondDirection::Up,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum DoubleBondConfig {
+    Cis,
+    Trans,
+}
+
+impl DoubleBondConfig {
+    pub fn inverted(self) -> DoubleBondConfig {
+        match self {
+            DoubleBondConfig::Cis => DoubleBondConfig::Trans,
+            DoubleBondConfig::Trans => DoubleBondConfig::Cis,
+        }
+    }
+}
+
+/// Cis/trans configuration of a double bond, relative to one neighbor on each side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BondStereo {
+    pub config: DoubleBondConfig,
+    pub start_neighbor: AtomIdx, // neighbor of the bond's first atom
+    pub end_neighbor: AtomIdx,   // neighbor of the bond's second atom
+}
+
+/// Where an atom or bond is written in the SMILES it was parsed from, in bytes of the
+/// input, also after the non-ASCII characters that lenient parsing skips. Not part of
+/// the chemistry: it's equal to any other
+/// span, so that the same atom written elsewhere is still the same atom.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SourceSpan(pub Option<Span>);
+
+impl SourceSpan {
+    pub fn range(self) -> Option<Range<usize>> {
+        self.0.map(Range::from)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl PartialEq for SourceSpan {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SourceSpan {}
+
+/// The chemistry of a bond. Its atoms are stored by the molecule, see [`Mol::bond_atoms`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Bond {
-    pub atom_start: usize, // graph index
-    pub atom_end: usize,   // graph index
+    pub order: BondOrder,
+    pub direction: Option<BondDirection>, // only single bonds written with / or \
+    pub stereo: Option<BondStereo>,       // only double bonds
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Props::is_empty"))]
+    pub props: Props, // user annotations, not written to SMILES
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub span: SourceSpan, // the bond symbol or closing ring bond, if written
+}
+
+impl Bond {
+    /// Whether the bond is aromatic, unlike a single bond between aromatic atoms such as
+    /// the linkage of biphenyl.
+    pub fn is_aromatic(&self) -> bool {
+        self.order == BondOrder::Aromatic
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        into = "crate::serialization::MolData",
+        try_from = "crate::serialization::MolData"
+    )
+)]
 pub struct Mol {
-    pub graph: Graph<Atom, Bond>,
+    // read with the accessors and edit with MolBuilder, which keep the implicit hydrogens
+    // in sync with the bonds
+    pub(crate) graph: UnGraph<Atom, Bond>,
 }
 
 impl Mol {
@@ -28,11 +320,494 @@ impl Mol {
         self.graph.edge_count()
     }
 
-    pub fn atom_with_idx(&self, idx: usize) -> Option<&Atom> {
-        self.graph.node_weight(NodeIndex::new(idx))
+    pub fn atom_with_idx(&self, idx: AtomIdx) -> Option<&Atom> {
+        self.graph.node_weight(idx.into())
+    }
+
+    pub fn bond_with_idx(&self, idx: BondIdx) -> Option<&Bond> {
+        self.graph.edge_weight(idx.into())
+    }
+
+    /// The atoms of the bond, first the one it was written from. Bond directions and
+    /// cis/trans stereo are relative to this order.
+    pub fn bond_atoms(&self, idx: BondIdx) -> Option<(AtomIdx, AtomIdx)> {
+        let (a, b) = self.graph.edge_endpoints(idx.into())?;
+        Some((a.into(), b.into()))
+    }
+
+    /// The atoms, in index order.
+    pub fn atoms(&self) -> impl Iterator<Item = &Atom> {
+        self.graph.node_weights()
+    }
+
+    /// The bonds, in index order.
+    pub fn bonds(&self) -> impl Iterator<Item = &Bond> {
+        self.graph.edge_weights()
+    }
+
+    /// Indices of the atoms bonded to the atom, empty if there's no such atom.
+    pub fn neighbors(&self, atom_idx: AtomIdx) -> impl Iterator<Item = AtomIdx> + '_ {
+        self.graph.neighbors(atom_idx.into()).map(AtomIdx::from)
+    }
+
+    /// The bonds of the atom, empty if there's no such atom.
+    pub fn bonds_of(&self, atom_idx: AtomIdx) -> impl Iterator<Item = &Bond> {
+        self.graph.edges(atom_idx.into()).map(|edge| edge.weight())
+    }
+
+    /// Number of atoms bonded to the atom (hydrogens only if they're graph atoms).
+    pub fn degree(&self, atom_idx: AtomIdx) -> usize {
+        self.neighbors(atom_idx).count()
+    }
+
+    /// Implicit hydrogens of the atom, derived from its element, charge, aromaticity and bonds.
+    pub fn implicit_h_count(&self, atom_idx: AtomIdx) -> Option<u8> {
+        self.atom_with_idx(atom_idx)?;
+        Some(valence::implicit_h_count(self, atom_idx.into()))
+    }
+
+    /// The user property of the atom read as `T`, if the atom has it with this type.
+    pub fn atom_prop<T: FromProp>(&self, atom_idx: AtomIdx, key: &str) -> Option<T> {
+        self.atom_with_idx(atom_idx)?.props.get(key)
+    }
+
+    /// Sets a user property of the atom, e.g. a predicted charge.
+    /// Panics if the atom isn't in the molecule.
+    pub fn set_atom_prop(&mut self, atom_idx: AtomIdx, key: &str, value: impl Into<PropValue>) {
+        self.graph[NodeIndex::from(atom_idx)].props.set(key, value);
+    }
+
+    /// The user property of the bond read as `T`, if the bond has it with this type.
+    pub fn bond_prop<T: FromProp>(&self, bond_idx: BondIdx, key: &str) -> Option<T> {
+        self.bond_with_idx(bond_idx)?.props.get(key)
+    }
+
+    /// Sets a user property of the bond, e.g. a highlight flag.
+    /// Panics if the bond isn't in the molecule.
+    pub fn set_bond_prop(&mut self, bond_idx: BondIdx, key: &str, value: impl Into<PropValue>) {
+        self.graph[EdgeIndex::from(bond_idx)].props.set(key, value);
+    }
+
+    /// Where the atom is written in the SMILES it was parsed from, e.g. to point at it
+    /// in an error message. None for atoms that weren't parsed, like added hydrogens.
+    pub fn atom_span(&self, atom_idx: AtomIdx) -> Option<Range<usize>> {
+        self.atom_with_idx(atom_idx)?.span.range()
+    }
+
+    /// Where the bond is written in the SMILES it was parsed from: its symbol, or the
+    /// ring bond that closes it. None for bonds without a symbol between two atoms.
+    pub fn bond_span(&self, bond_idx: BondIdx) -> Option<Range<usize>> {
+        self.bond_with_idx(bond_idx)?.span.range()
+    }
+
+    /// Writes the molecule as a SMILES string.
+    pub fn to_smiles(&self) -> String {
+        writer::to_smiles(self)
+    }
+
+    /// Writes the molecule as a SMILES string for tools that expect a given flavor, e.g.
+    /// kekulized without stereo.
+    pub fn to_smiles_with_options(&self, options: SmilesWriteOptions) -> String {
+        writer::to_smiles_with_options(self, options)
+    }
+
+    /// Writes the molecule as a CXSMILES: the SMILES, then the extensions between `|`, with
+    /// their atoms renumbered in the order they're written. Radicals of the atoms are
+    /// written along with those of the extensions. Fields that weren't read,
+    /// [`MolExtensions::other_fields`], are left out.
+    pub fn to_cxsmiles(&self, extensions: &MolExtensions) -> String {
+        let (smiles, order) = writer::to_smiles_with_order(self);
+        let mut extensions = extensions.clone();
+        for (i, atom) in self.graph.node_weights().enumerate() {
+            if atom.num_radical_electrons > 0 {
+                extensions
+                    .radicals
+                    .insert(AtomIdx::new(i), atom.num_radical_electrons);
+            }
+        }
+        smiles + &cxsmiles::write(&extensions, &order)
+    }
+
+    /// Writes up to `n` distinct, non-canonical SMILES of the molecule, in a random atom
+    /// order given by the seed, e.g. for data augmentation.
+    pub fn random_smiles(&self, n: usize, seed: u64) -> Vec<String> {
+        writer::random_smiles(self, n, seed)
+    }
+
+    /// Writes the molecule as a canonical SMILES string: the same molecule always gives
+    /// the same string, regardless of the atom order in the input and of it being written
+    /// in a Kekulé or aromatic form. Aromaticity is perceived again before writing.
+    pub fn to_canonical_smiles(&self) -> String {
+        canon::to_canonical_smiles(self)
+    }
+
+    /// Canonical rank of each atom, by atom index: unique, from 0 to n - 1, and the same
+    /// for the same molecule regardless of the input atom order and of its Kekulé or
+    /// aromatic form. Used by
+    /// [`to_canonical_smiles`](Self::to_canonical_smiles).
+    pub fn canonical_ranks(&self) -> Vec<usize> {
+        canon::canonical_ranks(self)
+    }
+
+    /// Symmetry class of each atom, by atom index. Atoms in the same class are
+    /// topologically equivalent, e.g. the six carbons of benzene.
+    pub fn symmetry_classes(&self) -> Vec<usize> {
+        canon::symmetry_classes(self)
     }
 
-    pub fn bond_with_idx(&self, idx: usize) -> Option<&Bond> {
-        self.graph.edge_weight(EdgeIndex::new(idx))
+    /// Molecular formula in Hill order, e.g. `C6H6`.
+    pub fn molecular_formula(&self) -> String {
+        formula::molecular_formula(self)
+    }
+
+    /// Sum of the formal charges, e.g. 0 for `[Na+].[Cl-]`.
+    pub fn net_charge(&self) -> i32 {
+        formula::net_charge(self)
+    }
+
+    /// Counts, polar surface area and log P estimate, see [`descriptors`](crate::descriptors).
+    pub fn descriptors(&self) -> Descriptors {
+        Descriptors::new(self)
+    }
+
+    /// The criteria of Lipinski's rule of five that the molecule fails.
+    pub fn lipinski_violations(&self) -> Vec<Criterion> {
+        RuleSet::lipinski().evaluate(self).violations()
+    }
+
+    /// Average molecular weight, in g/mol.
+    pub fn molecular_weight(&self) -> f64 {
+        formula::molecular_weight(self)
+    }
+
+    /// Monoisotopic mass, in Da.
+    pub fn exact_mass(&self) -> f64 {
+        formula::exact_mass(self)
+    }
+
+    /// Replaces aromatic bonds with alternating single and double bonds.
+    pub fn kekulize(&mut self) -> Result<(), KekulizeError> {
+        aromaticity::kekulize(self)
+    }
+
+    /// Perceives aromaticity from scratch, marking the atoms and bonds of aromatic rings.
+    pub fn set_aromaticity(&mut self, model: AromaticityModel) -> Result<(), KekulizeError> {
+        aromaticity::set_aromaticity(self, model)
+    }
+
+    /// Whether each pair of atoms is bonded, indexed like the atoms.
+    pub fn adjacency_matrix(&self) -> Vec<Vec<bool>> {
+        topology::adjacency_matrix(self)
+    }
+
+    /// The number of bonds between each pair of atoms, `None` between fragments.
+    pub fn distance_matrix(&self) -> Vec<Vec<Option<usize>>> {
+        topology::distance_matrix(self)
+    }
+
+    /// The atoms of a shortest path between the atoms, both included, empty if there's
+    /// none.
+    pub fn shortest_path(&self, a: AtomIdx, b: AtomIdx) -> Vec<AtomIdx> {
+        topology::shortest_path(self, a, b)
+    }
+
+    /// The atoms and bonds up to `radius` bonds away from the atom, and their SMILES,
+    /// `None` if there's no such atom.
+    pub fn atom_environment(&self, atom_idx: AtomIdx, radius: usize) -> Option<AtomEnvironment> {
+        environment::atom_environment(self, atom_idx, radius)
+    }
+
+    /// Ring membership of atoms and bonds, ring sizes and fused ring systems.
+    pub fn ring_info(&self) -> RingInfo {
+        RingInfo::new(self)
+    }
+
+    /// Atom indices of each match of the query, in query atom order.
+    /// Matches covering the same atoms are reported once.
+    pub fn substruct_matches(&self, query: &Smarts) -> Vec<Vec<AtomIdx>> {
+        substruct::substruct_matches(self, query)
+    }
+
+    pub fn has_substruct_match(&self, query: &Smarts) -> bool {
+        !self.substruct_matches(query).is_empty()
+    }
+
+    /// Matches of the functional groups of [`FunctionalGroup::all`](crate::FunctionalGroup::all),
+    /// e.g. an ester and a carboxylic acid for aspirin.
+    #[cfg(feature = "std")]
+    pub fn functional_groups(&self) -> Vec<FunctionalGroupHit> {
+        functional_groups::functional_groups(self)
+    }
+
+    /// Whether both molecules have the same structure (graph isomorphism on element, charge,
+    /// isotope, hydrogen count and bond order), regardless of atom order.
+    pub fn is_same_structure(&self, other: &Mol) -> bool {
+        substruct::is_same_structure(self, other)
+    }
+
+    /// Whether both are the same molecule, like [`is_same_structure`](Self::is_same_structure)
+    /// but also comparing stereo, and regardless of the aromaticity perceived or written and
+    /// of hydrogens written as atoms. Radicals still count: `[CH2]C` isn't ethane.
+    pub fn is_same_mol(&self, other: &Mol) -> bool {
+        compare::normalized_smiles(self) == compare::normalized_smiles(other)
+    }
+
+    /// Reads an MDL molfile (V2000): atoms, bonds, charges, isotopes, radicals and valences.
+    pub fn from_molblock(molblock: &str) -> Result<Mol, MolfileError> {
+        molfile::from_molblock(molblock)
+    }
+
+    /// Reads a Marvin document (MRV) from ChemAxon's drawing tools: atoms, bonds, charges,
+    /// isotopes and map numbers.
+    pub fn from_mrv(mrv: &str) -> Result<Mol, MrvError> {
+        mrv::from_mrv(mrv)
+    }
+
+    /// Writes an MDL molfile (V2000), kekulized when possible and without coordinates.
+    pub fn to_molblock(&self) -> String {
+        molfile::to_molblock(self)
+    }
+
+    /// Layered, InChI-like description of the structure: formula, connections, hydrogens,
+    /// charges and isotopes, independent of atom order and Kekulé/aromatic form.
+    pub fn structure_layers(&self) -> String {
+        structure_key::structure_layers(self)
+    }
+
+    /// Fixed length hash of the structure layers, InChIKey-like, for deduplication and indexing.
+    pub fn structure_key(&self) -> String {
+        structure_key::structure_key(self)
+    }
+
+    /// ECFP-like circular fingerprint of `nbits` bits, with environments up to `radius` bonds.
+    pub fn morgan_fingerprint(&self, radius: usize, nbits: usize) -> BitVec {
+        fingerprint::morgan_fingerprint(self, radius, nbits)
+    }
+
+    /// [`Mol::morgan_fingerprint`] with the atom environments that set each bit.
+    pub fn morgan_fingerprint_info(&self, radius: usize, nbits: usize) -> Fingerprint {
+        fingerprint::morgan_fingerprint_info(self, radius, nbits)
+    }
+
+    /// Fingerprint to screen substructure searches: a molecule can only match a query if
+    /// it has all the bits of [`Smarts::pattern_fingerprint`].
+    pub fn pattern_fingerprint(&self) -> BitVec {
+        fingerprint::pattern_fingerprint(self)
+    }
+
+    /// Daylight-like fingerprint of `nbits` bits, from the linear paths of `min_len` to
+    /// `max_len` bonds.
+    pub fn path_fingerprint(&self, min_len: usize, max_len: usize, nbits: usize) -> BitVec {
+        fingerprint::path_fingerprint(self, min_len, max_len, nbits)
+    }
+
+    /// [`Mol::path_fingerprint`] with the paths that set each bit.
+    pub fn path_fingerprint_info(
+        &self,
+        min_len: usize,
+        max_len: usize,
+        nbits: usize,
+    ) -> Fingerprint {
+        fingerprint::path_fingerprint_info(self, min_len, max_len, nbits)
+    }
+
+    /// 2D coordinates of the atoms, for depiction.
+    pub fn compute_2d_coords(&self) -> Conformer {
+        layout::compute_2d_coords(self)
+    }
+
+    /// 3D coordinates of the atoms, by distance geometry. Implicit hydrogens don't get
+    /// coordinates, see [`Mol::add_hs`] to place them too.
+    pub fn embed_3d(&self, options: &EmbedOptions) -> conformer::Conformer {
+        conformer::embed(self, options)
+    }
+
+    /// SVG drawing of the molecule, from its 2D coordinates.
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        svg::to_svg(self, options)
+    }
+
+    /// Rough text drawing of the molecule from its 2D coordinates, for terminals. Best for
+    /// small molecules.
+    pub fn to_ascii_art(&self) -> String {
+        ascii::to_ascii_art(self)
+    }
+
+    /// The standard InChI, with the `inchi` feature, which links the InChI library.
+    #[cfg(feature = "inchi")]
+    pub fn to_inchi(&self) -> Result<String, InchiError> {
+        inchi::to_inchi(self)
+    }
+
+    /// The standard InChIKey, with the `inchi` feature, which links the InChI library.
+    #[cfg(feature = "inchi")]
+    pub fn to_inchikey(&self) -> Result<String, InchiError> {
+        inchi::to_inchikey(self)
+    }
+
+    /// The molecule in the JSON format of [`json`](crate::json), on one line.
+    pub fn to_json(&self) -> String {
+        json::to_json(self)
+    }
+
+    /// Reads a molecule in the JSON format of [`json`](crate::json).
+    pub fn from_json(json: &str) -> Result<Mol, JsonError> {
+        json::from_json(json)
+    }
+
+    /// Indices of the atoms with the map number, e.g. 1 for `[CH3:1]`.
+    pub fn atoms_with_map_number(&self, map_number: u32) -> Vec<AtomIdx> {
+        self.graph
+            .node_indices()
+            .filter(|node| self.graph[*node].map_number == Some(map_number))
+            .map(AtomIdx::from)
+            .collect()
+    }
+
+    /// Checks that the molecule is chemically possible: valences and aromatic systems.
+    /// Hypervalent nitrogen oxides are written charge separated first.
+    pub fn sanitize(&mut self) -> Result<(), SanitizeError> {
+        sanitize::sanitize(self)
+    }
+
+    /// Number of disconnected fragments, e.g. 2 for `[Na+].[Cl-]`.
+    pub fn num_fragments(&self) -> usize {
+        fragments::fragment_atoms(self).len()
+    }
+
+    /// Each disconnected fragment as its own molecule, ordered by their first atom.
+    pub fn fragments(&self) -> Vec<Mol> {
+        fragments::fragments(self)
+    }
+
+    /// The parent molecule, without counter-ions and solvents, e.g. `CCN` for `CCN.Cl`.
+    /// Charges are kept, [`neutralize`](Self::neutralize) the parent to remove them.
+    pub fn strip_salts(&self, policy: &SaltPolicy) -> Mol {
+        salts::strip_salts(self, policy)
+    }
+
+    /// The molecule with its charges neutralized where hydrogens can be added or removed,
+    /// e.g. `CN` for `C[NH3+]`, and `CC(=O)O` for `CC(=O)[O-]`. Atoms bonded to an atom of
+    /// opposite charge, as in nitro groups, keep their charge.
+    pub fn neutralize(&self) -> Mol {
+        salts::neutralize(self)
+    }
+
+    /// The most stable tautomer, the same for all the tautomers of the molecule. See the
+    /// [`tautomer`](crate::tautomer) module.
+    pub fn canonical_tautomer(&self) -> Mol {
+        tautomer::canonical(self)
+    }
+
+    /// The fragments left when the bonds of the rules are broken, with `*` atoms where
+    /// they were, e.g. `CC(=O)*` and `N(C)*` for `CC(=O)NC` with RECAP's rules.
+    #[cfg(feature = "std")]
+    pub fn fragment(&self, rules: &FragmentRules) -> Vec<Mol> {
+        fragmentation::fragment(self, rules)
+    }
+
+    /// The Bemis-Murcko scaffold: ring systems and linkers, without side chains.
+    pub fn murcko_scaffold(&self) -> Mol {
+        scaffold::murcko_scaffold(self)
+    }
+
+    /// The Murcko scaffold with only carbons and single bonds.
+    pub fn generic_scaffold(&self) -> Mol {
+        scaffold::generic_scaffold(self)
+    }
+
+    /// The molecule with its hydrogens as atoms, added after the other atoms.
+    pub fn add_hs(&self) -> Mol {
+        hydrogens::add_hs(self)
+    }
+
+    /// The molecule with hydrogen atoms folded into their neighbor's hydrogen count,
+    /// except those needed for charge, isotope or stereo.
+    pub fn remove_hs(&self) -> Mol {
+        hydrogens::remove_hs(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::smiles::SmilesParser;
+
+    use super::{AtomIdx, BondIdx, BondOrder, Chirality, Mol};
+
+    fn mol(smiles: &str) -> Mol {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap()
+    }
+
+    #[test]
+    fn reordered_chirality() {
+        let [a, b, c] = [0, 1, 2].map(AtomIdx::new);
+
+        assert_eq!(
+            Some(Chirality::Clockwise),
+            Chirality::Anticlockwise.reordered(&[a, b, c], &[b, a, c])
+        );
+        assert_eq!(
+            Some(Chirality::Anticlockwise),
+            Chirality::Anticlockwise.reordered(&[a, b, c], &[b, c, a])
+        );
+        assert_eq!(
+            None,
+            Chirality::Anticlockwise.reordered(&[a, b, c], &[a, b])
+        );
+        // not a permutation
+        assert_eq!(
+            None,
+            Chirality::Anticlockwise.reordered(&[a, b, c], &[a, a, b])
+        );
+        assert_eq!(
+            None,
+            Chirality::Anticlockwise.reordered(&[a, a, b], &[a, b, c])
+        );
+    }
+
+    #[test]
+    fn iterate_atoms_and_bonds() {
+        let mol = mol("CC(=O)O");
+
+        let numbers: Vec<u32> = mol.atoms().map(|atom| atom.number).collect();
+        assert_eq!(vec![6, 6, 8, 8], numbers);
+        let orders: Vec<BondOrder> = mol.bonds().map(|bond| bond.order).collect();
+        assert_eq!(
+            vec![BondOrder::Single, BondOrder::Double, BondOrder::Single],
+            orders
+        );
+    }
+
+    #[test]
+    fn neighbors() {
+        let mol = mol("CC(=O)O");
+
+        let carbon = AtomIdx::new(1);
+        let oxygen = AtomIdx::new(2);
+
+        let mut neighbors: Vec<usize> = mol.neighbors(carbon).map(AtomIdx::index).collect();
+        neighbors.sort();
+        assert_eq!(vec![0, 2, 3], neighbors);
+        assert_eq!(3, mol.degree(carbon));
+        assert_eq!(1, mol.degree(oxygen));
+        assert_eq!(
+            vec![BondOrder::Double],
+            mol.bonds_of(oxygen)
+                .map(|bond| bond.order)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(0, mol.degree(AtomIdx::new(10)));
+    }
+
+    #[test]
+    fn bond_atoms() {
+        let mol = mol("C1CC1");
+
+        let atoms = |bond| mol.bond_atoms(BondIdx::new(bond));
+        assert_eq!(Some((AtomIdx::new(0), AtomIdx::new(1))), atoms(0));
+        // a ring bond goes from where it was opened
+        assert_eq!(Some((AtomIdx::new(0), AtomIdx::new(2))), atoms(2));
+        assert_eq!(None, atoms(3));
     }
 }
@@ -0,0 +1,189 @@
+use petgraph::graph::NodeIndex;
+
+use crate::periodic_table::Element;
+use crate::types::{Atom, BondOrder, Mol};
+
+/// Normal valences of the element, lowest first, used to derive implicit hydrogens.
+/// Empty for elements that don't get implicit hydrogens.
+pub fn default_valences(number: u32) -> &'static [u8] {
+    Element::from_number(number).map_or(&[], |element| element.default_valences)
+}
+
+/// Shifts the valence for charged atoms, following the isoelectronic element:
+/// e.g. N+ behaves like C, O- like F and B- like C. H+ and H- have no bonds.
+pub fn charged_valence(number: u32, valence: u8, charge: i8) -> Option<u8> {
+    let shifted = match number {
+        5 => valence as i16 - charge as i16,
+        1 | 6 => valence as i16 - (charge as i16).abs(),
+        _ => valence as i16 + charge as i16,
+    };
+    u8::try_from(shifted).ok()
+}
+
+/// Sum of the bond orders of an atom, aromatic bonds counting as 1.
+pub fn bond_order_sum(mol: &Mol, node: NodeIndex) -> u8 {
+    mol.graph
+        .edges(node)
+        .map(|edge| bond_valence(edge.weight().order))
+        .sum()
+}
+
+/// What a bond counts towards the valence of its atoms, aromatic bonds counting as 1.
+pub fn bond_valence(order: BondOrder) -> u8 {
+    match order {
+        BondOrder::Single | BondOrder::Aromatic => 1,
+        BondOrder::Double => 2,
+        BondOrder::Triple => 3,
+        BondOrder::Quadruple => 4,
+    }
+}
+
+/// Bonds and explicit hydrogens of an atom, aromatic atoms counting one more bond.
+pub fn explicit_valence(mol: &Mol, node: NodeIndex) -> u8 {
+    let atom = &mol.graph[node];
+    bond_order_sum(mol, node) + atom.aromatic as u8 + atom.explicit_h.unwrap_or(0)
+}
+
+/// Highest normal valence of the atom, given its charge. `None` for elements without
+/// a valence model.
+pub fn max_valence(atom: &Atom) -> Option<u8> {
+    default_valences(atom.number)
+        .iter()
+        .filter_map(|valence| charged_valence(atom.number, *valence, atom.charge))
+        .max()
+}
+
+/// Whether the atom's explicit valence and radical electrons are within its normal
+/// valences.
+pub fn is_valence_allowed(mol: &Mol, node: NodeIndex) -> bool {
+    let atom = &mol.graph[node];
+    max_valence(atom)
+        .is_none_or(|max| explicit_valence(mol, node) + atom.num_radical_electrons <= max)
+}
+
+/// Radical electrons of a bracket atom: what its bonds and hydrogens leave of the lowest
+/// normal valence that accommodates them, e.g. 1 for `[CH3]` and 2 for `[CH2]`. 0 for
+/// aromatic atoms and elements without a valence model.
+pub fn valence_deficit(mol: &Mol, node: NodeIndex) -> u8 {
+    let atom = &mol.graph[node];
+    if atom.aromatic {
+        return 0;
+    }
+    let valence = explicit_valence(mol, node);
+    default_valences(atom.number)
+        .iter()
+        .filter_map(|normal| charged_valence(atom.number, *normal, atom.charge))
+        .find(|normal| *normal >= valence)
+        .map_or(0, |normal| normal - valence)
+}
+
+/// Implicit hydrogens of an atom, according to the SMILES valence model:
+/// hydrogens are added up to the lowest normal valence that accommodates the explicit bonds.
+/// Bracket atoms don't have implicit hydrogens (they're all written).
+/// Aromatic atoms count one more bond (their part of the aromatic system) and only use
+/// their lowest valence. Radical electrons take the place of hydrogens.
+pub fn implicit_h_count(mol: &Mol, node: NodeIndex) -> u8 {
+    let atom: &Atom = &mol.graph[node];
+    if atom.explicit_h.is_some() {
+        return 0;
+    }
+
+    let mut bonds = bond_order_sum(mol, node) + atom.num_radical_electrons;
+    let mut valences = default_valences(atom.number);
+    if atom.aromatic {
+        bonds += 1;
+        valences = &valences[..valences.len().min(1)];
+    }
+
+    valences
+        .iter()
+        .filter_map(|valence| charged_valence(atom.number, *valence, atom.charge))
+        .find(|valence| *valence >= bonds)
+        .map(|valence| valence - bonds)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use petgraph::graph::NodeIndex;
+
+    use crate::smiles::SmilesParser;
+
+    use super::implicit_h_count;
+
+    fn implicit_hs(smiles: &str) -> Vec<u8> {
+        let parser = SmilesParser::new();
+        let mol = parser.parse(smiles).unwrap();
+        (0..mol.num_atoms())
+            .map(|i| implicit_h_count(&mol, NodeIndex::new(i)))
+            .collect()
+    }
+
+    #[test]
+    fn implicit_hs_aliphatic() {
+        assert_eq!(vec![4], implicit_hs("C"));
+        assert_eq!(vec![3, 2, 1], implicit_hs("CCO"));
+        assert_eq!(vec![2, 1, 0], implicit_hs("C=CCl"));
+        assert_eq!(vec![1, 0], implicit_hs("C#N"));
+        assert_eq!(vec![3, 0, 0, 1], implicit_hs("CC(=O)O"));
+    }
+
+    #[test]
+    fn implicit_hs_higher_valences() {
+        assert_eq!(vec![3, 0, 0, 3], implicit_hs("CS(=O)C"));
+        assert_eq!(vec![3, 0, 3, 0, 0], implicit_hs("CS(C)(=O)=O"));
+        assert_eq!(vec![3, 0, 0, 0], implicit_hs("CN(=O)=O"));
+        assert_eq!(vec![0, 0, 0, 0, 0, 0], implicit_hs("FP(F)(F)(F)F"));
+    }
+
+    #[test]
+    fn implicit_hs_aromatic() {
+        assert_eq!(vec![1; 6], implicit_hs("c1ccccc1"));
+        assert_eq!(vec![1, 1, 1, 1, 0, 1], implicit_hs("c1cccnc1"));
+        assert_eq!(vec![1, 1, 1, 1, 0], implicit_hs("c1cccs1"));
+        assert_eq!(vec![1, 1, 1, 1, 0], implicit_hs("c1ccco1"));
+        assert_eq!(vec![3, 0, 1, 1, 1, 1, 1], implicit_hs("Cc1ccccc1"));
+    }
+
+    #[test]
+    fn implicit_hs_bracket_atoms() {
+        assert_eq!(vec![0], implicit_hs("[CH4]"));
+        assert_eq!(vec![3, 0], implicit_hs("C[NH3+]"));
+        assert_eq!(vec![0], implicit_hs("[C]"));
+    }
+
+    #[test]
+    fn radical_electrons() {
+        let parser = SmilesParser::new();
+        let radicals = |smiles: &str| -> Vec<u8> {
+            let mol = parser.parse(smiles).unwrap();
+            mol.atoms().map(|atom| atom.num_radical_electrons).collect()
+        };
+        assert_eq!(vec![1], radicals("[CH3]"));
+        assert_eq!(vec![0, 2], radicals("C[CH]"));
+        assert_eq!(vec![4], radicals("[C]"));
+        assert_eq!(vec![0, 1], radicals("C[O]"));
+        assert_eq!(vec![0, 0, 0, 0], radicals("[CH4].[CH3+].C[O-]"));
+        assert_eq!(vec![0, 0, 0], radicals("[H+].[H-].[Na+]"));
+        assert_eq!(vec![1, 0], radicals("[H].[SH2]"));
+        // TEMPO
+        assert_eq!(1, radicals("CC1(C)CCCC(C)(C)N1[O]")[10]);
+        // aromatic atoms are left out
+        assert_eq!(vec![0; 5], radicals("c1cc[c]c1"));
+    }
+
+    #[test]
+    fn implicit_hs_radicals() {
+        let parser = SmilesParser::new();
+        let (mut mol, _) = parser.parse_cxsmiles("CO").unwrap();
+        mol.graph[NodeIndex::new(1)].num_radical_electrons = 1;
+        assert_eq!(0, implicit_h_count(&mol, NodeIndex::new(1)));
+        mol.graph[NodeIndex::new(0)].num_radical_electrons = 2;
+        assert_eq!(1, implicit_h_count(&mol, NodeIndex::new(0)));
+    }
+
+    #[test]
+    fn implicit_hs_exceeded_valence() {
+        assert_eq!(vec![3, 0, 3, 3, 3, 3], implicit_hs("CC(C)(C)(C)C"));
+    }
+}
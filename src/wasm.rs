@@ -0,0 +1,112 @@
+//! JavaScript bindings, with the `wasm` feature:
+//!
+//! ```js
+//! const mol = parse("OCC");
+//! mol.toCanonicalSmiles(); // "CCO"
+//! mol.descriptors().tpsa;
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::descriptors::Descriptors;
+use crate::smiles::SmilesParser;
+use crate::svg::SvgOptions;
+use crate::types::Mol;
+
+/// A parsed molecule.
+#[wasm_bindgen(js_name = Molecule)]
+pub struct JsMol {
+    mol: Mol,
+}
+
+#[wasm_bindgen(js_class = Molecule)]
+impl JsMol {
+    #[wasm_bindgen(js_name = numAtoms)]
+    pub fn num_atoms(&self) -> usize {
+        self.mol.num_atoms()
+    }
+
+    #[wasm_bindgen(js_name = numBonds)]
+    pub fn num_bonds(&self) -> usize {
+        self.mol.num_bonds()
+    }
+
+    #[wasm_bindgen(js_name = toSmiles)]
+    pub fn to_smiles(&self) -> String {
+        self.mol.to_smiles()
+    }
+
+    #[wasm_bindgen(js_name = toCanonicalSmiles)]
+    pub fn to_canonical_smiles(&self) -> String {
+        self.mol.to_canonical_smiles()
+    }
+
+    #[wasm_bindgen(js_name = molecularFormula)]
+    pub fn molecular_formula(&self) -> String {
+        self.mol.molecular_formula()
+    }
+
+    pub fn descriptors(&self) -> JsDescriptors {
+        self.mol.descriptors().into()
+    }
+
+    /// SVG drawing with the default options.
+    #[wasm_bindgen(js_name = toSvg)]
+    pub fn to_svg(&self) -> String {
+        self.mol.to_svg(&SvgOptions::default())
+    }
+}
+
+/// See [`Descriptors`].
+#[wasm_bindgen(js_name = Descriptors)]
+#[derive(Debug, Clone, Copy)]
+pub struct JsDescriptors {
+    #[wasm_bindgen(js_name = molecularWeight)]
+    pub molecular_weight: f64,
+    #[wasm_bindgen(js_name = heavyAtomCount)]
+    pub heavy_atom_count: usize,
+    #[wasm_bindgen(js_name = ringCount)]
+    pub ring_count: usize,
+    #[wasm_bindgen(js_name = rotatableBondCount)]
+    pub rotatable_bond_count: usize,
+    #[wasm_bindgen(js_name = hBondDonorCount)]
+    pub h_bond_donor_count: usize,
+    #[wasm_bindgen(js_name = hBondAcceptorCount)]
+    pub h_bond_acceptor_count: usize,
+    pub tpsa: f64,
+    pub clogp: f64,
+}
+
+impl From<Descriptors> for JsDescriptors {
+    fn from(descriptors: Descriptors) -> Self {
+        JsDescriptors {
+            molecular_weight: descriptors.molecular_weight,
+            heavy_atom_count: descriptors.heavy_atom_count,
+            ring_count: descriptors.ring_count,
+            rotatable_bond_count: descriptors.rotatable_bond_count,
+            h_bond_donor_count: descriptors.h_bond_donor_count,
+            h_bond_acceptor_count: descriptors.h_bond_acceptor_count,
+            tpsa: descriptors.tpsa,
+            clogp: descriptors.clogp,
+        }
+    }
+}
+
+/// Parses the SMILES, throws an `Error` with the parser's message if it's invalid.
+#[wasm_bindgen]
+pub fn parse(smiles: &str) -> Result<JsMol, JsError> {
+    let mol = SmilesParser::new().parse(smiles)?;
+    Ok(JsMol { mol })
+}
+
+/// The canonical SMILES of the SMILES.
+#[wasm_bindgen]
+pub fn canonicalize(smiles: &str) -> Result<String, JsError> {
+    Ok(parse(smiles)?.to_canonical_smiles())
+}
+
+/// SVG drawing of the SMILES.
+#[wasm_bindgen]
+pub fn depict(smiles: &str) -> Result<String, JsError> {
+    Ok(parse(smiles)?.to_svg())
+}
@@ -0,0 +1,981 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use hashbrown::{HashMap, HashSet};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::canon;
+use crate::smiles::element_symbol;
+use crate::topology;
+use crate::types::{AtomIdx, BondDirection, BondOrder, Chirality, DoubleBondConfig, Mol};
+use crate::valence;
+
+/// Symbols that can be written without brackets.
+// `*` is the wildcard atom, also written without brackets
+static ORGANIC_SUBSET: [&str; 11] = ["B", "C", "N", "O", "P", "S", "F", "Cl", "Br", "I", "*"];
+static AROMATIC_ORGANIC_SUBSET: [&str; 6] = ["B", "C", "N", "O", "P", "S"];
+
+/// What [`Mol::to_smiles_with_options`] writes. The default writes like
+/// [`Mol::to_smiles`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmilesWriteOptions {
+    /// Writes alternating single and double bonds instead of aromatic atoms, when the
+    /// molecule has a Kekulé form. Otherwise it's written aromatic.
+    pub kekulize: bool,
+    /// Writes the chirality of atoms and the directions of bonds around double bonds.
+    pub stereo: bool,
+    /// Writes the isotopes of atoms.
+    pub isotopes: bool,
+    /// Writes the atom map numbers.
+    pub atom_maps: bool,
+    /// Walks the atoms in canonical order, like [`Mol::to_canonical_smiles`], instead of
+    /// input order.
+    pub canonical: bool,
+    /// Reuses the number of a closed ring for the next ring, instead of a new number for
+    /// each ring. Numbers are reused anyway once all 99 are used.
+    pub reuse_ring_numbers: bool,
+}
+
+impl Default for SmilesWriteOptions {
+    fn default() -> Self {
+        SmilesWriteOptions {
+            kekulize: false,
+            stereo: true,
+            isotopes: true,
+            atom_maps: true,
+            canonical: false,
+            reuse_ring_numbers: true,
+        }
+    }
+}
+
+/// Ring closure as seen from one of its atoms.
+struct RingBond {
+    edge: EdgeIndex,
+    opens: bool, // true at the atom written first
+}
+
+/// Serializes the molecule to SMILES, walking the graph depth first in input order.
+pub fn to_smiles(mol: &Mol) -> String {
+    let ranks: Vec<usize> = (0..mol.graph.node_count()).collect();
+    to_smiles_ranked(mol, &ranks)
+}
+
+/// Serializes the molecule to SMILES as the options say.
+pub fn to_smiles_with_options(mol: &Mol, options: SmilesWriteOptions) -> String {
+    let mut kekulized = mol.clone();
+    let mol = if options.kekulize && kekulized.kekulize().is_ok() {
+        &kekulized
+    } else {
+        mol
+    };
+    let ranks: Vec<usize> = if options.canonical {
+        canon::canonical_ranks(mol)
+    } else {
+        (0..mol.graph.node_count()).collect()
+    };
+    write_ranked(mol, &ranks, options).0
+}
+
+/// Serializes the molecule to SMILES, walking the graph depth first.
+/// The walk starts at the atom with lowest rank and visits neighbors in rank order.
+/// Each connected component is written separately, separated by `.`.
+pub fn to_smiles_ranked(mol: &Mol, ranks: &[usize]) -> String {
+    write_ranked(mol, ranks, SmilesWriteOptions::default()).0
+}
+
+/// Serializes the molecule to SMILES in input order, with the atoms in the order they're
+/// written.
+pub(crate) fn to_smiles_with_order(mol: &Mol) -> (String, Vec<AtomIdx>) {
+    let ranks: Vec<usize> = (0..mol.graph.node_count()).collect();
+    write_ranked(mol, &ranks, SmilesWriteOptions::default())
+}
+
+fn write_ranked(mol: &Mol, ranks: &[usize], options: SmilesWriteOptions) -> (String, Vec<AtomIdx>) {
+    let graph = &mol.graph;
+    let mut visited = vec![false; graph.node_count()];
+    let mut children: Vec<Vec<(EdgeIndex, NodeIndex)>> = vec![vec![]; graph.node_count()];
+    let mut ring_bonds: Vec<Vec<RingBond>> = (0..graph.node_count()).map(|_| vec![]).collect();
+
+    let mut nodes: Vec<NodeIndex> = graph.node_indices().collect();
+    nodes.sort_by_key(|node| ranks[node.index()]);
+
+    let mut roots = vec![];
+    for node in nodes {
+        if !visited[node.index()] {
+            roots.push(node);
+            classify_edges(
+                mol,
+                ranks,
+                node,
+                &mut visited,
+                &mut children,
+                &mut ring_bonds,
+            );
+        }
+    }
+
+    let mut writer = Writer {
+        mol,
+        options,
+        directions: if options.stereo {
+            bond_directions(mol, ranks)
+        } else {
+            HashMap::new()
+        },
+        children,
+        ring_bonds,
+        open_rings: vec![],
+        out: String::new(),
+        written: Vec::with_capacity(graph.node_count()),
+        in_ring: if graph
+            .edge_weights()
+            .any(|bond| bond.order == BondOrder::Aromatic)
+        {
+            topology::ring_bonds(mol)
+        } else {
+            vec![]
+        },
+    };
+    for (i, root) in roots.into_iter().enumerate() {
+        if i > 0 {
+            writer.out.push('.');
+        }
+        writer.write_atom_tree(root);
+    }
+    (writer.out, writer.written)
+}
+
+/// Writes up to `n` distinct SMILES of the molecule, each from a random atom order: the
+/// walk starts at a random atom and visits the branches in random order. The same seed
+/// gives the same strings. Fewer are returned if the molecule doesn't have `n`, e.g.
+/// methane has only `C`.
+pub fn random_smiles(mol: &Mol, n: usize, seed: u64) -> Vec<String> {
+    let mut rng = SplitMix64(seed);
+    let mut ranks: Vec<usize> = (0..mol.graph.node_count()).collect();
+    let mut seen = HashSet::new();
+    let mut written = vec![];
+    // gives up after enough tries without a new string
+    let mut tries = 0;
+    while written.len() < n && tries < 100 {
+        // Fisher-Yates shuffle
+        for i in (1..ranks.len()).rev() {
+            ranks.swap(i, rng.below(i + 1));
+        }
+        let smiles = to_smiles_ranked(mol, &ranks);
+        if seen.insert(smiles.clone()) {
+            written.push(smiles);
+            tries = 0;
+        } else {
+            tries += 1;
+        }
+    }
+    written
+}
+
+/// Small seedable pseudo-random generator, good enough to shuffle atoms.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0 to `bound` (exclusive).
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// A number from 0 to 1 (exclusive).
+    pub(crate) fn fraction(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// First pass: finds the DFS tree (children of each atom) and the ring closures,
+/// which have to be known before writing the atom where they open.
+fn classify_edges(
+    mol: &Mol,
+    ranks: &[usize],
+    root: NodeIndex,
+    visited: &mut [bool],
+    children: &mut [Vec<(EdgeIndex, NodeIndex)>],
+    ring_bonds: &mut [Vec<RingBond>],
+) {
+    let graph = &mol.graph;
+    let mut seen_edges = vec![false; graph.edge_count()];
+    // (node, edge used to reach it)
+    let mut stack: Vec<(NodeIndex, Option<EdgeIndex>)> = vec![(root, None)];
+
+    while let Some((node, via)) = stack.pop() {
+        if visited[node.index()] {
+            // reached again through another edge before being expanded: ring closure
+            let edge = via.unwrap(); // unwrap: the root is never visited twice
+            let (a, b) = graph.edge_endpoints(edge).unwrap(); // unwrap: edge comes from the graph
+            let parent = if a == node { b } else { a };
+            children[parent.index()].retain(|(e, _)| *e != edge);
+            ring_bonds[parent.index()].push(RingBond { edge, opens: true });
+            ring_bonds[node.index()].push(RingBond { edge, opens: false });
+            continue;
+        }
+        visited[node.index()] = true;
+
+        let mut edges: Vec<(EdgeIndex, NodeIndex)> = graph
+            .edges(node)
+            .map(|edge| (edge.id(), edge.target()))
+            .filter(|(edge, _)| Some(*edge) != via && !seen_edges[edge.index()])
+            .collect();
+        edges.sort_by_key(|(_, neighbor)| ranks[neighbor.index()]);
+
+        for (edge, neighbor) in &edges {
+            seen_edges[edge.index()] = true;
+            if visited[neighbor.index()] {
+                // neighbor already written: ring closure back to it
+                ring_bonds[neighbor.index()].push(RingBond {
+                    edge: *edge,
+                    opens: true,
+                });
+                ring_bonds[node.index()].push(RingBond {
+                    edge: *edge,
+                    opens: false,
+                });
+            } else {
+                children[node.index()].push((*edge, *neighbor));
+            }
+        }
+        // reverse so that the first child is expanded first
+        for (edge, neighbor) in children[node.index()].iter().rev() {
+            stack.push((*neighbor, Some(*edge)));
+        }
+    }
+}
+
+/// Directions to write on single bonds so that the double bonds keep their cis/trans
+/// configuration. Each double bond gets one directional bond on each side, to its neighbor
+/// with the lowest rank, unless a bond on that side already has a direction from a
+/// conjugated double bond: then that one is reused, so that the marks agree.
+fn bond_directions(mol: &Mol, ranks: &[usize]) -> HashMap<EdgeIndex, (NodeIndex, BondDirection)> {
+    let graph = &mol.graph;
+    let mut directions: HashMap<EdgeIndex, (NodeIndex, BondDirection)> = HashMap::new();
+    // directions that follow from the written ones on the other single bonds of the double
+    // bonds' atoms, a neighbor on the other side. They're written once a double bond uses
+    // the bond.
+    let mut implied: HashMap<EdgeIndex, (NodeIndex, BondDirection)> = HashMap::new();
+
+    let mut double_bonds: Vec<EdgeIndex> = graph
+        .edge_indices()
+        .filter(|edge| graph[*edge].stereo.is_some())
+        .collect();
+    double_bonds.sort_by_key(|edge| {
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(*edge).unwrap();
+        ranks[a.index()].min(ranks[b.index()])
+    });
+
+    while !double_bonds.is_empty() {
+        let known = |edge: EdgeIndex| directions.contains_key(&edge) || implied.contains_key(&edge);
+        // next a double bond conjugated with one done, so that a chain of them is done in
+        // order and only its first double bond picks directions freely
+        let next = double_bonds
+            .iter()
+            .position(|double_bond| {
+                // unwrap: edge comes from the graph
+                let (a, b) = graph.edge_endpoints(*double_bond).unwrap();
+                single_bonds(mol, a, *double_bond)
+                    .chain(single_bonds(mol, b, *double_bond))
+                    .any(|(edge, _)| known(edge))
+            })
+            .unwrap_or(0);
+        let double_bond = double_bonds.remove(next);
+        let bond = &graph[double_bond];
+        let Some(stereo) = bond.stereo else {
+            continue;
+        };
+        // start from the atom with lowest rank, so that the output doesn't depend on input order
+        // unwrap: edge comes from the graph
+        let (mut start, mut end) = graph.edge_endpoints(double_bond).unwrap();
+        let (mut stored_start_neighbor, mut stored_end_neighbor) =
+            (stereo.start_neighbor, stereo.end_neighbor);
+        if ranks[end.index()] < ranks[start.index()] {
+            core::mem::swap(&mut start, &mut end);
+            core::mem::swap(&mut stored_start_neighbor, &mut stored_end_neighbor);
+        }
+        let (Some((start_edge, start_neighbor)), Some((end_edge, end_neighbor))) = (
+            reference_neighbor(mol, ranks, start, double_bond, known),
+            reference_neighbor(mol, ranks, end, double_bond, known),
+        ) else {
+            continue;
+        };
+
+        let mut config = stereo.config;
+        if AtomIdx::from(start_neighbor) != stored_start_neighbor {
+            config = config.inverted();
+        }
+        if AtomIdx::from(end_neighbor) != stored_end_neighbor {
+            config = config.inverted();
+        }
+
+        // directions normalized to go from start_neighbor to start, and from end to end_neighbor
+        let existing = |edge: EdgeIndex, from: NodeIndex| {
+            directions
+                .get(&edge)
+                .or_else(|| implied.get(&edge))
+                .map(|(stored_from, d)| {
+                    if *stored_from == from {
+                        *d
+                    } else {
+                        d.inverted()
+                    }
+                })
+        };
+        let existing_start = existing(start_edge, start_neighbor);
+        let existing_end = existing(end_edge, end);
+        let matching = |direction: BondDirection| match config {
+            DoubleBondConfig::Trans => direction,
+            DoubleBondConfig::Cis => direction.inverted(),
+        };
+        let (start_direction, end_direction) = match (existing_start, existing_end) {
+            (None, None) => (BondDirection::Up, matching(BondDirection::Up)),
+            (Some(start_direction), None) => (start_direction, matching(start_direction)),
+            // matching is its own inverse
+            (None, Some(end_direction)) => (matching(end_direction), end_direction),
+            // both already set by other double bonds, which only happens in a ring of
+            // conjugated double bonds
+            (Some(start_direction), Some(end_direction)) => (start_direction, end_direction),
+        };
+        directions.insert(start_edge, (start_neighbor, start_direction));
+        directions.insert(end_edge, (end, end_direction));
+
+        // a second neighbor is on the other side of the double bond
+        for (edge, neighbor) in single_bonds(mol, start, double_bond) {
+            if edge != start_edge && !directions.contains_key(&edge) {
+                implied.insert(edge, (neighbor, start_direction.inverted()));
+            }
+        }
+        for (edge, _) in single_bonds(mol, end, double_bond) {
+            if edge != end_edge && !directions.contains_key(&edge) {
+                implied.insert(edge, (end, end_direction.inverted()));
+            }
+        }
+    }
+    directions
+}
+
+/// The single bonds from `atom` to its neighbors, ignoring `double_bond`.
+fn single_bonds(
+    mol: &Mol,
+    atom: NodeIndex,
+    double_bond: EdgeIndex,
+) -> impl Iterator<Item = (EdgeIndex, NodeIndex)> + '_ {
+    mol.graph
+        .edges(atom)
+        .filter(move |edge| edge.id() != double_bond && edge.weight().order == BondOrder::Single)
+        .map(|edge| (edge.id(), edge.target()))
+}
+
+/// The single bond from `atom` whose direction is `known`, or else the one to its neighbor
+/// with lowest rank, ignoring `double_bond`.
+fn reference_neighbor(
+    mol: &Mol,
+    ranks: &[usize],
+    atom: NodeIndex,
+    double_bond: EdgeIndex,
+    known: impl Fn(EdgeIndex) -> bool,
+) -> Option<(EdgeIndex, NodeIndex)> {
+    single_bonds(mol, atom, double_bond)
+        .min_by_key(|(edge, neighbor)| (!known(*edge), ranks[neighbor.index()]))
+}
+
+/// What's left to write of a tree of atoms, in [`Writer::write_atom_tree`].
+enum Step {
+    /// The atom with its subtree, bonded to the parent atom through the edge, in a
+    /// branch if `branch`.
+    Atom {
+        node: NodeIndex,
+        parent: Option<(NodeIndex, EdgeIndex)>,
+        branch: bool,
+    },
+    /// The `)` after a branch.
+    CloseBranch,
+}
+
+struct Writer<'a> {
+    mol: &'a Mol,
+    options: SmilesWriteOptions,
+    // directions to write on single bonds, starting at the given atom
+    directions: HashMap<EdgeIndex, (NodeIndex, BondDirection)>,
+    children: Vec<Vec<(EdgeIndex, NodeIndex)>>,
+    ring_bonds: Vec<Vec<RingBond>>,
+    // ring number currently used by each open ring closure, index = ring number
+    open_rings: Vec<Option<EdgeIndex>>,
+    out: String,
+    // the atoms in the order they're written
+    written: Vec<AtomIdx>,
+    // whether each bond is in a ring, only computed with aromatic bonds
+    in_ring: Vec<bool>,
+}
+
+impl Writer<'_> {
+    fn write_atom_tree(&mut self, root: NodeIndex) {
+        // an explicit stack rather than recursion, which long chains would overflow
+        let mut steps = vec![Step::Atom {
+            node: root,
+            parent: None,
+            branch: false,
+        }];
+        while let Some(step) = steps.pop() {
+            let Step::Atom {
+                node,
+                parent,
+                branch,
+            } = step
+            else {
+                self.out.push(')');
+                continue;
+            };
+            if branch {
+                self.out.push('(');
+            }
+            if let Some((parent, edge)) = parent {
+                self.write_bond(edge, parent);
+            }
+
+            let mut ring_bonds = core::mem::take(&mut self.ring_bonds[node.index()]);
+            // close rings first so that their numbers can be reused by the rings opening here
+            ring_bonds.sort_by_key(|r| r.opens);
+
+            let parent = parent.map(|(parent, _)| parent);
+            let chirality = self.output_chirality(node, parent, &ring_bonds);
+            self.write_atom(node, chirality);
+            self.written.push(node.into());
+
+            for ring_bond in &ring_bonds {
+                if ring_bond.opens {
+                    let ring = self.open_ring(ring_bond.edge);
+                    self.write_bond(ring_bond.edge, node);
+                    self.write_ring_number(ring);
+                } else {
+                    let ring = self.close_ring(ring_bond.edge);
+                    self.write_ring_number(ring);
+                }
+            }
+
+            // pushed in reverse to be written in order: all children but the last in branches
+            let children = core::mem::take(&mut self.children[node.index()]);
+            let last = children.len().saturating_sub(1);
+            for (i, (edge, child)) in children.into_iter().enumerate().rev() {
+                if i < last {
+                    steps.push(Step::CloseBranch);
+                }
+                steps.push(Step::Atom {
+                    node: child,
+                    parent: Some((node, edge)),
+                    branch: i < last,
+                });
+            }
+        }
+    }
+
+    /// The atom's chirality, adjusted to the order in which its neighbors are written.
+    fn output_chirality(
+        &self,
+        node: NodeIndex,
+        parent: Option<NodeIndex>,
+        ring_bonds: &[RingBond],
+    ) -> Option<Chirality> {
+        let graph = &self.mol.graph;
+        let atom = &graph[node];
+        if !self.options.stereo {
+            return None;
+        }
+        let chirality = atom.chirality?;
+
+        let mut order: Vec<AtomIdx> = parent.iter().map(|p| AtomIdx::from(*p)).collect();
+        for ring_bond in ring_bonds {
+            // unwrap: edge comes from the graph
+            let (a, b) = graph.edge_endpoints(ring_bond.edge).unwrap();
+            order.push(if a == node { b.into() } else { a.into() });
+        }
+        order.extend(
+            self.children[node.index()]
+                .iter()
+                .map(|(_, c)| AtomIdx::from(*c)),
+        );
+
+        let chirality = chirality.reordered(&atom.chiral_neighbors, &order)?;
+        if parent.is_some() && atom.total_num_hs() == 1 {
+            // the hydrogen is written after the parent instead of first
+            Some(chirality.inverted())
+        } else {
+            Some(chirality)
+        }
+    }
+
+    fn open_ring(&mut self, edge: EdgeIndex) -> usize {
+        // lowest free ring number, starting at 1, or a new one until there are 99
+        let reuse = self.options.reuse_ring_numbers || self.open_rings.len() > 99;
+        let free = (1..self.open_rings.len()).find(|ring| self.open_rings[*ring].is_none());
+        match free.filter(|_| reuse) {
+            Some(ring) => {
+                self.open_rings[ring] = Some(edge);
+                ring
+            }
+            None => {
+                if self.open_rings.is_empty() {
+                    self.open_rings.push(None); // ring number 0 is not used
+                }
+                self.open_rings.push(Some(edge));
+                self.open_rings.len() - 1
+            }
+        }
+    }
+
+    fn close_ring(&mut self, edge: EdgeIndex) -> usize {
+        // unwrap: rings are always opened before being closed
+        let ring = self
+            .open_rings
+            .iter()
+            .position(|open| *open == Some(edge))
+            .unwrap();
+        self.open_rings[ring] = None;
+        ring
+    }
+
+    fn write_ring_number(&mut self, ring: usize) {
+        if ring < 10 {
+            self.out.push_str(&ring.to_string());
+        } else if ring < 100 {
+            self.out.push_str(&format!("%{}", ring));
+        } else {
+            // with more than 99 rings open at once, read back with a higher
+            // `max_ring_number`
+            self.out.push_str(&format!("%({})", ring));
+        }
+    }
+
+    /// Writes the symbol of the bond, when walking it from `from`.
+    fn write_bond(&mut self, edge: EdgeIndex, from: NodeIndex) {
+        if let Some((direction_from, direction)) = self.directions.get(&edge) {
+            let direction = if *direction_from == from {
+                *direction
+            } else {
+                direction.inverted()
+            };
+            self.out.push(match direction {
+                BondDirection::Up => '/',
+                BondDirection::Down => '\\',
+            });
+            return;
+        }
+
+        let graph = &self.mol.graph;
+        // unwrap: edge comes from the graph
+        let (a, b) = graph.edge_endpoints(edge).unwrap();
+        let both_aromatic = graph[a].aromatic && graph[b].aromatic;
+        let symbol = match graph[edge].order {
+            BondOrder::Single if both_aromatic => "-",
+            BondOrder::Single => "",
+            BondOrder::Double => "=",
+            BondOrder::Triple => "#",
+            BondOrder::Quadruple => "$",
+            // written implicitly, it would be read as single outside rings
+            BondOrder::Aromatic if both_aromatic && self.in_ring[edge.index()] => "",
+            BondOrder::Aromatic => ":",
+        };
+        self.out.push_str(symbol);
+    }
+
+    fn write_atom(&mut self, node: NodeIndex, chirality: Option<Chirality>) {
+        let atom = &self.mol.graph[node];
+        let isotope = atom.isotope.filter(|_| self.options.isotopes);
+        let map_number = atom.map_number.filter(|_| self.options.atom_maps);
+        let symbol = element_symbol(atom.number).unwrap_or("*");
+        let organic = if atom.aromatic {
+            AROMATIC_ORGANIC_SUBSET.contains(&symbol)
+        } else {
+            ORGANIC_SUBSET.contains(&symbol)
+        };
+        let symbol = if atom.aromatic {
+            symbol.to_lowercase()
+        } else {
+            symbol.to_string()
+        };
+
+        // hydrogens that a reader wouldn't derive from the valence model must be written
+        let implied_h = valence::implicit_h_count(self.mol, node) == atom.implicit_h;
+        // atoms read in brackets keep them, unless for what the options leave out
+        let left_out = (atom.chirality.is_some() && !self.options.stereo)
+            || isotope != atom.isotope
+            || map_number != atom.map_number;
+        let needs_bracket = !organic
+            || (atom.explicit_h.is_some() && !left_out)
+            || !implied_h
+            || isotope.is_some()
+            || atom.charge != 0
+            || map_number.is_some()
+            || atom.num_radical_electrons > 0
+            || chirality.is_some();
+        if !needs_bracket {
+            self.out.push_str(&symbol);
+            return;
+        }
+
+        self.out.push('[');
+        if let Some(isotope) = isotope {
+            self.out.push_str(&isotope.to_string());
+        }
+        self.out.push_str(&symbol);
+        match chirality {
+            Some(Chirality::Anticlockwise) => self.out.push('@'),
+            Some(Chirality::Clockwise) => self.out.push_str("@@"),
+            None => {}
+        }
+        match atom.total_num_hs() {
+            0 => {}
+            1 => self.out.push('H'),
+            count => self.out.push_str(&format!("H{}", count)),
+        }
+        match atom.charge {
+            0 => {}
+            1 => self.out.push('+'),
+            -1 => self.out.push('-'),
+            charge if charge > 0 => self.out.push_str(&format!("+{}", charge)),
+            charge => self.out.push_str(&charge.to_string()),
+        }
+        if let Some(map_number) = map_number {
+            self.out.push_str(&format!(":{}", map_number));
+        }
+        self.out.push(']');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use petgraph::algo::is_isomorphic_matching;
+    use petgraph::graph::NodeIndex;
+    use petgraph::visit::EdgeRef;
+
+    use super::{write_ranked, SmilesWriteOptions, SplitMix64};
+    use crate::smiles::{SmilesParser, SmilesParserOptions};
+    use crate::types::{BondDirection, BondOrder, DoubleBondConfig, Mol};
+
+    fn write(smiles: &str) -> String {
+        let parser = SmilesParser::new();
+        parser.parse(smiles).unwrap().to_smiles()
+    }
+
+    /// Whether no atom of a double bond has directional bonds to two neighbors on the same
+    /// side.
+    fn marks_agree(smiles: &str) -> bool {
+        let mol = SmilesParser::new().parse(smiles).unwrap();
+        let graph = &mol.graph;
+        graph
+            .edge_indices()
+            .filter(|edge| graph[*edge].order == BondOrder::Double)
+            .all(|double_bond| {
+                // unwrap: edge comes from the graph
+                let (a, b) = graph.edge_endpoints(double_bond).unwrap();
+                [a, b].into_iter().all(|atom| {
+                    // normalized to go from the neighbor to the atom
+                    let directions: Vec<BondDirection> = graph
+                        .edges(atom)
+                        .filter(|edge| edge.id() != double_bond)
+                        .filter_map(|edge| {
+                            let direction = edge.weight().direction?;
+                            let (start, _) = graph.edge_endpoints(edge.id()).unwrap();
+                            Some(if start == atom {
+                                direction.inverted()
+                            } else {
+                                direction
+                            })
+                        })
+                        .collect();
+                    directions.len() < 2 || directions[0] != directions[1]
+                })
+            })
+    }
+
+    /// The configuration of each double bond with stereo, relative to the neighbors with
+    /// the lowest index, with the atoms numbered by `index`.
+    fn double_bond_configs(mol: &Mol, index: &[usize]) -> Vec<(usize, usize, bool)> {
+        let graph = &mol.graph;
+        let mut configs: Vec<(usize, usize, bool)> = graph
+            .edge_indices()
+            .filter_map(|edge| {
+                let stereo = graph[edge].stereo?;
+                let (start, end) = graph.edge_endpoints(edge).unwrap();
+                let lowest = |atom: NodeIndex, other: NodeIndex| {
+                    graph
+                        .neighbors(atom)
+                        .filter(|neighbor| *neighbor != other)
+                        .min_by_key(|neighbor| index[neighbor.index()])
+                        .unwrap()
+                };
+                let mut config = stereo.config;
+                if NodeIndex::from(stereo.start_neighbor) != lowest(start, end) {
+                    config = config.inverted();
+                }
+                if NodeIndex::from(stereo.end_neighbor) != lowest(end, start) {
+                    config = config.inverted();
+                }
+                let (a, b) = (index[start.index()], index[end.index()]);
+                Some((a.min(b), a.max(b), config == DoubleBondConfig::Trans))
+            })
+            .collect();
+        configs.sort();
+        configs
+    }
+
+    fn assert_round_trip(smiles: &str) {
+        let parser = SmilesParser::new();
+        let mol = parser.parse(smiles).unwrap();
+        let written = mol.to_smiles();
+        let reparsed = parser.parse(&written).unwrap();
+
+        assert!(
+            is_isomorphic_matching(
+                &mol.graph,
+                &reparsed.graph,
+                |a, b| a == b,
+                |a, b| a.order == b.order
+            ),
+            "{} was written as {}",
+            smiles,
+            written
+        );
+    }
+
+    #[test]
+    fn write_chain() {
+        assert_eq!("CCO", write("CCO"));
+    }
+
+    #[test]
+    fn write_branches() {
+        assert_eq!("CC(=O)O", write("CC(=O)O"));
+        assert_eq!("FC(F)(F)Cl", write("FC(F)(F)Cl"));
+    }
+
+    #[test]
+    fn write_rings() {
+        assert_eq!("c1ccccc1", write("c1ccccc1"));
+        assert_eq!("C=1CCCCC1", write("C=1CCCCC=1"));
+    }
+
+    #[test]
+    fn write_reuses_ring_numbers() {
+        assert_eq!("c1ccccc1-c1ccccc1", write("c1ccccc1-c2ccccc2"));
+    }
+
+    #[test]
+    fn write_extended_ring_numbers() {
+        // a fan: the first atom opens a ring bond to each atom of the chain but the first
+        let rings: String = (2..=120).map(|ring| format!("%({})", ring)).collect();
+        let chain: String = (2..=120).map(|ring| format!("*%({})", ring)).collect();
+        let parser = SmilesParser::with_options(SmilesParserOptions {
+            max_ring_number: 999,
+            ..Default::default()
+        });
+        let mol = parser.parse(&format!("*{}*{}", rings, chain)).unwrap();
+
+        let written = mol.to_smiles();
+        assert!(written.contains("%(100)"), "{}", written);
+        let reparsed = parser.parse(&written).unwrap();
+        assert!(is_isomorphic_matching(
+            &mol.graph,
+            &reparsed.graph,
+            |a, b| a == b,
+            |a, b| a.order == b.order
+        ));
+    }
+
+    #[test]
+    fn write_long_chain() {
+        let parser = SmilesParser::new();
+        let smiles = "C".repeat(100_000);
+        let mol = parser.parse(&smiles).unwrap();
+
+        assert_eq!(smiles, mol.to_smiles());
+        // how canonical SMILES are written, without the ranking
+        let ranks: Vec<usize> = (0..mol.graph.node_count()).collect();
+        assert_eq!(smiles, super::to_smiles_ranked(&mol, &ranks));
+    }
+
+    #[test]
+    fn write_wildcards() {
+        assert_eq!("*CC*", write("*CC*"));
+        assert_eq!("[*]CC[*]", write("[*]CC[*]"));
+        assert_eq!("[*:1]CC[2*]", write("[*:1]CC[2*]"));
+    }
+
+    #[test]
+    fn write_bracket_atoms() {
+        assert_eq!("[13CH4]", write("[13CH4]"));
+        assert_eq!("C[NH3+]", write("C[NH3+]"));
+        assert_eq!("[Fe+2]", write("[Fe++]"));
+        assert_eq!("[O-]C", write("[O-]C"));
+        assert_eq!("[CH3:1]C", write("[CH3:1]C"));
+        assert_eq!("c1cc[se]c1", write("c1cc[se]c1"));
+    }
+
+    #[test]
+    fn write_explicit_bonds() {
+        assert_eq!("C#N", write("C#N"));
+        assert_eq!("C:C", write("C:C"));
+        assert_eq!("C$C", write("C$C"));
+    }
+
+    #[test]
+    fn write_chirality() {
+        assert_eq!("N[C@@H](C)C(=O)O", write("N[C@@H](C)C(=O)O"));
+        assert_eq!("[C@@H](N)(C)C(=O)O", write("[C@@H](N)(C)C(=O)O"));
+        assert_eq!("F[C@](Cl)(Br)I", write("F[C@](Cl)(Br)I"));
+        assert_eq!("C[C@H]1CCCCO1", write("C[C@H]1CCCCO1"));
+    }
+
+    #[test]
+    fn write_chirality_with_implicit_hydrogen() {
+        let parser = SmilesParser::new();
+        let mut mol = parser.parse("N[C@@H](C)C(=O)O").unwrap();
+        let atom = &mut mol.graph[NodeIndex::new(1)];
+        atom.explicit_h = None;
+        atom.implicit_h = 1;
+
+        assert_eq!("N[C@@H](C)C(=O)O", mol.to_smiles());
+    }
+
+    #[test]
+    fn write_double_bond_stereo() {
+        assert_eq!("F/C=C/F", write("F/C=C/F"));
+        assert_eq!("F/C=C/F", write("F\\C=C\\F"));
+        assert_eq!("F/C=C\\F", write("F/C=C\\F"));
+        assert_eq!("C(\\F)=C/F", write("C(\\F)=C/F"));
+        // conjugated double bonds share the middle directional bond
+        assert_eq!("C/C=C/C=C/C", write("C/C=C/C=C/C"));
+        // a substituted atom between conjugated double bonds: both of its bonds are marked,
+        // on opposite sides
+        assert_eq!("C/C=C(/C)\\C=C\\C", write("C/C=C(/C)\\C=C\\C"));
+        assert_eq!("C/C=C/C(=C/C)/C=C/C", write("C/C=C/C(=C/C)/C=C/C"));
+
+        // written from random atom orders, the marks agree and read back as the same
+        // configurations
+        let parser = SmilesParser::new();
+        let mut rng = SplitMix64(3);
+        for smiles in [
+            "C/C=C(/C)\\C=C\\C",
+            "C/C=C/C(=C/C)/C=C/C",
+            "C/C=C/C(=C/C)/C=C\\C",
+            "C/C=C/C=C/C=C/C",
+            "F/C(Cl)=C(/Br)\\C=C\\C=C/I",
+        ] {
+            let mol = parser.parse(smiles).unwrap();
+            let identity: Vec<usize> = (0..mol.num_atoms()).collect();
+            let mut ranks = identity.clone();
+            for _ in 0..20 {
+                for i in (1..ranks.len()).rev() {
+                    ranks.swap(i, rng.below(i + 1));
+                }
+                let (written, order) = write_ranked(&mol, &ranks, SmilesWriteOptions::default());
+                assert!(marks_agree(&written), "{} written as {}", smiles, written);
+                let order: Vec<usize> = order.iter().map(|atom| atom.index()).collect();
+                assert_eq!(
+                    double_bond_configs(&mol, &identity),
+                    double_bond_configs(&parser.parse(&written).unwrap(), &order),
+                    "{} written as {}",
+                    smiles,
+                    written
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn write_options() {
+        let parser = SmilesParser::new();
+        let write_with = |smiles: &str, options: SmilesWriteOptions| {
+            parser
+                .parse(smiles)
+                .unwrap()
+                .to_smiles_with_options(options)
+        };
+        let default = SmilesWriteOptions::default();
+
+        assert_eq!(
+            "C[C@H](O)c1ccccc1",
+            write_with("C[C@H](O)c1ccccc1", default)
+        );
+        let kekulized = SmilesWriteOptions {
+            kekulize: true,
+            ..default
+        };
+        assert_eq!("C1=CC=CC=C1", write_with("c1ccccc1", kekulized));
+        // no Kekulé form
+        assert_eq!("c1cccc1", write_with("c1cccc1", kekulized));
+        let no_stereo = SmilesWriteOptions {
+            stereo: false,
+            ..default
+        };
+        assert_eq!("CC(O)CC=CC", write_with("C[C@H](O)C/C=C/C", no_stereo));
+        assert_eq!(
+            "CC",
+            write_with(
+                "[13CH3:1]C",
+                SmilesWriteOptions {
+                    isotopes: false,
+                    atom_maps: false,
+                    ..default
+                }
+            )
+        );
+        let canonical = SmilesWriteOptions {
+            canonical: true,
+            ..default
+        };
+        assert_eq!(write_with("OCC", canonical), write_with("C(C)O", canonical));
+        assert_eq!(
+            "c1ccccc1-c2ccccc2",
+            write_with(
+                "c1ccccc1-c1ccccc1",
+                SmilesWriteOptions {
+                    reuse_ring_numbers: false,
+                    ..default
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_round_trip("CC(C)(C)C(=O)OC");
+        assert_round_trip("c1ccc2ccccc2c1");
+        assert_round_trip("C1CC2CCC1C2");
+        assert_round_trip("C12C3C4C1C5C2C3C45");
+        assert_round_trip("OC(=O)c1ccccc1O");
+        assert_round_trip("C%10CCCCC%10C1CC1");
+        assert_round_trip("[NH3+]C(C)C(=O)[O-]");
+        assert_round_trip("c1ccc(-c2ccccc2)cc1");
+        assert_round_trip("c1ccc(:c2ccccc2)cc1");
+    }
+
+    #[test]
+    fn random_smiles() {
+        let parser = SmilesParser::new();
+        let mol = parser.parse("N[C@@H](C)C(=O)O").unwrap();
+        let written = mol.random_smiles(10, 7);
+
+        assert_eq!(10, written.len());
+        assert_eq!(written, mol.random_smiles(10, 7));
+        let canonical = mol.to_canonical_smiles();
+        for smiles in &written {
+            let reparsed = parser.parse(smiles).unwrap();
+            assert_eq!(canonical, reparsed.to_canonical_smiles(), "{}", smiles);
+        }
+        assert_eq!(vec!["C"], parser.parse("C").unwrap().random_smiles(5, 0));
+    }
+}